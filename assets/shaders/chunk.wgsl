@@ -0,0 +1,193 @@
+// 区块着色器
+//
+// 区块顶点只有打包成 4 个 u8 的位置和面朝向（格式见 chunk_vertex.wgsl），
+// 以及打包成 u32 的 RGBA8 颜色（已烘焙环境光遮蔽）。顶点阶段解包位置、法线和颜色，
+// 之后交给标准 PBR 流程计算光照、阴影和雾效。
+//
+// 含燃烧方块的网格额外带一个打包的发光色（定义 VOXEL_GLOW），片元阶段按时间闪烁后叠加到自发光上。
+//
+// 水面材质定义 VOXEL_WATER：顶点阶段让水面顶点（方块内高度不是整数的顶点）按几组正弦波上下起伏，
+// 片元阶段在朝上和朝下的面上叠加随时间移动的细小波纹法线。水柱内部和水底的顶点都在整数高度上，不会移动，
+// 相邻水方块共用的顶点位移相同，水面不会裂开。
+//
+// 受光照的材质按正上方云层的浓度压暗，形成随云飘动的云影（参数由云层系统每帧更新）。
+
+#import bevy_pbr::{
+    mesh_functions,
+    mesh_view_bindings::globals,
+    forward_io::{VertexOutput, FragmentOutput},
+    view_transformations::position_world_to_clip,
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT,
+}
+#import "shaders/chunk_vertex.wgsl"::{chunk_position, chunk_normal}
+#import "shaders/cloud_noise.wgsl"::cloud_density
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) packed_position: vec4<u32>,
+    @location(1) packed_color: u32,
+#ifdef VOXEL_GLOW
+    @location(2) packed_glow: u32,
+#endif
+};
+
+// 与 bevy 的 VertexOutput 相同的插值位置，另外加上发光色；整个面的法线相同，不需要插值
+struct ChunkVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(5) color: vec4<f32>,
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    @location(6) @interpolate(flat) instance_index: u32,
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    @location(7) @interpolate(flat) visibility_range_dither: i32,
+#endif
+#ifdef VOXEL_GLOW
+    @location(8) @interpolate(flat) glow: vec4<f32>,
+#endif
+    @location(9) @interpolate(flat) world_normal: vec3<f32>,
+};
+
+// 与 materials.rs 中的 CloudShadow 对应
+struct CloudShadow {
+    offset: vec2<f32>,
+    coverage: f32,
+    // 云最浓处压暗的比例，夜晚为 0
+    strength: f32,
+    // 云层高度，高于云层的方块没有云影
+    height: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var<uniform> cloud_shadow: CloudShadow;
+
+// 发光色满强度时的 HDR 亮度，超过 1 的部分由泛光扩散成火光
+const GLOW_INTENSITY: f32 = 4.0;
+
+// 水面起伏的最大幅度（方块），水面在方块内 0.9 处，起伏后仍低于方块顶面
+const WAVE_AMPLITUDE: f32 = 0.04;
+// 波纹法线的强度
+const RIPPLE_STRENGTH: f32 = 0.08;
+
+// 水平位置 xz 处水面的起伏，范围 [-WAVE_AMPLITUDE, WAVE_AMPLITUDE]
+fn water_wave(xz: vec2<f32>, time: f32) -> f32 {
+    let wave = sin(xz.x * 0.7 + time * 1.3)
+        + sin(xz.y * 0.9 - time * 1.1)
+        + 0.5 * sin((xz.x + xz.y) * 1.6 + time * 2.1);
+    return wave / 2.5 * WAVE_AMPLITUDE;
+}
+
+// 水平位置 xz 处随时间移动的波纹法线偏移
+fn water_ripple(xz: vec2<f32>, time: f32) -> vec2<f32> {
+    let x = cos(xz.x * 3.1 + time * 1.7) + 0.5 * cos((xz.x + xz.y) * 4.3 - time * 2.3);
+    let z = cos(xz.y * 2.7 - time * 1.9) + 0.5 * cos((xz.y - xz.x) * 3.7 + time * 2.9);
+    return vec2<f32>(x, z) / 1.5 * RIPPLE_STRENGTH;
+}
+
+// 云影的亮度系数：1 为不受遮挡
+fn cloud_light(world_position: vec3<f32>) -> f32 {
+    if cloud_shadow.strength <= 0.0 || world_position.y > cloud_shadow.height {
+        return 1.0;
+    }
+    let density = cloud_density(world_position.xz, cloud_shadow.offset, cloud_shadow.coverage);
+    return 1.0 - density * cloud_shadow.strength;
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> ChunkVertexOutput {
+    var out: ChunkVertexOutput;
+
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.world_position = mesh_functions::mesh_position_local_to_world(
+        world_from_local,
+        vec4<f32>(chunk_position(vertex.packed_position), 1.0),
+    );
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(
+        chunk_normal(vertex.packed_position),
+        vertex.instance_index,
+    );
+#ifdef VOXEL_WATER
+    let height = out.world_position.y;
+    if abs(height - round(height)) > 0.01 {
+        out.world_position.y += water_wave(out.world_position.xz, globals.time);
+    }
+#endif
+    out.position = position_world_to_clip(out.world_position.xyz);
+    // R 在最低字节
+    out.color = unpack4x8unorm(vertex.packed_color);
+
+#ifdef VOXEL_GLOW
+    out.glow = unpack4x8unorm(vertex.packed_glow);
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+        vertex.instance_index,
+        world_from_local[3],
+    );
+#endif
+
+    return out;
+}
+
+@fragment
+fn fragment(
+    chunk_in: ChunkVertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var in: VertexOutput;
+    in.position = chunk_in.position;
+    in.world_position = chunk_in.world_position;
+    in.color = chunk_in.color;
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    in.instance_index = chunk_in.instance_index;
+#endif
+#ifdef VISIBILITY_RANGE_DITHER
+    in.visibility_range_dither = chunk_in.visibility_range_dither;
+#endif
+
+    // 透明方块不剔除背面，从背面看时（例如从水下看水面）法线翻转到朝向摄像机的一侧
+    let world_position = in.world_position.xyz;
+    in.world_normal = select(-chunk_in.world_normal, chunk_in.world_normal, is_front);
+
+#ifdef VOXEL_WATER
+    // 只扰动水平的面（水面和从水下看到的水面），侧面保持平直
+    let ripple = water_ripple(world_position.xz, globals.time) * abs(in.world_normal.y);
+    in.world_normal = normalize(in.world_normal + vec3<f32>(ripple.x, 0.0, ripple.y));
+#endif
+
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef VOXEL_GLOW
+    // 每个方块按所在位置错开相位闪烁，避免整片火焰同步明暗
+    let block = floor(world_position - in.world_normal * 0.5);
+    let phase = dot(block, vec3<f32>(1.7, 3.1, 2.3));
+    let flicker = 0.8 + 0.2 * sin(globals.time * 9.0 + phase) * sin(globals.time * 5.3 + phase * 0.7);
+    let glow = chunk_in.glow.rgb * chunk_in.glow.a * GLOW_INTENSITY * flicker;
+    pbr_input.material.emissive = vec4<f32>(
+        pbr_input.material.emissive.rgb + glow,
+        pbr_input.material.emissive.a,
+    );
+#endif
+
+    var out: FragmentOutput;
+    if (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
+        let shade = cloud_light(world_position);
+        pbr_input.material.base_color = vec4<f32>(
+            pbr_input.material.base_color.rgb * shade,
+            pbr_input.material.base_color.a,
+        );
+        out.color = apply_pbr_lighting(pbr_input);
+    } else {
+        out.color = pbr_input.material.base_color;
+    }
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+
+    return out;
+}
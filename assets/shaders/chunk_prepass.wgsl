@@ -0,0 +1,58 @@
+// 区块预处理着色器（深度、阴影、法线和运动向量）
+//
+// 默认的预处理着色器读取浮点位置，区块顶点的位置是打包的 u8（见 chunk_vertex.wgsl），
+// 这里解包后输出与默认预处理着色器相同的结果，片元阶段沿用标准材质的预处理着色器。
+
+#import bevy_pbr::{
+    mesh_functions,
+    prepass_io::VertexOutput,
+    view_transformations::position_world_to_clip,
+}
+#import "shaders/chunk_vertex.wgsl"::{chunk_position, chunk_normal}
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) packed_position: vec4<u32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    let position = vec4<f32>(chunk_position(vertex.packed_position), 1.0);
+    out.world_position = mesh_functions::mesh_position_local_to_world(world_from_local, position);
+    out.position = position_world_to_clip(out.world_position.xyz);
+#ifdef UNCLIPPED_DEPTH_ORTHO_EMULATION
+    out.unclipped_depth = out.position.z;
+    // 限制深度，避免被裁剪
+    out.position.z = min(out.position.z, 1.0);
+#endif
+
+#ifdef NORMAL_PREPASS_OR_DEFERRED_PREPASS
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(
+        chunk_normal(vertex.packed_position),
+        vertex.instance_index,
+    );
+#endif
+
+#ifdef MOTION_VECTOR_PREPASS
+    out.previous_world_position = mesh_functions::mesh_position_local_to_world(
+        mesh_functions::get_previous_world_from_local(vertex.instance_index),
+        position,
+    );
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+
+#ifdef VISIBILITY_RANGE_DITHER
+    out.visibility_range_dither = mesh_functions::get_visibility_range_dither_level(
+        vertex.instance_index,
+        world_from_local[3],
+    );
+#endif
+
+    return out;
+}
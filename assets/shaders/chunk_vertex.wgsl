@@ -0,0 +1,33 @@
+// 区块顶点格式
+//
+// 位置打包成 4 个 u8：第一、三个是区块内的 x、z 坐标（1/8 格为单位，0-128），第二个是 y 的整数部分
+// （0-16），第四个的低 3 位是面的朝向编号，高 5 位是 y 的小数部分（1/32 格，用于不满一格的水面和半砖）。
+// 朝向编号依次为 +X、-X、+Y、-Y、+Z、-Z，与 mesh.rs 中的 face_index 一致。
+//
+// 主渲染着色器（chunk.wgsl）和预处理着色器（chunk_prepass.wgsl）共用这里的解包函数。
+// 透明和水网格的原点在区块中心（定义 VOXEL_CENTERED），解包时减去半个区块。
+
+// y 小数部分的精度（每格的份数）
+const HEIGHT_STEPS: f32 = 32.0;
+// x、z 的精度（每格的份数）
+const HORIZONTAL_STEPS: f32 = 8.0;
+// 区块边长的一半
+const HALF_CHUNK: f32 = 8.0;
+
+// 网格局部坐标中的顶点位置
+fn chunk_position(packed: vec4<u32>) -> vec3<f32> {
+    let y = f32(packed.y) + f32(packed.w >> 3u) / HEIGHT_STEPS;
+    var position = vec3<f32>(f32(packed.x) / HORIZONTAL_STEPS, y, f32(packed.z) / HORIZONTAL_STEPS);
+#ifdef VOXEL_CENTERED
+    position -= vec3<f32>(HALF_CHUNK);
+#endif
+    return position;
+}
+
+// 顶点所在面的法线：编号除以 2 是轴，奇数为负方向
+fn chunk_normal(packed: vec4<u32>) -> vec3<f32> {
+    let face = packed.w & 7u;
+    var normal = vec3<f32>(0.0);
+    normal[face >> 1u] = select(1.0, -1.0, (face & 1u) == 1u);
+    return normal;
+}
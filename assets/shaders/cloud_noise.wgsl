@@ -0,0 +1,50 @@
+// 云层噪声
+//
+// 云层着色器（clouds.wgsl）用它画出云，区块着色器（chunk.wgsl）用它计算地面上的云影，
+// 两者取同一位置的浓度，云影和云的形状一致。噪声固定在世界坐标上，offset 是随风漂移的距离。
+
+// 一格噪声覆盖的方块数
+const CLOUD_SCALE: f32 = 96.0;
+// 云的边缘从透明过渡到不透明的噪声范围
+const EDGE_SOFTNESS: f32 = 0.2;
+
+// 网格点上的伪随机值（0-1）
+fn cloud_hash(cell: vec2<i32>) -> f32 {
+    var h = (bitcast<u32>(cell.x) * 0x8da6b343u) ^ (bitcast<u32>(cell.y) * 0xd8163841u);
+    h = (h ^ (h >> 15u)) * 0x2c1b3c6du;
+    h = h ^ (h >> 12u);
+    return f32(h & 0xffffu) / 65535.0;
+}
+
+// 值噪声：四个网格点的随机值平滑插值
+fn value_noise(p: vec2<f32>) -> f32 {
+    let cell = vec2<i32>(floor(p));
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let a = cloud_hash(cell);
+    let b = cloud_hash(cell + vec2<i32>(1, 0));
+    let c = cloud_hash(cell + vec2<i32>(0, 1));
+    let d = cloud_hash(cell + vec2<i32>(1, 1));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// 四层叠加的值噪声，归一化到 0-1
+fn cloud_fbm(p: vec2<f32>) -> f32 {
+    var sum = 0.0;
+    var amplitude = 0.5;
+    var q = p;
+    for (var i = 0; i < 4; i += 1) {
+        sum += amplitude * value_noise(q);
+        // 每层错开一点，避免各层的网格对齐
+        q = q * 2.03 + vec2<f32>(17.1, 9.7);
+        amplitude *= 0.5;
+    }
+    return sum / 0.9375;
+}
+
+// 世界坐标 (x, z) 处云的浓度（0-1）：coverage 为 0 时无云，为 1 时布满天空
+fn cloud_density(world_xz: vec2<f32>, offset: vec2<f32>, coverage: f32) -> f32 {
+    let noise = cloud_fbm((world_xz - offset) / CLOUD_SCALE);
+    let threshold = 1.0 - coverage;
+    return smoothstep(threshold - EDGE_SOFTNESS * 0.5, threshold + EDGE_SOFTNESS * 0.5, noise);
+}
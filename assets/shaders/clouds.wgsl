@@ -0,0 +1,28 @@
+// 云层着色器
+//
+// 云层是一块跟随摄像机的水平大平面，云的形状取自世界坐标上的噪声（见 cloud_noise.wgsl），
+// 平面移动时云不会跟着移动。离摄像机越远云越透明，看不到平面的边缘。
+
+#import bevy_pbr::{forward_io::VertexOutput, mesh_view_bindings::view}
+#import "shaders/cloud_noise.wgsl"::cloud_density
+
+struct CloudParams {
+    // 云的颜色（已按昼夜和天气调暗），a 为整体不透明度
+    color: vec4<f32>,
+    offset: vec2<f32>,
+    coverage: f32,
+    // 超过这个水平距离的云完全透明
+    fade_distance: f32,
+}
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> clouds: CloudParams;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let density = cloud_density(in.world_position.xz, clouds.offset, clouds.coverage);
+    let distance = length(in.world_position.xz - view.world_position.xz);
+    let fade = 1.0 - smoothstep(clouds.fade_distance * 0.5, clouds.fade_distance, distance);
+    // 越浓的云越暗，云团中间比边缘厚重
+    let shade = mix(1.0, 0.7, density);
+    return vec4<f32>(clouds.color.rgb * shade, density * fade * clouds.color.a);
+}
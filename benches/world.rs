@@ -0,0 +1,60 @@
+//! Chunk generation, meshing and command commit benchmarks
+//!
+//! Run with `cargo bench`; throughput is reported in chunks (or commands) per second.
+//! `cargo run --release -- --bench-world [radius]` runs the same workloads once
+//! without criterion.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::hint::black_box;
+
+use voxworld::voxel::bench::{BenchWorld, BENCH_SEED, COMMIT_BATCH};
+use voxworld::voxel::build_chunk_mesh_async;
+
+/// Smaller than the runtime default so a criterion run stays under a few minutes
+const RADIUS: i32 = 1;
+
+fn generation(c: &mut Criterion) {
+    let bench = BenchWorld::new(BENCH_SEED, RADIUS);
+    let mut group = c.benchmark_group("generate_chunk");
+    group.throughput(Throughput::Elements(bench.positions().len() as u64));
+    group.sample_size(10);
+    group.bench_function("world", |b| b.iter(|| bench.generate_all()));
+    group.finish();
+}
+
+fn meshing(c: &mut Criterion) {
+    let bench = BenchWorld::new(BENCH_SEED, RADIUS);
+    let mut group = c.benchmark_group("build_chunk_mesh");
+    let workloads = [
+        ("empty_edges", bench.mesh_inputs(false)),
+        ("neighbor_edges", bench.mesh_inputs(true)),
+        ("dense_underground", bench.dense_mesh_inputs()),
+    ];
+    for (name, inputs) in workloads {
+        group.throughput(Throughput::Elements(inputs.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || inputs.clone(),
+                |inputs| {
+                    for input in inputs {
+                        black_box(build_chunk_mesh_async(input));
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn commit(c: &mut Criterion) {
+    let bench = BenchWorld::new(BENCH_SEED, RADIUS);
+    let mut commit_bench = bench.commit_bench(COMMIT_BATCH);
+    let mut group = c.benchmark_group("commit_system");
+    group.throughput(Throughput::Elements(commit_bench.batch_size() as u64));
+    group.bench_function("mixed_commands", |b| b.iter(|| commit_bench.run_tick()));
+    group.finish();
+}
+
+criterion_group!(benches, generation, meshing, commit);
+criterion_main!(benches);
@@ -0,0 +1,479 @@
+//! Sound effects and ambience
+//!
+//! There are no audio files: every sound is synthesized into a short sample buffer at
+//! startup and played through bevy's audio with a custom [`Decodable`] source.
+//!
+//! - Breaking a block plays a thud at the block, pitched by the material's hardness
+//!   (soft dirt is low and dull, stone and metal are higher and sharper)
+//! - Burning voxels near the player crackle; the nearest few get a looping emitter
+//! - Water within a few blocks plays a looping murmur from the middle of the water
+//! - Wind fades in as the player climbs above the water level
+//!
+//! Block and fire sounds are scaled by the sfx volume, everything by the master volume.
+
+use bevy::audio::{AddAudioSource, Source, SpatialScale, Volume};
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::player::PlayerCamera;
+use crate::raycast::BlockBroken;
+use crate::settings::GameSettings;
+use crate::voxel::domains::thermal::api::idx_to_xyz;
+use crate::voxel::{ivec3_to_vec3, ChunkLookup, Chunks, VoxelKind, WorldGenConfig, XorShift32};
+
+const SAMPLE_RATE: u32 = 44_100;
+
+/// World units per unit of audio distance. Spatial sounds fall off with the square
+/// of the distance, so without scaling a block 5 m away would already be at 4%.
+const SPATIAL_SCALE: f32 = 0.25;
+
+/// How often burning and water voxels around the player are rescanned
+const SCAN_INTERVAL: Duration = Duration::from_millis(500);
+/// Looping volumes move this fraction of the way to their target per second
+const FADE_RATE: f32 = 2.0;
+
+const FIRE_RADIUS: f32 = 24.0;
+const MAX_FIRE_EMITTERS: usize = 6;
+const FIRE_VOLUME: f32 = 0.6;
+
+const WATER_RADIUS: i32 = 12;
+/// Sampled water voxels (every second block on each axis) for full water volume
+const WATER_FULL_COUNT: f32 = 60.0;
+const WATER_VOLUME: f32 = 0.5;
+
+/// Height above the water level where wind starts, and how much higher it's at full volume
+const WIND_START: f32 = 20.0;
+const WIND_RANGE: f32 = 60.0;
+const WIND_VOLUME: f32 = 0.4;
+
+/// Mono sample buffer synthesized at startup
+#[derive(Asset, TypePath, Clone)]
+pub struct SynthSound {
+    samples: Arc<[f32]>,
+}
+
+impl Decodable for SynthSound {
+    type DecoderItem = f32;
+    type Decoder = SynthDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        SynthDecoder {
+            samples: self.samples.clone(),
+            pos: 0,
+        }
+    }
+}
+
+pub struct SynthDecoder {
+    samples: Arc<[f32]>,
+    pos: usize,
+}
+
+impl Iterator for SynthDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.samples.get(self.pos).copied();
+        self.pos += 1;
+        sample
+    }
+}
+
+impl Source for SynthDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.samples.len().saturating_sub(self.pos))
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.samples.len() as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
+
+#[derive(Resource)]
+struct SoundLibrary {
+    block: Handle<SynthSound>,
+    crackle: Handle<SynthSound>,
+}
+
+/// Looping sound whose volume eases toward `target` so it never pops in or out
+#[derive(Component)]
+struct LoopingSound {
+    target: f32,
+    current: f32,
+    /// Scaled by the sfx volume as well as the master volume
+    is_sfx: bool,
+}
+
+impl LoopingSound {
+    fn new(target: f32, is_sfx: bool) -> Self {
+        Self {
+            target,
+            current: 0.0,
+            is_sfx,
+        }
+    }
+}
+
+#[derive(Component)]
+struct WaterAmbience;
+
+#[derive(Component)]
+struct WindAmbience;
+
+/// Crackle emitters currently playing, by burning voxel position
+#[derive(Resource, Default)]
+struct FireEmitters {
+    emitters: HashMap<IVec3, Entity>,
+}
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<SynthSound>()
+            .add_audio_source::<SynthSound>()
+            .init_resource::<FireEmitters>()
+            .add_systems(Startup, setup_sounds)
+            .add_systems(
+                Update,
+                (
+                    attach_listener,
+                    play_break_sounds,
+                    (update_fire_emitters, update_ambience_targets).run_if(on_timer(SCAN_INTERVAL)),
+                    fade_looping_sounds,
+                ),
+            );
+    }
+}
+
+fn setup_sounds(mut commands: Commands, mut sounds: ResMut<Assets<SynthSound>>) {
+    let water = sounds.add(synth_water());
+    let wind = sounds.add(synth_wind());
+    commands.insert_resource(SoundLibrary {
+        block: sounds.add(synth_block()),
+        crackle: sounds.add(synth_crackle()),
+    });
+
+    commands.spawn((
+        AudioPlayer(water),
+        PlaybackSettings::LOOP
+            .with_spatial(true)
+            .with_spatial_scale(SpatialScale::new(SPATIAL_SCALE))
+            .with_volume(Volume::SILENT),
+        Transform::default(),
+        LoopingSound::new(0.0, false),
+        WaterAmbience,
+    ));
+    commands.spawn((
+        AudioPlayer(wind),
+        PlaybackSettings::LOOP.with_volume(Volume::SILENT),
+        LoopingSound::new(0.0, false),
+        WindAmbience,
+    ));
+}
+
+/// Spatial sounds are heard from the player camera
+fn attach_listener(
+    mut commands: Commands,
+    camera_q: Query<Entity, (With<PlayerCamera>, Without<SpatialListener>)>,
+) {
+    for camera in &camera_q {
+        commands.entity(camera).insert(SpatialListener::default());
+    }
+}
+
+fn play_break_sounds(
+    mut commands: Commands,
+    mut broken: MessageReader<BlockBroken>,
+    library: Option<Res<SoundLibrary>>,
+    settings: Res<GameSettings>,
+) {
+    let Some(library) = library else {
+        return;
+    };
+    for event in broken.read() {
+        play_block_sound(&mut commands, &library, &settings, event.pos, event.kind);
+    }
+}
+
+/// One-shot thud at a block. Harder materials play it faster (higher and shorter)
+/// and louder; a small per-position jitter keeps repeated hits from sounding identical.
+fn play_block_sound(
+    commands: &mut Commands,
+    library: &SoundLibrary,
+    settings: &GameSettings,
+    pos: IVec3,
+    kind: VoxelKind,
+) {
+    let hardness = kind.def().props.hardness.clamp(0.0, 1.0);
+    let hash = (pos.x.wrapping_mul(73_856_093)
+        ^ pos.y.wrapping_mul(19_349_663)
+        ^ pos.z.wrapping_mul(83_492_791)) as u32;
+    let jitter = (hash % 100) as f32 / 1000.0 - 0.05;
+    let speed = 0.6 + hardness + jitter;
+    let volume = settings.master_volume * settings.sfx_volume * (0.6 + 0.4 * hardness);
+
+    commands.spawn((
+        AudioPlayer(library.block.clone()),
+        PlaybackSettings::DESPAWN
+            .with_spatial(true)
+            .with_spatial_scale(SpatialScale::new(SPATIAL_SCALE))
+            .with_volume(Volume::Linear(volume))
+            .with_speed(speed),
+        Transform::from_translation(ivec3_to_vec3(pos) + Vec3::splat(0.5)),
+    ));
+}
+
+/// Keeps a crackle emitter on each of the nearest burning voxels
+fn update_fire_emitters(
+    mut commands: Commands,
+    chunks: Chunks,
+    library: Option<Res<SoundLibrary>>,
+    mut fires: ResMut<FireEmitters>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+) {
+    let (Some(library), Ok(camera)) = (library, camera_q.single()) else {
+        return;
+    };
+    let eye = camera.translation();
+
+    let mut burning: Vec<(f32, IVec3)> = chunks
+        .iter()
+        .filter(|(_, chunk)| !chunk.active_burning.is_empty())
+        .flat_map(|(chunk_pos, chunk)| {
+            let origin = chunk_pos.world_origin();
+            chunk.active_burning.iter().map(move |&idx| {
+                let (x, y, z) = idx_to_xyz(idx);
+                origin + IVec3::new(x, y, z)
+            })
+        })
+        .map(|pos| (eye.distance(ivec3_to_vec3(pos) + Vec3::splat(0.5)), pos))
+        .filter(|(distance, _)| *distance <= FIRE_RADIUS)
+        .collect();
+    burning.sort_by(|a, b| a.0.total_cmp(&b.0));
+    burning.truncate(MAX_FIRE_EMITTERS);
+
+    fires.emitters.retain(|pos, entity| {
+        let keep = burning.iter().any(|(_, p)| p == pos);
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+        keep
+    });
+
+    for (_, pos) in burning {
+        if fires.emitters.contains_key(&pos) {
+            continue;
+        }
+        // Slightly different speeds keep neighbouring fires from crackling in unison
+        let speed = 0.9 + (fires.emitters.len() as f32 * 0.37).fract() * 0.2;
+        let entity = commands
+            .spawn((
+                AudioPlayer(library.crackle.clone()),
+                PlaybackSettings::LOOP
+                    .with_spatial(true)
+                    .with_spatial_scale(SpatialScale::new(SPATIAL_SCALE))
+                    .with_volume(Volume::SILENT)
+                    .with_speed(speed),
+                Transform::from_translation(ivec3_to_vec3(pos) + Vec3::splat(0.5)),
+                LoopingSound::new(FIRE_VOLUME, true),
+            ))
+            .id();
+        fires.emitters.insert(pos, entity);
+    }
+}
+
+/// Moves the water emitter to the nearby water and sets how loud water and wind should be
+fn update_ambience_targets(
+    chunks: Chunks,
+    config: Res<WorldGenConfig>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut water_q: Query<(&mut Transform, &mut LoopingSound), With<WaterAmbience>>,
+    mut wind_q: Query<&mut LoopingSound, (With<WindAmbience>, Without<WaterAmbience>)>,
+) {
+    let Ok(camera) = camera_q.single() else {
+        return;
+    };
+    let eye = camera.translation();
+
+    if let Ok((mut transform, mut sound)) = water_q.single_mut() {
+        let center = eye.floor().as_ivec3();
+        let mut count = 0;
+        let mut sum = Vec3::ZERO;
+        for dx in (-WATER_RADIUS..=WATER_RADIUS).step_by(2) {
+            for dy in (-WATER_RADIUS..=WATER_RADIUS).step_by(2) {
+                for dz in (-WATER_RADIUS..=WATER_RADIUS).step_by(2) {
+                    let pos = center + IVec3::new(dx, dy, dz);
+                    if chunks.get_voxel(pos) == VoxelKind::Water {
+                        count += 1;
+                        sum += ivec3_to_vec3(pos);
+                    }
+                }
+            }
+        }
+        if count > 0 {
+            transform.translation = sum / count as f32 + Vec3::splat(0.5);
+        }
+        sound.target = (count as f32 / WATER_FULL_COUNT).min(1.0) * WATER_VOLUME;
+    }
+
+    if let Ok(mut sound) = wind_q.single_mut() {
+        let height = eye.y - config.terrain.water_level as f32;
+        sound.target = ((height - WIND_START) / WIND_RANGE).clamp(0.0, 1.0) * WIND_VOLUME;
+    }
+}
+
+fn fade_looping_sounds(
+    time: Res<Time>,
+    settings: Res<GameSettings>,
+    mut sounds: Query<(
+        &mut LoopingSound,
+        Option<&mut AudioSink>,
+        Option<&mut SpatialAudioSink>,
+    )>,
+) {
+    let blend = (FADE_RATE * time.delta_secs()).min(1.0);
+    for (mut sound, sink, spatial_sink) in &mut sounds {
+        sound.current += (sound.target - sound.current) * blend;
+
+        let mut volume = sound.current * settings.master_volume;
+        if sound.is_sfx {
+            volume *= settings.sfx_volume;
+        }
+        let volume = Volume::Linear(volume);
+        if let Some(mut sink) = sink {
+            sink.set_volume(volume);
+        }
+        if let Some(mut sink) = spatial_sink {
+            sink.set_volume(volume);
+        }
+    }
+}
+
+/// Deterministic white noise so the synthesized sounds are the same every run
+struct NoiseSource(XorShift32);
+
+impl NoiseSource {
+    fn new(state: u32) -> Self {
+        Self(XorShift32::new(state))
+    }
+
+    /// Uniform sample in -1..1
+    fn next(&mut self) -> f32 {
+        self.0.range(-1.0, 1.0)
+    }
+}
+
+fn seconds(duration: f32) -> usize {
+    (duration * SAMPLE_RATE as f32) as usize
+}
+
+/// Scales the buffer so its loudest sample is at `peak`
+fn into_sound(mut samples: Vec<f32>, peak: f32) -> SynthSound {
+    let max = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    if max > 0.0 {
+        for sample in &mut samples {
+            *sample *= peak / max;
+        }
+    }
+    SynthSound {
+        samples: samples.into(),
+    }
+}
+
+/// Cross-fades the last `fade` samples into the start so the buffer loops without a click
+fn make_loop(mut samples: Vec<f32>, fade: usize) -> Vec<f32> {
+    let len = samples.len() - fade;
+    for i in 0..fade {
+        let t = i as f32 / fade as f32;
+        samples[i] = samples[i] * t + samples[len + i] * (1.0 - t);
+    }
+    samples.truncate(len);
+    samples
+}
+
+/// Short low thump: filtered noise burst over a decaying 90 Hz tone
+fn synth_block() -> SynthSound {
+    let mut noise = NoiseSource::new(0x1234_5678);
+    let mut filtered = 0.0;
+    let samples = (0..seconds(0.18))
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            filtered += (noise.next() - filtered) * 0.25;
+            filtered * (-t * 30.0).exp() + (TAU * 90.0 * t).sin() * (-t * 40.0).exp() * 0.6
+        })
+        .collect();
+    into_sound(samples, 0.8)
+}
+
+/// Sparse sharp pops over a faint hiss
+fn synth_crackle() -> SynthSound {
+    let mut noise = NoiseSource::new(0x0bad_f00d);
+    let mut hiss = 0.0;
+    let mut pop = 0.0f32;
+    let mut previous = 0.0;
+    let fade = seconds(0.05);
+    let samples = (0..seconds(2.0) + fade)
+        .map(|_| {
+            let white = noise.next();
+            hiss += (white - hiss) * 0.05;
+            // About 35 pops per second with random strength
+            if noise.next() > 0.9984 {
+                pop = 0.3 + 0.7 * noise.next().abs();
+            }
+            pop *= 0.993;
+            let sample = (white - previous) * pop + hiss * 0.15;
+            previous = white;
+            sample
+        })
+        .collect();
+    into_sound(make_loop(samples, fade), 0.7)
+}
+
+/// Low rumbling noise with a slow swell
+fn synth_water() -> SynthSound {
+    let mut noise = NoiseSource::new(0x5eed_1e55);
+    let mut low = 0.0;
+    let mut lower = 0.0;
+    let fade = seconds(0.2);
+    let samples = (0..seconds(4.0) + fade)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            low += (noise.next() - low) * 0.12;
+            lower += (low - lower) * 0.3;
+            // Two swells per loop
+            lower * (0.7 + 0.3 * (TAU * 0.5 * t).sin())
+        })
+        .collect();
+    into_sound(make_loop(samples, fade), 0.6)
+}
+
+/// Heavily filtered noise whose brightness and loudness drift like gusts
+fn synth_wind() -> SynthSound {
+    let mut noise = NoiseSource::new(0x00c0_ffee);
+    let mut filtered = 0.0;
+    let fade = seconds(0.5);
+    let samples = (0..seconds(6.0) + fade)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let gust = 0.5 + 0.5 * (TAU * t / 3.0).sin();
+            filtered += (noise.next() - filtered) * (0.005 + 0.02 * gust);
+            filtered * (0.4 + 0.6 * gust)
+        })
+        .collect();
+    into_sound(make_loop(samples, fade), 0.6)
+}
@@ -0,0 +1,157 @@
+//! Camera effects that depend on the voxel the camera is in
+//!
+//! Each frame the voxel containing the camera decides its [`CameraMedium`]:
+//!
+//! - Water (below the rendered water surface): blue distance fog and a blue tint over the
+//!   screen, slower movement and a darker exposure target for the auto-exposure
+//! - Inside an opaque solid block (flying through terrain, or pushed into a wall):
+//!   a nearly black overlay, so the inside faces of the surrounding blocks don't show
+//!
+//! The effects are added when the medium changes and removed again on the way out.
+
+use bevy::pbr::{DistanceFog, FogFalloff};
+use bevy::prelude::*;
+
+use crate::player::PlayerCamera;
+use crate::voxel::domains::fluid::surface_height;
+use crate::voxel::{ChunkLookup, Chunks, VoxelKind, VoxelWorld};
+
+/// Fog color under water
+const WATER_FOG_COLOR: Color = Color::srgb(0.08, 0.25, 0.45);
+/// Density of the underwater fog; things fade out over roughly 20 blocks
+const WATER_FOG_DENSITY: f32 = 0.15;
+/// Screen tint under water
+const WATER_TINT: Color = Color::srgba(0.1, 0.3, 0.6, 0.35);
+/// Screen overlay inside a solid block
+const SOLID_TINT: Color = Color::srgba(0.02, 0.02, 0.02, 0.92);
+
+/// What the camera is in, updated every frame from the voxel at the camera position
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CameraMedium {
+    #[default]
+    Air,
+    /// Below the surface of a water block
+    Water,
+    /// Inside an opaque solid block
+    Solid,
+}
+
+impl CameraMedium {
+    /// Multiplier on the player's movement speed
+    pub fn speed_multiplier(self) -> f32 {
+        match self {
+            CameraMedium::Water => 0.5,
+            CameraMedium::Air | CameraMedium::Solid => 1.0,
+        }
+    }
+
+    /// Added to the auto-exposure target (EV100, higher is darker)
+    pub fn exposure_offset(self) -> f32 {
+        match self {
+            CameraMedium::Water => 1.5,
+            CameraMedium::Air | CameraMedium::Solid => 0.0,
+        }
+    }
+
+    /// Color laid over the screen, if any
+    fn tint(self) -> Option<Color> {
+        match self {
+            CameraMedium::Air => None,
+            CameraMedium::Water => Some(WATER_TINT),
+            CameraMedium::Solid => Some(SOLID_TINT),
+        }
+    }
+}
+
+/// The medium at a camera position; unloaded chunks count as air
+pub fn camera_medium(world: &impl ChunkLookup, position: Vec3) -> CameraMedium {
+    let block = position.floor().as_ivec3();
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(block);
+    let Some(chunk) = world.chunk(&chunk_pos) else {
+        return CameraMedium::Air;
+    };
+    let kind = chunk.voxels.get(idx);
+    if kind == VoxelKind::Water {
+        // The top block of a body of water is only filled up to its rendered surface
+        let filled = if world.get_voxel(block + IVec3::Y) == VoxelKind::Water {
+            1.0
+        } else {
+            surface_height(chunk.variant.get(idx))
+        };
+        if position.y - block.y as f32 <= filled {
+            return CameraMedium::Water;
+        }
+    } else if kind.is_solid() && !kind.is_transparent() {
+        return CameraMedium::Solid;
+    }
+    CameraMedium::Air
+}
+
+/// Full-screen node that carries the tint
+#[derive(Component)]
+struct MediumOverlay;
+
+pub struct CameraEffectsPlugin;
+
+impl Plugin for CameraEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_medium_overlay)
+            .add_systems(Update, (update_camera_medium, apply_medium_effects).chain());
+    }
+}
+
+fn spawn_medium_overlay(mut commands: Commands) {
+    // Below the rest of the UI, so the crosshair and menus stay readable
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: percent(100.0),
+            height: percent(100.0),
+            ..default()
+        },
+        BackgroundColor(Color::NONE),
+        Visibility::Hidden,
+        GlobalZIndex(-1),
+        MediumOverlay,
+    ));
+}
+
+fn update_camera_medium(
+    chunks: Chunks,
+    mut camera_q: Query<(&GlobalTransform, &mut CameraMedium), With<PlayerCamera>>,
+) {
+    for (transform, mut medium) in &mut camera_q {
+        medium.set_if_neq(camera_medium(&chunks, transform.translation()));
+    }
+}
+
+/// Swaps the fog and the overlay when the camera changes medium
+fn apply_medium_effects(
+    mut commands: Commands,
+    camera_q: Query<(Entity, &CameraMedium), Changed<CameraMedium>>,
+    mut overlay_q: Query<(&mut BackgroundColor, &mut Visibility), With<MediumOverlay>>,
+) {
+    for (entity, medium) in &camera_q {
+        if *medium == CameraMedium::Water {
+            commands.entity(entity).insert(DistanceFog {
+                color: WATER_FOG_COLOR,
+                falloff: FogFalloff::Exponential {
+                    density: WATER_FOG_DENSITY,
+                },
+                ..default()
+            });
+        } else {
+            commands.entity(entity).remove::<DistanceFog>();
+        }
+
+        for (mut background, mut visibility) in &mut overlay_q {
+            match medium.tint() {
+                Some(tint) => {
+                    background.0 = tint;
+                    *visibility = Visibility::Inherited;
+                }
+                None => *visibility = Visibility::Hidden,
+            }
+        }
+    }
+}
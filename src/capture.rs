@@ -0,0 +1,321 @@
+//! Screenshots and timelapses
+//!
+//! [`Action::Screenshot`] (F2 by default) and the `screenshot` console command save a
+//! PNG of the window to [`CAPTURES_DIR`]. File names carry the world seed and the
+//! camera's block coordinates, so a capture can be reproduced by flying back to the
+//! same spot in the same world. In photo mode the key takes a high-resolution photo
+//! instead (see [`crate::photo_mode`]).
+//!
+//! `timelapse` captures a frame at a fixed interval, either in real seconds or in
+//! in-game hours (see [`GameClock`]), into its own directory with numbered frames. The
+//! game-time mode follows the clock, so pausing the sun also pauses the timelapse.
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::{save_to_disk, Screenshot};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::celestial::GameClock;
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::input::{Action, ActionInput};
+use crate::photo_mode::photo_mode_inactive;
+use crate::player::PlayerCamera;
+use crate::voxel::WorldSeed;
+
+/// Directory screenshots and timelapses are written to, relative to the working directory
+pub const CAPTURES_DIR: &str = "captures";
+
+/// Shortest real-time interval between timelapse frames
+const MIN_INTERVAL_SECS: f32 = 0.1;
+
+/// How often a timelapse captures a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimelapseInterval {
+    /// Every given number of real seconds
+    Seconds(f32),
+    /// Every given number of in-game ticks
+    GameTicks(u64),
+}
+
+impl TimelapseInterval {
+    /// Parses the `timelapse` arguments: `<seconds>` or `game <hours>`
+    fn parse(args: &[String]) -> Result<Self, String> {
+        match args {
+            [seconds] => {
+                let seconds: f32 = seconds
+                    .parse()
+                    .map_err(|_| format!("无效的秒数：{seconds}"))?;
+                if !seconds.is_finite() || seconds < MIN_INTERVAL_SECS {
+                    return Err(format!("间隔至少为 {MIN_INTERVAL_SECS} 秒"));
+                }
+                Ok(Self::Seconds(seconds))
+            }
+            [mode, hours] if mode == "game" => {
+                let hours: f64 = hours
+                    .parse()
+                    .map_err(|_| format!("无效的小时数：{hours}"))?;
+                let ticks = (hours * GameClock::TICKS_PER_DAY as f64 / 24.0).round();
+                if !ticks.is_finite() || ticks < 1.0 {
+                    return Err("游戏时间间隔太短".to_string());
+                }
+                Ok(Self::GameTicks(ticks as u64))
+            }
+            _ => {
+                Err("用法：timelapse <秒> | timelapse game <游戏小时> | timelapse off".to_string())
+            }
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            Self::Seconds(seconds) => format!("每 {seconds} 秒"),
+            Self::GameTicks(ticks) => format!(
+                "每 {} 游戏小时",
+                ticks as f64 * 24.0 / GameClock::TICKS_PER_DAY as f64
+            ),
+        }
+    }
+}
+
+/// A running timelapse
+#[derive(Debug)]
+struct TimelapseSession {
+    interval: TimelapseInterval,
+    dir: PathBuf,
+    /// Frames captured so far
+    frames: u32,
+    /// Real-time countdown, used by [`TimelapseInterval::Seconds`]
+    timer: Timer,
+    /// Clock tick of the next frame, used by [`TimelapseInterval::GameTicks`]
+    next_tick: u64,
+}
+
+impl TimelapseSession {
+    fn new(interval: TimelapseInterval, dir: PathBuf, now_tick: u64) -> Self {
+        let (timer, next_tick) = match interval {
+            TimelapseInterval::Seconds(seconds) => {
+                (Timer::from_seconds(seconds, TimerMode::Repeating), now_tick)
+            }
+            TimelapseInterval::GameTicks(ticks) => (Timer::default(), now_tick + ticks),
+        };
+        Self {
+            interval,
+            dir,
+            frames: 0,
+            timer,
+            next_tick,
+        }
+    }
+
+    /// Whether a frame is due after `real_delta` of real time with the clock at `tick`
+    fn due(&mut self, real_delta: std::time::Duration, tick: u64) -> bool {
+        match self.interval {
+            TimelapseInterval::Seconds(_) => self.timer.tick(real_delta).just_finished(),
+            TimelapseInterval::GameTicks(ticks) => {
+                if tick < self.next_tick {
+                    return false;
+                }
+                // Skip frames missed during a long hitch instead of bursting them out
+                self.next_tick += (tick - self.next_tick) / ticks * ticks + ticks;
+                true
+            }
+        }
+    }
+}
+
+/// The timelapse currently recording, if any
+#[derive(Resource, Debug, Default)]
+pub struct Timelapse {
+    session: Option<TimelapseSession>,
+}
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        let mut commands = app.world_mut().resource_mut::<ConsoleCommands>();
+        commands.register("screenshot", "screenshot", "截图并保存到 captures 目录");
+        commands.register(
+            "timelapse",
+            "timelapse <秒> | game <游戏小时> | off",
+            "按真实或游戏时间间隔连续截图",
+        );
+
+        app.init_resource::<Timelapse>().add_systems(
+            Update,
+            (
+                screenshot_key.run_if(photo_mode_inactive),
+                handle_capture_commands,
+                record_timelapse,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Milliseconds since the Unix epoch, used to keep capture names unique and sortable
+pub(crate) fn timestamp_millis() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+/// Block the camera is in, or the origin if there is no player camera
+fn camera_block(camera_q: &Query<&GlobalTransform, With<PlayerCamera>>) -> IVec3 {
+    camera_q
+        .single()
+        .map(|transform| transform.translation().floor().as_ivec3())
+        .unwrap_or(IVec3::ZERO)
+}
+
+/// File name of a capture: `<prefix>_seed<seed>_<x>_<y>_<z>.png`
+pub(crate) fn capture_file_name(prefix: &str, seed: u32, block: IVec3) -> String {
+    format!(
+        "{prefix}_seed{seed}_{}_{}_{}.png",
+        block.x, block.y, block.z
+    )
+}
+
+/// Creates `dir` if needed and queues `screenshot` (of the window or an image) to `dir/name`
+///
+/// The image is written on a later frame once the GPU readback completes.
+pub(crate) fn capture_to(
+    commands: &mut Commands,
+    screenshot: Screenshot,
+    dir: &Path,
+    name: &str,
+) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(name);
+    commands
+        .spawn(screenshot)
+        .observe(save_to_disk(path.clone()));
+    Ok(path)
+}
+
+/// Takes a single screenshot named after the current time, seed and camera position
+fn take_screenshot(
+    commands: &mut Commands,
+    seed: &WorldSeed,
+    camera_q: &Query<&GlobalTransform, With<PlayerCamera>>,
+    log: &mut ConsoleLog,
+) {
+    let name = capture_file_name(
+        &timestamp_millis().to_string(),
+        seed.seed,
+        camera_block(camera_q),
+    );
+    match capture_to(
+        commands,
+        Screenshot::primary_window(),
+        Path::new(CAPTURES_DIR),
+        &name,
+    ) {
+        Ok(path) => log.print(format!("截图已保存到 {}", path.display())),
+        Err(err) => log.print(format!("无法创建截图目录 {CAPTURES_DIR}：{err}")),
+    }
+}
+
+fn screenshot_key(
+    actions: ActionInput,
+    mut commands: Commands,
+    seed: Res<WorldSeed>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    if actions.just_pressed(Action::Screenshot) {
+        take_screenshot(&mut commands, &seed, &camera_q, &mut log);
+    }
+}
+
+fn handle_capture_commands(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut commands: Commands,
+    seed: Res<WorldSeed>,
+    clock: Res<GameClock>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut timelapse: ResMut<Timelapse>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        match command.name.as_str() {
+            "screenshot" => take_screenshot(&mut commands, &seed, &camera_q, &mut log),
+            "timelapse" => match command.args.as_slice() {
+                [] => match &timelapse.session {
+                    Some(session) => log.print(format!(
+                        "延时摄影进行中：{}，已拍摄 {} 帧，保存在 {}",
+                        session.interval.describe(),
+                        session.frames,
+                        session.dir.display()
+                    )),
+                    None => log.print("延时摄影未开启"),
+                },
+                [off] if off == "off" => match timelapse.session.take() {
+                    Some(session) => log.print(format!(
+                        "延时摄影已停止，共 {} 帧，保存在 {}",
+                        session.frames,
+                        session.dir.display()
+                    )),
+                    None => log.print("延时摄影未开启"),
+                },
+                args => match TimelapseInterval::parse(args) {
+                    Ok(interval) => {
+                        let dir = Path::new(CAPTURES_DIR).join(format!(
+                            "timelapse_{}_seed{}",
+                            timestamp_millis(),
+                            seed.seed
+                        ));
+                        log.print(format!(
+                            "延时摄影开始：{}，保存在 {}",
+                            interval.describe(),
+                            dir.display()
+                        ));
+                        timelapse.session = Some(TimelapseSession::new(interval, dir, clock.ticks));
+                    }
+                    Err(message) => log.print(format!("timelapse：{message}")),
+                },
+            },
+            _ => {}
+        }
+    }
+}
+
+fn record_timelapse(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    clock: Res<GameClock>,
+    seed: Res<WorldSeed>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut timelapse: ResMut<Timelapse>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    let Some(session) = timelapse.session.as_mut() else {
+        return;
+    };
+    if !session.due(time.delta(), clock.ticks) {
+        return;
+    }
+
+    let name = capture_file_name(
+        &format!("frame{:05}", session.frames),
+        seed.seed,
+        camera_block(&camera_q),
+    );
+    match capture_to(
+        &mut commands,
+        Screenshot::primary_window(),
+        &session.dir,
+        &name,
+    ) {
+        Ok(_) => session.frames += 1,
+        Err(err) => {
+            log.print(format!(
+                "延时摄影已停止，无法创建目录 {}：{err}",
+                session.dir.display()
+            ));
+            timelapse.session = None;
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use bevy::camera::Exposure;
 use bevy::light::{light_consts::lux, VolumetricLight, CascadeShadowConfigBuilder};
 use bevy::prelude::*;
-use std::f32::consts::PI;
+use std::f32::consts::{PI, TAU};
+
+use crate::camera_effects::CameraMedium;
 
 /// Marker component for the sun light source
 #[derive(Component)]
@@ -11,12 +13,20 @@ pub struct Sun;
 #[derive(Component)]
 pub struct Moon;
 
+/// Moon illuminance when it is high in the sky
+/// Real moonlight is ~1/400,000 of sunlight, but we use ~1/1000 for better game visibility
+const MOON_ILLUMINANCE: f32 = lux::RAW_SUNLIGHT / 1_000.0;
+
+/// Ambient light brightness at night and at midday
+const NIGHT_AMBIENT_BRIGHTNESS: f32 = 150.0;
+const DAY_AMBIENT_BRIGHTNESS: f32 = 500.0;
+
 /// Resource to control celestial body motion
 #[derive(Resource)]
 pub struct CelestialSettings {
-    /// Whether celestial bodies are paused
+    /// Whether celestial bodies (and the game clock) are paused
     pub paused: bool,
-    /// Rotation speed in radians per second (default: PI/10 ≈ 18 degrees/sec)
+    /// Rotation speed in radians per second (default: PI/10 ≈ 18 degrees/sec, a 20 second day)
     pub rotation_speed: f32,
 }
 
@@ -29,16 +39,124 @@ impl Default for CelestialSettings {
     }
 }
 
+/// In-game clock driving the day/night cycle
+///
+/// Time of day is normalized to [0, 1): 0.0 is midnight, 0.25 sunrise,
+/// 0.5 noon and 0.75 sunset. The sun and moon positions are derived from it.
+#[derive(Resource, Debug, Clone)]
+pub struct GameClock {
+    /// Total in-game ticks since the world started
+    pub ticks: u64,
+    /// Number of fully elapsed days
+    pub day: u32,
+    /// Normalized time of day in [0, 1)
+    pub time_of_day: f32,
+    /// Fractional tick carried over between frames
+    tick_fraction: f64,
+}
+
+impl GameClock {
+    /// Number of ticks in one in-game day
+    pub const TICKS_PER_DAY: u64 = 24_000;
+
+    /// Create a clock on day 0 at the given normalized time of day
+    pub fn at_time_of_day(time_of_day: f32) -> Self {
+        let mut clock = Self {
+            ticks: 0,
+            day: 0,
+            time_of_day: 0.0,
+            tick_fraction: 0.0,
+        };
+        clock.set_ticks((time_of_day.rem_euclid(1.0) as f64 * Self::TICKS_PER_DAY as f64) as u64);
+        clock
+    }
+
+    /// Create a clock that has run for `ticks` ticks, e.g. one restored from a save
+    pub fn at_ticks(ticks: u64) -> Self {
+        let mut clock = Self::at_time_of_day(0.0);
+        clock.set_ticks(ticks);
+        clock
+    }
+
+    /// Advance the clock by a (possibly fractional) number of ticks
+    pub fn advance(&mut self, ticks: f64) {
+        let total = self.tick_fraction + ticks.max(0.0);
+        let whole = total.floor();
+        self.tick_fraction = total - whole;
+        self.set_ticks(self.ticks + whole as u64);
+    }
+
+    fn set_ticks(&mut self, ticks: u64) {
+        self.ticks = ticks;
+        self.day = (ticks / Self::TICKS_PER_DAY) as u32;
+        self.time_of_day = (ticks % Self::TICKS_PER_DAY) as f32 / Self::TICKS_PER_DAY as f32;
+    }
+
+    /// Sun height in the sky: 1.0 at noon, 0.0 on the horizon, -1.0 at midnight
+    pub fn sun_altitude(&self) -> f32 {
+        -(self.time_of_day * TAU).cos()
+    }
+
+    /// Whether the sun is above the horizon
+    pub fn is_day(&self) -> bool {
+        self.sun_altitude() > 0.0
+    }
+
+    /// Daylight factor from 0.0 (night) to 1.0 (day), blended around the horizon
+    pub fn daylight(&self) -> f32 {
+        (self.sun_altitude() * 2.0 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+impl Default for GameClock {
+    /// Worlds start at noon
+    fn default() -> Self {
+        Self::at_time_of_day(0.5)
+    }
+}
+
+/// Sent when the sun crosses the horizon going up
+#[derive(Message, Debug, Clone, Copy)]
+pub struct Sunrise {
+    pub day: u32,
+}
+
+/// Sent when the sun crosses the horizon going down
+#[derive(Message, Debug, Clone, Copy)]
+pub struct Sunset {
+    pub day: u32,
+}
+
 pub struct CelestialPlugin;
 
 impl Plugin for CelestialPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CelestialSettings::default())
+            .init_resource::<GameClock>()
+            .add_message::<Sunrise>()
+            .add_message::<Sunset>()
             .add_systems(Startup, setup_celestial_bodies)
-            .add_systems(Update, (update_celestial_motion, update_auto_exposure));
+            .add_systems(
+                Update,
+                (
+                    update_game_clock,
+                    log_day_transitions,
+                    update_celestial_motion,
+                    update_sky_lighting,
+                    update_auto_exposure,
+                )
+                    .chain(),
+            );
     }
 }
 
+/// Orientation of the sun at noon (looking straight down at the world)
+fn sun_base_rotation() -> Quat {
+    Transform::from_xyz(0.0, 1.0, 0.0)
+        .looking_at(Vec3::ZERO, Vec3::Z)
+        .rotation
+}
+
 fn setup_celestial_bodies(mut commands: Commands) {
     // Configure cascade shadow map for sun
     let cascade_shadow_config = CascadeShadowConfigBuilder {
@@ -49,7 +167,8 @@ fn setup_celestial_bodies(mut commands: Commands) {
     .build();
 
     // Base rotation for celestial bodies (looking down at the world)
-    let base_transform = Transform::from_xyz(0.0, 1.0, 0.0).looking_at(Vec3::ZERO, Vec3::Z);
+    // The actual positions are set from the game clock every frame
+    let base_transform = Transform::from_rotation(sun_base_rotation());
 
     // Sun - primary light source during day
     commands.spawn((
@@ -65,13 +184,12 @@ fn setup_celestial_bodies(mut commands: Commands) {
     ));
 
     // Moon - secondary light source during night
-    // Real moonlight is ~1/400,000 of sunlight, but we use ~1/1000 for better game visibility
-    // Moon starts 180 degrees opposite to the sun (rotated PI around X axis)
+    // Moon stays 180 degrees opposite to the sun (rotated PI around X axis)
     let moon_transform = base_transform.with_rotation(base_transform.rotation * Quat::from_rotation_x(PI));
 
     commands.spawn((
         DirectionalLight {
-            illuminance: lux::RAW_SUNLIGHT / 1_000.0,
+            illuminance: MOON_ILLUMINANCE,
             shadows_enabled: true,
             shadow_depth_bias: 0.3,
             ..default()
@@ -84,14 +202,16 @@ fn setup_celestial_bodies(mut commands: Commands) {
     // Provides a subtle bluish fill light so the scene isn't completely black
     commands.spawn(AmbientLight {
         color: Color::srgb(0.6, 0.7, 1.0), // Slight blue tint for night sky
-        brightness: 150.0,
+        brightness: NIGHT_AMBIENT_BRIGHTNESS,
         affects_lightmapped_meshes: true,
     });
 }
 
-fn update_celestial_motion(
-    mut sun_query: Query<&mut Transform, (With<Sun>, Without<Moon>)>,
-    mut moon_query: Query<&mut Transform, With<Moon>>,
+/// Advance the game clock and send sunrise/sunset messages when the sun crosses the horizon
+fn update_game_clock(
+    mut clock: ResMut<GameClock>,
+    mut sunrise: MessageWriter<Sunrise>,
+    mut sunset: MessageWriter<Sunset>,
     time: Res<Time>,
     settings: Res<CelestialSettings>,
 ) {
@@ -99,25 +219,87 @@ fn update_celestial_motion(
         return;
     }
 
-    let delta_rotation = time.delta_secs() * settings.rotation_speed;
+    // One full sun rotation (TAU radians) is one day
+    let ticks_per_sec = settings.rotation_speed as f64 / TAU as f64 * GameClock::TICKS_PER_DAY as f64;
+
+    let was_day = clock.is_day();
+    clock.advance(time.delta_secs_f64() * ticks_per_sec);
 
-    // Rotate both sun and moon around the X axis in the same direction
+    match (was_day, clock.is_day()) {
+        (false, true) => {
+            sunrise.write(Sunrise { day: clock.day });
+        }
+        (true, false) => {
+            sunset.write(Sunset { day: clock.day });
+        }
+        _ => {}
+    }
+}
+
+/// Log sunrise and sunset as they happen
+fn log_day_transitions(mut sunrise: MessageReader<Sunrise>, mut sunset: MessageReader<Sunset>) {
+    for event in sunrise.read() {
+        info!("Sunrise on day {}", event.day);
+    }
+    for event in sunset.read() {
+        info!("Sunset on day {}", event.day);
+    }
+}
+
+/// Place the sun and moon according to the game clock
+fn update_celestial_motion(
+    mut sun_query: Query<&mut Transform, (With<Sun>, Without<Moon>)>,
+    mut moon_query: Query<&mut Transform, With<Moon>>,
+    clock: Res<GameClock>,
+) {
+    // Angle around the X axis, zero at noon
+    let angle = (clock.time_of_day - 0.5) * TAU;
+    let sun_rotation = Quat::from_rotation_x(-angle) * sun_base_rotation();
+
+    // Both bodies rotate around the X axis in the same direction
     // They maintain their 180 degree offset, so when sun sets, moon rises
     for mut sun_transform in &mut sun_query {
-        sun_transform.rotate_x(-delta_rotation);
+        sun_transform.rotation = sun_rotation;
     }
 
     for mut moon_transform in &mut moon_query {
-        moon_transform.rotate_x(-delta_rotation);
+        moon_transform.rotation = sun_rotation * Quat::from_rotation_x(PI);
+    }
+}
+
+/// Scale ambient light and moonlight with the time of day
+fn update_sky_lighting(
+    clock: Res<GameClock>,
+    mut moon_query: Query<&mut DirectionalLight, With<Moon>>,
+    mut ambient_query: Query<&mut AmbientLight>,
+) {
+    let daylight = clock.daylight();
+
+    // Moon altitude is the opposite of the sun's, fade it out below the horizon
+    let moon_altitude = -clock.sun_altitude();
+    let moon_factor = (moon_altitude * 4.0).clamp(0.0, 1.0);
+    for mut moon_light in &mut moon_query {
+        moon_light.illuminance = MOON_ILLUMINANCE * moon_factor;
+    }
+
+    // Bluish starlight at night, neutral sky light during the day
+    let night_color = Vec3::new(0.6, 0.7, 1.0);
+    let day_color = Vec3::new(1.0, 1.0, 1.0);
+    let color = night_color.lerp(day_color, daylight);
+    for mut ambient in &mut ambient_query {
+        ambient.brightness =
+            NIGHT_AMBIENT_BRIGHTNESS + (DAY_AMBIENT_BRIGHTNESS - NIGHT_AMBIENT_BRIGHTNESS) * daylight;
+        ambient.color = Color::srgb(color.x, color.y, color.z);
     }
 }
 
 /// Auto-exposure system that adjusts camera exposure based on sun position
 /// - Daytime (sun above horizon): Higher EV100 (~13) for bright scenes
 /// - Nighttime (sun below horizon): Lower EV100 (~5) to see in moonlight
+/// - Under water the target is raised by [`CameraMedium::exposure_offset`]
 fn update_auto_exposure(
     sun_query: Query<&Transform, With<Sun>>,
-    mut camera_query: Query<&mut Exposure, With<Camera3d>>,
+    mut camera_query: Query<(&mut Exposure, Option<&CameraMedium>), With<Camera3d>>,
     time: Res<Time>,
 ) {
     let Ok(sun_transform) = sun_query.single() else {
@@ -143,9 +325,52 @@ fn update_auto_exposure(
     let target_ev100 = NIGHT_EV100 + (DAY_EV100 - NIGHT_EV100) * t;
 
     // Smoothly interpolate current exposure toward target
-    for mut exposure in &mut camera_query {
+    for (mut exposure, medium) in &mut camera_query {
+        let target_ev100 = target_ev100 + medium.map_or(0.0, |medium| medium.exposure_offset());
         let current = exposure.ev100;
         let delta = target_ev100 - current;
         exposure.ev100 = current + delta * (time.delta_secs() * TRANSITION_SPEED).min(1.0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_carries_fractional_ticks() {
+        let mut clock = GameClock::at_ticks(100);
+        clock.advance(0.4);
+        assert_eq!(clock.ticks, 100);
+        clock.advance(0.7);
+        assert_eq!(clock.ticks, 101);
+        // Negative steps don't turn the clock back
+        clock.advance(-5.0);
+        assert_eq!(clock.ticks, 101);
+    }
+
+    #[test]
+    fn test_clock_wraps_into_the_next_day() {
+        let mut clock = GameClock::at_ticks(GameClock::TICKS_PER_DAY - 1);
+        assert_eq!(clock.day, 0);
+        clock.advance(2.0);
+        assert_eq!(clock.day, 1);
+        assert_eq!(clock.ticks, GameClock::TICKS_PER_DAY + 1);
+        assert_eq!(clock.time_of_day, 1.0 / GameClock::TICKS_PER_DAY as f32);
+
+        let clock = GameClock::at_time_of_day(1.25);
+        assert_eq!(clock.day, 0);
+        assert_eq!(clock.time_of_day, 0.25);
+    }
+
+    #[test]
+    fn test_sun_is_up_at_noon_and_down_at_midnight() {
+        let noon = GameClock::default();
+        assert!(noon.is_day());
+        assert_eq!(noon.daylight(), 1.0);
+
+        let midnight = GameClock::at_time_of_day(0.0);
+        assert!(!midnight.is_day());
+        assert_eq!(midnight.daylight(), 0.0);
+    }
+}
@@ -0,0 +1,234 @@
+//! Cloud layer
+//!
+//! A flat layer of procedural clouds at [`CLOUD_HEIGHT`] that follows the camera. The
+//! cloud shapes come from noise evaluated in the shader (`assets/shaders/clouds.wgsl`)
+//! at world coordinates, so the layer is a single large quad and the clouds stay put as
+//! it moves; only the wind moves them.
+//!
+//! Cloud cover follows the weather: scattered clouds on clear days, thickening over the
+//! last [`FORECAST_SECS`] seconds before rain and overcast while it rains. The terrain
+//! under the clouds is darkened with the same noise through the chunk material's
+//! [`CloudShadow`], fading out at night along with the clouds' brightness.
+
+use bevy::light::{NotShadowCaster, NotShadowReceiver};
+use bevy::mesh::MeshVertexBufferLayoutRef;
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    AsBindGroup, RenderPipelineDescriptor, ShaderType, SpecializedMeshPipelineError,
+};
+use bevy::shader::ShaderRef;
+
+use crate::celestial::GameClock;
+use crate::player::PlayerCamera;
+use crate::voxel::domains::weather::{WeatherState, TICKS_PER_SECOND};
+use crate::voxel::materials::{ChunkMaterial, CloudShadow};
+use crate::voxel::ChunkMaterials;
+
+const CLOUD_SHADER_PATH: &str = "shaders/clouds.wgsl";
+
+/// Height of the cloud layer (world Y)
+pub const CLOUD_HEIGHT: f32 = 140.0;
+/// Side length of the cloud quad centered on the camera
+const LAYER_SIZE: f32 = 2048.0;
+/// Horizontal distance at which the clouds have faded out completely
+const FADE_DISTANCE: f32 = 900.0;
+
+/// Cloud cover on a clear day and while it rains (0 = clear sky, 1 = overcast)
+const CLEAR_COVERAGE: f32 = 0.35;
+const RAIN_COVERAGE: f32 = 0.9;
+/// Seconds before rain when the clouds start to thicken
+pub const FORECAST_SECS: u32 = 90;
+/// How fast the cover moves toward the weather's, per second
+const COVERAGE_RATE: f32 = 0.02;
+
+/// Wind on a clear day, blocks per second; it blows twice as hard in the rain
+const WIND: Vec2 = Vec2::new(2.5, 1.0);
+const RAIN_WIND_FACTOR: f32 = 2.0;
+
+/// How much the densest cloud darkens the terrain under it at midday
+const SHADOW_STRENGTH: f32 = 0.35;
+/// Cloud brightness at night relative to midday
+const NIGHT_BRIGHTNESS: f32 = 0.04;
+/// Color of fair-weather and rain clouds
+const CLEAR_COLOR: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+const RAIN_COLOR: Vec3 = Vec3::new(0.55, 0.57, 0.6);
+
+/// Material of the cloud quad
+#[derive(Asset, AsBindGroup, TypePath, Debug, Clone, Default)]
+pub struct CloudMaterial {
+    #[uniform(0)]
+    pub params: CloudParams,
+}
+
+/// Matches `CloudParams` in the cloud shader
+#[derive(ShaderType, Debug, Clone, Copy, Default)]
+pub struct CloudParams {
+    /// Cloud color in linear RGB, alpha is the layer's overall opacity
+    pub color: Vec4,
+    pub offset: Vec2,
+    pub coverage: f32,
+    pub fade_distance: f32,
+}
+
+impl Material for CloudMaterial {
+    fn fragment_shader() -> ShaderRef {
+        CLOUD_SHADER_PATH.into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
+    }
+
+    fn enable_prepass() -> bool {
+        false
+    }
+
+    fn enable_shadows() -> bool {
+        false
+    }
+
+    /// The layer is seen from below and, when flying above it, from the top
+    fn specialize(
+        _pipeline: &MaterialPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.primitive.cull_mode = None;
+        Ok(())
+    }
+}
+
+/// Current state of the sky's clouds
+#[derive(Resource, Debug, Clone)]
+pub struct CloudState {
+    /// How far the wind has carried the clouds
+    pub offset: Vec2,
+    /// Current cloud cover, easing toward the weather's
+    pub coverage: f32,
+}
+
+impl Default for CloudState {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            coverage: CLEAR_COVERAGE,
+        }
+    }
+}
+
+/// Cloud cover the weather calls for: thickening linearly toward the rain cover during
+/// the forecast window before rain
+pub fn target_coverage(weather: &WeatherState) -> f32 {
+    let forecast = FORECAST_SECS * TICKS_PER_SECOND;
+    let approach = 1.0 - weather.ticks_until_rain().min(forecast) as f32 / forecast as f32;
+    CLEAR_COVERAGE + (RAIN_COVERAGE - CLEAR_COVERAGE) * approach
+}
+
+/// Marker for the cloud quad
+#[derive(Component)]
+struct CloudLayer;
+
+pub struct CloudsPlugin;
+
+impl Plugin for CloudsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<CloudMaterial>::default())
+            .init_resource::<CloudState>()
+            .add_systems(Startup, spawn_cloud_layer)
+            .add_systems(
+                Update,
+                (update_clouds, follow_camera, apply_cloud_params).chain(),
+            );
+    }
+}
+
+fn spawn_cloud_layer(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CloudMaterial>>,
+) {
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(LAYER_SIZE, LAYER_SIZE))),
+        MeshMaterial3d(materials.add(CloudMaterial::default())),
+        Transform::from_xyz(0.0, CLOUD_HEIGHT, 0.0),
+        NotShadowCaster,
+        NotShadowReceiver,
+        CloudLayer,
+    ));
+}
+
+/// Blows the clouds along and eases the cover toward the weather's
+fn update_clouds(time: Res<Time>, weather: Res<WeatherState>, mut clouds: ResMut<CloudState>) {
+    let dt = time.delta_secs();
+    let wind = if weather.is_raining() {
+        WIND * RAIN_WIND_FACTOR
+    } else {
+        WIND
+    };
+    clouds.offset += wind * dt;
+
+    let target = target_coverage(&weather);
+    let step = COVERAGE_RATE * dt;
+    clouds.coverage += (target - clouds.coverage).clamp(-step, step);
+}
+
+/// Keeps the cloud quad centered above the camera
+fn follow_camera(
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut layer_q: Query<&mut Transform, With<CloudLayer>>,
+) {
+    let Ok(camera) = camera_q.single() else {
+        return;
+    };
+    let eye = camera.translation();
+    for mut transform in &mut layer_q {
+        transform.translation = Vec3::new(eye.x, CLOUD_HEIGHT, eye.z);
+    }
+}
+
+/// Passes the cloud state to the cloud material and the chunk materials' cloud shadows
+fn apply_cloud_params(
+    clouds: Res<CloudState>,
+    clock: Res<GameClock>,
+    layer_q: Query<&MeshMaterial3d<CloudMaterial>, With<CloudLayer>>,
+    mut cloud_materials: ResMut<Assets<CloudMaterial>>,
+    chunk_materials: Res<ChunkMaterials>,
+    mut chunk_assets: ResMut<Assets<ChunkMaterial>>,
+) {
+    let daylight = clock.daylight();
+    // Rain clouds darken as the cover closes in
+    let rain =
+        ((clouds.coverage - CLEAR_COVERAGE) / (RAIN_COVERAGE - CLEAR_COVERAGE)).clamp(0.0, 1.0);
+    let color = CLEAR_COLOR.lerp(RAIN_COLOR, rain)
+        * (NIGHT_BRIGHTNESS + (1.0 - NIGHT_BRIGHTNESS) * daylight);
+    let params = CloudParams {
+        color: color.extend(1.0),
+        offset: clouds.offset,
+        coverage: clouds.coverage,
+        fade_distance: FADE_DISTANCE,
+    };
+    for material in &layer_q {
+        if let Some(material) = cloud_materials.get_mut(&material.0) {
+            material.params = params;
+        }
+    }
+
+    let shadow = CloudShadow {
+        offset: clouds.offset,
+        coverage: clouds.coverage,
+        strength: SHADOW_STRENGTH * daylight,
+        height: CLOUD_HEIGHT,
+    };
+    // The emissive material is unlit and has no cloud shadows
+    for handle in [
+        &chunk_materials.opaque,
+        &chunk_materials.transparent,
+        &chunk_materials.water,
+    ] {
+        if let Some(material) = chunk_assets.get_mut(handle) {
+            material.extension.cloud_shadow = shadow;
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Command console
+//!
+//! The console opens with [`Action::OpenConsole`] (`/` by default) and reads one line
+//! of text. Enter runs it, Escape closes it without running anything. A line is split
+//! on whitespace into a command name and arguments and sent as a [`ConsoleCommand`]
+//! message; the plugin that registered the name handles it and prints its reply to
+//! the [`ConsoleLog`].
+//!
+//! Plugins register their commands at build time so `help` can list them and unknown
+//! names are reported instead of silently dropped:
+//!
+//! ```ignore
+//! app.init_resource::<ConsoleCommands>();
+//! app.world_mut()
+//!     .resource_mut::<ConsoleCommands>()
+//!     .register("spawn", "spawn", "回到出生点");
+//! ```
+
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::input::{Action, ActionInput, InputCapture};
+use crate::ui::{MenuState, UI_FONT_PATH};
+
+/// Lines kept in the log
+const LOG_CAPACITY: usize = 100;
+/// Log lines shown above the input line
+const VISIBLE_LINES: usize = 12;
+/// Longest line the input accepts
+const MAX_INPUT_LEN: usize = 256;
+const CONSOLE_BG: Color = Color::srgba(0.04, 0.05, 0.08, 0.82);
+
+/// A command line entered in the console
+#[derive(Message, Debug, Clone)]
+pub struct ConsoleCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// A command some plugin handles
+#[derive(Debug, Clone)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    /// Name and arguments, e.g. `tp <x> <y> <z>`
+    pub usage: &'static str,
+    pub description: &'static str,
+}
+
+/// Every command the console accepts
+#[derive(Resource, Debug, Default)]
+pub struct ConsoleCommands {
+    pub commands: Vec<CommandInfo>,
+}
+
+impl ConsoleCommands {
+    pub fn register(&mut self, name: &'static str, usage: &'static str, description: &'static str) {
+        self.commands.push(CommandInfo {
+            name,
+            usage,
+            description,
+        });
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CommandInfo> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+}
+
+/// Output shown in the console, newest last
+#[derive(Resource, Debug, Default)]
+pub struct ConsoleLog {
+    lines: VecDeque<String>,
+}
+
+impl ConsoleLog {
+    pub fn print(&mut self, line: impl Into<String>) {
+        let line = line.into();
+        info!("[console] {line}");
+        if self.lines.len() == LOG_CAPACITY {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    /// Lines still in the log, oldest first
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+}
+
+/// Whether the console is open and what has been typed so far
+#[derive(Resource, Debug, Default)]
+struct ConsoleState {
+    open: bool,
+    /// Set on the frame the console opens
+    just_opened: bool,
+    input: String,
+}
+
+#[derive(Component)]
+struct ConsoleRoot;
+
+#[derive(Component)]
+struct ConsoleText;
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "help",
+            "help [命令]",
+            "列出所有命令或查看某个命令的用法",
+        );
+
+        app.init_resource::<ConsoleLog>()
+            .init_resource::<ConsoleState>()
+            .add_message::<ConsoleCommand>()
+            .add_systems(Startup, setup_console)
+            .add_systems(
+                Update,
+                (
+                    toggle_console,
+                    read_console_input,
+                    handle_help,
+                    update_console_text,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup_console(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: px(14.0),
+                bottom: px(14.0),
+                width: px(560.0),
+                padding: UiRect::all(px(10.0)),
+                ..default()
+            },
+            BackgroundColor(CONSOLE_BG),
+            Visibility::Hidden,
+            ConsoleRoot,
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont {
+                font: asset_server.load(UI_FONT_PATH),
+                font_size: 15.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            ConsoleText,
+        ));
+}
+
+/// Opens the console, unless the pause menu is up
+///
+/// Keyboard messages from the frame it opens are skipped, so the key that opened it
+/// doesn't end up in the input line.
+fn toggle_console(
+    actions: ActionInput,
+    menu_state: Res<MenuState>,
+    mut state: ResMut<ConsoleState>,
+    mut capture: ResMut<InputCapture>,
+    mut root_q: Query<&mut Visibility, With<ConsoleRoot>>,
+) {
+    if state.open || menu_state.open || !actions.just_pressed(Action::OpenConsole) {
+        return;
+    }
+    state.open = true;
+    state.just_opened = true;
+    state.input.clear();
+    capture.typing = true;
+    if let Ok(mut visibility) = root_q.single_mut() {
+        *visibility = Visibility::Visible;
+    }
+}
+
+fn read_console_input(
+    mut keyboard: MessageReader<KeyboardInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut state: ResMut<ConsoleState>,
+    mut capture: ResMut<InputCapture>,
+    mut log: ResMut<ConsoleLog>,
+    mut commands_out: MessageWriter<ConsoleCommand>,
+    mut root_q: Query<&mut Visibility, With<ConsoleRoot>>,
+) {
+    if !state.open {
+        keyboard.clear();
+        return;
+    }
+    if state.just_opened {
+        state.just_opened = false;
+        keyboard.clear();
+        return;
+    }
+
+    let mut close = false;
+    for event in keyboard.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match event.key_code {
+            KeyCode::Escape => close = true,
+            KeyCode::Enter | KeyCode::NumpadEnter => {
+                let line = std::mem::take(&mut state.input);
+                submit_line(&line, &mut log, &mut commands_out);
+                close = true;
+            }
+            KeyCode::Backspace => {
+                state.input.pop();
+            }
+            _ => {
+                let Some(text) = &event.text else {
+                    continue;
+                };
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if state.input.len() < MAX_INPUT_LEN {
+                        state.input.push(c);
+                    }
+                }
+            }
+        }
+        if close {
+            break;
+        }
+    }
+
+    if close {
+        state.open = false;
+        state.input.clear();
+        capture.typing = false;
+        // Keep the closing key from also reaching the pause menu this frame
+        keys.clear_just_pressed(KeyCode::Escape);
+        keys.clear_just_pressed(KeyCode::Enter);
+        keyboard.clear();
+        if let Ok(mut visibility) = root_q.single_mut() {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+/// Echoes `line` to the log and sends it as a command; blank lines are dropped
+fn submit_line(line: &str, log: &mut ConsoleLog, commands_out: &mut MessageWriter<ConsoleCommand>) {
+    let Some(command) = parse_line(line) else {
+        return;
+    };
+    log.print(format!("> {}", line.trim()));
+    commands_out.write(command);
+}
+
+/// Splits a line into a command name and arguments; a leading `/` is optional
+pub fn parse_line(line: &str) -> Option<ConsoleCommand> {
+    let mut words = line.trim().trim_start_matches('/').split_whitespace();
+    let name = words.next()?.to_lowercase();
+    Some(ConsoleCommand {
+        name,
+        args: words.map(str::to_string).collect(),
+    })
+}
+
+/// Lists the registered commands and reports names nobody registered
+fn handle_help(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    registry: Res<ConsoleCommands>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "help" {
+            if registry.get(&command.name).is_none() {
+                log.print(format!(
+                    "未知命令：{}（输入 /help 查看所有命令）",
+                    command.name
+                ));
+            }
+            continue;
+        }
+        match command.args.first() {
+            Some(name) => match registry.get(name.trim_start_matches('/')) {
+                Some(info) => log.print(format!("/{} - {}", info.usage, info.description)),
+                None => log.print(format!("未知命令：{name}")),
+            },
+            None => {
+                log.print("可用命令：");
+                for info in &registry.commands {
+                    log.print(format!("  /{} - {}", info.usage, info.description));
+                }
+            }
+        }
+    }
+}
+
+fn update_console_text(
+    state: Res<ConsoleState>,
+    log: Res<ConsoleLog>,
+    mut text_q: Query<&mut Text, With<ConsoleText>>,
+) {
+    if !state.is_changed() && !log.is_changed() {
+        return;
+    }
+    let Ok(mut text) = text_q.single_mut() else {
+        return;
+    };
+    let skip = log.lines.len().saturating_sub(VISIBLE_LINES);
+    let mut content = String::new();
+    for line in log.lines.iter().skip(skip) {
+        content.push_str(line);
+        content.push('\n');
+    }
+    content.push('/');
+    content.push_str(&state.input);
+    content.push('_');
+    text.0 = content;
+}
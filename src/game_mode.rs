@@ -0,0 +1,298 @@
+//! Creative and survival game modes
+//!
+//! In creative mode placing doesn't use up the inventory, blocks break on the first click
+//! and the player can fly. Survival mode places only what the inventory holds, breaks a
+//! block after holding the break action for a time that grows with its hardness (see
+//! [`GameMode::break_time`]), can't break burning or hot blocks and keeps the player
+//! walking. The `gamemode` console command
+//! shows or switches the mode.
+//!
+//! The mode and the inventory belong to a world like its stats, so they're stored next to
+//! its regions in `<save directory>/player.ron` (see [`ActiveWorld`]). They're written
+//! every [`SAVE_INTERVAL_SECS`] seconds while they change, before another world is opened
+//! and when the game exits.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::items::Inventory;
+use crate::player::{MovementMode, PlayerCamera, PlayerPhysics};
+use crate::voxel::persistence::{write_atomic, ActiveWorld};
+use crate::voxel::VoxelKind;
+
+/// Player file inside the world's save directory
+pub const PLAYER_FILE: &str = "player.ron";
+/// How often a changed mode or inventory is written, in seconds
+pub const SAVE_INTERVAL_SECS: f32 = 30.0;
+/// Survival break time of a block without hardness, in seconds
+const BASE_BREAK_SECS: f32 = 0.1;
+/// Survival break time added per unit of hardness, in seconds
+const BREAK_SECS_PER_HARDNESS: f32 = 1.5;
+
+/// How the player interacts with the world
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Unlimited blocks, instant breaking, flying and no damage
+    #[default]
+    Creative,
+    /// Finite inventory, breaking takes time, hot blocks can't be broken and no flying
+    Survival,
+}
+
+impl GameMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            GameMode::Creative => "创造",
+            GameMode::Survival => "生存",
+        }
+    }
+
+    /// Whether placing takes blocks from the inventory
+    pub fn uses_inventory(self) -> bool {
+        self == GameMode::Survival
+    }
+
+    pub fn allows_flying(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    /// Whether heat, cold and drowning hurt the player
+    pub fn takes_damage(self) -> bool {
+        self == GameMode::Survival
+    }
+
+    /// Whether burning and hot blocks can be broken
+    pub fn breaks_hot_blocks(self) -> bool {
+        self == GameMode::Creative
+    }
+
+    /// Seconds the break action has to be held to break `kind`, zero when a click breaks it
+    pub fn break_time(self, kind: VoxelKind) -> f32 {
+        match self {
+            GameMode::Creative => 0.0,
+            GameMode::Survival => {
+                BASE_BREAK_SECS + kind.def().props.hardness.max(0.0) * BREAK_SECS_PER_HARDNESS
+            }
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "creative" | "c" => Some(GameMode::Creative),
+            "survival" | "s" => Some(GameMode::Survival),
+            _ => None,
+        }
+    }
+}
+
+/// What's saved for the player of a world
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct PlayerSave {
+    mode: GameMode,
+    inventory: Inventory,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PlayerSaveError {
+    #[error("could not access player file: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not parse player file: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize player file: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+/// Where the mode and inventory of the current world are saved
+#[derive(Resource, Debug, Default)]
+struct PlayerSaveFile {
+    /// None when they aren't persisted
+    path: Option<PathBuf>,
+    /// Whether the mode or inventory changed since they were last written
+    unsaved: bool,
+}
+
+impl PlayerSaveFile {
+    fn read(path: &Path) -> Result<PlayerSave, PlayerSaveError> {
+        let text = fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    fn save(&mut self, mode: GameMode, inventory: &Inventory) -> Result<(), PlayerSaveError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let save = PlayerSave {
+            mode,
+            inventory: inventory.clone(),
+        };
+        let text = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default())?;
+        write_atomic(path, text.as_bytes())?;
+        self.unsaved = false;
+        Ok(())
+    }
+
+    /// Writes the mode and inventory if they changed since the last save, reporting failures
+    fn save_if_changed(&mut self, mode: GameMode, inventory: &Inventory) {
+        if !self.unsaved {
+            return;
+        }
+        if let Err(err) = self.save(mode, inventory) {
+            warn!("Failed to save player: {err}");
+        }
+    }
+}
+
+pub struct GameModePlugin;
+
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "gamemode",
+            "gamemode [creative|survival]",
+            "查看或切换游戏模式（创造：方块无限、立即破坏、可以飞行；生存：消耗背包、按硬度破坏、不能飞行）",
+        );
+
+        app.init_resource::<GameMode>()
+            .init_resource::<Inventory>()
+            .init_resource::<PlayerSaveFile>()
+            .add_systems(
+                Update,
+                (
+                    load_player,
+                    gamemode_command,
+                    land_in_survival,
+                    track_player_changes,
+                    save_player_periodically,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, save_player_on_exit);
+    }
+}
+
+/// Reads the mode and inventory of the world being played, again when the new world
+/// screen opens another world; those of the previous world are written first. A missing
+/// file means a new world, which starts in the default mode with an empty inventory.
+fn load_player(
+    world: Res<ActiveWorld>,
+    mut file: ResMut<PlayerSaveFile>,
+    mut mode: ResMut<GameMode>,
+    mut inventory: ResMut<Inventory>,
+) {
+    if !world.is_changed() {
+        return;
+    }
+    file.save_if_changed(*mode, &inventory);
+
+    let path = world.storage.root().join(PLAYER_FILE);
+    let save = match PlayerSaveFile::read(&path) {
+        Ok(save) => save,
+        Err(PlayerSaveError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+            PlayerSave::default()
+        }
+        Err(err) => {
+            warn!("Ignoring {}: {err}", path.display());
+            PlayerSave::default()
+        }
+    };
+    *mode = save.mode;
+    *inventory = save.inventory;
+    *file = PlayerSaveFile {
+        path: Some(path),
+        unsaved: false,
+    };
+}
+
+fn gamemode_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut mode: ResMut<GameMode>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "gamemode" {
+            continue;
+        }
+        let args: Vec<&str> = command.args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            [] => log.print(format!("游戏模式：{}", mode.label())),
+            [name] => match GameMode::parse(name) {
+                Some(new_mode) => {
+                    if *mode != new_mode {
+                        *mode = new_mode;
+                        info!("Game mode: {new_mode:?}");
+                    }
+                    log.print(format!("游戏模式：{}", new_mode.label()));
+                }
+                None => log.print(format!("gamemode：未知的游戏模式：{name}")),
+            },
+            _ => log.print("用法：gamemode [creative|survival]"),
+        }
+    }
+}
+
+/// A flying player starts falling when the mode switches to one without flying
+fn land_in_survival(
+    mode: Res<GameMode>,
+    mut player_q: Query<(&mut MovementMode, &mut PlayerPhysics), With<PlayerCamera>>,
+) {
+    if !mode.is_changed() || mode.allows_flying() {
+        return;
+    }
+    let Ok((mut movement, mut physics)) = player_q.single_mut() else {
+        return;
+    };
+    if *movement == MovementMode::Fly {
+        *movement = MovementMode::Walk;
+        physics.velocity = Vec3::ZERO;
+        physics.on_ground = false;
+    }
+}
+
+/// Marks the player for the next save when the mode or inventory changed, except for the
+/// changes [`load_player`] made
+fn track_player_changes(
+    world: Res<ActiveWorld>,
+    mode: Res<GameMode>,
+    inventory: Res<Inventory>,
+    mut file: ResMut<PlayerSaveFile>,
+) {
+    if !world.is_changed() && (mode.is_changed() || inventory.is_changed()) && !file.unsaved {
+        file.unsaved = true;
+    }
+}
+
+fn save_player_periodically(
+    time: Res<Time>,
+    mode: Res<GameMode>,
+    inventory: Res<Inventory>,
+    mut elapsed: Local<f32>,
+    mut file: ResMut<PlayerSaveFile>,
+) {
+    *elapsed += time.delta_secs();
+    if *elapsed < SAVE_INTERVAL_SECS {
+        return;
+    }
+    *elapsed = 0.0;
+    file.save_if_changed(*mode, &inventory);
+}
+
+fn save_player_on_exit(
+    mut exit: MessageReader<AppExit>,
+    mode: Res<GameMode>,
+    inventory: Res<Inventory>,
+    mut file: ResMut<PlayerSaveFile>,
+) {
+    if exit.read().next().is_some() {
+        file.save_if_changed(*mode, &inventory);
+    }
+}
@@ -0,0 +1,159 @@
+//! Graphics quality presets
+//!
+//! The player camera starts with every expensive effect on: screen space reflections,
+//! volumetric fog lit by the sun, bloom and four shadow cascades. Weak GPUs can't keep up,
+//! so the settings page offers three presets ([`GraphicsQuality`]) that add or remove those
+//! components at runtime and pick the atmosphere rendering method and shadow map size:
+//!
+//! - Low: no reflections, volumetric fog or bloom, two 1024² shadow cascades up to 150
+//!   blocks and the lookup texture atmosphere
+//! - Medium: fog and bloom without reflections, three 2048² cascades up to 300 blocks and
+//!   the lookup texture atmosphere
+//! - High: everything, four 4096² cascades up to 500 blocks and the raymarched atmosphere
+//!
+//! The `1`/`2` atmosphere keys still switch the rendering method until the preset changes.
+
+use bevy::light::{
+    CascadeShadowConfig, CascadeShadowConfigBuilder, DirectionalLightShadowMap, VolumetricFog,
+    VolumetricLight,
+};
+use bevy::pbr::{AtmosphereMode, AtmosphereSettings, ScreenSpaceReflections};
+use bevy::post_process::bloom::Bloom;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::celestial::Sun;
+use crate::player::PlayerCamera;
+use crate::settings::GameSettings;
+
+/// How much rendering work the player camera does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GraphicsQuality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl GraphicsQuality {
+    pub fn label(self) -> &'static str {
+        match self {
+            GraphicsQuality::Low => "低",
+            GraphicsQuality::Medium => "中",
+            GraphicsQuality::High => "高",
+        }
+    }
+
+    /// The preset after this one, wrapping from High back to Low
+    pub fn next(self) -> Self {
+        match self {
+            GraphicsQuality::Low => GraphicsQuality::Medium,
+            GraphicsQuality::Medium => GraphicsQuality::High,
+            GraphicsQuality::High => GraphicsQuality::Low,
+        }
+    }
+
+    pub fn reflections(self) -> bool {
+        self == GraphicsQuality::High
+    }
+
+    pub fn volumetric_fog(self) -> bool {
+        self != GraphicsQuality::Low
+    }
+
+    pub fn bloom(self) -> bool {
+        self != GraphicsQuality::Low
+    }
+
+    pub fn atmosphere_mode(self) -> AtmosphereMode {
+        match self {
+            GraphicsQuality::High => AtmosphereMode::Raymarched,
+            GraphicsQuality::Low | GraphicsQuality::Medium => AtmosphereMode::LookupTexture,
+        }
+    }
+
+    /// Edge length of each sun shadow cascade's shadow map, in texels
+    pub fn shadow_map_size(self) -> usize {
+        match self {
+            GraphicsQuality::Low => 1024,
+            GraphicsQuality::Medium => 2048,
+            GraphicsQuality::High => 4096,
+        }
+    }
+
+    pub fn shadow_cascades(self) -> CascadeShadowConfig {
+        let (num_cascades, maximum_distance) = match self {
+            GraphicsQuality::Low => (2, 150.0),
+            GraphicsQuality::Medium => (3, 300.0),
+            GraphicsQuality::High => (4, 500.0),
+        };
+        CascadeShadowConfigBuilder {
+            num_cascades,
+            first_cascade_far_bound: 30.0,
+            maximum_distance,
+            ..default()
+        }
+        .build()
+    }
+}
+
+pub struct GraphicsPlugin;
+
+impl Plugin for GraphicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, apply_graphics_quality);
+    }
+}
+
+/// Adds or removes the camera and sun components of the selected preset whenever the
+/// preset changes, and once the camera and sun exist
+fn apply_graphics_quality(
+    mut commands: Commands,
+    settings: Res<GameSettings>,
+    mut applied: Local<Option<GraphicsQuality>>,
+    mut shadow_map: ResMut<DirectionalLightShadowMap>,
+    mut camera_q: Query<(Entity, &mut AtmosphereSettings), With<PlayerCamera>>,
+    sun_q: Query<Entity, With<Sun>>,
+) {
+    let quality = settings.graphics_quality;
+    if *applied == Some(quality) {
+        return;
+    }
+    let (Ok((camera, mut atmosphere)), Ok(sun)) = (camera_q.single_mut(), sun_q.single()) else {
+        return;
+    };
+
+    let mut camera_commands = commands.entity(camera);
+    if quality.reflections() {
+        camera_commands.insert(ScreenSpaceReflections::default());
+    } else {
+        camera_commands.remove::<ScreenSpaceReflections>();
+    }
+    if quality.bloom() {
+        camera_commands.insert(Bloom::NATURAL);
+    } else {
+        camera_commands.remove::<Bloom>();
+    }
+    if quality.volumetric_fog() {
+        camera_commands.insert(VolumetricFog {
+            ambient_intensity: 0.0,
+            ..default()
+        });
+    } else {
+        camera_commands.remove::<VolumetricFog>();
+    }
+
+    // The fog only scatters light from lights marked as volumetric
+    let mut sun_commands = commands.entity(sun);
+    if quality.volumetric_fog() {
+        sun_commands.insert(VolumetricLight);
+    } else {
+        sun_commands.remove::<VolumetricLight>();
+    }
+    sun_commands.insert(quality.shadow_cascades());
+
+    atmosphere.rendering_method = quality.atmosphere_mode();
+    shadow_map.size = quality.shadow_map_size();
+    *applied = Some(quality);
+    info!("Graphics quality: {quality:?}");
+}
@@ -0,0 +1,478 @@
+//! Player health
+//!
+//! The player has [`MAX_HEALTH`] hit points, shown as a bar at the bottom of the screen.
+//! While walking, the voxels around the player's body hurt:
+//!
+//! - Lava, burning blocks and anything hotter than [`HOT_DAMAGE_TEMP`] (temperatures come
+//!   from the thermal simulation, so standing next to a fire warms the air up)
+//! - Air colder than [`FREEZING_TEMP`], and nights under the open sky in snowy biomes or
+//!   above the snow line
+//! - Water over the camera drains the breath bar, and once it's empty the player drowns
+//!
+//! Health regenerates after a few seconds without damage. At zero health the player
+//! respawns at the spawn point with full health and breath. Flying takes no damage, and
+//! neither does anything in creative mode.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::camera_effects::CameraMedium;
+use crate::celestial::GameClock;
+use crate::console::ConsoleLog;
+use crate::game_mode::GameMode;
+use crate::player::{MovementMode, PlayerCamera, PlayerStance, RespawnPlayer};
+use crate::ui::UI_FONT_PATH;
+use crate::voxel::domains::thermal::ThermalApi;
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::{Chunks, VoxelFlags, VoxelKind, VoxelWorld};
+
+pub const MAX_HEALTH: f32 = 20.0;
+/// Seconds the player can stay under water before drowning
+pub const MAX_BREATH: f32 = 10.0;
+/// Voxels hotter than this hurt (°C)
+pub const HOT_DAMAGE_TEMP: f32 = 60.0;
+/// Voxels colder than this hurt (°C)
+pub const FREEZING_TEMP: f32 = -15.0;
+
+/// Damage per second standing in lava
+const LAVA_DAMAGE: f32 = 8.0;
+/// Damage per second touching a burning block
+const BURN_DAMAGE: f32 = 3.0;
+/// Damage per second at [`HOT_DAMAGE_TEMP`] + [`HEAT_DAMAGE_RANGE`] and above
+const HEAT_DAMAGE: f32 = 3.0;
+/// Degrees above [`HOT_DAMAGE_TEMP`] over which heat damage ramps up to its maximum
+const HEAT_DAMAGE_RANGE: f32 = 60.0;
+/// Damage per second in freezing air or exposed to a cold night
+const FREEZE_DAMAGE: f32 = 0.5;
+/// Damage per second once the breath bar is empty
+const DROWN_DAMAGE: f32 = 2.0;
+/// Breath regained per second above water
+const BREATH_REFILL_RATE: f32 = 4.0;
+/// Seconds without damage before health regenerates
+const REGEN_DELAY: f32 = 5.0;
+/// Health regained per second
+const REGEN_RATE: f32 = 0.5;
+/// Seconds the death notice stays on screen
+const DEATH_NOTICE_SECS: f32 = 3.0;
+
+const HEALTH_COLOR: Color = Color::srgb(0.85, 0.18, 0.16);
+const BREATH_COLOR: Color = Color::srgb(0.35, 0.65, 1.0);
+const BAR_BG: Color = Color::srgba(0.0, 0.0, 0.0, 0.55);
+
+/// What hurt the player
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageCause {
+    Lava,
+    Burning,
+    Heat,
+    Freezing,
+    Drowning,
+}
+
+impl DamageCause {
+    /// Shown when the player dies
+    pub fn death_message(self) -> &'static str {
+        match self {
+            DamageCause::Lava => "你掉进了熔岩",
+            DamageCause::Burning => "你被烧死了",
+            DamageCause::Heat => "你被高温烤死了",
+            DamageCause::Freezing => "你冻死了",
+            DamageCause::Drowning => "你淹死了",
+        }
+    }
+}
+
+/// The player's health and breath
+#[derive(Resource, Debug, Clone)]
+pub struct Health {
+    pub current: f32,
+    /// Seconds of air left under water
+    pub breath: f32,
+    /// Seconds since the player last took damage
+    since_damage: f32,
+    last_cause: Option<DamageCause>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            current: MAX_HEALTH,
+            breath: MAX_BREATH,
+            since_damage: REGEN_DELAY,
+            last_cause: None,
+        }
+    }
+}
+
+impl Health {
+    pub fn damage(&mut self, amount: f32, cause: DamageCause) {
+        if amount <= 0.0 {
+            return;
+        }
+        self.current = (self.current - amount).max(0.0);
+        self.since_damage = 0.0;
+        self.last_cause = Some(cause);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// The message shown after dying, and how long it stays on screen
+#[derive(Resource, Default)]
+struct DeathNotice {
+    message: &'static str,
+    remaining: f32,
+}
+
+#[derive(Component)]
+struct HealthFill;
+
+/// Container of the breath bar, hidden while the breath is full
+#[derive(Component)]
+struct BreathBar;
+
+#[derive(Component)]
+struct BreathFill;
+
+#[derive(Component)]
+struct DeathNoticeRoot;
+
+#[derive(Component)]
+struct DeathNoticeText;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Health>()
+            .init_resource::<DeathNotice>()
+            .add_systems(Startup, setup_health_hud)
+            .add_systems(
+                Update,
+                (
+                    environment_damage.run_if(takes_environment_damage),
+                    update_breath,
+                    regenerate_health,
+                    handle_death,
+                    update_health_hud,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup_health_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            bottom: px(36.0),
+            width: percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: px(4.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    bar_node(),
+                    BackgroundColor(BAR_BG),
+                    Visibility::Hidden,
+                    BreathBar,
+                ))
+                .with_child((fill_node(), BackgroundColor(BREATH_COLOR), BreathFill));
+            parent
+                .spawn((bar_node(), BackgroundColor(BAR_BG)))
+                .with_child((fill_node(), BackgroundColor(HEALTH_COLOR), HealthFill));
+        });
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: percent(35.0),
+                width: percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            Visibility::Hidden,
+            DeathNoticeRoot,
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont {
+                font: asset_server.load(UI_FONT_PATH),
+                font_size: 32.0,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.35, 0.3)),
+            DeathNoticeText,
+        ));
+}
+
+fn bar_node() -> Node {
+    Node {
+        width: px(220.0),
+        height: px(10.0),
+        padding: UiRect::all(px(2.0)),
+        ..default()
+    }
+}
+
+fn fill_node() -> Node {
+    Node {
+        width: percent(100.0),
+        height: percent(100.0),
+        ..default()
+    }
+}
+
+/// Hurts the player standing in hot, burning or freezing voxels, or exposed to a cold
+/// night without shelter
+/// Run condition of the damage from the surroundings, which spares creative players
+fn takes_environment_damage(game_mode: Res<GameMode>) -> bool {
+    game_mode.takes_damage()
+}
+
+fn environment_damage(
+    time: Res<Time>,
+    world: Res<VoxelWorld>,
+    chunks: Chunks,
+    terrain: Res<SharedTerrain>,
+    clock: Res<GameClock>,
+    player_q: Query<(&Transform, &PlayerStance, &MovementMode), With<PlayerCamera>>,
+    mut health: ResMut<Health>,
+) {
+    let Ok((transform, stance, mode)) = player_q.single() else {
+        return;
+    };
+    if *mode != MovementMode::Walk {
+        return;
+    }
+    let eye = transform.translation;
+    let feet = stance.feet(eye);
+
+    // The block stood on, the feet and the head
+    let mut hot: Option<(f32, DamageCause)> = None;
+    let mut freezing = false;
+    for point in [feet - Vec3::Y * 0.1, feet + Vec3::Y * 0.1, eye] {
+        let pos = point.floor().as_ivec3();
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        let Some(chunk) = chunks.get(&chunk_pos) else {
+            continue;
+        };
+        let kind = chunk.voxels.get(idx);
+        let temp = ThermalApi::get_temp(chunk, idx);
+        let (rate, cause) = if kind == VoxelKind::Lava {
+            (LAVA_DAMAGE, DamageCause::Lava)
+        } else if chunk.flags.get(idx).contains(VoxelFlags::BURNING) {
+            (BURN_DAMAGE, DamageCause::Burning)
+        } else if temp > HOT_DAMAGE_TEMP {
+            let heat = ((temp - HOT_DAMAGE_TEMP) / HEAT_DAMAGE_RANGE).min(1.0);
+            (HEAT_DAMAGE * heat, DamageCause::Heat)
+        } else {
+            freezing |= temp < FREEZING_TEMP;
+            continue;
+        };
+        if hot.is_none_or(|(worst, _)| rate > worst) {
+            hot = Some((rate, cause));
+        }
+    }
+
+    let dt = time.delta_secs();
+    if let Some((rate, cause)) = hot {
+        health.damage(rate * dt, cause);
+    }
+
+    let cold_region = terrain.generator().is_cold(
+        eye.x.floor() as i32,
+        feet.y.floor() as i32,
+        eye.z.floor() as i32,
+    );
+    let sheltered = world
+        .heightmap
+        .height(eye.x.floor() as i32, eye.z.floor() as i32)
+        .is_some_and(|height| height as f32 >= eye.y);
+    if freezing || (cold_region && !clock.is_day() && !sheltered) {
+        health.damage(FREEZE_DAMAGE * dt, DamageCause::Freezing);
+    }
+}
+
+/// Drains the breath with the camera under water and drowns the player once it's empty
+fn update_breath(
+    time: Res<Time>,
+    game_mode: Res<GameMode>,
+    player_q: Query<(&CameraMedium, &MovementMode), With<PlayerCamera>>,
+    mut health: ResMut<Health>,
+) {
+    let Ok((medium, mode)) = player_q.single() else {
+        return;
+    };
+    let dt = time.delta_secs();
+    if *medium == CameraMedium::Water && *mode == MovementMode::Walk && game_mode.takes_damage() {
+        health.breath = (health.breath - dt).max(0.0);
+        if health.breath <= 0.0 {
+            health.damage(DROWN_DAMAGE * dt, DamageCause::Drowning);
+        }
+    } else if health.breath < MAX_BREATH {
+        health.breath = (health.breath + BREATH_REFILL_RATE * dt).min(MAX_BREATH);
+    }
+}
+
+fn regenerate_health(time: Res<Time>, mut health: ResMut<Health>) {
+    let dt = time.delta_secs();
+    health.since_damage += dt;
+    if health.since_damage >= REGEN_DELAY && health.current < MAX_HEALTH && !health.is_dead() {
+        health.current = (health.current + REGEN_RATE * dt).min(MAX_HEALTH);
+    }
+}
+
+/// Sends a dead player back to the spawn point with full health
+fn handle_death(
+    mut health: ResMut<Health>,
+    mut notice: ResMut<DeathNotice>,
+    mut respawn: MessageWriter<RespawnPlayer>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    if !health.is_dead() {
+        return;
+    }
+    let message = health
+        .last_cause
+        .map_or("你死了", DamageCause::death_message);
+    info!("Player died: {message}");
+    log.print(format!("{message}，回到出生点"));
+    respawn.write(RespawnPlayer);
+    *health = Health::default();
+    *notice = DeathNotice {
+        message,
+        remaining: DEATH_NOTICE_SECS,
+    };
+}
+
+/// The health and breath bar nodes
+#[derive(SystemParam)]
+struct HealthBars<'w, 's> {
+    health_fill_q: Query<'w, 's, &'static mut Node, (With<HealthFill>, Without<BreathFill>)>,
+    breath_fill_q: Query<'w, 's, &'static mut Node, (With<BreathFill>, Without<HealthFill>)>,
+    breath_bar_q:
+        Query<'w, 's, &'static mut Visibility, (With<BreathBar>, Without<DeathNoticeRoot>)>,
+}
+
+/// The death notice overlay and its message text
+#[derive(SystemParam)]
+struct DeathNoticeUi<'w, 's> {
+    root_q: Query<'w, 's, &'static mut Visibility, With<DeathNoticeRoot>>,
+    text_q: Query<'w, 's, &'static mut Text, With<DeathNoticeText>>,
+}
+
+fn update_health_hud(
+    time: Res<Time>,
+    health: Res<Health>,
+    mut notice: ResMut<DeathNotice>,
+    mut bars: HealthBars,
+    mut notice_ui: DeathNoticeUi,
+) {
+    for mut node in &mut bars.health_fill_q {
+        node.width = percent(health.current / MAX_HEALTH * 100.0);
+    }
+    for mut node in &mut bars.breath_fill_q {
+        node.width = percent(health.breath / MAX_BREATH * 100.0);
+    }
+    for mut visibility in &mut bars.breath_bar_q {
+        visibility.set_if_neq(if health.breath < MAX_BREATH {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+
+    let showing = notice.remaining > 0.0;
+    for mut visibility in &mut notice_ui.root_q {
+        visibility.set_if_neq(if showing {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+    if !showing {
+        return;
+    }
+    for mut text in &mut notice_ui.text_q {
+        if text.0 != notice.message {
+            text.0 = notice.message.to_string();
+        }
+    }
+    notice.remaining -= time.delta_secs();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::spawn_chunk;
+    use crate::voxel::seed::WorldSeed;
+    use crate::voxel::worldgen::WorldGenConfig;
+    use crate::voxel::{ChunkData, ChunkPos};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Health after one second standing in lava with the head under water and no breath left
+    fn health_after_lava_and_water(game_mode: GameMode) -> Health {
+        let mut chunk = ChunkData::new();
+        for y in 0..8 {
+            chunk.set(4, y, 4, VoxelKind::Lava);
+        }
+
+        let mut world = World::new();
+        spawn_chunk(&mut world, ChunkPos::new(0, 0, 0), chunk);
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs(1));
+        world.insert_resource(time);
+        world.insert_resource(SharedTerrain::new(
+            WorldSeed::new(1),
+            WorldGenConfig::default(),
+            Arc::new(Vec::new()),
+        ));
+        world.insert_resource(game_mode);
+        world.insert_resource(Health {
+            breath: 0.0,
+            ..default()
+        });
+        world.init_resource::<VoxelWorld>();
+        world.init_resource::<GameClock>();
+        world.spawn((
+            PlayerCamera,
+            Transform::from_xyz(4.5, 6.0, 4.5),
+            PlayerStance::default(),
+            MovementMode::Walk,
+            CameraMedium::Water,
+        ));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                environment_damage.run_if(takes_environment_damage),
+                update_breath,
+            )
+                .chain(),
+        );
+        schedule.run(&mut world);
+        world.remove_resource::<Health>().unwrap()
+    }
+
+    #[test]
+    fn test_survival_takes_environment_damage() {
+        let health = health_after_lava_and_water(GameMode::Survival);
+        assert!(health.current <= MAX_HEALTH - LAVA_DAMAGE - DROWN_DAMAGE);
+        assert_eq!(health.breath, 0.0);
+    }
+
+    #[test]
+    fn test_creative_takes_no_environment_damage() {
+        let health = health_after_lava_and_water(GameMode::Creative);
+        assert_eq!(health.current, MAX_HEALTH);
+        assert!(health.breath > 0.0);
+    }
+}
@@ -0,0 +1,375 @@
+//! Input actions
+//!
+//! Systems ask whether an [`Action`] is pressed instead of checking raw keys. Each
+//! action has a list of bindings (keys or mouse buttons) and any of them triggers it,
+//! so alternate layouts can be bound side by side. The bindings are part of the
+//! settings file and can be changed from the settings page of the pause menu.
+//!
+//! While a text field or the full-screen map has the keyboard (see [`InputCapture`])
+//! no action reports as pressed, so typing into the console or panning the map doesn't
+//! also walk or break blocks.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Everything the player can trigger from the keyboard or mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    /// Jump while walking, move up while flying
+    Jump,
+    /// Move down while flying
+    Descend,
+    /// Hold to run faster
+    Sprint,
+    /// Hold to move slowly without walking off edges
+    Crouch,
+    ToggleFly,
+    BreakBlock,
+    /// Use the held item: plant a sapling from the inventory on the targeted dirt or
+    /// grass, or scoop up or pour out water with the bucket
+    PlaceBlock,
+    /// Switch between holding saplings and the bucket
+    SwitchItem,
+    /// Open/close the pause menu, also cancels key capture on the settings page
+    Pause,
+    ToggleDebugOverlay,
+    ToggleChunkBorders,
+    ToggleWorldGrid,
+    PlaceHeatSource,
+    ShowTemperature,
+    ClearTemperature,
+    ToggleThermalOverlay,
+    PrintWorldDigest,
+    /// Save a PNG of the window to the captures directory
+    Screenshot,
+    /// Open/close the full-screen world map
+    ToggleMap,
+    /// Save a waypoint where the player stands
+    SaveWaypoint,
+    AtmosphereLookupTexture,
+    AtmosphereRaymarched,
+    ToggleCelestialPause,
+    ExposureUp,
+    ExposureDown,
+    OpenConsole,
+    /// Pause/resume the block simulation (heat, fire, water, growth)
+    ToggleSimulationPause,
+    /// Pause the block simulation and advance it by one tick
+    StepSimulation,
+    SimulationFaster,
+    SimulationSlower,
+    /// Undo the last block edit (with Ctrl held)
+    UndoEdit,
+    /// Redo the last undone block edit (with Ctrl held)
+    RedoEdit,
+    /// Roll the photo mode camera counterclockwise
+    PhotoRollLeft,
+    /// Roll the photo mode camera clockwise
+    PhotoRollRight,
+    /// Show/hide the photo mode panel
+    PhotoPanel,
+}
+
+impl Action {
+    pub const ALL: [Action; 39] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Descend,
+        Action::Sprint,
+        Action::Crouch,
+        Action::ToggleFly,
+        Action::BreakBlock,
+        Action::PlaceBlock,
+        Action::SwitchItem,
+        Action::Pause,
+        Action::ToggleDebugOverlay,
+        Action::ToggleChunkBorders,
+        Action::ToggleWorldGrid,
+        Action::PlaceHeatSource,
+        Action::ShowTemperature,
+        Action::ClearTemperature,
+        Action::ToggleThermalOverlay,
+        Action::PrintWorldDigest,
+        Action::Screenshot,
+        Action::ToggleMap,
+        Action::SaveWaypoint,
+        Action::AtmosphereLookupTexture,
+        Action::AtmosphereRaymarched,
+        Action::ToggleCelestialPause,
+        Action::ExposureUp,
+        Action::ExposureDown,
+        Action::OpenConsole,
+        Action::ToggleSimulationPause,
+        Action::StepSimulation,
+        Action::SimulationFaster,
+        Action::SimulationSlower,
+        Action::UndoEdit,
+        Action::RedoEdit,
+        Action::PhotoRollLeft,
+        Action::PhotoRollRight,
+        Action::PhotoPanel,
+    ];
+
+    /// Name shown on the settings page
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveForward => "前进",
+            Action::MoveBack => "后退",
+            Action::MoveLeft => "向左",
+            Action::MoveRight => "向右",
+            Action::Jump => "跳跃 / 上升",
+            Action::Descend => "下降",
+            Action::Sprint => "疾跑",
+            Action::Crouch => "潜行",
+            Action::ToggleFly => "切换飞行",
+            Action::BreakBlock => "破坏方块",
+            Action::PlaceBlock => "使用物品",
+            Action::SwitchItem => "切换手持物品",
+            Action::Pause => "暂停菜单",
+            Action::ToggleDebugOverlay => "调试信息",
+            Action::ToggleChunkBorders => "区块边界",
+            Action::ToggleWorldGrid => "世界网格",
+            Action::PlaceHeatSource => "放置热源",
+            Action::ShowTemperature => "显示温度",
+            Action::ClearTemperature => "清除温度",
+            Action::ToggleThermalOverlay => "温度可视化",
+            Action::PrintWorldDigest => "输出世界摘要",
+            Action::Screenshot => "截图",
+            Action::ToggleMap => "世界地图",
+            Action::SaveWaypoint => "保存路标",
+            Action::AtmosphereLookupTexture => "大气：查找表",
+            Action::AtmosphereRaymarched => "大气：光线步进",
+            Action::ToggleCelestialPause => "暂停日月运行",
+            Action::ExposureUp => "增加曝光",
+            Action::ExposureDown => "降低曝光",
+            Action::OpenConsole => "命令控制台",
+            Action::ToggleSimulationPause => "暂停模拟",
+            Action::StepSimulation => "模拟单步",
+            Action::SimulationFaster => "模拟加速",
+            Action::SimulationSlower => "模拟减速",
+            Action::UndoEdit => "撤销编辑（按住 Ctrl）",
+            Action::RedoEdit => "重做编辑（按住 Ctrl）",
+            Action::PhotoRollLeft => "拍照：向左旋转",
+            Action::PhotoRollRight => "拍照：向右旋转",
+            Action::PhotoPanel => "拍照：设置面板",
+        }
+    }
+
+    pub fn default_bindings(self) -> Vec<Binding> {
+        let binding = match self {
+            Action::MoveForward => Binding::Key(KeyCode::KeyW),
+            Action::MoveBack => Binding::Key(KeyCode::KeyS),
+            Action::MoveLeft => Binding::Key(KeyCode::KeyA),
+            Action::MoveRight => Binding::Key(KeyCode::KeyD),
+            Action::Jump => Binding::Key(KeyCode::Space),
+            Action::Descend => Binding::Key(KeyCode::ShiftLeft),
+            Action::Sprint => Binding::Key(KeyCode::ControlLeft),
+            Action::Crouch => Binding::Key(KeyCode::KeyC),
+            Action::ToggleFly => Binding::Key(KeyCode::KeyF),
+            Action::BreakBlock => Binding::Mouse(MouseButton::Left),
+            Action::PlaceBlock => Binding::Mouse(MouseButton::Right),
+            Action::SwitchItem => Binding::Key(KeyCode::KeyR),
+            Action::Pause => Binding::Key(KeyCode::Escape),
+            Action::ToggleDebugOverlay => Binding::Key(KeyCode::F3),
+            Action::ToggleChunkBorders => Binding::Key(KeyCode::F4),
+            Action::ToggleWorldGrid => Binding::Key(KeyCode::F10),
+            Action::PlaceHeatSource => Binding::Key(KeyCode::F5),
+            Action::ShowTemperature => Binding::Key(KeyCode::F6),
+            Action::ClearTemperature => Binding::Key(KeyCode::F7),
+            Action::ToggleThermalOverlay => Binding::Key(KeyCode::F8),
+            Action::PrintWorldDigest => Binding::Key(KeyCode::F9),
+            Action::Screenshot => Binding::Key(KeyCode::F2),
+            Action::ToggleMap => Binding::Key(KeyCode::KeyM),
+            Action::SaveWaypoint => Binding::Key(KeyCode::KeyB),
+            Action::AtmosphereLookupTexture => Binding::Key(KeyCode::Digit1),
+            Action::AtmosphereRaymarched => Binding::Key(KeyCode::Digit2),
+            Action::ToggleCelestialPause => Binding::Key(KeyCode::KeyP),
+            // Up makes the image brighter, i.e. lowers EV100
+            Action::ExposureUp => Binding::Key(KeyCode::ArrowUp),
+            Action::ExposureDown => Binding::Key(KeyCode::ArrowDown),
+            Action::OpenConsole => Binding::Key(KeyCode::Slash),
+            Action::ToggleSimulationPause => Binding::Key(KeyCode::KeyO),
+            Action::StepSimulation => Binding::Key(KeyCode::Period),
+            Action::SimulationFaster => Binding::Key(KeyCode::BracketRight),
+            Action::SimulationSlower => Binding::Key(KeyCode::BracketLeft),
+            Action::UndoEdit => Binding::Key(KeyCode::KeyZ),
+            Action::RedoEdit => Binding::Key(KeyCode::KeyY),
+            Action::PhotoRollLeft => Binding::Key(KeyCode::KeyQ),
+            Action::PhotoRollRight => Binding::Key(KeyCode::KeyE),
+            Action::PhotoPanel => Binding::Key(KeyCode::Tab),
+        };
+        vec![binding]
+    }
+}
+
+/// A physical input that can trigger an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    /// Short display name ("W" instead of "KeyW")
+    pub fn name(self) -> String {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{key:?}");
+                ["Key", "Digit"]
+                    .iter()
+                    .find_map(|prefix| name.strip_prefix(prefix))
+                    .map(str::to_string)
+                    .unwrap_or(name)
+            }
+            Binding::Mouse(MouseButton::Left) => "鼠标左键".to_string(),
+            Binding::Mouse(MouseButton::Right) => "鼠标右键".to_string(),
+            Binding::Mouse(MouseButton::Middle) => "鼠标中键".to_string(),
+            Binding::Mouse(button) => format!("鼠标 {button:?}"),
+        }
+    }
+}
+
+/// Bindings of every action
+///
+/// Actions missing from the settings file keep their default bindings, so files
+/// written before an action existed still load.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "BTreeMap<Action, Vec<Binding>>",
+    into = "BTreeMap<Action, Vec<Binding>>"
+)]
+pub struct InputBindings {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self::from(BTreeMap::new())
+    }
+}
+
+impl From<BTreeMap<Action, Vec<Binding>>> for InputBindings {
+    fn from(mut bindings: BTreeMap<Action, Vec<Binding>>) -> Self {
+        for action in Action::ALL {
+            bindings
+                .entry(action)
+                .or_insert_with(|| action.default_bindings());
+        }
+        Self { bindings }
+    }
+}
+
+impl From<InputBindings> for BTreeMap<Action, Vec<Binding>> {
+    fn from(bindings: InputBindings) -> Self {
+        bindings.bindings
+    }
+}
+
+impl InputBindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Actions that `binding` triggers
+    pub fn actions_for(&self, binding: Binding) -> impl Iterator<Item = Action> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, list)| list.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// Replaces the first binding of `action`, keeping its alternates. If another
+    /// action's first binding was `binding`, that action takes over the replaced
+    /// binding, so rebinding never leaves an action without input.
+    pub fn set_primary(&mut self, action: Action, binding: Binding) {
+        let previous = self.get(action).first().copied();
+
+        for (other, list) in self.bindings.iter_mut() {
+            if *other == action {
+                continue;
+            }
+            match (list.first() == Some(&binding), previous) {
+                (true, Some(previous)) => list[0] = previous,
+                _ => list.retain(|b| *b != binding),
+            }
+        }
+
+        let list = self.bindings.entry(action).or_default();
+        list.retain(|b| *b != binding);
+        if list.is_empty() {
+            list.push(binding);
+        } else {
+            list[0] = binding;
+        }
+    }
+}
+
+/// Whether a text field or the full-screen map currently owns the keyboard
+#[derive(Resource, Debug, Default)]
+pub struct InputCapture {
+    pub typing: bool,
+}
+
+/// Read access to the current state of every action
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    bindings: Res<'w, InputBindings>,
+    capture: Res<'w, InputCapture>,
+}
+
+impl ActionInput<'_> {
+    /// Whether any binding of `action` is held down
+    pub fn pressed(&self, action: Action) -> bool {
+        !self.capture.typing
+            && self
+                .bindings
+                .get(action)
+                .iter()
+                .any(|binding| match *binding {
+                    Binding::Key(key) => self.keys.pressed(key),
+                    Binding::Mouse(button) => self.mouse.pressed(button),
+                })
+    }
+
+    /// Whether any binding of `action` was pressed this frame
+    pub fn just_pressed(&self, action: Action) -> bool {
+        !self.capture.typing
+            && self
+                .bindings
+                .get(action)
+                .iter()
+                .any(|binding| match *binding {
+                    Binding::Key(key) => self.keys.just_pressed(key),
+                    Binding::Mouse(button) => self.mouse.just_pressed(button),
+                })
+    }
+
+    /// First binding pressed this frame, used when capturing a new binding
+    pub fn any_just_pressed(&self) -> Option<Binding> {
+        self.keys
+            .get_just_pressed()
+            .next()
+            .map(|&key| Binding::Key(key))
+            .or_else(|| {
+                self.mouse
+                    .get_just_pressed()
+                    .next()
+                    .map(|&button| Binding::Mouse(button))
+            })
+    }
+
+    pub fn bindings(&self) -> &InputBindings {
+        &self.bindings
+    }
+}
@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::input::{Action, ActionInput};
+use crate::player::{distance_to_player, PlayerCamera, PlayerStance};
+use crate::raycast::BlockBroken;
+use crate::ui::MenuState;
+use crate::voxel::collision::{sweep_axis, Aabb};
+use crate::voxel::domains::fluid::is_fluid;
+use crate::voxel::{ivec3_to_vec3, ChunkLookup, Chunks, VoxelKind};
+
+/// Edge length of a dropped item cube
+const DROP_SIZE: f32 = 0.25;
+/// Upward velocity a drop pops out of the broken block with (blocks/s)
+const DROP_POP_SPEED: f32 = 4.0;
+/// Downward acceleration of drops (blocks/s²)
+const DROP_GRAVITY: f32 = 20.0;
+/// Maximum falling speed of drops (blocks/s)
+const DROP_TERMINAL_VELOCITY: f32 = 30.0;
+/// Spin speed around the vertical axis (radians/s)
+const DROP_SPIN_SPEED: f32 = 2.0;
+/// Height of the resting bob animation
+const DROP_BOB_HEIGHT: f32 = 0.08;
+/// Drops can't be collected until they've existed this long, so they visibly pop out first
+const PICKUP_DELAY: f32 = 0.4;
+/// Drops closer than this to the player collider are collected
+const PICKUP_RANGE: f32 = 1.0;
+/// Uncollected drops disappear after this many seconds
+const DROP_LIFETIME: f32 = 300.0;
+
+/// What the player's bucket holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Bucket {
+    #[default]
+    Empty,
+    Water,
+}
+
+/// Item the place action uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HeldItem {
+    #[default]
+    Sapling,
+    Bucket,
+}
+
+/// Blocks the player has collected, plus the bucket they always carry
+///
+/// Saved with the world together with the game mode, see [`crate::game_mode`]
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Inventory {
+    counts: HashMap<VoxelKind, u32>,
+    pub bucket: Bucket,
+    pub held: HeldItem,
+}
+
+impl Inventory {
+    pub fn add(&mut self, kind: VoxelKind, amount: u32) {
+        *self.counts.entry(kind).or_default() += amount;
+    }
+
+    pub fn count(&self, kind: VoxelKind) -> u32 {
+        self.counts.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// Removes one `kind`, returning false if there was none
+    pub fn take(&mut self, kind: VoxelKind) -> bool {
+        match self.counts.get_mut(&kind) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// What breaking a block leaves behind: leaves drop saplings, everything else itself
+fn drop_kind(broken: VoxelKind) -> VoxelKind {
+    match broken {
+        VoxelKind::OakLeaves | VoxelKind::BirchLeaves | VoxelKind::SpruceLeaves => {
+            VoxelKind::Sapling
+        }
+        kind => kind,
+    }
+}
+
+/// A block lying in the world waiting to be picked up
+#[derive(Component, Debug)]
+pub struct ItemDrop {
+    pub kind: VoxelKind,
+    /// Center of the cube, without the bob animation
+    pub position: Vec3,
+    pub vertical_velocity: f32,
+    pub age: f32,
+}
+
+/// Shared mesh and per-kind materials for drop entities
+#[derive(Resource)]
+struct DropAssets {
+    mesh: Handle<Mesh>,
+    materials: HashMap<VoxelKind, Handle<StandardMaterial>>,
+}
+
+pub struct ItemsPlugin;
+
+impl Plugin for ItemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inventory>()
+            .add_systems(Startup, setup_drop_assets)
+            .add_systems(
+                Update,
+                (spawn_item_drops, update_item_drops, collect_item_drops).chain(),
+            )
+            .add_systems(Update, switch_held_item);
+    }
+}
+
+fn setup_drop_assets(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(DropAssets {
+        mesh: meshes.add(Cuboid::from_length(DROP_SIZE)),
+        materials: HashMap::new(),
+    });
+}
+
+/// Spawns a drop for every block the player broke this frame
+fn spawn_item_drops(
+    mut commands: Commands,
+    mut broken: MessageReader<BlockBroken>,
+    mut drop_assets: ResMut<DropAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in broken.read() {
+        if is_fluid(event.kind) {
+            continue;
+        }
+        let kind = drop_kind(event.kind);
+
+        let material = drop_assets
+            .materials
+            .entry(kind)
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: kind.def().color,
+                    perceptual_roughness: 0.9,
+                    ..default()
+                })
+            })
+            .clone();
+
+        let position = ivec3_to_vec3(event.pos) + Vec3::splat(0.5);
+        commands.spawn((
+            Mesh3d(drop_assets.mesh.clone()),
+            MeshMaterial3d(material),
+            Transform::from_translation(position),
+            ItemDrop {
+                kind,
+                position,
+                vertical_velocity: DROP_POP_SPEED,
+                age: 0.0,
+            },
+        ));
+    }
+}
+
+/// Drops fall until they land on a solid voxel, then spin and bob in place
+fn update_item_drops(
+    mut commands: Commands,
+    time: Res<Time>,
+    chunks: Chunks,
+    mut drops: Query<(Entity, &mut ItemDrop, &mut Transform)>,
+) {
+    let dt = time.delta_secs();
+    let half = DROP_SIZE * 0.5;
+
+    for (entity, mut drop, mut transform) in &mut drops {
+        drop.age += dt;
+        if drop.age > DROP_LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        // Pushed out upwards if a block now occupies the drop's space
+        let inside = drop.position.floor().as_ivec3();
+        if chunks.get_voxel(inside).is_solid() {
+            drop.position.y = inside.y as f32 + 1.0 + half;
+            drop.vertical_velocity = 0.0;
+        }
+
+        drop.vertical_velocity =
+            (drop.vertical_velocity - DROP_GRAVITY * dt).max(-DROP_TERMINAL_VELOCITY);
+
+        // Land on top of the voxel under the cube's bottom face
+        let bounds = Aabb::cube(drop.position, half);
+        let fall = sweep_axis(&chunks, bounds, 1, drop.vertical_velocity * dt);
+        drop.position += fall.offset;
+        if fall.blocked.y {
+            drop.vertical_velocity = 0.0;
+        }
+
+        let bob = (drop.age * 2.5).sin() * DROP_BOB_HEIGHT + DROP_BOB_HEIGHT;
+        transform.translation = drop.position + Vec3::Y * bob;
+        transform.rotation = Quat::from_rotation_y(drop.age * DROP_SPIN_SPEED);
+    }
+}
+
+/// Moves drops near the player into the inventory
+fn collect_item_drops(
+    mut commands: Commands,
+    mut inventory: ResMut<Inventory>,
+    camera_q: Query<(&GlobalTransform, &PlayerStance), With<PlayerCamera>>,
+    drops: Query<(Entity, &ItemDrop)>,
+) {
+    let Ok((camera, stance)) = camera_q.single() else {
+        return;
+    };
+    let feet = stance.feet(camera.translation());
+
+    for (entity, drop) in &drops {
+        if drop.age < PICKUP_DELAY || distance_to_player(feet, drop.position) > PICKUP_RANGE {
+            continue;
+        }
+
+        inventory.add(drop.kind, 1);
+        info!(
+            "Picked up {} ({} in inventory)",
+            drop.kind.def().name,
+            inventory.count(drop.kind)
+        );
+        commands.entity(entity).despawn();
+    }
+}
+
+/// The switch item action (R by default) swaps between the saplings and the bucket
+fn switch_held_item(
+    actions: ActionInput,
+    menu_state: Res<MenuState>,
+    mut inventory: ResMut<Inventory>,
+) {
+    if menu_state.open || !actions.just_pressed(Action::SwitchItem) {
+        return;
+    }
+    inventory.held = match inventory.held {
+        HeldItem::Sapling => HeldItem::Bucket,
+        HeldItem::Bucket => HeldItem::Sapling,
+    };
+    match inventory.held {
+        HeldItem::Sapling => info!(
+            "Holding saplings ({} in inventory)",
+            inventory.count(VoxelKind::Sapling)
+        ),
+        HeldItem::Bucket => info!("Holding the bucket ({:?})", inventory.bucket),
+    }
+}
@@ -0,0 +1,31 @@
+//! Voxworld game modules
+//!
+//! Shared by the game binary (`main.rs`) and the criterion benchmarks in `benches/`.
+
+pub mod audio;
+pub mod camera_effects;
+pub mod capture;
+pub mod celestial;
+pub mod clouds;
+pub mod console;
+pub mod game_mode;
+pub mod graphics;
+pub mod health;
+pub mod input;
+pub mod items;
+pub mod map;
+pub mod mobs;
+pub mod net;
+pub mod new_world;
+pub mod particles;
+pub mod photo_mode;
+pub mod player;
+pub mod raycast;
+pub mod replay;
+pub mod settings;
+pub mod stats;
+pub mod ui;
+pub mod voxel;
+pub mod waypoints;
+pub mod world_border;
+pub mod world_state;
@@ -1,26 +1,131 @@
-mod celestial;
-mod player;
-mod raycast;
-mod ui;
-mod voxel;
+use voxworld::{
+    audio, camera_effects, capture, celestial, clouds, console, game_mode, graphics, health, input,
+    items, map, mobs, net, new_world, particles, photo_mode, player, raycast, replay, settings,
+    stats, ui, voxel, waypoints, world_border, world_state,
+};
 
+use audio::SoundPlugin;
 use bevy::camera::Exposure;
 use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
-use bevy::input::keyboard::KeyCode;
 use bevy::pbr::{AtmosphereMode, AtmosphereSettings};
 use bevy::prelude::*;
+use camera_effects::CameraEffectsPlugin;
+use capture::CapturePlugin;
 use celestial::{CelestialPlugin, CelestialSettings};
+use clouds::CloudsPlugin;
+use console::ConsolePlugin;
+use game_mode::GameModePlugin;
+use graphics::GraphicsPlugin;
+use health::HealthPlugin;
+use input::{Action, ActionInput};
+use items::ItemsPlugin;
+use map::MapPlugin;
+use mobs::MobsPlugin;
+use net::client::NetClientPlugin;
+use new_world::{NewWorldPlugin, NewWorldScreen};
+use particles::ParticlesPlugin;
+use photo_mode::PhotoModePlugin;
 use player::PlayerPlugin;
 use raycast::RaycastPlugin;
+use replay::{ReplayPlayback, ReplayPlugin};
+use settings::SettingsPlugin;
+use stats::StatsPlugin;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use ui::UiPlugin;
-use voxel::{VoxelPlugin, WorldSeed};
+use voxel::bench::{per_second, run_bench_world, DEFAULT_BENCH_RADIUS};
+use voxel::headless::{
+    install_block_definitions, run_headless_sim, HeadlessSimOptions, DEFAULT_SIM_RADIUS,
+    DEFAULT_SIM_TICKS,
+};
+use voxel::persistence::WorldStorage;
+use voxel::pregen::{run_pregen, PregenOptions};
+use voxel::{TerrainShape, VoxelPlugin, WorldGenOptions, WorldSeed};
+use waypoints::WaypointsPlugin;
+use world_border::WorldBorderPlugin;
+use world_state::WorldStatePlugin;
 
 fn main() {
-    // Parse seed from command line or environment variable
-    let seed = parse_seed();
+    // Parse seed from command line or environment variable; without one the game asks
+    // for it on the new world screen and the random seed is only its suggestion
+    let explicit_seed = parse_seed();
+    let mut show_new_world = explicit_seed.is_none();
+    let mut seed = explicit_seed.unwrap_or_else(|| {
+        let random_seed = WorldSeed::random_seed();
+        info!("Using random seed: {}", random_seed);
+        WorldSeed::new(random_seed)
+    });
 
-    App::new()
-        .insert_resource(seed)
+    // Headless pre-generation: --pregen <radius> [--save-dir <path>] [--threads <n>]
+    if let Some(radius) = parse_arg("--pregen") {
+        std::process::exit(pregen(seed.seed, radius));
+    }
+
+    // Headless benchmark: --bench-world [radius] (pass --seed for comparable runs)
+    if std::env::args().any(|arg| arg == "--bench-world") {
+        let radius = parse_arg("--bench-world").unwrap_or(DEFAULT_BENCH_RADIUS);
+        std::process::exit(bench_world(seed.seed, radius));
+    }
+
+    // Headless simulation: --headless-sim [ticks] [--sim-radius <radius>]
+    // [--sim-command <line>]... [--sim-json <path or ->]
+    if std::env::args().any(|arg| arg == "--headless-sim") {
+        let ticks = parse_arg("--headless-sim").unwrap_or(DEFAULT_SIM_TICKS);
+        std::process::exit(headless_sim(seed.seed, ticks));
+    }
+
+    // Headless sync server: --server <port>
+    if let Some(port) = parse_arg("--server") {
+        std::process::exit(net::server::run_server(seed.seed, port));
+    }
+
+    // Terrain: --generator <normal|flat|superflat|showcase>, overrides the mode in worldgen.ron
+    let mut options = WorldGenOptions {
+        shape: parse_generator(),
+        ..default()
+    };
+
+    // Multiplayer client: --connect <host:port>, plays in the server's world
+    let mut app = App::new();
+    if let Some(addr) = parse_arg::<String>("--connect") {
+        match net::client::connect(&addr) {
+            Ok((client, server_seed)) => {
+                println!("Connected to {addr}, world seed {server_seed}");
+                seed = WorldSeed::new(server_seed);
+                show_new_world = false;
+                app.insert_resource(client);
+            }
+            Err(err) => {
+                eprintln!("Could not connect to {addr}: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Replay playback: --replay <file>, plays in a fresh copy of the recorded world
+    if let Some(path) = parse_arg::<String>("--replay") {
+        match ReplayPlayback::open(Path::new(&path)) {
+            Ok((playback, meta)) => {
+                println!("Playing back {path}, world seed {}", meta.seed);
+                seed = WorldSeed::new(meta.seed);
+                options = meta.options;
+                show_new_world = false;
+                app.insert_resource(ReplayPlayback::world(meta))
+                    .insert_resource(playback);
+            }
+            Err(err) => {
+                eprintln!("Could not open replay {path}: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if show_new_world {
+        app.insert_resource(NewWorldScreen::opened(seed.seed, options));
+    }
+
+    app.insert_resource(seed)
+        .insert_resource(options)
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Voxworld".to_string(),
@@ -33,22 +138,67 @@ fn main() {
             PlayerPlugin,
             CelestialPlugin,
             RaycastPlugin,
+            ItemsPlugin,
+            SettingsPlugin,
             UiPlugin,
+            SoundPlugin,
+            ParticlesPlugin,
+            NetClientPlugin,
+            ConsolePlugin,
+            CapturePlugin,
+            MapPlugin,
+            WaypointsPlugin,
             FrameTimeDiagnosticsPlugin::default(),
         ))
+        .add_plugins((
+            NewWorldPlugin,
+            CameraEffectsPlugin,
+            MobsPlugin,
+            ReplayPlugin,
+            WorldBorderPlugin,
+            HealthPlugin,
+            StatsPlugin,
+            CloudsPlugin,
+            PhotoModePlugin,
+            GameModePlugin,
+            WorldStatePlugin,
+            GraphicsPlugin,
+        ))
         .add_systems(Startup, print_controls)
         .add_systems(Update, atmosphere_controls)
         .run();
 }
 
 fn print_controls() {
-    println!("=== Voxworld Controls ===");
+    println!("=== Voxworld Controls (defaults, rebind in Esc > Settings) ===");
     println!("  WASD       - Move");
-    println!("  Space      - Move up");
-    println!("  Shift      - Move down");
+    println!("  Space      - Jump (walk) / Move up (fly)");
+    println!("  Shift      - Move down (fly)");
+    println!("  Ctrl       - Sprint (hold)");
+    println!("  C          - Crouch (hold, walk mode)");
+    println!("  F          - Toggle walk/fly mode (creative mode only)");
     println!("  Mouse      - Look around");
-    println!("  Esc        - Pause menu");
+    println!("  Left click - Break block (hold in survival mode, where hot blocks can't be mined)");
+    println!("  Right click - Plant sapling (leaves drop saplings) or use the bucket");
+    println!("  R          - Switch between saplings and the bucket");
+    println!("  Esc        - Pause menu / settings");
+    println!("  /          - Command console (/help lists commands)");
+    println!("  M          - World map (wheel to zoom, drag to pan)");
+    println!("  B          - Save waypoint (Esc > Waypoints to teleport)");
+    println!("  F2         - Screenshot (/timelapse for timelapses)");
+    println!("  Q/E, Tab   - Roll, settings panel in photo mode (Esc > Photo mode)");
     println!("  F3         - Toggle debug overlay");
+    println!("  F4         - Toggle chunk borders");
+    println!("  F10        - Toggle world grid");
+    println!("  F8         - Toggle thermal overlay");
+    println!("  F9         - Print world generation digest");
+    println!("  Ctrl+Z/Y   - Undo/Redo block edits");
+    println!("  /record    - Record block changes for --replay <file>");
+    println!("  /gamemode  - Switch between creative and survival mode");
+    println!("  /worldgen  - Try another generation preset or shape on untouched chunks");
+    println!("  O          - Pause/Resume block simulation (/sim for more)");
+    println!("  .          - Step block simulation by one tick");
+    println!("  [ / ]      - Slow down/Speed up block simulation");
     println!();
     println!("=== Atmosphere Controls ===");
     println!("  1          - Switch to lookup texture rendering method");
@@ -58,45 +208,213 @@ fn print_controls() {
 }
 
 fn atmosphere_controls(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: ActionInput,
     mut atmosphere_settings: Query<&mut AtmosphereSettings>,
     mut celestial_settings: ResMut<CelestialSettings>,
     mut camera_exposure: Query<&mut Exposure, With<Camera3d>>,
     time: Res<Time>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Digit1) {
+    if actions.just_pressed(Action::AtmosphereLookupTexture) {
         for mut settings in &mut atmosphere_settings {
             settings.rendering_method = AtmosphereMode::LookupTexture;
             println!("Switched to lookup texture rendering method");
         }
     }
 
-    if keyboard_input.just_pressed(KeyCode::Digit2) {
+    if actions.just_pressed(Action::AtmosphereRaymarched) {
         for mut settings in &mut atmosphere_settings {
             settings.rendering_method = AtmosphereMode::Raymarched;
             println!("Switched to raymarched rendering method");
         }
     }
 
-    if keyboard_input.just_pressed(KeyCode::KeyP) {
+    if actions.just_pressed(Action::ToggleCelestialPause) {
         celestial_settings.paused = !celestial_settings.paused;
         println!("Celestial motion: {}", if celestial_settings.paused { "PAUSED" } else { "RESUMED" });
     }
 
-    if keyboard_input.pressed(KeyCode::ArrowUp) {
+    if actions.pressed(Action::ExposureUp) {
         for mut exposure in &mut camera_exposure {
             exposure.ev100 -= time.delta_secs() * 2.0;
         }
     }
 
-    if keyboard_input.pressed(KeyCode::ArrowDown) {
+    if actions.pressed(Action::ExposureDown) {
         for mut exposure in &mut camera_exposure {
             exposure.ev100 += time.delta_secs() * 2.0;
         }
     }
 }
 
-fn parse_seed() -> WorldSeed {
+/// Value following a command line flag, if present and parseable
+fn parse_arg<T: std::str::FromStr>(flag: &str) -> Option<T> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .and_then(|value| value.parse().ok())
+}
+
+/// Every value following a flag that may be repeated
+fn parse_arg_values(flag: &str) -> Vec<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.windows(2)
+        .filter(|pair| pair[0] == flag)
+        .map(|pair| pair[1].clone())
+        .collect()
+}
+
+/// Generate and save all chunks within `radius` chunks of the origin without
+/// starting the renderer. Returns the process exit code.
+fn pregen(seed: u32, radius: i32) -> i32 {
+    let storage = parse_arg::<String>("--save-dir")
+        .map(WorldStorage::new)
+        .unwrap_or_else(|| WorldStorage::for_seed(seed));
+    let threads = parse_arg("--threads").unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+
+    let options = PregenOptions {
+        radius: radius.max(0),
+        seed,
+        storage,
+        threads,
+    };
+
+    match run_pregen(&options) {
+        Ok(summary) => {
+            println!(
+                "[pregen] Done: {} chunks in {} regions in {:.1}s",
+                summary.chunks,
+                summary.regions,
+                summary.elapsed.as_secs_f32()
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("[pregen] Failed: {err}");
+            1
+        }
+    }
+}
+
+/// Time chunk generation, meshing and command commits on a single thread and
+/// print the throughput. Returns the process exit code.
+fn bench_world(seed: u32, radius: i32) -> i32 {
+    println!("[bench] seed {seed}, radius {radius} chunks");
+    let report = run_bench_world(seed, radius);
+    let line = |name: &str, count: usize, elapsed: Duration, unit: &str| {
+        println!(
+            "[bench] {name:<24} {count:>7} in {:>8.1}ms  {:>10.0} {unit}/s",
+            elapsed.as_secs_f64() * 1000.0,
+            per_second(count, elapsed)
+        );
+    };
+    line("generate_chunk", report.chunks, report.generate, "chunks");
+    line(
+        "mesh (empty edges)",
+        report.meshed_chunks,
+        report.mesh_without_neighbors,
+        "chunks",
+    );
+    line(
+        "mesh (neighbor edges)",
+        report.meshed_chunks,
+        report.mesh_with_neighbors,
+        "chunks",
+    );
+    line(
+        "mesh (dense underground)",
+        report.dense_chunks,
+        report.mesh_dense,
+        "chunks",
+    );
+    line("commit_system", report.commands, report.commit, "commands");
+    0
+}
+
+/// Run the block simulation without a window and print a summary, optionally also
+/// written as JSON. Returns the process exit code.
+fn headless_sim(seed: u32, ticks: u64) -> i32 {
+    install_block_definitions();
+    let options = HeadlessSimOptions {
+        seed,
+        radius: parse_arg("--sim-radius").unwrap_or(DEFAULT_SIM_RADIUS),
+        ticks,
+        worldgen: WorldGenOptions {
+            shape: parse_generator(),
+            ..default()
+        },
+        commands: parse_arg_values("--sim-command"),
+    };
+    println!(
+        "[sim] seed {seed}, radius {} chunks, {ticks} ticks",
+        options.radius
+    );
+
+    let start = Instant::now();
+    let summary = match run_headless_sim(&options) {
+        Ok(summary) => summary,
+        Err(err) => {
+            eprintln!("[sim] Failed: {err}");
+            return 1;
+        }
+    };
+    for line in &summary.console {
+        println!("[sim] {line}");
+    }
+    println!(
+        "[sim] {} chunks, {} ticks in {:.1}s",
+        summary.chunks,
+        summary.ticks,
+        start.elapsed().as_secs_f32()
+    );
+    println!(
+        "[sim] changes {}, changed blocks {}, burned {}, still burning {}",
+        summary.changes, summary.changed_blocks, summary.burned_blocks, summary.burning
+    );
+    match (summary.max_temperature, summary.mean_temperature) {
+        (Some(max), Some(mean)) => println!(
+            "[sim] active thermal {}, max {max:.1}°C, mean {mean:.1}°C",
+            summary.active_thermal
+        ),
+        _ => println!("[sim] no active thermal blocks"),
+    }
+    for (kind, delta) in &summary.block_deltas {
+        println!("[sim]   {kind:<16} {delta:+}");
+    }
+    println!("[sim] world digest {}", summary.digest);
+
+    // --sim-json - prints the JSON to stdout instead of a file
+    if let Some(path) = parse_arg::<String>("--sim-json") {
+        let json = serde_json::to_string_pretty(&summary).expect("summary serializes to JSON");
+        if path == "-" {
+            println!("{json}");
+        } else if let Err(err) = std::fs::write(&path, json) {
+            eprintln!("[sim] Could not write {path}: {err}");
+            return 1;
+        } else {
+            println!("[sim] Summary written to {path}");
+        }
+    }
+    0
+}
+
+/// Terrain shape given with --generator; unknown names fall back to the normal terrain
+fn parse_generator() -> TerrainShape {
+    let Some(name) = parse_arg::<String>("--generator") else {
+        return TerrainShape::default();
+    };
+    TerrainShape::from_name(&name).unwrap_or_else(|| {
+        eprintln!("Unknown generator {name:?}, expected normal, flat, superflat or showcase");
+        TerrainShape::default()
+    })
+}
+
+/// Seed given with --seed/-s or VOXWORLD_SEED, if any
+fn parse_seed() -> Option<WorldSeed> {
     // Check command line arguments: --seed <value> or -s <value>
     let args: Vec<String> = std::env::args().collect();
     for i in 0..args.len() {
@@ -105,11 +423,11 @@ fn parse_seed() -> WorldSeed {
             // Try to parse as number first
             if let Ok(num) = seed_str.parse::<u32>() {
                 info!("Using seed from command line: {}", num);
-                return WorldSeed::new(num);
+                return Some(WorldSeed::new(num));
             } else {
                 // Use string as seed
                 info!("Using string seed from command line: {}", seed_str);
-                return WorldSeed::from_string(seed_str);
+                return Some(WorldSeed::from_string(seed_str));
             }
         }
     }
@@ -118,19 +436,12 @@ fn parse_seed() -> WorldSeed {
     if let Ok(seed_str) = std::env::var("VOXWORLD_SEED") {
         if let Ok(num) = seed_str.parse::<u32>() {
             info!("Using seed from environment: {}", num);
-            return WorldSeed::new(num);
+            return Some(WorldSeed::new(num));
         } else {
             info!("Using string seed from environment: {}", seed_str);
-            return WorldSeed::from_string(&seed_str);
+            return Some(WorldSeed::from_string(&seed_str));
         }
     }
 
-    // Generate random seed based on current time
-    let random_seed = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map(|d| d.as_millis() as u32)
-        .unwrap_or(12345);
-
-    info!("Using random seed: {}", random_seed);
-    WorldSeed::new(random_seed)
+    None
 }
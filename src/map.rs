@@ -0,0 +1,586 @@
+//! World map
+//!
+//! Every chunk column that has been loaded leaves a [`MapTile`] of surface colors in
+//! the [`ExploredMap`], taken from the highest non-air block of each world column and
+//! shaded by height. Tiles stay after their chunks unload, so the map shows everywhere
+//! the player has been. Columns are rescanned when the heightmap reports them changed
+//! (see [`Heightmap::columns_since`]).
+//!
+//! The map is drawn into two textures: a minimap in the top right corner, centered on
+//! the player with north up, and a full-screen map toggled with [`Action::ToggleMap`]
+//! (`M` by default). The full-screen map zooms with the mouse wheel and pans by
+//! dragging or with WASD/arrow keys; while it is open it owns the keyboard and mouse
+//! (see [`InputCapture`]). Both show the player as an arrow pointing where the camera
+//! faces.
+//!
+//! [`Heightmap::columns_since`]: crate::voxel::heightmap::Heightmap::columns_since
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::input::mouse::AccumulatedMouseScroll;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::window::{CursorGrabMode, CursorOptions};
+use std::collections::HashMap;
+
+use crate::input::{Action, ActionInput, Binding, InputBindings, InputCapture};
+use crate::player::{LookAngles, PlayerCamera};
+use crate::ui::{MenuState, UI_FONT_PATH};
+use crate::voxel::{
+    ChunkLookup, ChunkPos, Chunks, VoxelKind, VoxelRegistry, VoxelWorld, WorldGenConfig, CHUNK_SIZE,
+};
+
+/// World columns in one chunk column
+const COLUMN_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+/// Minimap texture size; one block per pixel
+const MINIMAP_PIXELS: u32 = 128;
+/// Minimap size on screen
+const MINIMAP_DISPLAY: f32 = 192.0;
+/// Full-screen map texture size
+const FULL_MAP_PIXELS: u32 = 512;
+/// Full-screen map height on screen, in percent of the window height
+const FULL_MAP_HEIGHT: f32 = 85.0;
+/// Blocks per pixel of the full-screen map zoom levels, closest first
+const ZOOM_LEVELS: [u32; 5] = [1, 2, 4, 8, 16];
+/// Full-screen map pan speed with the keyboard, in pixels per second
+const KEY_PAN_SPEED: f32 = 240.0;
+/// Facing is quantized to this many steps before deciding whether to redraw
+const FACING_STEPS: f32 = 64.0;
+/// Color of columns nobody has seen
+const UNEXPLORED: [u8; 4] = [16, 18, 24, 255];
+const MARKER_COLOR: [u8; 4] = [255, 64, 48, 255];
+/// Distance from the player's position to the tip of the marker, in pixels
+const MARKER_SIZE: f32 = 5.0;
+const MAP_FRAME: Color = Color::srgba(0.06, 0.08, 0.12, 0.78);
+const FULL_MAP_BG: Color = Color::srgba(0.0, 0.0, 0.0, 0.85);
+
+/// Surface colors of one chunk column, indexed by `z * CHUNK_SIZE + x`
+///
+/// Alpha is 0 for world columns whose loaded chunks are all air.
+pub struct MapTile {
+    pixels: [[u8; 4]; COLUMN_AREA],
+}
+
+impl MapTile {
+    /// Scans the loaded chunks of `column` (`layers` are chunk y, highest first) for
+    /// the top non-air block of each world column
+    fn scan(chunks: &impl ChunkLookup, column: IVec2, layers: &[i32], water_level: i32) -> Self {
+        let mut surface: [Option<(VoxelKind, i32)>; COLUMN_AREA] = [None; COLUMN_AREA];
+        for &layer in layers {
+            let Some(chunk) = chunks.chunk(&ChunkPos::new(column.x, layer, column.y)) else {
+                continue;
+            };
+            if chunk.voxels.uniform_value() == Some(VoxelKind::Air) {
+                continue;
+            }
+            for (i, top) in surface.iter_mut().enumerate() {
+                if top.is_some() {
+                    continue;
+                }
+                let (x, z) = (i as i32 % CHUNK_SIZE, i as i32 / CHUNK_SIZE);
+                *top = (0..CHUNK_SIZE).rev().find_map(|y| {
+                    let kind = chunk.get(x, y, z);
+                    (kind != VoxelKind::Air).then_some((kind, layer * CHUNK_SIZE + y))
+                });
+            }
+        }
+
+        let registry = VoxelRegistry::current();
+        let mut pixels = [[0; 4]; COLUMN_AREA];
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let Some((kind, height)) = surface[i] else {
+                continue;
+            };
+            // Relief: slopes facing north are lit, slopes facing south are shaded
+            let north = i
+                .checked_sub(CHUNK_SIZE as usize)
+                .and_then(|north| surface[north])
+                .map_or(height, |(_, north_height)| north_height);
+            let relief = (height - north).clamp(-1, 1) as f32 * 0.08;
+            let altitude = ((height - water_level) as f32 / 96.0).clamp(-0.35, 0.25);
+            let shade = 1.0 + altitude + relief;
+
+            let color = registry.get(kind).face_color(IVec3::Y).to_srgba();
+            let channel = |c: f32| ((c * shade).clamp(0.0, 1.0) * 255.0) as u8;
+            *pixel = [
+                channel(color.red),
+                channel(color.green),
+                channel(color.blue),
+                255,
+            ];
+        }
+        Self { pixels }
+    }
+}
+
+/// Surface colors of every chunk column seen so far
+#[derive(Resource, Default)]
+pub struct ExploredMap {
+    tiles: HashMap<IVec2, MapTile>,
+    /// Heightmap revision the tiles are up to date with
+    revision: u64,
+    /// Bumped whenever a tile changes, so the maps know to redraw
+    changes: u64,
+}
+
+impl ExploredMap {
+    /// Surface color of world column (x, z), if it has been seen
+    pub fn color(&self, x: i32, z: i32) -> Option<[u8; 4]> {
+        let tile = self.tiles.get(&IVec2::new(
+            x.div_euclid(CHUNK_SIZE),
+            z.div_euclid(CHUNK_SIZE),
+        ))?;
+        let idx = z.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE);
+        let pixel = tile.pixels[idx as usize];
+        (pixel[3] != 0).then_some(pixel)
+    }
+
+    /// Number of chunk columns seen
+    pub fn explored_columns(&self) -> usize {
+        self.tiles.len()
+    }
+}
+
+/// Full-screen map state
+#[derive(Resource, Debug, Default)]
+pub struct MapView {
+    pub full_screen: bool,
+    /// Index into [`ZOOM_LEVELS`]
+    zoom: usize,
+    /// Offset of the map center from the player, in blocks
+    pan: Vec2,
+}
+
+impl MapView {
+    /// Blocks per pixel of the full-screen map
+    pub fn blocks_per_pixel(&self) -> u32 {
+        ZOOM_LEVELS[self.zoom]
+    }
+}
+
+/// Textures the maps are drawn into
+#[derive(Resource)]
+struct MapImages {
+    minimap: Handle<Image>,
+    full: Handle<Image>,
+}
+
+/// What a map texture was last drawn with; it is redrawn only when this changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DrawKey {
+    /// Map center in pixels
+    center: IVec2,
+    blocks_per_pixel: u32,
+    /// Player position relative to the center, in pixels
+    marker: IVec2,
+    facing: i32,
+    changes: u64,
+}
+
+#[derive(Component)]
+struct FullMapRoot;
+
+#[derive(Component)]
+struct FullMapText;
+
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExploredMap>()
+            .init_resource::<MapView>()
+            .add_systems(Startup, setup_map)
+            .add_systems(
+                Update,
+                (
+                    update_explored_map,
+                    close_full_map,
+                    open_full_map,
+                    control_full_map,
+                    draw_maps,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn map_image(size: u32) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &UNEXPLORED,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    // Keep block edges sharp when the texture is scaled up on screen
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn setup_map(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    asset_server: Res<AssetServer>,
+) {
+    let minimap = images.add(map_image(MINIMAP_PIXELS));
+    let full = images.add(map_image(FULL_MAP_PIXELS));
+
+    // Minimap (top right, below the seed info)
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: px(14.0),
+                top: px(64.0),
+                padding: UiRect::all(px(4.0)),
+                ..default()
+            },
+            BackgroundColor(MAP_FRAME),
+        ))
+        .with_child((
+            ImageNode::new(minimap.clone()),
+            Node {
+                width: px(MINIMAP_DISPLAY),
+                height: px(MINIMAP_DISPLAY),
+                ..default()
+            },
+        ));
+
+    // Full-screen map (initially hidden)
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: percent(100.0),
+                height: percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: px(10.0),
+                ..default()
+            },
+            BackgroundColor(FULL_MAP_BG),
+            GlobalZIndex(10),
+            Visibility::Hidden,
+            FullMapRoot,
+        ))
+        .with_children(|root| {
+            root.spawn((
+                ImageNode::new(full.clone()),
+                Node {
+                    height: vh(FULL_MAP_HEIGHT),
+                    aspect_ratio: Some(1.0),
+                    ..default()
+                },
+            ));
+            root.spawn((
+                Text::new(""),
+                TextFont {
+                    font: asset_server.load(UI_FONT_PATH),
+                    font_size: 15.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.8, 0.85, 0.9)),
+                FullMapText,
+            ));
+        });
+
+    commands.insert_resource(MapImages { minimap, full });
+}
+
+/// Rescans the chunk columns the heightmap refreshed since the last update
+fn update_explored_map(
+    world: Res<VoxelWorld>,
+    chunks: Chunks,
+    config: Res<WorldGenConfig>,
+    mut map: ResMut<ExploredMap>,
+) {
+    let revision = world.heightmap.revision();
+    if revision == map.revision {
+        return;
+    }
+    let mut layers: HashMap<IVec2, Vec<i32>> = world
+        .heightmap
+        .columns_since(map.revision)
+        .map(|column| (column, Vec::new()))
+        .collect();
+    map.revision = revision;
+
+    for chunk_pos in chunks.chunk_positions() {
+        if let Some(column) = layers.get_mut(&IVec2::new(chunk_pos.x, chunk_pos.z)) {
+            column.push(chunk_pos.y);
+        }
+    }
+    for (column, mut column_layers) in layers {
+        column_layers.sort_unstable_by(|a, b| b.cmp(a));
+        let tile = MapTile::scan(&chunks, column, &column_layers, config.terrain.water_level);
+        map.tiles.insert(column, tile);
+    }
+    map.changes += 1;
+}
+
+/// Shows or hides the full-screen map and hands the cursor to it or back to the game
+fn set_full_map_open(
+    open: bool,
+    view: &mut MapView,
+    capture: &mut InputCapture,
+    cursor_options: &mut CursorOptions,
+    root_q: &mut Query<&mut Visibility, With<FullMapRoot>>,
+) {
+    view.full_screen = open;
+    capture.typing = open;
+    cursor_options.visible = open;
+    cursor_options.grab_mode = if open {
+        CursorGrabMode::None
+    } else {
+        CursorGrabMode::Locked
+    };
+    if let Ok(mut visibility) = root_q.single_mut() {
+        *visibility = if open {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+/// Closes the full-screen map on its toggle key or Escape
+///
+/// Actions report nothing while the map has the keyboard, so the keys are read
+/// directly. They are cleared afterwards so the same press doesn't reopen the map or
+/// open the pause menu.
+fn close_full_map(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    bindings: Res<InputBindings>,
+    mut view: ResMut<MapView>,
+    mut capture: ResMut<InputCapture>,
+    mut cursor_options: Single<&mut CursorOptions>,
+    mut root_q: Query<&mut Visibility, With<FullMapRoot>>,
+) {
+    if !view.full_screen {
+        return;
+    }
+    let mut close_keys = bindings
+        .get(Action::ToggleMap)
+        .iter()
+        .filter_map(|binding| match *binding {
+            Binding::Key(key) => Some(key),
+            Binding::Mouse(_) => None,
+        })
+        .chain([KeyCode::Escape]);
+    let Some(key) = close_keys.find(|key| keys.just_pressed(*key)) else {
+        return;
+    };
+    keys.clear_just_pressed(key);
+    set_full_map_open(
+        false,
+        &mut view,
+        &mut capture,
+        &mut cursor_options,
+        &mut root_q,
+    );
+}
+
+/// Opens the full-screen map centered on the player, unless the pause menu is up
+fn open_full_map(
+    actions: ActionInput,
+    menu_state: Res<MenuState>,
+    mut view: ResMut<MapView>,
+    mut capture: ResMut<InputCapture>,
+    mut cursor_options: Single<&mut CursorOptions>,
+    mut root_q: Query<&mut Visibility, With<FullMapRoot>>,
+) {
+    if view.full_screen || menu_state.open || !actions.just_pressed(Action::ToggleMap) {
+        return;
+    }
+    view.pan = Vec2::ZERO;
+    set_full_map_open(
+        true,
+        &mut view,
+        &mut capture,
+        &mut cursor_options,
+        &mut root_q,
+    );
+}
+
+/// Zooms the full-screen map with the mouse wheel and pans it by dragging or with
+/// WASD/arrow keys; Space centers it back on the player
+fn control_full_map(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    scroll: Res<AccumulatedMouseScroll>,
+    window: Single<&Window>,
+    time: Res<Time>,
+    mut view: ResMut<MapView>,
+    mut last_cursor: Local<Option<Vec2>>,
+) {
+    if !view.full_screen {
+        *last_cursor = None;
+        return;
+    }
+
+    if scroll.delta.y > 0.0 {
+        view.zoom = view.zoom.saturating_sub(1);
+    } else if scroll.delta.y < 0.0 {
+        view.zoom = (view.zoom + 1).min(ZOOM_LEVELS.len() - 1);
+    }
+    let blocks_per_pixel = view.blocks_per_pixel() as f32;
+
+    // Map pixels per logical screen pixel
+    let image_scale = FULL_MAP_PIXELS as f32 / (window.height() * FULL_MAP_HEIGHT / 100.0).max(1.0);
+    let cursor = window.cursor_position();
+    if mouse.pressed(MouseButton::Left) {
+        if let (Some(last), Some(cursor)) = (*last_cursor, cursor)
+            && cursor != last
+        {
+            view.pan -= (cursor - last) * image_scale * blocks_per_pixel;
+        }
+        *last_cursor = cursor;
+    } else {
+        *last_cursor = None;
+    }
+
+    let mut direction = Vec2::ZERO;
+    for (keys_for, step) in [
+        ([KeyCode::KeyW, KeyCode::ArrowUp], Vec2::NEG_Y),
+        ([KeyCode::KeyS, KeyCode::ArrowDown], Vec2::Y),
+        ([KeyCode::KeyA, KeyCode::ArrowLeft], Vec2::NEG_X),
+        ([KeyCode::KeyD, KeyCode::ArrowRight], Vec2::X),
+    ] {
+        if keys.any_pressed(keys_for) {
+            direction += step;
+        }
+    }
+    if direction != Vec2::ZERO {
+        view.pan += direction * KEY_PAN_SPEED * blocks_per_pixel * time.delta_secs();
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        view.pan = Vec2::ZERO;
+    }
+}
+
+/// Fills `pixels` (RGBA8, `size` × `size`) with the map around world position
+/// `center` (x, z) at `blocks_per_pixel`
+fn draw_map(map: &ExploredMap, center: IVec2, blocks_per_pixel: u32, size: u32, pixels: &mut [u8]) {
+    let scale = blocks_per_pixel as i32;
+    let half = size as i32 / 2;
+    for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+        let (px, py) = (i as i32 % size as i32, i as i32 / size as i32);
+        let x = center.x + (px - half) * scale;
+        let z = center.y + (py - half) * scale;
+        pixel.copy_from_slice(&map.color(x, z).unwrap_or(UNEXPLORED));
+    }
+}
+
+/// Draws the player arrow with its tip pointing along `facing` (map space, +y is
+/// south) from pixel `at`
+fn draw_marker(pixels: &mut [u8], size: u32, at: Vec2, facing: Vec2) {
+    let side = facing.perp();
+    let tip = at + facing * MARKER_SIZE;
+    let left = at - facing * (MARKER_SIZE * 0.6) + side * (MARKER_SIZE * 0.7);
+    let right = at - facing * (MARKER_SIZE * 0.6) - side * (MARKER_SIZE * 0.7);
+    let edge = |a: Vec2, b: Vec2, p: Vec2| (b - a).perp_dot(p - a);
+
+    let radius = MARKER_SIZE.ceil() as i32 + 1;
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let p = at.floor() + Vec2::new(dx as f32, dy as f32) + Vec2::splat(0.5);
+            let inside = [
+                edge(tip, left, p),
+                edge(left, right, p),
+                edge(right, tip, p),
+            ];
+            if !(inside.iter().all(|e| *e >= 0.0) || inside.iter().all(|e| *e <= 0.0)) {
+                continue;
+            }
+            let (x, y) = (p.x as i32, p.y as i32);
+            if x < 0 || y < 0 || x >= size as i32 || y >= size as i32 {
+                continue;
+            }
+            let idx = (y as usize * size as usize + x as usize) * 4;
+            pixels[idx..idx + 4].copy_from_slice(&MARKER_COLOR);
+        }
+    }
+}
+
+/// Redraws the minimap, and the full-screen map while it is open, when the player
+/// moved or turned, the view changed or new tiles arrived
+fn draw_maps(
+    map: Res<ExploredMap>,
+    view: Res<MapView>,
+    map_images: Res<MapImages>,
+    mut images: ResMut<Assets<Image>>,
+    camera_q: Query<(&Transform, &LookAngles), With<PlayerCamera>>,
+    mut text_q: Query<&mut Text, With<FullMapText>>,
+    mut last: Local<[Option<DrawKey>; 2]>,
+) {
+    let Ok((transform, angles)) = camera_q.single() else {
+        return;
+    };
+    let player = transform.translation.xz();
+    // Camera forward is -Z rotated by yaw
+    let facing = Vec2::new(-angles.yaw.sin(), -angles.yaw.cos());
+    let facing_step = (angles.yaw.rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU
+        * FACING_STEPS)
+        .round() as i32;
+
+    let mut targets = vec![(0, &map_images.minimap, MINIMAP_PIXELS, 1, Vec2::ZERO)];
+    if view.full_screen {
+        targets.push((
+            1,
+            &map_images.full,
+            FULL_MAP_PIXELS,
+            view.blocks_per_pixel(),
+            view.pan,
+        ));
+    }
+
+    for (slot, handle, size, blocks_per_pixel, pan) in targets {
+        let scale = blocks_per_pixel as f32;
+        let center = ((player + pan) / scale).floor().as_ivec2();
+        let marker = (player / scale).floor().as_ivec2() - center;
+        let key = DrawKey {
+            center,
+            blocks_per_pixel,
+            marker,
+            facing: facing_step,
+            changes: map.changes,
+        };
+        if last[slot] == Some(key) {
+            continue;
+        }
+        let Some(image) = images.get_mut(handle) else {
+            continue;
+        };
+        let Some(pixels) = image.data.as_mut() else {
+            continue;
+        };
+        last[slot] = Some(key);
+
+        draw_map(
+            &map,
+            center * blocks_per_pixel as i32,
+            blocks_per_pixel,
+            size,
+            pixels,
+        );
+        let at = (player / scale) - center.as_vec2() + Vec2::splat((size / 2) as f32);
+        draw_marker(pixels, size, at, facing);
+    }
+
+    if view.full_screen
+        && (view.is_changed() || map.is_changed())
+        && let Ok(mut text) = text_q.single_mut()
+    {
+        text.0 = format!(
+            "缩放：每像素 {} 格    已探索 {} 个区块列    滚轮缩放  拖动/WASD 平移  空格回到玩家  Esc 关闭",
+            view.blocks_per_pixel(),
+            map.explored_columns()
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! Mobs: small animals living on the surface around the player
+//!
+//! The only mob so far is the [`Critter`], a passive animal that wanders around on grass.
+//! Critters are spawned on grass columns between [`SPAWN_MIN_DISTANCE`] and
+//! [`SPAWN_MAX_DISTANCE`] blocks from the player and despawned again beyond
+//! [`DESPAWN_DISTANCE`], so only the area around the player is populated.
+//!
+//! - Spawn density depends on the biome at the column (plains are busiest, forests and
+//!   taiga quieter, deserts, snow and oceans have none) and on the day/night clock:
+//!   far fewer critters appear at night, and the ones around mostly stand still
+//! - Movement uses the voxel collision queries: critters fall under gravity, hop onto
+//!   one-block ledges and turn around at drops they couldn't climb back up
+//! - Critters in unloaded chunks are frozen until the ground under them exists
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+use crate::celestial::GameClock;
+use crate::player::{PlayerCamera, PlayerStance};
+use crate::voxel::collision::{aabb_collides, sweep_aabb, Aabb};
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::{Biome, ChunkLookup, ChunkPos, Chunks, VoxelKind, VoxelWorld, XorShift32};
+
+/// Most critters alive at once
+const MAX_CRITTERS: usize = 16;
+/// Seconds between spawn attempts
+const SPAWN_INTERVAL: f32 = 0.5;
+/// Closest a critter spawns to the player (blocks), so they don't pop in on screen
+pub const SPAWN_MIN_DISTANCE: f32 = 16.0;
+/// Farthest a critter spawns from the player (blocks)
+pub const SPAWN_MAX_DISTANCE: f32 = 40.0;
+/// Critters farther than this from the player horizontally are removed (blocks)
+pub const DESPAWN_DISTANCE: f32 = 64.0;
+/// Share of the daytime spawn rate left at midnight
+const NIGHT_SPAWN_FACTOR: f32 = 0.15;
+
+/// Half of the critter collider's horizontal extent
+const CRITTER_HALF_WIDTH: f32 = 0.3;
+/// Height of the critter collider
+const CRITTER_HEIGHT: f32 = 0.6;
+const CRITTER_WALK_SPEED: f32 = 1.2;
+/// Upward speed of a hop onto a ledge, enough to clear one block
+const CRITTER_HOP_SPEED: f32 = 6.5;
+const CRITTER_GRAVITY: f32 = 20.0;
+const CRITTER_TERMINAL_VELOCITY: f32 = 30.0;
+/// Cap on the frame delta, so hitches don't drop critters through the ground
+const MAX_CRITTER_DT: f32 = 0.05;
+/// Deepest drop a wandering critter walks off
+const MAX_DROP: i32 = 2;
+
+const CRITTER_BODY_COLOR: Color = Color::srgb(0.78, 0.62, 0.45);
+const CRITTER_HEAD_COLOR: Color = Color::srgb(0.62, 0.47, 0.33);
+
+/// A small passive animal that wanders around on the surface
+#[derive(Component, Debug)]
+pub struct Critter {
+    /// Bottom center of the collider
+    pub feet: Vec3,
+    pub velocity: Vec3,
+    pub on_ground: bool,
+    /// Facing direction, radians around the vertical axis (0 faces -Z)
+    heading: f32,
+    walking: bool,
+    /// Seconds until the critter picks a new heading and decides whether to walk
+    decision_timer: f32,
+}
+
+impl Critter {
+    fn new(feet: Vec3, heading: f32) -> Self {
+        Self {
+            feet,
+            velocity: Vec3::ZERO,
+            on_ground: false,
+            heading,
+            walking: false,
+            decision_timer: 0.0,
+        }
+    }
+
+    /// Horizontal unit vector the critter faces
+    fn forward(&self) -> Vec3 {
+        Quat::from_rotation_y(self.heading) * Vec3::NEG_Z
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::from_bottom(self.feet, CRITTER_HALF_WIDTH, CRITTER_HEIGHT)
+    }
+}
+
+/// How many critters a biome supports, relative to plains
+pub fn biome_spawn_density(biome: Biome) -> f32 {
+    match biome {
+        Biome::Plains => 1.0,
+        Biome::Forest | Biome::BirchForest => 0.6,
+        Biome::FloatingIslands => 0.5,
+        Biome::Taiga => 0.4,
+        Biome::Mountains => 0.3,
+        Biome::Swamp => 0.2,
+        Biome::Desert | Biome::Snowy | Biome::Ocean | Biome::Beach | Biome::River => 0.0,
+    }
+}
+
+/// Spawn rate multiplier for the time of day: 1.0 in daylight, NIGHT_SPAWN_FACTOR at night
+pub fn time_spawn_factor(clock: &GameClock) -> f32 {
+    NIGHT_SPAWN_FACTOR + (1.0 - NIGHT_SPAWN_FACTOR) * clock.daylight()
+}
+
+/// Shared meshes and materials for critter entities
+#[derive(Resource)]
+struct CritterAssets {
+    body: Handle<Mesh>,
+    head: Handle<Mesh>,
+    body_material: Handle<StandardMaterial>,
+    head_material: Handle<StandardMaterial>,
+}
+
+/// Spawn timer and the random state used for spawning and wandering
+#[derive(Resource)]
+struct MobState {
+    spawn_timer: Timer,
+    rng: XorShift32,
+}
+
+impl Default for MobState {
+    fn default() -> Self {
+        Self {
+            spawn_timer: Timer::from_seconds(SPAWN_INTERVAL, TimerMode::Repeating),
+            rng: XorShift32::new(0x6c07_8965),
+        }
+    }
+}
+
+impl MobState {
+    fn random(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        self.rng.range(min, max)
+    }
+}
+
+pub struct MobsPlugin;
+
+impl Plugin for MobsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MobState>()
+            .add_systems(Startup, setup_critter_assets)
+            .add_systems(
+                Update,
+                (
+                    despawn_far_critters,
+                    spawn_critters,
+                    wander_critters,
+                    move_critters,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup_critter_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut material = |color: Color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            perceptual_roughness: 0.9,
+            ..default()
+        })
+    };
+    commands.insert_resource(CritterAssets {
+        body: meshes.add(Cuboid::new(0.5, 0.4, 0.7)),
+        head: meshes.add(Cuboid::from_length(0.3)),
+        body_material: material(CRITTER_BODY_COLOR),
+        head_material: material(CRITTER_HEAD_COLOR),
+    });
+}
+
+/// Feet of the player, if there is one
+fn player_feet(
+    camera_q: &Query<(&GlobalTransform, &PlayerStance), With<PlayerCamera>>,
+) -> Option<Vec3> {
+    let (camera, stance) = camera_q.single().ok()?;
+    Some(stance.feet(camera.translation()))
+}
+
+fn despawn_far_critters(
+    mut commands: Commands,
+    camera_q: Query<(&GlobalTransform, &PlayerStance), With<PlayerCamera>>,
+    critters: Query<(Entity, &Critter)>,
+) {
+    let Some(feet) = player_feet(&camera_q) else {
+        return;
+    };
+    for (entity, critter) in &critters {
+        if critter.feet.xz().distance(feet.xz()) > DESPAWN_DISTANCE {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// World state that decides where critters can spawn and how likely they are to
+#[derive(SystemParam)]
+struct SpawnConditions<'w, 's> {
+    world: Res<'w, VoxelWorld>,
+    chunks: Chunks<'w, 's>,
+    terrain: Res<'w, SharedTerrain>,
+    clock: Res<'w, GameClock>,
+}
+
+impl SpawnConditions<'_, '_> {
+    /// Top block of a loaded column if it's grass with room for a critter above it
+    fn ground(&self, column: IVec2) -> Option<IVec3> {
+        let y = self.world.heightmap.height(column.x, column.y)?;
+        let ground = IVec3::new(column.x, y, column.y);
+        let above = ground + IVec3::Y;
+        let has_room = !self.chunks.get_voxel(above).is_solid()
+            && self
+                .chunks
+                .contains(&ChunkPos::from_world_pos(above.x, above.y, above.z));
+        (self.chunks.get_voxel(ground) == VoxelKind::Grass && has_room).then_some(ground)
+    }
+
+    /// Probability that a spawn attempt on the column succeeds
+    fn chance(&self, column: IVec2) -> f32 {
+        let biome = self.terrain.generator().get_biome(column.x, column.y);
+        biome_spawn_density(biome) * time_spawn_factor(&self.clock)
+    }
+}
+
+/// Every SPAWN_INTERVAL, tries to put a critter on a random grass column around the player
+fn spawn_critters(
+    mut commands: Commands,
+    time: Res<Time>,
+    conditions: SpawnConditions,
+    assets: Res<CritterAssets>,
+    mut state: ResMut<MobState>,
+    camera_q: Query<(&GlobalTransform, &PlayerStance), With<PlayerCamera>>,
+    critters: Query<(), With<Critter>>,
+) {
+    if !state.spawn_timer.tick(time.delta()).just_finished()
+        || critters.iter().count() >= MAX_CRITTERS
+    {
+        return;
+    }
+    let Some(feet) = player_feet(&camera_q) else {
+        return;
+    };
+
+    let angle = state.range(0.0, TAU);
+    let distance = state.range(SPAWN_MIN_DISTANCE, SPAWN_MAX_DISTANCE);
+    let column = (feet.xz() + Vec2::from_angle(angle) * distance)
+        .floor()
+        .as_ivec2();
+    let Some(ground) = conditions.ground(column) else {
+        return;
+    };
+    if state.random() >= conditions.chance(column) {
+        return;
+    }
+
+    let feet = ground.as_vec3() + Vec3::new(0.5, 1.0, 0.5);
+    let critter = Critter::new(feet, state.range(0.0, TAU));
+    commands
+        .spawn((
+            Transform::from_translation(critter.feet),
+            Visibility::default(),
+            critter,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Mesh3d(assets.body.clone()),
+                MeshMaterial3d(assets.body_material.clone()),
+                Transform::from_xyz(0.0, 0.3, 0.0),
+            ));
+            parent.spawn((
+                Mesh3d(assets.head.clone()),
+                MeshMaterial3d(assets.head_material.clone()),
+                Transform::from_xyz(0.0, 0.45, -0.45),
+            ));
+        });
+}
+
+/// Picks a new heading every few seconds; at night critters mostly stand still
+fn wander_critters(
+    time: Res<Time>,
+    chunks: Chunks,
+    clock: Res<GameClock>,
+    mut state: ResMut<MobState>,
+    mut critters: Query<&mut Critter>,
+) {
+    let dt = time.delta_secs();
+    let walk_chance = 0.2 + 0.5 * clock.daylight();
+
+    for mut critter in &mut critters {
+        critter.decision_timer -= dt;
+        if critter.decision_timer <= 0.0 {
+            critter.decision_timer = state.range(2.0, 6.0);
+            critter.walking = state.random() < walk_chance;
+            critter.heading = state.range(0.0, TAU);
+        }
+
+        // Turn around instead of walking off a cliff
+        if critter.walking && critter.on_ground && is_drop_ahead(&chunks, &critter) {
+            critter.heading = (critter.heading + PI).rem_euclid(TAU);
+            // Stand still on a ledge with drops on both sides
+            critter.walking = !is_drop_ahead(&chunks, &critter);
+        }
+
+        let speed = if critter.walking {
+            CRITTER_WALK_SPEED
+        } else {
+            0.0
+        };
+        let horizontal = critter.forward() * speed;
+        critter.velocity.x = horizontal.x;
+        critter.velocity.z = horizontal.z;
+    }
+}
+
+/// Whether the ground half a block ahead falls away by more than MAX_DROP blocks
+fn is_drop_ahead(world: &impl ChunkLookup, critter: &Critter) -> bool {
+    let ahead = (critter.feet + critter.forward() * 0.6).floor().as_ivec3();
+    (1..=MAX_DROP + 1).all(|depth| !world.get_voxel(ahead - IVec3::Y * depth).is_solid())
+}
+
+/// Applies gravity and moves critters through the voxel world, hopping onto ledges
+fn move_critters(
+    time: Res<Time>,
+    chunks: Chunks,
+    mut critters: Query<(&mut Critter, &mut Transform)>,
+) {
+    let dt = time.delta_secs().min(MAX_CRITTER_DT);
+
+    for (mut critter, mut transform) in &mut critters {
+        if !chunks.contains(&ChunkPos::containing(critter.feet)) {
+            continue;
+        }
+
+        critter.velocity.y =
+            (critter.velocity.y - CRITTER_GRAVITY * dt).max(-CRITTER_TERMINAL_VELOCITY);
+        let sweep = sweep_aabb(&chunks, critter.bounds(), critter.velocity * dt);
+        critter.feet += sweep.offset;
+
+        critter.on_ground = sweep.blocked.y && critter.velocity.y < 0.0;
+        if sweep.blocked.y {
+            critter.velocity.y = 0.0;
+        }
+
+        // Hop when walking into a one-block ledge with room above it
+        let hop_room = critter.bounds().translated(Vec3::Y);
+        if (sweep.blocked.x || sweep.blocked.z)
+            && critter.on_ground
+            && !aabb_collides(&chunks, hop_room)
+        {
+            critter.velocity.y = CRITTER_HOP_SPEED;
+            critter.on_ground = false;
+        }
+
+        transform.translation = critter.feet;
+        transform.rotation = Quat::from_rotation_y(critter.heading);
+    }
+}
@@ -0,0 +1,246 @@
+//! Sync client
+//!
+//! [`connect`] runs before the app starts so the world is generated from the server's
+//! seed. Afterwards two fixed-step systems exchange diffs with the server: incoming
+//! packets become block commands during [`SimulationSet::ExternalActions`], and after
+//! the commit system runs the changes it made are sent back, except those that came
+//! from the server in the first place.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use super::Connection;
+use crate::voxel::domains::command::{commit_system, CommandQueue};
+use crate::voxel::domains::thermal::ThermalApi;
+use crate::voxel::loading::UnloadedChunks;
+use crate::voxel::sync::{apply_changes, FrameReader, Packet, PROTOCOL_VERSION};
+use crate::voxel::{
+    BlockChange, ChunkData, ChunkPos, Chunks, DomainCommand, SimulationSet, VoxelKind,
+};
+
+/// How long to wait for the server's welcome packet
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most changes kept waiting for their chunks to load; diffs beyond this are dropped
+const MAX_PENDING_CHANGES: usize = 64 * 1024;
+
+/// Connection to the sync server; present only when playing multiplayer
+#[derive(Resource)]
+pub struct NetClient {
+    connection: Connection,
+    /// Voxels set from server diffs this tick, so they aren't sent back
+    echoes: HashSet<(ChunkPos, usize)>,
+    /// Diffs for chunks that aren't loaded or cached yet, applied when they load
+    pending: HashMap<ChunkPos, Vec<BlockChange>>,
+    /// Total number of changes in `pending`
+    pending_changes: usize,
+}
+
+/// Connects to `addr` and waits for the welcome packet. Returns the client and the
+/// seed of the server's world.
+pub fn connect(addr: &str) -> io::Result<(NetClient, u32)> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+    let mut reader = FrameReader::default();
+    let mut buffer = [0; 1024];
+    let seed = loop {
+        if let Some(packet) = reader.next_packet() {
+            match packet.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))? {
+                Packet::Welcome { version, seed } if version == PROTOCOL_VERSION => break seed,
+                Packet::Welcome { version, .. } => {
+                    return Err(io::Error::other(format!(
+                        "server speaks protocol {version}, this client {PROTOCOL_VERSION}"
+                    )));
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "server did not start with a welcome packet",
+                    ));
+                }
+            }
+        }
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        reader.extend(&buffer[..read]);
+    };
+    stream.set_read_timeout(None)?;
+
+    let client = NetClient {
+        connection: Connection::new(stream, reader)?,
+        echoes: HashSet::new(),
+        pending: HashMap::new(),
+        pending_changes: 0,
+    };
+    Ok((client, seed))
+}
+
+pub struct NetClientPlugin;
+
+impl Plugin for NetClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                receive_remote_changes.in_set(SimulationSet::ExternalActions),
+                send_local_changes
+                    .in_set(SimulationSet::Commit)
+                    .after(commit_system),
+            )
+                .run_if(resource_exists::<NetClient>),
+        );
+    }
+}
+
+/// Applies snapshots and diffs from the server
+///
+/// Loaded chunks are changed through the command queue so the simulation sees the new
+/// blocks; chunks kept in memory after unloading are patched directly; diffs for chunks
+/// that don't exist locally yet wait until the chunk loads, up to [`MAX_PENDING_CHANGES`]
+/// changes in total.
+fn receive_remote_changes(
+    mut commands: Commands,
+    mut client: ResMut<NetClient>,
+    chunks: Chunks,
+    mut unloaded: ResMut<UnloadedChunks>,
+    mut queues: Query<&mut CommandQueue>,
+) {
+    let Some(mut queue) = queues.iter_mut().next() else {
+        return;
+    };
+    let client = &mut *client;
+
+    let packets = match client.connection.receive() {
+        Ok(packets) => packets,
+        Err(err) => {
+            warn!("Lost connection to server: {err}");
+            commands.remove_resource::<NetClient>();
+            return;
+        }
+    };
+
+    for packet in packets {
+        match packet {
+            Packet::ChunkSnapshot { pos, chunk } => {
+                if let Some(local) = chunks.get(&pos) {
+                    for idx in 0..ChunkData::VOXEL_COUNT {
+                        let voxel = chunk.voxels.get(idx);
+                        let variant = chunk.variant.get(idx);
+                        // Setting a block resets its variant, which is then compared to zero
+                        let mut local_variant = local.variant.get(idx);
+                        if local.voxels.get(idx) != voxel {
+                            set_remote_block(&mut queue, &mut client.echoes, pos, idx, voxel);
+                            local_variant = 0;
+                        }
+                        if local_variant != variant {
+                            queue.push(pos, DomainCommand::SetVariant { idx, variant });
+                        }
+                    }
+                } else {
+                    let mut chunk = *chunk;
+                    chunk.is_modified = true;
+                    ThermalApi::register_heat_sources(&mut chunk);
+                    unloaded.chunks.insert(pos, chunk);
+                }
+            }
+            Packet::ChunkDiff { pos, changes } => {
+                if chunks.contains(&pos) {
+                    for change in &changes {
+                        set_remote_change(&mut queue, &mut client.echoes, pos, change);
+                    }
+                } else if let Some(chunk) = unloaded.chunks.get_mut(&pos) {
+                    apply_changes(chunk, &changes);
+                } else if client.pending_changes + changes.len() > MAX_PENDING_CHANGES {
+                    warn!("Dropping server diff for unloaded chunk {pos:?}: too many waiting");
+                } else {
+                    client.pending_changes += changes.len();
+                    client.pending.entry(pos).or_default().extend(changes);
+                }
+            }
+            Packet::Welcome { .. } => warn!("Ignoring repeated welcome packet from server"),
+        }
+    }
+
+    // Diffs that arrived before their chunk was generated
+    let loaded: Vec<ChunkPos> = client
+        .pending
+        .keys()
+        .filter(|pos| chunks.contains(pos))
+        .copied()
+        .collect();
+    for pos in loaded {
+        let changes = client.pending.remove(&pos).unwrap_or_default();
+        client.pending_changes -= changes.len();
+        for change in changes {
+            set_remote_change(&mut queue, &mut client.echoes, pos, &change);
+        }
+    }
+}
+
+/// Queues the block changes of a server diff; other change kinds are ignored
+fn set_remote_change(
+    queue: &mut CommandQueue,
+    echoes: &mut HashSet<(ChunkPos, usize)>,
+    pos: ChunkPos,
+    change: &BlockChange,
+) {
+    match *change {
+        BlockChange::SetVoxel { idx, new, .. } => set_remote_block(queue, echoes, pos, idx, new),
+        BlockChange::FillRun { new, .. } => {
+            for idx in change.indices() {
+                set_remote_block(queue, echoes, pos, idx, new);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn set_remote_block(
+    queue: &mut CommandQueue,
+    echoes: &mut HashSet<(ChunkPos, usize)>,
+    pos: ChunkPos,
+    idx: usize,
+    new_voxel: VoxelKind,
+) {
+    queue.push(pos, DomainCommand::SetBlock { idx, new_voxel });
+    echoes.insert((pos, idx));
+}
+
+/// Sends the voxel changes committed this tick, one diff packet per chunk
+///
+/// Only block types are synced: temperature, flags and fluid levels follow from the
+/// blocks through each side's own simulation.
+fn send_local_changes(mut commands: Commands, mut client: ResMut<NetClient>, chunks: Chunks) {
+    let client = &mut *client;
+
+    for (pos, chunk) in chunks.iter() {
+        let changes: Vec<BlockChange> = chunk
+            .changes
+            .iter()
+            .filter(|change| match change {
+                BlockChange::SetVoxel { idx, old, new } => {
+                    old != new && !client.echoes.contains(&(pos, *idx))
+                }
+                BlockChange::FillRun { .. } => change
+                    .indices()
+                    .any(|idx| !client.echoes.contains(&(pos, idx))),
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        if !changes.is_empty() {
+            client.connection.send(&Packet::ChunkDiff { pos, changes });
+        }
+    }
+    client.echoes.clear();
+
+    if let Err(err) = client.connection.flush() {
+        warn!("Lost connection to server: {err}");
+        commands.remove_resource::<NetClient>();
+    }
+}
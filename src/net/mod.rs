@@ -0,0 +1,96 @@
+//! Multiplayer block sync
+//!
+//! A headless server (`--server <port>`) keeps every chunk that has been edited and
+//! relays edits between clients; it doesn't render or simulate anything. A client
+//! (`--connect <host:port>`) takes the world seed from the server, so both sides
+//! generate the same terrain and only edits have to travel:
+//!
+//! 1. On connect the server sends a welcome packet with the seed, then a snapshot of
+//!    every chunk it has seen edited
+//! 2. Each simulation tick the client sends the voxel changes it committed, one diff
+//!    packet per chunk
+//! 3. The server applies the diffs to its copy and forwards them to every other client,
+//!    which applies them through its command queue
+//!
+//! Packets and their encoding are defined in [`crate::voxel::sync`].
+
+pub mod client;
+pub mod server;
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+use crate::voxel::sync::{encode_frame, FrameReader, Packet};
+
+/// Most bytes a connection keeps queued for a peer that isn't reading them; past this
+/// [`Connection::flush`] fails and the peer is dropped
+const MAX_OUTGOING_BYTES: usize = 64 * 1024 * 1024;
+
+/// Buffered, non-blocking packet stream over TCP
+struct Connection {
+    stream: TcpStream,
+    reader: FrameReader,
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    /// Wraps a connected stream; bytes already read into `reader` are kept
+    fn new(stream: TcpStream, reader: FrameReader) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            reader,
+            outgoing: Vec::new(),
+        })
+    }
+
+    /// Queues a packet; it's written on the next [`Connection::flush`]
+    fn send(&mut self, packet: &Packet) {
+        self.outgoing.extend_from_slice(&encode_frame(packet));
+    }
+
+    /// Writes as much of the queued data as the socket accepts without blocking.
+    /// Fails when more than [`MAX_OUTGOING_BYTES`] are still queued afterwards.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if self.outgoing.len() > MAX_OUTGOING_BYTES {
+            return Err(io::Error::other(format!(
+                "{} bytes queued, peer is not reading",
+                self.outgoing.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reads everything that has arrived and returns the complete packets.
+    /// Fails when the peer disconnected or sent something unparseable.
+    fn receive(&mut self) -> io::Result<Vec<Packet>> {
+        let mut buffer = [0; 16 * 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => self.reader.extend(&buffer[..read]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut packets = Vec::new();
+        while let Some(packet) = self.reader.next_packet() {
+            packets.push(packet.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?);
+        }
+        Ok(packets)
+    }
+}
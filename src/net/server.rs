@@ -0,0 +1,148 @@
+//! Headless sync server
+//!
+//! Runs a plain loop at [`TICK_RATE`] without a bevy app: accept new clients, read
+//! their diffs, apply them to the edited-chunk store and forward them to everyone else.
+//! Chunks are generated from the seed the first time a diff touches them.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::Connection;
+use crate::voxel::pregen::{read_structure_templates, read_worldgen_config, ASSETS_DIR};
+use crate::voxel::sync::{apply_changes, FrameReader, Packet, PROTOCOL_VERSION};
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::{BlockChange, ChunkData, ChunkPos, WorldSeed};
+
+/// Server ticks per second
+const TICK_RATE: u32 = 20;
+
+struct RemoteClient {
+    addr: SocketAddr,
+    connection: Connection,
+}
+
+/// Serves the world for `seed` on `port` until the process is killed.
+/// Returns the process exit code.
+pub fn run_server(seed: u32, port: u16) -> i32 {
+    match serve(seed, port) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("[server] Failed: {err}");
+            1
+        }
+    }
+}
+
+fn serve(seed: u32, port: u16) -> io::Result<()> {
+    let assets_dir = Path::new(ASSETS_DIR);
+    let terrain = SharedTerrain::new(
+        WorldSeed::new(seed),
+        read_worldgen_config(assets_dir),
+        Arc::new(read_structure_templates(assets_dir)),
+    );
+    let generator = terrain.generator();
+
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    listener.set_nonblocking(true)?;
+    println!("[server] seed {seed}, listening on port {port}");
+
+    let tick = Duration::from_secs(1) / TICK_RATE;
+    let mut chunks: HashMap<ChunkPos, ChunkData> = HashMap::new();
+    let mut clients: Vec<RemoteClient> = Vec::new();
+
+    loop {
+        let start = Instant::now();
+
+        // New clients get the seed and everything edited so far
+        loop {
+            let (stream, addr) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            };
+            let mut connection = match Connection::new(stream, FrameReader::default()) {
+                Ok(connection) => connection,
+                Err(err) => {
+                    eprintln!("[server] Could not set up connection from {addr}: {err}");
+                    continue;
+                }
+            };
+            connection.send(&Packet::Welcome {
+                version: PROTOCOL_VERSION,
+                seed,
+            });
+            for (&pos, chunk) in &chunks {
+                connection.send(&Packet::ChunkSnapshot {
+                    pos,
+                    chunk: Box::new(chunk.clone()),
+                });
+            }
+            println!(
+                "[server] {addr} joined ({} edited chunks sent)",
+                chunks.len()
+            );
+            clients.push(RemoteClient { addr, connection });
+        }
+
+        // Diffs received this tick, with the index of the client that sent them
+        let mut diffs: Vec<(usize, ChunkPos, Vec<BlockChange>)> = Vec::new();
+        let mut disconnected = vec![false; clients.len()];
+        for (index, client) in clients.iter_mut().enumerate() {
+            let packets = match client.connection.receive() {
+                Ok(packets) => packets,
+                Err(err) => {
+                    println!("[server] {} left: {err}", client.addr);
+                    disconnected[index] = true;
+                    continue;
+                }
+            };
+            for packet in packets {
+                match packet {
+                    Packet::ChunkDiff { pos, changes } => {
+                        let chunk = chunks
+                            .entry(pos)
+                            .or_insert_with(|| generator.generate_chunk(pos));
+                        apply_changes(chunk, &changes);
+                        diffs.push((index, pos, changes));
+                    }
+                    Packet::Welcome { .. } | Packet::ChunkSnapshot { .. } => {
+                        eprintln!("[server] Ignoring unexpected packet from {}", client.addr);
+                    }
+                }
+            }
+        }
+
+        for (sender, pos, changes) in diffs {
+            let packet = Packet::ChunkDiff { pos, changes };
+            for (index, client) in clients.iter_mut().enumerate() {
+                if index != sender && !disconnected[index] {
+                    client.connection.send(&packet);
+                }
+            }
+        }
+
+        for (index, client) in clients.iter_mut().enumerate() {
+            if disconnected[index] {
+                continue;
+            }
+            if let Err(err) = client.connection.flush() {
+                println!("[server] {} left: {err}", client.addr);
+                disconnected[index] = true;
+            }
+        }
+        let mut index = 0;
+        clients.retain(|_| {
+            index += 1;
+            !disconnected[index - 1]
+        });
+
+        if let Some(remaining) = tick.checked_sub(start.elapsed()) {
+            thread::sleep(remaining);
+        }
+    }
+}
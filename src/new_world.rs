@@ -0,0 +1,517 @@
+//! New world screen
+//!
+//! Started without a seed on the command line or in `VOXWORLD_SEED` (and not connecting
+//! to a server), the game opens this screen before any chunk loads: type a world name
+//! and a seed or roll a random one, pick a generation preset and the terrain (normal,
+//! flat, or the superflat and block showcase test worlds), then create the world.
+//!
+//! The choices become the [`WorldSeed`] and [`WorldGenOptions`], and the world is saved
+//! under `saves/<name>` with its metadata (see [`ActiveWorld`]). Creating a world whose
+//! folder already has metadata reopens it with the saved seed and options instead.
+//!
+//! While the screen is up chunk loading is paused and the keyboard belongs to the text
+//! fields (see [`InputCapture`]).
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, CursorOptions};
+
+use crate::input::InputCapture;
+use crate::ui::{BUTTON_HOVER, BUTTON_NORMAL, MENU_BG, UI_FONT_PATH};
+use crate::voxel::persistence::{ActiveWorld, WorldMeta, WorldStorage};
+use crate::voxel::{ChunkLoadQueue, WorldGenOptions, WorldSeed};
+
+/// Name used when the name field is left empty
+const DEFAULT_WORLD_NAME: &str = "新世界";
+/// Longest world name or seed, in characters
+const MAX_FIELD_CHARS: usize = 32;
+const SCREEN_BG: Color = Color::srgb(0.05, 0.06, 0.09);
+const FIELD_BG: Color = Color::srgb(0.12, 0.13, 0.17);
+const FIELD_BORDER: Color = Color::srgb(0.35, 0.38, 0.45);
+const FIELD_FOCUSED: Color = Color::srgb(0.55, 0.8, 1.0);
+
+/// What has been entered on the new world screen, and whether it's still up
+#[derive(Resource, Debug, Default)]
+pub struct NewWorldScreen {
+    pub open: bool,
+    pub name: String,
+    /// Seed as typed: a number is used as is, other text is hashed and an empty field
+    /// rolls a random seed
+    pub seed: String,
+    pub options: WorldGenOptions,
+    focus: Field,
+    /// Set by the create button or Enter, handled by create_world
+    create: bool,
+}
+
+impl NewWorldScreen {
+    /// An open screen with the default name, `seed` and `options` filled in
+    pub fn opened(seed: u32, options: WorldGenOptions) -> Self {
+        Self {
+            open: true,
+            name: DEFAULT_WORLD_NAME.to_string(),
+            seed: seed.to_string(),
+            options,
+            ..default()
+        }
+    }
+
+    /// Metadata of the world the fields describe
+    fn world_meta(&self) -> WorldMeta {
+        let name = self.name.trim();
+        let seed = if self.seed.trim().is_empty() {
+            WorldSeed::random_seed()
+        } else {
+            WorldSeed::parse(&self.seed).seed
+        };
+        WorldMeta {
+            name: if name.is_empty() {
+                DEFAULT_WORLD_NAME
+            } else {
+                name
+            }
+            .to_string(),
+            seed,
+            options: self.options,
+        }
+    }
+
+    fn field(&self, field: Field) -> &String {
+        match field {
+            Field::Name => &self.name,
+            Field::Seed => &self.seed,
+        }
+    }
+
+    fn field_mut(&mut self, field: Field) -> &mut String {
+        match field {
+            Field::Name => &mut self.name,
+            Field::Seed => &mut self.seed,
+        }
+    }
+}
+
+/// Text field that receives typing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Field {
+    #[default]
+    Name,
+    Seed,
+}
+
+impl Field {
+    fn other(self) -> Self {
+        match self {
+            Field::Name => Field::Seed,
+            Field::Seed => Field::Name,
+        }
+    }
+}
+
+#[derive(Component)]
+struct NewWorldRoot;
+
+#[derive(Component, Clone, Copy)]
+enum NewWorldButton {
+    /// Clicking a text field gives it the keyboard
+    Focus(Field),
+    RandomSeed,
+    CyclePreset,
+    CycleShape,
+    Create,
+}
+
+/// Text showing a field or the current choice of an option
+#[derive(Component, Clone, Copy)]
+enum NewWorldText {
+    Field(Field),
+    Preset,
+    Shape,
+}
+
+/// Resources that decide which world is played
+#[derive(SystemParam)]
+struct WorldChoice<'w> {
+    seed: ResMut<'w, WorldSeed>,
+    options: ResMut<'w, WorldGenOptions>,
+    active: ResMut<'w, ActiveWorld>,
+}
+
+pub struct NewWorldPlugin;
+
+impl Plugin for NewWorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NewWorldScreen>()
+            .add_systems(Startup, setup_new_world_screen)
+            .add_systems(
+                Update,
+                (
+                    release_cursor,
+                    new_world_button_system,
+                    read_new_world_input,
+                    update_new_world_text,
+                    create_world,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup_new_world_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    screen: Res<NewWorldScreen>,
+    mut queue: ResMut<ChunkLoadQueue>,
+    mut capture: ResMut<InputCapture>,
+) {
+    if !screen.open {
+        return;
+    }
+    // Nothing loads until the seed and generation options are chosen
+    queue.paused = true;
+    capture.typing = true;
+
+    let font = asset_server.load(UI_FONT_PATH);
+    let label_font = TextFont {
+        font: font.clone(),
+        font_size: 15.0,
+        ..default()
+    };
+    let button_font = TextFont {
+        font: font.clone(),
+        font_size: 18.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: percent(100.0),
+                height: percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(SCREEN_BG),
+            GlobalZIndex(20),
+            NewWorldRoot,
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Node {
+                    width: px(420.0),
+                    padding: UiRect::all(px(18.0)),
+                    row_gap: px(10.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                BackgroundColor(MENU_BG),
+            ))
+            .with_children(|panel| {
+                panel.spawn((
+                    Text::new("新建世界"),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+
+                panel.spawn((Text::new("世界名称"), label_font.clone()));
+                spawn_text_field(panel, &label_font, Field::Name);
+
+                panel.spawn((Text::new("种子（留空则随机）"), label_font.clone()));
+                panel
+                    .spawn(Node {
+                        width: percent(100.0),
+                        column_gap: px(8.0),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        spawn_text_field(row, &label_font, Field::Seed);
+                        row.spawn((
+                            Button,
+                            NewWorldButton::RandomSeed,
+                            Node {
+                                width: px(72.0),
+                                border: UiRect::all(px(1.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON_NORMAL),
+                            BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                        ))
+                        .with_child((Text::new("随机"), label_font.clone()));
+                    });
+
+                spawn_option_row(
+                    panel,
+                    &label_font,
+                    "生成预设",
+                    NewWorldButton::CyclePreset,
+                    NewWorldText::Preset,
+                );
+                spawn_option_row(
+                    panel,
+                    &label_font,
+                    "地形",
+                    NewWorldButton::CycleShape,
+                    NewWorldText::Shape,
+                );
+
+                panel
+                    .spawn((
+                        Button,
+                        NewWorldButton::Create,
+                        Node {
+                            width: percent(100.0),
+                            height: px(44.0),
+                            margin: UiRect::top(px(8.0)),
+                            border: UiRect::all(px(1.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_NORMAL),
+                        BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                    ))
+                    .with_child((Text::new("创建世界"), button_font.clone()));
+
+                panel.spawn((
+                    Text::new("Tab 切换输入框，Enter 创建；已有同名世界时直接打开"),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 13.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(0.7, 0.74, 0.82)),
+                ));
+            });
+        });
+}
+
+fn spawn_text_field(parent: &mut ChildSpawnerCommands, font: &TextFont, field: Field) {
+    parent
+        .spawn((
+            Button,
+            NewWorldButton::Focus(field),
+            Node {
+                flex_grow: 1.0,
+                height: px(34.0),
+                padding: UiRect::horizontal(px(8.0)),
+                border: UiRect::all(px(1.0)),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(FIELD_BG),
+            BorderColor::all(FIELD_BORDER),
+        ))
+        .with_child((Text::new(""), font.clone(), NewWorldText::Field(field)));
+}
+
+/// Label on the left, a button cycling through the choices on the right
+fn spawn_option_row(
+    parent: &mut ChildSpawnerCommands,
+    font: &TextFont,
+    label: &str,
+    button: NewWorldButton,
+    value: NewWorldText,
+) {
+    parent
+        .spawn(Node {
+            width: percent(100.0),
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .with_children(|row| {
+            row.spawn((Text::new(label), font.clone()));
+            row.spawn((
+                Button,
+                button,
+                Node {
+                    width: px(140.0),
+                    height: px(30.0),
+                    border: UiRect::all(px(1.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(BUTTON_NORMAL),
+                BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+            ))
+            .with_child((Text::new(""), font.clone(), value));
+        });
+}
+
+/// Keeps the cursor free while the screen is up; the player camera grabs it at startup
+fn release_cursor(screen: Res<NewWorldScreen>, mut cursor_options: Single<&mut CursorOptions>) {
+    if screen.open && cursor_options.grab_mode != CursorGrabMode::None {
+        cursor_options.visible = true;
+        cursor_options.grab_mode = CursorGrabMode::None;
+    }
+}
+
+fn new_world_button_system(
+    mut interaction_q: Query<
+        (&Interaction, &NewWorldButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut screen: ResMut<NewWorldScreen>,
+) {
+    for (interaction, button, mut color) in &mut interaction_q {
+        if let NewWorldButton::Focus(field) = *button {
+            if *interaction == Interaction::Pressed {
+                screen.focus = field;
+            }
+            continue;
+        }
+        match *interaction {
+            Interaction::Pressed => {
+                *color = BUTTON_HOVER.into();
+                match *button {
+                    NewWorldButton::Focus(_) => {}
+                    NewWorldButton::RandomSeed => {
+                        screen.seed = WorldSeed::random_seed().to_string();
+                    }
+                    NewWorldButton::CyclePreset => {
+                        screen.options.preset = screen.options.preset.next();
+                    }
+                    NewWorldButton::CycleShape => {
+                        screen.options.shape = screen.options.shape.next();
+                    }
+                    NewWorldButton::Create => screen.create = true,
+                }
+            }
+            Interaction::Hovered => *color = BUTTON_HOVER.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+/// Types into the focused field; Tab moves to the other field and Enter creates the world
+fn read_new_world_input(
+    mut keyboard: MessageReader<KeyboardInput>,
+    mut screen: ResMut<NewWorldScreen>,
+) {
+    if !screen.open {
+        keyboard.clear();
+        return;
+    }
+    for event in keyboard.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        let field = screen.focus;
+        match event.key_code {
+            KeyCode::Tab => screen.focus = field.other(),
+            KeyCode::Enter | KeyCode::NumpadEnter => screen.create = true,
+            KeyCode::Backspace => {
+                screen.field_mut(field).pop();
+            }
+            _ => {
+                let Some(text) = &event.text else {
+                    continue;
+                };
+                let value = screen.field_mut(field);
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if value.chars().count() < MAX_FIELD_CHARS {
+                        value.push(c);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Shows the fields, with a caret in the focused one, and the chosen options
+fn update_new_world_text(
+    screen: Res<NewWorldScreen>,
+    mut text_q: Query<(&NewWorldText, &mut Text)>,
+    mut field_q: Query<(&NewWorldButton, &mut BorderColor)>,
+) {
+    if !screen.open || !screen.is_changed() {
+        return;
+    }
+    for (label, mut text) in &mut text_q {
+        text.0 = match *label {
+            NewWorldText::Field(field) if field == screen.focus => {
+                format!("{}_", screen.field(field))
+            }
+            NewWorldText::Field(field) => screen.field(field).clone(),
+            NewWorldText::Preset => screen.options.preset.label().to_string(),
+            NewWorldText::Shape => screen.options.shape.label().to_string(),
+        };
+    }
+    for (button, mut border) in &mut field_q {
+        if let NewWorldButton::Focus(field) = *button {
+            let color = if field == screen.focus {
+                FIELD_FOCUSED
+            } else {
+                FIELD_BORDER
+            };
+            *border = BorderColor::all(color);
+        }
+    }
+}
+
+/// Opens the world described on the screen, or the saved one with the same name, and
+/// hands the game back to the player
+fn create_world(
+    mut commands: Commands,
+    mut screen: ResMut<NewWorldScreen>,
+    mut world: WorldChoice,
+    mut queue: ResMut<ChunkLoadQueue>,
+    mut capture: ResMut<InputCapture>,
+    mut cursor_options: Single<&mut CursorOptions>,
+    root_q: Query<Entity, With<NewWorldRoot>>,
+) {
+    if !screen.create {
+        return;
+    }
+    screen.create = false;
+    screen.open = false;
+
+    let meta = screen.world_meta();
+    let storage = WorldStorage::for_world(&meta.name);
+    let meta = match storage.load_meta() {
+        Ok(Some(saved)) => {
+            info!("Opening saved world \"{}\"", saved.name);
+            saved
+        }
+        Ok(None) => {
+            if let Err(err) = storage.save_meta(&meta) {
+                warn!("Failed to save world info: {err}");
+            }
+            meta
+        }
+        Err(err) => {
+            warn!("Ignoring world info in {}: {err}", storage.root().display());
+            meta
+        }
+    };
+    info!(
+        "World \"{}\": seed {}, {:?}, saved in {}",
+        meta.name,
+        meta.seed,
+        meta.options,
+        storage.root().display()
+    );
+
+    *world.seed = WorldSeed::new(meta.seed);
+    *world.options = meta.options;
+    *world.active = ActiveWorld { meta, storage };
+
+    queue.paused = false;
+    capture.typing = false;
+    cursor_options.visible = false;
+    cursor_options.grab_mode = CursorGrabMode::Locked;
+    for entity in &root_q {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,556 @@
+//! Particle effects
+//!
+//! Particles are small camera-facing quads that drift, grow or shrink and disappear
+//! after a short lifetime:
+//!
+//! - Burning voxels give off smoke and occasional embers
+//! - Evaporating (boiling) voxels give off steam, and so does water touching a hot block
+//! - Broken blocks burst into debris in the block's color
+//! - Rain falls around the player while it's raining, only where the sky is open; in cold
+//!   regions it falls as slow, drifting snowflakes instead
+//!
+//! Voxels farther than [`ParticleSettings::max_distance`] from the player don't emit,
+//! and nothing new spawns while [`ParticleSettings::max_particles`] are alive.
+
+use bevy::ecs::system::SystemParam;
+use bevy::light::NotShadowCaster;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::player::PlayerCamera;
+use crate::raycast::BlockBroken;
+use crate::voxel::domains::thermal::api::{get_valid_neighbor_indices, idx_to_xyz};
+use crate::voxel::domains::weather::WeatherState;
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::{
+    ivec3_to_vec3, ChunkLookup, Chunks, VoxelFlags, VoxelKind, VoxelWorld, XorShift32, CHUNK_SIZE,
+};
+
+/// Smoke puffs per burning voxel per second
+const SMOKE_RATE: f32 = 3.0;
+/// Embers per burning voxel per second
+const EMBER_RATE: f32 = 1.5;
+/// Steam puffs per evaporating voxel per second
+const STEAM_RATE: f32 = 4.0;
+/// Steam puffs per second where water touches a hot block
+const HISS_RATE: f32 = 6.0;
+/// Raindrops per second around the player
+const RAIN_RATE: f32 = 240.0;
+/// Raindrops spawn within this horizontal distance of the player (blocks)
+const RAIN_RADIUS: f32 = 14.0;
+/// Raindrops spawn this far above the player (blocks)
+const RAIN_HEIGHT: f32 = 12.0;
+const RAIN_SPEED: f32 = 14.0;
+/// Snowflakes per second around the player; they fall slowly and live long, so fewer spawn
+const SNOW_RATE: f32 = 60.0;
+const SNOW_SPEED: f32 = 2.0;
+/// Largest sideways drift of a falling snowflake (blocks per second)
+const SNOW_DRIFT: f32 = 0.6;
+/// Debris pieces per broken block
+const DEBRIS_COUNT: usize = 10;
+const DEBRIS_GRAVITY: f32 = 18.0;
+/// Embers are drawn additively, so values above 1 make them glow through bloom
+const EMBER_COLOR: Color = Color::linear_rgb(4.0, 1.2, 0.2);
+
+/// Budget and culling limits, pushed from the game settings
+#[derive(Resource, Debug, Clone)]
+pub struct ParticleSettings {
+    /// Particles alive at once; emitters pause when it's reached
+    pub max_particles: usize,
+    /// Voxels farther than this from the camera don't emit (blocks)
+    pub max_distance: f32,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            max_particles: 800,
+            max_distance: 32.0,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+struct Particle {
+    velocity: Vec3,
+    /// Gravity (negative y) or buoyancy (positive y)
+    acceleration: Vec3,
+    /// Fraction of velocity lost per second
+    drag: f32,
+    /// Stops when entering a solid voxel instead of passing through
+    collides: bool,
+    age: f32,
+    lifetime: f32,
+    start_size: f32,
+    end_size: f32,
+}
+
+/// Shared quad mesh and materials
+#[derive(Resource)]
+struct ParticleAssets {
+    quad: Handle<Mesh>,
+    smoke: Handle<StandardMaterial>,
+    ember: Handle<StandardMaterial>,
+    steam: Handle<StandardMaterial>,
+    rain: Handle<StandardMaterial>,
+    snow: Handle<StandardMaterial>,
+    debris: HashMap<VoxelKind, Handle<StandardMaterial>>,
+}
+
+/// Live particle count and the random state used for emission
+#[derive(Resource)]
+struct ParticleState {
+    live: usize,
+    rng: XorShift32,
+}
+
+impl Default for ParticleState {
+    fn default() -> Self {
+        Self {
+            live: 0,
+            rng: XorShift32::new(0x9e37_79b9),
+        }
+    }
+}
+
+impl ParticleState {
+    fn random(&mut self) -> f32 {
+        self.rng.next_f32()
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        self.rng.range(min, max)
+    }
+
+    /// Whether an emitter firing `rate` times per second fires during `dt`
+    fn chance(&mut self, rate: f32, dt: f32) -> bool {
+        self.random() < rate * dt
+    }
+
+    /// Counts a new particle against the budget, returning false when it's exhausted
+    fn reserve(&mut self, settings: &ParticleSettings) -> bool {
+        if self.live >= settings.max_particles {
+            return false;
+        }
+        self.live += 1;
+        true
+    }
+}
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParticleSettings>()
+            .init_resource::<ParticleState>()
+            .add_systems(Startup, setup_particle_assets)
+            .add_systems(
+                Update,
+                (
+                    emit_voxel_particles,
+                    emit_rain,
+                    emit_debris,
+                    update_particles,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn setup_particle_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut billboard = |color: Color, alpha_mode: AlphaMode| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            alpha_mode,
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        })
+    };
+    commands.insert_resource(ParticleAssets {
+        quad: meshes.add(Rectangle::from_length(1.0)),
+        smoke: billboard(Color::srgba(0.22, 0.21, 0.2, 0.45), AlphaMode::Blend),
+        ember: billboard(EMBER_COLOR, AlphaMode::Add),
+        steam: billboard(Color::srgba(0.92, 0.94, 0.97, 0.3), AlphaMode::Blend),
+        rain: billboard(Color::srgba(0.7, 0.78, 0.9, 0.5), AlphaMode::Blend),
+        snow: billboard(Color::srgba(0.97, 0.98, 1.0, 0.9), AlphaMode::Blend),
+        debris: HashMap::new(),
+    });
+}
+
+fn spawn_particle(
+    commands: &mut Commands,
+    assets: &ParticleAssets,
+    material: Handle<StandardMaterial>,
+    position: Vec3,
+    particle: Particle,
+) {
+    commands.spawn((
+        Mesh3d(assets.quad.clone()),
+        MeshMaterial3d(material),
+        Transform::from_translation(position).with_scale(Vec3::splat(particle.start_size)),
+        NotShadowCaster,
+        particle,
+    ));
+}
+
+/// A rising steam puff
+fn steam_particle(state: &mut ParticleState) -> Particle {
+    Particle {
+        velocity: Vec3::new(
+            state.range(-0.15, 0.15),
+            state.range(0.8, 1.2),
+            state.range(-0.15, 0.15),
+        ),
+        acceleration: Vec3::Y * 0.5,
+        drag: 0.6,
+        collides: false,
+        age: 0.0,
+        lifetime: state.range(1.2, 1.8),
+        start_size: 0.2,
+        end_size: 0.7,
+    }
+}
+
+/// Smoke and embers from burning voxels, steam from evaporating ones and from water
+/// touching hot blocks
+fn emit_voxel_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    chunks: Chunks,
+    settings: Res<ParticleSettings>,
+    assets: Option<Res<ParticleAssets>>,
+    mut state: ResMut<ParticleState>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+) {
+    let (Some(assets), Ok(camera)) = (assets, camera_q.single()) else {
+        return;
+    };
+    let eye = camera.translation();
+    let dt = time.delta_secs();
+    // Chunks whose center is farther than this can't contain an emitter in range
+    let chunk_reach = settings.max_distance + CHUNK_SIZE as f32 * 0.87;
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        if chunk.active_burning.is_empty()
+            && chunk.active_melting.is_empty()
+            && chunk.active_thermal.is_empty()
+        {
+            continue;
+        }
+        let origin = chunk_pos.world_origin();
+        let center = ivec3_to_vec3(origin) + Vec3::splat(CHUNK_SIZE as f32 * 0.5);
+        if center.distance(eye) > chunk_reach {
+            continue;
+        }
+
+        let voxel_top = |idx: usize| {
+            let (x, y, z) = idx_to_xyz(idx);
+            ivec3_to_vec3(origin + IVec3::new(x, y, z)) + Vec3::new(0.5, 1.0, 0.5)
+        };
+
+        for &idx in &chunk.active_burning {
+            let top = voxel_top(idx);
+            if top.distance(eye) > settings.max_distance {
+                continue;
+            }
+            if state.chance(SMOKE_RATE, dt) && state.reserve(&settings) {
+                let offset = Vec3::new(state.range(-0.4, 0.4), 0.0, state.range(-0.4, 0.4));
+                let particle = Particle {
+                    velocity: Vec3::new(
+                        state.range(-0.2, 0.2),
+                        state.range(1.0, 1.6),
+                        state.range(-0.2, 0.2),
+                    ),
+                    acceleration: Vec3::Y * 0.3,
+                    drag: 0.5,
+                    collides: false,
+                    age: 0.0,
+                    lifetime: state.range(2.0, 3.0),
+                    start_size: 0.25,
+                    end_size: 0.9,
+                };
+                spawn_particle(
+                    &mut commands,
+                    &assets,
+                    assets.smoke.clone(),
+                    top + offset,
+                    particle,
+                );
+            }
+            if state.chance(EMBER_RATE, dt) && state.reserve(&settings) {
+                let offset = Vec3::new(state.range(-0.4, 0.4), 0.0, state.range(-0.4, 0.4));
+                let particle = Particle {
+                    velocity: Vec3::new(
+                        state.range(-0.6, 0.6),
+                        state.range(1.5, 3.0),
+                        state.range(-0.6, 0.6),
+                    ),
+                    acceleration: Vec3::NEG_Y * 1.0,
+                    drag: 0.8,
+                    collides: false,
+                    age: 0.0,
+                    lifetime: state.range(0.6, 1.2),
+                    start_size: 0.08,
+                    end_size: 0.02,
+                };
+                spawn_particle(
+                    &mut commands,
+                    &assets,
+                    assets.ember.clone(),
+                    top + offset,
+                    particle,
+                );
+            }
+        }
+
+        for &idx in &chunk.active_melting {
+            if !chunk.flags.get(idx).contains(VoxelFlags::EVAPORATING) {
+                continue;
+            }
+            let top = voxel_top(idx);
+            if top.distance(eye) > settings.max_distance
+                || !state.chance(STEAM_RATE, dt)
+                || !state.reserve(&settings)
+            {
+                continue;
+            }
+            let offset = Vec3::new(state.range(-0.45, 0.45), 0.0, state.range(-0.45, 0.45));
+            let particle = steam_particle(&mut state);
+            spawn_particle(
+                &mut commands,
+                &assets,
+                assets.steam.clone(),
+                top + offset,
+                particle,
+            );
+        }
+
+        // Water hisses where it touches a hot block, before it starts boiling
+        for &idx in &chunk.active_thermal {
+            let kind = chunk.voxels.get(idx);
+            if kind == VoxelKind::Water
+                || kind == VoxelKind::Lava
+                || !chunk.flags.get(idx).contains(VoxelFlags::HOT)
+            {
+                continue;
+            }
+            let Some(water_idx) = get_valid_neighbor_indices(idx)
+                .into_iter()
+                .find(|&neighbor_idx| chunk.voxels.get(neighbor_idx) == VoxelKind::Water)
+            else {
+                continue;
+            };
+            // Midway between the two voxels, on the shared face
+            let contact = (voxel_top(idx) + voxel_top(water_idx)) * 0.5 - Vec3::Y * 0.5;
+            if contact.distance(eye) > settings.max_distance
+                || !state.chance(HISS_RATE, dt)
+                || !state.reserve(&settings)
+            {
+                continue;
+            }
+            let offset = Vec3::new(state.range(-0.3, 0.3), 0.0, state.range(-0.3, 0.3));
+            let particle = steam_particle(&mut state);
+            spawn_particle(
+                &mut commands,
+                &assets,
+                assets.steam.clone(),
+                contact + offset,
+                particle,
+            );
+        }
+    }
+}
+
+/// What falls from the sky around the player: the weather, the terrain that decides
+/// whether it's rain or snow, and the surface it lands on
+#[derive(SystemParam)]
+struct Precipitation<'w> {
+    weather: Res<'w, WeatherState>,
+    terrain: Res<'w, SharedTerrain>,
+    world: Res<'w, VoxelWorld>,
+}
+
+/// Raindrops falling around the player while it rains, snowflakes in cold regions
+///
+/// Drops only spawn over columns whose surface is below the spawn height, and live
+/// just long enough to reach the surface
+fn emit_rain(
+    mut commands: Commands,
+    time: Res<Time>,
+    sky: Precipitation,
+    settings: Res<ParticleSettings>,
+    assets: Option<Res<ParticleAssets>>,
+    mut state: ResMut<ParticleState>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+) {
+    let (Some(assets), Ok(camera)) = (assets, camera_q.single()) else {
+        return;
+    };
+    if !sky.weather.is_raining() {
+        return;
+    }
+    let eye = camera.translation();
+    let spawn_y = eye.y + RAIN_HEIGHT;
+    let snowing = sky.terrain.generator().is_cold(
+        eye.x.floor() as i32,
+        eye.y.floor() as i32,
+        eye.z.floor() as i32,
+    );
+    let (rate, speed, size, material) = if snowing {
+        (SNOW_RATE, SNOW_SPEED, 0.1, &assets.snow)
+    } else {
+        (RAIN_RATE, RAIN_SPEED, 0.07, &assets.rain)
+    };
+
+    let mut budget = rate * time.delta_secs();
+    while budget > 0.0 {
+        // The fractional part of the budget spawns a drop with that probability
+        if budget < 1.0 && state.random() >= budget {
+            break;
+        }
+        budget -= 1.0;
+
+        let position = Vec3::new(
+            eye.x + state.range(-RAIN_RADIUS, RAIN_RADIUS),
+            spawn_y,
+            eye.z + state.range(-RAIN_RADIUS, RAIN_RADIUS),
+        );
+        let column = position.floor().as_ivec3();
+        let Some(surface) = sky.world.heightmap.height(column.x, column.z) else {
+            continue;
+        };
+        let fall = spawn_y - (surface + 1) as f32;
+        if fall <= 0.0 || !state.reserve(&settings) {
+            continue;
+        }
+        let drift = if snowing {
+            Vec3::new(
+                state.range(-SNOW_DRIFT, SNOW_DRIFT),
+                0.0,
+                state.range(-SNOW_DRIFT, SNOW_DRIFT),
+            )
+        } else {
+            Vec3::ZERO
+        };
+        let particle = Particle {
+            velocity: Vec3::NEG_Y * speed + drift,
+            acceleration: Vec3::ZERO,
+            drag: 0.0,
+            collides: false,
+            age: 0.0,
+            lifetime: fall / speed,
+            start_size: size,
+            end_size: size,
+        };
+        spawn_particle(&mut commands, &assets, material.clone(), position, particle);
+    }
+}
+
+/// Bursts a broken block into pieces of its color
+fn emit_debris(
+    mut commands: Commands,
+    mut broken: MessageReader<BlockBroken>,
+    settings: Res<ParticleSettings>,
+    assets: Option<ResMut<ParticleAssets>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut state: ResMut<ParticleState>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+) {
+    let (Some(mut assets), Ok(camera)) = (assets, camera_q.single()) else {
+        return;
+    };
+    let eye = camera.translation();
+
+    for event in broken.read() {
+        let center = ivec3_to_vec3(event.pos) + Vec3::splat(0.5);
+        if center.distance(eye) > settings.max_distance {
+            continue;
+        }
+
+        let material = assets
+            .debris
+            .entry(event.kind)
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: event.kind.def().color,
+                    unlit: true,
+                    cull_mode: None,
+                    ..default()
+                })
+            })
+            .clone();
+
+        for _ in 0..DEBRIS_COUNT {
+            if !state.reserve(&settings) {
+                return;
+            }
+            let offset = Vec3::new(
+                state.range(-0.35, 0.35),
+                state.range(-0.35, 0.35),
+                state.range(-0.35, 0.35),
+            );
+            let particle = Particle {
+                velocity: offset * 4.0 + Vec3::Y * state.range(2.0, 4.0),
+                acceleration: Vec3::NEG_Y * DEBRIS_GRAVITY,
+                drag: 0.2,
+                collides: true,
+                age: 0.0,
+                lifetime: state.range(0.6, 1.0),
+                start_size: state.range(0.08, 0.14),
+                end_size: 0.03,
+            };
+            spawn_particle(
+                &mut commands,
+                &assets,
+                material.clone(),
+                center + offset,
+                particle,
+            );
+        }
+    }
+}
+
+/// Moves, resizes and turns particles toward the camera, despawning expired ones
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    chunks: Chunks,
+    mut state: ResMut<ParticleState>,
+    camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform)>,
+) {
+    let dt = time.delta_secs();
+    let facing = camera_q
+        .single()
+        .map(|camera| camera.rotation())
+        .unwrap_or_default();
+
+    for (entity, mut particle, mut transform) in &mut particles {
+        particle.age += dt;
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn();
+            state.live = state.live.saturating_sub(1);
+            continue;
+        }
+
+        let acceleration = particle.acceleration;
+        let drag = (1.0 - particle.drag * dt).max(0.0);
+        particle.velocity = (particle.velocity + acceleration * dt) * drag;
+
+        let next = transform.translation + particle.velocity * dt;
+        if particle.collides && chunks.get_voxel(next.floor().as_ivec3()).is_solid() {
+            particle.velocity = Vec3::ZERO;
+        } else {
+            transform.translation = next;
+        }
+
+        let t = particle.age / particle.lifetime;
+        transform.scale = Vec3::splat(particle.start_size.lerp(particle.end_size, t));
+        transform.rotation = facing;
+    }
+}
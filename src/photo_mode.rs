@@ -0,0 +1,826 @@
+//! Photo mode
+//!
+//! Opened from the pause menu. The game freezes: virtual time is paused, so the block
+//! simulation, the sun, weather, clouds and mobs all stop where they are. The player
+//! camera detaches into a free camera that flies in every direction with the movement
+//! keys, looks around with the mouse and rolls with [`Action::PhotoRollLeft`] and
+//! [`Action::PhotoRollRight`]. The HUD is hidden; [`Action::PhotoPanel`] opens a small
+//! panel to adjust the field of view, exposure, roll and depth of field.
+//!
+//! [`Action::Screenshot`] takes a photo at [`CAPTURE_SCALE`] times the window
+//! resolution: a temporary camera with the player camera's settings renders to an
+//! image, which is saved to [`CAPTURES_DIR`] like regular screenshots. The pause key
+//! leaves photo mode and puts the camera, exposure, HUD and time back as they were.
+
+use bevy::camera::visibility::VisibilitySystems;
+use bevy::camera::{Exposure, RenderTarget};
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::input::mouse::AccumulatedMouseMotion;
+use bevy::light::AtmosphereEnvironmentMapLight;
+use bevy::pbr::{Atmosphere, AtmosphereSettings, DistanceFog};
+use bevy::post_process::bloom::Bloom;
+use bevy::post_process::dof::DepthOfField;
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use bevy::render::view::screenshot::Screenshot;
+use bevy::window::{CursorGrabMode, CursorOptions};
+use std::f32::consts::{PI, TAU};
+use std::path::Path;
+
+use crate::capture::{capture_file_name, capture_to, timestamp_millis, CAPTURES_DIR};
+use crate::console::ConsoleLog;
+use crate::input::{Action, ActionInput, InputCapture};
+use crate::player::{LookAngles, PlayerCamera, PlayerSettings};
+use crate::ui::{BUTTON_HOVER, BUTTON_NORMAL, MENU_BG, UI_FONT_PATH};
+use crate::voxel::WorldSeed;
+
+/// Photos are this many times the window resolution
+pub const CAPTURE_SCALE: u32 = 2;
+/// Longest side of a photo, in pixels
+const MAX_CAPTURE_SIZE: u32 = 8192;
+/// Frames the photo camera renders before the capture, so its pipelines are ready
+const CAPTURE_DELAY_FRAMES: u32 = 10;
+
+/// Free camera speed (blocks/s), and the multiplier while sprinting
+const FLY_SPEED: f32 = 8.0;
+const FLY_SPRINT_FACTOR: f32 = 4.0;
+/// Roll speed while a roll key is held (radians/s)
+const ROLL_SPEED: f32 = 1.0;
+/// Pitch limit, the same as the player camera's
+const MAX_PITCH: f32 = 1.54;
+
+const FOV_MIN: f32 = 10.0;
+const FOV_MAX: f32 = 120.0;
+const FOV_STEP: f32 = 5.0;
+const EV100_MIN: f32 = -4.0;
+const EV100_MAX: f32 = 20.0;
+const EV100_STEP: f32 = 0.5;
+const ROLL_STEP_DEGREES: f32 = 5.0;
+const FOCAL_DISTANCE_MIN: f32 = 0.5;
+const FOCAL_DISTANCE_MAX: f32 = 512.0;
+/// Each focus step moves the focal distance by this factor
+const FOCAL_DISTANCE_FACTOR: f32 = 1.25;
+/// Aperture steps, in f-stops
+const F_STOPS: [f32; 10] = [0.5, 1.0, 1.4, 2.0, 2.8, 4.0, 5.6, 8.0, 11.0, 16.0];
+
+/// Camera settings adjusted in photo mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhotoSettings {
+    pub fov_degrees: f32,
+    pub ev100: f32,
+    /// Camera roll, radians in [-π, π)
+    pub roll: f32,
+    pub depth_of_field: bool,
+    /// Distance in focus (blocks)
+    pub focal_distance: f32,
+    pub aperture_f_stops: f32,
+}
+
+impl Default for PhotoSettings {
+    fn default() -> Self {
+        Self {
+            fov_degrees: 45.0,
+            ev100: 13.0,
+            roll: 0.0,
+            depth_of_field: false,
+            focal_distance: 10.0,
+            aperture_f_stops: 2.8,
+        }
+    }
+}
+
+impl PhotoSettings {
+    /// Steps a setting down (-1) or up (+1)
+    fn adjust(&mut self, row: PhotoRow, step: i32) {
+        let step_f = step as f32;
+        match row {
+            PhotoRow::Fov => {
+                self.fov_degrees = (self.fov_degrees + step_f * FOV_STEP).clamp(FOV_MIN, FOV_MAX);
+            }
+            PhotoRow::Exposure => {
+                self.ev100 = (self.ev100 + step_f * EV100_STEP).clamp(EV100_MIN, EV100_MAX);
+            }
+            PhotoRow::Roll => {
+                self.roll = wrap_angle(self.roll + (step_f * ROLL_STEP_DEGREES).to_radians());
+            }
+            PhotoRow::DepthOfField => self.depth_of_field = !self.depth_of_field,
+            PhotoRow::FocalDistance => {
+                self.focal_distance = (self.focal_distance * FOCAL_DISTANCE_FACTOR.powi(step))
+                    .clamp(FOCAL_DISTANCE_MIN, FOCAL_DISTANCE_MAX);
+            }
+            PhotoRow::Aperture => {
+                // Nearest aperture step, so values set elsewhere snap onto the scale
+                let current = F_STOPS
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| {
+                        (*a - self.aperture_f_stops)
+                            .abs()
+                            .total_cmp(&(*b - self.aperture_f_stops).abs())
+                    })
+                    .map_or(0, |(index, _)| index);
+                let index = (current as i32 + step).clamp(0, F_STOPS.len() as i32 - 1);
+                self.aperture_f_stops = F_STOPS[index as usize];
+            }
+        }
+    }
+
+    /// Value shown on the panel
+    fn describe(&self, row: PhotoRow) -> String {
+        match row {
+            PhotoRow::Fov => format!("{:.0}°", self.fov_degrees),
+            PhotoRow::Exposure => format!("{:.1}", self.ev100),
+            PhotoRow::Roll => format!("{:.0}°", self.roll.to_degrees()),
+            PhotoRow::DepthOfField => String::from(if self.depth_of_field { "开" } else { "关" }),
+            PhotoRow::FocalDistance => format!("{:.1} 格", self.focal_distance),
+            PhotoRow::Aperture => format!("f/{}", self.aperture_f_stops),
+        }
+    }
+
+    /// Depth of field component for the camera, None while it's off
+    fn depth_of_field(&self) -> Option<DepthOfField> {
+        self.depth_of_field.then(|| DepthOfField {
+            focal_distance: self.focal_distance,
+            aperture_f_stops: self.aperture_f_stops,
+            ..default()
+        })
+    }
+}
+
+/// Wraps an angle into [-π, π)
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+/// What photo mode changed, saved on entry to be put back on exit
+#[derive(Debug)]
+struct PhotoSession {
+    transform: Transform,
+    ev100: f32,
+    /// Whether virtual time was already paused
+    time_paused: bool,
+    /// HUD roots hidden on entry and their visibility before
+    hidden: Vec<(Entity, Visibility)>,
+    /// Free camera heading; the player's [`LookAngles`] stay untouched
+    yaw: f32,
+    pitch: f32,
+}
+
+/// A photo waiting for its camera to render
+#[derive(Debug)]
+struct PendingCapture {
+    camera: Entity,
+    image: Handle<Image>,
+    name: String,
+    frames_left: u32,
+}
+
+/// Photo mode state; [`PhotoMode::active`] while the free camera is out
+#[derive(Resource, Debug, Default)]
+pub struct PhotoMode {
+    session: Option<PhotoSession>,
+    /// Field of view and exposure start from the player camera's each time, the depth
+    /// of field settings carry over to the next session
+    pub settings: PhotoSettings,
+    panel_open: bool,
+    /// Set by the screenshot key or the panel, handled by start_capture
+    take_photo: bool,
+    capture: Option<PendingCapture>,
+}
+
+impl PhotoMode {
+    pub fn active(&self) -> bool {
+        self.session.is_some()
+    }
+}
+
+/// Run condition for systems that only apply in photo mode
+pub fn photo_mode_active(photo_mode: Res<PhotoMode>) -> bool {
+    photo_mode.active()
+}
+
+/// Run condition for player systems that stop while the free camera is out
+pub fn photo_mode_inactive(photo_mode: Res<PhotoMode>) -> bool {
+    !photo_mode.active()
+}
+
+/// Enters photo mode (sent by the pause menu)
+#[derive(Message, Debug, Clone, Copy)]
+pub struct EnterPhotoMode;
+
+/// Leaves photo mode (sent by the pause key and the panel)
+#[derive(Message, Debug, Clone, Copy)]
+pub struct LeavePhotoMode;
+
+#[derive(Component)]
+struct PhotoPanel;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PhotoRow {
+    Fov,
+    Exposure,
+    Roll,
+    DepthOfField,
+    FocalDistance,
+    Aperture,
+}
+
+#[derive(Component, Clone, Copy)]
+enum PhotoButton {
+    /// Step a setting down (-1) or up (+1); either toggles depth of field
+    Adjust(PhotoRow, i32),
+    TakePhoto,
+    Leave,
+}
+
+/// Text showing the current value of a setting
+#[derive(Component)]
+struct PhotoValueText(PhotoRow);
+
+pub struct PhotoModePlugin;
+
+impl Plugin for PhotoModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhotoMode>()
+            .add_message::<EnterPhotoMode>()
+            .add_message::<LeavePhotoMode>()
+            .add_systems(Startup, spawn_photo_panel)
+            .add_systems(
+                Update,
+                (
+                    (
+                        photo_keys,
+                        photo_button_system,
+                        fly_camera,
+                        apply_photo_settings,
+                        update_photo_values,
+                        start_capture,
+                    )
+                        .chain()
+                        .run_if(photo_mode_active),
+                    finish_capture,
+                ),
+            )
+            // After the pause menu has closed and the pause key was handled, and before
+            // the camera and HUD changes propagate
+            .add_systems(
+                PostUpdate,
+                (enter_photo_mode, leave_photo_mode, apply_panel_open)
+                    .chain()
+                    .before(TransformSystems::Propagate)
+                    .before(VisibilitySystems::VisibilityPropagate),
+            );
+    }
+}
+
+fn spawn_photo_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load(UI_FONT_PATH);
+    let label_font = TextFont {
+        font: font.clone(),
+        font_size: 15.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: px(14.0),
+                top: px(14.0),
+                width: px(300.0),
+                padding: UiRect::all(px(14.0)),
+                row_gap: px(8.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(MENU_BG),
+            Visibility::Hidden,
+            PhotoPanel,
+        ))
+        .with_children(|panel| {
+            panel.spawn((
+                Text::new("拍照模式"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            for (label, row) in [
+                ("视野 (FOV)", PhotoRow::Fov),
+                ("曝光 (EV100)", PhotoRow::Exposure),
+                ("旋转", PhotoRow::Roll),
+                ("对焦距离", PhotoRow::FocalDistance),
+                ("光圈", PhotoRow::Aperture),
+            ] {
+                panel.spawn(photo_row_node()).with_children(|row_parent| {
+                    row_parent.spawn((Text::new(label), label_font.clone()));
+                    row_parent
+                        .spawn(Node {
+                            column_gap: px(8.0),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        })
+                        .with_children(|controls| {
+                            spawn_photo_button(
+                                controls,
+                                &label_font,
+                                "-",
+                                PhotoButton::Adjust(row, -1),
+                                px(32.0),
+                            );
+                            controls
+                                .spawn(Node {
+                                    min_width: px(72.0),
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                })
+                                .with_child((
+                                    Text::new(""),
+                                    label_font.clone(),
+                                    PhotoValueText(row),
+                                ));
+                            spawn_photo_button(
+                                controls,
+                                &label_font,
+                                "+",
+                                PhotoButton::Adjust(row, 1),
+                                px(32.0),
+                            );
+                        });
+                });
+            }
+
+            panel.spawn(photo_row_node()).with_children(|row_parent| {
+                row_parent.spawn((Text::new("景深"), label_font.clone()));
+                row_parent
+                    .spawn((
+                        Button,
+                        PhotoButton::Adjust(PhotoRow::DepthOfField, 1),
+                        Node {
+                            width: px(128.0),
+                            height: px(28.0),
+                            border: UiRect::all(px(1.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(BUTTON_NORMAL),
+                        BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                    ))
+                    .with_child((
+                        Text::new(""),
+                        label_font.clone(),
+                        PhotoValueText(PhotoRow::DepthOfField),
+                    ));
+            });
+
+            spawn_photo_button(
+                panel,
+                &label_font,
+                "拍摄",
+                PhotoButton::TakePhoto,
+                percent(100.0),
+            );
+            spawn_photo_button(
+                panel,
+                &label_font,
+                "退出拍照模式",
+                PhotoButton::Leave,
+                percent(100.0),
+            );
+
+            panel.spawn((
+                Text::new("Tab 隐藏面板，F2 拍摄，Q/E 旋转，Esc 退出"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 13.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.7, 0.74, 0.82)),
+            ));
+        });
+}
+
+fn photo_row_node() -> Node {
+    Node {
+        width: percent(100.0),
+        justify_content: JustifyContent::SpaceBetween,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+fn spawn_photo_button(
+    parent: &mut ChildSpawnerCommands,
+    font: &TextFont,
+    label: &str,
+    button: PhotoButton,
+    width: Val,
+) {
+    parent
+        .spawn((
+            Button,
+            button,
+            Node {
+                width,
+                height: px(28.0),
+                border: UiRect::all(px(1.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_NORMAL),
+            BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+        ))
+        .with_child((Text::new(label), font.clone()));
+}
+
+/// Top-level UI nodes other than the photo panel, hidden while in photo mode
+type HudRootQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut Visibility),
+    (With<Node>, Without<ChildOf>, Without<PhotoPanel>),
+>;
+
+/// Hides the HUD, pauses time and detaches the free camera where the player camera is
+fn enter_photo_mode(
+    mut enter: MessageReader<EnterPhotoMode>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut time: ResMut<Time<Virtual>>,
+    camera_q: Query<(&Transform, &LookAngles, &Exposure, &Projection), With<PlayerCamera>>,
+    mut root_q: HudRootQuery,
+    mut log: ResMut<ConsoleLog>,
+) {
+    if enter.read().count() == 0 || photo_mode.active() {
+        return;
+    }
+    let Ok((transform, angles, exposure, projection)) = camera_q.single() else {
+        return;
+    };
+
+    let hidden = root_q
+        .iter_mut()
+        .map(|(entity, mut visibility)| {
+            let previous = *visibility;
+            *visibility = Visibility::Hidden;
+            (entity, previous)
+        })
+        .collect();
+
+    if let Projection::Perspective(perspective) = projection {
+        photo_mode.settings.fov_degrees = perspective.fov.to_degrees();
+    }
+    photo_mode.settings.ev100 = exposure.ev100;
+    photo_mode.settings.roll = 0.0;
+    photo_mode.panel_open = false;
+    photo_mode.session = Some(PhotoSession {
+        transform: *transform,
+        ev100: exposure.ev100,
+        time_paused: time.is_paused(),
+        hidden,
+        yaw: angles.yaw,
+        pitch: angles.pitch,
+    });
+    time.pause();
+    log.print("拍照模式：Tab 打开设置面板，F2 拍摄，Esc 退出");
+}
+
+/// Puts the camera, exposure, HUD and time back as they were before photo mode
+fn leave_photo_mode(
+    mut commands: Commands,
+    mut leave: MessageReader<LeavePhotoMode>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut time: ResMut<Time<Virtual>>,
+    mut camera_q: Query<(Entity, &mut Transform, &mut Exposure), With<PlayerCamera>>,
+    mut visibility_q: Query<&mut Visibility>,
+) {
+    if leave.read().count() == 0 {
+        return;
+    }
+    let Some(session) = photo_mode.session.take() else {
+        return;
+    };
+    photo_mode.panel_open = false;
+
+    if let Ok((entity, mut transform, mut exposure)) = camera_q.single_mut() {
+        *transform = session.transform;
+        exposure.ev100 = session.ev100;
+        commands.entity(entity).remove::<DepthOfField>();
+    }
+    // Roots shown meanwhile (the console or the map opened in photo mode) stay shown
+    for (entity, previous) in session.hidden {
+        if let Ok(mut visibility) = visibility_q.get_mut(entity)
+            && *visibility == Visibility::Hidden
+        {
+            *visibility = previous;
+        }
+    }
+    if !session.time_paused {
+        time.unpause();
+    }
+}
+
+/// Shows the panel and frees the cursor while it's open, grabbing the cursor again when
+/// it closes or photo mode ends
+fn apply_panel_open(
+    photo_mode: Res<PhotoMode>,
+    mut shown: Local<bool>,
+    mut panel_q: Query<&mut Visibility, With<PhotoPanel>>,
+    mut cursor_options: Single<&mut CursorOptions>,
+) {
+    let open = photo_mode.active() && photo_mode.panel_open;
+    if open == *shown {
+        return;
+    }
+    *shown = open;
+
+    for mut visibility in &mut panel_q {
+        *visibility = if open {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+    cursor_options.visible = open;
+    cursor_options.grab_mode = if open {
+        CursorGrabMode::None
+    } else {
+        CursorGrabMode::Locked
+    };
+}
+
+fn photo_keys(
+    actions: ActionInput,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut leave: MessageWriter<LeavePhotoMode>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        leave.write(LeavePhotoMode);
+        return;
+    }
+    if actions.just_pressed(Action::PhotoPanel) {
+        photo_mode.panel_open = !photo_mode.panel_open;
+    }
+    if actions.just_pressed(Action::Screenshot) {
+        photo_mode.take_photo = true;
+    }
+}
+
+fn photo_button_system(
+    mut interaction_q: Query<
+        (&Interaction, &PhotoButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut leave: MessageWriter<LeavePhotoMode>,
+) {
+    for (interaction, button, mut color) in &mut interaction_q {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = BUTTON_HOVER.into();
+                match *button {
+                    PhotoButton::Adjust(row, step) => photo_mode.settings.adjust(row, step),
+                    PhotoButton::TakePhoto => photo_mode.take_photo = true,
+                    PhotoButton::Leave => {
+                        leave.write(LeavePhotoMode);
+                    }
+                }
+            }
+            Interaction::Hovered => *color = BUTTON_HOVER.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+/// Flies the free camera in real time, since virtual time is paused
+///
+/// Movement follows the camera in every direction and jump/descend move straight up and
+/// down. The mouse only looks around while the panel and the console are closed.
+fn fly_camera(
+    time: Res<Time<Real>>,
+    actions: ActionInput,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    player_settings: Res<PlayerSettings>,
+    capture: Res<InputCapture>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut camera_q: Query<&mut Transform, With<PlayerCamera>>,
+) {
+    let Ok(mut transform) = camera_q.single_mut() else {
+        return;
+    };
+    let dt = time.delta_secs();
+    let PhotoMode {
+        session,
+        settings,
+        panel_open,
+        ..
+    } = &mut *photo_mode;
+    let Some(session) = session.as_mut() else {
+        return;
+    };
+
+    if !*panel_open && !capture.typing {
+        let delta = mouse_motion.delta;
+        session.yaw -= delta.x * player_settings.look_sensitivity;
+        session.pitch = (session.pitch - delta.y * player_settings.look_sensitivity)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+    }
+    let mut roll = 0.0;
+    if actions.pressed(Action::PhotoRollLeft) {
+        roll += 1.0;
+    }
+    if actions.pressed(Action::PhotoRollRight) {
+        roll -= 1.0;
+    }
+    if roll != 0.0 {
+        settings.roll = wrap_angle(settings.roll + roll * ROLL_SPEED * dt);
+    }
+    let rotation = Quat::from_euler(EulerRot::YXZ, session.yaw, session.pitch, settings.roll);
+    if transform.rotation != rotation {
+        transform.rotation = rotation;
+    }
+
+    let forward = transform.forward().as_vec3();
+    let right = transform.right().as_vec3();
+    let mut input = Vec3::ZERO;
+    for (action, direction) in [
+        (Action::MoveForward, forward),
+        (Action::MoveBack, -forward),
+        (Action::MoveLeft, -right),
+        (Action::MoveRight, right),
+        (Action::Jump, Vec3::Y),
+        (Action::Descend, Vec3::NEG_Y),
+    ] {
+        if actions.pressed(action) {
+            input += direction;
+        }
+    }
+    if input == Vec3::ZERO {
+        return;
+    }
+    let speed = if actions.pressed(Action::Sprint) {
+        FLY_SPEED * FLY_SPRINT_FACTOR
+    } else {
+        FLY_SPEED
+    };
+    transform.translation += input.normalize_or_zero() * speed * dt;
+}
+
+/// Applies the panel's field of view, exposure and depth of field to the camera
+fn apply_photo_settings(
+    mut commands: Commands,
+    photo_mode: Res<PhotoMode>,
+    mut camera_q: Query<
+        (
+            Entity,
+            &mut Projection,
+            &mut Exposure,
+            Option<&DepthOfField>,
+        ),
+        With<PlayerCamera>,
+    >,
+) {
+    let settings = &photo_mode.settings;
+    for (entity, mut projection, mut exposure, depth_of_field) in &mut camera_q {
+        let fov = settings.fov_degrees.to_radians();
+        if let Projection::Perspective(perspective) = projection.as_mut()
+            && perspective.fov != fov
+        {
+            perspective.fov = fov;
+        }
+        if exposure.ev100 != settings.ev100 {
+            exposure.ev100 = settings.ev100;
+        }
+        match (settings.depth_of_field(), depth_of_field) {
+            (Some(wanted), Some(current))
+                if wanted.focal_distance == current.focal_distance
+                    && wanted.aperture_f_stops == current.aperture_f_stops => {}
+            (Some(wanted), _) => {
+                commands.entity(entity).insert(wanted);
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<DepthOfField>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn update_photo_values(
+    photo_mode: Res<PhotoMode>,
+    mut text_q: Query<(&mut Text, &PhotoValueText)>,
+) {
+    if !photo_mode.panel_open {
+        return;
+    }
+    for (mut text, value) in &mut text_q {
+        let value = photo_mode.settings.describe(value.0);
+        if text.0 != value {
+            text.0 = value;
+        }
+    }
+}
+
+/// Spawns a camera rendering the player camera's view to an image [`CAPTURE_SCALE`]
+/// times the window size; [`finish_capture`] saves it once it has rendered
+fn start_capture(
+    mut commands: Commands,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut images: ResMut<Assets<Image>>,
+    window: Single<&Window>,
+    camera_q: Query<(Entity, &GlobalTransform), With<PlayerCamera>>,
+    seed: Res<WorldSeed>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    if !std::mem::take(&mut photo_mode.take_photo) {
+        return;
+    }
+    if photo_mode.capture.is_some() {
+        log.print("上一张照片还在拍摄中");
+        return;
+    }
+    let Ok((player_camera, transform)) = camera_q.single() else {
+        return;
+    };
+
+    let size = UVec2::new(window.physical_width(), window.physical_height()).max(UVec2::ONE);
+    let scale = CAPTURE_SCALE
+        .min(MAX_CAPTURE_SIZE / size.max_element())
+        .max(1);
+    let size = size * scale;
+    let image = images.add(Image::new_target_texture(
+        size.x,
+        size.y,
+        TextureFormat::Rgba8UnormSrgb,
+        None,
+    ));
+
+    let camera = commands
+        .spawn((
+            Camera3d::default(),
+            RenderTarget::from(image.clone()),
+            Msaa::Off,
+        ))
+        .id();
+    commands.entity(player_camera).clone_components::<(
+        Transform,
+        Projection,
+        Exposure,
+        Tonemapping,
+        Bloom,
+        Atmosphere,
+        AtmosphereSettings,
+        AtmosphereEnvironmentMapLight,
+        DistanceFog,
+        DepthOfField,
+    )>(camera);
+
+    let name = capture_file_name(
+        &format!("{}_photo", timestamp_millis()),
+        seed.seed,
+        transform.translation().floor().as_ivec3(),
+    );
+    photo_mode.capture = Some(PendingCapture {
+        camera,
+        image,
+        name,
+        frames_left: CAPTURE_DELAY_FRAMES,
+    });
+    log.print(format!("正在拍摄 {}x{} 的照片", size.x, size.y));
+}
+
+/// Saves the photo camera's image once it has rendered for a few frames and removes the
+/// camera; keeps going if photo mode ends in the meantime
+fn finish_capture(
+    mut commands: Commands,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    let Some(capture) = photo_mode.capture.as_mut() else {
+        return;
+    };
+    if capture.frames_left > 0 {
+        capture.frames_left -= 1;
+        return;
+    }
+    let Some(capture) = photo_mode.capture.take() else {
+        return;
+    };
+
+    // The image keeps the last frame the camera rendered after the camera is gone
+    commands.entity(capture.camera).despawn();
+    let screenshot = Screenshot::image(capture.image);
+    match capture_to(
+        &mut commands,
+        screenshot,
+        Path::new(CAPTURES_DIR),
+        &capture.name,
+    ) {
+        Ok(path) => log.print(format!("照片已保存到 {}", path.display())),
+        Err(err) => log.print(format!("无法创建截图目录 {CAPTURES_DIR}：{err}")),
+    }
+}
@@ -7,8 +7,38 @@ use bevy::pbr::{Atmosphere, AtmosphereSettings, ScatteringMedium, ScreenSpaceRef
 use bevy::post_process::bloom::Bloom;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, CursorOptions};
+use serde::{Deserialize, Serialize};
 
+use crate::camera_effects::CameraMedium;
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::game_mode::GameMode;
+use crate::input::{Action, ActionInput, InputCapture};
+use crate::photo_mode::photo_mode_inactive;
 use crate::ui::MenuState;
+use crate::voxel::collision::{self, aabb_collides, Aabb};
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::{ChunkLookup, ChunkPos, Chunks, WorldGenConfig};
+
+/// Half of the player collider's horizontal extent (collider is 0.6 x 1.8 x 0.6)
+const PLAYER_HALF_WIDTH: f32 = 0.3;
+/// Full height of the player collider
+const PLAYER_HEIGHT: f32 = 1.8;
+/// Camera height above the bottom of the collider
+const EYE_HEIGHT: f32 = 1.62;
+/// Camera height while crouching
+const CROUCH_EYE_HEIGHT: f32 = 1.32;
+/// Deepest drop a crouching player can walk off
+const CROUCH_LEDGE_DEPTH: f32 = 0.5;
+/// Highest ledge the player walks onto without jumping
+const STEP_HEIGHT: f32 = 1.0;
+/// Cap on the frame delta used for physics, so hitches don't launch the player through floors
+const MAX_PHYSICS_DT: f32 = 0.05;
+/// How far from the world origin to look for land to spawn on (blocks)
+const SPAWN_SEARCH_RADIUS: i32 = 256;
+/// How far the player is lifted out of blocks at the spawn point before giving up
+const MAX_SPAWN_LIFT: i32 = 64;
+/// Distance below the bottom of the world at which a falling player respawns
+const VOID_MARGIN: f32 = 32.0;
 
 #[derive(Component)]
 pub struct PlayerCamera;
@@ -19,10 +49,102 @@ pub struct LookAngles {
     pub pitch: f32,
 }
 
+/// How the player moves through the world
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MovementMode {
+    /// Gravity and voxel collision
+    #[default]
+    Walk,
+    /// Free flight, no collision
+    Fly,
+}
+
+/// Physics state of the player body
+#[derive(Component, Debug, Default)]
+pub struct PlayerPhysics {
+    pub velocity: Vec3,
+    pub on_ground: bool,
+}
+
+/// Sprinting and crouching, and the values they ease towards
+#[derive(Component, Debug)]
+pub struct PlayerStance {
+    pub sprinting: bool,
+    pub crouching: bool,
+    /// Camera height above the feet
+    pub eye_height: f32,
+    /// Multiplier on `PlayerSettings::move_speed`
+    pub speed_factor: f32,
+    /// Added to the field of view from the settings (degrees)
+    pub fov_boost: f32,
+}
+
+impl Default for PlayerStance {
+    fn default() -> Self {
+        Self {
+            sprinting: false,
+            crouching: false,
+            eye_height: EYE_HEIGHT,
+            speed_factor: 1.0,
+            fov_boost: 0.0,
+        }
+    }
+}
+
+impl PlayerStance {
+    /// Bottom of the collider for a camera at `eye`
+    pub fn feet(&self, eye: Vec3) -> Vec3 {
+        eye - Vec3::Y * self.eye_height
+    }
+}
+
+/// Where the player (re)spawns, as the position of the feet
+#[derive(Resource, Debug, Default)]
+pub struct SpawnPoint {
+    pub feet: Vec3,
+}
+
+/// The player has just (re)spawned or teleported and waits for the ground there to load
+#[derive(Component)]
+struct Spawning {
+    /// Whether the player is at the spawn point and moves along when it changes, rather
+    /// than at a teleport destination
+    at_spawn_point: bool,
+}
+
+/// Moves the player back to the spawn point
+#[derive(Message, Debug, Clone, Copy)]
+pub struct RespawnPlayer;
+
+/// Moves the player to `feet`, looking in the given direction (e.g. to a waypoint)
+#[derive(Message, Debug, Clone, Copy)]
+pub struct TeleportPlayer {
+    pub feet: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
 #[derive(Resource)]
 pub struct PlayerSettings {
     pub move_speed: f32,
+    /// Speed multiplier while sprinting
+    pub sprint_multiplier: f32,
+    /// Speed multiplier while crouching
+    pub crouch_multiplier: f32,
+    /// Field of view added while sprinting (degrees)
+    pub sprint_fov_boost: f32,
+    /// How quickly speed, FOV and camera height follow stance changes (1/s)
+    pub stance_blend_rate: f32,
+    /// Kept in sync with GameSettings
     pub look_sensitivity: f32,
+    /// Kept in sync with GameSettings (degrees)
+    pub fov_degrees: f32,
+    /// Downward acceleration in walk mode (blocks/s²)
+    pub gravity: f32,
+    /// Initial upward velocity of a jump (blocks/s)
+    pub jump_speed: f32,
+    /// Maximum falling speed (blocks/s)
+    pub terminal_velocity: f32,
 }
 
 pub struct PlayerPlugin;
@@ -31,10 +153,44 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(PlayerSettings {
             move_speed: 6.5,
+            sprint_multiplier: 1.5,
+            crouch_multiplier: 0.35,
+            sprint_fov_boost: 8.0,
+            stance_blend_rate: 12.0,
             look_sensitivity: 0.0025,
+            fov_degrees: 45.0,
+            gravity: 28.0,
+            jump_speed: 9.0,
+            terminal_velocity: 60.0,
         })
-        .add_systems(Startup, setup_player)
-        .add_systems(Update, (player_look, player_move));
+        .init_resource::<SpawnPoint>()
+        .add_message::<RespawnPlayer>()
+        .add_message::<TeleportPlayer>()
+        .init_resource::<ConsoleCommands>();
+        app.world_mut()
+            .resource_mut::<ConsoleCommands>()
+            .register("spawn", "spawn", "回到出生点");
+
+        app.add_systems(Startup, setup_player).add_systems(
+            Update,
+            (
+                update_spawn_point,
+                handle_spawn_command,
+                respawn_player,
+                teleport_player,
+                settle_at_spawn,
+                player_look,
+                toggle_movement_mode,
+                update_stance,
+                player_move,
+                clamp_to_world_border,
+                apply_fov,
+                respawn_below_world,
+            )
+                .chain()
+                // Photo mode flies the camera on its own and puts it back afterwards
+                .run_if(photo_mode_inactive),
+        );
     }
 }
 
@@ -53,6 +209,15 @@ fn setup_player(
         Transform::from_xyz(0.0, 50.0, 20.0).with_rotation(rotation),
         PlayerCamera,
         LookAngles { yaw, pitch },
+        (
+            MovementMode::default(),
+            PlayerPhysics::default(),
+            PlayerStance::default(),
+            CameraMedium::default(),
+            Spawning {
+                at_spawn_point: true,
+            },
+        ),
         // Earthlike atmosphere
         Atmosphere::earthlike(scattering_mediums.add(ScatteringMedium::default())),
         AtmosphereSettings::default(),
@@ -76,13 +241,182 @@ fn setup_player(
     cursor_options.visible = false;
 }
 
+/// Searches for land near the origin whenever the terrain inputs change
+///
+/// A player that hasn't touched the ground at the spawn point yet is moved along, so the
+/// first spawn uses the loaded world generation config rather than the defaults. One
+/// waiting at a teleport destination stays there.
+fn update_spawn_point(
+    terrain: Res<SharedTerrain>,
+    mut spawn: ResMut<SpawnPoint>,
+    mut player_q: Query<(&mut Transform, &PlayerStance, &Spawning)>,
+) {
+    if !terrain.is_changed() {
+        return;
+    }
+    let column = terrain.generator().find_spawn(0, 0, SPAWN_SEARCH_RADIUS);
+    spawn.feet = column.as_vec3() + Vec3::new(0.5, 0.0, 0.5);
+    info!("Spawn point: {}", spawn.feet);
+
+    if let Ok((mut transform, stance, spawning)) = player_q.single_mut()
+        && spawning.at_spawn_point
+    {
+        transform.translation = spawn.feet + Vec3::Y * stance.eye_height;
+    }
+}
+
+fn handle_spawn_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut respawn: MessageWriter<RespawnPlayer>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name == "spawn" {
+            respawn.write(RespawnPlayer);
+            log.print("已回到出生点");
+        }
+    }
+}
+
+fn respawn_player(
+    mut commands: Commands,
+    mut respawn: MessageReader<RespawnPlayer>,
+    spawn: Res<SpawnPoint>,
+    mut player_q: Query<
+        (Entity, &mut Transform, &mut PlayerPhysics, &PlayerStance),
+        With<PlayerCamera>,
+    >,
+) {
+    if respawn.read().count() == 0 {
+        return;
+    }
+    let Ok((entity, mut transform, mut physics, stance)) = player_q.single_mut() else {
+        return;
+    };
+    transform.translation = spawn.feet + Vec3::Y * stance.eye_height;
+    *physics = PlayerPhysics::default();
+    commands.entity(entity).insert(Spawning {
+        at_spawn_point: true,
+    });
+}
+
+/// Destinations outside the world border are moved just inside it
+fn teleport_player(
+    mut commands: Commands,
+    config: Res<WorldGenConfig>,
+    mut teleports: MessageReader<TeleportPlayer>,
+    mut player_q: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut LookAngles,
+            &mut PlayerPhysics,
+            &PlayerStance,
+        ),
+        With<PlayerCamera>,
+    >,
+) {
+    let Some(teleport) = teleports.read().last().copied() else {
+        return;
+    };
+    let Ok((entity, mut transform, mut angles, mut physics, stance)) = player_q.single_mut() else {
+        return;
+    };
+    let feet = config
+        .terrain
+        .clamp_to_border(teleport.feet, PLAYER_HALF_WIDTH);
+    transform.translation = feet + Vec3::Y * stance.eye_height;
+    angles.yaw = teleport.yaw;
+    angles.pitch = teleport.pitch;
+    transform.rotation =
+        Quat::from_axis_angle(Vec3::Y, angles.yaw) * Quat::from_axis_angle(Vec3::X, angles.pitch);
+    *physics = PlayerPhysics::default();
+    // Hold the player in place until the destination loads, as after a respawn
+    commands.entity(entity).insert(Spawning {
+        at_spawn_point: false,
+    });
+}
+
+/// Once the chunk at the spawn point has loaded, lifts the player out of anything
+/// solid there (a tree or a structure) and hands control back to physics
+fn settle_at_spawn(
+    mut commands: Commands,
+    chunks: Chunks,
+    mut player_q: Query<
+        (Entity, &mut Transform, &mut PlayerPhysics, &PlayerStance),
+        With<Spawning>,
+    >,
+) {
+    let Ok((entity, mut transform, mut physics, stance)) = player_q.single_mut() else {
+        return;
+    };
+    let mut feet = stance.feet(transform.translation);
+    let feet_chunk = ChunkPos::from_world_pos(
+        feet.x.floor() as i32,
+        feet.y.floor() as i32,
+        feet.z.floor() as i32,
+    );
+    if !chunks.contains(&feet_chunk) {
+        return;
+    }
+
+    for _ in 0..MAX_SPAWN_LIFT {
+        if !body_collides(&chunks, feet) {
+            break;
+        }
+        feet.y = feet.y.floor() + 1.0;
+    }
+    transform.translation = feet + Vec3::Y * stance.eye_height;
+    *physics = PlayerPhysics::default();
+    commands.entity(entity).remove::<Spawning>();
+}
+
+/// Stops the player at the world border, keeping the collider inside it
+fn clamp_to_world_border(
+    config: Res<WorldGenConfig>,
+    mut player_q: Query<(&mut Transform, &mut PlayerPhysics), With<PlayerCamera>>,
+) {
+    let Ok((mut transform, mut physics)) = player_q.single_mut() else {
+        return;
+    };
+    let clamped = config
+        .terrain
+        .clamp_to_border(transform.translation, PLAYER_HALF_WIDTH);
+    if clamped.x != transform.translation.x {
+        physics.velocity.x = 0.0;
+    }
+    if clamped.z != transform.translation.z {
+        physics.velocity.z = 0.0;
+    }
+    if clamped != transform.translation {
+        transform.translation = clamped;
+    }
+}
+
+/// Sends a player that fell out of the world back to the spawn point
+fn respawn_below_world(
+    config: Res<WorldGenConfig>,
+    player_q: Query<(&Transform, &PlayerStance), Without<Spawning>>,
+    mut respawn: MessageWriter<RespawnPlayer>,
+) {
+    let Ok((transform, stance)) = player_q.single() else {
+        return;
+    };
+    if stance.feet(transform.translation).y < config.terrain.min_y as f32 - VOID_MARGIN {
+        info!("Fell out of the world, respawning");
+        respawn.write(RespawnPlayer);
+    }
+}
+
 fn player_look(
     mouse_motion: Res<AccumulatedMouseMotion>,
     mut query: Query<(&mut Transform, &mut LookAngles), With<PlayerCamera>>,
     settings: Res<PlayerSettings>,
     menu_state: Res<MenuState>,
+    capture: Res<InputCapture>,
 ) {
-    if menu_state.open {
+    // The console and the full-screen map keep the mouse while they are open
+    if menu_state.open || capture.typing {
         return;
     }
     let delta = mouse_motion.delta;
@@ -99,47 +433,266 @@ fn player_look(
     transform.rotation = yaw * pitch;
 }
 
+fn toggle_movement_mode(
+    actions: ActionInput,
+    mut query: Query<(&mut MovementMode, &mut PlayerPhysics), With<PlayerCamera>>,
+    menu_state: Res<MenuState>,
+    game_mode: Res<GameMode>,
+) {
+    if menu_state.open || !actions.just_pressed(Action::ToggleFly) {
+        return;
+    }
+    let Ok((mut mode, mut physics)) = query.single_mut() else {
+        return;
+    };
+    if *mode == MovementMode::Walk && !game_mode.allows_flying() {
+        info!("Flying isn't allowed in {game_mode:?} mode");
+        return;
+    }
+    *mode = match *mode {
+        MovementMode::Walk => MovementMode::Fly,
+        MovementMode::Fly => MovementMode::Walk,
+    };
+    physics.velocity = Vec3::ZERO;
+    physics.on_ground = false;
+    info!("Movement mode: {:?}", *mode);
+}
+
+/// Picks the stance from the held keys and eases speed, FOV and camera height towards it
+///
+/// Sprinting needs movement input and cancels crouching; crouching only applies while
+/// walking, since the descend key already moves a flying player down. Water slows
+/// every stance down.
+fn update_stance(
+    time: Res<Time>,
+    actions: ActionInput,
+    settings: Res<PlayerSettings>,
+    menu_state: Res<MenuState>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut PlayerStance,
+            &MovementMode,
+            &CameraMedium,
+        ),
+        With<PlayerCamera>,
+    >,
+) {
+    let Ok((mut transform, mut stance, mode, medium)) = query.single_mut() else {
+        return;
+    };
+    let held = |action| !menu_state.open && actions.pressed(action);
+    let moving = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+    ]
+    .into_iter()
+    .any(held);
+
+    stance.crouching = *mode == MovementMode::Walk && held(Action::Crouch);
+    stance.sprinting = !stance.crouching && moving && held(Action::Sprint);
+
+    let (speed_target, fov_target, eye_target) = if stance.sprinting {
+        (
+            settings.sprint_multiplier,
+            settings.sprint_fov_boost,
+            EYE_HEIGHT,
+        )
+    } else if stance.crouching {
+        (settings.crouch_multiplier, 0.0, CROUCH_EYE_HEIGHT)
+    } else {
+        (1.0, 0.0, EYE_HEIGHT)
+    };
+    let speed_target = speed_target * medium.speed_multiplier();
+
+    let blend = 1.0 - (-settings.stance_blend_rate * time.delta_secs()).exp();
+    let feet = stance.feet(transform.translation);
+    stance.speed_factor += (speed_target - stance.speed_factor) * blend;
+    stance.fov_boost += (fov_target - stance.fov_boost) * blend;
+    stance.eye_height += (eye_target - stance.eye_height) * blend;
+    transform.translation = feet + Vec3::Y * stance.eye_height;
+}
+
+/// Sets the camera's field of view to the settings value plus the sprint boost
+fn apply_fov(
+    settings: Res<PlayerSettings>,
+    mut query: Query<(&mut Projection, &PlayerStance), With<PlayerCamera>>,
+) {
+    for (mut projection, stance) in &mut query {
+        let fov = (settings.fov_degrees + stance.fov_boost).to_radians();
+        if let Projection::Perspective(perspective) = projection.as_mut()
+            && perspective.fov != fov
+        {
+            perspective.fov = fov;
+        }
+    }
+}
+
 fn player_move(
     time: Res<Time>,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut query: Query<&mut Transform, With<PlayerCamera>>,
+    actions: ActionInput,
+    chunks: Chunks,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut PlayerPhysics,
+            &PlayerStance,
+            &MovementMode,
+        ),
+        With<PlayerCamera>,
+    >,
     settings: Res<PlayerSettings>,
     menu_state: Res<MenuState>,
 ) {
     if menu_state.open {
         return;
     }
-    let Ok(mut transform) = query.single_mut() else {
+    let Ok((mut transform, mut physics, stance, mode)) = query.single_mut() else {
         return;
     };
+    let speed = settings.move_speed * stance.speed_factor;
     let forward = transform.forward().as_vec3();
     let right = transform.right().as_vec3();
     let forward_flat = Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero();
     let right_flat = Vec3::new(right.x, 0.0, right.z).normalize_or_zero();
 
     let mut input = Vec3::ZERO;
-    if keys.pressed(KeyCode::KeyW) {
+    if actions.pressed(Action::MoveForward) {
         input += forward_flat;
     }
-    if keys.pressed(KeyCode::KeyS) {
+    if actions.pressed(Action::MoveBack) {
         input -= forward_flat;
     }
-    if keys.pressed(KeyCode::KeyA) {
+    if actions.pressed(Action::MoveLeft) {
         input -= right_flat;
     }
-    if keys.pressed(KeyCode::KeyD) {
+    if actions.pressed(Action::MoveRight) {
         input += right_flat;
     }
-    if keys.pressed(KeyCode::Space) {
-        input += Vec3::Y;
+
+    match mode {
+        MovementMode::Fly => {
+            if actions.pressed(Action::Jump) {
+                input += Vec3::Y;
+            }
+            if actions.pressed(Action::Descend) {
+                input -= Vec3::Y;
+            }
+
+            if input == Vec3::ZERO {
+                return;
+            }
+
+            transform.translation += input.normalize_or_zero() * speed * time.delta_secs();
+        }
+        MovementMode::Walk => {
+            let dt = time.delta_secs().min(MAX_PHYSICS_DT);
+            let mut feet = stance.feet(transform.translation);
+
+            // Don't simulate until the ground under the player exists,
+            // otherwise unloaded chunks read as air and the player falls forever
+            let feet_chunk = ChunkPos::from_world_pos(
+                feet.x.floor() as i32,
+                feet.y.floor() as i32,
+                feet.z.floor() as i32,
+            );
+            if !chunks.contains(&feet_chunk) {
+                return;
+            }
+
+            let horizontal = input.normalize_or_zero() * speed;
+            physics.velocity.x = horizontal.x;
+            physics.velocity.z = horizontal.z;
+
+            if physics.on_ground && actions.pressed(Action::Jump) {
+                physics.velocity.y = settings.jump_speed;
+                physics.on_ground = false;
+            }
+            physics.velocity.y =
+                (physics.velocity.y - settings.gravity * dt).max(-settings.terminal_velocity);
+
+            // Vertical first so on_ground is up to date for step-up checks
+            let delta = physics.velocity * dt;
+            let was_on_ground = physics.on_ground;
+            physics.on_ground = false;
+            if sweep_body(&chunks, &mut feet, 1, delta.y) {
+                if delta.y < 0.0 {
+                    physics.on_ground = true;
+                }
+                physics.velocity.y = 0.0;
+            }
+
+            let can_step = was_on_ground || physics.on_ground;
+            for axis in [0, 2] {
+                let before = feet;
+                move_horizontal(&chunks, &mut feet, axis, delta[axis], can_step);
+                // Crouching keeps the player from walking off ledges
+                if stance.crouching && physics.on_ground && !has_ground_below(&chunks, feet) {
+                    feet = before;
+                }
+            }
+
+            transform.translation = feet + Vec3::Y * stance.eye_height;
+        }
     }
-    if keys.pressed(KeyCode::ShiftLeft) {
-        input -= Vec3::Y;
+}
+
+/// Moves the body along a horizontal axis, stepping up onto ledges no taller than STEP_HEIGHT
+fn move_horizontal(
+    world: &impl ChunkLookup,
+    feet: &mut Vec3,
+    axis: usize,
+    delta: f32,
+    can_step: bool,
+) {
+    let start = *feet;
+    if !sweep_body(world, feet, axis, delta) || !can_step {
+        return;
     }
 
-    if input == Vec3::ZERO {
+    let mut raised = start + Vec3::Y * STEP_HEIGHT;
+    if body_collides(world, raised) {
         return;
     }
+    if !sweep_body(world, &mut raised, axis, delta) {
+        *feet = raised;
+    }
+}
+
+/// Moves the body along one axis, snapping against the first voxel face hit
+///
+/// Returns true if the movement was blocked
+fn sweep_body(world: &impl ChunkLookup, feet: &mut Vec3, axis: usize, delta: f32) -> bool {
+    let sweep = collision::sweep_axis(world, body_aabb(*feet), axis, delta);
+    *feet += sweep.offset;
+    sweep.blocked.test(axis)
+}
+
+/// Player collider bounds for the given feet position
+fn body_aabb(feet: Vec3) -> Aabb {
+    Aabb::from_bottom(feet, PLAYER_HALF_WIDTH, PLAYER_HEIGHT)
+}
+
+/// Checks whether a block at `block` would overlap the collider of a player
+/// whose feet are at `feet`
+pub fn player_overlaps_block(feet: Vec3, block: IVec3) -> bool {
+    body_aabb(feet).overlaps_block(block)
+}
+
+/// Distance from `point` to the collider of a player whose feet are at `feet`
+/// (zero when the point is inside the collider)
+pub fn distance_to_player(feet: Vec3, point: Vec3) -> f32 {
+    body_aabb(feet).distance_to(point)
+}
+
+/// Checks whether the player collider overlaps any solid voxel
+fn body_collides(world: &impl ChunkLookup, feet: Vec3) -> bool {
+    aabb_collides(world, body_aabb(feet))
+}
 
-    transform.translation += input.normalize_or_zero() * settings.move_speed * time.delta_secs();
+/// Checks whether anything solid is at most CROUCH_LEDGE_DEPTH below the collider
+fn has_ground_below(world: &impl ChunkLookup, feet: Vec3) -> bool {
+    body_collides(world, feet - Vec3::Y * CROUCH_LEDGE_DEPTH)
 }
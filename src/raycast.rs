@@ -1,126 +1,649 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 
-use crate::player::PlayerCamera;
-use crate::voxel::{ivec3_to_vec3, VoxelKind, VoxelWorld};
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::game_mode::GameMode;
+use crate::input::{Action, ActionInput};
+use crate::items::{Bucket, HeldItem, Inventory};
+use crate::photo_mode::PhotoMode;
+use crate::player::{player_overlaps_block, PlayerCamera, PlayerStance};
+use crate::ui::MenuState;
+use crate::voxel::domains::fluid::{can_flow_into, is_fluid};
+use crate::voxel::domains::growth::is_sapling_soil;
+use crate::voxel::domains::history::PlayerEditApi;
+use crate::voxel::domains::thermal::api::TEMP_HOT_THRESHOLD;
+use crate::voxel::domains::thermal::ThermalApi;
+use crate::voxel::raycast::{raycast, RaycastFilter, VoxelHit};
+use crate::voxel::{ivec3_to_vec3, ChunkLookup, Chunks, VoxelFlags, VoxelKind, VoxelWorld};
 
-#[derive(Debug, Clone, Copy)]
-pub struct VoxelHit {
-    pub pos: IVec3,
-    pub kind: VoxelKind,
-    pub distance: f32,
+const HIGHLIGHT_COLOR: Color = Color::srgb(1.0, 0.95, 0.2);
+const HOT_HIGHLIGHT_COLOR: Color = Color::srgb(1.0, 0.2, 0.1);
+const FROZEN_HIGHLIGHT_COLOR: Color = Color::srgb(0.3, 0.65, 1.0);
+const GHOST_VALID_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.25);
+const GHOST_BLOCKED_COLOR: Color = Color::srgba(1.0, 0.15, 0.1, 0.35);
+/// Number of crack stages a survival break goes through
+pub const BREAK_STAGES: usize = 5;
+const CRACK_COLOR: Color = Color::srgb(0.08, 0.06, 0.05);
+/// Opacity of the darkening overlay at the last crack stage
+const CRACK_MAX_ALPHA: f32 = 0.55;
+const FACE_NORMALS: [Vec3; 6] = [
+    Vec3::X,
+    Vec3::NEG_X,
+    Vec3::Y,
+    Vec3::NEG_Y,
+    Vec3::Z,
+    Vec3::NEG_Z,
+];
+/// Six directions the crack lines of each face can run in
+const CRACK_ANGLES: [f32; 6] = [0.3, 2.7, 4.4, 1.5, 5.6, 3.6];
+
+/// Reach used until the settings are changed, in blocks
+pub const DEFAULT_REACH: f32 = 8.0;
+/// Longest reach the `raycast reach` command accepts
+const MAX_REACH: f32 = 64.0;
+
+/// How far the player can target blocks and which blocks stop the ray
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct RaycastSettings {
+    pub reach: f32,
+    pub filter: RaycastFilter,
+}
+
+impl Default for RaycastSettings {
+    fn default() -> Self {
+        Self {
+            reach: DEFAULT_REACH,
+            filter: RaycastFilter::default(),
+        }
+    }
 }
 
 #[derive(Resource, Default)]
 pub struct HighlightState {
     pub current: Option<VoxelHit>,
+    /// What makes the highlighted block dangerous to touch, from its flags and temperature
+    pub hazard: Option<BlockHazard>,
+    /// Water under the crosshair for the bucket, found whether or not the filter
+    /// targets fluids
+    pub water: Option<VoxelHit>,
+}
+
+/// Why a block is dangerous to touch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockHazard {
+    /// Burning, smoldering or above the hot temperature threshold
+    Hot,
+    Frozen,
+}
+
+impl BlockHazard {
+    /// The hazard of a block with these flags and temperature; heat wins over frost
+    pub fn of(flags: VoxelFlags, temperature: f32) -> Option<Self> {
+        let burning = VoxelFlags::BURNING | VoxelFlags::SMOLDERING | VoxelFlags::HOT;
+        if flags.intersects(burning) || temperature > TEMP_HOT_THRESHOLD {
+            Some(BlockHazard::Hot)
+        } else if flags.contains(VoxelFlags::FROZEN) {
+            Some(BlockHazard::Frozen)
+        } else {
+            None
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BlockHazard::Hot => "高温",
+            BlockHazard::Frozen => "冻结",
+        }
+    }
+
+    /// Whether the player can't break the block in `mode`
+    pub fn too_hot_to_mine(self, mode: GameMode) -> bool {
+        self == BlockHazard::Hot && !mode.breaks_hot_blocks()
+    }
+
+    fn highlight_color(self) -> Color {
+        match self {
+            BlockHazard::Hot => HOT_HIGHLIGHT_COLOR,
+            BlockHazard::Frozen => FROZEN_HIGHLIGHT_COLOR,
+        }
+    }
+}
+
+/// How long the break action has been held on a block in survival mode
+#[derive(Resource, Debug, Default)]
+pub struct BreakProgress {
+    pub target: Option<IVec3>,
+    pub elapsed: f32,
+    /// Seconds the target takes to break
+    pub duration: f32,
+}
+
+impl BreakProgress {
+    /// Share of the break time that has passed, 0 without a target
+    pub fn fraction(&self) -> f32 {
+        if self.target.is_none() || self.duration <= 0.0 {
+            return 0.0;
+        }
+        (self.elapsed / self.duration).min(1.0)
+    }
+
+    /// Crack stage of the target, from 0 to [`BREAK_STAGES`] - 1, None without a target
+    pub fn stage(&self) -> Option<usize> {
+        self.target?;
+        Some(((self.fraction() * BREAK_STAGES as f32) as usize).min(BREAK_STAGES - 1))
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Sent when the player breaks a block
+#[derive(Message, Debug, Clone, Copy)]
+pub struct BlockBroken {
+    pub pos: IVec3,
+    pub kind: VoxelKind,
+}
+
+/// Sent when the player places a block
+#[derive(Message, Debug, Clone, Copy)]
+pub struct BlockPlaced {
+    pub pos: IVec3,
+    pub kind: VoxelKind,
+}
+
+/// Translucent preview of the block that would be placed
+#[derive(Component)]
+struct PlacementGhost;
+
+/// Darkened shell over the block being broken in survival mode
+#[derive(Component)]
+struct CrackOverlay;
+
+/// Overlay material of each crack stage, darker at later stages
+#[derive(Resource)]
+struct CrackMaterials(Vec<Handle<StandardMaterial>>);
+
+#[derive(Resource)]
+struct GhostMaterials {
+    valid: Handle<StandardMaterial>,
+    blocked: Handle<StandardMaterial>,
 }
 
 pub struct RaycastPlugin;
 
 impl Plugin for RaycastPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "raycast",
+            "raycast [reach <距离>|fluids on|off|foliage on|off]",
+            "查看或修改选取方块的距离，以及是否选取流体和花草",
+        );
+
         app.init_resource::<HighlightState>()
-            .add_systems(Update, (raycast_voxels, draw_highlight_gizmo));
+            .init_resource::<RaycastSettings>()
+            .init_resource::<BreakProgress>()
+            .add_message::<BlockBroken>()
+            .add_message::<BlockPlaced>()
+            .add_systems(Startup, (setup_placement_ghost, setup_crack_overlay))
+            .add_systems(
+                Update,
+                (
+                    raycast_settings_command,
+                    raycast_voxels,
+                    (
+                        draw_highlight_gizmo,
+                        update_placement_ghost,
+                        update_crack_overlay,
+                        break_block,
+                        plant_sapling,
+                        use_bucket,
+                    ),
+                )
+                    .chain(),
+            );
     }
 }
 
-/// DDA (Digital Differential Analyzer) voxel raycast algorithm
-/// Much more efficient than testing every voxel
+/// Targets the block under the crosshair with the player's reach and filter
+///
+/// Nothing is targeted in photo mode, so the highlight and the placement ghost stay out
+/// of the photos and clicks don't edit blocks.
 fn raycast_voxels(
-    world: Res<VoxelWorld>,
+    chunks: Chunks,
+    settings: Res<RaycastSettings>,
+    photo_mode: Res<PhotoMode>,
     camera_q: Query<&GlobalTransform, With<PlayerCamera>>,
     mut highlight: ResMut<HighlightState>,
 ) {
-    let Ok(camera_transform) = camera_q.single() else {
+    let camera_transform = camera_q.single().ok().filter(|_| !photo_mode.active());
+    let Some(camera_transform) = camera_transform else {
         highlight.current = None;
+        highlight.hazard = None;
+        highlight.water = None;
         return;
     };
-
     let origin = camera_transform.translation();
     let dir = camera_transform.forward().as_vec3();
-    let max_dist = 8.0;
-
-    highlight.current = dda_raycast(&world, origin, dir, max_dist);
-}
-
-/// Fast voxel traversal using DDA algorithm
-fn dda_raycast(world: &VoxelWorld, origin: Vec3, dir: Vec3, max_dist: f32) -> Option<VoxelHit> {
-    // Current voxel position
-    let mut pos = IVec3::new(
-        origin.x.floor() as i32,
-        origin.y.floor() as i32,
-        origin.z.floor() as i32,
-    );
-
-    // Direction to step in each axis
-    let step = IVec3::new(
-        if dir.x >= 0.0 { 1 } else { -1 },
-        if dir.y >= 0.0 { 1 } else { -1 },
-        if dir.z >= 0.0 { 1 } else { -1 },
-    );
-
-    // Distance along ray to cross one voxel in each axis
-    let delta = Vec3::new(
-        if dir.x.abs() < 1e-10 { f32::MAX } else { (1.0 / dir.x).abs() },
-        if dir.y.abs() < 1e-10 { f32::MAX } else { (1.0 / dir.y).abs() },
-        if dir.z.abs() < 1e-10 { f32::MAX } else { (1.0 / dir.z).abs() },
-    );
-
-    // Distance to next voxel boundary in each axis
-    let mut t_max = Vec3::new(
-        if dir.x >= 0.0 {
-            ((pos.x + 1) as f32 - origin.x) * delta.x
-        } else {
-            (origin.x - pos.x as f32) * delta.x
-        },
-        if dir.y >= 0.0 {
-            ((pos.y + 1) as f32 - origin.y) * delta.y
-        } else {
-            (origin.y - pos.y as f32) * delta.y
-        },
-        if dir.z >= 0.0 {
-            ((pos.z + 1) as f32 - origin.z) * delta.z
-        } else {
-            (origin.z - pos.z as f32) * delta.z
-        },
-    );
-
-    let mut distance = 0.0;
-
-    while distance < max_dist {
-        // Check current voxel
-        let kind = world.get_voxel(pos);
-        if kind != VoxelKind::Air && kind.is_solid() {
-            return Some(VoxelHit {
-                pos,
-                kind,
-                distance,
-            });
+
+    highlight.current = raycast(&chunks, origin, dir, settings.reach, settings.filter);
+    // Read every frame, a block can catch fire or cool down while it's looked at
+    highlight.hazard = highlight.current.and_then(|hit| {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(hit.pos);
+        let chunk = chunks.get(&chunk_pos)?;
+        BlockHazard::of(chunk.flags.get(idx), ThermalApi::get_temp(chunk, idx))
+    });
+    let fluids = RaycastFilter {
+        fluids: true,
+        ..settings.filter
+    };
+    highlight.water =
+        raycast(&chunks, origin, dir, settings.reach, fluids).filter(|hit| is_fluid(hit.kind));
+}
+
+fn raycast_settings_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut settings: ResMut<RaycastSettings>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "raycast" {
+            continue;
         }
+        let args: Vec<&str> = command.args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            [] => log.print(format!(
+                "选取距离 {}，流体{}，花草{}",
+                settings.reach,
+                on_off(settings.filter.fluids),
+                on_off(settings.filter.foliage)
+            )),
+            ["reach", reach] => match reach.parse::<f32>() {
+                Ok(reach) if (1.0..=MAX_REACH).contains(&reach) => {
+                    settings.reach = reach;
+                    log.print(format!("选取距离 {reach}"));
+                }
+                _ => log.print(format!("raycast：距离应在 1 到 {MAX_REACH} 之间：{reach}")),
+            },
+            [target @ ("fluids" | "foliage"), value @ ("on" | "off")] => {
+                let enabled = *value == "on";
+                if *target == "fluids" {
+                    settings.filter.fluids = enabled;
+                    log.print(format!("选取流体：{}", on_off(enabled)));
+                } else {
+                    settings.filter.foliage = enabled;
+                    log.print(format!("选取花草：{}", on_off(enabled)));
+                }
+            }
+            _ => log.print("用法：raycast [reach <距离>|fluids on|off|foliage on|off]"),
+        }
+    }
+}
 
-        // Move to next voxel (step along the axis with smallest t_max)
-        if t_max.x < t_max.y && t_max.x < t_max.z {
-            distance = t_max.x;
-            t_max.x += delta.x;
-            pos.x += step.x;
-        } else if t_max.y < t_max.z {
-            distance = t_max.y;
-            t_max.y += delta.y;
-            pos.y += step.y;
-        } else {
-            distance = t_max.z;
-            t_max.z += delta.z;
-            pos.z += step.z;
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "开"
+    } else {
+        "关"
+    }
+}
+
+/// Outlines the highlighted block, in red when it's hot and in blue when it's frozen, and
+/// while a survival break is in progress draws cracks on its faces that spread further at
+/// each stage
+fn draw_highlight_gizmo(
+    mut gizmos: Gizmos,
+    highlight: Res<HighlightState>,
+    progress: Res<BreakProgress>,
+) {
+    let Some(hit) = highlight.current else {
+        return;
+    };
+    let center = ivec3_to_vec3(hit.pos) + Vec3::splat(0.5);
+    let transform = Transform::from_translation(center).with_scale(Vec3::splat(1.02));
+    let color = highlight
+        .hazard
+        .map_or(HIGHLIGHT_COLOR, BlockHazard::highlight_color);
+    gizmos.cube(transform, color);
+
+    if progress.target != Some(hit.pos) {
+        return;
+    }
+    let Some(stage) = progress.stage() else {
+        return;
+    };
+    for normal in FACE_NORMALS {
+        // Just outside the face so the lines aren't hidden by the block
+        let face_center = center + normal * 0.505;
+        let (u, v) = normal.any_orthonormal_pair();
+        for (i, &angle) in CRACK_ANGLES.iter().take(stage + 2).enumerate() {
+            let dir = u * angle.cos() + v * angle.sin();
+            let side = u * -angle.sin() + v * angle.cos();
+            let length = 0.12 + 0.08 * stage as f32;
+            let bend = if i % 2 == 0 { 0.06 } else { -0.06 };
+            gizmos.linestrip(
+                [
+                    face_center,
+                    face_center + dir * length * 0.5 + side * bend,
+                    face_center + dir * length,
+                ],
+                CRACK_COLOR,
+            );
         }
     }
+}
 
-    None
+/// The game mode and the progress of a survival break
+#[derive(SystemParam)]
+struct BreakTimer<'w> {
+    time: Res<'w, Time>,
+    game_mode: Res<'w, GameMode>,
+    progress: ResMut<'w, BreakProgress>,
 }
 
-fn draw_highlight_gizmo(mut gizmos: Gizmos, highlight: Res<HighlightState>) {
-    if let Some(hit) = highlight.current {
-        let center = ivec3_to_vec3(hit.pos) + Vec3::splat(0.5);
-        let transform = Transform::from_translation(center).with_scale(Vec3::splat(1.02));
-        gizmos.cube(transform, Color::srgb(1.0, 0.95, 0.2));
+impl BreakTimer<'_> {
+    /// Advances the break of `hit` by a frame, returning whether it's done
+    fn tick(&mut self, hit: VoxelHit) -> bool {
+        let duration = self.game_mode.break_time(hit.kind);
+        if self.progress.target != Some(hit.pos) {
+            *self.progress = BreakProgress {
+                target: Some(hit.pos),
+                elapsed: 0.0,
+                duration,
+            };
+        }
+        self.progress.elapsed += self.time.delta_secs();
+        if self.progress.elapsed < self.progress.duration {
+            return false;
+        }
+        self.progress.reset();
+        true
+    }
+
+    fn cancel(&mut self) {
+        if self.progress.target.is_some() {
+            self.progress.reset();
+        }
+    }
+}
+
+/// The break action (left click by default) removes the highlighted block
+///
+/// A click is enough in creative mode. In survival mode the action has to be held on the
+/// same block for its break time; looking at another block or letting go starts over.
+/// Survival players can't break hot blocks at all (see [`BlockHazard::too_hot_to_mine`]).
+fn break_block(
+    actions: ActionInput,
+    menu_state: Res<MenuState>,
+    highlight: Res<HighlightState>,
+    mut timer: BreakTimer,
+    mut edit: PlayerEditApi,
+    mut broken: MessageWriter<BlockBroken>,
+) {
+    let hit = highlight.current.filter(|_| !menu_state.open);
+    let Some(hit) = hit.filter(|_| actions.pressed(Action::BreakBlock)) else {
+        timer.cancel();
+        return;
+    };
+    let game_mode = *timer.game_mode;
+    if highlight
+        .hazard
+        .is_some_and(|hazard| hazard.too_hot_to_mine(game_mode))
+    {
+        timer.cancel();
+        return;
+    }
+    let done = if game_mode.break_time(hit.kind) <= 0.0 {
+        actions.just_pressed(Action::BreakBlock)
+    } else {
+        timer.tick(hit)
+    };
+    if !done {
+        return;
+    }
+    if edit.set_block(hit.pos, VoxelKind::Air).is_err() {
+        return;
+    }
+    broken.write(BlockBroken {
+        pos: hit.pos,
+        kind: hit.kind,
+    });
+}
+
+/// The place action (right click by default) and the highlighted block it's aimed at
+#[derive(SystemParam)]
+struct PlaceAction<'w> {
+    actions: ActionInput<'w>,
+    menu_state: Res<'w, MenuState>,
+    highlight: Res<'w, HighlightState>,
+}
+
+impl PlaceAction<'_> {
+    /// Whether the action was just pressed with no menu open
+    fn just_pressed(&self) -> bool {
+        !self.menu_state.open && self.actions.just_pressed(Action::PlaceBlock)
+    }
+}
+
+/// The place action (right click by default) plants a sapling from the inventory
+/// on top of the highlighted dirt or grass block; creative mode doesn't use one up
+fn plant_sapling(
+    place_action: PlaceAction,
+    chunks: Chunks,
+    game_mode: Res<GameMode>,
+    mut inventory: ResMut<Inventory>,
+    mut edit: PlayerEditApi,
+    mut placed: MessageWriter<BlockPlaced>,
+) {
+    if inventory.held != HeldItem::Sapling || !place_action.just_pressed() {
+        return;
+    }
+    let Some(hit) = place_action.highlight.current else {
+        return;
+    };
+    let place = hit.pos + IVec3::Y;
+    if hit.normal != IVec3::Y
+        || !is_sapling_soil(hit.kind)
+        || chunks.get_voxel(place) != VoxelKind::Air
+        || (game_mode.uses_inventory() && inventory.count(VoxelKind::Sapling) == 0)
+    {
+        return;
+    }
+    if edit.set_block(place, VoxelKind::Sapling).is_err() {
+        return;
+    }
+    if game_mode.uses_inventory() {
+        inventory.take(VoxelKind::Sapling);
+    }
+    placed.write(BlockPlaced {
+        pos: place,
+        kind: VoxelKind::Sapling,
+    });
+}
+
+/// The place action with the bucket in hand scoops up the water source under the
+/// crosshair into the empty bucket, or pours a full one out as a new source against the
+/// highlighted face. Flowing water can't be scooped up but can be poured over. In
+/// creative mode the bucket stays full after pouring.
+fn use_bucket(
+    place_action: PlaceAction,
+    chunks: Chunks,
+    game_mode: Res<GameMode>,
+    mut inventory: ResMut<Inventory>,
+    mut edit: PlayerEditApi,
+    mut placed: MessageWriter<BlockPlaced>,
+) {
+    if inventory.held != HeldItem::Bucket || !place_action.just_pressed() {
+        return;
+    }
+    let highlight = &place_action.highlight;
+
+    match inventory.bucket {
+        Bucket::Empty => {
+            let Some(hit) = highlight.water else {
+                return;
+            };
+            if !is_water_source(&chunks, hit.pos)
+                || edit.set_block(hit.pos, VoxelKind::Air).is_err()
+            {
+                return;
+            }
+            inventory.bucket = Bucket::Water;
+            info!("Filled the bucket with water");
+        }
+        Bucket::Water => {
+            let Some(place) = highlight.current.and_then(|hit| hit.placement_pos()) else {
+                return;
+            };
+            let target = chunks.get_voxel(place);
+            let pourable =
+                can_flow_into(target) || (is_fluid(target) && !is_water_source(&chunks, place));
+            if !pourable || edit.set_block(place, VoxelKind::Water).is_err() {
+                return;
+            }
+            if game_mode.uses_inventory() {
+                inventory.bucket = Bucket::Empty;
+            }
+            placed.write(BlockPlaced {
+                pos: place,
+                kind: VoxelKind::Water,
+            });
+        }
+    }
+}
+
+/// Whether the block at `pos` is a water source rather than flowing water
+fn is_water_source(world: &impl ChunkLookup, pos: IVec3) -> bool {
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+    world
+        .chunk(&chunk_pos)
+        .is_some_and(|chunk| is_fluid(chunk.voxels.get(idx)) && chunk.variant.get(idx) == 0)
+}
+
+fn setup_placement_ghost(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut ghost_material = |color: Color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        })
+    };
+    let ghost_materials = GhostMaterials {
+        valid: ghost_material(GHOST_VALID_COLOR),
+        blocked: ghost_material(GHOST_BLOCKED_COLOR),
+    };
+
+    commands.spawn((
+        // Slightly smaller than a block so it doesn't z-fight with neighbours
+        Mesh3d(meshes.add(Cuboid::from_length(0.98))),
+        MeshMaterial3d(ghost_materials.valid.clone()),
+        Transform::default(),
+        Visibility::Hidden,
+        PlacementGhost,
+    ));
+    commands.insert_resource(ghost_materials);
+}
+
+fn setup_crack_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let stages = (1..=BREAK_STAGES)
+        .map(|stage| {
+            let alpha = CRACK_MAX_ALPHA * stage as f32 / BREAK_STAGES as f32;
+            materials.add(StandardMaterial {
+                base_color: CRACK_COLOR.with_alpha(alpha),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    commands.spawn((
+        // Slightly larger than a block so it covers the faces without z-fighting
+        Mesh3d(meshes.add(Cuboid::from_length(1.004))),
+        MeshMaterial3d(stages[0].clone()),
+        Transform::default(),
+        Visibility::Hidden,
+        CrackOverlay,
+    ));
+    commands.insert_resource(CrackMaterials(stages));
+}
+
+/// Darkens the block being broken, more at each crack stage
+fn update_crack_overlay(
+    progress: Res<BreakProgress>,
+    crack_materials: Res<CrackMaterials>,
+    mut overlay_q: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut MeshMaterial3d<StandardMaterial>,
+        ),
+        With<CrackOverlay>,
+    >,
+) {
+    let Ok((mut transform, mut visibility, mut material)) = overlay_q.single_mut() else {
+        return;
+    };
+    let (Some(target), Some(stage)) = (progress.target, progress.stage()) else {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+
+    transform.translation = ivec3_to_vec3(target) + Vec3::splat(0.5);
+    *visibility = Visibility::Visible;
+    let stage_material = &crack_materials.0[stage];
+    if material.0 != *stage_material {
+        material.0 = stage_material.clone();
+    }
+}
+
+/// Moves the ghost block onto the hit face, tinting it red when the placed
+/// block would intersect the player
+fn update_placement_ghost(
+    highlight: Res<HighlightState>,
+    ghost_materials: Res<GhostMaterials>,
+    camera_q: Query<(&GlobalTransform, &PlayerStance), With<PlayerCamera>>,
+    mut ghost_q: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut MeshMaterial3d<StandardMaterial>,
+        ),
+        With<PlacementGhost>,
+    >,
+) {
+    let Ok((mut transform, mut visibility, mut material)) = ghost_q.single_mut() else {
+        return;
+    };
+
+    let placement = highlight.current.and_then(|hit| hit.placement_pos());
+    let (Some(place), Ok((camera, stance))) = (placement, camera_q.single()) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    transform.translation = ivec3_to_vec3(place) + Vec3::splat(0.5);
+    *visibility = Visibility::Visible;
+
+    let blocked = player_overlaps_block(stance.feet(camera.translation()), place);
+    let target = if blocked {
+        &ghost_materials.blocked
+    } else {
+        &ghost_materials.valid
+    };
+    if material.0 != *target {
+        material.0 = target.clone();
     }
 }
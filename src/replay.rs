@@ -0,0 +1,338 @@
+//! Replay recording and playback
+//!
+//! `record start` writes the block changes committed every simulation tick, together
+//! with the player's position and view, to a replay file in [`REPLAYS_DIR`] until
+//! `record stop` (the format is described in [`crate::voxel::replay`]). Chunks edited
+//! before recording started go into the first tick as snapshots, so a replay shared
+//! with someone else doesn't depend on the rest of the session.
+//!
+//! `--replay <file>` starts the game in a fresh world with the recorded seed and
+//! generation options and plays back one recorded tick per simulation tick. The block
+//! simulation is paused so only recorded changes happen, which keeps fires and floods
+//! exactly as they were; the simulation speed keys change the playback speed. The
+//! camera follows the recorded player until `replay follow` hands it back to you.
+//! Temperatures and moisture aren't recorded.
+
+use bevy::prelude::*;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::capture::timestamp_millis;
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::player::{LookAngles, MovementMode, PlayerCamera, PlayerPhysics};
+use crate::voxel::domains::command::commit_system;
+use crate::voxel::loading::UnloadedChunks;
+use crate::voxel::persistence::{ActiveWorld, WorldMeta, WorldStorage};
+use crate::voxel::replay::{
+    apply_packet, diff_packets, read_replay, snapshot_packets, PendingPackets, ReplayError,
+    ReplayFrame, ReplayWriter, ViewerPose, REPLAY_EXTENSION,
+};
+use crate::voxel::{Chunks, ChunksMut, SimulationClock, SimulationSet};
+
+/// Directory recordings are written to, relative to the working directory
+pub const REPLAYS_DIR: &str = "replays";
+
+/// The recording in progress, if any
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    session: Option<RecordingSession>,
+}
+
+struct RecordingSession {
+    path: PathBuf,
+    writer: ReplayWriter<BufWriter<File>>,
+}
+
+impl RecordingSession {
+    /// Creates `replays/replay_<time>_seed<seed>.vxreplay` for the world in `meta`
+    fn start(meta: &WorldMeta) -> Result<Self, ReplayError> {
+        fs::create_dir_all(REPLAYS_DIR)?;
+        let path = Path::new(REPLAYS_DIR).join(format!(
+            "replay_{}_seed{}.{REPLAY_EXTENSION}",
+            timestamp_millis(),
+            meta.seed
+        ));
+        let writer = ReplayWriter::new(BufWriter::new(File::create(&path)?), meta)?;
+        Ok(Self { path, writer })
+    }
+}
+
+/// A replay being played back; present only when the game was started with `--replay`
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    path: PathBuf,
+    frames: std::vec::IntoIter<ReplayFrame>,
+    total: usize,
+    played: usize,
+    /// Diffs for chunks that haven't been generated yet
+    pending: PendingPackets,
+    paused: bool,
+    /// Whether the camera follows the recorded player
+    follow: bool,
+}
+
+impl ReplayPlayback {
+    /// Reads the replay at `path`. Returns the playback and the world it was recorded in.
+    pub fn open(path: &Path) -> Result<(Self, WorldMeta), ReplayError> {
+        let replay = read_replay(path)?;
+        let playback = Self {
+            path: path.to_path_buf(),
+            total: replay.frames.len(),
+            frames: replay.frames.into_iter(),
+            played: 0,
+            pending: PendingPackets::default(),
+            paused: false,
+            follow: true,
+        };
+        Ok((playback, replay.meta))
+    }
+
+    /// The world to play back in: the recorded seed and options with a scratch save
+    /// directory, so waypoints set while watching stay out of the recorded world's saves
+    pub fn world(meta: WorldMeta) -> ActiveWorld {
+        ActiveWorld {
+            meta,
+            storage: WorldStorage::new(std::env::temp_dir().join("voxworld-replay")),
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.played == self.total
+    }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        let mut commands = app.world_mut().resource_mut::<ConsoleCommands>();
+        commands.register(
+            "record",
+            "record start | stop",
+            "录制方块变化和玩家视角，保存到 replays 目录",
+        );
+        commands.register(
+            "replay",
+            "replay pause | follow | stop",
+            "控制用 --replay <文件> 启动的回放",
+        );
+
+        app.init_resource::<ReplayRecorder>()
+            .add_systems(
+                Startup,
+                start_playback.run_if(resource_exists::<ReplayPlayback>),
+            )
+            .add_systems(Update, handle_replay_commands)
+            .add_systems(
+                FixedUpdate,
+                (
+                    record_frame,
+                    play_frame.run_if(resource_exists::<ReplayPlayback>),
+                )
+                    .in_set(SimulationSet::Commit)
+                    .after(commit_system),
+            );
+    }
+}
+
+fn handle_replay_commands(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut commands: Commands,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut playback: Option<ResMut<ReplayPlayback>>,
+    active: Res<ActiveWorld>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        match (command.name.as_str(), command.args.as_slice()) {
+            ("record", []) => match &recorder.session {
+                Some(session) => log.print(format!(
+                    "录制中：已录制 {} 个 tick，保存到 {}",
+                    session.writer.frames(),
+                    session.path.display()
+                )),
+                None => log.print("没有在录制"),
+            },
+            ("record", [start]) if start == "start" => {
+                if playback.is_some() {
+                    log.print("回放时不能录制");
+                } else if recorder.session.is_some() {
+                    log.print("已经在录制");
+                } else {
+                    match RecordingSession::start(&active.meta) {
+                        Ok(session) => {
+                            log.print(format!("开始录制，保存到 {}", session.path.display()));
+                            recorder.session = Some(session);
+                        }
+                        Err(err) => log.print(format!("无法开始录制：{err}")),
+                    }
+                }
+            }
+            ("record", [stop]) if stop == "stop" => match recorder.session.take() {
+                Some(session) => {
+                    let frames = session.writer.frames();
+                    match session.writer.finish() {
+                        Ok(_) => log.print(format!(
+                            "录制已停止，共 {frames} 个 tick，保存在 {}",
+                            session.path.display()
+                        )),
+                        Err(err) => log.print(format!(
+                            "录制已停止，但无法写入 {}：{err}",
+                            session.path.display()
+                        )),
+                    }
+                }
+                None => log.print("没有在录制"),
+            },
+            ("record", _) => log.print("用法：record start | record stop"),
+            ("replay", args) => {
+                let Some(playback) = playback.as_deref_mut() else {
+                    log.print("没有正在播放的回放，用 --replay <文件> 启动游戏来播放");
+                    continue;
+                };
+                match args {
+                    [] => log.print(format!(
+                        "回放 {}：第 {} / {} 个 tick{}",
+                        playback.path.display(),
+                        playback.played,
+                        playback.total,
+                        if playback.paused {
+                            "（已暂停）"
+                        } else {
+                            ""
+                        }
+                    )),
+                    [pause] if pause == "pause" => {
+                        playback.paused = !playback.paused;
+                        log.print(if playback.paused {
+                            "回放已暂停"
+                        } else {
+                            "回放继续"
+                        });
+                    }
+                    [follow] if follow == "follow" => {
+                        playback.follow = !playback.follow;
+                        log.print(if playback.follow {
+                            "镜头跟随录制的玩家"
+                        } else {
+                            "镜头由你控制"
+                        });
+                    }
+                    [stop] if stop == "stop" => {
+                        commands.remove_resource::<ReplayPlayback>();
+                        log.print("回放已停止，世界保持当前状态，方块模拟仍然暂停");
+                    }
+                    _ => log.print("用法：replay pause | replay follow | replay stop"),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Pauses the block simulation so only the recorded changes happen
+fn start_playback(
+    playback: Res<ReplayPlayback>,
+    mut clock: ResMut<SimulationClock>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    clock.paused = true;
+    log.print(format!(
+        "开始回放 {}，共 {} 个 tick",
+        playback.path.display(),
+        playback.total
+    ));
+}
+
+/// Appends this tick's committed changes and the player's view to the recording
+fn record_frame(
+    mut recorder: ResMut<ReplayRecorder>,
+    chunks: Chunks,
+    unloaded: Res<UnloadedChunks>,
+    player_q: Query<(&Transform, &LookAngles), With<PlayerCamera>>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    let Some(session) = recorder.session.as_mut() else {
+        return;
+    };
+
+    // The first tick records where the recording starts from
+    let packets = if session.writer.frames() == 0 {
+        snapshot_packets(&chunks, &unloaded)
+    } else {
+        diff_packets(&chunks)
+    };
+    let viewer = player_q
+        .single()
+        .map(|(transform, angles)| ViewerPose {
+            position: transform.translation,
+            yaw: angles.yaw,
+            pitch: angles.pitch,
+        })
+        .unwrap_or_default();
+
+    if let Err(err) = session.writer.write_frame(&ReplayFrame { viewer, packets }) {
+        log.print(format!(
+            "录制已停止，无法写入 {}：{err}",
+            session.path.display()
+        ));
+        recorder.session = None;
+    }
+}
+
+/// Applies the next recorded tick and moves the camera to the recorded view
+fn play_frame(
+    mut playback: ResMut<ReplayPlayback>,
+    mut chunks: ChunksMut,
+    mut unloaded: ResMut<UnloadedChunks>,
+    mut player_q: Query<
+        (
+            &mut Transform,
+            &mut LookAngles,
+            &mut MovementMode,
+            &mut PlayerPhysics,
+        ),
+        With<PlayerCamera>,
+    >,
+    mut log: ResMut<ConsoleLog>,
+) {
+    let playback = &mut *playback;
+    playback.pending.flush(&mut chunks, &mut unloaded);
+    if playback.paused || playback.finished() {
+        return;
+    }
+    let Some(frame) = playback.frames.next() else {
+        return;
+    };
+    playback.played += 1;
+
+    for packet in frame.packets {
+        if let Some(packet) = apply_packet(&mut chunks, &mut unloaded, packet) {
+            playback.pending.push(packet);
+        }
+    }
+    if playback.finished() {
+        log.print("回放结束");
+    }
+
+    if !playback.follow {
+        return;
+    }
+    let Ok((mut transform, mut angles, mut mode, mut physics)) = player_q.single_mut() else {
+        return;
+    };
+    let ViewerPose {
+        position,
+        yaw,
+        pitch,
+    } = frame.viewer;
+    transform.translation = position;
+    transform.rotation =
+        Quat::from_axis_angle(Vec3::Y, yaw) * Quat::from_axis_angle(Vec3::X, pitch);
+    angles.yaw = yaw;
+    angles.pitch = pitch;
+    *mode = MovementMode::Fly;
+    *physics = PlayerPhysics::default();
+}
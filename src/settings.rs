@@ -0,0 +1,239 @@
+//! Persistent player settings
+//!
+//! Settings live in `settings.ron` in the working directory. They are read once at
+//! startup (a missing or unreadable file falls back to the defaults) and written back
+//! every time they're edited from the settings page of the pause menu.
+
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::graphics::GraphicsQuality;
+use crate::input::{InputBindings, InputCapture};
+use crate::particles::ParticleSettings;
+use crate::player::PlayerSettings;
+use crate::voxel::RenderDistance;
+
+/// Settings file, relative to the working directory
+pub const SETTINGS_PATH: &str = "settings.ron";
+
+pub const SENSITIVITY_MIN: f32 = 0.0005;
+pub const SENSITIVITY_MAX: f32 = 0.01;
+pub const SENSITIVITY_STEP: f32 = 0.0005;
+pub const FOV_MIN: f32 = 30.0;
+pub const FOV_MAX: f32 = 110.0;
+pub const FOV_STEP: f32 = 5.0;
+pub const RENDER_DISTANCE_MIN: i32 = 2;
+pub const RENDER_DISTANCE_MAX: i32 = 16;
+pub const SURFACE_BUDGET_MAX: i32 = 16;
+pub const VOLUME_STEP: f32 = 0.1;
+pub const PARTICLE_BUDGET_MAX: usize = 3000;
+pub const PARTICLE_BUDGET_STEP: usize = 100;
+
+/// Everything the player can change from the settings page
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    /// Camera rotation per pixel of mouse movement (radians)
+    pub look_sensitivity: f32,
+    /// Vertical field of view (degrees)
+    pub fov_degrees: f32,
+    /// Horizontal chunk loading radius (chunks)
+    pub render_distance: i32,
+    /// Extra chunk layers loaded below the vertical render distance to keep the ground
+    /// in view when flying high above it
+    pub surface_budget: i32,
+    pub vsync: bool,
+    /// Preset for reflections, fog, bloom, shadows and the atmosphere
+    pub graphics_quality: GraphicsQuality,
+    /// Scales every sound (0..=1)
+    pub master_volume: f32,
+    /// Scales block and fire sounds on top of the master volume (0..=1)
+    pub sfx_volume: f32,
+    /// Particles alive at once (0 disables particles)
+    pub particle_budget: usize,
+    /// Draw beacons over nearby waypoints
+    pub waypoint_beacons: bool,
+    pub bindings: InputBindings,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            look_sensitivity: 0.0025,
+            fov_degrees: 45.0,
+            render_distance: RenderDistance::default().horizontal,
+            surface_budget: RenderDistance::default().surface_budget,
+            vsync: true,
+            graphics_quality: GraphicsQuality::default(),
+            master_volume: 0.8,
+            sfx_volume: 1.0,
+            particle_budget: ParticleSettings::default().max_particles,
+            waypoint_beacons: true,
+            bindings: InputBindings::default(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("could not access settings file: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not parse settings: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize settings: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+impl GameSettings {
+    /// Reads settings from `path`, falling back to the defaults if the file
+    /// doesn't exist or can't be parsed
+    pub fn load(path: &Path) -> Self {
+        match Self::read(path) {
+            Ok(settings) => settings,
+            Err(SettingsError::Io(err)) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                warn!("Ignoring {}: {err}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, SettingsError> {
+        let text = fs::read_to_string(path)?;
+        let mut settings: Self = ron::from_str(&text)?;
+        settings.clamp_to_limits();
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SettingsError> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Pulls hand-edited values back into the ranges the settings page allows
+    pub fn clamp_to_limits(&mut self) {
+        self.look_sensitivity = self
+            .look_sensitivity
+            .clamp(SENSITIVITY_MIN, SENSITIVITY_MAX);
+        self.fov_degrees = self.fov_degrees.clamp(FOV_MIN, FOV_MAX);
+        self.render_distance = self
+            .render_distance
+            .clamp(RENDER_DISTANCE_MIN, RENDER_DISTANCE_MAX);
+        self.surface_budget = self.surface_budget.clamp(0, SURFACE_BUDGET_MAX);
+        self.master_volume = self.master_volume.clamp(0.0, 1.0);
+        self.sfx_volume = self.sfx_volume.clamp(0.0, 1.0);
+        self.particle_budget = self.particle_budget.min(PARTICLE_BUDGET_MAX);
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = GameSettings::load(Path::new(SETTINGS_PATH));
+        app.insert_resource(settings.bindings.clone())
+            .init_resource::<InputCapture>()
+            .insert_resource(settings)
+            .add_systems(Update, (apply_settings, save_settings));
+    }
+}
+
+/// Pushes changed settings into the window, player (look speed and field of view),
+/// input bindings, chunk loader and particle budget
+fn apply_settings(
+    settings: Res<GameSettings>,
+    mut player_settings: ResMut<PlayerSettings>,
+    mut particle_settings: ResMut<ParticleSettings>,
+    mut bindings: ResMut<InputBindings>,
+    mut render_distance: ResMut<RenderDistance>,
+    mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    player_settings.look_sensitivity = settings.look_sensitivity;
+    player_settings.fov_degrees = settings.fov_degrees;
+    particle_settings.max_particles = settings.particle_budget;
+    bindings.set_if_neq(settings.bindings.clone());
+    render_distance.set_if_neq(
+        RenderDistance::from_horizontal(settings.render_distance)
+            .with_surface_budget(settings.surface_budget),
+    );
+
+    let present_mode = if settings.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+    for mut window in &mut window_q {
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+}
+
+/// Writes the settings file whenever the settings are edited
+fn save_settings(settings: Res<GameSettings>) {
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    if let Err(err) = settings.save(Path::new(SETTINGS_PATH)) {
+        warn!("Failed to save {SETTINGS_PATH}: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_round_trip_through_ron() {
+        let path =
+            std::env::temp_dir().join(format!("voxworld-settings-{}.ron", std::process::id()));
+        let settings = GameSettings {
+            look_sensitivity: 0.004,
+            fov_degrees: 70.0,
+            render_distance: 6,
+            surface_budget: 3,
+            vsync: false,
+            graphics_quality: GraphicsQuality::Low,
+            master_volume: 0.5,
+            sfx_volume: 0.25,
+            particle_budget: 500,
+            waypoint_beacons: false,
+            ..default()
+        };
+
+        settings.save(&path).unwrap();
+        assert_eq!(GameSettings::load(&path), settings);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_loading_clamps_out_of_range_values() {
+        let path = std::env::temp_dir().join(format!("voxworld-clamp-{}.ron", std::process::id()));
+        fs::write(&path, "(fov_degrees: 500.0, master_volume: -1.0)").unwrap();
+        let settings = GameSettings::load(&path);
+        assert_eq!(settings.fov_degrees, FOV_MAX);
+        assert_eq!(settings.master_volume, 0.0);
+        // Fields missing from the file keep their defaults
+        assert_eq!(
+            settings.render_distance,
+            GameSettings::default().render_distance
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_missing_settings_file_uses_defaults() {
+        let path = std::env::temp_dir().join("voxworld-settings-missing.ron");
+        let _ = fs::remove_file(&path);
+        assert_eq!(GameSettings::load(&path), GameSettings::default());
+    }
+}
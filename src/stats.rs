@@ -0,0 +1,321 @@
+//! World statistics
+//!
+//! Cumulative counters for the world being played: blocks the player broke and placed,
+//! chunks generated, distance traveled, fires started, blocks that caught fire and the
+//! most voxels the thermal simulation had active at once. They're collected from the
+//! messages other systems already send ([`BlockBroken`], [`BlockPlaced`],
+//! [`ChunkLoaded`], [`BlockIgnited`]) and from the player camera and voxel world.
+//!
+//! Stats belong to a world like waypoints, so they're stored next to its regions in
+//! `<save directory>/stats.ron` (see [`ActiveWorld`]). They're written every
+//! [`SAVE_INTERVAL_SECS`] seconds while they change, before another world is opened and
+//! when the game exits. The `stats` console command and the stats page of the pause
+//! menu show them.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::photo_mode::photo_mode_inactive;
+use crate::player::PlayerCamera;
+use crate::raycast::{BlockBroken, BlockPlaced};
+use crate::voxel::events::{BlockIgnited, ChunkLoaded};
+use crate::voxel::persistence::{write_atomic, ActiveWorld};
+use crate::voxel::{Chunks, VoxelFlags, VoxelWorld};
+
+/// Stats file inside the world's save directory
+pub const STATS_FILE: &str = "stats.ron";
+/// How often changed stats are written, in seconds
+pub const SAVE_INTERVAL_SECS: f32 = 30.0;
+/// Camera moves longer than this in one frame are teleports and aren't traveled (blocks)
+const MAX_FRAME_STEP: f32 = 16.0;
+
+const NEIGHBOR_DIRS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// The counters saved with the world
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatCounters {
+    pub blocks_broken: u64,
+    pub blocks_placed: u64,
+    pub chunks_generated: u64,
+    /// Distance the player moved, in blocks
+    pub distance_traveled: f64,
+    /// Ignitions with no fire next to them
+    pub fires_started: u64,
+    /// Every block that caught fire, including ones a fire spread to
+    pub blocks_burned: u64,
+    pub peak_active_thermal: u64,
+}
+
+impl StatCounters {
+    /// Label and formatted value of each counter, in display order
+    pub fn rows(&self) -> [(&'static str, String); 7] {
+        [
+            ("破坏方块", self.blocks_broken.to_string()),
+            ("放置方块", self.blocks_placed.to_string()),
+            ("生成区块", self.chunks_generated.to_string()),
+            ("移动距离", format!("{:.0} 格", self.distance_traveled)),
+            ("引发火灾", self.fires_started.to_string()),
+            ("烧着的方块", self.blocks_burned.to_string()),
+            ("活跃热方块峰值", self.peak_active_thermal.to_string()),
+        ]
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StatsError {
+    #[error("could not access stats file: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not parse stats: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize stats: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+/// Stats of the current world
+#[derive(Resource, Debug, Default)]
+pub struct WorldStats {
+    pub counters: StatCounters,
+    /// File the stats are saved to, None when they aren't persisted
+    path: Option<PathBuf>,
+    /// Whether the counters changed since they were last written
+    unsaved: bool,
+}
+
+impl WorldStats {
+    /// Reads the stats from `path`; a missing file means a world without stats yet, an
+    /// unreadable one is reported and the counting starts over
+    pub fn load(path: PathBuf) -> Self {
+        let counters = match Self::read(&path) {
+            Ok(counters) => counters,
+            Err(StatsError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+                StatCounters::default()
+            }
+            Err(err) => {
+                warn!("Ignoring {}: {err}", path.display());
+                StatCounters::default()
+            }
+        };
+        Self {
+            counters,
+            path: Some(path),
+            unsaved: false,
+        }
+    }
+
+    fn read(path: &Path) -> Result<StatCounters, StatsError> {
+        let text = fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    pub fn save(&mut self) -> Result<(), StatsError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(&self.counters, ron::ser::PrettyConfig::default())?;
+        write_atomic(path, text.as_bytes())?;
+        self.unsaved = false;
+        Ok(())
+    }
+
+    /// Writes the stats if they changed since the last save, reporting failures
+    fn save_if_changed(&mut self) {
+        if !self.unsaved {
+            return;
+        }
+        if let Err(err) = self.save() {
+            warn!("Failed to save stats: {err}");
+        }
+    }
+
+    /// Changes the counters and marks them for the next save
+    pub fn update(&mut self, f: impl FnOnce(&mut StatCounters)) {
+        f(&mut self.counters);
+        self.unsaved = true;
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "stats",
+            "stats",
+            "查看当前世界的统计数据",
+        );
+
+        app.init_resource::<WorldStats>()
+            .add_systems(
+                Update,
+                (
+                    load_stats,
+                    (
+                        count_block_edits,
+                        count_generated_chunks,
+                        count_fires,
+                        // The photo mode camera isn't the player moving
+                        track_distance.run_if(photo_mode_inactive),
+                        track_peak_thermal,
+                    ),
+                    stats_command,
+                    save_stats_periodically,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, save_stats_on_exit);
+    }
+}
+
+/// Reads the stats of the world being played, again when the new world screen opens
+/// another world; the stats of the previous world are written first
+fn load_stats(world: Res<ActiveWorld>, mut stats: ResMut<WorldStats>) {
+    if !world.is_changed() {
+        return;
+    }
+    stats.save_if_changed();
+    *stats = WorldStats::load(world.storage.root().join(STATS_FILE));
+}
+
+fn count_block_edits(
+    mut broken: MessageReader<BlockBroken>,
+    mut placed: MessageReader<BlockPlaced>,
+    mut stats: ResMut<WorldStats>,
+) {
+    let broken = broken.read().count() as u64;
+    let placed = placed.read().count() as u64;
+    if broken + placed > 0 {
+        stats.update(|counters| {
+            counters.blocks_broken += broken;
+            counters.blocks_placed += placed;
+        });
+    }
+}
+
+/// Chunks restored from the modified chunks kept while unloaded were counted when they
+/// were first generated
+fn count_generated_chunks(mut loaded: MessageReader<ChunkLoaded>, mut stats: ResMut<WorldStats>) {
+    let generated = loaded.read().filter(|chunk| !chunk.restored).count() as u64;
+    if generated > 0 {
+        stats.update(|counters| counters.chunks_generated += generated);
+    }
+}
+
+/// Counts the blocks that caught fire, and as new fires the ones with no burning
+/// neighbor. A block next to one that caught fire earlier in the same batch belongs to
+/// that fire, so a cluster ignited at once counts as one fire.
+fn count_fires(
+    mut ignited: MessageReader<BlockIgnited>,
+    chunks: Chunks,
+    mut stats: ResMut<WorldStats>,
+) {
+    let batch: Vec<IVec3> = ignited.read().map(|ignited| ignited.pos).collect();
+    if batch.is_empty() {
+        return;
+    }
+    let pending: HashSet<IVec3> = batch.iter().copied().collect();
+    let mut counted = HashSet::with_capacity(batch.len());
+    let mut fires = 0;
+    for &pos in &batch {
+        let next_to_fire = NEIGHBOR_DIRS.iter().any(|&dir| {
+            let neighbor = pos + dir;
+            let (chunk_pos, idx) = VoxelWorld::split_world_pos(neighbor);
+            let burning = chunks
+                .get(&chunk_pos)
+                .is_some_and(|chunk| chunk.flags.get(idx).contains(VoxelFlags::BURNING));
+            burning && (!pending.contains(&neighbor) || counted.contains(&neighbor))
+        });
+        if !next_to_fire {
+            fires += 1;
+        }
+        counted.insert(pos);
+    }
+    stats.update(|counters| {
+        counters.blocks_burned += batch.len() as u64;
+        counters.fires_started += fires;
+    });
+}
+
+/// Adds up how far the camera moves, skipping teleports
+fn track_distance(
+    world: Res<ActiveWorld>,
+    camera_q: Query<&Transform, With<PlayerCamera>>,
+    mut last: Local<Option<Vec3>>,
+    mut stats: ResMut<WorldStats>,
+) {
+    let Ok(transform) = camera_q.single() else {
+        return;
+    };
+    let position = transform.translation;
+    if world.is_changed() {
+        *last = None;
+    }
+    if let Some(previous) = last.replace(position) {
+        let step = previous.distance(position);
+        if step > 0.0 && step <= MAX_FRAME_STEP {
+            stats.update(|counters| counters.distance_traveled += step as f64);
+        }
+    }
+}
+
+fn track_peak_thermal(chunks: Chunks, mut stats: ResMut<WorldStats>) {
+    let active: u64 = chunks
+        .iter()
+        .map(|(_, chunk)| chunk.active_thermal.len() as u64)
+        .sum();
+    if active > stats.counters.peak_active_thermal {
+        stats.update(|counters| counters.peak_active_thermal = active);
+    }
+}
+
+fn stats_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    stats: Res<WorldStats>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "stats" {
+            continue;
+        }
+        log.print("世界统计：");
+        for (label, value) in stats.counters.rows() {
+            log.print(format!("  {label}：{value}"));
+        }
+    }
+}
+
+fn save_stats_periodically(
+    time: Res<Time>,
+    mut elapsed: Local<f32>,
+    mut stats: ResMut<WorldStats>,
+) {
+    *elapsed += time.delta_secs();
+    if *elapsed < SAVE_INTERVAL_SECS {
+        return;
+    }
+    *elapsed = 0.0;
+    stats.save_if_changed();
+}
+
+fn save_stats_on_exit(mut exit: MessageReader<AppExit>, mut stats: ResMut<WorldStats>) {
+    if exit.read().next().is_some() {
+        stats.save_if_changed();
+    }
+}
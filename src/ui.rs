@@ -1,21 +1,52 @@
 use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, CursorOptions};
 
+use crate::celestial::GameClock;
+use crate::game_mode::GameMode;
+use crate::input::{Action, ActionInput};
+use crate::photo_mode::{photo_mode_inactive, EnterPhotoMode};
+use crate::player::TeleportPlayer;
 use crate::raycast::HighlightState;
-use crate::voxel::WorldSeed;
+use crate::settings::{
+    GameSettings, FOV_MAX, FOV_MIN, FOV_STEP, PARTICLE_BUDGET_MAX, PARTICLE_BUDGET_STEP,
+    RENDER_DISTANCE_MAX, RENDER_DISTANCE_MIN, SENSITIVITY_MAX, SENSITIVITY_MIN, SENSITIVITY_STEP,
+    SURFACE_BUDGET_MAX, VOLUME_STEP,
+};
+use crate::stats::{StatCounters, WorldStats};
+use crate::voxel::domains::thermal::systems::ENV_TEMPERATURE;
+use crate::voxel::domains::thermal::ThermalApi;
+use crate::voxel::profiling::{Stage, StageTimings};
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::{
+    ChunkLoadQueue, ChunkTasks, Chunks, RemeshScheduler, SimulationClock, VoxelWorld, WorldSeed,
+};
+use crate::waypoints::Waypoints;
 
-const UI_FONT_PATH: &str = "fonts/SourceHanSansSC-Regular.otf";
-const MENU_BG: Color = Color::srgba(0.08, 0.09, 0.12, 0.92);
+pub const UI_FONT_PATH: &str = "fonts/SourceHanSansSC-Regular.otf";
+pub const MENU_BG: Color = Color::srgba(0.08, 0.09, 0.12, 0.92);
 const MENU_OVERLAY: Color = Color::srgba(0.0, 0.0, 0.0, 0.45);
 const INFO_BG: Color = Color::srgba(0.06, 0.08, 0.12, 0.78);
-const BUTTON_NORMAL: Color = Color::srgb(0.20, 0.22, 0.28);
-const BUTTON_HOVER: Color = Color::srgb(0.28, 0.30, 0.38);
+pub const BUTTON_NORMAL: Color = Color::srgb(0.20, 0.22, 0.28);
+pub const BUTTON_HOVER: Color = Color::srgb(0.28, 0.30, 0.38);
 const BUTTON_PRESSED: Color = Color::srgb(0.36, 0.12, 0.12);
 
 #[derive(Resource, Default)]
 pub struct MenuState {
     pub open: bool,
+    pub page: MenuPage,
+    /// Action waiting for a key press on the settings page
+    pub rebinding: Option<Action>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MenuPage {
+    #[default]
+    Main,
+    Settings,
+    Waypoints,
+    Stats,
 }
 
 #[derive(Resource, Default)]
@@ -32,6 +63,58 @@ struct ExitMenuRoot;
 #[derive(Component)]
 struct ExitButton;
 
+/// Panel of the pause menu shown while its page is open
+#[derive(Component)]
+struct MenuPanel(MenuPage);
+
+/// Container of the waypoint rows, rebuilt whenever the waypoints change
+#[derive(Component)]
+struct WaypointList;
+
+/// Value of the stats row at this index, see [`StatCounters::rows`]
+#[derive(Component)]
+struct StatValueText(usize);
+
+/// Pause menu buttons other than exit
+#[derive(Component, Clone, Copy)]
+enum MenuButton {
+    OpenSettings,
+    OpenWaypoints,
+    OpenStats,
+    /// Close the menu and enter photo mode
+    EnterPhotoMode,
+    Back,
+    /// Step a numeric setting down (-1) or up (+1)
+    Adjust(SettingRow, i32),
+    ToggleVsync,
+    /// Switch to the next graphics quality preset
+    CycleGraphicsQuality,
+    ToggleBeacons,
+    Rebind(Action),
+    /// Teleport to the waypoint at this index and close the menu
+    Teleport(usize),
+    DeleteWaypoint(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SettingRow {
+    Sensitivity,
+    Fov,
+    RenderDistance,
+    SurfaceBudget,
+    MasterVolume,
+    SfxVolume,
+    ParticleBudget,
+    Vsync,
+    GraphicsQuality,
+    WaypointBeacons,
+    Key(Action),
+}
+
+/// Text showing the current value of a setting
+#[derive(Component)]
+struct SettingValueText(SettingRow);
+
 #[derive(Component)]
 struct Crosshair;
 
@@ -44,6 +127,21 @@ struct DebugOverlay;
 #[derive(Component)]
 struct DebugText;
 
+/// World-side numbers shown in the F3 overlay.
+#[derive(SystemParam)]
+struct DebugWorldStats<'w, 's> {
+    world: Res<'w, VoxelWorld>,
+    chunks: Chunks<'w, 's>,
+    seed: Res<'w, WorldSeed>,
+    queue: Res<'w, ChunkLoadQueue>,
+    remesh: Res<'w, RemeshScheduler>,
+    timings: Res<'w, StageTimings>,
+    clock: Res<'w, SimulationClock>,
+    game_clock: Res<'w, GameClock>,
+    terrain: Res<'w, SharedTerrain>,
+    chunk_tasks: Res<'w, ChunkTasks>,
+}
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
@@ -56,8 +154,16 @@ impl Plugin for UiPlugin {
                 (
                     update_voxel_info,
                     update_seed_info,
-                    toggle_exit_menu,
+                    // Photo mode handles the pause key itself
+                    toggle_exit_menu.run_if(photo_mode_inactive),
+                    apply_menu_open.after(menu_button_system),
                     exit_button_system,
+                    menu_button_system.after(toggle_exit_menu),
+                    capture_rebind_key.after(menu_button_system),
+                    update_menu_page,
+                    update_setting_values,
+                    update_waypoint_list,
+                    update_stat_values,
                     toggle_debug_overlay,
                     update_debug_overlay,
                 ),
@@ -189,128 +295,612 @@ fn setup_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             Visibility::Hidden,
             ExitMenuRoot,
         ))
-        .with_child((
+        .with_children(|root| {
+            root.spawn((
+                Node {
+                    width: px(320.0),
+                    padding: UiRect::all(px(18.0)),
+                    row_gap: px(12.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                BackgroundColor(MENU_BG),
+                BorderColor::all(Color::srgb(0.5, 0.55, 0.62)),
+                MenuPanel(MenuPage::Main),
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Text::new("暂停菜单"),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+
+                parent.spawn((
+                    Text::new("按 Esc 返回游戏"),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 14.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(0.8, 0.82, 0.9)),
+                ));
+
+                parent
+                    .spawn((
+                        Button,
+                        MenuButton::OpenSettings,
+                        wide_button_node(),
+                        BackgroundColor(BUTTON_NORMAL),
+                        BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                    ))
+                    .with_child((
+                        Text::new("设置"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+
+                parent
+                    .spawn((
+                        Button,
+                        MenuButton::OpenWaypoints,
+                        wide_button_node(),
+                        BackgroundColor(BUTTON_NORMAL),
+                        BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                    ))
+                    .with_child((
+                        Text::new("路标"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+
+                parent
+                    .spawn((
+                        Button,
+                        MenuButton::OpenStats,
+                        wide_button_node(),
+                        BackgroundColor(BUTTON_NORMAL),
+                        BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                    ))
+                    .with_child((
+                        Text::new("统计"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+
+                parent
+                    .spawn((
+                        Button,
+                        MenuButton::EnterPhotoMode,
+                        wide_button_node(),
+                        BackgroundColor(BUTTON_NORMAL),
+                        BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                    ))
+                    .with_child((
+                        Text::new("拍照模式"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+
+                parent
+                    .spawn((
+                        Button,
+                        ExitButton,
+                        wide_button_node(),
+                        BackgroundColor(BUTTON_NORMAL),
+                        BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                    ))
+                    .with_child((
+                        Text::new("退出游戏"),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(Color::WHITE),
+                    ));
+            });
+
+            spawn_settings_panel(root, &font);
+            spawn_waypoints_panel(root, &font);
+            spawn_stats_panel(root, &font);
+        });
+}
+
+fn wide_button_node() -> Node {
+    Node {
+        width: percent(100.0),
+        height: px(44.0),
+        border: UiRect::all(px(1.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+/// Settings page of the pause menu, hidden until opened from the main page
+fn spawn_settings_panel(root: &mut ChildSpawnerCommands, font: &Handle<Font>) {
+    let label_font = TextFont {
+        font: font.clone(),
+        font_size: 15.0,
+        ..default()
+    };
+
+    root.spawn((
+        Node {
+            width: px(640.0),
+            padding: UiRect::all(px(18.0)),
+            row_gap: px(8.0),
+            flex_direction: FlexDirection::Column,
+            display: Display::None,
+            ..default()
+        },
+        BackgroundColor(MENU_BG),
+        BorderColor::all(Color::srgb(0.5, 0.55, 0.62)),
+        MenuPanel(MenuPage::Settings),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Text::new("设置"),
+            TextFont {
+                font: font.clone(),
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        ));
+
+        for (label, row) in [
+            ("视角灵敏度", SettingRow::Sensitivity),
+            ("视野 (FOV)", SettingRow::Fov),
+            ("渲染距离", SettingRow::RenderDistance),
+            ("地表保留", SettingRow::SurfaceBudget),
+            ("主音量", SettingRow::MasterVolume),
+            ("音效音量", SettingRow::SfxVolume),
+            ("粒子上限", SettingRow::ParticleBudget),
+        ] {
+            parent
+                .spawn(setting_row_node())
+                .with_children(|row_parent| {
+                    row_parent.spawn((Text::new(label), label_font.clone()));
+                    row_parent
+                        .spawn(Node {
+                            column_gap: px(8.0),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        })
+                        .with_children(|controls| {
+                            spawn_small_button(
+                                controls,
+                                &label_font,
+                                "-",
+                                MenuButton::Adjust(row, -1),
+                            );
+                            controls
+                                .spawn(Node {
+                                    min_width: px(72.0),
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                })
+                                .with_child((
+                                    Text::new(""),
+                                    label_font.clone(),
+                                    SettingValueText(row),
+                                ));
+                            spawn_small_button(
+                                controls,
+                                &label_font,
+                                "+",
+                                MenuButton::Adjust(row, 1),
+                            );
+                        });
+                });
+        }
+
+        parent
+            .spawn(setting_row_node())
+            .with_children(|row_parent| {
+                row_parent.spawn((Text::new("垂直同步"), label_font.clone()));
+                spawn_value_button(
+                    row_parent,
+                    &label_font,
+                    MenuButton::ToggleVsync,
+                    SettingRow::Vsync,
+                );
+            });
+
+        parent
+            .spawn(setting_row_node())
+            .with_children(|row_parent| {
+                row_parent.spawn((Text::new("画质"), label_font.clone()));
+                spawn_value_button(
+                    row_parent,
+                    &label_font,
+                    MenuButton::CycleGraphicsQuality,
+                    SettingRow::GraphicsQuality,
+                );
+            });
+
+        parent
+            .spawn(setting_row_node())
+            .with_children(|row_parent| {
+                row_parent.spawn((Text::new("路标信标"), label_font.clone()));
+                spawn_value_button(
+                    row_parent,
+                    &label_font,
+                    MenuButton::ToggleBeacons,
+                    SettingRow::WaypointBeacons,
+                );
+            });
+
+        parent.spawn((
+            Text::new("按键绑定（点击后按下新按键，Esc 取消）"),
+            TextFont {
+                font: font.clone(),
+                font_size: 13.0,
+                ..default()
+            },
+            TextColor(Color::srgb(0.7, 0.74, 0.82)),
             Node {
-                width: px(320.0),
-                padding: UiRect::all(px(18.0)),
-                row_gap: px(12.0),
-                flex_direction: FlexDirection::Column,
+                margin: UiRect::top(px(8.0)),
                 ..default()
             },
-            BackgroundColor(MENU_BG),
-            BorderColor::all(Color::srgb(0.5, 0.55, 0.62)),
-        ))
-        .with_children(|parent| {
-            parent.spawn((
-                Text::new("暂停菜单"),
+        ));
+
+        // The pause binding stays fixed so the menu can always be closed
+        parent
+            .spawn(Node {
+                display: Display::Grid,
+                grid_template_columns: RepeatedGridTrack::flex(2, 1.0),
+                column_gap: px(18.0),
+                row_gap: px(6.0),
+                ..default()
+            })
+            .with_children(|grid| {
+                for action in Action::ALL.into_iter().filter(|&a| a != Action::Pause) {
+                    grid.spawn(setting_row_node()).with_children(|row_parent| {
+                        row_parent.spawn((Text::new(action.label()), label_font.clone()));
+                        spawn_value_button(
+                            row_parent,
+                            &label_font,
+                            MenuButton::Rebind(action),
+                            SettingRow::Key(action),
+                        );
+                    });
+                }
+            });
+
+        parent
+            .spawn((
+                Button,
+                MenuButton::Back,
+                Node {
+                    margin: UiRect::top(px(8.0)),
+                    ..wide_button_node()
+                },
+                BackgroundColor(BUTTON_NORMAL),
+                BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+            ))
+            .with_child((
+                Text::new("返回"),
                 TextFont {
                     font: font.clone(),
-                    font_size: 22.0,
+                    font_size: 18.0,
                     ..default()
                 },
                 TextColor(Color::WHITE),
             ));
+    });
+}
 
-            parent.spawn((
-                Text::new("按 Esc 返回游戏"),
+/// Waypoints page of the pause menu; the rows are filled in by [`update_waypoint_list`]
+fn spawn_waypoints_panel(root: &mut ChildSpawnerCommands, font: &Handle<Font>) {
+    root.spawn((
+        Node {
+            width: px(480.0),
+            padding: UiRect::all(px(18.0)),
+            row_gap: px(8.0),
+            flex_direction: FlexDirection::Column,
+            display: Display::None,
+            ..default()
+        },
+        BackgroundColor(MENU_BG),
+        BorderColor::all(Color::srgb(0.5, 0.55, 0.62)),
+        MenuPanel(MenuPage::Waypoints),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Text::new("路标"),
+            TextFont {
+                font: font.clone(),
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        ));
+
+        parent.spawn((
+            Node {
+                width: percent(100.0),
+                row_gap: px(6.0),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            WaypointList,
+        ));
+
+        parent
+            .spawn((
+                Button,
+                MenuButton::Back,
+                Node {
+                    margin: UiRect::top(px(8.0)),
+                    ..wide_button_node()
+                },
+                BackgroundColor(BUTTON_NORMAL),
+                BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+            ))
+            .with_child((
+                Text::new("返回"),
                 TextFont {
                     font: font.clone(),
-                    font_size: 14.0,
+                    font_size: 18.0,
                     ..default()
                 },
-                TextColor(Color::srgb(0.8, 0.82, 0.9)),
+                TextColor(Color::WHITE),
             ));
+    });
+}
 
-            parent
-                .spawn((
-                    Button,
-                    ExitButton,
-                    Node {
-                        width: percent(100.0),
-                        height: px(44.0),
-                        border: UiRect::all(px(1.0)),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    BackgroundColor(BUTTON_NORMAL),
-                    BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
-                ))
-                .with_child((
-                    Text::new("退出游戏"),
-                    TextFont {
-                        font: font.clone(),
-                        font_size: 18.0,
-                        ..default()
-                    },
-                    TextColor(Color::WHITE),
+/// Stats page of the pause menu; the values are filled in by [`update_stat_values`]
+fn spawn_stats_panel(root: &mut ChildSpawnerCommands, font: &Handle<Font>) {
+    let row_font = TextFont {
+        font: font.clone(),
+        font_size: 15.0,
+        ..default()
+    };
+
+    root.spawn((
+        Node {
+            width: px(400.0),
+            padding: UiRect::all(px(18.0)),
+            row_gap: px(8.0),
+            flex_direction: FlexDirection::Column,
+            display: Display::None,
+            ..default()
+        },
+        BackgroundColor(MENU_BG),
+        BorderColor::all(Color::srgb(0.5, 0.55, 0.62)),
+        MenuPanel(MenuPage::Stats),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Text::new("统计"),
+            TextFont {
+                font: font.clone(),
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        ));
+
+        for (index, (label, _)) in StatCounters::default().rows().into_iter().enumerate() {
+            parent.spawn(setting_row_node()).with_children(|row| {
+                row.spawn((Text::new(label), row_font.clone()));
+                row.spawn((
+                    Text::new(""),
+                    row_font.clone(),
+                    TextColor(Color::srgb(0.95, 0.85, 0.45)),
+                    StatValueText(index),
                 ));
-        });
+            });
+        }
+
+        parent
+            .spawn((
+                Button,
+                MenuButton::Back,
+                Node {
+                    margin: UiRect::top(px(8.0)),
+                    ..wide_button_node()
+                },
+                BackgroundColor(BUTTON_NORMAL),
+                BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+            ))
+            .with_child((
+                Text::new("返回"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+    });
 }
 
+fn setting_row_node() -> Node {
+    Node {
+        width: percent(100.0),
+        justify_content: JustifyContent::SpaceBetween,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+fn spawn_small_button(
+    parent: &mut ChildSpawnerCommands,
+    font: &TextFont,
+    label: &str,
+    action: MenuButton,
+) {
+    parent
+        .spawn((
+            Button,
+            action,
+            Node {
+                width: px(32.0),
+                height: px(28.0),
+                border: UiRect::all(px(1.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_NORMAL),
+            BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+        ))
+        .with_child((Text::new(label), font.clone()));
+}
+
+/// Button whose label shows the current value of a setting
+fn spawn_value_button(
+    parent: &mut ChildSpawnerCommands,
+    font: &TextFont,
+    action: MenuButton,
+    row: SettingRow,
+) {
+    parent
+        .spawn((
+            Button,
+            action,
+            Node {
+                width: px(128.0),
+                height: px(28.0),
+                border: UiRect::all(px(1.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_NORMAL),
+            BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+        ))
+        .with_child((Text::new(""), font.clone(), SettingValueText(row)));
+}
+
+/// Shows the live simulation state of the block under the crosshair.
+/// Refreshed every frame because temperature and flags change while the
+/// player keeps looking at the same block
 fn update_voxel_info(
     highlight: Res<HighlightState>,
+    chunks: Chunks,
+    game_mode: Res<GameMode>,
     mut text_q: Query<&mut Text, With<VoxelInfoText>>,
 ) {
-    if !highlight.is_changed() {
-        return;
-    }
     let Ok(mut text) = text_q.single_mut() else {
         return;
     };
     let value = match highlight.current {
         Some(hit) => {
             let def = hit.kind.def();
+            let (chunk_pos, idx) = VoxelWorld::split_world_pos(hit.pos);
+            let chunk = chunks.get(&chunk_pos);
+            let temperature = chunk
+                .map(|chunk| ThermalApi::get_temp(chunk, idx))
+                .unwrap_or(def.props.temperature);
+            let flags = chunk.map(|chunk| chunk.flags.get(idx)).unwrap_or_default();
+            let variant = chunk.map(|chunk| chunk.variant.get(idx)).unwrap_or(0);
+            let states: Vec<&str> = flags.names().collect();
+            let states = if states.is_empty() {
+                "无".to_string()
+            } else {
+                states.join("、")
+            };
+            let warning = match highlight.hazard {
+                Some(hazard) if hazard.too_hot_to_mine(*game_mode) => {
+                    "\n太烫了，无法开采".to_string()
+                }
+                Some(hazard) => format!("\n危险：{}", hazard.label()),
+                None => String::new(),
+            };
             format!(
-                "注视方块：{}\n位置: ({}, {}, {})\n温度: {:.1}°C\n湿度: {:.2}\n硬度: {:.2}\n延展度: {:.2}",
+                "注视方块：{}\n位置: ({}, {}, {})\n温度: {:.1}°C\n湿度: {:.2}\n状态: {}\n变体: {}\n硬度: {:.2}\n延展度: {:.2}{}",
                 def.name,
                 hit.pos.x,
                 hit.pos.y,
                 hit.pos.z,
-                def.props.temperature,
+                temperature,
                 def.props.humidity,
+                states,
+                variant,
                 def.props.hardness,
-                def.props.ductility
+                def.props.ductility,
+                warning
             )
         }
         None => "注视方块：无".to_string(),
     };
-    text.0 = value;
+    if text.0 != value {
+        text.0 = value;
+    }
 }
 
-fn update_seed_info(
-    seed: Res<WorldSeed>,
-    mut text_q: Query<&mut Text, With<SeedInfoText>>,
-    mut initialized: Local<bool>,
-) {
-    if *initialized {
+/// Shows the seed, again when the new world screen picks another one
+fn update_seed_info(seed: Res<WorldSeed>, mut text_q: Query<&mut Text, With<SeedInfoText>>) {
+    if !seed.is_changed() {
         return;
     }
     let Ok(mut text) = text_q.single_mut() else {
         return;
     };
     text.0 = format!("种子: {}", seed.seed);
-    *initialized = true;
 }
 
-fn toggle_exit_menu(
-    keys: Res<ButtonInput<KeyCode>>,
-    mut menu_state: ResMut<MenuState>,
+fn toggle_exit_menu(actions: ActionInput, mut menu_state: ResMut<MenuState>) {
+    if !actions.just_pressed(Action::Pause) {
+        return;
+    }
+
+    // Esc backs out of key capture and the sub-pages before closing the menu
+    if menu_state.rebinding.is_some() {
+        menu_state.rebinding = None;
+        return;
+    }
+    if menu_state.page != MenuPage::Main {
+        menu_state.page = MenuPage::Main;
+        return;
+    }
+
+    menu_state.open = !menu_state.open;
+}
+
+/// Shows or hides the menu and frees or grabs the cursor when the menu opens or closes,
+/// whether from the pause key or a menu button
+fn apply_menu_open(
+    menu_state: Res<MenuState>,
+    mut shown: Local<bool>,
     mut menu_q: Query<&mut Visibility, With<ExitMenuRoot>>,
     mut crosshair_q: Query<&mut Visibility, (With<Crosshair>, Without<ExitMenuRoot>)>,
     mut cursor_options: Single<&mut CursorOptions>,
 ) {
-    if !keys.just_pressed(KeyCode::Escape) {
+    if menu_state.open == *shown {
         return;
     }
-
-    menu_state.open = !menu_state.open;
     let Ok(mut visibility) = menu_q.single_mut() else {
         return;
     };
     let Ok(mut crosshair_visibility) = crosshair_q.single_mut() else {
         return;
     };
+    *shown = menu_state.open;
 
     if menu_state.open {
         *visibility = Visibility::Visible;
@@ -355,12 +945,286 @@ fn exit_button_system(
     }
 }
 
+fn menu_button_system(
+    mut interaction_q: Query<
+        (&Interaction, &MenuButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut menu_state: ResMut<MenuState>,
+    mut settings: ResMut<GameSettings>,
+    mut waypoints: ResMut<Waypoints>,
+    mut teleport: MessageWriter<TeleportPlayer>,
+    mut photo_mode: MessageWriter<EnterPhotoMode>,
+) {
+    for (interaction, button, mut color) in &mut interaction_q {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = BUTTON_HOVER.into();
+                match *button {
+                    MenuButton::OpenSettings => menu_state.page = MenuPage::Settings,
+                    MenuButton::OpenWaypoints => menu_state.page = MenuPage::Waypoints,
+                    MenuButton::OpenStats => menu_state.page = MenuPage::Stats,
+                    MenuButton::EnterPhotoMode => {
+                        photo_mode.write(EnterPhotoMode);
+                        menu_state.open = false;
+                    }
+                    MenuButton::Back => {
+                        menu_state.page = MenuPage::Main;
+                        menu_state.rebinding = None;
+                    }
+                    MenuButton::Adjust(row, step) => adjust_setting(&mut settings, row, step),
+                    MenuButton::ToggleVsync => settings.vsync = !settings.vsync,
+                    MenuButton::CycleGraphicsQuality => {
+                        settings.graphics_quality = settings.graphics_quality.next();
+                    }
+                    MenuButton::ToggleBeacons => {
+                        settings.waypoint_beacons = !settings.waypoint_beacons;
+                    }
+                    MenuButton::Rebind(action) => menu_state.rebinding = Some(action),
+                    MenuButton::Teleport(index) => {
+                        if let Some(waypoint) = waypoints.get(index) {
+                            teleport.write(TeleportPlayer {
+                                feet: waypoint.feet,
+                                yaw: waypoint.yaw,
+                                pitch: waypoint.pitch,
+                            });
+                            menu_state.page = MenuPage::Main;
+                            menu_state.open = false;
+                        }
+                    }
+                    MenuButton::DeleteWaypoint(index) => {
+                        waypoints.remove_at(index);
+                    }
+                }
+            }
+            Interaction::Hovered => *color = BUTTON_HOVER.into(),
+            Interaction::None => *color = BUTTON_NORMAL.into(),
+        }
+    }
+}
+
+fn adjust_setting(settings: &mut GameSettings, row: SettingRow, step: i32) {
+    match row {
+        SettingRow::Sensitivity => {
+            // Snap to whole steps so repeated clicks don't accumulate float error
+            let steps = (settings.look_sensitivity / SENSITIVITY_STEP).round() + step as f32;
+            settings.look_sensitivity =
+                (steps * SENSITIVITY_STEP).clamp(SENSITIVITY_MIN, SENSITIVITY_MAX);
+        }
+        SettingRow::Fov => {
+            settings.fov_degrees =
+                (settings.fov_degrees + step as f32 * FOV_STEP).clamp(FOV_MIN, FOV_MAX);
+        }
+        SettingRow::RenderDistance => {
+            settings.render_distance =
+                (settings.render_distance + step).clamp(RENDER_DISTANCE_MIN, RENDER_DISTANCE_MAX);
+        }
+        SettingRow::SurfaceBudget => {
+            settings.surface_budget = (settings.surface_budget + step).clamp(0, SURFACE_BUDGET_MAX);
+        }
+        SettingRow::MasterVolume => {
+            settings.master_volume = step_volume(settings.master_volume, step);
+        }
+        SettingRow::SfxVolume => {
+            settings.sfx_volume = step_volume(settings.sfx_volume, step);
+        }
+        SettingRow::ParticleBudget => {
+            let budget = settings.particle_budget as i32 + step * PARTICLE_BUDGET_STEP as i32;
+            settings.particle_budget = budget.clamp(0, PARTICLE_BUDGET_MAX as i32) as usize;
+        }
+        SettingRow::Vsync
+        | SettingRow::GraphicsQuality
+        | SettingRow::WaypointBeacons
+        | SettingRow::Key(_) => {}
+    }
+}
+
+/// Moves a volume by whole steps, snapping so the display stays on round percentages
+fn step_volume(volume: f32, step: i32) -> f32 {
+    let steps = (volume / VOLUME_STEP).round() + step as f32;
+    (steps * VOLUME_STEP).clamp(0.0, 1.0)
+}
+
+/// Binds the next key or mouse button pressed while a binding button is waiting
+fn capture_rebind_key(
+    actions: ActionInput,
+    mut menu_state: ResMut<MenuState>,
+    mut settings: ResMut<GameSettings>,
+) {
+    // Wait a frame after the capture starts, otherwise the click on the button is captured
+    if menu_state.is_changed() {
+        return;
+    }
+    let Some(action) = menu_state.rebinding else {
+        return;
+    };
+    let Some(binding) = actions.any_just_pressed() else {
+        return;
+    };
+    // The pause binding is reserved for cancelling, see toggle_exit_menu
+    if actions
+        .bindings()
+        .actions_for(binding)
+        .any(|a| a == Action::Pause)
+    {
+        return;
+    }
+    settings.bindings.set_primary(action, binding);
+    menu_state.rebinding = None;
+}
+
+fn update_menu_page(menu_state: Res<MenuState>, mut panel_q: Query<(&MenuPanel, &mut Node)>) {
+    if !menu_state.is_changed() {
+        return;
+    }
+    for (panel, mut node) in &mut panel_q {
+        node.display = if panel.0 == menu_state.page {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+}
+
+/// Rebuilds the rows of the waypoints page: name and block, then teleport and delete
+/// buttons
+fn update_waypoint_list(
+    mut commands: Commands,
+    waypoints: Res<Waypoints>,
+    settings: Res<GameSettings>,
+    asset_server: Res<AssetServer>,
+    list_q: Query<Entity, With<WaypointList>>,
+) {
+    if !waypoints.is_changed() {
+        return;
+    }
+    let Ok(list) = list_q.single() else {
+        return;
+    };
+    let font = TextFont {
+        font: asset_server.load(UI_FONT_PATH),
+        font_size: 15.0,
+        ..default()
+    };
+
+    commands.entity(list).despawn_related::<Children>();
+    commands.entity(list).with_children(|parent| {
+        if waypoints.is_empty() {
+            let key = settings
+                .bindings
+                .get(Action::SaveWaypoint)
+                .first()
+                .map(|binding| binding.name())
+                .unwrap_or_default();
+            parent.spawn((
+                Text::new(format!("还没有路标，按 {key} 保存当前位置")),
+                font.clone(),
+                TextColor(Color::srgb(0.7, 0.74, 0.82)),
+            ));
+        }
+        for (index, waypoint) in waypoints.iter().enumerate() {
+            let block = waypoint.block();
+            parent.spawn(setting_row_node()).with_children(|row| {
+                row.spawn((
+                    Text::new(format!(
+                        "{}  ({}, {}, {})",
+                        waypoint.name, block.x, block.y, block.z
+                    )),
+                    font.clone(),
+                ));
+                row.spawn(Node {
+                    column_gap: px(8.0),
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|controls| {
+                    controls
+                        .spawn((
+                            Button,
+                            MenuButton::Teleport(index),
+                            Node {
+                                width: px(64.0),
+                                height: px(28.0),
+                                border: UiRect::all(px(1.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            BackgroundColor(BUTTON_NORMAL),
+                            BorderColor::all(Color::srgb(0.55, 0.6, 0.7)),
+                        ))
+                        .with_child((Text::new("传送"), font.clone()));
+                    spawn_small_button(controls, &font, "×", MenuButton::DeleteWaypoint(index));
+                });
+            });
+        }
+    });
+}
+
+/// Refreshes the stats page while it's open
+fn update_stat_values(
+    stats: Res<WorldStats>,
+    menu_state: Res<MenuState>,
+    mut text_q: Query<(&StatValueText, &mut Text)>,
+) {
+    if menu_state.page != MenuPage::Stats || (!stats.is_changed() && !menu_state.is_changed()) {
+        return;
+    }
+    let rows = stats.counters.rows();
+    for (value, mut text) in &mut text_q {
+        if let Some((_, row)) = rows.get(value.0)
+            && text.0 != *row
+        {
+            text.0.clone_from(row);
+        }
+    }
+}
+
+fn update_setting_values(
+    settings: Res<GameSettings>,
+    menu_state: Res<MenuState>,
+    mut text_q: Query<(&SettingValueText, &mut Text)>,
+) {
+    if !settings.is_changed() && !menu_state.is_changed() {
+        return;
+    }
+    for (value, mut text) in &mut text_q {
+        text.0 = match value.0 {
+            SettingRow::Sensitivity => format!("{:.1}", settings.look_sensitivity * 1000.0),
+            SettingRow::Fov => format!("{:.0}°", settings.fov_degrees),
+            SettingRow::RenderDistance => format!("{} 区块", settings.render_distance),
+            SettingRow::SurfaceBudget => format!("{} 层", settings.surface_budget),
+            SettingRow::MasterVolume => format!("{:.0}%", settings.master_volume * 100.0),
+            SettingRow::SfxVolume => format!("{:.0}%", settings.sfx_volume * 100.0),
+            SettingRow::ParticleBudget => settings.particle_budget.to_string(),
+            SettingRow::Vsync => if settings.vsync { "开" } else { "关" }.to_string(),
+            SettingRow::GraphicsQuality => settings.graphics_quality.label().to_string(),
+            SettingRow::WaypointBeacons => if settings.waypoint_beacons {
+                "开"
+            } else {
+                "关"
+            }
+            .to_string(),
+            SettingRow::Key(action) if menu_state.rebinding == Some(action) => {
+                "按下新按键...".to_string()
+            }
+            SettingRow::Key(action) => settings
+                .bindings
+                .get(action)
+                .iter()
+                .map(|binding| binding.name())
+                .collect::<Vec<_>>()
+                .join(" / "),
+        };
+    }
+}
+
 fn toggle_debug_overlay(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: ActionInput,
     mut debug_state: ResMut<DebugOverlayState>,
     mut overlay_q: Query<&mut Visibility, With<DebugOverlay>>,
 ) {
-    if !keys.just_pressed(KeyCode::F3) {
+    if !actions.just_pressed(Action::ToggleDebugOverlay) {
         return;
     }
 
@@ -380,7 +1244,7 @@ fn update_debug_overlay(
     debug_state: Res<DebugOverlayState>,
     mut text_q: Query<&mut Text, With<DebugText>>,
     camera_q: Query<(&Transform, &crate::player::LookAngles), With<crate::player::PlayerCamera>>,
-    world: Res<crate::voxel::VoxelWorld>,
+    stats: DebugWorldStats,
     time: Res<Time>,
     diagnostics: Res<bevy::diagnostic::DiagnosticsStore>,
 ) {
@@ -397,11 +1261,7 @@ fn update_debug_overlay(
     };
 
     let pos = transform.translation;
-    let chunk_pos = crate::voxel::ChunkPos::from_world_pos(
-        pos.x as i32,
-        pos.y as i32,
-        pos.z as i32,
-    );
+    let chunk_pos = crate::voxel::ChunkPos::containing(pos);
 
     // Get FPS from diagnostics
     let fps = diagnostics
@@ -409,17 +1269,68 @@ fn update_debug_overlay(
         .and_then(|fps| fps.smoothed())
         .unwrap_or(0.0);
 
+    let world = &stats.world;
+    let chunks = &stats.chunks;
+
     // 计算渲染统计
     let rendered_chunks = world.loaded_chunks.len(); // 实际渲染的chunk数（有mesh的）
-    let total_chunks = world.chunks.len(); // 所有生成的chunk数
+    let total_chunks = chunks.len(); // 所有生成的chunk数
     let culled_chunks = total_chunks - rendered_chunks; // 被剔除的chunk数（空气或完全被包围）
 
+    // 模拟统计
+    let (active_thermal, active_burning, total_active) =
+        chunks
+            .iter()
+            .fold((0, 0, 0), |(thermal, burning, total), (_, chunk)| {
+                (
+                    thermal + chunk.active_thermal.len(),
+                    burning + chunk.active_burning.len(),
+                    total + chunk.active_count(),
+                )
+            });
+
+    // 体素存储统计（调色板压缩后）
+    let storage_bytes: usize = chunks.iter().map(|(_, chunk)| chunk.storage_size()).sum();
+    let uniform_chunks = chunks
+        .iter()
+        .filter(|(_, chunk)| chunk.voxels.uniform_value().is_some())
+        .count();
+
+    // 玩家所在列的环境：生物群系和气候来自地形生成器，温度来自热力学模拟
+    let block = pos.floor().as_ivec3();
+    let generator = stats.terrain.generator();
+    let column = generator.sample_column(block.x, block.z);
+    let (climate_temp, climate_humid) = generator.climate(block.x, block.z);
+    let (block_chunk, idx) = VoxelWorld::split_world_pos(block);
+    let air_temp = chunks
+        .get(&block_chunk)
+        .map(|chunk| ThermalApi::get_temp(chunk, idx))
+        .unwrap_or(ENV_TEMPERATURE);
+    let minutes = (stats.game_clock.time_of_day * 24.0 * 60.0) as u32;
+
+    // 分阶段耗时，工作线程上的阶段是各线程之和
+    let stage_timings: String = Stage::ALL
+        .into_iter()
+        .map(|stage| {
+            let stage_stats = stats.timings.get(stage);
+            format!(
+                "\n  {}: {:.2}ms x{:.1} (max {:.2}ms)",
+                stage.label(),
+                stage_stats.ms_per_frame,
+                stage_stats.calls_per_frame,
+                stage_stats.max_ms
+            )
+        })
+        .collect();
+
     text.0 = format!(
         "Voxworld Debug (F3 to toggle)\n\
         \n\
         FPS: {:.1}\n\
         Frame Time: {:.2}ms\n\
         \n\
+        Seed: {}\n\
+        \n\
         Position: {:.2}, {:.2}, {:.2}\n\
         Chunk: ({}, {}, {})\n\
         \n\
@@ -427,20 +1338,63 @@ fn update_debug_overlay(
           Yaw: {:.2}°\n\
           Pitch: {:.2}°\n\
         \n\
+        Environment:\n\
+          Biome: {:?}\n\
+          Surface Height: {}\n\
+          Climate: temperature {:+.2}, humidity {:.0}%\n\
+          Temperature: {:.1}°C (ambient {:.0}°C)\n\
+          Time: day {} {:02}:{:02}\n\
+        \n\
         World:\n\
           Rendered Chunks: {} (with geometry)\n\
           Culled Chunks: {} (empty/enclosed)\n\
           Total Chunks: {}\n\
-          Draw Calls: ~{}",
+          Draw Calls: ~{}\n\
+          Voxel Storage: {:.1} KiB ({} uniform)\n\
+        \n\
+        Tasks:\n\
+          Generating: {} (queued {}, cancelled {})\n\
+          Remeshing: {} (backlog {}{})\n\
+        \n\
+        Simulation:\n\
+          Clock: {}\n\
+          Active Thermal: {}\n\
+          Burning: {}\n\
+          Total Active: {}\n\
+        \n\
+        Stage Timings (per frame):{}",
         fps,
         time.delta_secs() * 1000.0,
+        stats.seed.seed,
         pos.x, pos.y, pos.z,
         chunk_pos.x, chunk_pos.y, chunk_pos.z,
         angles.yaw.to_degrees(),
         angles.pitch.to_degrees(),
+        column.biome,
+        column.height,
+        climate_temp,
+        (climate_humid + 1.0) * 50.0,
+        air_temp,
+        ENV_TEMPERATURE,
+        stats.game_clock.day,
+        minutes / 60,
+        minutes % 60,
         rendered_chunks,
         culled_chunks,
         total_chunks,
         rendered_chunks, // 估计的drawcall数（每个chunk约1个）
+        storage_bytes as f32 / 1024.0,
+        uniform_chunks,
+        stats.chunk_tasks.len(),
+        stats.queue.to_load.len(),
+        stats.queue.cancelled_tasks,
+        stats.chunk_tasks.remesh_count(),
+        stats.remesh.backlog,
+        if stats.remesh.idle { ", idle" } else { "" },
+        stats.clock.status(),
+        active_thermal,
+        active_burning,
+        total_active,
+        stage_timings,
     );
 }
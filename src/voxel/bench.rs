@@ -0,0 +1,296 @@
+//! 性能基准工作负载
+//!
+//! criterion 基准（`benches/world.rs`）和 `--bench-world [半径]` 启动参数共用这里的工作负载，
+//! 为网格合并、调色板等优化提供可追踪的基线：
+//!
+//! - 地形生成：`TerrainGenerator::generate_chunk`
+//! - 网格构建：`build_chunk_mesh_async`，分别使用空边界（首次生成）和真实相邻边界（重建），
+//!   以及只包含地下密实区块的重建（衡量不透明位掩码跳过被包围的层和体素的效果）
+//! - 命令提交：大量命令下 `commit_system` 的吞吐量
+//!
+//! 地形使用资源目录中的世界生成配置和结构模板，与游戏和预生成一致。
+//! `--bench-world` 在单线程上依次运行各项，结果以每秒区块数（命令为每秒命令数）报告。
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use crate::voxel::chunk::{spawn_chunk, ChunkData, ChunkIndex, ChunkPos};
+use crate::voxel::domains::command::{commit_system, CommandQueue, DomainCommand};
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::loading::{MeshBuildInput, NeighborEdges};
+use crate::voxel::mesh_gen::build_chunk_mesh_async;
+use crate::voxel::pregen::{
+    plan_regions, read_structure_templates, read_worldgen_config, vertical_chunk_range, ASSETS_DIR,
+};
+use crate::voxel::seed::WorldSeed;
+use crate::voxel::solid_mask::SolidMask;
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// criterion 基准使用的固定种子，`--bench-world` 需要可比较的结果时用 `--seed` 指定同一种子
+pub const BENCH_SEED: u32 = 12345;
+/// 未指定半径时的水平半径（区块）
+pub const DEFAULT_BENCH_RADIUS: i32 = 2;
+/// 命令提交基准中每个 tick 提交的命令数
+pub const COMMIT_BATCH: usize = 20_000;
+/// `--bench-world` 运行的命令提交 tick 数
+const COMMIT_TICKS: usize = 10;
+/// 不透明体素至少占这个比例的区块算作地下密实区块
+const DENSE_FRACTION: f32 = 0.75;
+
+/// 基准世界：原点周围水平半径内、地形可能到达的高度范围内的全部区块
+pub struct BenchWorld {
+    terrain: SharedTerrain,
+    positions: Vec<ChunkPos>,
+    chunks: HashMap<ChunkPos, ChunkData>,
+}
+
+impl BenchWorld {
+    /// 读取资源目录中的配置并预先生成全部区块（用于网格和命令基准）
+    pub fn new(seed: u32, radius: i32) -> Self {
+        let assets_dir = Path::new(ASSETS_DIR);
+        let config = read_worldgen_config(assets_dir);
+        let structures = read_structure_templates(assets_dir);
+        let y_range = vertical_chunk_range(&config, &structures);
+        let terrain = SharedTerrain::new(WorldSeed::new(seed), config, Arc::new(structures));
+        let positions: Vec<ChunkPos> = plan_regions(radius.max(0), y_range)
+            .into_iter()
+            .flat_map(|(_, chunks)| chunks)
+            .collect();
+
+        let mut chunks = HashMap::new();
+        let generator = terrain.generator();
+        for &chunk_pos in &positions {
+            let mut chunk = generator.generate_chunk(chunk_pos);
+            chunk.compact();
+            chunks.insert(chunk_pos, chunk);
+        }
+
+        Self {
+            terrain,
+            positions,
+            chunks,
+        }
+    }
+
+    /// 基准世界中的全部区块位置
+    pub fn positions(&self) -> &[ChunkPos] {
+        &self.positions
+    }
+
+    /// 重新生成全部区块，返回生成的区块数
+    pub fn generate_all(&self) -> usize {
+        let generator = self.terrain.generator();
+        for &chunk_pos in &self.positions {
+            black_box(generator.generate_chunk(chunk_pos));
+        }
+        self.positions.len()
+    }
+
+    /// 非空区块的网格构建输入
+    ///
+    /// with_neighbors 为 false 时与首次生成一样使用空边界，为 true 时与重建一样
+    /// 使用相邻区块的真实边界
+    pub fn mesh_inputs(&self, with_neighbors: bool) -> Vec<MeshBuildInput> {
+        self.positions
+            .iter()
+            .filter_map(|&chunk_pos| {
+                let chunk = self.chunks.get(&chunk_pos)?;
+                (!chunk.is_empty()).then(|| self.mesh_input(chunk_pos, chunk, with_neighbors))
+            })
+            .collect()
+    }
+
+    /// 地下密实区块（不透明体素至少占 [`DENSE_FRACTION`]）使用真实相邻边界的网格构建输入
+    pub fn dense_mesh_inputs(&self) -> Vec<MeshBuildInput> {
+        let min_solid = (ChunkData::VOXEL_COUNT as f32 * DENSE_FRACTION) as usize;
+        self.positions
+            .iter()
+            .filter_map(|&chunk_pos| {
+                let chunk = self.chunks.get(&chunk_pos)?;
+                let solid = chunk
+                    .voxels
+                    .iter()
+                    .filter(|kind| !kind.is_transparent())
+                    .count();
+                (solid >= min_solid).then(|| self.mesh_input(chunk_pos, chunk, true))
+            })
+            .collect()
+    }
+
+    fn mesh_input(
+        &self,
+        chunk_pos: ChunkPos,
+        chunk: &ChunkData,
+        with_neighbors: bool,
+    ) -> MeshBuildInput {
+        let (neighbor_edges, neighbor_light) = if with_neighbors {
+            (
+                NeighborEdges::from_world(&self.chunks, chunk_pos),
+                NeighborEdges::light_from_world(&self.chunks, chunk_pos),
+            )
+        } else {
+            (NeighborEdges::default(), NeighborEdges::default())
+        };
+        MeshBuildInput {
+            chunk_pos,
+            voxels: Arc::new(chunk.voxels.to_vec()),
+            solid: SolidMask::from_chunk(chunk),
+            variants: Arc::new(chunk.variant.to_vec()),
+            flags: Arc::new(chunk.flags.to_vec()),
+            light: Arc::new(chunk.light.to_vec()),
+            neighbor_edges,
+            neighbor_light,
+        }
+    }
+
+    /// 一个 tick 的命令负载：随机分布在全部区块中的放置方块、加热、变体和标志位命令，
+    /// 同一种子下结果固定
+    pub fn commit_commands(&self, count: usize) -> Vec<(ChunkPos, DomainCommand)> {
+        let mut state: u32 = 0x9E37_79B9;
+        (0..count)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                let chunk_pos = self.positions[state as usize % self.positions.len()];
+                let idx = (state >> 7) as usize % ChunkData::VOXEL_COUNT;
+                let command = match i % 4 {
+                    0 => DomainCommand::SetBlock {
+                        idx,
+                        new_voxel: VoxelKind::Stone,
+                    },
+                    1 => DomainCommand::AddHeat { idx, heat: 5000.0 },
+                    2 => DomainCommand::SetVariant { idx, variant: 1 },
+                    _ => DomainCommand::AddFlag {
+                        idx,
+                        flag: VoxelFlags::WET,
+                    },
+                };
+                (chunk_pos, command)
+            })
+            .collect()
+    }
+
+    /// 以基准世界区块副本为状态的命令提交基准
+    pub fn commit_bench(&self, count: usize) -> CommitBench {
+        let mut world = World::new();
+        world.init_resource::<ChunkIndex>();
+        for (&chunk_pos, chunk) in &self.chunks {
+            spawn_chunk(&mut world, chunk_pos, chunk.clone());
+        }
+        world.spawn(CommandQueue::default());
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(commit_system);
+
+        CommitBench {
+            world,
+            schedule,
+            commands: self.commit_commands(count),
+        }
+    }
+}
+
+/// 在独立的 ECS 世界中反复运行 commit_system
+pub struct CommitBench {
+    world: World,
+    schedule: Schedule,
+    commands: Vec<(ChunkPos, DomainCommand)>,
+}
+
+impl CommitBench {
+    /// 每个 tick 提交的命令数
+    pub fn batch_size(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// 提交一个 tick 的命令并运行 commit_system
+    pub fn run_tick(&mut self) {
+        let mut queues = self.world.query::<&mut CommandQueue>();
+        for mut queue in queues.iter_mut(&mut self.world) {
+            for (chunk_pos, command) in &self.commands {
+                queue.push(*chunk_pos, command.clone());
+            }
+        }
+        self.schedule.run(&mut self.world);
+
+        // 变更日志和脏记录在游戏中由同步和网格系统消费，这里直接清空，避免逐 tick 累积
+        let mut chunks = self.world.query::<&mut ChunkData>();
+        for mut chunk in chunks.iter_mut(&mut self.world) {
+            chunk.changes.clear();
+            chunk.dirty_blocks.clear();
+        }
+    }
+}
+
+/// `--bench-world` 的测量结果
+#[derive(Debug, Clone, Copy)]
+pub struct BenchReport {
+    /// 生成的区块数
+    pub chunks: usize,
+    pub generate: Duration,
+    /// 构建网格的非空区块数
+    pub meshed_chunks: usize,
+    pub mesh_without_neighbors: Duration,
+    pub mesh_with_neighbors: Duration,
+    /// 构建网格的地下密实区块数
+    pub dense_chunks: usize,
+    pub mesh_dense: Duration,
+    /// 提交的命令总数
+    pub commands: usize,
+    pub commit: Duration,
+}
+
+/// 每秒处理的数量
+pub fn per_second(count: usize, elapsed: Duration) -> f64 {
+    count as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+/// 单线程依次运行全部基准
+pub fn run_bench_world(seed: u32, radius: i32) -> BenchReport {
+    let bench = BenchWorld::new(seed, radius);
+
+    let start = Instant::now();
+    bench.generate_all();
+    let generate = start.elapsed();
+
+    let mesh_time = |inputs: Vec<MeshBuildInput>| {
+        let start = Instant::now();
+        for input in inputs {
+            black_box(build_chunk_mesh_async(input));
+        }
+        start.elapsed()
+    };
+    let without_neighbors = bench.mesh_inputs(false);
+    let meshed_chunks = without_neighbors.len();
+    let mesh_without_neighbors = mesh_time(without_neighbors);
+    let mesh_with_neighbors = mesh_time(bench.mesh_inputs(true));
+    let dense = bench.dense_mesh_inputs();
+    let dense_chunks = dense.len();
+    let mesh_dense = mesh_time(dense);
+
+    let mut commit_bench = bench.commit_bench(COMMIT_BATCH);
+    let start = Instant::now();
+    for _ in 0..COMMIT_TICKS {
+        commit_bench.run_tick();
+    }
+    let commit = start.elapsed();
+
+    BenchReport {
+        chunks: bench.positions().len(),
+        generate,
+        meshed_chunks,
+        mesh_without_neighbors,
+        mesh_with_neighbors,
+        dense_chunks,
+        mesh_dense,
+        commands: COMMIT_BATCH * COMMIT_TICKS,
+        commit,
+    }
+}
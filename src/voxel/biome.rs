@@ -1,9 +1,11 @@
 //! 生物群系定义
 
+use serde::{Deserialize, Serialize};
+
 use crate::voxel::voxel_kind::VoxelKind;
 
 /// 生物群系类型 - 决定地形的表面方块、植被和环境特征
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Biome {
     Plains,
     Forest,
@@ -14,6 +16,9 @@ pub enum Biome {
     Ocean,
     Beach,
     FloatingIslands, // 浮空岛生物群系
+    Mountains,
+    Swamp,
+    River,
 }
 
 impl Biome {
@@ -24,9 +29,12 @@ impl Biome {
             Biome::Desert => VoxelKind::Sand,
             Biome::Snowy => VoxelKind::Snow,
             Biome::Taiga => VoxelKind::Grass,
-            Biome::Ocean => VoxelKind::Gravel,
+            Biome::Ocean => VoxelKind::Gravel, // 海底按水深从沙子过渡到黏土，由地形生成器决定
             Biome::Beach => VoxelKind::Sand,
             Biome::FloatingIslands => VoxelKind::Grass, // 浮空岛顶部是草地
+            Biome::Mountains => VoxelKind::Grass, // 山脚是草地，高处的岩石和积雪由地形生成器按海拔决定
+            Biome::Swamp => VoxelKind::SwampGrass,
+            Biome::River => VoxelKind::Sand, // 河床较深处为砂砾，由地形生成器按深度决定
         }
     }
 
@@ -38,6 +46,9 @@ impl Biome {
             Biome::Snowy => VoxelKind::Dirt,
             Biome::Ocean => VoxelKind::Clay,
             Biome::FloatingIslands => VoxelKind::Dirt, // 浮空岛次表层是泥土
+            Biome::Mountains => VoxelKind::Dirt,
+            Biome::Swamp => VoxelKind::Clay,
+            Biome::River => VoxelKind::Gravel,
         }
     }
 }
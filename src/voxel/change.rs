@@ -3,6 +3,7 @@
 /// 用于 diff 日志，支持网络同步和存档
 use super::flags::VoxelFlags;
 use super::voxel_kind::VoxelKind;
+use std::ops::Range;
 
 /// 单个方块的变更操作
 #[derive(Clone, Debug, PartialEq)]
@@ -29,10 +30,19 @@ pub enum BlockChange {
 
     /// 湿度变化
     SetMoisture { idx: usize, moisture: f32 },
+
+    /// 区域操作：从 start 开始连续 len 个方块都变为 new
+    ///
+    /// 这些方块的变体归零、相变状态清除，不再单独记录
+    FillRun {
+        start: usize,
+        len: usize,
+        new: VoxelKind,
+    },
 }
 
 impl BlockChange {
-    /// 获取影响的方块索引
+    /// 获取影响的方块索引（FillRun 返回第一个方块）
     pub fn idx(&self) -> usize {
         match self {
             BlockChange::SetVoxel { idx, .. } => *idx,
@@ -40,15 +50,60 @@ impl BlockChange {
             BlockChange::SetVariant { idx, .. } => *idx,
             BlockChange::SetTemp { idx, .. } => *idx,
             BlockChange::SetMoisture { idx, .. } => *idx,
+            BlockChange::FillRun { start, .. } => *start,
+        }
+    }
+
+    /// 获取影响的所有方块索引
+    pub fn indices(&self) -> Range<usize> {
+        match self {
+            BlockChange::FillRun { start, len, .. } => *start..*start + *len,
+            _ => self.idx()..self.idx() + 1,
         }
     }
 
     /// 判断是否需要重建网格
     pub fn needs_remesh(&self) -> bool {
-        matches!(
-            self,
-            BlockChange::SetVoxel { .. } | BlockChange::SetVariant { .. }
-        )
+        match self {
+            BlockChange::SetVoxel { .. }
+            | BlockChange::SetVariant { .. }
+            | BlockChange::FillRun { .. } => true,
+            BlockChange::SetFlag { flag, .. } => flag.intersects(VoxelFlags::VISUAL),
+            BlockChange::SetTemp { .. } | BlockChange::SetMoisture { .. } => false,
+        }
+    }
+
+    /// 变更是否会影响相邻区块的网格（改变方块形状或透明度）
+    ///
+    /// 标志位只改变方块自身的颜色，不影响相邻方块的面剔除和环境光遮蔽
+    pub fn affects_neighbors(&self) -> bool {
+        !matches!(self, BlockChange::SetFlag { .. })
+    }
+
+    /// 撤销这条变更的反向变更
+    ///
+    /// 只有记录了原值的变更可以撤销；温度、湿度和 FillRun 不记录原值，返回 None
+    pub fn inverse(&self) -> Option<BlockChange> {
+        match *self {
+            BlockChange::SetVoxel { idx, old, new } => Some(BlockChange::SetVoxel {
+                idx,
+                old: new,
+                new: old,
+            }),
+            BlockChange::SetFlag { idx, flag, set } => Some(BlockChange::SetFlag {
+                idx,
+                flag,
+                set: !set,
+            }),
+            BlockChange::SetVariant { idx, old, new } => Some(BlockChange::SetVariant {
+                idx,
+                old: new,
+                new: old,
+            }),
+            BlockChange::SetTemp { .. }
+            | BlockChange::SetMoisture { .. }
+            | BlockChange::FillRun { .. } => None,
+        }
     }
 }
 
@@ -77,5 +132,64 @@ mod tests {
 
         let change2 = BlockChange::SetTemp { idx: 0, temp: 100.0 };
         assert!(!change2.needs_remesh());
+
+        let ignite = BlockChange::SetFlag {
+            idx: 0,
+            flag: VoxelFlags::BURNING,
+            set: true,
+        };
+        assert!(ignite.needs_remesh());
+        assert!(!ignite.affects_neighbors());
+
+        // 潮湿的方块颜色变深，需要重建网格；高温只影响模拟
+        let wet = BlockChange::SetFlag {
+            idx: 0,
+            flag: VoxelFlags::WET,
+            set: true,
+        };
+        assert!(wet.needs_remesh());
+        let hot = BlockChange::SetFlag {
+            idx: 0,
+            flag: VoxelFlags::HOT,
+            set: true,
+        };
+        assert!(!hot.needs_remesh());
+    }
+
+    #[test]
+    fn test_fill_run_indices() {
+        let run = BlockChange::FillRun {
+            start: 16,
+            len: 4,
+            new: VoxelKind::Stone,
+        };
+        assert_eq!(run.idx(), 16);
+        assert_eq!(run.indices(), 16..20);
+        assert!(run.needs_remesh());
+
+        let single = BlockChange::SetTemp { idx: 7, temp: 0.0 };
+        assert_eq!(single.indices(), 7..8);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let change = BlockChange::SetVoxel {
+            idx: 3,
+            old: VoxelKind::Stone,
+            new: VoxelKind::Air,
+        };
+        let inverse = change.inverse().unwrap();
+        assert_eq!(
+            inverse,
+            BlockChange::SetVoxel {
+                idx: 3,
+                old: VoxelKind::Air,
+                new: VoxelKind::Stone,
+            }
+        );
+        assert_eq!(inverse.inverse(), Some(change));
+
+        let heat = BlockChange::SetTemp { idx: 0, temp: 0.0 };
+        assert!(heat.inverse().is_none());
     }
 }
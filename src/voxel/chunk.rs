@@ -1,17 +1,27 @@
 //! 区块数据结构
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use std::collections::{HashMap, HashSet};
+use std::ops::DerefMut;
 
 use crate::voxel::change::BlockChange;
 use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::domains::phase::PhaseState;
 use crate::voxel::domains::thermal::ThermalState;
 use crate::voxel::flags::VoxelFlags;
+use crate::voxel::heightmap::Heightmap;
+use crate::voxel::light::DEFAULT_LIGHT;
+use crate::voxel::palette::PalettedArray;
+use crate::voxel::registry::VoxelRegistry;
 use crate::voxel::voxel_kind::VoxelKind;
 
 /// 区块坐标 - 用于标识世界中区块的位置
 /// 注意：这是区块坐标，不是体素（方块）坐标
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// 排序按 x、y、z 依次比较，用于需要确定遍历顺序的地方（命令提交、世界摘要）
+///
+/// 同时是区块数据实体上的组件，和 [`ChunkData`] 一起组成一个区块
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ChunkPos {
     pub x: i32,
     pub y: i32,
@@ -34,6 +44,13 @@ impl ChunkPos {
         }
     }
 
+    /// 包含浮点世界坐标（如摄像机位置）所在方块的区块
+    /// 先向下取整：直接截断会把 (-1, 0) 之间的坐标算进 0 号区块
+    pub fn containing(pos: Vec3) -> Self {
+        let block = pos.floor().as_ivec3();
+        Self::from_world_pos(block.x, block.y, block.z)
+    }
+
     /// 获取区块在世界坐标系中的起始位置（3D原点）
     pub fn world_origin(&self) -> IVec3 {
         IVec3::new(
@@ -63,38 +80,65 @@ pub struct ChunkMarker {
     pub pos: ChunkPos,
 }
 
+/// 区块网格分段组件 - 区块实体的子实体，不透明、透明、水和自发光部分分别使用各自的材质
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkSection {
+    Opaque,
+    Transparent,
+    Water,
+    Emissive,
+}
+
 /// 区块数据 - 存储区块内所有体素的类型数据和状态
+///
+/// 每个已生成的区块是一个带 [`ChunkPos`] 和 `ChunkData` 的实体，按坐标查找走 [`ChunkIndex`]
+#[derive(Component)]
 pub struct ChunkData {
     // === 基础数据 ===
     /// 体素数组，大小为 CHUNK_SIZE³ = 4096
-    /// 使用一维数组存储三维数据，通过 index() 函数计算索引
-    pub voxels: Vec<VoxelKind>,
+    /// 使用一维数组存储三维数据，通过 index() 函数计算索引；调色板压缩存储
+    pub voxels: PalettedArray<VoxelKind>,
     /// 脏标记 - 标识区块是否被修改，需要重新生成网格
     pub is_dirty: bool,
+    /// 区块是否与地形生成结果不同（玩家修改或模拟产生过变化），卸载时需要保留
+    pub is_modified: bool,
 
-    // === 通用状态（轻量，全量存储）===
+    // === 通用状态（调色板压缩，大多数区块只有一种值）===
     /// 每个方块的状态标志位
-    pub flags: Vec<VoxelFlags>,
+    pub flags: PalettedArray<VoxelFlags>,
     /// 每个方块的变体/阶段（水位、生长阶段等，0-255）
-    pub variant: Vec<u8>,
+    pub variant: PalettedArray<u8>,
+    /// 每个方块的光照（高 4 位天空光，低 4 位方块光，见 [`light`](crate::voxel::light)）
+    pub light: PalettedArray<u8>,
 
     // === 专用状态（稀疏，按需分配）===
     /// 温度场状态（稀疏存储）
     pub thermal_state: Option<ThermalState>,
+    /// 相变进度状态（稀疏存储）
+    pub phase_state: Option<PhaseState>,
     // TODO: 后续添加
     // pub moisture_state: Option<MoistureState>,
     // pub combustion_state: Option<CombustionState>,
-    // pub phase_state: Option<PhaseState>,
 
     // === 活跃集合（驱动计算）===
     /// 活跃的温度变化方块索引
     pub active_thermal: HashSet<usize>,
     /// 正在燃烧的方块索引
     pub active_burning: HashSet<usize>,
+    /// 持续产热的方块索引（熔岩等）
+    pub active_heat_sources: HashSet<usize>,
     /// 正在冻结的方块索引
     pub active_freezing: HashSet<usize>,
     /// 正在融化的方块索引
     pub active_melting: HashSet<usize>,
+    /// 需要检查流动的流体方块索引
+    pub active_fluid: HashSet<usize>,
+    /// 需要检查支撑的重力方块索引
+    pub active_falling: HashSet<usize>,
+    /// 相邻方块被移除、需要检查结构支撑的方块索引
+    pub active_support: HashSet<usize>,
+    /// 接触水分、正在腐蚀的金属方块索引
+    pub active_corrosion: HashSet<usize>,
 
     // === 渲染与同步 ===
     /// 变化的方块索引列表（用于增量更新）
@@ -112,23 +156,34 @@ impl ChunkData {
     /// 创建一个空的区块数据，所有体素初始化为空气
     pub fn new() -> Self {
         Self {
-            voxels: vec![VoxelKind::Air; Self::VOXEL_COUNT],
+            voxels: PalettedArray::filled(Self::VOXEL_COUNT, VoxelKind::Air),
             is_dirty: true,
-            flags: vec![VoxelFlags::NONE; Self::VOXEL_COUNT],
-            variant: vec![0; Self::VOXEL_COUNT],
+            is_modified: false,
+            flags: PalettedArray::filled(Self::VOXEL_COUNT, VoxelFlags::NONE),
+            variant: PalettedArray::filled(Self::VOXEL_COUNT, 0),
+            light: PalettedArray::filled(Self::VOXEL_COUNT, DEFAULT_LIGHT),
             thermal_state: None,
+            phase_state: None,
             active_thermal: HashSet::new(),
             active_burning: HashSet::new(),
+            active_heat_sources: HashSet::new(),
             active_freezing: HashSet::new(),
             active_melting: HashSet::new(),
+            active_fluid: HashSet::new(),
+            active_falling: HashSet::new(),
+            active_support: HashSet::new(),
+            active_corrosion: HashSet::new(),
             dirty_blocks: Vec::new(),
             needs_remesh: false,
             changes: Vec::new(),
         }
     }
 
-    /// 清空变更日志
+    /// 清空变更日志（有过变更的区块标记为已修改）
     pub fn clear_changes(&mut self) {
+        if !self.changes.is_empty() {
+            self.is_modified = true;
+        }
         self.dirty_blocks.clear();
         self.changes.clear();
         self.needs_remesh = false;
@@ -138,8 +193,13 @@ impl ChunkData {
     pub fn active_count(&self) -> usize {
         self.active_thermal.len()
             + self.active_burning.len()
+            + self.active_heat_sources.len()
             + self.active_freezing.len()
             + self.active_melting.len()
+            + self.active_fluid.len()
+            + self.active_falling.len()
+            + self.active_support.len()
+            + self.active_corrosion.len()
     }
 
     /// 将三维坐标转换为一维数组索引
@@ -155,7 +215,7 @@ impl ChunkData {
         if x < 0 || x >= CHUNK_SIZE || y < 0 || y >= CHUNK_SIZE || z < 0 || z >= CHUNK_SIZE {
             return VoxelKind::Air;
         }
-        self.voxels[Self::index(x, y, z)]
+        self.voxels.get(Self::index(x, y, z))
     }
 
     /// 设置指定位置的体素类型
@@ -165,20 +225,39 @@ impl ChunkData {
         if x < 0 || x >= CHUNK_SIZE || y < 0 || y >= CHUNK_SIZE || z < 0 || z >= CHUNK_SIZE {
             return;
         }
-        self.voxels[Self::index(x, y, z)] = kind;
+        self.voxels.set(Self::index(x, y, z), kind);
         self.is_dirty = true;
     }
 
     /// 检查区块是否完全为空气
     /// 用于优化：空气区块不需要生成网格
     pub fn is_empty(&self) -> bool {
-        self.voxels.iter().all(|&kind| kind == VoxelKind::Air)
+        self.voxels.iter().all(|kind| kind == VoxelKind::Air)
     }
 
-    /// 检查区块是否完全不透明（所有体素都是实心方块）
+    /// 检查区块是否完全不透明（所有体素都是不透明的整格方块）
     /// 用于优化：完全被包围的不透明区块不需要生成网格
     pub fn is_fully_opaque(&self) -> bool {
-        self.voxels.iter().all(|&kind| !kind.is_transparent())
+        let registry = VoxelRegistry::current();
+        // 调色板中只有不透明的整格方块时不必逐个检查
+        !self.voxels.may_contain(|kind| !registry.occludes(kind))
+            || self.voxels.iter().all(|kind| registry.occludes(kind))
+    }
+
+    /// 移除调色板中不再使用的值，单一内容的数组退回单值存储
+    pub fn compact(&mut self) {
+        self.voxels.compact();
+        self.flags.compact();
+        self.variant.compact();
+        self.light.compact();
+    }
+
+    /// 体素、标志位、变体和光照存储占用的堆内存（字节）
+    pub fn storage_size(&self) -> usize {
+        self.voxels.heap_size()
+            + self.flags.heap_size()
+            + self.variant.heap_size()
+            + self.light.heap_size()
     }
 }
 
@@ -193,13 +272,21 @@ impl Clone for ChunkData {
         Self {
             voxels: self.voxels.clone(),
             is_dirty: self.is_dirty,
+            is_modified: self.is_modified,
             flags: self.flags.clone(),
             variant: self.variant.clone(),
+            light: self.light.clone(),
             thermal_state: self.thermal_state.clone(),
+            phase_state: self.phase_state.clone(),
             active_thermal: self.active_thermal.clone(),
             active_burning: self.active_burning.clone(),
+            active_heat_sources: self.active_heat_sources.clone(),
             active_freezing: self.active_freezing.clone(),
             active_melting: self.active_melting.clone(),
+            active_fluid: self.active_fluid.clone(),
+            active_falling: self.active_falling.clone(),
+            active_support: self.active_support.clone(),
+            active_corrosion: self.active_corrosion.clone(),
             dirty_blocks: self.dirty_blocks.clone(),
             needs_remesh: self.needs_remesh,
             changes: self.changes.clone(),
@@ -207,40 +294,390 @@ impl Clone for ChunkData {
     }
 }
 
-/// 体素世界 - 管理整个世界的所有区块
+/// 体素世界 - 管理区块的渲染实体和高度图
+///
+/// 区块数据在各自的实体上（见 [`ChunkData`]），系统通过 [`Chunks`]、[`ChunksMut`] 读写，
+/// 通过 [`ChunkEntities`] 加入和移除区块。
+/// 外部修改方块应使用 [`WorldEditApi`](crate::voxel::domains::edit::WorldEditApi)，
+/// 经命令队列提交才会产生变更记录和网格重建
 #[derive(Resource, Default)]
 pub struct VoxelWorld {
-    /// 存储所有已生成的区块数据
-    pub chunks: HashMap<ChunkPos, ChunkData>,
-    /// 存储已加载区块对应的实体ID，用于场景管理
+    /// 存储已加载区块对应的渲染实体ID，用于场景管理
     pub loaded_chunks: HashMap<ChunkPos, Entity>,
+    /// 已加载区块的列高度图，用于剔除被掩埋的地下区块
+    pub heightmap: Heightmap,
 }
 
 impl VoxelWorld {
-    /// 获取世界中指定位置的体素类型
-    /// 自动将世界坐标转换为区块坐标和局部坐标
-    pub fn get_voxel(&self, world_pos: IVec3) -> VoxelKind {
+    /// 将世界坐标拆分为区块坐标和区块内索引
+    pub fn split_world_pos(world_pos: IVec3) -> (ChunkPos, usize) {
         let chunk_pos = ChunkPos::from_world_pos(world_pos.x, world_pos.y, world_pos.z);
-        let local_x = world_pos.x.rem_euclid(CHUNK_SIZE);
-        let local_y = world_pos.y.rem_euclid(CHUNK_SIZE);
-        let local_z = world_pos.z.rem_euclid(CHUNK_SIZE);
+        let idx = ChunkData::index(
+            world_pos.x.rem_euclid(CHUNK_SIZE),
+            world_pos.y.rem_euclid(CHUNK_SIZE),
+            world_pos.z.rem_euclid(CHUNK_SIZE),
+        );
+        (chunk_pos, idx)
+    }
 
-        self.chunks
-            .get(&chunk_pos)
-            .map(|chunk| chunk.get(local_x, local_y, local_z))
+    /// 区块加载、卸载或修改后更新所在区块列的高度图
+    pub fn refresh_heightmap(
+        &mut self,
+        chunks: &impl ChunkLookup,
+        changed: impl IntoIterator<Item = ChunkPos>,
+    ) {
+        self.heightmap.refresh(chunks, changed);
+    }
+}
+
+/// 区块空间索引 - 区块坐标到区块数据实体的映射
+///
+/// 区块实体由 [`ChunkEntities`] 生成和销毁，索引在调用时立即更新；
+/// 新区块的组件要等到命令应用后才能查询到，在此之前按未加载处理
+#[derive(Resource, Default)]
+pub struct ChunkIndex {
+    entities: HashMap<ChunkPos, Entity>,
+}
+
+impl ChunkIndex {
+    /// 区块对应的实体
+    pub fn get(&self, chunk_pos: &ChunkPos) -> Option<Entity> {
+        self.entities.get(chunk_pos).copied()
+    }
+
+    /// 区块是否已生成（包括本帧刚生成、组件尚未应用的区块）
+    pub fn contains(&self, chunk_pos: &ChunkPos) -> bool {
+        self.entities.contains_key(chunk_pos)
+    }
+
+    /// 所有已生成区块的坐标
+    pub fn positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.entities.keys().copied()
+    }
+
+    /// 已生成的区块数
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// 按区块坐标读取区块数据
+///
+/// 运行中的世界由 [`Chunks`]、[`ChunksMut`] 和 [`World`] 实现；
+/// 不依赖 ECS 的工具函数和测试可以直接使用 `HashMap<ChunkPos, ChunkData>`
+pub trait ChunkLookup {
+    /// 获取区块数据，区块未加载时返回 None
+    fn chunk(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData>;
+
+    /// 所有已加载区块的坐标（顺序不固定）
+    fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_;
+
+    /// 遍历所有已加载区块（顺序不固定）
+    fn iter_chunks(&self) -> impl Iterator<Item = (ChunkPos, &ChunkData)> + '_ {
+        self.chunk_positions()
+            .filter_map(|chunk_pos| Some((chunk_pos, self.chunk(&chunk_pos)?)))
+    }
+
+    /// 获取世界中指定位置的体素类型，区块未加载时视为空气
+    fn get_voxel(&self, world_pos: IVec3) -> VoxelKind {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(world_pos);
+        self.chunk(&chunk_pos)
+            .map(|chunk| chunk.voxels.get(idx))
             .unwrap_or(VoxelKind::Air)
     }
+}
 
-    /// 设置世界中指定位置的体素类型
-    /// 如果对应的区块不存在，不执行操作
-    pub fn set_voxel(&mut self, world_pos: IVec3, kind: VoxelKind) {
-        let chunk_pos = ChunkPos::from_world_pos(world_pos.x, world_pos.y, world_pos.z);
-        let local_x = world_pos.x.rem_euclid(CHUNK_SIZE);
-        let local_y = world_pos.y.rem_euclid(CHUNK_SIZE);
-        let local_z = world_pos.z.rem_euclid(CHUNK_SIZE);
+/// 按区块坐标修改区块数据
+pub trait ChunkLookupMut: ChunkLookup {
+    /// 获取区块数据的可变引用，区块未加载时返回 None
+    fn chunk_mut(&mut self, chunk_pos: &ChunkPos)
+        -> Option<impl DerefMut<Target = ChunkData> + '_>;
+}
+
+impl ChunkLookup for HashMap<ChunkPos, ChunkData> {
+    fn chunk(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData> {
+        self.get(chunk_pos)
+    }
+
+    fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.keys().copied()
+    }
+}
+
+impl ChunkLookupMut for HashMap<ChunkPos, ChunkData> {
+    fn chunk_mut(
+        &mut self,
+        chunk_pos: &ChunkPos,
+    ) -> Option<impl DerefMut<Target = ChunkData> + '_> {
+        self.get_mut(chunk_pos)
+    }
+}
+
+impl ChunkLookup for World {
+    fn chunk(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData> {
+        let entity = self.get_resource::<ChunkIndex>()?.get(chunk_pos)?;
+        self.get::<ChunkData>(entity)
+    }
+
+    fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.get_resource::<ChunkIndex>()
+            .into_iter()
+            .flat_map(ChunkIndex::positions)
+    }
+}
+
+/// 只读访问所有区块数据
+#[derive(SystemParam)]
+pub struct Chunks<'w, 's> {
+    index: Res<'w, ChunkIndex>,
+    chunks: Query<'w, 's, (&'static ChunkPos, &'static ChunkData)>,
+}
+
+impl Chunks<'_, '_> {
+    /// 获取区块数据，区块未加载时返回 None
+    pub fn get(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData> {
+        let entity = self.index.get(chunk_pos)?;
+        self.chunks.get(entity).ok().map(|(_, chunk)| chunk)
+    }
+
+    /// 区块是否已生成
+    pub fn contains(&self, chunk_pos: &ChunkPos) -> bool {
+        self.index.contains(chunk_pos)
+    }
+
+    /// 遍历所有区块（顺序不固定，需要确定顺序时先按坐标排序）
+    pub fn iter(&self) -> impl Iterator<Item = (ChunkPos, &ChunkData)> + '_ {
+        self.chunks
+            .iter()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk))
+    }
+
+    /// 已生成的区块数
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
 
-        if let Some(chunk) = self.chunks.get_mut(&chunk_pos) {
-            chunk.set(local_x, local_y, local_z, kind);
+impl ChunkLookup for Chunks<'_, '_> {
+    fn chunk(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData> {
+        self.get(chunk_pos)
+    }
+
+    fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.index.positions()
+    }
+}
+
+/// 读写所有区块数据（不增删区块）
+///
+/// 可变访问只在实际写入时标记变化，`Changed<ChunkData>` 给出本帧被修改的区块
+#[derive(SystemParam)]
+pub struct ChunksMut<'w, 's> {
+    index: Res<'w, ChunkIndex>,
+    chunks: Query<'w, 's, (&'static ChunkPos, &'static mut ChunkData)>,
+}
+
+impl ChunksMut<'_, '_> {
+    /// 获取区块数据，区块未加载时返回 None
+    pub fn get(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData> {
+        let entity = self.index.get(chunk_pos)?;
+        self.chunks.get(entity).ok().map(|(_, chunk)| chunk)
+    }
+
+    /// 获取区块数据的可变引用，区块未加载时返回 None
+    pub fn get_mut(&mut self, chunk_pos: &ChunkPos) -> Option<Mut<'_, ChunkData>> {
+        let entity = self.index.get(chunk_pos)?;
+        self.chunks.get_mut(entity).ok().map(|(_, chunk)| chunk)
+    }
+
+    /// 区块是否已生成
+    pub fn contains(&self, chunk_pos: &ChunkPos) -> bool {
+        self.index.contains(chunk_pos)
+    }
+
+    /// 遍历所有区块（顺序不固定，需要确定顺序时先按坐标排序）
+    pub fn iter(&self) -> impl Iterator<Item = (ChunkPos, &ChunkData)> + '_ {
+        self.chunks
+            .iter()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk))
+    }
+
+    /// 可变遍历所有区块（顺序不固定）
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (ChunkPos, Mut<'_, ChunkData>)> + '_ {
+        self.chunks
+            .iter_mut()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk))
+    }
+
+    /// 已生成的区块数
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+impl ChunkLookup for ChunksMut<'_, '_> {
+    fn chunk(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData> {
+        self.get(chunk_pos)
+    }
+
+    fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.index.positions()
+    }
+}
+
+impl ChunkLookupMut for ChunksMut<'_, '_> {
+    fn chunk_mut(
+        &mut self,
+        chunk_pos: &ChunkPos,
+    ) -> Option<impl DerefMut<Target = ChunkData> + '_> {
+        self.get_mut(chunk_pos)
+    }
+}
+
+/// 加入和移除区块
+///
+/// 新区块的实体通过命令生成，本系统内读不到它的数据，
+/// 需要新区块数据的后续处理放在读取 [`ChunkLoaded`](crate::voxel::events::ChunkLoaded) 的系统中
+#[derive(SystemParam)]
+pub struct ChunkEntities<'w, 's> {
+    commands: Commands<'w, 's>,
+    index: ResMut<'w, ChunkIndex>,
+    chunks: Query<'w, 's, (&'static ChunkPos, &'static mut ChunkData)>,
+}
+
+impl<'w> ChunkEntities<'w, '_> {
+    /// 区块实体所用的命令队列，增删区块的系统用它一起处理其他实体
+    pub fn commands(&mut self) -> Commands<'w, '_> {
+        self.commands.reborrow()
+    }
+
+    /// 加入区块；区块已存在时直接替换它的数据
+    pub fn insert(&mut self, chunk_pos: ChunkPos, chunk: ChunkData) {
+        if let Some(entity) = self.index.get(&chunk_pos)
+            && let Ok((_, mut existing)) = self.chunks.get_mut(entity)
+        {
+            *existing = chunk;
+            return;
         }
+        let entity = self.commands.spawn((chunk_pos, chunk)).id();
+        if let Some(replaced) = self.index.entities.insert(chunk_pos, entity) {
+            self.commands.entity(replaced).despawn();
+        }
+    }
+
+    /// 移除区块并取出它的数据（本帧刚加入、组件尚未应用的区块只移除，不返回数据）
+    pub fn remove(&mut self, chunk_pos: &ChunkPos) -> Option<ChunkData> {
+        let entity = self.index.entities.remove(chunk_pos)?;
+        let chunk = self
+            .chunks
+            .get_mut(entity)
+            .ok()
+            .map(|(_, mut chunk)| std::mem::take(&mut *chunk));
+        self.commands.entity(entity).despawn();
+        chunk
+    }
+
+    /// 获取区块数据，区块未加载时返回 None
+    pub fn get(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData> {
+        let entity = self.index.get(chunk_pos)?;
+        self.chunks.get(entity).ok().map(|(_, chunk)| chunk)
+    }
+
+    /// 获取区块数据的可变引用，区块未加载时返回 None
+    pub fn get_mut(&mut self, chunk_pos: &ChunkPos) -> Option<Mut<'_, ChunkData>> {
+        let entity = self.index.get(chunk_pos)?;
+        self.chunks.get_mut(entity).ok().map(|(_, chunk)| chunk)
+    }
+
+    /// 区块是否已生成
+    pub fn contains(&self, chunk_pos: &ChunkPos) -> bool {
+        self.index.contains(chunk_pos)
+    }
+}
+
+impl ChunkLookup for ChunkEntities<'_, '_> {
+    fn chunk(&self, chunk_pos: &ChunkPos) -> Option<&ChunkData> {
+        self.get(chunk_pos)
+    }
+
+    fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.index.positions()
+    }
+}
+
+impl ChunkLookupMut for ChunkEntities<'_, '_> {
+    fn chunk_mut(
+        &mut self,
+        chunk_pos: &ChunkPos,
+    ) -> Option<impl DerefMut<Target = ChunkData> + '_> {
+        self.get_mut(chunk_pos)
+    }
+}
+
+/// 在独占访问的世界中直接生成区块实体并登记到索引
+///
+/// 无渲染模拟、基准测试和测试搭建世界时使用，区块已存在时替换它的数据
+pub fn spawn_chunk(world: &mut World, chunk_pos: ChunkPos, chunk: ChunkData) -> Entity {
+    let existing = world.get_resource_or_init::<ChunkIndex>().get(&chunk_pos);
+    if let Some(entity) = existing
+        && let Some(mut existing) = world.get_mut::<ChunkData>(entity)
+    {
+        *existing = chunk;
+        return entity;
+    }
+    let entity = world.spawn((chunk_pos, chunk)).id();
+    world
+        .resource_mut::<ChunkIndex>()
+        .entities
+        .insert(chunk_pos, entity);
+    entity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_coordinates() {
+        assert_eq!(
+            ChunkPos::from_world_pos(-1, -16, -17),
+            ChunkPos::new(-1, -1, -2)
+        );
+        assert_eq!(
+            ChunkPos::new(-1, -4, 0).world_origin(),
+            IVec3::new(-16, -64, 0)
+        );
+        assert_eq!(
+            ChunkPos::containing(Vec3::new(-0.5, -0.01, 15.9)),
+            ChunkPos::new(-1, -1, 0)
+        );
+        assert_eq!(
+            ChunkPos::containing(Vec3::new(0.5, -16.0, -16.5)),
+            ChunkPos::new(0, -1, -2)
+        );
+
+        let pos = IVec3::new(-1, -33, -16);
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        assert_eq!(chunk_pos, ChunkPos::new(-1, -3, -1));
+        assert_eq!(idx, ChunkData::index(15, 15, 0));
+
+        let mut chunks = HashMap::new();
+        let mut chunk = ChunkData::new();
+        chunk.set(15, 15, 0, VoxelKind::Stone);
+        chunks.insert(chunk_pos, chunk);
+        assert_eq!(chunks.get_voxel(pos), VoxelKind::Stone);
+        assert_eq!(chunks.get_voxel(pos + IVec3::Y), VoxelKind::Air);
     }
 }
@@ -0,0 +1,216 @@
+//! 实体与体素的碰撞查询
+//!
+//! 玩家、掉落物以及以后的生物都用轴对齐包围盒（[`Aabb`]）与方块碰撞：
+//!
+//! - [`aabb_collides`]：包围盒是否与任何固体方块重叠
+//! - [`sweep_axis`]：沿一个轴移动包围盒，贴着第一个挡住它的方块面停下
+//! - [`sweep_aabb`]：依次沿 y、x、z 轴移动，返回实际位移和被挡住的轴
+//!
+//! 移动按不超过 [`MAX_SUBSTEP`] 的子步推进，高速下也不会穿过一格厚的方块。
+//! 贴住方块面时保留 [`COLLISION_SKIN`] 的间隙，下一次查询不会把贴住的面算作重叠。
+//! 未加载的区块视为空气。
+
+use bevy::prelude::*;
+
+use crate::voxel::chunk::ChunkLookup;
+
+/// 贴住方块面时与方块保留的间隙
+pub const COLLISION_SKIN: f32 = 0.001;
+/// 每个子步最多移动的距离（方块）
+pub const MAX_SUBSTEP: f32 = 0.4;
+
+/// 轴对齐包围盒（世界坐标）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    /// 底面中心在 bottom、水平半宽 half_width、高 height 的包围盒（站立的实体）
+    pub fn from_bottom(bottom: Vec3, half_width: f32, height: f32) -> Self {
+        let half = Vec3::new(half_width, 0.0, half_width);
+        Self::new(bottom - half, bottom + half + Vec3::Y * height)
+    }
+
+    /// 中心在 center、各轴半边长为 half 的立方体
+    pub fn cube(center: Vec3, half: f32) -> Self {
+        Self::new(center - Vec3::splat(half), center + Vec3::splat(half))
+    }
+
+    /// 平移后的包围盒
+    pub fn translated(self, offset: Vec3) -> Self {
+        Self::new(self.min + offset, self.max + offset)
+    }
+
+    /// 是否与 block 处的方块重叠（仅仅贴住方块面不算）
+    pub fn overlaps_block(self, block: IVec3) -> bool {
+        let block_min = block.as_vec3();
+        let block_max = block_min + Vec3::ONE;
+        self.min.cmplt(block_max - COLLISION_SKIN).all()
+            && self.max.cmpgt(block_min + COLLISION_SKIN).all()
+    }
+
+    /// point 到包围盒的距离，在包围盒内时为 0
+    pub fn distance_to(self, point: Vec3) -> f32 {
+        point.clamp(self.min, self.max).distance(point)
+    }
+}
+
+/// 一次移动的结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sweep {
+    /// 实际位移
+    pub offset: Vec3,
+    /// 被方块挡住的轴
+    pub blocked: BVec3,
+}
+
+/// 包围盒是否与任何固体方块重叠
+pub fn aabb_collides(world: &impl ChunkLookup, aabb: Aabb) -> bool {
+    let lo = aabb.min.floor().as_ivec3();
+    // 贴住上方方块面时 max 恰好落在整数上，减去半个间隙避免把那一格算进来
+    let hi = (aabb.max - Vec3::splat(COLLISION_SKIN * 0.5))
+        .floor()
+        .as_ivec3();
+    for x in lo.x..=hi.x {
+        for y in lo.y..=hi.y {
+            for z in lo.z..=hi.z {
+                if world.get_voxel(IVec3::new(x, y, z)).is_solid() {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// 沿 axis（0、1、2 分别为 x、y、z）把包围盒移动 delta，被挡住时贴着方块面停下
+pub fn sweep_axis(world: &impl ChunkLookup, aabb: Aabb, axis: usize, delta: f32) -> Sweep {
+    let mut sweep = Sweep {
+        offset: Vec3::ZERO,
+        blocked: BVec3::FALSE,
+    };
+    if delta == 0.0 {
+        return sweep;
+    }
+    let steps = (delta.abs() / MAX_SUBSTEP).ceil().max(1.0) as i32;
+    let step = delta / steps as f32;
+    let mut moved = 0.0;
+
+    for _ in 0..steps {
+        let candidate = aabb.translated(Vec3::AXES[axis] * (moved + step));
+        if !aabb_collides(world, candidate) {
+            moved += step;
+            continue;
+        }
+
+        // 贴住挡住包围盒的方块面
+        let snapped = if step > 0.0 {
+            candidate.max[axis].floor() - COLLISION_SKIN - aabb.max[axis]
+        } else {
+            candidate.min[axis].floor() + 1.0 + COLLISION_SKIN - aabb.min[axis]
+        };
+        let moves_forward = if step > 0.0 {
+            snapped >= moved
+        } else {
+            snapped <= moved
+        };
+        if moves_forward && !aabb_collides(world, aabb.translated(Vec3::AXES[axis] * snapped)) {
+            moved = snapped;
+        }
+        sweep.blocked.set(axis, true);
+        break;
+    }
+
+    sweep.offset[axis] = moved;
+    sweep
+}
+
+/// 把包围盒移动 motion：先竖直再水平，每个轴单独检测，被挡住的轴停下、其余轴继续滑动
+pub fn sweep_aabb(world: &impl ChunkLookup, aabb: Aabb, motion: Vec3) -> Sweep {
+    let mut result = Sweep {
+        offset: Vec3::ZERO,
+        blocked: BVec3::FALSE,
+    };
+    for axis in [1, 0, 2] {
+        let sweep = sweep_axis(world, aabb.translated(result.offset), axis, motion[axis]);
+        result.offset += sweep.offset;
+        result.blocked.set(axis, sweep.blocked.test(axis));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::{ChunkData, ChunkPos, VoxelWorld};
+    use crate::voxel::voxel_kind::VoxelKind;
+    use std::collections::HashMap;
+
+    /// y = -1 的一层石头地面，(2, 0, 0) 处有一块石墙，(0, 0, 2) 处有花
+    fn fixture() -> HashMap<ChunkPos, ChunkData> {
+        let mut world: HashMap<ChunkPos, ChunkData> = HashMap::new();
+        let mut set = |pos: IVec3, kind: VoxelKind| {
+            let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+            world.entry(chunk_pos).or_default().voxels.set(idx, kind);
+        };
+        for x in -4..4 {
+            for z in -4..4 {
+                set(IVec3::new(x, -1, z), VoxelKind::Stone);
+            }
+        }
+        set(IVec3::new(2, 0, 0), VoxelKind::Stone);
+        set(IVec3::new(0, 0, 2), VoxelKind::Flower);
+        world
+    }
+
+    #[test]
+    fn test_aabb_collides() {
+        let world = fixture();
+        let body = Aabb::from_bottom(Vec3::new(0.5, 0.0, 0.5), 0.3, 1.8);
+        // 站在地面上只是贴住地面
+        assert!(!aabb_collides(&world, body));
+        assert!(aabb_collides(&world, body.translated(Vec3::NEG_Y * 0.1)));
+        assert!(aabb_collides(&world, body.translated(Vec3::X * 1.5)));
+        // 花没有碰撞体积
+        assert!(!aabb_collides(&world, body.translated(Vec3::Z * 2.0)));
+
+        assert!(body.overlaps_block(IVec3::new(0, 1, 0)));
+        assert!(!body.overlaps_block(IVec3::new(0, -1, 0)));
+        assert_eq!(body.distance_to(Vec3::new(0.5, 1.0, 0.5)), 0.0);
+        assert!((body.distance_to(Vec3::new(1.8, 1.0, 0.5)) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_sweep_stops_at_faces() {
+        let world = fixture();
+        let body = Aabb::from_bottom(Vec3::new(0.5, 3.0, 0.5), 0.3, 1.8);
+
+        // 高速下落也停在地面上，不会穿过一格厚的地面
+        let fall = sweep_axis(&world, body, 1, -20.0);
+        assert!(fall.blocked.y);
+        assert!((body.min.y + fall.offset.y - COLLISION_SKIN).abs() < 1e-4);
+
+        // 落地后向 +x 走，被石墙挡住，z 方向继续滑动
+        let grounded = body.translated(fall.offset);
+        let sweep = sweep_aabb(&world, grounded, Vec3::new(3.0, 0.0, 1.0));
+        assert_eq!(sweep.blocked, BVec3::new(true, false, false));
+        assert!((grounded.max.x + sweep.offset.x - (2.0 - COLLISION_SKIN)).abs() < 1e-4);
+        assert!((sweep.offset.z - 1.0).abs() < 1e-5);
+        assert!(!aabb_collides(&world, grounded.translated(sweep.offset)));
+    }
+
+    #[test]
+    fn test_sweep_in_unloaded_chunks() {
+        let world = fixture();
+        let drop = Aabb::cube(Vec3::new(40.5, -40.5, -40.5), 0.125);
+        let sweep = sweep_aabb(&world, drop, Vec3::new(0.8, -3.0, 0.0));
+        assert_eq!(sweep.blocked, BVec3::FALSE);
+        assert_eq!(sweep.offset, Vec3::new(0.8, -3.0, 0.0));
+    }
+}
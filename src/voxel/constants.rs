@@ -9,3 +9,6 @@ pub const RENDER_DISTANCE: i32 = 8;
 /// 垂直渲染距离（单位：区块数）- 控制玩家周围Y轴加载多少层区块
 /// 建议设置为RENDER_DISTANCE的1/4到1/2，以减少内存占用
 pub const VERTICAL_RENDER_DISTANCE: i32 = 4;
+
+/// 地表保留层数（单位：区块数）- 摄像机高于地表时，向下最多额外加载这么多层让地表保持可见
+pub const SURFACE_RENDER_BUDGET: i32 = 6;
@@ -0,0 +1,157 @@
+//! 区块调试渲染
+//!
+//! 用线框画出所有已知区块的边界，并按加载状态着色，用于排查加载和网格构建问题：
+//!
+//! - 灰色：占位符（已排队，尚未开始生成）
+//! - 黄色：正在生成或重建网格
+//! - 红色：数据已修改，等待重建网格
+//! - 绿色：已加载
+//!
+//! 另外可以在 y=0 平面上画出与区块边界对齐的世界网格。
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_2;
+
+use crate::input::{Action, ActionInput};
+use crate::voxel::chunk::{ChunkPos, Chunks};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::ivec3_to_vec3;
+use crate::voxel::loading::{ChunkTasks, PlaceholderEntities, RenderDistance};
+
+/// 区块边界线框向内收缩的距离，避免相邻区块的线框重叠后看不出颜色
+const BORDER_INSET: f32 = 0.1;
+
+/// 区块调试渲染开关
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ChunkDebugSettings {
+    /// 绘制区块边界
+    pub show_chunk_borders: bool,
+    /// 绘制 y=0 平面的区块网格
+    pub show_grid: bool,
+}
+
+/// 区块在调试视图中的状态（按优先级从高到低）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ChunkDebugState {
+    Meshing,
+    Dirty,
+    Placeholder,
+    Loaded,
+}
+
+impl ChunkDebugState {
+    fn color(self) -> Color {
+        match self {
+            ChunkDebugState::Meshing => Color::srgb(1.0, 0.85, 0.1),
+            ChunkDebugState::Dirty => Color::srgb(1.0, 0.2, 0.2),
+            ChunkDebugState::Placeholder => Color::srgb(0.6, 0.6, 0.6),
+            ChunkDebugState::Loaded => Color::srgba(0.2, 0.9, 0.3, 0.6),
+        }
+    }
+}
+
+/// 切换区块边界和世界网格
+pub fn toggle_chunk_debug(actions: ActionInput, mut settings: ResMut<ChunkDebugSettings>) {
+    if actions.just_pressed(Action::ToggleChunkBorders) {
+        settings.show_chunk_borders = !settings.show_chunk_borders;
+        info!(
+            "Chunk borders {} (gray: placeholder, yellow: meshing, red: dirty, green: loaded)",
+            if settings.show_chunk_borders {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+    if actions.just_pressed(Action::ToggleWorldGrid) {
+        settings.show_grid = !settings.show_grid;
+        info!(
+            "World grid {}",
+            if settings.show_grid {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+}
+
+/// 按状态绘制所有区块的边界线框
+pub fn draw_chunk_borders(
+    settings: Res<ChunkDebugSettings>,
+    chunks: Chunks,
+    placeholders: Res<PlaceholderEntities>,
+    compute_tasks: Res<ChunkTasks>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.show_chunk_borders {
+        return;
+    }
+
+    // 同一区块可能同时处于多种状态，保留优先级最高的
+    let mut states: HashMap<ChunkPos, ChunkDebugState> = HashMap::new();
+    let mut mark = |chunk_pos: ChunkPos, state: ChunkDebugState| {
+        states
+            .entry(chunk_pos)
+            .and_modify(|current| *current = (*current).min(state))
+            .or_insert(state);
+    };
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        let state = if chunk.is_dirty {
+            ChunkDebugState::Dirty
+        } else {
+            ChunkDebugState::Loaded
+        };
+        mark(chunk_pos, state);
+    }
+    for &chunk_pos in placeholders.map.keys() {
+        mark(chunk_pos, ChunkDebugState::Placeholder);
+    }
+    for &chunk_pos in compute_tasks.pending.keys() {
+        mark(chunk_pos, ChunkDebugState::Meshing);
+    }
+    for chunk_pos in compute_tasks.remeshing() {
+        mark(chunk_pos, ChunkDebugState::Meshing);
+    }
+
+    let size = CHUNK_SIZE as f32 - BORDER_INSET * 2.0;
+    for (chunk_pos, state) in states {
+        let center = ivec3_to_vec3(chunk_pos.world_origin()) + Vec3::splat(CHUNK_SIZE as f32 * 0.5);
+        let transform = Transform::from_translation(center).with_scale(Vec3::splat(size));
+        gizmos.cube(transform, state.color());
+    }
+}
+
+/// 在 y=0 平面上绘制覆盖渲染距离的区块网格，以相机所在区块为中心
+pub fn draw_world_grid(
+    settings: Res<ChunkDebugSettings>,
+    render_distance: Res<RenderDistance>,
+    camera_query: Query<&GlobalTransform, With<Camera3d>>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.show_grid {
+        return;
+    }
+    let Some(camera) = camera_query.iter().next() else {
+        return;
+    };
+
+    let eye = camera.translation().floor().as_ivec3();
+    let camera_chunk = ChunkPos::from_world_pos(eye.x, eye.y, eye.z);
+    let half = CHUNK_SIZE as f32 * 0.5;
+    let origin = ivec3_to_vec3(camera_chunk.world_origin());
+    let center = Vec3::new(origin.x + half, 0.0, origin.z + half);
+    // 奇数个格子，网格边缘正好落在区块边界上
+    let cells = (render_distance.horizontal.max(0) as u32) * 2 + 1;
+
+    gizmos
+        .grid(
+            Isometry3d::new(center, Quat::from_rotation_x(FRAC_PI_2)),
+            UVec2::splat(cells),
+            Vec2::splat(CHUNK_SIZE as f32),
+            Color::srgba(0.3, 0.6, 1.0, 0.5),
+        )
+        .outer_edges();
+}
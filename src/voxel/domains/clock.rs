@@ -0,0 +1,309 @@
+//! 模拟时钟
+//!
+//! 独立于渲染控制领域模拟的推进（以下为默认按键，可在设置中修改）：
+//! - O: 暂停/继续模拟
+//! - .: 暂停并单步推进一个 tick
+//! - `[` / `]`: 降低/提高模拟倍速
+//!
+//! 控制台的 sim 命令提供相同的功能，还可以一次推进多个 tick 或设置任意倍速。
+//!
+//! ## 暂停
+//!
+//! 暂停时跳过 FieldUpdate、StateUpdate、Reactions 三个阶段：温度不再扩散，燃烧、
+//! 流动、生长和反应全部停止。ExternalActions、Commit、Post 照常运行，玩家、控制台和
+//! 网络对方块的修改仍然立即生效，便于在暂停时布置场景再单步观察。
+//!
+//! ## 倍速
+//!
+//! 倍速按倍数缩短 `Time<Fixed>` 的步长，每秒执行更多（或更少）的 tick。
+//! 每个 tick 代表的模拟时间保持为默认步长（64Hz），按时间积分的系统用
+//! [`SimulationClock::scale`] 把实际步长换算回模拟时间，
+//! 所以按 tick 计数的领域（燃烧、腐蚀）和按时间积分的领域（温度）一起加速。
+
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::input::{Action, ActionInput};
+use crate::voxel::events::SimulationTickCompleted;
+
+/// 最低模拟倍速
+pub const MIN_SPEED: f32 = 0.125;
+/// 最高模拟倍速，再高时一帧内要追赶的 tick 过多
+pub const MAX_SPEED: f32 = 8.0;
+/// 按键调节倍速时依次切换的档位
+const SPEED_STEPS: [f32; 7] = [0.125, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+/// sim step 一次最多推进的 tick 数
+const MAX_STEP_TICKS: u32 = 6400;
+
+/// 模拟时钟
+#[derive(Resource, Debug, Clone)]
+pub struct SimulationClock {
+    pub paused: bool,
+    /// 暂停时还要推进的 tick 数
+    pending_steps: u32,
+    speed: f32,
+    /// 启动以来模拟推进的 tick 数
+    ticks: u64,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            pending_steps: 0,
+            speed: 1.0,
+            ticks: 0,
+        }
+    }
+}
+
+impl SimulationClock {
+    /// 本 tick 是否推进模拟
+    pub fn running(&self) -> bool {
+        !self.paused || self.pending_steps > 0
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// 设置倍速，超出范围时取最近的边界，返回实际使用的倍速
+    pub fn set_speed(&mut self, speed: f32) -> f32 {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.speed
+    }
+
+    /// 切换到下一个更高的档位
+    pub fn faster(&mut self) -> f32 {
+        let next = SPEED_STEPS.into_iter().find(|&step| step > self.speed);
+        self.set_speed(next.unwrap_or(MAX_SPEED))
+    }
+
+    /// 切换到下一个更低的档位
+    pub fn slower(&mut self) -> f32 {
+        let next = SPEED_STEPS
+            .into_iter()
+            .rev()
+            .find(|&step| step < self.speed);
+        self.set_speed(next.unwrap_or(MIN_SPEED))
+    }
+
+    /// 暂停或继续，丢弃尚未推进的单步
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.pending_steps = 0;
+    }
+
+    /// 暂停模拟并推进 ticks 个 tick
+    pub fn step(&mut self, ticks: u32) {
+        self.paused = true;
+        self.pending_steps = self.pending_steps.saturating_add(ticks);
+    }
+
+    /// 把 FixedUpdate 的实际步长换算为模拟时间
+    pub fn scale(&self, dt: f32) -> f32 {
+        dt * self.speed
+    }
+
+    /// 当前倍速下 FixedUpdate 的步长
+    pub fn timestep(&self) -> Duration {
+        Time::<Fixed>::default().timestep().div_f32(self.speed)
+    }
+
+    /// 调试信息中显示的状态
+    pub fn status(&self) -> String {
+        if self.paused {
+            format!("paused at tick {}", self.ticks)
+        } else {
+            format!("x{} at tick {}", self.speed, self.ticks)
+        }
+    }
+
+    /// 一个 tick 结束，记录推进的 tick 并消耗单步
+    fn advance(&mut self) {
+        if !self.running() {
+            return;
+        }
+        if self.paused {
+            self.pending_steps -= 1;
+        }
+        self.ticks += 1;
+    }
+}
+
+/// SimulationSet 的运行条件：暂停且没有待推进的单步时跳过模拟阶段
+pub fn simulation_running(clock: Res<SimulationClock>) -> bool {
+    clock.running()
+}
+
+/// 模拟时钟插件
+///
+/// 注册时钟资源、快捷键和 sim 控制台命令
+pub struct SimulationClockPlugin;
+
+impl Plugin for SimulationClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "sim",
+            "sim [pause|resume|step [tick 数]|speed <倍速>]",
+            "暂停、单步或调整领域模拟的倍速",
+        );
+
+        app.init_resource::<SimulationClock>()
+            .add_systems(
+                Update,
+                (
+                    simulation_clock_keys,
+                    sim_console_command,
+                    apply_simulation_speed,
+                )
+                    .chain(),
+            )
+            .add_systems(FixedUpdate, advance_clock.after(super::SimulationSet::Post));
+    }
+}
+
+/// 处理模拟时钟快捷键
+fn simulation_clock_keys(
+    actions: ActionInput,
+    mut clock: ResMut<SimulationClock>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    if actions.just_pressed(Action::ToggleSimulationPause) {
+        clock.toggle_pause();
+        log.print(if clock.paused {
+            "模拟已暂停"
+        } else {
+            "模拟已继续"
+        });
+    }
+    if actions.just_pressed(Action::StepSimulation) {
+        clock.step(1);
+    }
+    if actions.just_pressed(Action::SimulationFaster) {
+        let speed = clock.faster();
+        log.print(format!("模拟倍速 x{speed}"));
+    }
+    if actions.just_pressed(Action::SimulationSlower) {
+        let speed = clock.slower();
+        log.print(format!("模拟倍速 x{speed}"));
+    }
+}
+
+/// 执行 sim 控制台命令
+fn sim_console_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut clock: ResMut<SimulationClock>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "sim" {
+            continue;
+        }
+        let args: Vec<&str> = command.args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            [] => log.print(format!(
+                "模拟{}，倍速 x{}，已推进 {} 个 tick",
+                if clock.paused {
+                    "已暂停"
+                } else {
+                    "运行中"
+                },
+                clock.speed(),
+                clock.ticks()
+            )),
+            ["pause"] => {
+                clock.paused = true;
+                log.print("模拟已暂停");
+            }
+            ["resume"] => {
+                clock.paused = false;
+                log.print("模拟已继续");
+            }
+            ["step"] => clock.step(1),
+            ["step", ticks] => match ticks.parse::<u32>() {
+                Ok(ticks) if (1..=MAX_STEP_TICKS).contains(&ticks) => {
+                    clock.step(ticks);
+                    log.print(format!("推进 {ticks} 个 tick"));
+                }
+                _ => log.print(format!(
+                    "sim：tick 数应在 1 到 {MAX_STEP_TICKS} 之间：{ticks}"
+                )),
+            },
+            ["speed", speed] => match speed.parse::<f32>() {
+                Ok(speed) if speed.is_finite() && speed > 0.0 => {
+                    let speed = clock.set_speed(speed);
+                    log.print(format!("模拟倍速 x{speed}"));
+                }
+                _ => log.print(format!("sim：无效的倍速：{speed}")),
+            },
+            _ => log.print("用法：sim [pause|resume|step [tick 数]|speed <倍速>]"),
+        }
+    }
+}
+
+/// 倍速变化后更新 FixedUpdate 的步长
+fn apply_simulation_speed(clock: Res<SimulationClock>, mut fixed: ResMut<Time<Fixed>>) {
+    let timestep = clock.timestep();
+    if fixed.timestep() != timestep {
+        fixed.set_timestep(timestep);
+    }
+}
+
+/// 每个 FixedUpdate tick 结束时推进时钟，模拟推进了这个 tick 时发出完成事件
+fn advance_clock(
+    mut clock: ResMut<SimulationClock>,
+    mut completed: MessageWriter<SimulationTickCompleted>,
+) {
+    if clock.running() {
+        clock.advance();
+        completed.write(SimulationTickCompleted { tick: clock.ticks });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_runs_exact_ticks_while_paused() {
+        let mut clock = SimulationClock::default();
+        clock.toggle_pause();
+        assert!(!clock.running());
+
+        clock.step(3);
+        for _ in 0..5 {
+            clock.advance();
+        }
+        assert_eq!(clock.ticks(), 3);
+        assert!(clock.paused);
+        assert!(!clock.running());
+
+        clock.toggle_pause();
+        clock.advance();
+        assert_eq!(clock.ticks(), 4);
+    }
+
+    #[test]
+    fn test_speed_keeps_simulated_time_per_tick() {
+        let mut clock = SimulationClock::default();
+        let base = Time::<Fixed>::default().timestep().as_secs_f32();
+
+        assert_eq!(clock.faster(), 2.0);
+        assert_eq!(clock.faster(), 4.0);
+        assert!((clock.scale(clock.timestep().as_secs_f32()) - base).abs() < 1e-6);
+
+        assert_eq!(clock.set_speed(100.0), MAX_SPEED);
+        assert_eq!(clock.faster(), MAX_SPEED);
+        assert_eq!(clock.set_speed(0.3), 0.3);
+        assert_eq!(clock.slower(), 0.25);
+        assert_eq!(clock.set_speed(0.0), MIN_SPEED);
+        assert_eq!(clock.slower(), MIN_SPEED);
+    }
+}
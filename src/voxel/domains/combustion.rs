@@ -0,0 +1,279 @@
+//! 燃烧领域模块
+//!
+//! 点燃：可燃方块被加热到着火点后标记为 BURNING 并加入 active_burning，
+//! 之后由热源系统持续释放燃烧热量，进一步加热周围方块。
+//!
+//! 热量来自任何热源（熔岩、其他燃烧中的方块、玩家放置的热源），
+//! 所以火会沿着可燃方块蔓延，也会从熔岩池引燃附近的树木和草地。
+//!
+//! 熄灭：与水相邻的燃烧方块被浇灭，它和周围的方块一起降温到着火点以下；
+//! 下雨时露天的燃烧方块也会熄灭。与水相邻的高温方块把热量传给水，
+//! 水被加热到沸点后开始蒸发，冒出蒸汽。
+//!
+//! 所有判定都只检查同一区块内的邻居，和其他反应规则一样通过命令修改方块。
+
+use bevy::prelude::*;
+
+use super::command::DomainCommand;
+use super::reaction::{ReactionEnv, ReactionRule, ReactionRules};
+use super::thermal::api::{get_valid_neighbor_indices, idx_to_xyz};
+use super::thermal::ThermalApi;
+use crate::voxel::chunk::ChunkData;
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::light::{sky_light, MAX_LIGHT};
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 熄灭后方块温度的上限（°C），低于所有可燃方块的着火点，熄灭后不会立即复燃
+pub const EXTINGUISHED_TEMP: f32 = 60.0;
+
+/// 高温方块每 tick 传给相邻水的热量（J）
+const QUENCH_HEAT: f32 = 20000.0;
+
+/// 方块是否与水相邻
+fn touches_water(chunk: &ChunkData, idx: usize) -> bool {
+    get_valid_neighbor_indices(idx)
+        .into_iter()
+        .any(|neighbor_idx| chunk.voxels.get(neighbor_idx) == VoxelKind::Water)
+}
+
+/// 方块是否露天：上方方块的天空光为满级
+///
+/// 区块顶层的方块读取自身的光照，不透明方块在顶层时按不露天处理
+fn exposed_to_sky(chunk: &ChunkData, idx: usize) -> bool {
+    let (x, y, z) = idx_to_xyz(idx);
+    let probe = if y + 1 < CHUNK_SIZE {
+        ChunkData::index(x, y + 1, z)
+    } else {
+        idx
+    };
+    sky_light(chunk.light.get(probe)) == MAX_LIGHT
+}
+
+/// 熄灭命令：清除燃烧状态，降温到熄灭温度
+fn extinguish_commands(chunk: &ChunkData, idx: usize) -> Vec<DomainCommand> {
+    let temp = ThermalApi::get_temp(chunk, idx).min(EXTINGUISHED_TEMP);
+    vec![
+        DomainCommand::Extinguish { idx },
+        DomainCommand::SetTemp { idx, temp },
+    ]
+}
+
+/// 点燃规则
+///
+/// 可燃、尚未燃烧且温度达到着火点的方块被点燃
+pub struct IgnitionRule;
+
+impl ReactionRule for IgnitionRule {
+    fn evaluate(&self, chunk: &ChunkData, idx: usize) -> bool {
+        let props = chunk.voxels.get(idx).def().props;
+        props.is_flammable
+            && !chunk.flags.get(idx).contains(VoxelFlags::BURNING)
+            && ThermalApi::get_temp(chunk, idx) >= props.ignition_temp
+    }
+
+    fn emit_commands(&self, chunk: &ChunkData, idx: usize) -> Vec<DomainCommand> {
+        // 起火瞬间先释放一秒的燃烧热量
+        let power = chunk.voxels.get(idx).def().props.heat_release;
+        vec![DomainCommand::Ignite { idx, power }]
+    }
+}
+
+/// 浇水熄灭规则
+///
+/// 与水相邻的燃烧方块熄灭，周围没有燃烧的方块也一起降温，避免火立即从旁边复燃
+pub struct WaterExtinguishRule;
+
+impl ReactionRule for WaterExtinguishRule {
+    fn evaluate(&self, chunk: &ChunkData, idx: usize) -> bool {
+        chunk.flags.get(idx).contains(VoxelFlags::BURNING) && touches_water(chunk, idx)
+    }
+
+    fn emit_commands(&self, chunk: &ChunkData, idx: usize) -> Vec<DomainCommand> {
+        let mut commands = extinguish_commands(chunk, idx);
+        for neighbor_idx in get_valid_neighbor_indices(idx) {
+            // 水由淬火规则加热，燃烧中的邻居由它自己的规则判定
+            if chunk.voxels.get(neighbor_idx) == VoxelKind::Water
+                || chunk.flags.get(neighbor_idx).contains(VoxelFlags::BURNING)
+            {
+                continue;
+            }
+            if ThermalApi::get_temp(chunk, neighbor_idx) > EXTINGUISHED_TEMP {
+                commands.push(DomainCommand::SetTemp {
+                    idx: neighbor_idx,
+                    temp: EXTINGUISHED_TEMP,
+                });
+            }
+        }
+        commands
+    }
+}
+
+/// 雨水熄灭规则
+///
+/// 只在下雨时生效：露天的燃烧方块熄灭。已经与水相邻的方块由浇水熄灭规则处理
+pub struct RainExtinguishRule;
+
+impl ReactionRule for RainExtinguishRule {
+    fn enabled(&self, env: &ReactionEnv) -> bool {
+        env.raining
+    }
+
+    fn evaluate(&self, chunk: &ChunkData, idx: usize) -> bool {
+        chunk.flags.get(idx).contains(VoxelFlags::BURNING)
+            && exposed_to_sky(chunk, idx)
+            && !touches_water(chunk, idx)
+    }
+
+    fn emit_commands(&self, chunk: &ChunkData, idx: usize) -> Vec<DomainCommand> {
+        extinguish_commands(chunk, idx)
+    }
+}
+
+/// 淬火规则
+///
+/// 与水相邻的高温方块（水和熔岩除外）每 tick 把一部分热量传给相邻的水，
+/// 水达到沸点后由相变规则开始蒸发并冒出蒸汽。熔岩由熔岩淬火规则直接凝固
+pub struct QuenchRule;
+
+impl ReactionRule for QuenchRule {
+    fn evaluate(&self, chunk: &ChunkData, idx: usize) -> bool {
+        let kind = chunk.voxels.get(idx);
+        kind != VoxelKind::Water
+            && kind != VoxelKind::Lava
+            && chunk.flags.get(idx).contains(VoxelFlags::HOT)
+            && touches_water(chunk, idx)
+    }
+
+    fn emit_commands(&self, chunk: &ChunkData, idx: usize) -> Vec<DomainCommand> {
+        let water: Vec<usize> = get_valid_neighbor_indices(idx)
+            .into_iter()
+            .filter(|&neighbor_idx| chunk.voxels.get(neighbor_idx) == VoxelKind::Water)
+            .collect();
+        let share = QUENCH_HEAT / water.len() as f32;
+
+        let mut commands = vec![DomainCommand::AddHeat {
+            idx,
+            heat: -QUENCH_HEAT,
+        }];
+        commands.extend(
+            water
+                .into_iter()
+                .map(|idx| DomainCommand::AddHeat { idx, heat: share }),
+        );
+        commands
+    }
+}
+
+/// 燃烧插件
+///
+/// 将点燃、熄灭和淬火规则注册到反应规则表
+pub struct CombustionPlugin;
+
+impl Plugin for CombustionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReactionRules>();
+        let mut rules = app.world_mut().resource_mut::<ReactionRules>();
+        rules.rules.push(Box::new(IgnitionRule));
+        rules.rules.push(Box::new(WaterExtinguishRule));
+        rules.rules.push(Box::new(RainExtinguishRule));
+        rules.rules.push(Box::new(QuenchRule));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wood_ignites_above_ignition_temp() {
+        let mut chunk = ChunkData::new();
+        chunk.voxels.set(0, VoxelKind::OakLog);
+        assert!(!IgnitionRule.evaluate(&chunk, 0));
+
+        ThermalApi::set_temp(&mut chunk, 0, 350.0);
+        assert!(IgnitionRule.evaluate(&chunk, 0));
+        assert!(matches!(
+            IgnitionRule.emit_commands(&chunk, 0)[0],
+            DomainCommand::Ignite { idx: 0, .. }
+        ));
+
+        chunk.flags.update(0, |flags| flags.insert(VoxelFlags::BURNING));
+        assert!(!IgnitionRule.evaluate(&chunk, 0));
+    }
+
+    #[test]
+    fn test_stone_never_ignites() {
+        let mut chunk = ChunkData::new();
+        chunk.voxels.set(0, VoxelKind::Stone);
+        ThermalApi::set_temp(&mut chunk, 0, 1100.0);
+        assert!(!IgnitionRule.evaluate(&chunk, 0));
+    }
+
+    #[test]
+    fn test_water_extinguishes_burning_block() {
+        let mut chunk = ChunkData::new();
+        let log_idx = ChunkData::index(4, 4, 4);
+        let leaves_idx = ChunkData::index(5, 4, 4);
+        chunk.voxels.set(log_idx, VoxelKind::OakLog);
+        chunk.voxels.set(leaves_idx, VoxelKind::OakLeaves);
+        chunk.flags.update(log_idx, |flags| flags.insert(VoxelFlags::BURNING));
+        ThermalApi::set_temp(&mut chunk, log_idx, 500.0);
+        ThermalApi::set_temp(&mut chunk, leaves_idx, 200.0);
+        assert!(!WaterExtinguishRule.evaluate(&chunk, log_idx));
+
+        chunk.voxels.set(ChunkData::index(4, 5, 4), VoxelKind::Water);
+        assert!(WaterExtinguishRule.evaluate(&chunk, log_idx));
+        let commands = WaterExtinguishRule.emit_commands(&chunk, log_idx);
+        assert!(matches!(
+            commands[0],
+            DomainCommand::Extinguish { idx } if idx == log_idx
+        ));
+        assert!(commands.iter().any(|command| matches!(
+            command,
+            DomainCommand::SetTemp { idx, temp }
+                if *idx == leaves_idx && *temp == EXTINGUISHED_TEMP
+        )));
+    }
+
+    #[test]
+    fn test_rain_extinguishes_only_exposed_fires() {
+        let mut chunk = ChunkData::new();
+        let log_idx = ChunkData::index(4, 4, 4);
+        chunk.voxels.set(log_idx, VoxelKind::OakLog);
+        chunk.flags.update(log_idx, |flags| flags.insert(VoxelFlags::BURNING));
+
+        let clear = ReactionEnv { raining: false };
+        let rain = ReactionEnv { raining: true };
+        assert!(!RainExtinguishRule.enabled(&clear));
+        assert!(RainExtinguishRule.enabled(&rain));
+        assert!(RainExtinguishRule.evaluate(&chunk, log_idx));
+
+        // 上方有遮挡时天空光不是满级
+        chunk.light.set(ChunkData::index(4, 5, 4), 0);
+        assert!(!RainExtinguishRule.evaluate(&chunk, log_idx));
+    }
+
+    #[test]
+    fn test_hot_block_heats_adjacent_water() {
+        let mut chunk = ChunkData::new();
+        let stone_idx = ChunkData::index(4, 4, 4);
+        let water_idx = ChunkData::index(4, 5, 4);
+        chunk.voxels.set(stone_idx, VoxelKind::Stone);
+        chunk.voxels.set(water_idx, VoxelKind::Water);
+        ThermalApi::set_temp(&mut chunk, stone_idx, 80.0);
+        assert!(!QuenchRule.evaluate(&chunk, stone_idx));
+
+        ThermalApi::set_temp(&mut chunk, stone_idx, 600.0);
+        assert!(QuenchRule.evaluate(&chunk, stone_idx));
+        let commands = QuenchRule.emit_commands(&chunk, stone_idx);
+        assert!(matches!(
+            commands[0],
+            DomainCommand::AddHeat { idx, heat } if idx == stone_idx && heat < 0.0
+        ));
+        assert!(matches!(
+            commands[1],
+            DomainCommand::AddHeat { idx, heat } if idx == water_idx && heat > 0.0
+        ));
+    }
+}
@@ -1,14 +1,24 @@
-/// 领域命令系统
-///
-/// 定义了跨域交互的唯一接口：DomainCommand
-/// 所有领域系统只能通过产出命令来修改状态
+//! 领域命令系统
+//!
+//! 定义了跨域交互的唯一接口：DomainCommand
+//! 所有领域系统只能通过产出命令来修改状态
+//!
+//! 提交顺序是确定的：区块按坐标排序，区块内的方块按索引排序，同一方块上的命令保持提交顺序。
+//! 只要各领域系统按确定的顺序提交命令，同一初始世界和同一命令脚本总会得到相同的结果，
+//! 多人同步和回放依赖这一点
 
 use bevy::prelude::*;
 
+use std::collections::BTreeMap;
+
+use super::phase::{PhaseState, PhaseTransition};
 use super::thermal::ThermalApi;
+use super::thermal::api::is_heat_source;
 use crate::voxel::change::BlockChange;
-use crate::voxel::chunk::ChunkPos;
+use crate::voxel::chunk::{ChunkPos, ChunksMut};
+use crate::voxel::constants::CHUNK_SIZE;
 use crate::voxel::flags::VoxelFlags;
+use crate::voxel::profiling::{Stage, StageTimer};
 use crate::voxel::voxel_kind::VoxelKind;
 
 /// 统一的领域命令
@@ -52,19 +62,57 @@ pub enum DomainCommand {
     AddMoisture { idx: usize, delta: f32 },
 
     // === 燃烧操作 ===
-    /// 点燃方块
+    /// 点燃方块，power 为点燃瞬间释放的热量（J）
     Ignite { idx: usize, power: f32 },
 
     /// 熄灭方块
     Extinguish { idx: usize },
 
-    // === 相变操作（占位，后续实现）===
-    // StartPhaseTransition { idx: usize, phase: PhaseTransition },
-    // CompletePhaseTransition { idx: usize },
+    // === 相变操作 ===
+    /// 开始相变（加入活跃集合并设置状态标志）
+    StartPhaseTransition { idx: usize, phase: PhaseTransition },
+
+    /// 完成相变（替换方块并清除相变进度）
+    CompletePhaseTransition { idx: usize, new_voxel: VoxelKind },
+
+    /// 取消相变（条件不再满足，进度归零）
+    CancelPhaseTransition { idx: usize },
+
+    /// 设置相变进度
+    SetPhaseProgress { idx: usize, progress: u8 },
+
+    // === 流体操作 ===
+    /// 放置流体（替换方块并设置水位）
+    PlaceFluid {
+        idx: usize,
+        fluid: VoxelKind,
+        level: u8,
+    },
+
+    // === 结构操作 ===
+    /// 下落方块离开原位置（变为空气并清除不稳定标志）
+    Collapse { idx: usize },
+
+    /// 下落方块进入新位置（替换方块并标记为不稳定）
+    FallInto { idx: usize, voxel: VoxelKind },
+
+    // === 区域操作 ===
+    /// 把区块内 min 到 max（局部坐标，含边界）的方块全部设为 voxel
+    FillRegion {
+        min: IVec3,
+        max: IVec3,
+        voxel: VoxelKind,
+    },
+
+    /// 把区块内 min 到 max（局部坐标，含边界）所有 from 方块替换为 to
+    ReplaceRegion {
+        min: IVec3,
+        max: IVec3,
+        from: VoxelKind,
+        to: VoxelKind,
+    },
 
-    // === 结构操作（占位，后续实现）===
     // Damage { idx: usize, amount: f32 },
-    // Collapse { idx: usize },
 }
 
 /// 命令队列组件
@@ -72,7 +120,7 @@ pub enum DomainCommand {
 /// 全局单例，所有领域系统向这个队列提交命令
 #[derive(Component, Default)]
 pub struct CommandQueue {
-    pub commands: Vec<DomainCommand>,
+    pub commands: Vec<ChunkCommand>,
 }
 
 /// 带 chunk 位置的命令
@@ -85,61 +133,82 @@ pub struct ChunkCommand {
 impl CommandQueue {
     /// 添加带 chunk 位置的命令
     pub fn push(&mut self, chunk_pos: ChunkPos, command: DomainCommand) {
-        self.commands.push(command);
-        // 注意：简化实现，假设所有命令都针对同一个 chunk
-        // 实际实现需要存储 ChunkCommand
+        self.commands.push(ChunkCommand { chunk_pos, command });
     }
 }
 
 /// 统一提交系统
 ///
 /// 在 SimulationSet::Commit 阶段执行，处理所有命令
-pub fn commit_system(
-    mut voxel_world: ResMut<crate::voxel::VoxelWorld>,
-    mut command_queues: Query<&mut CommandQueue>,
-) {
+///
+/// 区块按坐标升序处理，变更日志的顺序不受哈希表遍历顺序影响
+pub fn commit_system(mut chunks: ChunksMut, mut command_queues: Query<&mut CommandQueue>) {
     // 获取命令队列（如果存在）
     let Some(mut queue) = command_queues.iter_mut().next() else {
         return;
     };
 
-    let commands: Vec<DomainCommand> = std::mem::take(&mut queue.commands);
+    let mut commands: Vec<ChunkCommand> = std::mem::take(&mut queue.commands);
 
     if commands.is_empty() {
         return;
     }
+    let _span = info_span!("commit_commands", commands = commands.len()).entered();
+    let _timer = StageTimer::start(Stage::Commit);
+
+    // 按 chunk 分组：稳定排序，同一 chunk 的命令保持提交顺序
+    commands.sort_by_key(|cmd| cmd.chunk_pos);
+    let mut per_chunk: Vec<(ChunkPos, Vec<DomainCommand>)> = Vec::new();
+    for ChunkCommand { chunk_pos, command } in commands {
+        match per_chunk.last_mut() {
+            Some((last, group)) if *last == chunk_pos => group.push(command),
+            _ => per_chunk.push((chunk_pos, vec![command])),
+        }
+    }
 
-    // 解析冲突并执行命令
-    let resolved = resolve_conflicts(commands);
+    // 在各自的 chunk 上解析冲突并执行命令（未加载的 chunk 直接丢弃）
+    // 区域命令按提交顺序先执行，单个方块的命令随后执行并覆盖区域结果
+    for (chunk_pos, commands) in per_chunk {
+        let Some(mut chunk) = chunks.get_mut(&chunk_pos) else {
+            continue;
+        };
 
-    // 遍历所有 chunk 执行命令
-    for chunk in voxel_world.chunks.values_mut() {
+        let (regions, commands): (Vec<_>, Vec<_>) =
+            commands.into_iter().partition(|cmd| cmd.idx().is_none());
+        for cmd in &regions {
+            execute_region_command(&mut chunk, cmd);
+        }
+
+        let resolved = resolve_conflicts(commands);
         for cmd in &resolved {
-            execute_command(chunk, cmd);
+            execute_command(&mut chunk, cmd);
         }
+
+        // 同一方块在一个 tick 内多次变化只保留一条脏记录
+        chunk.dirty_blocks.sort_unstable();
+        chunk.dirty_blocks.dedup();
     }
 }
 
 /// 解析命令冲突
 ///
-/// 优先级：SetBlock > 其他
+/// 优先级：方块替换（见 DomainCommand::replaces_block）> 其他
+///
+/// 结果按 idx 升序排列，同一 idx 的命令保持提交顺序
 fn resolve_conflicts(commands: Vec<DomainCommand>) -> Vec<DomainCommand> {
-    use std::collections::HashMap;
-
     // 按 idx 分组
-    let mut per_idx: HashMap<usize, Vec<DomainCommand>> = HashMap::new();
+    let mut per_idx: BTreeMap<usize, Vec<DomainCommand>> = BTreeMap::new();
     for cmd in commands {
-        let idx = cmd.idx();
+        let Some(idx) = cmd.idx() else {
+            continue;
+        };
         per_idx.entry(idx).or_default().push(cmd);
     }
 
     let mut resolved = Vec::new();
     for (_idx, cmds) in per_idx {
-        // SetBlock 优先级最高，只保留第一个
-        if let Some(set_block) = cmds
-            .iter()
-            .find(|c| matches!(c, DomainCommand::SetBlock { .. }))
-        {
+        // 方块替换优先级最高，只保留第一个
+        if let Some(set_block) = cmds.iter().find(|c| c.replaces_block()) {
             resolved.push(set_block.clone());
             continue;
         }
@@ -156,22 +225,13 @@ fn execute_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
     match cmd {
         DomainCommand::SetBlock { idx, new_voxel } => {
             if *idx < chunk.voxels.len() {
-                let old = chunk.voxels[*idx];
-                chunk.voxels[*idx] = *new_voxel;
-                chunk.changes.push(BlockChange::SetVoxel {
-                    idx: *idx,
-                    old,
-                    new: *new_voxel,
-                });
-                chunk.dirty_blocks.push(*idx);
-                chunk.needs_remesh = true;
-                chunk.is_dirty = true;
+                replace_voxel(chunk, *idx, *new_voxel, 0);
             }
         }
 
         DomainCommand::AddFlag { idx, flag } => {
             if *idx < chunk.flags.len() {
-                chunk.flags[*idx].insert(*flag);
+                chunk.flags.update(*idx, |flags| flags.insert(*flag));
                 chunk.changes.push(BlockChange::SetFlag {
                     idx: *idx,
                     flag: *flag,
@@ -183,7 +243,7 @@ fn execute_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
 
         DomainCommand::RemoveFlag { idx, flag } => {
             if *idx < chunk.flags.len() {
-                chunk.flags[*idx].remove(*flag);
+                chunk.flags.update(*idx, |flags| flags.remove(*flag));
                 chunk.changes.push(BlockChange::SetFlag {
                     idx: *idx,
                     flag: *flag,
@@ -195,8 +255,8 @@ fn execute_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
 
         DomainCommand::SetVariant { idx, variant } => {
             if *idx < chunk.variant.len() {
-                let old = chunk.variant[*idx];
-                chunk.variant[*idx] = *variant;
+                let old = chunk.variant.get(*idx);
+                chunk.variant.set(*idx, *variant);
                 chunk.changes.push(BlockChange::SetVariant {
                     idx: *idx,
                     old,
@@ -208,12 +268,12 @@ fn execute_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
 
         DomainCommand::IncrementVariant { idx } => {
             if *idx < chunk.variant.len() {
-                let old = chunk.variant[*idx];
-                chunk.variant[*idx] = old.saturating_add(1);
+                let old = chunk.variant.get(*idx);
+                chunk.variant.set(*idx, old.saturating_add(1));
                 chunk.changes.push(BlockChange::SetVariant {
                     idx: *idx,
                     old,
-                    new: chunk.variant[*idx],
+                    new: chunk.variant.get(*idx),
                 });
                 chunk.dirty_blocks.push(*idx);
             }
@@ -221,12 +281,12 @@ fn execute_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
 
         DomainCommand::DecrementVariant { idx } => {
             if *idx < chunk.variant.len() {
-                let old = chunk.variant[*idx];
-                chunk.variant[*idx] = old.saturating_sub(1);
+                let old = chunk.variant.get(*idx);
+                chunk.variant.set(*idx, old.saturating_sub(1));
                 chunk.changes.push(BlockChange::SetVariant {
                     idx: *idx,
                     old,
-                    new: chunk.variant[*idx],
+                    new: chunk.variant.get(*idx),
                 });
                 chunk.dirty_blocks.push(*idx);
             }
@@ -261,9 +321,12 @@ fn execute_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
             }
         }
 
-        DomainCommand::Ignite { idx, power: _ } => {
+        DomainCommand::Ignite { idx, power } => {
             if *idx < chunk.flags.len() {
-                chunk.flags[*idx].insert(VoxelFlags::BURNING);
+                if *power > 0.0 {
+                    ThermalApi::add_heat(chunk, *idx, *power);
+                }
+                chunk.flags.update(*idx, |flags| flags.insert(VoxelFlags::BURNING));
                 chunk.active_burning.insert(*idx);
                 chunk.changes.push(BlockChange::SetFlag {
                     idx: *idx,
@@ -276,7 +339,7 @@ fn execute_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
 
         DomainCommand::Extinguish { idx } => {
             if *idx < chunk.flags.len() {
-                chunk.flags[*idx].remove(VoxelFlags::BURNING);
+                chunk.flags.update(*idx, |flags| flags.remove(VoxelFlags::BURNING));
                 chunk.active_burning.remove(idx);
                 chunk.changes.push(BlockChange::SetFlag {
                     idx: *idx,
@@ -286,13 +349,229 @@ fn execute_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
                 chunk.dirty_blocks.push(*idx);
             }
         }
+
+        DomainCommand::StartPhaseTransition { idx, phase } => {
+            if *idx < chunk.voxels.len() {
+                match phase {
+                    // 升温相变（融化、沸腾）
+                    PhaseTransition::Melting | PhaseTransition::Boiling => {
+                        chunk.active_melting.insert(*idx);
+                    }
+                    // 降温相变（冻结）
+                    PhaseTransition::Freezing => {
+                        chunk.active_freezing.insert(*idx);
+                    }
+                }
+                if let Some(flag) = phase.flag() {
+                    set_flag(chunk, *idx, flag, true);
+                }
+            }
+        }
+
+        DomainCommand::CompletePhaseTransition { idx, new_voxel } => {
+            if *idx < chunk.voxels.len() {
+                replace_voxel(chunk, *idx, *new_voxel, 0);
+            }
+        }
+
+        DomainCommand::CancelPhaseTransition { idx } => {
+            if *idx < chunk.voxels.len() {
+                clear_phase_state(chunk, *idx);
+            }
+        }
+
+        DomainCommand::SetPhaseProgress { idx, progress } => {
+            if *idx < chunk.voxels.len() {
+                chunk
+                    .phase_state
+                    .get_or_insert_with(PhaseState::default)
+                    .set(*idx, *progress);
+            }
+        }
+
+        DomainCommand::PlaceFluid { idx, fluid, level } => {
+            if *idx < chunk.voxels.len() {
+                replace_voxel(chunk, *idx, *fluid, *level);
+            }
+        }
+
+        DomainCommand::Collapse { idx } => {
+            if *idx < chunk.voxels.len() {
+                set_flag(chunk, *idx, VoxelFlags::UNSTABLE, false);
+                replace_voxel(chunk, *idx, VoxelKind::Air, 0);
+            }
+        }
+
+        DomainCommand::FallInto { idx, voxel } => {
+            if *idx < chunk.voxels.len() {
+                replace_voxel(chunk, *idx, *voxel, 0);
+                set_flag(chunk, *idx, VoxelFlags::UNSTABLE, true);
+            }
+        }
+
+        DomainCommand::FillRegion { .. } | DomainCommand::ReplaceRegion { .. } => {
+            execute_region_command(chunk, cmd);
+        }
+    }
+}
+
+/// 执行区域命令：一次遍历区域，连续下标上的修改合并为一条 FillRun 变更
+///
+/// 每个被替换的方块和单个 SetBlock 一样重置自身状态（相变进度、热源、变体），
+/// 但这些重置不再单独记录，由 FillRun 隐含
+fn execute_region_command(chunk: &mut crate::voxel::ChunkData, cmd: &DomainCommand) {
+    let (min, max, from, new_voxel) = match *cmd {
+        DomainCommand::FillRegion { min, max, voxel } => (min, max, None, voxel),
+        DomainCommand::ReplaceRegion { min, max, from, to } => (min, max, Some(from), to),
+        _ => return,
+    };
+    let min = min.max(IVec3::ZERO);
+    let max = max.min(IVec3::splat(CHUNK_SIZE - 1));
+
+    // 按 y、z、x 的顺序遍历，下标递增，同一行内的修改自然连成一段
+    let mut changed = Vec::new();
+    for y in min.y..=max.y {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let idx = crate::voxel::ChunkData::index(x, y, z);
+                let old = chunk.voxels.get(idx);
+                let matches = match from {
+                    Some(from) => old == from,
+                    None => true,
+                };
+                if matches && old != new_voxel {
+                    reset_voxel(chunk, idx, new_voxel);
+                    changed.push(idx);
+                }
+            }
+        }
+    }
+    if changed.is_empty() {
+        return;
+    }
+
+    for (start, len) in index_runs(&changed) {
+        chunk.changes.push(BlockChange::FillRun {
+            start,
+            len,
+            new: new_voxel,
+        });
+    }
+    chunk.dirty_blocks.extend(changed);
+    chunk.needs_remesh = true;
+    chunk.is_dirty = true;
+}
+
+/// 把递增的下标序列拆成连续段，返回每段的起点和长度
+fn index_runs(indices: &[usize]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for &idx in indices {
+        match runs.last_mut() {
+            Some((start, len)) if *start + *len == idx => *len += 1,
+            _ => runs.push((idx, 1)),
+        }
+    }
+    runs
+}
+
+/// 随方块一起消失的燃烧标志
+const COMBUSTION_FLAGS: [VoxelFlags; 2] = [VoxelFlags::BURNING, VoxelFlags::SMOLDERING];
+
+/// 替换方块类型并重置方块自身状态
+///
+/// variant 的含义由方块类型决定，替换时一并写入新值；旧方块的相变进度和燃烧状态随之作废
+fn replace_voxel(chunk: &mut crate::voxel::ChunkData, idx: usize, new_voxel: VoxelKind, variant: u8) {
+    clear_phase_state(chunk, idx);
+    for flag in COMBUSTION_FLAGS {
+        set_flag(chunk, idx, flag, false);
+    }
+    chunk.active_burning.remove(&idx);
+
+    let old = chunk.voxels.get(idx);
+    chunk.voxels.set(idx, new_voxel);
+    if is_heat_source(new_voxel) {
+        chunk.active_heat_sources.insert(idx);
+    } else {
+        chunk.active_heat_sources.remove(&idx);
+    }
+    chunk.changes.push(BlockChange::SetVoxel {
+        idx,
+        old,
+        new: new_voxel,
+    });
+
+    let old_variant = chunk.variant.get(idx);
+    if old_variant != variant {
+        chunk.variant.set(idx, variant);
+        chunk.changes.push(BlockChange::SetVariant {
+            idx,
+            old: old_variant,
+            new: variant,
+        });
+    }
+
+    chunk.dirty_blocks.push(idx);
+    chunk.needs_remesh = true;
+    chunk.is_dirty = true;
+}
+
+/// 替换方块并重置方块自身状态，不写变更日志（FillRun 的语义）
+fn reset_voxel(chunk: &mut crate::voxel::ChunkData, idx: usize, new_voxel: VoxelKind) {
+    clear_phase_progress(chunk, idx);
+    for flag in PhaseTransition::ALL_FLAGS
+        .into_iter()
+        .chain(COMBUSTION_FLAGS)
+    {
+        chunk.flags.update(idx, |flags| flags.remove(flag));
+    }
+    chunk.active_burning.remove(&idx);
+
+    chunk.voxels.set(idx, new_voxel);
+    chunk.variant.set(idx, 0);
+    if is_heat_source(new_voxel) {
+        chunk.active_heat_sources.insert(idx);
+    } else {
+        chunk.active_heat_sources.remove(&idx);
+    }
+}
+
+/// 设置/清除标志位并记录变更（状态未变化时不记录）
+fn set_flag(chunk: &mut crate::voxel::ChunkData, idx: usize, flag: VoxelFlags, set: bool) {
+    if chunk.flags.get(idx).contains(flag) == set {
+        return;
+    }
+    chunk.flags.update(idx, |flags| flags.set(flag, set));
+    chunk.changes.push(BlockChange::SetFlag { idx, flag, set });
+    chunk.dirty_blocks.push(idx);
+}
+
+/// 清除方块的相变进度、相变标志和活跃集合成员
+fn clear_phase_state(chunk: &mut crate::voxel::ChunkData, idx: usize) {
+    for flag in PhaseTransition::ALL_FLAGS {
+        set_flag(chunk, idx, flag, false);
+    }
+    clear_phase_progress(chunk, idx);
+}
+
+/// 清除方块的相变进度和活跃集合成员（不涉及标志位）
+fn clear_phase_progress(chunk: &mut crate::voxel::ChunkData, idx: usize) {
+    chunk.active_melting.remove(&idx);
+    chunk.active_freezing.remove(&idx);
+    if let Some(state) = &mut chunk.phase_state {
+        state.set(idx, 0);
+        if state.is_empty() {
+            chunk.phase_state = None;
+        }
     }
 }
 
 impl DomainCommand {
-    /// 获取命令影响的方块索引
-    pub fn idx(&self) -> usize {
-        match self {
+    /// 获取命令影响的方块索引（区域命令返回 None）
+    pub fn idx(&self) -> Option<usize> {
+        let idx = match self {
+            DomainCommand::FillRegion { .. } | DomainCommand::ReplaceRegion { .. } => {
+                return None;
+            }
             DomainCommand::SetBlock { idx, .. } => *idx,
             DomainCommand::AddFlag { idx, .. } => *idx,
             DomainCommand::RemoveFlag { idx, .. } => *idx,
@@ -305,6 +584,179 @@ impl DomainCommand {
             DomainCommand::AddMoisture { idx, .. } => *idx,
             DomainCommand::Ignite { idx, .. } => *idx,
             DomainCommand::Extinguish { idx } => *idx,
+            DomainCommand::StartPhaseTransition { idx, .. } => *idx,
+            DomainCommand::CompletePhaseTransition { idx, .. } => *idx,
+            DomainCommand::CancelPhaseTransition { idx } => *idx,
+            DomainCommand::SetPhaseProgress { idx, .. } => *idx,
+            DomainCommand::PlaceFluid { idx, .. } => *idx,
+            DomainCommand::Collapse { idx } => *idx,
+            DomainCommand::FallInto { idx, .. } => *idx,
+        };
+        Some(idx)
+    }
+
+    /// 判断命令是否会替换方块类型
+    pub fn replaces_block(&self) -> bool {
+        matches!(
+            self,
+            DomainCommand::SetBlock { .. }
+                | DomainCommand::CompletePhaseTransition { .. }
+                | DomainCommand::PlaceFluid { .. }
+                | DomainCommand::Collapse { .. }
+                | DomainCommand::FallInto { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::ChunkData;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_index_runs() {
+        assert_eq!(index_runs(&[]), vec![]);
+        assert_eq!(
+            index_runs(&[3, 4, 5, 9, 10, 20]),
+            vec![(3, 3), (9, 2), (20, 1)]
+        );
+    }
+
+    #[test]
+    fn test_fill_region_logs_one_change_per_run() {
+        let mut chunk = ChunkData::new();
+        chunk.changes.clear();
+        execute_command(
+            &mut chunk,
+            &DomainCommand::FillRegion {
+                min: IVec3::new(0, 0, 0),
+                max: IVec3::new(15, 0, 1),
+                voxel: VoxelKind::Stone,
+            },
+        );
+
+        // 两行 x 方向的方块在下标上首尾相接，合并为一段
+        assert_eq!(
+            chunk.changes,
+            vec![BlockChange::FillRun {
+                start: 0,
+                len: 32,
+                new: VoxelKind::Stone,
+            }]
+        );
+        assert_eq!(chunk.dirty_blocks.len(), 32);
+        let voxel_at = |x, y, z| chunk.voxels.get(ChunkData::index(x, y, z));
+        assert_eq!(voxel_at(15, 0, 1), VoxelKind::Stone);
+        assert_eq!(voxel_at(0, 1, 0), VoxelKind::Air);
+    }
+
+    #[test]
+    fn test_replace_region_skips_other_kinds() {
+        let mut chunk = ChunkData::new();
+        for x in [1, 2, 4] {
+            chunk.voxels.set(ChunkData::index(x, 0, 0), VoxelKind::Stone);
         }
+        chunk.changes.clear();
+        execute_command(
+            &mut chunk,
+            &DomainCommand::ReplaceRegion {
+                min: IVec3::ZERO,
+                max: IVec3::new(15, 0, 0),
+                from: VoxelKind::Stone,
+                to: VoxelKind::Lava,
+            },
+        );
+
+        assert_eq!(chunk.changes.len(), 2);
+        assert_eq!(chunk.changes[0].indices(), 1..3);
+        assert_eq!(chunk.changes[1].indices(), 4..5);
+        assert_eq!(chunk.voxels.get(ChunkData::index(3, 0, 0)), VoxelKind::Air);
+        assert!(chunk.active_heat_sources.contains(&ChunkData::index(4, 0, 0)));
+    }
+
+    #[test]
+    fn test_replacing_burning_block_puts_out_fire() {
+        let mut chunk = ChunkData::new();
+        let log = ChunkData::index(1, 0, 0);
+        let smoldering = ChunkData::index(2, 0, 0);
+        for idx in [log, smoldering] {
+            chunk.voxels.set(idx, VoxelKind::OakLog);
+        }
+        execute_command(
+            &mut chunk,
+            &DomainCommand::Ignite {
+                idx: log,
+                power: 0.0,
+            },
+        );
+        chunk
+            .flags
+            .update(smoldering, |flags| flags.insert(VoxelFlags::SMOLDERING));
+        chunk.active_burning.insert(smoldering);
+        chunk.changes.clear();
+
+        // 打掉燃烧的原木
+        execute_command(
+            &mut chunk,
+            &DomainCommand::SetBlock {
+                idx: log,
+                new_voxel: VoxelKind::Air,
+            },
+        );
+        assert_eq!(chunk.flags.get(log), VoxelFlags::NONE);
+        assert!(chunk.changes.contains(&BlockChange::SetFlag {
+            idx: log,
+            flag: VoxelFlags::BURNING,
+            set: false,
+        }));
+        assert_eq!(chunk.active_burning, HashSet::from([smoldering]));
+
+        // 区域填充同样清除燃烧状态
+        execute_command(
+            &mut chunk,
+            &DomainCommand::FillRegion {
+                min: IVec3::ZERO,
+                max: IVec3::new(3, 0, 0),
+                voxel: VoxelKind::Stone,
+            },
+        );
+        assert_eq!(chunk.flags.get(smoldering), VoxelFlags::NONE);
+        assert!(chunk.active_burning.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_conflicts_orders_by_index() {
+        let resolved = resolve_conflicts(vec![
+            DomainCommand::AddHeat { idx: 9, heat: 1.0 },
+            DomainCommand::SetVariant { idx: 2, variant: 1 },
+            DomainCommand::SetBlock {
+                idx: 9,
+                new_voxel: VoxelKind::Stone,
+            },
+            DomainCommand::IncrementVariant { idx: 2 },
+            DomainCommand::SetBlock {
+                idx: 9,
+                new_voxel: VoxelKind::Sand,
+            },
+        ]);
+
+        // idx 升序；同一 idx 上保持提交顺序，方块替换只保留第一个
+        assert_eq!(resolved.len(), 3);
+        assert!(matches!(
+            resolved[0],
+            DomainCommand::SetVariant { idx: 2, .. }
+        ));
+        assert!(matches!(
+            resolved[1],
+            DomainCommand::IncrementVariant { idx: 2 }
+        ));
+        assert!(matches!(
+            resolved[2],
+            DomainCommand::SetBlock {
+                idx: 9,
+                new_voxel: VoxelKind::Stone
+            }
+        ));
     }
 }
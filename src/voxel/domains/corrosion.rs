@@ -0,0 +1,289 @@
+//! 腐蚀领域模块
+//!
+//! 接触水分的金属会慢慢生锈：与水相邻或带有 WET 标志的铁矿石进入 active_corrosion，
+//! 由反应系统每 CORROSION_INTERVAL_TICKS 个 tick 评估一次 CorrosionRule。
+//!
+//! ## 腐蚀进度
+//!
+//! 腐蚀程度记录在变体字节中，每次评估增加 CORROSION_RATE × (1 - corrosion_resistance)，
+//! 抗腐蚀性越低锈得越快。进度分为 CORROSION_STAGES 个阶段，网格构建时按阶段把颜色
+//! 向锈蚀形式混合；进度达到 CORRODED_AT 时方块替换为锈蚀形式（铁矿石 → 锈铁）。
+//!
+//! 水分只检查同一区块内的邻居，离开水后腐蚀停止，已有的锈迹保留。
+
+use bevy::prelude::*;
+
+use super::command::{commit_system, DomainCommand};
+use super::reaction::{ReactionRule, ReactionRules};
+use super::thermal::api::{get_valid_neighbor_indices, idx_to_xyz};
+use super::SimulationSet;
+use crate::voxel::change::BlockChange;
+use crate::voxel::chunk::{ChunkData, ChunksMut, VoxelWorld};
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 两次腐蚀评估之间的 tick 数（FixedUpdate 默认 64Hz，约 4 秒）
+pub const CORROSION_INTERVAL_TICKS: u32 = 256;
+
+/// 抗腐蚀性为 0 的金属每次评估增加的腐蚀进度
+const CORROSION_RATE: f32 = 10.0;
+
+/// 腐蚀进度达到此值时方块完全锈蚀（铁矿石约 2.5 分钟）
+pub const CORRODED_AT: u8 = 250;
+
+/// 完全锈蚀前可见的颜色阶段数
+pub const CORROSION_STAGES: u8 = 3;
+
+/// 六个方向的邻居偏移
+const NEIGHBOR_DIRS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// 方块完全锈蚀后变成的方块，None 表示不会生锈
+///
+/// 以后加入的铁制方块在这里登记各自的锈蚀形式
+pub fn corroded_form(kind: VoxelKind) -> Option<VoxelKind> {
+    match kind {
+        VoxelKind::IronOre => Some(VoxelKind::RustedIron),
+        _ => None,
+    }
+}
+
+/// 方块是否接触水分（带 WET 标志，或与同一区块内的水相邻）
+pub fn is_moist(chunk: &ChunkData, idx: usize) -> bool {
+    chunk.flags.get(idx).contains(VoxelFlags::WET)
+        || get_valid_neighbor_indices(idx)
+            .into_iter()
+            .any(|neighbor_idx| chunk.voxels.get(neighbor_idx) == VoxelKind::Water)
+}
+
+/// 方块是否正在腐蚀
+pub fn is_corroding(chunk: &ChunkData, idx: usize) -> bool {
+    let kind = chunk.voxels.get(idx);
+    corroded_form(kind).is_some() && corrosion_step(kind) > 0 && is_moist(chunk, idx)
+}
+
+/// 每次评估增加的腐蚀进度
+fn corrosion_step(kind: VoxelKind) -> u8 {
+    let resistance = kind.def().props.corrosion_resistance.clamp(0.0, 1.0);
+    (CORROSION_RATE * (1.0 - resistance)).round() as u8
+}
+
+/// 腐蚀进度所处的颜色阶段（0 为未生锈，最大为 CORROSION_STAGES）
+pub fn corrosion_stage(variant: u8) -> u8 {
+    (variant as u32 * (CORROSION_STAGES as u32 + 1) / CORRODED_AT as u32) as u8
+}
+
+/// 颜色向锈蚀形式混合的比例，随阶段递增，完全锈蚀时才替换为锈蚀形式的颜色
+pub fn rust_tint(variant: u8) -> f32 {
+    corrosion_stage(variant) as f32 / (CORROSION_STAGES + 1) as f32
+}
+
+/// 腐蚀规则
+///
+/// 推进接触水分的金属的腐蚀进度，完全锈蚀时替换为锈蚀形式
+pub struct CorrosionRule;
+
+impl ReactionRule for CorrosionRule {
+    fn evaluate(&self, chunk: &ChunkData, idx: usize) -> bool {
+        is_corroding(chunk, idx)
+    }
+
+    fn emit_commands(&self, chunk: &ChunkData, idx: usize) -> Vec<DomainCommand> {
+        let kind = chunk.voxels.get(idx);
+        let Some(new_voxel) = corroded_form(kind) else {
+            return vec![];
+        };
+
+        let variant = chunk.variant.get(idx).saturating_add(corrosion_step(kind));
+        if variant >= CORRODED_AT {
+            vec![DomainCommand::SetBlock { idx, new_voxel }]
+        } else {
+            vec![DomainCommand::SetVariant { idx, variant }]
+        }
+    }
+}
+
+/// 腐蚀唤醒系统
+///
+/// 在提交之后执行：方块或 WET 标志变化时，变化处及其邻居中开始腐蚀的金属加入
+/// active_corrosion；有变化的区块顺便移除已经不再腐蚀的方块（锈透了或离开了水）
+pub fn corrosion_wake_system(mut chunks: ChunksMut) {
+    let mut to_wake = Vec::new();
+    let mut changed_chunks = Vec::new();
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        let origin = chunk_pos.world_origin();
+        let mut changed = false;
+        for change in &chunk.changes {
+            let wakes = match *change {
+                BlockChange::SetVoxel { .. } | BlockChange::FillRun { .. } => true,
+                BlockChange::SetFlag { flag, .. } => flag == VoxelFlags::WET,
+                _ => false,
+            };
+            if !wakes {
+                continue;
+            }
+            changed = true;
+            for idx in change.indices() {
+                let (x, y, z) = idx_to_xyz(idx);
+                let pos = origin + IVec3::new(x, y, z);
+                to_wake.push(pos);
+                to_wake.extend(NEIGHBOR_DIRS.map(|dir| pos + dir));
+            }
+        }
+        if changed && !chunk.active_corrosion.is_empty() {
+            changed_chunks.push(chunk_pos);
+        }
+    }
+
+    for chunk_pos in changed_chunks {
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos) {
+            let corroding: Vec<usize> = chunk
+                .active_corrosion
+                .iter()
+                .copied()
+                .filter(|&idx| is_corroding(&chunk, idx))
+                .collect();
+            chunk.active_corrosion = corroding.into_iter().collect();
+        }
+    }
+
+    for pos in to_wake {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos)
+            && is_corroding(&chunk, idx)
+        {
+            chunk.active_corrosion.insert(idx);
+        }
+    }
+}
+
+/// 腐蚀插件
+///
+/// 将腐蚀规则注册到反应规则表，并在提交后唤醒接触水分的金属
+pub struct CorrosionPlugin;
+
+impl Plugin for CorrosionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReactionRules>();
+        app.world_mut()
+            .resource_mut::<ReactionRules>()
+            .rules
+            .push(Box::new(CorrosionRule));
+
+        app.add_systems(
+            FixedUpdate,
+            corrosion_wake_system
+                .in_set(SimulationSet::Commit)
+                .after(commit_system),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::{spawn_chunk, ChunkLookup, ChunkPos};
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_iron_corrodes_only_when_moist() {
+        let mut chunk = ChunkData::new();
+        let idx = ChunkData::index(4, 4, 4);
+        chunk.voxels.set(idx, VoxelKind::IronOre);
+        assert!(!CorrosionRule.evaluate(&chunk, idx));
+
+        chunk
+            .voxels
+            .set(ChunkData::index(5, 4, 4), VoxelKind::Water);
+        assert!(CorrosionRule.evaluate(&chunk, idx));
+        assert!(matches!(
+            CorrosionRule.emit_commands(&chunk, idx)[0],
+            DomainCommand::SetVariant { variant: 7, .. }
+        ));
+
+        chunk.voxels.set(ChunkData::index(5, 4, 4), VoxelKind::Air);
+        chunk.flags.set(idx, VoxelFlags::WET);
+        assert!(CorrosionRule.evaluate(&chunk, idx));
+    }
+
+    #[test]
+    fn test_resistant_metals_do_not_corrode() {
+        let mut chunk = ChunkData::new();
+        let idx = ChunkData::index(4, 4, 4);
+        chunk.voxels.set(idx, VoxelKind::GoldOre);
+        chunk.flags.set(idx, VoxelFlags::WET);
+        assert!(!CorrosionRule.evaluate(&chunk, idx));
+
+        chunk.voxels.set(idx, VoxelKind::RustedIron);
+        assert!(!CorrosionRule.evaluate(&chunk, idx));
+    }
+
+    #[test]
+    fn test_fully_corroded_iron_turns_to_rust() {
+        let mut chunk = ChunkData::new();
+        let idx = ChunkData::index(4, 4, 4);
+        chunk.voxels.set(idx, VoxelKind::IronOre);
+        chunk.flags.set(idx, VoxelFlags::WET);
+        chunk.variant.set(idx, CORRODED_AT - 1);
+
+        assert!(matches!(
+            CorrosionRule.emit_commands(&chunk, idx)[0],
+            DomainCommand::SetBlock {
+                new_voxel: VoxelKind::RustedIron,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_tint_advances_by_stage() {
+        assert_eq!(rust_tint(0), 0.0);
+        assert_eq!(corrosion_stage(62), 0);
+        assert_eq!(corrosion_stage(63), 1);
+        assert_eq!(corrosion_stage(CORRODED_AT - 1), CORROSION_STAGES);
+        assert!(rust_tint(CORRODED_AT - 1) < 1.0);
+    }
+
+    #[test]
+    fn test_placing_water_wakes_adjacent_iron() {
+        let chunk_pos = ChunkPos::new(0, 0, 0);
+        let iron = ChunkData::index(4, 4, 4);
+        let water = ChunkData::index(4, 5, 4);
+        let mut chunk = ChunkData::new();
+        chunk.voxels.set(iron, VoxelKind::IronOre);
+        chunk.voxels.set(water, VoxelKind::Water);
+        chunk.changes.push(BlockChange::SetVoxel {
+            idx: water,
+            old: VoxelKind::Air,
+            new: VoxelKind::Water,
+        });
+
+        let mut ecs = World::new();
+        let entity = spawn_chunk(&mut ecs, chunk_pos, chunk);
+        ecs.run_system_once(corrosion_wake_system).unwrap();
+        assert!(ecs
+            .chunk(&chunk_pos)
+            .unwrap()
+            .active_corrosion
+            .contains(&iron));
+
+        // 水被移走后，下一次变化把不再腐蚀的方块移出集合
+        let mut chunk = ecs.get_mut::<ChunkData>(entity).unwrap();
+        chunk.clear_changes();
+        chunk.voxels.set(water, VoxelKind::Air);
+        chunk.changes.push(BlockChange::SetVoxel {
+            idx: water,
+            old: VoxelKind::Water,
+            new: VoxelKind::Air,
+        });
+        ecs.run_system_once(corrosion_wake_system).unwrap();
+        assert!(ecs.chunk(&chunk_pos).unwrap().active_corrosion.is_empty());
+    }
+}
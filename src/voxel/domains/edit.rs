@@ -0,0 +1,444 @@
+//! 世界编辑接口
+//!
+//! 玩家交互、控制台命令和脚本修改方块的唯一入口：WorldEditApi 只把命令按区块放进
+//! 命令队列，由 Commit 阶段统一执行。这样外部修改和模拟产生的修改走同一条路径——
+//! 写入 BlockChange 变更日志、标记网格重建、参与网络同步和存档。
+//!
+//! 单个方块提交 SetBlock，区域编辑按区块拆分，每个区块只提交一条 FillRegion /
+//! ReplaceRegion。玩家发起的编辑经由 [`PlayerEditApi`](super::history::PlayerEditApi)
+//! 提交，同时记入撤销历史
+
+use bevy::ecs::system::SystemParam;
+use bevy::math::I64Vec3;
+use bevy::prelude::*;
+
+use super::command::{CommandQueue, DomainCommand};
+use super::history::PlayerEditApi;
+use super::SimulationSet;
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::voxel::change::BlockChange;
+use crate::voxel::chunk::{ChunkData, ChunkPos, Chunks, VoxelWorld};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 单次区域编辑最多涉及的方块数
+pub const MAX_EDIT_VOLUME: usize = 64 * 64 * 64;
+
+/// 世界编辑失败的原因
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EditError {
+    #[error("方块 {0} 所在的区块未加载")]
+    ChunkNotLoaded(IVec3),
+    #[error("区域包含 {volume} 个方块，超过上限 {max}")]
+    RegionTooLarge { volume: usize, max: usize },
+    #[error("方块 {0} 被占据")]
+    Obstructed(IVec3),
+    #[error("命令队列尚未创建")]
+    NoCommandQueue,
+}
+
+/// 世界编辑接口
+///
+/// 修改在当帧之后的第一次 Commit 阶段生效；未加载区块内的方块不会被修改
+#[derive(SystemParam)]
+pub struct WorldEditApi<'w, 's> {
+    chunks: Chunks<'w, 's>,
+    queues: Query<'w, 's, &'static mut CommandQueue>,
+}
+
+impl WorldEditApi<'_, '_> {
+    /// 读取方块，所在区块未加载时返回 None
+    pub fn voxel(&self, pos: IVec3) -> Option<VoxelKind> {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        self.chunks
+            .get(&chunk_pos)
+            .map(|chunk| chunk.voxels.get(idx))
+    }
+
+    /// 设置单个方块
+    pub fn set_block(&mut self, pos: IVec3, kind: VoxelKind) -> Result<(), EditError> {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        if !self.chunks.contains(&chunk_pos) {
+            return Err(EditError::ChunkNotLoaded(pos));
+        }
+        let mut queue = self
+            .queues
+            .single_mut()
+            .map_err(|_| EditError::NoCommandQueue)?;
+        queue.push(
+            chunk_pos,
+            DomainCommand::SetBlock {
+                idx,
+                new_voxel: kind,
+            },
+        );
+        Ok(())
+    }
+
+    /// 以 origin 为原点放置一组方块（偏移、方块类型），可以跨越区块边界
+    ///
+    /// 要么全部提交，要么一个都不提交：任一位置所在区块未加载或 can_replace
+    /// 拒绝该位置的原有方块时返回错误
+    pub fn place_blocks(
+        &mut self,
+        origin: IVec3,
+        blocks: &[(IVec3, VoxelKind)],
+        can_replace: impl Fn(VoxelKind) -> bool,
+    ) -> Result<(), EditError> {
+        if blocks.len() > MAX_EDIT_VOLUME {
+            return Err(EditError::RegionTooLarge {
+                volume: blocks.len(),
+                max: MAX_EDIT_VOLUME,
+            });
+        }
+        let mut commands = Vec::with_capacity(blocks.len());
+        for &(offset, new_voxel) in blocks {
+            let pos = origin + offset;
+            let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+            let Some(chunk) = self.chunks.get(&chunk_pos) else {
+                return Err(EditError::ChunkNotLoaded(pos));
+            };
+            if !can_replace(chunk.voxels.get(idx)) {
+                return Err(EditError::Obstructed(pos));
+            }
+            commands.push((chunk_pos, DomainCommand::SetBlock { idx, new_voxel }));
+        }
+
+        let mut queue = self
+            .queues
+            .single_mut()
+            .map_err(|_| EditError::NoCommandQueue)?;
+        for (chunk_pos, command) in commands {
+            queue.push(chunk_pos, command);
+        }
+        Ok(())
+    }
+
+    /// 把 a、b 两角之间（含边界）的方块全部设为 kind，返回实际提交的方块数
+    ///
+    /// 已经是 kind 的方块和未加载区块内的方块会被跳过
+    pub fn fill_region(&mut self, a: IVec3, b: IVec3, kind: VoxelKind) -> Result<usize, EditError> {
+        self.edit_region(
+            a,
+            b,
+            |current| current != kind,
+            |min, max| DomainCommand::FillRegion {
+                min,
+                max,
+                voxel: kind,
+            },
+        )
+    }
+
+    /// 把 a、b 两角之间（含边界）所有 from 方块替换为 to，返回实际提交的方块数
+    pub fn swap_kind(
+        &mut self,
+        a: IVec3,
+        b: IVec3,
+        from: VoxelKind,
+        to: VoxelKind,
+    ) -> Result<usize, EditError> {
+        self.edit_region(
+            a,
+            b,
+            |current| current == from && from != to,
+            |min, max| DomainCommand::ReplaceRegion { min, max, from, to },
+        )
+    }
+
+    /// 提交一组记录下来的方块类型变更，每条变更把方块设为其中的新方块
+    ///
+    /// 用于撤销和重做；其他种类的变更和未加载区块内的方块被跳过，返回提交的方块数
+    pub fn apply_changes(
+        &mut self,
+        changes: &[(ChunkPos, BlockChange)],
+    ) -> Result<usize, EditError> {
+        let mut queue = self
+            .queues
+            .single_mut()
+            .map_err(|_| EditError::NoCommandQueue)?;
+        let mut count = 0;
+        for (chunk_pos, change) in changes {
+            let BlockChange::SetVoxel { idx, new, .. } = *change else {
+                continue;
+            };
+            if !self.chunks.contains(chunk_pos) {
+                continue;
+            }
+            queue.push(
+                *chunk_pos,
+                DomainCommand::SetBlock {
+                    idx,
+                    new_voxel: new,
+                },
+            );
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// 区域（a、b 两角之间，含边界）内 new 会改变的方块，按区块记录为 SetVoxel 变更
+    ///
+    /// new 返回方块的新类型，不修改时返回 None；未加载区块内的方块被跳过
+    pub fn region_changes(
+        &self,
+        a: IVec3,
+        b: IVec3,
+        new: impl Fn(VoxelKind) -> Option<VoxelKind>,
+    ) -> Vec<(ChunkPos, BlockChange)> {
+        let mut changes = Vec::new();
+        for (chunk_pos, local_min, local_max) in chunk_boxes(a.min(b), a.max(b)) {
+            let Some(chunk) = self.chunks.get(&chunk_pos) else {
+                continue;
+            };
+            for y in local_min.y..=local_max.y {
+                for z in local_min.z..=local_max.z {
+                    for x in local_min.x..=local_max.x {
+                        let idx = ChunkData::index(x, y, z);
+                        let old = chunk.voxels.get(idx);
+                        if let Some(new) = new(old).filter(|&new| new != old) {
+                            changes.push((chunk_pos, BlockChange::SetVoxel { idx, old, new }));
+                        }
+                    }
+                }
+            }
+        }
+        changes
+    }
+
+    /// 按区块拆分区域，每个有方块会变化的已加载区块提交一条 command(局部 min, 局部 max)
+    ///
+    /// changes 判断方块是否会被修改，只用于统计返回的方块数和跳过无需修改的区块
+    fn edit_region(
+        &mut self,
+        a: IVec3,
+        b: IVec3,
+        changes: impl Fn(VoxelKind) -> bool,
+        command: impl Fn(IVec3, IVec3) -> DomainCommand,
+    ) -> Result<usize, EditError> {
+        let (min, max) = (a.min(b), a.max(b));
+        let volume = region_volume(min, max);
+        if volume > MAX_EDIT_VOLUME {
+            return Err(EditError::RegionTooLarge {
+                volume,
+                max: MAX_EDIT_VOLUME,
+            });
+        }
+        let mut queue = self
+            .queues
+            .single_mut()
+            .map_err(|_| EditError::NoCommandQueue)?;
+
+        let mut total = 0;
+        for (chunk_pos, local_min, local_max) in chunk_boxes(min, max) {
+            let Some(chunk) = self.chunks.get(&chunk_pos) else {
+                continue;
+            };
+            let mut count = 0;
+            for y in local_min.y..=local_max.y {
+                for z in local_min.z..=local_max.z {
+                    for x in local_min.x..=local_max.x {
+                        if changes(chunk.voxels.get(ChunkData::index(x, y, z))) {
+                            count += 1;
+                        }
+                    }
+                }
+            }
+            if count > 0 {
+                queue.push(chunk_pos, command(local_min, local_max));
+                total += count;
+            }
+        }
+        Ok(total)
+    }
+}
+
+/// 把世界区域（min 到 max，含边界）拆成各区块内的局部区域
+fn chunk_boxes(min: IVec3, max: IVec3) -> impl Iterator<Item = (ChunkPos, IVec3, IVec3)> {
+    let first = ChunkPos::from_world_pos(min.x, min.y, min.z);
+    let last = ChunkPos::from_world_pos(max.x, max.y, max.z);
+    (first.x..=last.x).flat_map(move |cx| {
+        (first.y..=last.y).flat_map(move |cy| {
+            (first.z..=last.z).map(move |cz| {
+                let chunk_pos = ChunkPos::new(cx, cy, cz);
+                let origin = chunk_pos.world_origin();
+                let local_min = (min - origin).max(IVec3::ZERO);
+                let local_max = (max - origin).min(IVec3::splat(CHUNK_SIZE - 1));
+                (chunk_pos, local_min, local_max)
+            })
+        })
+    })
+}
+
+/// 区域（min 到 max，含边界）包含的方块数
+///
+/// 先转换为 i64 再相减，乘积过大时取饱和值，任意 i32 坐标都不会溢出
+fn region_volume(min: IVec3, max: IVec3) -> usize {
+    let size = max.as_i64vec3() - min.as_i64vec3() + I64Vec3::ONE;
+    let volume = size.x.saturating_mul(size.y).saturating_mul(size.z);
+    usize::try_from(volume).unwrap_or(usize::MAX)
+}
+
+/// 世界编辑插件
+///
+/// 注册 setblock、fill、replace 三个控制台命令
+pub struct EditPlugin;
+
+impl Plugin for EditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        let mut commands = app.world_mut().resource_mut::<ConsoleCommands>();
+        commands.register("setblock", "setblock <x> <y> <z> <方块>", "设置一个方块");
+        commands.register(
+            "fill",
+            "fill <x1> <y1> <z1> <x2> <y2> <z2> <方块>",
+            "用方块填充区域",
+        );
+        commands.register(
+            "replace",
+            "replace <x1> <y1> <z1> <x2> <y2> <z2> <原方块> <新方块>",
+            "替换区域内的某种方块",
+        );
+
+        app.add_systems(
+            FixedUpdate,
+            edit_console_commands.in_set(SimulationSet::ExternalActions),
+        );
+    }
+}
+
+/// 执行世界编辑控制台命令
+///
+/// 在 ExternalActions 阶段运行，提交的命令在同一个 tick 的 Commit 阶段生效
+pub(super) fn edit_console_commands(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut edit: PlayerEditApi,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        let result = match command.name.as_str() {
+            "setblock" => parse_edit_args(&command.args, 1, 1).and_then(|(corners, kinds)| {
+                edit.set_block(corners[0], kinds[0])
+                    .map(|()| format!("已将 {} 设为{}", corners[0], kinds[0].def().name))
+                    .map_err(|err| err.to_string())
+            }),
+            "fill" => parse_edit_args(&command.args, 2, 1).and_then(|(corners, kinds)| {
+                edit.fill_region(corners[0], corners[1], kinds[0])
+                    .map(|count| format!("已填充 {count} 个方块"))
+                    .map_err(|err| err.to_string())
+            }),
+            "replace" => parse_edit_args(&command.args, 2, 2).and_then(|(corners, kinds)| {
+                edit.swap_kind(corners[0], corners[1], kinds[0], kinds[1])
+                    .map(|count| format!("已替换 {count} 个方块"))
+                    .map_err(|err| err.to_string())
+            }),
+            _ => continue,
+        };
+        match result {
+            Ok(message) => log.print(message),
+            Err(message) => log.print(format!("{}：{message}", command.name)),
+        }
+    }
+}
+
+/// 解析 corners 个坐标（每个三个整数）后跟 kinds 个方块名的参数列表
+fn parse_edit_args(
+    args: &[String],
+    corners: usize,
+    kinds: usize,
+) -> Result<(Vec<IVec3>, Vec<VoxelKind>), String> {
+    if args.len() != corners * 3 + kinds {
+        return Err(format!("需要 {} 个参数", corners * 3 + kinds));
+    }
+    let (coords, names) = args.split_at(corners * 3);
+    let coords = coords
+        .iter()
+        .map(|arg| arg.parse::<i32>().map_err(|_| format!("无效的坐标：{arg}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let positions = coords
+        .chunks(3)
+        .map(|c| IVec3::new(c[0], c[1], c[2]))
+        .collect();
+    let kinds = names
+        .iter()
+        .map(|name| VoxelKind::from_name(name).ok_or_else(|| format!("未知的方块：{name}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((positions, kinds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::ChunkIndex;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn test_region_volume() {
+        assert_eq!(region_volume(IVec3::ZERO, IVec3::ZERO), 1);
+        assert_eq!(
+            region_volume(IVec3::new(-1, 0, 0), IVec3::new(1, 2, 3)),
+            3 * 3 * 4
+        );
+        assert!(region_volume(IVec3::splat(i32::MIN), IVec3::splat(i32::MAX)) > MAX_EDIT_VOLUME);
+    }
+
+    #[test]
+    fn test_fill_with_extreme_corners_is_too_large() {
+        let mut world = World::new();
+        world.init_resource::<ChunkIndex>();
+        let result = world
+            .run_system_once(|mut edit: WorldEditApi| {
+                edit.fill_region(
+                    IVec3::splat(i32::MIN),
+                    IVec3::splat(i32::MAX),
+                    VoxelKind::Stone,
+                )
+            })
+            .unwrap();
+        assert!(matches!(result, Err(EditError::RegionTooLarge { .. })));
+    }
+
+    #[test]
+    fn test_chunk_boxes() {
+        let boxes: Vec<_> = chunk_boxes(IVec3::new(-2, 0, 5), IVec3::new(17, 3, 5)).collect();
+        assert_eq!(
+            boxes,
+            vec![
+                (
+                    ChunkPos::new(-1, 0, 0),
+                    IVec3::new(14, 0, 5),
+                    IVec3::new(15, 3, 5)
+                ),
+                (
+                    ChunkPos::new(0, 0, 0),
+                    IVec3::new(0, 0, 5),
+                    IVec3::new(15, 3, 5)
+                ),
+                (
+                    ChunkPos::new(1, 0, 0),
+                    IVec3::new(0, 0, 5),
+                    IVec3::new(1, 3, 5)
+                ),
+            ]
+        );
+
+        let volume: usize = chunk_boxes(IVec3::splat(-20), IVec3::splat(20))
+            .map(|(_, a, b)| region_volume(a, b))
+            .sum();
+        assert_eq!(volume, region_volume(IVec3::splat(-20), IVec3::splat(20)));
+    }
+
+    #[test]
+    fn test_parse_edit_args() {
+        let args: Vec<String> = ["1", "-2", "3", "4", "5", "6", "stone", "oak_log"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (corners, kinds) = parse_edit_args(&args, 2, 2).unwrap();
+        assert_eq!(corners, vec![IVec3::new(1, -2, 3), IVec3::new(4, 5, 6)]);
+        assert_eq!(kinds, vec![VoxelKind::Stone, VoxelKind::OakLog]);
+
+        assert!(parse_edit_args(&args[..7], 2, 2).is_err());
+        assert!(parse_edit_args(&args[..4], 1, 1).is_err());
+    }
+}
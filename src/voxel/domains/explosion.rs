@@ -0,0 +1,274 @@
+//! 爆炸领域模块
+//!
+//! 爆炸不直接修改区块：ExplosionApi 根据爆炸中心和威力计算出一组 DomainCommand，
+//! 和其他领域一样在 Commit 阶段统一执行。
+//!
+//! - 半径内的方块按硬度判定是否被炸毁，离中心越近冲击越强，黑曜石不会被炸毁
+//! - 幸存的方块（包括半径外一圈边缘）被加热，可燃方块达到着火点时直接点燃
+//! - 空气和液体（硬度为 0）不受影响
+//!
+//! 被炸毁的方块产生 SetVoxel 变更，由 mark_remesh_system 标记所在区块及相邻区块重建网格，
+//! 下落方块和流体也会因为这些变更被唤醒
+
+use bevy::prelude::*;
+
+use super::command::{CommandQueue, DomainCommand};
+use super::thermal::ThermalApi;
+use super::SimulationSet;
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::voxel::chunk::{ChunkLookup, ChunkPos, Chunks, VoxelWorld};
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 爆炸威力上限（威力即炸毁半径，单位为方块）
+pub const MAX_EXPLOSION_POWER: f32 = 16.0;
+/// 控制台命令未指定威力时使用的威力
+const DEFAULT_CONSOLE_POWER: f32 = 4.0;
+/// 半径外仍会被加热的边缘宽度（方块）
+const RIM_WIDTH: f32 = 2.0;
+/// 边缘内侧方块的升温幅度（°C），向外线性衰减到 0
+const RIM_TEMP_RISE: f32 = 500.0;
+
+/// 爆炸消息
+///
+/// 任何系统都可以发送，在 ExternalActions 阶段转换为命令
+#[derive(Message, Debug, Clone, Copy)]
+pub struct Explosion {
+    /// 爆炸中心（世界坐标）
+    pub center: IVec3,
+    /// 威力，即炸毁半径（方块），超过 MAX_EXPLOSION_POWER 时截断
+    pub power: f32,
+}
+
+/// 爆炸 API
+pub struct ExplosionApi;
+
+impl ExplosionApi {
+    /// 计算一次爆炸产生的命令（按区块坐标分组前的原始列表）
+    ///
+    /// 未加载区块内的方块被跳过
+    pub fn plan(
+        world: &impl ChunkLookup,
+        center: IVec3,
+        power: f32,
+    ) -> Vec<(ChunkPos, DomainCommand)> {
+        let radius = power.clamp(0.0, MAX_EXPLOSION_POWER);
+        if radius <= 0.0 {
+            return Vec::new();
+        }
+        let reach = radius + RIM_WIDTH;
+        let extent = reach.ceil() as i32;
+
+        let mut commands = Vec::new();
+        for x in -extent..=extent {
+            for y in -extent..=extent {
+                for z in -extent..=extent {
+                    let offset = IVec3::new(x, y, z);
+                    let distance = offset.as_vec3().length();
+                    if distance > reach {
+                        continue;
+                    }
+                    let (chunk_pos, idx) = VoxelWorld::split_world_pos(center + offset);
+                    let Some(chunk) = world.chunk(&chunk_pos) else {
+                        continue;
+                    };
+                    let props = chunk.voxels.get(idx).def().props;
+                    if props.hardness <= 0.0 {
+                        continue;
+                    }
+
+                    // 冲击强度从中心的 1 按距离平方衰减到半径处的 0
+                    if distance <= radius {
+                        let strength = 1.0 - (distance / radius).powi(2);
+                        if strength > props.hardness {
+                            let new_voxel = VoxelKind::Air;
+                            commands.push((chunk_pos, DomainCommand::SetBlock { idx, new_voxel }));
+                            continue;
+                        }
+                    }
+
+                    let rise = RIM_TEMP_RISE * ((reach - distance) / RIM_WIDTH).min(1.0);
+                    let heat = props.heat_capacity * rise;
+                    commands.push((chunk_pos, DomainCommand::AddHeat { idx, heat }));
+
+                    let burning = chunk.flags.get(idx).contains(VoxelFlags::BURNING);
+                    if props.is_flammable
+                        && !burning
+                        && ThermalApi::get_temp(chunk, idx) + rise >= props.ignition_temp
+                    {
+                        let power = props.heat_release;
+                        commands.push((chunk_pos, DomainCommand::Ignite { idx, power }));
+                    }
+                }
+            }
+        }
+        commands
+    }
+}
+
+/// 爆炸插件
+///
+/// 注册 Explosion 消息和 explode 控制台命令
+pub struct ExplosionPlugin;
+
+impl Plugin for ExplosionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "explode",
+            "explode <x> <y> <z> [威力]",
+            "在指定位置引发爆炸",
+        );
+
+        app.add_message::<Explosion>().add_systems(
+            FixedUpdate,
+            (explode_console_command, explosion_system)
+                .chain()
+                .in_set(SimulationSet::ExternalActions),
+        );
+    }
+}
+
+/// 把 explode 控制台命令转换为爆炸消息
+fn explode_console_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut explosions: MessageWriter<Explosion>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "explode" {
+            continue;
+        }
+        match parse_explode_args(&command.args) {
+            Ok(explosion) => {
+                log.print(format!(
+                    "在 {} 引发威力 {} 的爆炸",
+                    explosion.center, explosion.power
+                ));
+                explosions.write(explosion);
+            }
+            Err(message) => log.print(format!("explode：{message}")),
+        }
+    }
+}
+
+/// 把本 tick 的爆炸转换为命令放入命令队列
+fn explosion_system(
+    mut explosions: MessageReader<Explosion>,
+    chunks: Chunks,
+    mut queues: Query<&mut CommandQueue>,
+) {
+    let Some(mut queue) = queues.iter_mut().next() else {
+        explosions.clear();
+        return;
+    };
+    for explosion in explosions.read() {
+        for (chunk_pos, command) in ExplosionApi::plan(&chunks, explosion.center, explosion.power) {
+            queue.push(chunk_pos, command);
+        }
+    }
+}
+
+/// 解析 `<x> <y> <z> [威力]`
+fn parse_explode_args(args: &[String]) -> Result<Explosion, String> {
+    if !(3..=4).contains(&args.len()) {
+        return Err("需要 3 或 4 个参数".to_string());
+    }
+    let coords = args[..3]
+        .iter()
+        .map(|arg| arg.parse::<i32>().map_err(|_| format!("无效的坐标：{arg}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let power = match args.get(3) {
+        Some(arg) => arg
+            .parse::<f32>()
+            .ok()
+            .filter(|power| *power > 0.0 && *power <= MAX_EXPLOSION_POWER)
+            .ok_or_else(|| format!("威力应在 0 到 {MAX_EXPLOSION_POWER} 之间：{arg}"))?,
+        None => DEFAULT_CONSOLE_POWER,
+    };
+    Ok(Explosion {
+        center: IVec3::new(coords[0], coords[1], coords[2]),
+        power,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::ChunkData;
+    use std::collections::HashMap;
+
+    /// 一个填满 kind 的区块，位于原点
+    fn world_of(kind: VoxelKind) -> HashMap<ChunkPos, ChunkData> {
+        let mut chunk = ChunkData::new();
+        for idx in 0..ChunkData::VOXEL_COUNT {
+            chunk.voxels.set(idx, kind);
+        }
+        let mut world = HashMap::new();
+        world.insert(ChunkPos::new(0, 0, 0), chunk);
+        world
+    }
+
+    fn removed(commands: &[(ChunkPos, DomainCommand)]) -> usize {
+        commands
+            .iter()
+            .filter(|(_, cmd)| matches!(cmd, DomainCommand::SetBlock { .. }))
+            .count()
+    }
+
+    #[test]
+    fn test_soft_blocks_break_further_than_hard_ones() {
+        let center = IVec3::splat(8);
+        let dirt = ExplosionApi::plan(&world_of(VoxelKind::Dirt), center, 5.0);
+        let stone = ExplosionApi::plan(&world_of(VoxelKind::Stone), center, 5.0);
+        assert!(removed(&stone) > 0);
+        assert!(removed(&dirt) > removed(&stone));
+
+        let obsidian = ExplosionApi::plan(&world_of(VoxelKind::Obsidian), center, 5.0);
+        assert_eq!(removed(&obsidian), 0);
+        assert!(obsidian
+            .iter()
+            .all(|(_, cmd)| matches!(cmd, DomainCommand::AddHeat { .. })));
+    }
+
+    #[test]
+    fn test_rim_heats_and_ignites_wood() {
+        let commands = ExplosionApi::plan(&world_of(VoxelKind::OakLog), IVec3::splat(8), 3.0);
+        let (_, rim) = VoxelWorld::split_world_pos(IVec3::new(11, 8, 8));
+        assert!(commands
+            .iter()
+            .any(|(_, cmd)| matches!(cmd, DomainCommand::Ignite { idx, .. } if *idx == rim)));
+
+        // 边缘之外不受影响
+        let (_, outside) = VoxelWorld::split_world_pos(IVec3::new(14, 8, 8));
+        assert!(commands.iter().all(|(_, cmd)| cmd.idx() != Some(outside)));
+    }
+
+    #[test]
+    fn test_air_and_unloaded_chunks_are_skipped() {
+        assert!(ExplosionApi::plan(&world_of(VoxelKind::Air), IVec3::splat(8), 4.0).is_empty());
+        assert!(ExplosionApi::plan(&HashMap::new(), IVec3::ZERO, 4.0).is_empty());
+    }
+
+    #[test]
+    fn test_parse_explode_args() {
+        let args: Vec<String> = ["1", "-2", "3", "6"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let explosion = parse_explode_args(&args).unwrap();
+        assert_eq!(explosion.center, IVec3::new(1, -2, 3));
+        assert_eq!(explosion.power, 6.0);
+        assert_eq!(
+            parse_explode_args(&args[..3]).unwrap().power,
+            DEFAULT_CONSOLE_POWER
+        );
+        assert!(parse_explode_args(&args[..2]).is_err());
+
+        let too_strong: Vec<String> = ["0", "0", "0", "100"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(parse_explode_args(&too_strong).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! 流体领域模块
+//!
+//! 模拟水的流动：
+//! - 水位存储在方块的 variant 字节中：0 表示水源，1..=MAX_FLOW_LEVEL 表示流动水
+//!   （数值越大离水源越远，水面越低）
+//! - 每个 tick 活跃的水方块优先向下流动，落地后向水平相邻的空气扩散一格
+//! - 流动水依赖上方或水平方向的补给，水源被移除后会逐格干涸
+//! - 可以跨区块流动，但不会流入未加载的区块
+//!
+//! ## 激活
+//!
+//! 只检查 active_fluid 中的方块。提交阶段之后，方块或水位发生变化的位置
+//! 及其六个邻居中的水方块会被重新唤醒，稳定的水面不产生任何开销。
+
+use bevy::prelude::*;
+
+use super::command::{commit_system, CommandQueue, DomainCommand};
+use super::thermal::api::idx_to_xyz;
+use super::SimulationSet;
+use crate::voxel::change::BlockChange;
+use crate::voxel::chunk::{ChunkLookup, ChunkPos, ChunksMut, VoxelWorld};
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 流动水的最大水位，达到后不再水平扩散
+pub const MAX_FLOW_LEVEL: u8 = 7;
+
+/// 水平扩散方向
+const HORIZONTAL_DIRS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// 唤醒时检查的邻居方向
+const NEIGHBOR_DIRS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// 判断方块是否为流体
+pub fn is_fluid(kind: VoxelKind) -> bool {
+    kind == VoxelKind::Water
+}
+
+/// 判断流体能否流入该方块（空气、花草和积雪会被冲掉）
+pub fn can_flow_into(kind: VoxelKind) -> bool {
+    matches!(
+        kind,
+        VoxelKind::Air
+            | VoxelKind::Flower
+            | VoxelKind::TallGrass
+            | VoxelKind::DeadBush
+            | VoxelKind::Sapling
+            | VoxelKind::GlowMushroom
+            | VoxelKind::SnowLayer
+    )
+}
+
+/// 水位对应的水面高度（方块内 0.0-1.0）
+pub fn fluid_height(level: u8) -> f32 {
+    let level = level.min(MAX_FLOW_LEVEL);
+    1.0 - level as f32 / (MAX_FLOW_LEVEL + 1) as f32
+}
+
+/// 水源水面在方块内的高度，略低于方块顶面，水面与岸边方块之间留出一道边
+pub const WATER_SURFACE_HEIGHT: f32 = 0.9;
+
+/// 上方没有水时水面的渲染高度：按水位降低，且不高于 [`WATER_SURFACE_HEIGHT`]
+pub fn surface_height(level: u8) -> f32 {
+    fluid_height(level).min(WATER_SURFACE_HEIGHT)
+}
+
+/// 读取世界坐标处的方块类型和 variant，区块未加载时返回 None
+fn voxel_at(world: &impl ChunkLookup, pos: IVec3) -> Option<(VoxelKind, u8)> {
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+    world
+        .chunk(&chunk_pos)
+        .map(|chunk| (chunk.voxels.get(idx), chunk.variant.get(idx)))
+}
+
+/// 计算流动水在当前补给下应有的水位
+///
+/// - 上方有水：下落水柱，水位为 1
+/// - 否则取水平相邻水方块的最低水位 + 1
+/// - 没有补给返回 None（应当干涸）
+///
+/// 相邻区块未加载时无法判断补给，保持当前水位
+fn supported_level(world: &impl ChunkLookup, pos: IVec3, current: u8) -> Option<u8> {
+    match voxel_at(world, pos + IVec3::Y) {
+        Some((kind, _)) if is_fluid(kind) => return Some(1),
+        None => return Some(current),
+        _ => {}
+    }
+
+    let mut best = None;
+    for dir in HORIZONTAL_DIRS {
+        match voxel_at(world, pos + dir) {
+            Some((kind, level)) if is_fluid(kind) && level < MAX_FLOW_LEVEL => {
+                best = Some(best.map_or(level + 1, |b: u8| b.min(level + 1)));
+            }
+            None => return Some(current),
+            _ => {}
+        }
+    }
+    best
+}
+
+/// 计算单个水方块本 tick 产生的流动命令
+///
+/// 返回 (目标区块, 命令) 列表，目标可能位于相邻区块
+pub fn compute_flow(world: &impl ChunkLookup, pos: IVec3) -> Vec<(ChunkPos, DomainCommand)> {
+    let mut commands = Vec::new();
+
+    let Some((kind, mut level)) = voxel_at(world, pos) else {
+        return commands;
+    };
+    if !is_fluid(kind) {
+        return commands;
+    }
+
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+
+    // 流动水：根据补给重新计算水位，失去补给则干涸
+    if level > 0 {
+        match supported_level(world, pos, level) {
+            None => {
+                commands.push((
+                    chunk_pos,
+                    DomainCommand::SetBlock {
+                        idx,
+                        new_voxel: VoxelKind::Air,
+                    },
+                ));
+                return commands;
+            }
+            Some(supported) if supported != level => {
+                commands.push((
+                    chunk_pos,
+                    DomainCommand::SetVariant {
+                        idx,
+                        variant: supported,
+                    },
+                ));
+                level = supported;
+            }
+            _ => {}
+        }
+    }
+
+    // 优先向下流动
+    match voxel_at(world, pos + IVec3::NEG_Y) {
+        Some((below, _)) if can_flow_into(below) => {
+            push_place_fluid(&mut commands, pos + IVec3::NEG_Y, kind, 1);
+            return commands;
+        }
+        // 流动水落在水面上时汇入，不再扩散
+        Some((below, _)) if is_fluid(below) && level > 0 => return commands,
+        // 下方区块未加载，等待加载后再流动
+        None => return commands,
+        _ => {}
+    }
+
+    // 水平扩散
+    if level < MAX_FLOW_LEVEL {
+        for dir in HORIZONTAL_DIRS {
+            let target = pos + dir;
+            if let Some((neighbor, _)) = voxel_at(world, target)
+                && can_flow_into(neighbor)
+            {
+                push_place_fluid(&mut commands, target, kind, level + 1);
+            }
+        }
+    }
+
+    commands
+}
+
+fn push_place_fluid(
+    commands: &mut Vec<(ChunkPos, DomainCommand)>,
+    pos: IVec3,
+    fluid: VoxelKind,
+    level: u8,
+) {
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+    commands.push((chunk_pos, DomainCommand::PlaceFluid { idx, fluid, level }));
+}
+
+/// 流体流动系统
+///
+/// 在 SimulationSet::StateUpdate 阶段执行：取出所有活跃水方块计算流动。
+/// 活跃集合每 tick 清空，产生变化的方块会在提交后由唤醒系统重新加入
+pub fn fluid_flow_system(mut chunks: ChunksMut, mut command_queues: Query<&mut CommandQueue>) {
+    let Some(mut queue) = command_queues.iter_mut().next() else {
+        return;
+    };
+
+    let mut active = Vec::new();
+    for (chunk_pos, mut chunk) in chunks.iter_mut() {
+        if chunk.active_fluid.is_empty() {
+            continue;
+        }
+        let origin = chunk_pos.world_origin();
+        for idx in chunk.active_fluid.drain() {
+            let (x, y, z) = idx_to_xyz(idx);
+            active.push(origin + IVec3::new(x, y, z));
+        }
+    }
+
+    // 按坐标顺序计算流动，同一目标上的竞争每次都由同一个来源胜出
+    active.sort_unstable_by_key(|pos| (pos.y, pos.z, pos.x));
+    for pos in active {
+        for (chunk_pos, command) in compute_flow(&chunks, pos) {
+            queue.push(chunk_pos, command);
+        }
+    }
+}
+
+/// 流体唤醒系统
+///
+/// 在提交之后执行：方块或水位变化的位置及其邻居中的水方块重新进入活跃集合
+pub fn fluid_wake_system(mut chunks: ChunksMut) {
+    let mut to_wake = Vec::new();
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        let origin = chunk_pos.world_origin();
+        for change in &chunk.changes {
+            if !matches!(
+                change,
+                BlockChange::SetVoxel { .. }
+                    | BlockChange::SetVariant { .. }
+                    | BlockChange::FillRun { .. }
+            ) {
+                continue;
+            }
+
+            for idx in change.indices() {
+                let (x, y, z) = idx_to_xyz(idx);
+                let pos = origin + IVec3::new(x, y, z);
+                to_wake.push(pos);
+                to_wake.extend(NEIGHBOR_DIRS.iter().map(|&dir| pos + dir));
+            }
+        }
+    }
+
+    for pos in to_wake {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos)
+            && is_fluid(chunk.voxels.get(idx))
+        {
+            chunk.active_fluid.insert(idx);
+        }
+    }
+}
+
+/// 流体插件
+pub struct FluidPlugin;
+
+impl Plugin for FluidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                fluid_flow_system.in_set(SimulationSet::StateUpdate),
+                fluid_wake_system
+                    .in_set(SimulationSet::Commit)
+                    .after(commit_system),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::ChunkData;
+    use std::collections::HashMap;
+
+    /// 创建以石头为地面（y = 0）的单区块世界
+    fn flat_world() -> HashMap<ChunkPos, ChunkData> {
+        let mut chunk = ChunkData::new();
+        for z in 0..16 {
+            for x in 0..16 {
+                chunk.set(x, 0, z, VoxelKind::Stone);
+            }
+        }
+        HashMap::from([(ChunkPos::new(0, 0, 0), chunk)])
+    }
+
+    fn place_water(world: &mut HashMap<ChunkPos, ChunkData>, pos: IVec3, level: u8) {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        let chunk = world.get_mut(&chunk_pos).unwrap();
+        chunk.voxels.set(idx, VoxelKind::Water);
+        chunk.variant.set(idx, level);
+    }
+
+    #[test]
+    fn test_source_spreads_horizontally() {
+        let mut world = flat_world();
+        place_water(&mut world, IVec3::new(8, 1, 8), 0);
+
+        let commands = compute_flow(&world, IVec3::new(8, 1, 8));
+        assert_eq!(commands.len(), 4);
+        assert!(commands
+            .iter()
+            .all(|(_, cmd)| matches!(cmd, DomainCommand::PlaceFluid { level: 1, .. })));
+    }
+
+    #[test]
+    fn test_water_falls_before_spreading() {
+        let mut world = flat_world();
+        place_water(&mut world, IVec3::new(8, 5, 8), 0);
+
+        let commands = compute_flow(&world, IVec3::new(8, 5, 8));
+        let (_, below_idx) = VoxelWorld::split_world_pos(IVec3::new(8, 4, 8));
+        assert_eq!(commands.len(), 1);
+        assert!(matches!(
+            commands[0].1,
+            DomainCommand::PlaceFluid { idx, level: 1, .. } if idx == below_idx
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_flow_drains() {
+        let mut world = flat_world();
+        place_water(&mut world, IVec3::new(8, 1, 8), 3);
+
+        let commands = compute_flow(&world, IVec3::new(8, 1, 8));
+        assert!(matches!(
+            commands[0].1,
+            DomainCommand::SetBlock {
+                new_voxel: VoxelKind::Air,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_flow_crosses_chunk_boundary() {
+        let mut world = flat_world();
+        world.insert(
+            ChunkPos::new(1, 0, 0),
+            world[&ChunkPos::new(0, 0, 0)].clone(),
+        );
+        place_water(&mut world, IVec3::new(15, 1, 8), 0);
+
+        let commands = compute_flow(&world, IVec3::new(15, 1, 8));
+        assert!(commands
+            .iter()
+            .any(|(chunk_pos, _)| *chunk_pos == ChunkPos::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn test_fluid_height() {
+        assert_eq!(fluid_height(0), 1.0);
+        assert!(fluid_height(MAX_FLOW_LEVEL) > 0.0);
+        assert!(fluid_height(1) > fluid_height(2));
+
+        assert_eq!(surface_height(0), WATER_SURFACE_HEIGHT);
+        assert!(surface_height(0) >= surface_height(1));
+        assert_eq!(surface_height(MAX_FLOW_LEVEL), fluid_height(MAX_FLOW_LEVEL));
+    }
+}
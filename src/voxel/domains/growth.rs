@@ -0,0 +1,313 @@
+//! 生长领域模块
+//!
+//! 可生长的方块（VoxelProperties::is_growable）用变体记录生长阶段，从 0 长到
+//! max_growth_stage。每个 tick 在每个区块里随机抽取少量方块做一次生长判定，
+//! 成功概率为 growth_rate 乘以三个环境系数：
+//!
+//! - 光照：上方直到天空都是透明方块时为 1，被遮挡时不生长
+//! - 湿度：附近有水时按植物的 humidity 提高概率，缺水时相应降低
+//! - 温度：方块温度偏离植物的默认温度越远概率越低，偏离超过 GROWTH_TEMP_TOLERANCE 不生长
+//!
+//! 树苗还要求下方是泥土或草方块。它长满所有阶段后再生长一次会变成一棵树：
+//! 每次判定都是随机的，所以从种下到长成所需的 tick 数也是随机的。树的形状与
+//! 地形生成相同（树种取决于所在的生物群系），通过 WorldEditApi 提交，可以跨越区块边界。
+
+use bevy::prelude::*;
+
+use super::command::{CommandQueue, DomainCommand};
+use super::edit::WorldEditApi;
+use super::fluid::is_fluid;
+use super::thermal::api::idx_to_xyz;
+use super::thermal::ThermalApi;
+use super::SimulationSet;
+use crate::voxel::biome::Biome;
+use crate::voxel::chunk::{ChunkData, ChunkLookup, ChunkPos, Chunks, VoxelWorld};
+use crate::voxel::seed::XorShift32;
+use crate::voxel::terrain::{tree_blocks, SharedTerrain};
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 每个区块每个 tick 抽取的生长判定次数
+const RANDOM_TICKS_PER_CHUNK: usize = 3;
+/// 向上检查光照的最大高度（方块），更高处的遮挡忽略不计
+const SKY_SCAN_HEIGHT: i32 = 32;
+/// 水平方向上搜索水源的半径（方块）
+const MOISTURE_RADIUS: i32 = 4;
+/// 温度偏离植物默认温度超过此值（°C）时停止生长
+const GROWTH_TEMP_TOLERANCE: f32 = 20.0;
+
+/// 树苗长满后再次判定成功，在 pos 处长成树
+#[derive(Message, Debug, Clone, Copy)]
+pub struct SaplingGrown {
+    pub pos: IVec3,
+}
+
+/// 生长判定使用的随机数
+#[derive(Resource, Debug)]
+pub struct GrowthRng(XorShift32);
+
+impl Default for GrowthRng {
+    fn default() -> Self {
+        Self(XorShift32::new(0x2545_f491))
+    }
+}
+
+/// 树苗能否种在该方块上
+pub fn is_sapling_soil(kind: VoxelKind) -> bool {
+    matches!(
+        kind,
+        VoxelKind::Dirt | VoxelKind::Grass | VoxelKind::SwampGrass
+    )
+}
+
+/// 方块能否被长出的树覆盖（空气、花草、积雪、树叶和树苗本身）
+pub fn can_grow_into(kind: VoxelKind) -> bool {
+    matches!(
+        kind,
+        VoxelKind::Air
+            | VoxelKind::Flower
+            | VoxelKind::TallGrass
+            | VoxelKind::DeadBush
+            | VoxelKind::Sapling
+            | VoxelKind::GlowMushroom
+            | VoxelKind::SnowLayer
+            | VoxelKind::OakLeaves
+            | VoxelKind::BirchLeaves
+            | VoxelKind::SpruceLeaves
+    )
+}
+
+/// 读取世界坐标处的方块，区块未加载时返回 None
+fn voxel_at(world: &impl ChunkLookup, pos: IVec3) -> Option<VoxelKind> {
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+    world.chunk(&chunk_pos).map(|chunk| chunk.voxels.get(idx))
+}
+
+/// 生长 API
+pub struct GrowthApi;
+
+impl GrowthApi {
+    /// 方块上方是否露天（向上 SKY_SCAN_HEIGHT 格内全是透明方块，未加载的区域视为透明）
+    pub fn has_sky_light(world: &impl ChunkLookup, pos: IVec3) -> bool {
+        (1..=SKY_SCAN_HEIGHT)
+            .all(|dy| voxel_at(world, pos + IVec3::Y * dy).is_none_or(VoxelKind::is_transparent))
+    }
+
+    /// 同一高度或低一格、水平 MOISTURE_RADIUS 范围内是否有水
+    pub fn has_water_nearby(world: &impl ChunkLookup, pos: IVec3) -> bool {
+        (-1..=0).any(|dy| {
+            (-MOISTURE_RADIUS..=MOISTURE_RADIUS).any(|dx| {
+                (-MOISTURE_RADIUS..=MOISTURE_RADIUS)
+                    .any(|dz| voxel_at(world, pos + IVec3::new(dx, dy, dz)).is_some_and(is_fluid))
+            })
+        })
+    }
+
+    /// 一次生长判定的成功概率
+    pub fn growth_chance(
+        world: &impl ChunkLookup,
+        chunk: &ChunkData,
+        idx: usize,
+        pos: IVec3,
+    ) -> f32 {
+        let kind = chunk.voxels.get(idx);
+        let props = kind.def().props;
+        if !props.is_growable || !Self::has_sky_light(world, pos) {
+            return 0.0;
+        }
+        if kind == VoxelKind::Sapling
+            && !voxel_at(world, pos + IVec3::NEG_Y).is_some_and(is_sapling_soil)
+        {
+            return 0.0;
+        }
+
+        let moisture = if Self::has_water_nearby(world, pos) {
+            1.0 + props.humidity
+        } else {
+            1.0 - props.humidity
+        };
+        let deviation = (ThermalApi::get_temp(chunk, idx) - props.temperature).abs();
+        let temperature = (1.0 - deviation / GROWTH_TEMP_TOLERANCE).max(0.0);
+
+        props.growth_rate * moisture * temperature
+    }
+
+    /// 进入下一生长阶段的命令，已经长满时返回 None
+    pub fn advance_stage(chunk: &ChunkData, idx: usize) -> Option<DomainCommand> {
+        let max_stage = chunk.voxels.get(idx).def().props.max_growth_stage;
+        (chunk.variant.get(idx) < max_stage).then_some(DomainCommand::IncrementVariant { idx })
+    }
+
+    /// pos 处的树苗长成的树（相对树苗的偏移和方块类型）
+    ///
+    /// 树种与地形生成一致由生物群系决定，不长树的生物群系里长成橡树
+    pub fn sapling_tree(biome: Biome) -> Vec<(IVec3, VoxelKind)> {
+        let blocks = tree_blocks(biome);
+        if blocks.is_empty() {
+            tree_blocks(Biome::Plains)
+        } else {
+            blocks
+        }
+    }
+}
+
+/// 生长系统
+///
+/// 在 StateUpdate 阶段执行：每个区块抽取 RANDOM_TICKS_PER_CHUNK 个方块做生长判定，
+/// 判定成功的方块进入下一阶段，已经长满的树苗交给 grow_saplings_system
+pub fn growth_system(
+    chunks: Chunks,
+    mut rng: ResMut<GrowthRng>,
+    mut command_queues: Query<&mut CommandQueue>,
+    mut grown: MessageWriter<SaplingGrown>,
+) {
+    let Some(mut queue) = command_queues.iter_mut().next() else {
+        return;
+    };
+
+    // 按区块坐标顺序消耗随机数，同一种子下每次运行抽到的方块相同
+    let mut sorted: Vec<(ChunkPos, &ChunkData)> = chunks.iter().collect();
+    sorted.sort_unstable_by_key(|&(chunk_pos, _)| chunk_pos);
+    for (chunk_pos, chunk) in sorted {
+        let origin = chunk_pos.world_origin();
+        for _ in 0..RANDOM_TICKS_PER_CHUNK {
+            let idx = rng.0.next_u32() as usize % ChunkData::VOXEL_COUNT;
+            if !chunk.voxels.get(idx).def().props.is_growable {
+                continue;
+            }
+            let (x, y, z) = idx_to_xyz(idx);
+            let pos = origin + IVec3::new(x, y, z);
+            if rng.0.next_f32() >= GrowthApi::growth_chance(&chunks, chunk, idx, pos) {
+                continue;
+            }
+            match GrowthApi::advance_stage(chunk, idx) {
+                Some(command) => queue.push(chunk_pos, command),
+                None if chunk.voxels.get(idx) == VoxelKind::Sapling => {
+                    grown.write(SaplingGrown { pos });
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// 把长满的树苗替换为树
+///
+/// 树所在的区块未全部加载或位置被占据时放弃，树苗留在原地等待下一次判定
+fn grow_saplings_system(
+    mut grown: MessageReader<SaplingGrown>,
+    terrain: Res<SharedTerrain>,
+    mut edit: WorldEditApi,
+) {
+    for &SaplingGrown { pos } in grown.read() {
+        let biome = terrain.generator().get_biome(pos.x, pos.z);
+        let tree = GrowthApi::sapling_tree(biome);
+        if let Err(err) = edit.place_blocks(pos, &tree, can_grow_into) {
+            debug!("Sapling at {pos} can't grow yet: {err}");
+        }
+    }
+}
+
+/// 生长插件
+pub struct GrowthPlugin;
+
+impl Plugin for GrowthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GrowthRng>()
+            .add_message::<SaplingGrown>()
+            .add_systems(
+                FixedUpdate,
+                (growth_system, grow_saplings_system)
+                    .chain()
+                    .in_set(SimulationSet::StateUpdate),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::constants::CHUNK_SIZE;
+    use std::collections::HashMap;
+
+    /// 以草方块为地面（y = 0）的单区块世界，plant 种在 (8, 1, 8)
+    fn meadow(plant: VoxelKind) -> (HashMap<ChunkPos, ChunkData>, usize) {
+        let mut chunk = ChunkData::new();
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                chunk.set(x, 0, z, VoxelKind::Grass);
+            }
+        }
+        chunk.set(8, 1, 8, plant);
+        let mut world = HashMap::new();
+        world.insert(ChunkPos::new(0, 0, 0), chunk);
+        (world, ChunkData::index(8, 1, 8))
+    }
+
+    const PLANT: IVec3 = IVec3::new(8, 1, 8);
+
+    #[test]
+    fn test_shade_stops_growth() {
+        let (mut world, idx) = meadow(VoxelKind::Flower);
+        let chunk = &world[&ChunkPos::new(0, 0, 0)];
+        assert!(GrowthApi::growth_chance(&world, chunk, idx, PLANT) > 0.0);
+
+        let chunk = world.get_mut(&ChunkPos::new(0, 0, 0)).unwrap();
+        chunk.set(8, 6, 8, VoxelKind::Stone);
+        let chunk = &world[&ChunkPos::new(0, 0, 0)];
+        assert_eq!(GrowthApi::growth_chance(&world, chunk, idx, PLANT), 0.0);
+    }
+
+    #[test]
+    fn test_water_and_heat_change_growth_chance() {
+        let (mut world, idx) = meadow(VoxelKind::TallGrass);
+        let pos = ChunkPos::new(0, 0, 0);
+        let dry = GrowthApi::growth_chance(&world, &world[&pos], idx, PLANT);
+
+        world.get_mut(&pos).unwrap().set(10, 1, 8, VoxelKind::Water);
+        let wet = GrowthApi::growth_chance(&world, &world[&pos], idx, PLANT);
+        assert!(wet > dry);
+
+        ThermalApi::set_temp(world.get_mut(&pos).unwrap(), idx, 60.0);
+        assert_eq!(
+            GrowthApi::growth_chance(&world, &world[&pos], idx, PLANT),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_sapling_needs_soil() {
+        let (mut world, idx) = meadow(VoxelKind::Sapling);
+        let pos = ChunkPos::new(0, 0, 0);
+        assert!(GrowthApi::growth_chance(&world, &world[&pos], idx, PLANT) > 0.0);
+
+        world.get_mut(&pos).unwrap().set(8, 0, 8, VoxelKind::Stone);
+        assert_eq!(
+            GrowthApi::growth_chance(&world, &world[&pos], idx, PLANT),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_stage_advances_until_max() {
+        let (mut world, idx) = meadow(VoxelKind::Sapling);
+        let pos = ChunkPos::new(0, 0, 0);
+        assert!(matches!(
+            GrowthApi::advance_stage(&world[&pos], idx),
+            Some(DomainCommand::IncrementVariant { .. })
+        ));
+
+        world.get_mut(&pos).unwrap().variant.set(idx, 3);
+        assert!(GrowthApi::advance_stage(&world[&pos], idx).is_none());
+    }
+
+    #[test]
+    fn test_sapling_tree_follows_biome() {
+        let spruce = GrowthApi::sapling_tree(Biome::Taiga);
+        assert_eq!(spruce[0], (IVec3::ZERO, VoxelKind::SpruceLog));
+
+        // 沙漠本身不长树，树苗长成橡树
+        let oak = GrowthApi::sapling_tree(Biome::Desert);
+        assert_eq!(oak, tree_blocks(Biome::Plains));
+        assert!(oak.iter().all(|(offset, _)| offset.y >= 0));
+    }
+}
@@ -0,0 +1,302 @@
+//! 编辑历史（撤销/重做）
+//!
+//! 玩家发起的编辑（破坏方块、种植树苗、用水桶舀水和倒水、控制台的 setblock / fill / replace）通过
+//! [`PlayerEditApi`] 提交：每次点击或每条命令记为一个批次，批次按区块保存每个被修改方块的
+//! [`BlockChange::SetVoxel`]（原方块和新方块）。
+//!
+//! - Ctrl+Z / 控制台 undo：按相反顺序提交最近批次中每条变更的反向变更，方块恢复为原方块
+//! - Ctrl+Y / 控制台 redo：重新提交最近撤销的批次
+//!
+//! 撤销和重做同样以 SetBlock 进入命令队列，在 Commit 阶段生效、写入变更日志并参与同步，
+//! 所在区块已卸载的方块被跳过。模拟产生的变化（水流、燃烧、生长）不进入历史，
+//! 撤销时被原方块直接覆盖。新的编辑会清空重做栈；历史最多保留 [`MAX_HISTORY_BATCHES`]
+//! 个批次、共 [`MAX_HISTORY_BLOCKS`] 个方块，超出时丢弃最早的批次。
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use super::edit::{edit_console_commands, EditError, WorldEditApi};
+use super::SimulationSet;
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::input::{Action, ActionInput};
+use crate::voxel::change::BlockChange;
+use crate::voxel::chunk::{ChunkPos, VoxelWorld};
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 历史中最多保留的批次数
+pub const MAX_HISTORY_BATCHES: usize = 64;
+/// 历史中（包括重做栈）最多保留的方块数，大范围 fill 会挤掉更早的批次
+pub const MAX_HISTORY_BLOCKS: usize = 1 << 20;
+
+/// 一次编辑操作修改的方块
+#[derive(Debug, Clone, Default)]
+pub struct EditBatch {
+    pub changes: Vec<(ChunkPos, BlockChange)>,
+}
+
+/// 撤销栈和重做栈
+#[derive(Resource, Debug, Default)]
+pub struct EditHistory {
+    undo: VecDeque<EditBatch>,
+    redo: Vec<EditBatch>,
+    /// 两个栈中的方块总数
+    blocks: usize,
+}
+
+impl EditHistory {
+    /// 记录一次新的编辑并清空重做栈，空批次被忽略
+    pub fn record(&mut self, batch: EditBatch) {
+        if batch.changes.is_empty() {
+            return;
+        }
+        for discarded in self.redo.drain(..) {
+            self.blocks -= discarded.changes.len();
+        }
+        self.blocks += batch.changes.len();
+        self.undo.push_back(batch);
+        while self.undo.len() > MAX_HISTORY_BATCHES
+            || (self.blocks > MAX_HISTORY_BLOCKS && self.undo.len() > 1)
+        {
+            if let Some(oldest) = self.undo.pop_front() {
+                self.blocks -= oldest.changes.len();
+            }
+        }
+    }
+
+    /// 把最近的批次移到重做栈，返回撤销它需要提交的变更
+    pub fn undo(&mut self) -> Option<Vec<(ChunkPos, BlockChange)>> {
+        let batch = self.undo.pop_back()?;
+        let inverse = batch
+            .changes
+            .iter()
+            .rev()
+            .filter_map(|(chunk_pos, change)| Some((*chunk_pos, change.inverse()?)))
+            .collect();
+        self.redo.push(batch);
+        Some(inverse)
+    }
+
+    /// 把最近撤销的批次移回撤销栈，返回需要重新提交的变更
+    pub fn redo(&mut self) -> Option<Vec<(ChunkPos, BlockChange)>> {
+        let batch = self.redo.pop()?;
+        let changes = batch.changes.clone();
+        self.undo.push_back(batch);
+        Some(changes)
+    }
+
+    /// 可以撤销的批次数
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// 可以重做的批次数
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+/// 玩家编辑接口
+///
+/// 与 [`WorldEditApi`] 相同的编辑操作，成功提交后把修改的方块记为一个撤销批次
+#[derive(SystemParam)]
+pub struct PlayerEditApi<'w, 's> {
+    edit: WorldEditApi<'w, 's>,
+    history: ResMut<'w, EditHistory>,
+}
+
+impl PlayerEditApi<'_, '_> {
+    /// 设置单个方块
+    pub fn set_block(&mut self, pos: IVec3, kind: VoxelKind) -> Result<(), EditError> {
+        let old = self.edit.voxel(pos);
+        self.edit.set_block(pos, kind)?;
+        if let Some(old) = old
+            && old != kind
+        {
+            let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+            self.history.record(EditBatch {
+                changes: vec![(
+                    chunk_pos,
+                    BlockChange::SetVoxel {
+                        idx,
+                        old,
+                        new: kind,
+                    },
+                )],
+            });
+        }
+        Ok(())
+    }
+
+    /// 把 a、b 两角之间（含边界）的方块全部设为 kind，返回实际提交的方块数
+    pub fn fill_region(&mut self, a: IVec3, b: IVec3, kind: VoxelKind) -> Result<usize, EditError> {
+        let count = self.edit.fill_region(a, b, kind)?;
+        // 命令在 Commit 阶段才执行，此时世界中仍是原方块
+        let changes = self.edit.region_changes(a, b, |_| Some(kind));
+        self.history.record(EditBatch { changes });
+        Ok(count)
+    }
+
+    /// 把 a、b 两角之间（含边界）所有 from 方块替换为 to，返回实际提交的方块数
+    pub fn swap_kind(
+        &mut self,
+        a: IVec3,
+        b: IVec3,
+        from: VoxelKind,
+        to: VoxelKind,
+    ) -> Result<usize, EditError> {
+        let count = self.edit.swap_kind(a, b, from, to)?;
+        let changes = self
+            .edit
+            .region_changes(a, b, |current| (current == from).then_some(to));
+        self.history.record(EditBatch { changes });
+        Ok(count)
+    }
+
+    /// 撤销最近一次编辑，返回提交的方块数；没有可撤销的编辑时返回 None
+    pub fn undo(&mut self) -> Option<Result<usize, EditError>> {
+        let changes = self.history.undo()?;
+        Some(self.edit.apply_changes(&changes))
+    }
+
+    /// 重做最近撤销的编辑，返回提交的方块数；没有可重做的编辑时返回 None
+    pub fn redo(&mut self) -> Option<Result<usize, EditError>> {
+        let changes = self.history.redo()?;
+        Some(self.edit.apply_changes(&changes))
+    }
+}
+
+/// 编辑历史插件
+///
+/// 注册撤销快捷键和 undo、redo 控制台命令
+pub struct EditHistoryPlugin;
+
+impl Plugin for EditHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        let mut commands = app.world_mut().resource_mut::<ConsoleCommands>();
+        commands.register("undo", "undo", "撤销最近一次方块编辑");
+        commands.register("redo", "redo", "重做最近撤销的方块编辑");
+
+        app.init_resource::<EditHistory>()
+            .add_systems(Update, edit_history_keys)
+            .add_systems(
+                FixedUpdate,
+                history_console_commands
+                    .in_set(SimulationSet::ExternalActions)
+                    .after(edit_console_commands),
+            );
+    }
+}
+
+/// 处理撤销/重做快捷键（需要同时按住 Ctrl）
+fn edit_history_keys(
+    actions: ActionInput,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut edit: PlayerEditApi,
+    mut log: ResMut<ConsoleLog>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    if actions.just_pressed(Action::UndoEdit) {
+        log.print(undo_message(edit.undo()));
+    }
+    if actions.just_pressed(Action::RedoEdit) {
+        log.print(redo_message(edit.redo()));
+    }
+}
+
+/// 执行 undo、redo 控制台命令
+///
+/// 在同一 tick 的编辑命令之后运行，`fill` 之后紧跟的 `undo` 撤销的就是这次填充
+fn history_console_commands(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut edit: PlayerEditApi,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        match command.name.as_str() {
+            "undo" => log.print(undo_message(edit.undo())),
+            "redo" => log.print(redo_message(edit.redo())),
+            _ => {}
+        }
+    }
+}
+
+fn undo_message(result: Option<Result<usize, EditError>>) -> String {
+    match result {
+        None => "没有可撤销的编辑".to_string(),
+        Some(Ok(count)) => format!("已撤销 {count} 个方块"),
+        Some(Err(err)) => format!("undo：{err}"),
+    }
+}
+
+fn redo_message(result: Option<Result<usize, EditError>>) -> String {
+    match result {
+        None => "没有可重做的编辑".to_string(),
+        Some(Ok(count)) => format!("已重做 {count} 个方块"),
+        Some(Err(err)) => format!("redo：{err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(blocks: usize) -> EditBatch {
+        EditBatch {
+            changes: (0..blocks)
+                .map(|idx| {
+                    (
+                        ChunkPos::new(0, 0, 0),
+                        BlockChange::SetVoxel {
+                            idx,
+                            old: VoxelKind::Stone,
+                            new: VoxelKind::Air,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_undo_redo_round_trip() {
+        let mut history = EditHistory::default();
+        history.record(batch(2));
+        history.record(EditBatch::default());
+        assert_eq!(history.undo_len(), 1);
+
+        let undo = history.undo().unwrap();
+        assert_eq!(
+            undo[0].1,
+            BlockChange::SetVoxel {
+                idx: 1,
+                old: VoxelKind::Air,
+                new: VoxelKind::Stone,
+            }
+        );
+        assert!(history.undo().is_none());
+
+        let redo = history.redo().unwrap();
+        assert_eq!(redo, batch(2).changes);
+        assert_eq!((history.undo_len(), history.redo_len()), (1, 0));
+
+        // 新的编辑清空重做栈
+        history.undo();
+        history.record(batch(1));
+        assert!(history.redo().is_none());
+        assert_eq!(history.blocks, 1);
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut history = EditHistory::default();
+        for _ in 0..MAX_HISTORY_BATCHES + 5 {
+            history.record(batch(1));
+        }
+        assert_eq!(history.undo_len(), MAX_HISTORY_BATCHES);
+        assert_eq!(history.blocks, MAX_HISTORY_BATCHES);
+    }
+}
@@ -1,22 +1,53 @@
-/// 领域模块系统
-///
-/// 每个领域（Domain）代表一条物理/属性线：
-/// - thermal: 温度场
-/// - moisture: 湿度场
-/// - combustion: 燃烧系统
-/// - phase: 相变系统
-/// - reaction: 反应规则与命令系统
+//! 领域模块系统
+//!
+//! 每个领域（Domain）代表一条物理/属性线：
+//! - thermal: 温度场
+//! - moisture: 湿度场
+//! - combustion: 燃烧系统
+//! - phase: 相变系统
+//! - fluid: 流体流动
+//! - structure: 结构（沙子、沙砾下落，失去支撑的结构坍塌）
+//! - reaction: 反应规则与命令系统
+//! - edit: 世界编辑接口（玩家、控制台、脚本修改方块的入口）
+//! - history: 玩家编辑的撤销/重做历史
+//! - explosion: 爆炸（炸毁方块、加热并点燃周围方块）
+//! - growth: 植物生长（花草、仙人掌长大，树苗长成树）
+//! - corrosion: 腐蚀（接触水分的铁矿石逐渐生锈）
+//! - weather: 天气（晴天和雨天交替，雨水浇灭露天的火）
+//! - script: 脚本反应规则（`scripting` feature，从 assets/rules/ 加载 Rhai 脚本）
+//!
+//! clock 模块提供模拟时钟，控制上述领域的暂停、单步和倍速
 
 use bevy::prelude::*;
-
+use std::collections::HashSet;
+
+use crate::voxel::chunk::{ChunkData, ChunkPos, ChunksMut};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::events::{
+    emit_block_changes, BlockChanged, BlockIgnited, SimulationTickCompleted,
+};
+use crate::voxel::snapshot::{refresh_changed_snapshots, WorldSnapshot};
+use thermal::api::idx_to_xyz;
+
+pub mod clock;
+pub mod combustion;
 pub mod command;
+pub mod corrosion;
+pub mod edit;
+pub mod explosion;
+pub mod fluid;
+pub mod growth;
+pub mod history;
+pub mod phase;
 pub mod reaction;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod structure;
 pub mod thermal;
+pub mod weather;
 
 // TODO: 后续添加
 // pub mod moisture;
-// pub mod combustion;
-// pub mod phase;
 
 /// 模拟系统执行顺序
 ///
@@ -56,7 +87,8 @@ pub enum SimulationSet {
 
 /// 领域系统插件
 ///
-/// 配置所有领域相关的系统执行顺序
+/// 配置所有领域相关的系统执行顺序。插件不依赖窗口和渲染，
+/// 无渲染模拟（`--headless-sim`）直接在 MinimalPlugins 上运行它
 pub struct DomainPlugin;
 
 impl Plugin for DomainPlugin {
@@ -75,55 +107,112 @@ impl Plugin for DomainPlugin {
                 )
                     .chain(),
             )
+            // 模拟时钟暂停时跳过推进模拟的阶段，外部修改仍然提交
+            .configure_sets(
+                FixedUpdate,
+                (
+                    SimulationSet::FieldUpdate,
+                    SimulationSet::StateUpdate,
+                    SimulationSet::Reactions,
+                )
+                    .run_if(clock::simulation_running),
+            )
             // 注册反应规则资源
             .init_resource::<reaction::ReactionRules>()
+            // 供玩法扩展读取的方块和模拟事件
+            .add_message::<BlockChanged>()
+            .add_message::<BlockIgnited>()
+            .add_message::<SimulationTickCompleted>()
             // 添加命令队列组件
             .add_systems(Startup, spawn_command_queue)
+            // 添加反应规则判定系统
+            .add_systems(
+                FixedUpdate,
+                reaction::reaction_system.in_set(SimulationSet::Reactions),
+            )
             // 添加提交系统
             .add_systems(FixedUpdate, command::commit_system.in_set(SimulationSet::Commit))
-            // 添加后处理系统（清理变更日志）
-            .add_systems(FixedUpdate, cleanup_changes_system.in_set(SimulationSet::Post))
-            // 添加温度场可视化调试系统
-            .add_systems(Update, thermal_debug_system)
-            // 注册热力学插件和测试插件
-            .add_plugins((thermal::ThermalPlugin, thermal::ThermalTestPlugin));
+            // 供只读系统使用的区块快照
+            .init_resource::<WorldSnapshot>()
+            // 添加后处理系统（标记重建网格、发出方块事件、刷新快照，然后清理变更日志）
+            .add_systems(
+                FixedUpdate,
+                (
+                    mark_remesh_system,
+                    emit_block_changes,
+                    refresh_changed_snapshots,
+                    cleanup_changes_system,
+                )
+                    .chain()
+                    .in_set(SimulationSet::Post),
+            )
+            // 注册各领域插件
+            .add_plugins((
+                clock::SimulationClockPlugin,
+                thermal::ThermalPlugin,
+                combustion::CombustionPlugin,
+                phase::PhasePlugin,
+                fluid::FluidPlugin,
+                structure::StructurePlugin,
+                edit::EditPlugin,
+                history::EditHistoryPlugin,
+                explosion::ExplosionPlugin,
+                growth::GrowthPlugin,
+                corrosion::CorrosionPlugin,
+                weather::WeatherPlugin,
+            ));
     }
 }
 
-/// 清理变更日志系统
-fn cleanup_changes_system(mut voxel_world: ResMut<crate::voxel::VoxelWorld>) {
-    for chunk in voxel_world.chunks.values_mut() {
-        chunk.clear_changes();
+/// 标记需要重建网格的区块系统
+///
+/// 根据变更日志将区块标记为脏；边界方块的变化会影响相邻区块的面剔除，
+/// 所以对应方向的相邻区块也一并标记
+fn mark_remesh_system(mut chunks: ChunksMut) {
+    let mut dirty = HashSet::new();
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        for change in chunk.changes.iter().filter(|c| c.needs_remesh()) {
+            dirty.insert(chunk_pos);
+            if !change.affects_neighbors() {
+                continue;
+            }
+
+            for idx in change.indices() {
+                let (x, y, z) = idx_to_xyz(idx);
+                for (coord, axis) in [(x, IVec3::X), (y, IVec3::Y), (z, IVec3::Z)] {
+                    let offset = if coord == 0 {
+                        -axis
+                    } else if coord == CHUNK_SIZE - 1 {
+                        axis
+                    } else {
+                        continue;
+                    };
+                    dirty.insert(ChunkPos::new(
+                        chunk_pos.x + offset.x,
+                        chunk_pos.y + offset.y,
+                        chunk_pos.z + offset.z,
+                    ));
+                }
+            }
+        }
+    }
+
+    for chunk_pos in dirty {
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos) {
+            chunk.is_dirty = true;
+        }
     }
 }
 
-/// 温度场调试系统
+/// 清理变更日志系统
 ///
-/// 按 F3 显示活跃方块统计信息
-fn thermal_debug_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    voxel_world: Res<crate::voxel::VoxelWorld>,
-) {
-    if keyboard.just_pressed(KeyCode::F3) {
-        let mut total_active = 0;
-        let mut total_thermal = 0;
-        let mut chunks_with_thermal = 0;
-
-        for chunk in voxel_world.chunks.values() {
-            total_active += chunk.active_count();
-            total_thermal += chunk.active_thermal.len();
-            if !chunk.active_thermal.is_empty() {
-                chunks_with_thermal += 1;
-            }
+/// 只有上次清理之后被修改过的区块才会有变更日志
+fn cleanup_changes_system(mut chunks: Query<&mut ChunkData, Changed<ChunkData>>) {
+    for mut chunk in &mut chunks {
+        if !chunk.changes.is_empty() || !chunk.dirty_blocks.is_empty() || chunk.needs_remesh {
+            chunk.clear_changes();
         }
-
-        info!(
-            "=== Thermal Debug ===\nChunks: {}\nChunks with thermal: {}\nActive thermal: {}\nTotal active: {}",
-            voxel_world.chunks.len(),
-            chunks_with_thermal,
-            total_thermal,
-            total_active
-        );
     }
 }
 
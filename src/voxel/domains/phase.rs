@@ -0,0 +1,346 @@
+//! 相变领域模块
+//!
+//! 根据温度驱动方块的物态变化：
+//! - 融化：冰/雪 高于熔点 → 液态形式（水）；积雪每次融化一层，最后一层融化后消失
+//! - 冻结：水 低于冰点 → 固态形式（冰）
+//! - 沸腾：水 高于沸点 → 空气（蒸汽散逸）
+//! - 凝固：熔岩 冷却到冰点以下 → 石头；接触水时立即淬火 → 黑曜石
+//!
+//! ## 进度模型
+//!
+//! 相变不是瞬间完成的，进度稀疏存储在 PhaseState 中（variant 字节留给水位等方块自身状态）：
+//! - 每个 tick 满足条件时进度增加，超出阈值越多增加越快
+//! - 进度达到 PHASE_TRANSITION_TICKS 时替换方块
+//! - 条件不再满足时进度逐渐回退，归零后取消相变
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::command::DomainCommand;
+use super::reaction::{ReactionRule, ReactionRules};
+use super::thermal::ThermalApi;
+use super::thermal::api::get_valid_neighbor_indices;
+use crate::voxel::chunk::ChunkData;
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 完成一次相变所需的进度值（FixedUpdate 默认 64Hz，约 1 秒）
+pub const PHASE_TRANSITION_TICKS: u8 = 64;
+
+/// 每超出阈值多少度，进度额外 +1
+const PHASE_RATE_DEGREES: f32 = 10.0;
+
+/// 单个 tick 的最大进度增量
+const MAX_PROGRESS_STEP: u8 = 8;
+
+/// 相变类型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhaseTransition {
+    /// 固态 → 液态
+    Melting,
+    /// 液态 → 固态
+    Freezing,
+    /// 液态 → 气态
+    Boiling,
+}
+
+impl PhaseTransition {
+    /// 所有相变相关的状态标志
+    pub const ALL_FLAGS: [VoxelFlags; 2] = [VoxelFlags::MELTING, VoxelFlags::EVAPORATING];
+
+    /// 相变进行中对应的状态标志
+    pub fn flag(self) -> Option<VoxelFlags> {
+        match self {
+            PhaseTransition::Melting => Some(VoxelFlags::MELTING),
+            PhaseTransition::Boiling => Some(VoxelFlags::EVAPORATING),
+            PhaseTransition::Freezing => None,
+        }
+    }
+}
+
+/// 相变进度状态（稀疏存储）
+///
+/// 只存储正在进行相变的方块
+#[derive(Debug, Default, Clone)]
+pub struct PhaseState {
+    /// 相变进度 (idx -> 0..PHASE_TRANSITION_TICKS)
+    pub progress: HashMap<usize, u8>,
+}
+
+impl PhaseState {
+    /// 获取方块的相变进度（未记录时为 0）
+    pub fn get(&self, idx: usize) -> u8 {
+        self.progress.get(&idx).copied().unwrap_or(0)
+    }
+
+    /// 设置方块的相变进度，进度为 0 时移除记录
+    pub fn set(&mut self, idx: usize, progress: u8) {
+        if progress == 0 {
+            self.progress.remove(&idx);
+        } else {
+            self.progress.insert(idx, progress);
+        }
+    }
+
+    /// 检查是否为空
+    pub fn is_empty(&self) -> bool {
+        self.progress.is_empty()
+    }
+}
+
+/// 获取方块的相变进度
+pub fn get_progress(chunk: &ChunkData, idx: usize) -> u8 {
+    chunk.phase_state.as_ref().map_or(0, |state| state.get(idx))
+}
+
+/// 判断方块在指定温度下应发生的相变
+///
+/// 返回 (相变类型, 目标方块, 超出阈值的温度)
+pub fn target_transition(kind: VoxelKind, temp: f32) -> Option<(PhaseTransition, VoxelKind, f32)> {
+    let props = kind.def().props;
+
+    if let (Some(melting_point), Some(liquid)) = (props.melting_point, props.liquid_form)
+        && temp > melting_point
+    {
+        return Some((PhaseTransition::Melting, liquid, temp - melting_point));
+    }
+
+    if let Some(boiling_point) = props.boiling_point
+        && temp > boiling_point
+    {
+        return Some((
+            PhaseTransition::Boiling,
+            VoxelKind::Air,
+            temp - boiling_point,
+        ));
+    }
+
+    if let (Some(freezing_point), Some(solid)) = (props.freezing_point, props.solid_form)
+        && temp < freezing_point
+    {
+        return Some((PhaseTransition::Freezing, solid, freezing_point - temp));
+    }
+
+    None
+}
+
+/// 相变完成时的命令
+///
+/// 多层积雪每次只融化最上面一层，剩下的层重新累计进度；最后一层融化后消失
+fn melt_commands(
+    chunk: &ChunkData,
+    idx: usize,
+    phase: PhaseTransition,
+    new_voxel: VoxelKind,
+) -> Vec<DomainCommand> {
+    if phase == PhaseTransition::Melting
+        && chunk.voxels.get(idx) == VoxelKind::SnowLayer
+        && chunk.variant.get(idx) > 0
+    {
+        vec![
+            DomainCommand::DecrementVariant { idx },
+            DomainCommand::CancelPhaseTransition { idx },
+        ]
+    } else {
+        vec![DomainCommand::CompletePhaseTransition { idx, new_voxel }]
+    }
+}
+
+/// 判断方块类型是否可能发生相变
+fn has_phase_behavior(kind: VoxelKind) -> bool {
+    let props = kind.def().props;
+    (props.melting_point.is_some() && props.liquid_form.is_some())
+        || props.boiling_point.is_some()
+        || (props.freezing_point.is_some() && props.solid_form.is_some())
+}
+
+/// 相变规则
+///
+/// 评估方块温度，推进/回退相变进度，并在进度完成时替换方块
+pub struct PhaseTransitionRule;
+
+impl ReactionRule for PhaseTransitionRule {
+    fn evaluate(&self, chunk: &ChunkData, idx: usize) -> bool {
+        let kind = chunk.voxels.get(idx);
+        if !has_phase_behavior(kind) {
+            return false;
+        }
+
+        // 进行中的相变需要持续推进或回退
+        get_progress(chunk, idx) > 0
+            || target_transition(kind, ThermalApi::get_temp(chunk, idx)).is_some()
+    }
+
+    fn emit_commands(&self, chunk: &ChunkData, idx: usize) -> Vec<DomainCommand> {
+        let temp = ThermalApi::get_temp(chunk, idx);
+        let progress = get_progress(chunk, idx);
+
+        match target_transition(chunk.voxels.get(idx), temp) {
+            Some((phase, new_voxel, excess)) => {
+                let step = (1 + (excess / PHASE_RATE_DEGREES) as u8).min(MAX_PROGRESS_STEP);
+                let next = progress.saturating_add(step);
+
+                if next >= PHASE_TRANSITION_TICKS {
+                    melt_commands(chunk, idx, phase, new_voxel)
+                } else if progress == 0 {
+                    vec![
+                        DomainCommand::StartPhaseTransition { idx, phase },
+                        DomainCommand::SetPhaseProgress {
+                            idx,
+                            progress: next,
+                        },
+                    ]
+                } else {
+                    vec![DomainCommand::SetPhaseProgress {
+                        idx,
+                        progress: next,
+                    }]
+                }
+            }
+            None if progress <= 1 => vec![DomainCommand::CancelPhaseTransition { idx }],
+            None => vec![DomainCommand::SetPhaseProgress {
+                idx,
+                progress: progress - 1,
+            }],
+        }
+    }
+}
+
+/// 熔岩淬火规则
+///
+/// 与水相邻的熔岩来不及慢慢冷却，直接凝固为黑曜石（只检查同一区块内的邻居）
+pub struct LavaQuenchRule;
+
+impl ReactionRule for LavaQuenchRule {
+    fn evaluate(&self, chunk: &ChunkData, idx: usize) -> bool {
+        chunk.voxels.get(idx) == VoxelKind::Lava
+            && get_valid_neighbor_indices(idx)
+                .into_iter()
+                .any(|neighbor_idx| chunk.voxels.get(neighbor_idx) == VoxelKind::Water)
+    }
+
+    fn emit_commands(&self, _chunk: &ChunkData, idx: usize) -> Vec<DomainCommand> {
+        vec![DomainCommand::CompletePhaseTransition {
+            idx,
+            new_voxel: VoxelKind::Obsidian,
+        }]
+    }
+}
+
+/// 相变插件
+///
+/// 将相变规则和熔岩淬火规则注册到反应规则表
+pub struct PhasePlugin;
+
+impl Plugin for PhasePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReactionRules>();
+        let mut rules = app.world_mut().resource_mut::<ReactionRules>();
+        rules.rules.push(Box::new(PhaseTransitionRule));
+        rules.rules.push(Box::new(LavaQuenchRule));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ice_starts_melting_above_zero() {
+        let mut chunk = ChunkData::new();
+        chunk.voxels.set(0, VoxelKind::Ice);
+        ThermalApi::set_temp(&mut chunk, 0, 5.0);
+
+        let rule = PhaseTransitionRule;
+        assert!(rule.evaluate(&chunk, 0));
+
+        let commands = rule.emit_commands(&chunk, 0);
+        assert!(matches!(
+            commands[0],
+            DomainCommand::StartPhaseTransition {
+                idx: 0,
+                phase: PhaseTransition::Melting
+            }
+        ));
+    }
+
+    #[test]
+    fn test_transition_completes_at_full_progress() {
+        let mut chunk = ChunkData::new();
+        chunk.voxels.set(0, VoxelKind::Water);
+        chunk
+            .phase_state
+            .get_or_insert_with(PhaseState::default)
+            .set(0, PHASE_TRANSITION_TICKS - 1);
+        ThermalApi::set_temp(&mut chunk, 0, -5.0);
+
+        let commands = PhaseTransitionRule.emit_commands(&chunk, 0);
+        assert!(matches!(
+            commands[0],
+            DomainCommand::CompletePhaseTransition {
+                idx: 0,
+                new_voxel: VoxelKind::Ice
+            }
+        ));
+    }
+
+    #[test]
+    fn test_snow_layers_melt_one_at_a_time() {
+        let mut chunk = ChunkData::new();
+        chunk.voxels.set(0, VoxelKind::SnowLayer);
+        chunk.variant.set(0, 2);
+        chunk
+            .phase_state
+            .get_or_insert_with(PhaseState::default)
+            .set(0, PHASE_TRANSITION_TICKS - 1);
+        ThermalApi::set_temp(&mut chunk, 0, 5.0);
+
+        let commands = PhaseTransitionRule.emit_commands(&chunk, 0);
+        assert!(matches!(
+            commands[..],
+            [
+                DomainCommand::DecrementVariant { idx: 0 },
+                DomainCommand::CancelPhaseTransition { idx: 0 }
+            ]
+        ));
+
+        // 只剩一层时整个方块消失
+        chunk.variant.set(0, 0);
+        let commands = PhaseTransitionRule.emit_commands(&chunk, 0);
+        assert!(matches!(
+            commands[..],
+            [DomainCommand::CompletePhaseTransition {
+                idx: 0,
+                new_voxel: VoxelKind::Air
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_lava_next_to_water_turns_to_obsidian() {
+        let mut chunk = ChunkData::new();
+        let lava_idx = ChunkData::index(4, 4, 4);
+        chunk.voxels.set(lava_idx, VoxelKind::Lava);
+        assert!(!LavaQuenchRule.evaluate(&chunk, lava_idx));
+
+        chunk.voxels.set(ChunkData::index(4, 5, 4), VoxelKind::Water);
+        assert!(LavaQuenchRule.evaluate(&chunk, lava_idx));
+        assert!(matches!(
+            LavaQuenchRule.emit_commands(&chunk, lava_idx)[0],
+            DomainCommand::CompletePhaseTransition {
+                new_voxel: VoxelKind::Obsidian,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_boiling_water_turns_to_air() {
+        let transition = target_transition(VoxelKind::Water, 120.0);
+        assert!(matches!(
+            transition,
+            Some((PhaseTransition::Boiling, VoxelKind::Air, _))
+        ));
+        assert!(target_transition(VoxelKind::Water, 50.0).is_none());
+    }
+}
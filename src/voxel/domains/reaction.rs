@@ -1,11 +1,21 @@
-/// 反应规则系统
-///
-/// 定义了条件判定和命令生成的接口
+//! 反应规则系统
+//!
+//! 定义了条件判定和命令生成的接口
 
 use bevy::prelude::*;
+use std::collections::BTreeSet;
+
+use super::command::{CommandQueue, DomainCommand};
+use super::corrosion::CORROSION_INTERVAL_TICKS;
+use super::weather::WeatherState;
+use crate::voxel::chunk::{ChunkData, Chunks};
 
-use super::command::DomainCommand;
-use crate::voxel::chunk::ChunkData;
+/// 反应规则判定时的全局环境
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReactionEnv {
+    /// 是否在下雨
+    pub raining: bool,
+}
 
 /// 反应规则特征
 ///
@@ -22,6 +32,13 @@ pub trait ReactionRule: Send + Sync {
     ///
     /// 只读访问 ChunkData，返回需要执行的命令
     fn emit_commands(&self, chunk: &ChunkData, idx: usize) -> Vec<DomainCommand>;
+
+    /// 规则在当前环境下是否参与判定，默认始终参与
+    ///
+    /// 只在特定天气下生效的规则重写此方法，每个 tick 判定一次
+    fn enabled(&self, _env: &ReactionEnv) -> bool {
+        true
+    }
 }
 
 /// 反应规则注册表
@@ -32,6 +49,72 @@ pub struct ReactionRules {
     pub rules: Vec<Box<dyn ReactionRule>>,
 }
 
+/// 参与反应规则判定的候选方块：所有活跃集合的并集，按索引排序
+///
+/// 正在腐蚀的方块只在 include_corrosion 为 true 的 tick 参与
+pub fn reaction_candidates(chunk: &ChunkData, include_corrosion: bool) -> BTreeSet<usize> {
+    let corroding = include_corrosion.then_some(&chunk.active_corrosion);
+    chunk
+        .active_thermal
+        .iter()
+        .chain(chunk.active_burning.iter())
+        .chain(chunk.active_freezing.iter())
+        .chain(chunk.active_melting.iter())
+        .chain(chunk.active_heat_sources.iter())
+        .chain(corroding.into_iter().flatten())
+        .copied()
+        .collect()
+}
+
+/// 反应规则判定系统
+///
+/// 在 SimulationSet::Reactions 阶段执行：对每个 chunk 的活跃方块评估所有规则，
+/// 产出的命令进入命令队列，等待 Commit 阶段统一执行。
+/// 腐蚀非常缓慢，正在腐蚀的方块每 CORROSION_INTERVAL_TICKS 个 tick 才参与一次评估
+pub fn reaction_system(
+    chunks: Chunks,
+    rules: Res<ReactionRules>,
+    weather: Res<WeatherState>,
+    mut command_queues: Query<&mut CommandQueue>,
+    mut tick: Local<u32>,
+) {
+    if rules.rules.is_empty() {
+        return;
+    }
+    *tick = tick.wrapping_add(1);
+    let corrosion_tick = tick.is_multiple_of(CORROSION_INTERVAL_TICKS);
+
+    let Some(mut queue) = command_queues.iter_mut().next() else {
+        return;
+    };
+
+    let env = ReactionEnv {
+        raining: weather.is_raining(),
+    };
+    let rules: Vec<&dyn ReactionRule> = rules
+        .rules
+        .iter()
+        .map(|rule| rule.as_ref())
+        .filter(|rule| rule.enabled(&env))
+        .collect();
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        if chunk.active_count() == 0 {
+            continue;
+        }
+
+        for idx in reaction_candidates(chunk, corrosion_tick) {
+            for rule in &rules {
+                if rule.evaluate(chunk, idx) {
+                    for command in rule.emit_commands(chunk, idx) {
+                        queue.push(chunk_pos, command);
+                    }
+                }
+            }
+        }
+    }
+}
+
 // ===== 示例规则（占位，实际规则在各领域模块中实现）=====
 
 /// 示例：日志规则（打印所有方块信息）
@@ -47,10 +130,3 @@ impl ReactionRule for LogRule {
         vec![]
     }
 }
-
-// TODO: 后续添加实际规则
-// - ThermalIgnitionRule: 温度点燃规则
-// - FreezingRule: 水冻结规则
-// - MeltingRule: 冰融化规则
-// - GrowthRule: 植物生长规则
-// - CorrosionRule: 金属腐蚀规则
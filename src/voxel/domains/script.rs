@@ -0,0 +1,600 @@
+//! 脚本反应规则（需要启用 `scripting` feature）
+//!
+//! [`ReactionRule`](super::reaction::ReactionRule) 是 Rust trait，新增规则需要重新编译。
+//! 这个模块从 `assets/rules/` 加载 Rhai 脚本，每个脚本是一条规则，和内置规则一样
+//! 在 SimulationSet::Reactions 阶段对活跃方块求值，产出的命令进入同一个命令队列。
+//!
+//! 脚本定义两个函数，对应 `ReactionRule` 的两个方法：
+//!
+//! ```rhai
+//! fn evaluate(block) { block.kind == "Snow" && block.temp > 60.0 }
+//! fn emit(block) { [set_block(block.idx, "Water")] }
+//! ```
+//!
+//! 脚本只能通过只读的方块快照读取区块：`idx`、`x`/`y`/`z`（区块内坐标）、`kind`（英文名）、
+//! `temp`、`variant`、`has_flag("BURNING")`，以及 `neighbors()` 和 `neighbor(dx, dy, dz)`
+//! 读取同一区块内的相邻方块（区块外返回 `()`）。`emit` 返回命令数组，命令由
+//! `set_block`、`add_flag`、`remove_flag`、`set_variant`、`set_temp`、`add_heat`、
+//! `ignite`、`extinguish` 构造。
+//!
+//! 每次调用最多执行 [`SCRIPT_MAX_OPERATIONS`] 步，每个 tick 所有脚本合计最多调用
+//! [`SCRIPT_CALLS_PER_TICK`] 次，用完后剩下的区块留到下一个 tick。脚本出错时停用
+//! 并在控制台报告，修改文件热重载后重新启用。
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, LoadedFolder};
+use bevy::prelude::*;
+use rhai::{
+    Array, CallFnOptions, Dynamic, Engine, EvalAltResult, ImmutableString, Scope, Variant, AST,
+};
+
+use super::command::{CommandQueue, DomainCommand};
+use super::reaction::reaction_candidates;
+use super::thermal::api::{idx_to_xyz, xyz_to_idx};
+use super::thermal::ThermalApi;
+use super::SimulationSet;
+use crate::console::ConsoleLog;
+use crate::voxel::chunk::{ChunkData, ChunkPos, Chunks};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 规则脚本目录（相对于 assets 目录）
+pub const SCRIPT_RULES_DIR: &str = "rules";
+/// 每次调用脚本函数最多执行的操作数
+pub const SCRIPT_MAX_OPERATIONS: u64 = 10_000;
+/// 每个 tick 所有脚本合计最多调用的次数
+pub const SCRIPT_CALLS_PER_TICK: u32 = 20_000;
+
+/// 六个相邻方向
+const NEIGHBOR_DIRS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// `.rhai` 规则脚本源码
+#[derive(Asset, TypePath, Debug)]
+pub struct RuleScript {
+    pub source: String,
+}
+
+/// `.rhai` 规则脚本加载器，只读取源码，编译在应用时进行以便报告错误
+#[derive(Default, TypePath)]
+pub struct RuleScriptLoader;
+
+impl AssetLoader for RuleScriptLoader {
+    type Asset = RuleScript;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let source = String::from_utf8(bytes)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        Ok(RuleScript { source })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rhai"]
+    }
+}
+
+/// 脚本读取的单个方块状态
+#[derive(Debug, Clone, Copy)]
+struct BlockCell {
+    idx: usize,
+    kind: VoxelKind,
+    variant: u8,
+    temp: f32,
+    flags: VoxelFlags,
+}
+
+impl BlockCell {
+    fn read(chunk: &ChunkData, idx: usize) -> Self {
+        Self {
+            idx,
+            kind: chunk.voxels.get(idx),
+            variant: chunk.variant.get(idx),
+            temp: ThermalApi::get_temp(chunk, idx),
+            flags: chunk.flags.get(idx),
+        }
+    }
+}
+
+/// 传给脚本的方块快照：方块自身和同一区块内的相邻方块
+///
+/// 快照在调用前读取，脚本拿不到区块的引用，也无法直接修改区块
+#[derive(Debug, Clone)]
+pub struct ScriptBlock {
+    cell: BlockCell,
+    /// 相邻方块，按 NEIGHBOR_DIRS 的顺序，区块外为 None
+    neighbors: Vec<Option<BlockCell>>,
+}
+
+impl ScriptBlock {
+    fn read(chunk: &ChunkData, idx: usize) -> Self {
+        let (x, y, z) = idx_to_xyz(idx);
+        let local = IVec3::new(x, y, z);
+        let neighbors = NEIGHBOR_DIRS
+            .iter()
+            .map(|&dir| {
+                let pos = local + dir;
+                let in_chunk =
+                    pos.cmpge(IVec3::ZERO).all() && pos.cmplt(IVec3::splat(CHUNK_SIZE)).all();
+                in_chunk.then(|| BlockCell::read(chunk, xyz_to_idx(pos.x, pos.y, pos.z)))
+            })
+            .collect();
+        Self {
+            cell: BlockCell::read(chunk, idx),
+            neighbors,
+        }
+    }
+
+    /// 只有自身没有邻居信息的快照（邻居的邻居）
+    fn lone(cell: BlockCell) -> Self {
+        Self {
+            cell,
+            neighbors: Vec::new(),
+        }
+    }
+
+    fn local(&self) -> IVec3 {
+        let (x, y, z) = idx_to_xyz(self.cell.idx);
+        IVec3::new(x, y, z)
+    }
+
+    fn neighbor(&self, offset: IVec3) -> Dynamic {
+        NEIGHBOR_DIRS
+            .iter()
+            .position(|&dir| dir == offset)
+            .and_then(|i| self.neighbors.get(i).copied().flatten())
+            .map_or(Dynamic::UNIT, |cell| Dynamic::from(Self::lone(cell)))
+    }
+}
+
+/// 脚本构造的命令
+#[derive(Debug, Clone)]
+pub struct ScriptCommand(DomainCommand);
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+fn script_idx(idx: i64) -> ScriptResult<usize> {
+    usize::try_from(idx)
+        .ok()
+        .filter(|&idx| idx < ChunkData::VOXEL_COUNT)
+        .ok_or_else(|| format!("方块下标超出区块：{idx}").into())
+}
+
+fn script_kind(name: &str) -> ScriptResult<VoxelKind> {
+    VoxelKind::from_name(name).ok_or_else(|| format!("未知的方块：{name}").into())
+}
+
+fn script_flag(name: &str) -> ScriptResult<VoxelFlags> {
+    VoxelFlags::from_name(&name.to_ascii_uppercase())
+        .ok_or_else(|| format!("未知的标志位：{name}").into())
+}
+
+/// 创建注册了方块快照和命令构造函数的脚本引擎
+pub fn script_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(SCRIPT_MAX_OPERATIONS);
+    engine.set_max_call_levels(16);
+
+    engine
+        .register_type_with_name::<ScriptBlock>("Block")
+        .register_get("idx", |block: &mut ScriptBlock| block.cell.idx as i64)
+        .register_get("x", |block: &mut ScriptBlock| block.local().x as i64)
+        .register_get("y", |block: &mut ScriptBlock| block.local().y as i64)
+        .register_get("z", |block: &mut ScriptBlock| block.local().z as i64)
+        .register_get("kind", |block: &mut ScriptBlock| -> ImmutableString {
+            format!("{:?}", block.cell.kind).into()
+        })
+        .register_get("temp", |block: &mut ScriptBlock| block.cell.temp as f64)
+        .register_get("variant", |block: &mut ScriptBlock| {
+            block.cell.variant as i64
+        })
+        .register_fn(
+            "has_flag",
+            |block: &mut ScriptBlock, name: &str| -> ScriptResult<bool> {
+                Ok(block.cell.flags.contains(script_flag(name)?))
+            },
+        )
+        .register_fn("neighbors", |block: &mut ScriptBlock| -> Array {
+            block
+                .neighbors
+                .iter()
+                .flatten()
+                .map(|&cell| Dynamic::from(ScriptBlock::lone(cell)))
+                .collect()
+        })
+        .register_fn(
+            "neighbor",
+            |block: &mut ScriptBlock, dx: i64, dy: i64, dz: i64| {
+                block.neighbor(IVec3::new(dx as i32, dy as i32, dz as i32))
+            },
+        );
+
+    engine
+        .register_type_with_name::<ScriptCommand>("Command")
+        .register_fn(
+            "set_block",
+            |idx: i64, kind: &str| -> ScriptResult<ScriptCommand> {
+                Ok(ScriptCommand(DomainCommand::SetBlock {
+                    idx: script_idx(idx)?,
+                    new_voxel: script_kind(kind)?,
+                }))
+            },
+        )
+        .register_fn(
+            "add_flag",
+            |idx: i64, flag: &str| -> ScriptResult<ScriptCommand> {
+                Ok(ScriptCommand(DomainCommand::AddFlag {
+                    idx: script_idx(idx)?,
+                    flag: script_flag(flag)?,
+                }))
+            },
+        )
+        .register_fn(
+            "remove_flag",
+            |idx: i64, flag: &str| -> ScriptResult<ScriptCommand> {
+                Ok(ScriptCommand(DomainCommand::RemoveFlag {
+                    idx: script_idx(idx)?,
+                    flag: script_flag(flag)?,
+                }))
+            },
+        )
+        .register_fn(
+            "set_variant",
+            |idx: i64, variant: i64| -> ScriptResult<ScriptCommand> {
+                Ok(ScriptCommand(DomainCommand::SetVariant {
+                    idx: script_idx(idx)?,
+                    variant: variant.clamp(0, u8::MAX as i64) as u8,
+                }))
+            },
+        )
+        .register_fn(
+            "set_temp",
+            |idx: i64, temp: f64| -> ScriptResult<ScriptCommand> {
+                Ok(ScriptCommand(DomainCommand::SetTemp {
+                    idx: script_idx(idx)?,
+                    temp: temp as f32,
+                }))
+            },
+        )
+        .register_fn(
+            "add_heat",
+            |idx: i64, heat: f64| -> ScriptResult<ScriptCommand> {
+                Ok(ScriptCommand(DomainCommand::AddHeat {
+                    idx: script_idx(idx)?,
+                    heat: heat as f32,
+                }))
+            },
+        )
+        .register_fn(
+            "ignite",
+            |idx: i64, power: f64| -> ScriptResult<ScriptCommand> {
+                Ok(ScriptCommand(DomainCommand::Ignite {
+                    idx: script_idx(idx)?,
+                    power: power as f32,
+                }))
+            },
+        )
+        .register_fn("extinguish", |idx: i64| -> ScriptResult<ScriptCommand> {
+            Ok(ScriptCommand(DomainCommand::Extinguish {
+                idx: script_idx(idx)?,
+            }))
+        });
+
+    engine
+}
+
+/// 一条编译好的脚本规则
+pub struct ScriptRule {
+    /// 脚本文件路径，用于报告
+    pub path: String,
+    asset: AssetId<RuleScript>,
+    ast: AST,
+    /// 运行出错后停用，直到文件被修改
+    pub failed: bool,
+}
+
+impl ScriptRule {
+    /// 编译脚本，检查 evaluate 和 emit 函数是否存在
+    pub fn compile(
+        engine: &Engine,
+        path: String,
+        asset: AssetId<RuleScript>,
+        source: &str,
+    ) -> Result<Self, String> {
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        for name in ["evaluate", "emit"] {
+            if !ast
+                .iter_functions()
+                .any(|func| func.name == name && func.params.len() == 1)
+            {
+                return Err(format!("缺少函数 {name}(block)"));
+            }
+        }
+        Ok(Self {
+            path,
+            asset,
+            ast,
+            failed: false,
+        })
+    }
+
+    /// 判断规则是否在方块上触发
+    pub fn evaluate(&self, engine: &Engine, block: &ScriptBlock) -> Result<bool, String> {
+        self.call::<bool>(engine, "evaluate", block)
+    }
+
+    /// 产生命令列表
+    pub fn emit(&self, engine: &Engine, block: &ScriptBlock) -> Result<Vec<DomainCommand>, String> {
+        self.call::<Array>(engine, "emit", block)?
+            .into_iter()
+            .map(|item| {
+                let type_name = item.type_name();
+                item.try_cast::<ScriptCommand>()
+                    .map(|command| command.0)
+                    .ok_or_else(|| format!("emit 返回了非命令的值：{type_name}"))
+            })
+            .collect()
+    }
+
+    fn call<T: Variant + Clone>(
+        &self,
+        engine: &Engine,
+        name: &str,
+        block: &ScriptBlock,
+    ) -> Result<T, String> {
+        // 不重新执行脚本顶层语句，每次调用使用独立的作用域
+        let options = CallFnOptions::new().eval_ast(false);
+        engine
+            .call_fn_with_options::<T>(
+                options,
+                &mut Scope::new(),
+                &self.ast,
+                name,
+                (block.clone(),),
+            )
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// 已加载的脚本规则
+#[derive(Resource)]
+pub struct ScriptRules {
+    engine: Engine,
+    pub rules: Vec<ScriptRule>,
+    folder: Option<Handle<LoadedFolder>>,
+    /// 上个 tick 用完调用次数时停下的区块，下个 tick 从这里继续
+    resume_from: Option<ChunkPos>,
+}
+
+impl Default for ScriptRules {
+    fn default() -> Self {
+        Self {
+            engine: script_engine(),
+            rules: Vec::new(),
+            folder: None,
+            resume_from: None,
+        }
+    }
+}
+
+/// 脚本规则插件
+///
+/// 注册规则脚本资源和加载器，启动时加载 `assets/rules/` 下的所有脚本
+pub struct ScriptRulePlugin;
+
+impl Plugin for ScriptRulePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScriptRules>()
+            .init_asset::<RuleScript>()
+            .init_asset_loader::<RuleScriptLoader>()
+            .add_systems(Startup, load_rule_scripts)
+            .add_systems(Update, apply_rule_scripts)
+            .add_systems(
+                FixedUpdate,
+                script_reaction_system
+                    .in_set(SimulationSet::Reactions)
+                    .after(super::reaction::reaction_system),
+            );
+    }
+}
+
+fn load_rule_scripts(asset_server: Res<AssetServer>, mut rules: ResMut<ScriptRules>) {
+    rules.folder = Some(asset_server.load_folder(SCRIPT_RULES_DIR));
+}
+
+/// 脚本加载或修改后重新编译对应的规则
+fn apply_rule_scripts(
+    mut events: MessageReader<AssetEvent<RuleScript>>,
+    scripts: Res<Assets<RuleScript>>,
+    asset_server: Res<AssetServer>,
+    mut rules: ResMut<ScriptRules>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for event in events.read() {
+        let id = match *event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => id,
+            AssetEvent::Removed { id } => {
+                rules.rules.retain(|rule| rule.asset != id);
+                continue;
+            }
+            _ => continue,
+        };
+        let Some(script) = scripts.get(id) else {
+            continue;
+        };
+        let path = asset_server
+            .get_path(id)
+            .map_or_else(|| format!("{id:?}"), |path| path.to_string());
+
+        rules.rules.retain(|rule| rule.asset != id);
+        match ScriptRule::compile(&rules.engine, path.clone(), id, &script.source) {
+            Ok(rule) => {
+                info!("Loaded rule script {path}");
+                rules.rules.push(rule);
+            }
+            Err(err) => log.print(format!("规则脚本 {path} 编译失败：{err}")),
+        }
+    }
+}
+
+/// 脚本规则判定系统
+///
+/// 在内置规则之后对每个区块的活跃方块求值，调用次数达到每 tick 上限后停止，
+/// 下一个 tick 从停下的区块继续
+fn script_reaction_system(
+    chunks: Chunks,
+    mut rules: ResMut<ScriptRules>,
+    mut command_queues: Query<&mut CommandQueue>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    let rules = &mut *rules;
+    if rules.rules.iter().all(|rule| rule.failed) {
+        return;
+    }
+    let Some(mut queue) = command_queues.iter_mut().next() else {
+        return;
+    };
+
+    let mut active: Vec<(ChunkPos, &ChunkData)> = chunks
+        .iter()
+        .filter(|(_, chunk)| chunk.active_count() > 0)
+        .collect();
+    active.sort_by_key(|(pos, _)| (pos.x, pos.y, pos.z));
+    if let Some(resume) = rules.resume_from.take() {
+        let start = active
+            .partition_point(|(pos, _)| (pos.x, pos.y, pos.z) < (resume.x, resume.y, resume.z));
+        active.rotate_left(start);
+    }
+
+    let mut calls = 0;
+    for (chunk_pos, chunk) in active {
+        if calls >= SCRIPT_CALLS_PER_TICK {
+            rules.resume_from = Some(chunk_pos);
+            break;
+        }
+        for idx in reaction_candidates(chunk, false) {
+            let block = ScriptBlock::read(chunk, idx);
+            for rule in rules.rules.iter_mut().filter(|rule| !rule.failed) {
+                calls += 1;
+                let result = rule.evaluate(&rules.engine, &block).and_then(|triggered| {
+                    if triggered {
+                        rule.emit(&rules.engine, &block)
+                    } else {
+                        Ok(Vec::new())
+                    }
+                });
+                match result {
+                    Ok(commands) => {
+                        for command in commands {
+                            queue.push(chunk_pos, command);
+                        }
+                    }
+                    Err(err) => {
+                        rule.failed = true;
+                        log.print(format!("规则脚本 {} 出错，已停用：{err}", rule.path));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> ScriptRule {
+        ScriptRule::compile(
+            &script_engine(),
+            "test.rhai".into(),
+            AssetId::default(),
+            source,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_script_rule_emits_commands() {
+        let engine = script_engine();
+        let rule = compile(
+            r#"
+            fn evaluate(block) { block.kind == "Snow" && block.temp > 60.0 }
+            fn emit(block) { [set_block(block.idx, "Water"), add_heat(block.idx, -100.0)] }
+            "#,
+        );
+
+        let mut chunk = ChunkData::new();
+        let idx = ChunkData::index(1, 2, 3);
+        chunk.voxels.set(idx, VoxelKind::Snow);
+        assert!(!rule
+            .evaluate(&engine, &ScriptBlock::read(&chunk, idx))
+            .unwrap());
+
+        ThermalApi::set_temp(&mut chunk, idx, 80.0);
+        let block = ScriptBlock::read(&chunk, idx);
+        assert!(rule.evaluate(&engine, &block).unwrap());
+        let commands = rule.emit(&engine, &block).unwrap();
+        assert!(matches!(
+            commands[0],
+            DomainCommand::SetBlock { idx: i, new_voxel: VoxelKind::Water } if i == idx
+        ));
+        assert!(matches!(commands[1], DomainCommand::AddHeat { .. }));
+    }
+
+    #[test]
+    fn test_script_reads_neighbors_in_chunk() {
+        let engine = script_engine();
+        let rule = compile(
+            r#"
+            fn evaluate(block) {
+                block.neighbor(0, 1, 0).kind == "Water" && block.neighbor(-1, 0, 0) == ()
+            }
+            fn emit(block) { [] }
+            "#,
+        );
+
+        let mut chunk = ChunkData::new();
+        let idx = ChunkData::index(0, 4, 4);
+        chunk
+            .voxels
+            .set(ChunkData::index(0, 5, 4), VoxelKind::Water);
+        assert!(rule
+            .evaluate(&engine, &ScriptBlock::read(&chunk, idx))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_bad_scripts_are_rejected() {
+        let engine = script_engine();
+        let id = AssetId::default();
+        assert!(
+            ScriptRule::compile(&engine, "a".into(), id, "fn evaluate(block) { true }").is_err()
+        );
+
+        // 未知方块和死循环在运行时报错
+        let rule = compile(
+            r#"
+            fn evaluate(block) { loop {} }
+            fn emit(block) { [set_block(block.idx, "Marble")] }
+            "#,
+        );
+        let block = ScriptBlock::read(&ChunkData::new(), 0);
+        assert!(rule.evaluate(&engine, &block).is_err());
+        assert!(rule.emit(&engine, &block).is_err());
+    }
+}
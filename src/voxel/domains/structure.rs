@@ -0,0 +1,564 @@
+//! 结构领域模块
+//!
+//! 包含重力和结构支撑两部分：结构完整性低的方块（沙子、沙砾）失去下方支撑后会下落；
+//! 其他实心方块只要能通过相连的方块找到支撑就保持原位，悬空太远的部分会坍塌。
+//!
+//! ## 下落流程
+//!
+//! 1. 方块变化后，变化位置及其上方的重力方块进入 active_falling
+//! 2. 检查时发现下方没有支撑：标记 UNSTABLE
+//! 3. 已标记的方块每个 tick 下落一格（原位置 Collapse，下方 FallInto）
+//! 4. 落到支撑物上后清除 UNSTABLE，不再活跃
+//!
+//! ## 结构支撑
+//!
+//! 方块被移除后，它的六个相邻方块进入 active_support，由 [`find_support`] 沿相连的实心方块
+//! 搜索支撑：向下走不计距离（叠在别的方块上），水平或向上走一格距离加一（悬挑、吊挂）。
+//! 距离超过方块的跨度（[`support_span`]，由结构完整性决定，损坏的方块减半）仍找不到支撑，
+//! 这个方块和它下方叠着的方块一起标记 UNSTABLE，之后按上面的下落流程逐格落下。
+//! 落下留出的空位又会唤醒新的相邻方块，悬空的部分在之后的几个 tick 里逐步坍塌。
+//!
+//! 搜索走到未加载的区块，或访问的方块数超过 [`SUPPORT_SEARCH_BUDGET`] 时视为有支撑：
+//! 连着大片地形的方块不会因为挖掘而整片塌落
+
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+use super::command::{commit_system, CommandQueue, DomainCommand};
+use super::fluid::is_fluid;
+use super::thermal::api::idx_to_xyz;
+use super::SimulationSet;
+use crate::voxel::change::BlockChange;
+use crate::voxel::chunk::{ChunkLookup, ChunksMut, VoxelWorld};
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 结构完整性低于此值的方块受重力影响
+pub const GRAVITY_INTEGRITY_THRESHOLD: f32 = 0.5;
+
+/// 判断方块是否受重力影响
+pub fn is_gravity_affected(kind: VoxelKind) -> bool {
+    kind != VoxelKind::Air && kind.def().props.integrity < GRAVITY_INTEGRITY_THRESHOLD
+}
+
+/// 判断方块能否支撑上方的重力方块
+///
+/// 液体和花草无法支撑，下落方块会直接替换它们
+pub fn supports_gravity_block(kind: VoxelKind) -> bool {
+    kind.is_solid() && !is_fluid(kind)
+}
+
+/// 完整性为 1 的方块最多能悬挑的距离（格）
+pub const MAX_SUPPORT_DISTANCE: u32 = 6;
+
+/// 一次支撑搜索最多访问的方块数，超过后视为连着大片地形
+pub const SUPPORT_SEARCH_BUDGET: usize = 4096;
+
+/// 每个 tick 最多检查支撑的方块数，其余留到之后的 tick
+const SUPPORT_CHECKS_PER_TICK: usize = 64;
+
+/// 支撑搜索和唤醒时检查的邻居方向
+const NEIGHBOR_DIRS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// 判断方块是否需要结构支撑（重力方块由重力检查处理）
+pub fn needs_support(kind: VoxelKind) -> bool {
+    supports_gravity_block(kind) && !is_gravity_affected(kind)
+}
+
+/// 方块离最近支撑的最大允许距离
+pub fn support_span(kind: VoxelKind, flags: VoxelFlags) -> u32 {
+    let span =
+        (kind.def().props.integrity.clamp(0.0, 1.0) * MAX_SUPPORT_DISTANCE as f32).round() as u32;
+    if flags.contains(VoxelFlags::DAMAGED) {
+        span / 2
+    } else {
+        span
+    }
+}
+
+/// 结构支撑检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupportCheck {
+    /// 在跨度内找到了支撑
+    Supported,
+    /// 没有支撑：起点和它下方叠着的方块（自上而下），将一起坍塌
+    Unsupported(Vec<IVec3>),
+}
+
+/// 搜索方块到支撑的距离
+///
+/// 0-1 广度优先搜索：向下一格代价为 0，水平和向上一格代价为 1，代价超过起点方块的跨度
+/// 就不再继续。走到未加载的区块或访问方块数超出预算都视为找到支撑
+pub fn find_support(world: &impl ChunkLookup, start: IVec3) -> SupportCheck {
+    let Some((kind, flags)) = block_at(world, start) else {
+        return SupportCheck::Supported;
+    };
+    if !needs_support(kind) {
+        return SupportCheck::Supported;
+    }
+    let span = support_span(kind, flags);
+
+    let mut best: HashMap<IVec3, u32> = HashMap::from([(start, 0)]);
+    let mut frontier = VecDeque::from([(start, 0)]);
+    while let Some((pos, cost)) = frontier.pop_front() {
+        if best.get(&pos).is_some_and(|&known| known < cost) {
+            continue;
+        }
+        for dir in NEIGHBOR_DIRS {
+            let next = pos + dir;
+            let next_cost = if dir == IVec3::NEG_Y { cost } else { cost + 1 };
+            if next_cost > span || best.get(&next).is_some_and(|&known| known <= next_cost) {
+                continue;
+            }
+            match block_at(world, next) {
+                None => return SupportCheck::Supported,
+                Some((kind, _)) if !supports_gravity_block(kind) => continue,
+                Some(_) => {}
+            }
+            best.insert(next, next_cost);
+            if best.len() > SUPPORT_SEARCH_BUDGET {
+                return SupportCheck::Supported;
+            }
+            if next_cost == cost {
+                frontier.push_front((next, next_cost));
+            } else {
+                frontier.push_back((next, next_cost));
+            }
+        }
+    }
+
+    // 下方叠着的方块离支撑不会比起点更近，和起点一起坍塌
+    let column = std::iter::successors(Some(start), |pos| Some(*pos + IVec3::NEG_Y))
+        .take_while(|pos| best.get(pos) == Some(&0))
+        .collect();
+    SupportCheck::Unsupported(column)
+}
+
+/// 读取世界坐标处的方块类型和标志位，区块未加载时返回 None
+fn block_at(world: &impl ChunkLookup, pos: IVec3) -> Option<(VoxelKind, VoxelFlags)> {
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+    world
+        .chunk(&chunk_pos)
+        .map(|chunk| (chunk.voxels.get(idx), chunk.flags.get(idx)))
+}
+
+/// 重力检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GravityStep {
+    /// 不受重力影响或稳定，无需处理
+    Idle,
+    /// 下方区块未加载，保持活跃等待
+    Wait,
+    /// 下落的方块已落地，清除不稳定标志
+    Settle,
+    /// 刚失去支撑，标记为不稳定
+    Destabilize,
+    /// 下落一格
+    Fall(VoxelKind),
+}
+
+/// 判断方块是否会下落：重力方块，或失去结构支撑、已标记为不稳定的方块
+fn may_fall(kind: VoxelKind, flags: VoxelFlags) -> bool {
+    is_gravity_affected(kind) || (flags.contains(VoxelFlags::UNSTABLE) && needs_support(kind))
+}
+
+/// 计算单个方块本 tick 的重力行为
+pub fn compute_gravity(world: &impl ChunkLookup, pos: IVec3) -> GravityStep {
+    let Some((kind, flags)) = block_at(world, pos) else {
+        return GravityStep::Idle;
+    };
+    if !may_fall(kind, flags) {
+        return GravityStep::Idle;
+    }
+
+    let unstable = flags.contains(VoxelFlags::UNSTABLE);
+
+    match block_at(world, pos + IVec3::NEG_Y) {
+        None => GravityStep::Wait,
+        // 下方的方块也在下落，等它让开
+        Some((below, below_flags))
+            if unstable
+                && supports_gravity_block(below)
+                && below_flags.contains(VoxelFlags::UNSTABLE) =>
+        {
+            GravityStep::Wait
+        }
+        Some((below, _)) if supports_gravity_block(below) => {
+            if unstable {
+                GravityStep::Settle
+            } else {
+                GravityStep::Idle
+            }
+        }
+        Some(_) if !unstable => GravityStep::Destabilize,
+        Some(_) => GravityStep::Fall(kind),
+    }
+}
+
+/// 重力系统
+///
+/// 在 SimulationSet::StateUpdate 阶段执行：检查活跃的重力方块并产出下落命令。
+/// 等待中和刚标记为不稳定的方块自己保持活跃；下落后的方块由唤醒系统在新位置重新加入
+pub fn gravity_system(mut chunks: ChunksMut, mut command_queues: Query<&mut CommandQueue>) {
+    let Some(mut queue) = command_queues.iter_mut().next() else {
+        return;
+    };
+
+    let mut active = Vec::new();
+    for (chunk_pos, mut chunk) in chunks.iter_mut() {
+        if chunk.active_falling.is_empty() {
+            continue;
+        }
+        let origin = chunk_pos.world_origin();
+        for idx in chunk.active_falling.drain() {
+            let (x, y, z) = idx_to_xyz(idx);
+            active.push(origin + IVec3::new(x, y, z));
+        }
+    }
+
+    // 按坐标顺序处理，下落方块争夺同一位置时每次都由同一个方块胜出
+    active.sort_unstable_by_key(|pos| (pos.y, pos.z, pos.x));
+    let mut keep_active = Vec::new();
+    for pos in active {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+
+        match compute_gravity(&chunks, pos) {
+            GravityStep::Idle => {}
+            GravityStep::Wait => keep_active.push((chunk_pos, idx)),
+            GravityStep::Settle => queue.push(
+                chunk_pos,
+                DomainCommand::RemoveFlag {
+                    idx,
+                    flag: VoxelFlags::UNSTABLE,
+                },
+            ),
+            GravityStep::Destabilize => {
+                queue.push(
+                    chunk_pos,
+                    DomainCommand::AddFlag {
+                        idx,
+                        flag: VoxelFlags::UNSTABLE,
+                    },
+                );
+                keep_active.push((chunk_pos, idx));
+            }
+            GravityStep::Fall(voxel) => {
+                let (below_chunk, below_idx) = VoxelWorld::split_world_pos(pos + IVec3::NEG_Y);
+                queue.push(chunk_pos, DomainCommand::Collapse { idx });
+                queue.push(
+                    below_chunk,
+                    DomainCommand::FallInto {
+                        idx: below_idx,
+                        voxel,
+                    },
+                );
+            }
+        }
+    }
+
+    for (chunk_pos, idx) in keep_active {
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos) {
+            chunk.active_falling.insert(idx);
+        }
+    }
+}
+
+/// 重力唤醒系统
+///
+/// 在提交之后执行：方块变化的位置及其正上方的重力方块、刚被标记为不稳定的方块进入活跃集合
+pub fn gravity_wake_system(mut chunks: ChunksMut) {
+    let mut to_wake = Vec::new();
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        let origin = chunk_pos.world_origin();
+        for change in &chunk.changes {
+            let above = match change {
+                BlockChange::SetVoxel { .. } | BlockChange::FillRun { .. } => true,
+                BlockChange::SetFlag {
+                    flag, set: true, ..
+                } if *flag == VoxelFlags::UNSTABLE => false,
+                _ => continue,
+            };
+            for idx in change.indices() {
+                let (x, y, z) = idx_to_xyz(idx);
+                let pos = origin + IVec3::new(x, y, z);
+                to_wake.push(pos);
+                if above {
+                    to_wake.push(pos + IVec3::Y);
+                }
+            }
+        }
+    }
+
+    for pos in to_wake {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos)
+            && may_fall(chunk.voxels.get(idx), chunk.flags.get(idx))
+        {
+            chunk.active_falling.insert(idx);
+        }
+    }
+}
+
+/// 结构支撑系统
+///
+/// 在 SimulationSet::StateUpdate 阶段执行：检查待检查方块的支撑，
+/// 没有支撑的方块连同下方叠着的方块标记为不稳定，交给重力系统下落
+pub fn support_system(mut chunks: ChunksMut, mut command_queues: Query<&mut CommandQueue>) {
+    let Some(mut queue) = command_queues.iter_mut().next() else {
+        return;
+    };
+
+    let mut pending = Vec::new();
+    for (chunk_pos, mut chunk) in chunks.iter_mut() {
+        let remaining = SUPPORT_CHECKS_PER_TICK - pending.len();
+        if remaining == 0 {
+            break;
+        }
+        let batch: Vec<usize> = chunk
+            .active_support
+            .iter()
+            .copied()
+            .take(remaining)
+            .collect();
+        let origin = chunk_pos.world_origin();
+        for idx in batch {
+            chunk.active_support.remove(&idx);
+            let (x, y, z) = idx_to_xyz(idx);
+            pending.push(origin + IVec3::new(x, y, z));
+        }
+    }
+
+    for pos in pending {
+        // 已经在下落的方块不必再检查
+        if block_at(&chunks, pos).is_none_or(|(_, flags)| flags.contains(VoxelFlags::UNSTABLE)) {
+            continue;
+        }
+        let SupportCheck::Unsupported(column) = find_support(&chunks, pos) else {
+            continue;
+        };
+        for block in column {
+            let (chunk_pos, idx) = VoxelWorld::split_world_pos(block);
+            queue.push(
+                chunk_pos,
+                DomainCommand::AddFlag {
+                    idx,
+                    flag: VoxelFlags::UNSTABLE,
+                },
+            );
+        }
+    }
+}
+
+/// 结构支撑唤醒系统
+///
+/// 在提交之后执行：被移除（变为空气、液体或花草）的方块周围需要支撑的方块进入待检查集合
+pub fn support_wake_system(mut chunks: ChunksMut) {
+    let mut to_wake = Vec::new();
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        let origin = chunk_pos.world_origin();
+        for change in &chunk.changes {
+            let removed = match *change {
+                BlockChange::SetVoxel { new, .. } | BlockChange::FillRun { new, .. } => {
+                    !supports_gravity_block(new)
+                }
+                _ => false,
+            };
+            if !removed {
+                continue;
+            }
+            for idx in change.indices() {
+                let (x, y, z) = idx_to_xyz(idx);
+                let pos = origin + IVec3::new(x, y, z);
+                to_wake.extend(NEIGHBOR_DIRS.map(|dir| pos + dir));
+            }
+        }
+    }
+
+    for pos in to_wake {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos)
+            && needs_support(chunk.voxels.get(idx))
+        {
+            chunk.active_support.insert(idx);
+        }
+    }
+}
+
+/// 结构插件
+pub struct StructurePlugin;
+
+impl Plugin for StructurePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                (support_system, gravity_system).in_set(SimulationSet::StateUpdate),
+                (support_wake_system, gravity_wake_system)
+                    .in_set(SimulationSet::Commit)
+                    .after(commit_system),
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::{ChunkData, ChunkPos};
+    use std::collections::HashMap;
+
+    fn world_with(blocks: &[(IVec3, VoxelKind)]) -> HashMap<ChunkPos, ChunkData> {
+        let mut chunk = ChunkData::new();
+        for &(pos, kind) in blocks {
+            chunk.set(pos.x, pos.y, pos.z, kind);
+        }
+        HashMap::from([(ChunkPos::new(0, 0, 0), chunk)])
+    }
+
+    #[test]
+    fn test_gravity_affected_kinds() {
+        assert!(is_gravity_affected(VoxelKind::Sand));
+        assert!(is_gravity_affected(VoxelKind::Gravel));
+        assert!(!is_gravity_affected(VoxelKind::Stone));
+        assert!(!is_gravity_affected(VoxelKind::Air));
+    }
+
+    #[test]
+    fn test_supported_sand_is_idle() {
+        let world = world_with(&[
+            (IVec3::new(4, 4, 4), VoxelKind::Sand),
+            (IVec3::new(4, 3, 4), VoxelKind::Stone),
+        ]);
+        assert_eq!(
+            compute_gravity(&world, IVec3::new(4, 4, 4)),
+            GravityStep::Idle
+        );
+    }
+
+    #[test]
+    fn test_unsupported_sand_is_marked_then_falls() {
+        let mut world = world_with(&[(IVec3::new(4, 4, 4), VoxelKind::Sand)]);
+        assert_eq!(
+            compute_gravity(&world, IVec3::new(4, 4, 4)),
+            GravityStep::Destabilize
+        );
+
+        let chunk = world.get_mut(&ChunkPos::new(0, 0, 0)).unwrap();
+        chunk.flags.set(ChunkData::index(4, 4, 4), VoxelFlags::UNSTABLE);
+        assert_eq!(
+            compute_gravity(&world, IVec3::new(4, 4, 4)),
+            GravityStep::Fall(VoxelKind::Sand)
+        );
+    }
+
+    #[test]
+    fn test_support_span_from_integrity() {
+        assert_eq!(
+            support_span(VoxelKind::Stone, VoxelFlags::NONE),
+            MAX_SUPPORT_DISTANCE
+        );
+        assert_eq!(
+            support_span(VoxelKind::Stone, VoxelFlags::DAMAGED),
+            MAX_SUPPORT_DISTANCE / 2
+        );
+        assert!(needs_support(VoxelKind::Stone));
+        assert!(!needs_support(VoxelKind::Sand));
+        assert!(!needs_support(VoxelKind::Water));
+    }
+
+    #[test]
+    fn test_overhang_beyond_span_is_unsupported() {
+        // 立在区块底部的石柱（下方区块未加载，视为地面），顶端向 +x 伸出一根横梁
+        let mut blocks: Vec<_> = (0..=4)
+            .map(|y| (IVec3::new(0, y, 4), VoxelKind::Stone))
+            .collect();
+        blocks.extend((1..=8).map(|x| (IVec3::new(x, 4, 4), VoxelKind::Stone)));
+        let world = world_with(&blocks);
+
+        assert_eq!(
+            find_support(&world, IVec3::new(0, 4, 4)),
+            SupportCheck::Supported
+        );
+        let last_supported = MAX_SUPPORT_DISTANCE as i32;
+        assert_eq!(
+            find_support(&world, IVec3::new(last_supported, 4, 4)),
+            SupportCheck::Supported
+        );
+        assert_eq!(
+            find_support(&world, IVec3::new(last_supported + 1, 4, 4)),
+            SupportCheck::Unsupported(vec![IVec3::new(last_supported + 1, 4, 4)])
+        );
+    }
+
+    #[test]
+    fn test_floating_column_collapses_together() {
+        let world = world_with(&[
+            (IVec3::new(4, 6, 4), VoxelKind::Stone),
+            (IVec3::new(4, 5, 4), VoxelKind::OakLog),
+            (IVec3::new(4, 4, 4), VoxelKind::Stone),
+        ]);
+        assert_eq!(
+            find_support(&world, IVec3::new(4, 6, 4)),
+            SupportCheck::Unsupported(vec![
+                IVec3::new(4, 6, 4),
+                IVec3::new(4, 5, 4),
+                IVec3::new(4, 4, 4),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unstable_stone_falls_after_the_block_below() {
+        let mut world = world_with(&[
+            (IVec3::new(4, 5, 4), VoxelKind::Stone),
+            (IVec3::new(4, 4, 4), VoxelKind::Stone),
+        ]);
+        assert_eq!(
+            compute_gravity(&world, IVec3::new(4, 5, 4)),
+            GravityStep::Idle
+        );
+
+        let chunk = world.get_mut(&ChunkPos::new(0, 0, 0)).unwrap();
+        chunk.flags.set(ChunkData::index(4, 5, 4), VoxelFlags::UNSTABLE);
+        chunk.flags.set(ChunkData::index(4, 4, 4), VoxelFlags::UNSTABLE);
+        assert_eq!(
+            compute_gravity(&world, IVec3::new(4, 4, 4)),
+            GravityStep::Fall(VoxelKind::Stone)
+        );
+        assert_eq!(
+            compute_gravity(&world, IVec3::new(4, 5, 4)),
+            GravityStep::Wait
+        );
+    }
+
+    #[test]
+    fn test_falling_sand_settles_and_sinks_through_water() {
+        let mut world = world_with(&[
+            (IVec3::new(4, 4, 4), VoxelKind::Gravel),
+            (IVec3::new(4, 3, 4), VoxelKind::Water),
+            (IVec3::new(4, 1, 4), VoxelKind::Sand),
+            (IVec3::new(4, 0, 4), VoxelKind::Stone),
+        ]);
+        let chunk = world.get_mut(&ChunkPos::new(0, 0, 0)).unwrap();
+        chunk.flags.set(ChunkData::index(4, 4, 4), VoxelFlags::UNSTABLE);
+        chunk.flags.set(ChunkData::index(4, 1, 4), VoxelFlags::UNSTABLE);
+
+        assert_eq!(
+            compute_gravity(&world, IVec3::new(4, 4, 4)),
+            GravityStep::Fall(VoxelKind::Gravel)
+        );
+        assert_eq!(
+            compute_gravity(&world, IVec3::new(4, 1, 4)),
+            GravityStep::Settle
+        );
+    }
+}
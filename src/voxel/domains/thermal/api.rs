@@ -6,6 +6,7 @@ use super::state::ThermalState;
 use crate::voxel::change::BlockChange;
 use crate::voxel::chunk::ChunkData;
 use crate::voxel::flags::VoxelFlags;
+use crate::voxel::voxel_kind::VoxelKind;
 
 /// 温度阈值常量
 pub const TEMP_HOT_THRESHOLD: f32 = 100.0; // 高温阈值 100°C
@@ -26,7 +27,7 @@ impl ThermalApi {
             }
         }
         // 回退到默认值
-        chunk.voxels[idx].def().props.temperature
+        chunk.voxels.get(idx).def().props.temperature
     }
 
     /// 设置方块温度
@@ -36,7 +37,7 @@ impl ThermalApi {
     /// 2. 更新温度相关标志位（HOT/COLD）
     /// 3. 记录变更日志
     pub fn set_temp(chunk: &mut ChunkData, idx: usize, temp: f32) {
-        let default_temp = chunk.voxels[idx].def().props.temperature;
+        let default_temp = chunk.voxels.get(idx).def().props.temperature;
 
         // 延迟分配稀疏状态
         let thermal = chunk.thermal_state.get_or_insert_with(ThermalState::default);
@@ -62,7 +63,7 @@ impl ThermalApi {
     /// 根据方块的热容计算温度变化: ΔT = Q / C
     pub fn add_heat(chunk: &mut ChunkData, idx: usize, heat: f32) {
         let current_temp = Self::get_temp(chunk, idx);
-        let heat_capacity = chunk.voxels[idx].def().props.heat_capacity;
+        let heat_capacity = chunk.voxels.get(idx).def().props.heat_capacity;
 
         // 防止除零
         if heat_capacity <= 0.0 {
@@ -77,16 +78,16 @@ impl ThermalApi {
     fn update_temp_flags(chunk: &mut ChunkData, idx: usize, temp: f32) {
         // 高温标志
         if temp > TEMP_HOT_THRESHOLD {
-            if !chunk.flags[idx].contains(VoxelFlags::HOT) {
-                chunk.flags[idx].insert(VoxelFlags::HOT);
+            if !chunk.flags.get(idx).contains(VoxelFlags::HOT) {
+                chunk.flags.update(idx, |flags| flags.insert(VoxelFlags::HOT));
                 chunk.changes.push(BlockChange::SetFlag {
                     idx,
                     flag: VoxelFlags::HOT,
                     set: true,
                 });
             }
-        } else if chunk.flags[idx].contains(VoxelFlags::HOT) {
-            chunk.flags[idx].remove(VoxelFlags::HOT);
+        } else if chunk.flags.get(idx).contains(VoxelFlags::HOT) {
+            chunk.flags.update(idx, |flags| flags.remove(VoxelFlags::HOT));
             chunk.changes.push(BlockChange::SetFlag {
                 idx,
                 flag: VoxelFlags::HOT,
@@ -96,16 +97,16 @@ impl ThermalApi {
 
         // 低温标志
         if temp < TEMP_COLD_THRESHOLD {
-            if !chunk.flags[idx].contains(VoxelFlags::COLD) {
-                chunk.flags[idx].insert(VoxelFlags::COLD);
+            if !chunk.flags.get(idx).contains(VoxelFlags::COLD) {
+                chunk.flags.update(idx, |flags| flags.insert(VoxelFlags::COLD));
                 chunk.changes.push(BlockChange::SetFlag {
                     idx,
                     flag: VoxelFlags::COLD,
                     set: true,
                 });
             }
-        } else if chunk.flags[idx].contains(VoxelFlags::COLD) {
-            chunk.flags[idx].remove(VoxelFlags::COLD);
+        } else if chunk.flags.get(idx).contains(VoxelFlags::COLD) {
+            chunk.flags.update(idx, |flags| flags.remove(VoxelFlags::COLD));
             chunk.changes.push(BlockChange::SetFlag {
                 idx,
                 flag: VoxelFlags::COLD,
@@ -122,7 +123,7 @@ impl ThermalApi {
     /// 3. 存在温度梯度
     pub fn should_stay_active(chunk: &ChunkData, idx: usize) -> bool {
         let temp = Self::get_temp(chunk, idx);
-        let default_temp = chunk.voxels[idx].def().props.temperature;
+        let default_temp = chunk.voxels.get(idx).def().props.temperature;
 
         // 条件 1：温度偏离默认值
         if (temp - default_temp).abs() > 1.0 {
@@ -130,18 +131,14 @@ impl ThermalApi {
         }
 
         // 条件 2：正在燃烧
-        if chunk.flags[idx].contains(VoxelFlags::BURNING) {
+        if chunk.flags.get(idx).contains(VoxelFlags::BURNING) {
             return true;
         }
 
         // 条件 3：周围有温度梯度
-        for neighbor_idx in get_neighbor_indices(idx) {
-            if neighbor_idx >= chunk.voxels.len() {
-                continue;
-            }
-
+        for neighbor_idx in get_valid_neighbor_indices(idx) {
             // 邻居在燃烧
-            if chunk.flags[neighbor_idx].contains(VoxelFlags::BURNING) {
+            if chunk.flags.get(neighbor_idx).contains(VoxelFlags::BURNING) {
                 return true;
             }
 
@@ -161,10 +158,18 @@ impl ThermalApi {
     pub fn activate(chunk: &mut ChunkData, idx: usize) {
         chunk.active_thermal.insert(idx);
 
-        // 也激活邻居
-        for neighbor_idx in get_neighbor_indices(idx) {
-            if neighbor_idx < chunk.voxels.len() {
-                chunk.active_thermal.insert(neighbor_idx);
+        // 也激活邻居（区块边缘的方块不会绕到区块另一侧）
+        chunk.active_thermal.extend(get_valid_neighbor_indices(idx));
+    }
+
+    /// 登记区块中所有持续产热的方块（区块生成后加入世界时调用）
+    pub fn register_heat_sources(chunk: &mut ChunkData) {
+        if !chunk.voxels.may_contain(is_heat_source) {
+            return;
+        }
+        for idx in 0..chunk.voxels.len() {
+            if is_heat_source(chunk.voxels.get(idx)) {
+                chunk.active_heat_sources.insert(idx);
             }
         }
     }
@@ -179,6 +184,11 @@ impl ThermalApi {
     }
 }
 
+/// 判断方块是否持续产热（不需要燃烧）
+pub fn is_heat_source(kind: VoxelKind) -> bool {
+    kind.def().props.heat_output > 0.0
+}
+
 /// 获取 3D 坐标的 6 个邻居索引
 ///
 /// 使用 CHUNK_SIZE = 16 的 Y-Z-X 线性化顺序
@@ -3,7 +3,7 @@
 //! 提供温度场模拟功能：
 //! - 热扩散（相邻方块间的热传导）
 //! - 环境热交换（边界与环境的热交换）
-//! - 热源（燃烧等持续产热）
+//! - 热源（燃烧、熔岩等持续产热）
 //!
 //! ## 物理模型
 //!
@@ -1,15 +1,18 @@
 //! 热扩散系统
 //!
-//! 在 FieldUpdate 阶段执行，对活跃的温度方块进行扩散计算
+//! 在 FieldUpdate 阶段执行，对活跃的温度方块进行扩散计算，各区块在计算线程池上并行处理
 
 use bevy::prelude::*;
 
 use super::api::{get_valid_neighbor_indices, ThermalApi};
-use crate::voxel::chunk::VoxelWorld;
+use crate::voxel::chunk::ChunkData;
+use crate::voxel::domains::clock::SimulationClock;
 use crate::voxel::domains::SimulationSet;
+use crate::voxel::profiling::{Stage, StageTimer};
+use crate::voxel::registry::VoxelRegistry;
 
 /// 环境温度（摄氏度）
-const ENV_TEMPERATURE: f32 = 20.0;
+pub const ENV_TEMPERATURE: f32 = 20.0;
 
 /// 热扩散系统
 ///
@@ -17,122 +20,149 @@ const ENV_TEMPERATURE: f32 = 20.0;
 /// - 相邻方块之间根据导热系数传递热量
 /// - 边界方块与环境进行热交换
 /// - 温度稳定的方块从活跃集合移除
-pub fn thermal_diffusion_system(mut voxel_world: ResMut<VoxelWorld>, time: Res<Time>) {
-    let dt = time.delta_secs();
+///
+/// 热传导只在区块内部进行，区块之间互不读取，所以有活跃温度的区块通过并行查询交给计算线程池处理。
+/// 每个区块先用本 tick 开始时的温度算出所有方块的热量变化，再按索引顺序统一应用，
+/// 结果与线程调度和区块表的遍历顺序无关
+pub fn thermal_diffusion_system(
+    mut chunks: Query<&mut ChunkData>,
+    time: Res<Time>,
+    clock: Res<SimulationClock>,
+) {
+    let dt = clock.scale(time.delta_secs());
 
     // 避免在时间暂停时计算
     if dt <= 0.0 {
         return;
     }
+    let _span = info_span!("thermal_diffusion").entered();
+    let _timer = StageTimer::start(Stage::Thermal);
 
-    // 遍历所有 chunk
-    for chunk in voxel_world.chunks.values_mut() {
-        // 只处理有热力学状态的 chunk
-        if chunk.active_thermal.is_empty() {
-            continue;
-        }
+    // 逐邻居查询定义，先取出注册表
+    let registry = VoxelRegistry::current();
 
-        // 复制活跃索引（避免借用冲突）
-        let active_indices: Vec<usize> = chunk.active_thermal.iter().copied().collect();
+    // 只处理有热力学状态的 chunk
+    chunks.par_iter_mut().for_each(|mut chunk| {
+        if !chunk.active_thermal.is_empty() {
+            diffuse_chunk(&mut chunk, registry, dt);
+        }
+    });
+}
 
-        // 第一遍：计算热量变化（写入 heat_buffer）
-        // 确保 thermal_state 存在
-        let thermal = chunk.thermal_state.get_or_insert_with(Default::default);
+/// 对一个区块的活跃方块做一次热扩散
+fn diffuse_chunk(chunk: &mut ChunkData, registry: &VoxelRegistry, dt: f32) {
+    // 复制活跃索引（避免借用冲突）
+    let active_indices: Vec<usize> = chunk.active_thermal.iter().copied().collect();
 
-        for &idx in &active_indices {
-            let current_temp = if let Some(&t) = thermal.temp_overrides.get(&idx) {
-                t
-            } else {
-                chunk.voxels[idx].def().props.temperature
-            };
+    // 第一遍：计算热量变化（写入 heat_buffer）
+    // 确保 thermal_state 存在
+    let thermal = chunk.thermal_state.get_or_insert_with(Default::default);
 
-            let props = chunk.voxels[idx].def().props;
+    for &idx in &active_indices {
+        let current_temp = if let Some(&t) = thermal.temp_overrides.get(&idx) {
+            t
+        } else {
+            registry.get(chunk.voxels.get(idx)).props.temperature
+        };
 
-            let mut heat_delta = 0.0;
+        let props = registry.get(chunk.voxels.get(idx)).props;
 
-            // 对有效的邻居进行热传导计算
-            for neighbor_idx in get_valid_neighbor_indices(idx) {
-                let neighbor_temp = if let Some(&t) = thermal.temp_overrides.get(&neighbor_idx) {
-                    t
-                } else {
-                    chunk.voxels[neighbor_idx].def().props.temperature
-                };
+        let mut heat_delta = 0.0;
 
-                let neighbor_props = chunk.voxels[neighbor_idx].def().props;
+        // 对有效的邻居进行热传导计算
+        for neighbor_idx in get_valid_neighbor_indices(idx) {
+            let neighbor_props = registry.get(chunk.voxels.get(neighbor_idx)).props;
 
-                // 热传导公式：Q = k * A * ΔT * dt
-                // 这里 A = 1（单位面积），简化计算
-                let k_avg =
-                    (props.thermal_conductivity + neighbor_props.thermal_conductivity) / 2.0;
-                let delta_t = neighbor_temp - current_temp;
-                let heat_flow = k_avg * delta_t * dt;
+            let neighbor_temp = if let Some(&t) = thermal.temp_overrides.get(&neighbor_idx) {
+                t
+            } else {
+                neighbor_props.temperature
+            };
 
-                heat_delta += heat_flow;
-            }
+            // 热传导公式：Q = k * A * ΔT * dt
+            // 这里 A = 1（单位面积），简化计算
+            let k_avg = (props.thermal_conductivity + neighbor_props.thermal_conductivity) / 2.0;
+            let delta_t = neighbor_temp - current_temp;
+            let heat_flow = k_avg * delta_t * dt;
 
-            // 环境热交换（边界条件）
-            // 只有暴露在空气中的方块才与环境交换
-            if props.env_exchange_coef > 0.0 {
-                heat_delta += props.env_exchange_coef * (ENV_TEMPERATURE - current_temp) * dt;
-            }
-
-            // 存入缓冲区
-            if heat_delta.abs() > 0.001 {
-                thermal.heat_buffer.insert(idx, heat_delta);
-            }
+            heat_delta += heat_flow;
         }
 
-        // 第二遍：应用热量变化
-        // 需要再次获取 thermal_state（由于借用规则）
-        let heat_changes: Vec<(usize, f32)> = {
-            if let Some(thermal) = &chunk.thermal_state {
-                thermal
-                    .heat_buffer
-                    .iter()
-                    .map(|(&idx, &heat)| (idx, heat))
-                    .collect()
-            } else {
-                vec![]
-            }
-        };
-
-        for (idx, heat) in heat_changes {
-            ThermalApi::add_heat(chunk, idx, heat);
+        // 环境热交换（边界条件）
+        // 只有暴露在空气中的方块才与环境交换
+        if props.env_exchange_coef > 0.0 {
+            heat_delta += props.env_exchange_coef * (ENV_TEMPERATURE - current_temp) * dt;
         }
 
-        // 清空热量缓冲
-        if let Some(thermal) = &mut chunk.thermal_state {
-            thermal.heat_buffer.clear();
+        // 存入缓冲区
+        if heat_delta.abs() > 0.001 {
+            thermal.heat_buffer.insert(idx, heat_delta);
         }
+    }
 
-        // 第三遍：清理不再活跃的方块
-        let mut to_remove = Vec::new();
-        for &idx in &active_indices {
-            if !ThermalApi::should_stay_active(chunk, idx) {
-                to_remove.push(idx);
-            }
+    // 第二遍：应用热量变化
+    // 需要再次获取 thermal_state（由于借用规则）
+    let mut heat_changes: Vec<(usize, f32)> = {
+        if let Some(thermal) = &chunk.thermal_state {
+            thermal
+                .heat_buffer
+                .iter()
+                .map(|(&idx, &heat)| (idx, heat))
+                .collect()
+        } else {
+            vec![]
         }
-        for idx in to_remove {
-            chunk.active_thermal.remove(&idx);
+    };
+
+    // 按索引顺序应用，变更日志的顺序不受哈希表遍历顺序影响
+    heat_changes.sort_unstable_by_key(|&(idx, _)| idx);
+    for (idx, heat) in heat_changes {
+        ThermalApi::add_heat(chunk, idx, heat);
+    }
+
+    // 清空热量缓冲
+    if let Some(thermal) = &mut chunk.thermal_state {
+        thermal.heat_buffer.clear();
+    }
+
+    // 第三遍：清理不再活跃的方块
+    let mut to_remove = Vec::new();
+    for &idx in &active_indices {
+        if !ThermalApi::should_stay_active(chunk, idx) {
+            to_remove.push(idx);
         }
     }
+    for idx in to_remove {
+        chunk.active_thermal.remove(&idx);
+    }
 }
 
 /// 热源系统
 ///
-/// 处理持续产热的方块（如燃烧中的方块）
-pub fn heat_source_system(mut voxel_world: ResMut<VoxelWorld>, time: Res<Time>) {
-    let dt = time.delta_secs();
+/// 处理持续产热的方块：
+/// - 燃烧中的方块释放热量，自身和周围各占一半
+/// - 熔岩等恒定热源把热量分给周围非热源方块，自身低于默认温度时先补足自身
+pub fn heat_source_system(
+    mut chunks: Query<&mut ChunkData>,
+    time: Res<Time>,
+    clock: Res<SimulationClock>,
+) {
+    let dt = clock.scale(time.delta_secs());
     if dt <= 0.0 {
         return;
     }
 
-    for chunk in voxel_world.chunks.values_mut() {
+    for mut chunk in &mut chunks {
+        // 没有热源的区块不取可变引用，避免被标记为已修改
+        if chunk.active_burning.is_empty() && chunk.active_heat_sources.is_empty() {
+            continue;
+        }
+        let chunk = &mut *chunk;
         // 复制燃烧索引
         let burning_indices: Vec<usize> = chunk.active_burning.iter().copied().collect();
 
         for idx in burning_indices {
-            let props = chunk.voxels[idx].def().props;
+            let props = chunk.voxels.get(idx).def().props;
 
             // 燃烧释放热量
             if props.is_flammable && props.heat_release > 0.0 {
@@ -149,6 +179,38 @@ pub fn heat_source_system(mut voxel_world: ResMut<VoxelWorld>, time: Res<Time>)
                 }
             }
         }
+
+        let source_indices: Vec<usize> = chunk.active_heat_sources.iter().copied().collect();
+
+        for idx in source_indices {
+            let props = chunk.voxels.get(idx).def().props;
+
+            // 方块已被替换为非热源
+            if props.heat_output <= 0.0 {
+                chunk.active_heat_sources.remove(&idx);
+                continue;
+            }
+
+            let mut heat = props.heat_output * dt;
+            if ThermalApi::get_temp(chunk, idx) < props.temperature {
+                ThermalApi::add_heat(chunk, idx, heat * 0.5);
+                heat *= 0.5;
+            }
+
+            // 相邻的热源之间不互相加热
+            let neighbors: Vec<usize> = get_valid_neighbor_indices(idx)
+                .into_iter()
+                .filter(|neighbor_idx| !chunk.active_heat_sources.contains(neighbor_idx))
+                .collect();
+            if neighbors.is_empty() {
+                continue;
+            }
+
+            let heat_per_neighbor = heat / neighbors.len() as f32;
+            for neighbor_idx in neighbors {
+                ThermalApi::add_heat(chunk, neighbor_idx, heat_per_neighbor);
+            }
+        }
     }
 }
 
@@ -165,3 +227,52 @@ impl Plugin for ThermalPlugin {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::{spawn_chunk, ChunkPos};
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::tasks::{ComputeTaskPool, TaskPool};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parallel_diffusion_matches_serial() {
+        let mut expected = HashMap::new();
+        for i in 0..10 {
+            let mut chunk = ChunkData::new();
+            for j in 0..=i {
+                let idx = ChunkData::index(j, 8, 15 - j);
+                ThermalApi::set_temp(&mut chunk, idx, 100.0 + 50.0 * j as f32);
+                ThermalApi::activate(&mut chunk, idx);
+            }
+            chunk.clear_changes();
+            expected.insert(ChunkPos::new(i, -1, 0), chunk);
+        }
+
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_millis(50));
+        let dt = time.delta_secs();
+        // 没有 App 时并行查询用的任务池需要手动初始化
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let mut ecs = World::new();
+        for (&chunk_pos, chunk) in &expected {
+            spawn_chunk(&mut ecs, chunk_pos, chunk.clone());
+        }
+        ecs.insert_resource(time);
+        ecs.insert_resource(SimulationClock::default());
+        ecs.run_system_once(thermal_diffusion_system).unwrap();
+
+        let registry = VoxelRegistry::current();
+        for chunk in expected.values_mut() {
+            diffuse_chunk(chunk, registry, dt);
+        }
+        for (chunk_pos, chunk) in ecs.query::<(&ChunkPos, &ChunkData)>().iter(&ecs) {
+            let serial = &expected[chunk_pos];
+            assert!(!chunk.changes.is_empty());
+            assert_eq!(chunk.changes, serial.changes);
+            assert_eq!(chunk.active_thermal, serial.active_thermal);
+        }
+    }
+}
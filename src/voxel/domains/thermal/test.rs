@@ -1,27 +1,44 @@
 //! 热力学测试系统
 //!
-//! 提供交互式热力学测试功能：
+//! 提供交互式热力学测试功能（以下为默认按键，可在设置中修改）：
 //! - F5: 在玩家位置附近创建热源
 //! - F6: 显示当前位置的温度信息
 //! - F7: 清除所有温度覆盖
+//! - F8: 切换温度场可视化（在玩家附近的升温/降温方块上绘制彩色线框）
 
 use bevy::prelude::*;
 
-use super::api::ThermalApi;
-use crate::voxel::chunk::{ChunkData, ChunkPos, VoxelWorld};
+use super::api::{idx_to_xyz, ThermalApi};
+use crate::input::{Action, ActionInput};
+use crate::voxel::chunk::{ChunkData, ChunkPos, Chunks, ChunksMut};
 use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::ivec3_to_vec3;
+
+/// 温度可视化的绘制半径（方块）
+const THERMAL_OVERLAY_RADIUS: f32 = 48.0;
+
+/// 每帧最多绘制的方块数量，避免大面积升温时 gizmo 过多
+const THERMAL_OVERLAY_MAX_VOXELS: usize = 4096;
+
+/// 温度场可视化开关
+#[derive(Resource, Debug, Default)]
+pub struct ThermalOverlay {
+    pub enabled: bool,
+}
 
 /// 热力学测试插件
 pub struct ThermalTestPlugin;
 
 impl Plugin for ThermalTestPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.init_resource::<ThermalOverlay>().add_systems(
             Update,
             (
                 create_heat_source_system,
                 show_temperature_info_system,
                 clear_thermal_state_system,
+                toggle_thermal_overlay_system,
+                draw_thermal_overlay_system,
             ),
         );
     }
@@ -30,23 +47,20 @@ impl Plugin for ThermalTestPlugin {
 /// 创建热源系统
 ///
 /// 按 F5 在世界原点附近的 chunk 中创建一个热源
-fn create_heat_source_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut voxel_world: ResMut<VoxelWorld>,
-) {
-    if keyboard.just_pressed(KeyCode::F5) {
+fn create_heat_source_system(actions: ActionInput, mut chunks: ChunksMut) {
+    if actions.just_pressed(Action::PlaceHeatSource) {
         // 获取原点 chunk
         let chunk_pos = ChunkPos::new(0, 0, 0);
 
-        if let Some(chunk) = voxel_world.chunks.get_mut(&chunk_pos) {
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos) {
             // 在 chunk 中心创建热源
             let center_idx = ChunkData::index(8, 8, 8);
 
             // 设置高温（500°C）
-            ThermalApi::set_temp(chunk, center_idx, 500.0);
+            ThermalApi::set_temp(&mut chunk, center_idx, 500.0);
 
             // 激活周围方块
-            ThermalApi::activate(chunk, center_idx);
+            ThermalApi::activate(&mut chunk, center_idx);
 
             info!(
                 "Created heat source at chunk {:?}, idx {}, temp = 500°C",
@@ -65,14 +79,11 @@ fn create_heat_source_system(
 /// 显示温度信息系统
 ///
 /// 按 F6 显示原点 chunk 中心区域的温度信息
-fn show_temperature_info_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    voxel_world: Res<VoxelWorld>,
-) {
-    if keyboard.just_pressed(KeyCode::F6) {
+fn show_temperature_info_system(actions: ActionInput, chunks: Chunks) {
+    if actions.just_pressed(Action::ShowTemperature) {
         let chunk_pos = ChunkPos::new(0, 0, 0);
 
-        if let Some(chunk) = voxel_world.chunks.get(&chunk_pos) {
+        if let Some(chunk) = chunks.get(&chunk_pos) {
             info!("=== Temperature Info (Chunk {:?}) ===", chunk_pos);
             info!("Active thermal: {}", chunk.active_thermal.len());
 
@@ -116,14 +127,11 @@ fn show_temperature_info_system(
 /// 清除温度状态系统
 ///
 /// 按 F7 清除所有 chunk 的温度覆盖
-fn clear_thermal_state_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut voxel_world: ResMut<VoxelWorld>,
-) {
-    if keyboard.just_pressed(KeyCode::F7) {
+fn clear_thermal_state_system(actions: ActionInput, mut chunks: ChunksMut) {
+    if actions.just_pressed(Action::ClearTemperature) {
         let mut cleared_count = 0;
 
-        for chunk in voxel_world.chunks.values_mut() {
+        for (_, mut chunk) in chunks.iter_mut() {
             if let Some(thermal) = &mut chunk.thermal_state {
                 cleared_count += thermal.temp_overrides.len();
                 thermal.clear();
@@ -135,6 +143,109 @@ fn clear_thermal_state_system(
     }
 }
 
+/// 切换温度可视化系统
+///
+/// 按 F8 开启/关闭温度场可视化
+fn toggle_thermal_overlay_system(
+    actions: ActionInput,
+    mut overlay: ResMut<ThermalOverlay>,
+) {
+    if actions.just_pressed(Action::ToggleThermalOverlay) {
+        overlay.enabled = !overlay.enabled;
+        info!(
+            "Thermal overlay {}",
+            if overlay.enabled { "enabled" } else { "disabled" }
+        );
+    }
+}
+
+/// 绘制温度可视化系统
+///
+/// 对相机附近温度偏离默认值或正在扩散的方块绘制线框，颜色由 temp_to_color 决定
+fn draw_thermal_overlay_system(
+    overlay: Res<ThermalOverlay>,
+    chunks: Chunks,
+    camera_query: Query<&GlobalTransform, With<Camera3d>>,
+    mut gizmos: Gizmos,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    let Some(camera) = camera_query.iter().next() else {
+        return;
+    };
+    let eye = camera.translation();
+
+    // 区块中心到区块角的距离，用于粗略剔除
+    let chunk_reach = (CHUNK_SIZE as f32) * 0.5 * 3.0_f32.sqrt();
+    let mut drawn = 0;
+
+    for (chunk_pos, chunk) in chunks.iter() {
+        let overrides = chunk.thermal_state.as_ref().map(|t| &t.temp_overrides);
+        if overrides.is_none_or(|o| o.is_empty()) && chunk.active_thermal.is_empty() {
+            continue;
+        }
+
+        let origin = ivec3_to_vec3(chunk_pos.world_origin());
+        let center = origin + Vec3::splat(CHUNK_SIZE as f32 * 0.5);
+        if center.distance(eye) > THERMAL_OVERLAY_RADIUS + chunk_reach {
+            continue;
+        }
+
+        // 温度覆盖的方块 + 尚未偏离默认温度的活跃方块（扩散前沿）
+        let voxels = overrides.into_iter().flat_map(|o| o.keys().copied()).chain(
+            chunk
+                .active_thermal
+                .iter()
+                .copied()
+                .filter(|idx| !overrides.is_some_and(|o| o.contains_key(idx))),
+        );
+        drawn += draw_voxels(
+            &mut gizmos,
+            chunk,
+            chunk_pos,
+            voxels,
+            eye,
+            THERMAL_OVERLAY_MAX_VOXELS - drawn,
+        );
+
+        if drawn >= THERMAL_OVERLAY_MAX_VOXELS {
+            break;
+        }
+    }
+}
+
+/// 绘制区块内给定方块的温度线框，返回实际绘制的数量
+fn draw_voxels(
+    gizmos: &mut Gizmos,
+    chunk: &ChunkData,
+    chunk_pos: ChunkPos,
+    voxels: impl Iterator<Item = usize>,
+    eye: Vec3,
+    budget: usize,
+) -> usize {
+    let origin = chunk_pos.world_origin();
+    let mut drawn = 0;
+
+    for idx in voxels {
+        if drawn >= budget {
+            break;
+        }
+        let (x, y, z) = idx_to_xyz(idx);
+        let center = ivec3_to_vec3(origin + IVec3::new(x, y, z)) + Vec3::splat(0.5);
+        if center.distance(eye) > THERMAL_OVERLAY_RADIUS {
+            continue;
+        }
+
+        let color = temp_to_color(ThermalApi::get_temp(chunk, idx)).with_alpha(0.6);
+        let transform = Transform::from_translation(center).with_scale(Vec3::splat(0.9));
+        gizmos.cube(transform, color);
+        drawn += 1;
+    }
+
+    drawn
+}
+
 /// 创建温度可视化颜色
 ///
 /// 将温度映射到颜色：冷（蓝色）-> 常温（绿色）-> 热（红色）
@@ -176,6 +287,7 @@ pub fn create_test_heat_pattern(chunk: &mut ChunkData) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::voxel::chunk::VoxelWorld;
     use crate::voxel::voxel_kind::VoxelKind;
 
     #[test]
@@ -200,7 +312,7 @@ mod tests {
         let mut chunk = ChunkData::new();
 
         // 将一个方块设为石头（热容 2000）
-        chunk.voxels[0] = VoxelKind::Stone;
+        chunk.voxels.set(0, VoxelKind::Stone);
 
         let initial_temp = ThermalApi::get_temp(&chunk, 0);
 
@@ -213,6 +325,36 @@ mod tests {
         assert!((new_temp - initial_temp - 1.0).abs() < 0.1);
     }
 
+    #[test]
+    fn test_register_heat_sources() {
+        let mut chunk = ChunkData::new();
+        ThermalApi::register_heat_sources(&mut chunk);
+        assert!(chunk.active_heat_sources.is_empty());
+
+        let lava_idx = ChunkData::index(3, 4, 5);
+        chunk.voxels.set(lava_idx, VoxelKind::Lava);
+        ThermalApi::register_heat_sources(&mut chunk);
+        assert_eq!(chunk.active_heat_sources.len(), 1);
+        assert!(chunk.active_heat_sources.contains(&lava_idx));
+    }
+
+    #[test]
+    fn test_activate_at_chunk_edge() {
+        // 负坐标的方块落在所在区块的 x = 15, z = 0 边缘
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(IVec3::new(-1, -20, -16));
+        assert_eq!(chunk_pos, ChunkPos::new(-1, -2, -1));
+        assert_eq!(idx_to_xyz(idx), (15, 12, 0));
+
+        let mut chunk = ChunkData::new();
+        ThermalApi::set_temp(&mut chunk, idx, 300.0);
+        ThermalApi::activate(&mut chunk, idx);
+
+        // 自身和 4 个区块内的邻居，+X 和 -Z 在相邻区块中
+        assert_eq!(chunk.active_thermal.len(), 5);
+        assert!(!chunk.active_thermal.contains(&(idx + 1)));
+        assert!(ThermalApi::should_stay_active(&chunk, idx - 1));
+    }
+
     #[test]
     fn test_temp_to_color() {
         // 冷色
@@ -0,0 +1,437 @@
+//! 天气
+//!
+//! 世界在晴天和雨天之间交替。每段天气持续的 tick 数由世界种子和段序号决定，
+//! 同一个世界从头开始模拟时天气完全相同。天气随模拟推进，暂停模拟时天气也停止变化。
+//!
+//! 其他领域读取 [`WeatherState`]：
+//! 反应规则通过 [`ReactionEnv`](super::reaction::ReactionEnv) 得知是否在下雨（雨水浇灭露天的火），
+//! 粒子系统在镜头周围画出雨滴，云层（[`crate::clouds`]）在下雨前逐渐变厚。
+//!
+//! 寒冷地区（[`TerrainGenerator::is_cold`](crate::voxel::terrain::TerrainGenerator::is_cold)）
+//! 的降水是雪。下雪时天气会在露天地表积起薄薄的
+//! 积雪（[`VoxelKind::SnowLayer`]，变体记录层数减一）：每个区块每个 tick 抽取少量地表列，
+//! 按 [`SNOW_CHANCE`] 的概率加一层，最多积到 [`MAX_SNOWFALL_LAYERS`] 层。积雪升温后由相变
+//! 领域一层一层融化。
+//!
+//! 控制台的 weather 命令查看或切换天气。天气状态可以序列化，随世界保存
+//! （见 [`crate::world_state`]）。
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::command::{CommandQueue, DomainCommand};
+use super::SimulationSet;
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::voxel::chunk::{ChunkLookup, ChunkPos, Chunks, VoxelWorld};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::registry::VoxelRegistry;
+use crate::voxel::seed::XorShift32;
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 每秒的模拟 tick 数（FixedUpdate 默认 64Hz）
+pub const TICKS_PER_SECOND: u32 = 64;
+/// 晴天持续的秒数范围
+const CLEAR_SECONDS: (u32, u32) = (180, 480);
+/// 雨天持续的秒数范围
+const RAIN_SECONDS: (u32, u32) = (60, 180);
+
+/// 下雪时每个区块每个 tick 抽取的地表列数
+const SNOW_TICKS_PER_CHUNK: usize = 1;
+/// 抽到的露天地表积一层雪的概率（每列平均约 4 秒被抽到一次）
+pub const SNOW_CHANCE: f32 = 0.05;
+/// 降雪最多积到的层数
+pub const MAX_SNOWFALL_LAYERS: u8 = 4;
+/// 向上检查露天的高度（方块），更高处的遮挡忽略不计
+const SNOW_SKY_SCAN: i32 = 32;
+
+/// 天气类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Weather {
+    #[default]
+    Clear,
+    Rain,
+}
+
+impl Weather {
+    pub fn name(self) -> &'static str {
+        match self {
+            Weather::Clear => "晴",
+            Weather::Rain => "雨",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Weather::Clear => Weather::Rain,
+            Weather::Rain => Weather::Clear,
+        }
+    }
+
+    /// 按哈希值在持续时间范围内取一段天气的 tick 数
+    fn duration(self, hash: u64) -> u32 {
+        let (min, max) = match self {
+            Weather::Clear => CLEAR_SECONDS,
+            Weather::Rain => RAIN_SECONDS,
+        };
+        (min + (hash % (max - min + 1) as u64) as u32) * TICKS_PER_SECOND
+    }
+}
+
+/// 当前天气
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeatherState {
+    pub weather: Weather,
+    /// 当前天气还要持续的 tick 数
+    pub remaining_ticks: u32,
+    /// 已经开始的天气段数
+    spell: u64,
+    seed: u32,
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl WeatherState {
+    /// 世界开始时是晴天
+    pub fn new(seed: u32) -> Self {
+        let mut state = Self {
+            weather: Weather::Clear,
+            remaining_ticks: 0,
+            spell: 0,
+            seed,
+        };
+        state.remaining_ticks = state.weather.duration(state.spell_hash());
+        state
+    }
+
+    /// 决定各段天气时长的世界种子
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn is_raining(&self) -> bool {
+        self.weather == Weather::Rain
+    }
+
+    /// 距离下一场雨的 tick 数，正在下雨时为 0
+    pub fn ticks_until_rain(&self) -> u32 {
+        match self.weather {
+            Weather::Clear => self.remaining_ticks,
+            Weather::Rain => 0,
+        }
+    }
+
+    /// 推进一个 tick，当前天气结束时切换到下一种天气
+    pub fn advance(&mut self) {
+        self.remaining_ticks = self.remaining_ticks.saturating_sub(1);
+        if self.remaining_ticks == 0 {
+            self.set(self.weather.next());
+        }
+    }
+
+    /// 立即切换到指定天气，开始新的一段
+    pub fn set(&mut self, weather: Weather) {
+        self.weather = weather;
+        self.spell += 1;
+        self.remaining_ticks = weather.duration(self.spell_hash());
+    }
+
+    fn spell_hash(&self) -> u64 {
+        let mut h = (self.seed as u64) ^ 0x5851_F42D_4C95_7F2D;
+        h ^= self.spell.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^ (h >> 31)
+    }
+}
+
+/// 积雪判定使用的随机数
+#[derive(Resource, Debug)]
+pub struct SnowRng(XorShift32);
+
+impl Default for SnowRng {
+    fn default() -> Self {
+        Self(XorShift32::new(0x6b43_a9b5))
+    }
+}
+
+/// 在 surface（一列中最高的不透明方块）上落下一层雪的命令和它所在的区块
+///
+/// 地表已经是积雪（或高度图还没刷新，积雪在地表上方）时加厚一层；地表是不透明的整格方块、
+/// 上方是空气时铺上第一层。上方不露天、积雪已有 [`MAX_SNOWFALL_LAYERS`] 层或区块未加载时返回 None
+pub fn snowfall_command(
+    world: &impl ChunkLookup,
+    registry: &VoxelRegistry,
+    surface: IVec3,
+) -> Option<(ChunkPos, DomainCommand)> {
+    let above = surface + IVec3::Y;
+    let (snow_pos, existing) = match world.get_voxel(surface) {
+        VoxelKind::SnowLayer => (surface, true),
+        kind if registry.occludes(kind) => match world.get_voxel(above) {
+            VoxelKind::SnowLayer => (above, true),
+            VoxelKind::Air => (above, false),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let open_sky =
+        (1..=SNOW_SKY_SCAN).all(|dy| world.get_voxel(snow_pos + IVec3::Y * dy) == VoxelKind::Air);
+    if !open_sky {
+        return None;
+    }
+
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(snow_pos);
+    let chunk = world.chunk(&chunk_pos)?;
+    if !existing {
+        return Some((
+            chunk_pos,
+            DomainCommand::SetBlock {
+                idx,
+                new_voxel: VoxelKind::SnowLayer,
+            },
+        ));
+    }
+    (chunk.variant.get(idx) < MAX_SNOWFALL_LAYERS - 1)
+        .then_some((chunk_pos, DomainCommand::IncrementVariant { idx }))
+}
+
+/// 积雪系统
+///
+/// 在 StateUpdate 阶段、天气推进之后执行：下雨时每个区块抽取 SNOW_TICKS_PER_CHUNK 个地表列，
+/// 只处理地表位于本区块内、并且在寒冷地区的列，保证每列在每个 tick 最多被处理一次
+fn accumulate_snow(
+    weather: Res<WeatherState>,
+    world: Res<VoxelWorld>,
+    chunks: Chunks,
+    terrain: Res<SharedTerrain>,
+    mut rng: ResMut<SnowRng>,
+    mut command_queues: Query<&mut CommandQueue>,
+) {
+    if !weather.is_raining() {
+        return;
+    }
+    let Some(mut queue) = command_queues.iter_mut().next() else {
+        return;
+    };
+    let generator = terrain.generator();
+    let registry = VoxelRegistry::current();
+
+    // 按区块坐标顺序消耗随机数，同一种子下每次运行积雪的位置相同
+    let mut positions: Vec<ChunkPos> = chunks.chunk_positions().collect();
+    positions.sort_unstable();
+    for chunk_pos in positions {
+        let origin = chunk_pos.world_origin();
+        for _ in 0..SNOW_TICKS_PER_CHUNK {
+            let column = (rng.0.next_u32() % (CHUNK_SIZE * CHUNK_SIZE) as u32) as i32;
+            let roll = rng.0.next_f32();
+            let (x, z) = (
+                origin.x + column % CHUNK_SIZE,
+                origin.z + column / CHUNK_SIZE,
+            );
+            let Some(height) = world.heightmap.height(x, z) else {
+                continue;
+            };
+            if height.div_euclid(CHUNK_SIZE) != chunk_pos.y
+                || roll >= SNOW_CHANCE
+                || !generator.is_cold(x, height, z)
+            {
+                continue;
+            }
+            if let Some((target, command)) =
+                snowfall_command(&chunks, registry, IVec3::new(x, height, z))
+            {
+                queue.push(target, command);
+            }
+        }
+    }
+}
+
+/// 天气插件
+///
+/// 注册天气资源、天气推进和积雪系统以及 weather 控制台命令
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "weather",
+            "weather [clear|rain]",
+            "查看或切换天气",
+        );
+
+        app.init_resource::<WeatherState>()
+            .init_resource::<SnowRng>()
+            .add_systems(Update, weather_console_command)
+            .add_systems(
+                FixedUpdate,
+                (sync_weather_seed, advance_weather, accumulate_snow)
+                    .chain()
+                    .in_set(SimulationSet::StateUpdate),
+            );
+    }
+}
+
+/// 切换世界后按新世界的种子重新开始天气
+fn sync_weather_seed(terrain: Res<SharedTerrain>, mut weather: ResMut<WeatherState>) {
+    if weather.seed != terrain.seed() {
+        *weather = WeatherState::new(terrain.seed());
+    }
+}
+
+fn advance_weather(mut weather: ResMut<WeatherState>) {
+    weather.advance();
+}
+
+/// 执行 weather 控制台命令
+fn weather_console_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut weather: ResMut<WeatherState>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "weather" {
+            continue;
+        }
+        let args: Vec<&str> = command.args.iter().map(String::as_str).collect();
+        let target = match args.as_slice() {
+            [] => {
+                log.print(format!(
+                    "当前天气：{}，还会持续 {} 秒",
+                    weather.weather.name(),
+                    weather.remaining_ticks / TICKS_PER_SECOND
+                ));
+                continue;
+            }
+            ["clear"] => Weather::Clear,
+            ["rain"] => Weather::Rain,
+            _ => {
+                log.print("用法：weather [clear|rain]");
+                continue;
+            }
+        };
+        weather.set(target);
+        log.print(format!("天气切换为{}", target.name()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::ChunkData;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_weather_alternates() {
+        let mut state = WeatherState::new(42);
+        assert_eq!(state.weather, Weather::Clear);
+
+        let clear_ticks = state.remaining_ticks;
+        assert!(clear_ticks >= CLEAR_SECONDS.0 * TICKS_PER_SECOND);
+        assert!(clear_ticks <= CLEAR_SECONDS.1 * TICKS_PER_SECOND);
+        for _ in 0..clear_ticks {
+            state.advance();
+        }
+        assert!(state.is_raining());
+
+        let rain_ticks = state.remaining_ticks;
+        assert!(rain_ticks >= RAIN_SECONDS.0 * TICKS_PER_SECOND);
+        assert!(rain_ticks <= RAIN_SECONDS.1 * TICKS_PER_SECOND);
+        for _ in 0..rain_ticks {
+            state.advance();
+        }
+        assert_eq!(state.weather, Weather::Clear);
+    }
+
+    /// 地面（y = 0）是 ground 的单区块世界
+    fn field(ground: VoxelKind) -> HashMap<ChunkPos, ChunkData> {
+        let mut chunk = ChunkData::new();
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                chunk.set(x, 0, z, ground);
+            }
+        }
+        let mut world = HashMap::new();
+        world.insert(ChunkPos::new(0, 0, 0), chunk);
+        world
+    }
+
+    #[test]
+    fn test_snow_settles_on_open_ground() {
+        let world = field(VoxelKind::Grass);
+        let registry = VoxelRegistry::builtin();
+        let surface = IVec3::new(3, 0, 4);
+        let Some((chunk_pos, DomainCommand::SetBlock { idx, new_voxel })) =
+            snowfall_command(&world, &registry, surface)
+        else {
+            panic!("expected a new snow layer");
+        };
+        assert_eq!(chunk_pos, ChunkPos::new(0, 0, 0));
+        assert_eq!(idx, ChunkData::index(3, 1, 4));
+        assert_eq!(new_voxel, VoxelKind::SnowLayer);
+
+        // 头顶有遮挡时不积雪，花草上也不积雪
+        let mut world = field(VoxelKind::Grass);
+        let chunk = world.get_mut(&ChunkPos::new(0, 0, 0)).unwrap();
+        chunk.set(3, 8, 4, VoxelKind::OakLeaves);
+        chunk.set(5, 1, 5, VoxelKind::Flower);
+        assert!(snowfall_command(&world, &registry, surface).is_none());
+        assert!(snowfall_command(&world, &registry, IVec3::new(5, 0, 5)).is_none());
+    }
+
+    #[test]
+    fn test_snow_layers_stop_at_max() {
+        let mut world = field(VoxelKind::Stone);
+        let registry = VoxelRegistry::builtin();
+        let idx = ChunkData::index(2, 1, 2);
+        let chunk = world.get_mut(&ChunkPos::new(0, 0, 0)).unwrap();
+        chunk.set(2, 1, 2, VoxelKind::SnowLayer);
+
+        // 高度图是否已经算上积雪都会加厚同一层
+        for surface in [IVec3::new(2, 0, 2), IVec3::new(2, 1, 2)] {
+            assert!(matches!(
+                snowfall_command(&world, &registry, surface),
+                Some((_, DomainCommand::IncrementVariant { idx: i })) if i == idx
+            ));
+        }
+
+        let chunk = world.get_mut(&ChunkPos::new(0, 0, 0)).unwrap();
+        chunk.variant.set(idx, MAX_SNOWFALL_LAYERS - 1);
+        assert!(snowfall_command(&world, &registry, IVec3::new(2, 1, 2)).is_none());
+    }
+
+    #[test]
+    fn test_weather_is_deterministic() {
+        let mut a = WeatherState::new(7);
+        let mut b = WeatherState::new(7);
+        for _ in 0..100_000 {
+            a.advance();
+            b.advance();
+        }
+        assert_eq!(a.weather, b.weather);
+        assert_eq!(a.remaining_ticks, b.remaining_ticks);
+    }
+
+    #[test]
+    fn test_saved_weather_continues_the_same() {
+        let mut state = WeatherState::new(7);
+        state.set(Weather::Rain);
+        state.advance();
+
+        let text = ron::to_string(&state).unwrap();
+        let mut restored: WeatherState = ron::from_str(&text).unwrap();
+        assert_eq!(restored, state);
+        assert_eq!(restored.seed(), 7);
+
+        // 恢复后的天气段序号不变，之后的天气和没保存过一样
+        for _ in 0..100_000 {
+            state.advance();
+            restored.advance();
+        }
+        assert_eq!(restored, state);
+    }
+}
@@ -0,0 +1,158 @@
+//! 区块和方块事件
+//!
+//! 供玩法扩展（任务、统计等）读取的消息，由现有系统在对应时刻发出，
+//! 扩展只需用 `MessageReader` 读取，不需要修改体素内部：
+//!
+//! - [`ChunkLoaded`]：区块数据进入世界（新生成或恢复卸载时保留的已修改区块）
+//! - [`ChunkUnloaded`]：区块数据离开世界
+//! - [`ChunkRemeshed`]：区块的网格重建完成并替换到渲染实体上
+//! - [`BlockChanged`]：方块类型变化（玩家、控制台、模拟、回放等任何来源）
+//! - [`BlockIgnited`]：方块被点燃
+//! - [`SimulationTickCompleted`]：一个模拟 tick 推进完成（暂停时不发出）
+//!
+//! 方块和模拟事件在 FixedUpdate 中发出，区块事件在 Update 中发出。
+//! 区块事件由 [`VoxelPlugin`](super::VoxelPlugin) 注册，方块和模拟事件由
+//! [`DomainPlugin`](super::DomainPlugin) 注册，无渲染模拟中也可以读取。
+
+use bevy::prelude::*;
+
+use crate::voxel::change::BlockChange;
+use crate::voxel::chunk::{ChunkPos, Chunks};
+use crate::voxel::domains::thermal::api::idx_to_xyz;
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 区块数据进入世界
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ChunkLoaded {
+    pub chunk_pos: ChunkPos,
+    /// 是否是卸载时保留的已修改区块，否则是新生成的区块
+    pub restored: bool,
+}
+
+/// 区块数据离开世界
+///
+/// 被修改过的区块卸载时保留在内存中，重新进入加载范围时恢复并再次发出 [`ChunkLoaded`]
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ChunkUnloaded {
+    pub chunk_pos: ChunkPos,
+}
+
+/// 区块网格重建完成
+///
+/// 只在方块修改后重建网格时发出；首次加载的网格随 [`ChunkLoaded`] 一起生效
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ChunkRemeshed {
+    pub chunk_pos: ChunkPos,
+    /// 重建后区块是否还有可见的面
+    pub has_geometry: bool,
+}
+
+/// 方块类型变化
+#[derive(Message, Debug, Clone, Copy)]
+pub struct BlockChanged {
+    /// 方块的世界坐标
+    pub pos: IVec3,
+    /// 原来的方块类型，区域填充不记录被替换的方块，此时为 None
+    pub old: Option<VoxelKind>,
+    pub new: VoxelKind,
+}
+
+/// 方块被点燃
+#[derive(Message, Debug, Clone, Copy)]
+pub struct BlockIgnited {
+    /// 方块的世界坐标
+    pub pos: IVec3,
+}
+
+/// 一个模拟 tick 推进完成
+#[derive(Message, Debug, Clone, Copy)]
+pub struct SimulationTickCompleted {
+    /// 启动以来推进的 tick 数（包括这一个）
+    pub tick: u64,
+}
+
+/// 把本 tick 变更日志中的方块类型变化和点燃作为 [`BlockChanged`] 和 [`BlockIgnited`] 发出
+///
+/// 在 SimulationSet::Post 中、清理变更日志之前运行
+pub fn emit_block_changes(
+    chunks: Chunks,
+    mut changed: MessageWriter<BlockChanged>,
+    mut ignited: MessageWriter<BlockIgnited>,
+) {
+    for (chunk_pos, chunk) in chunks.iter() {
+        let origin = chunk_pos.world_origin();
+        let pos = |idx: usize| {
+            let (x, y, z) = idx_to_xyz(idx);
+            origin + IVec3::new(x, y, z)
+        };
+        for change in &chunk.changes {
+            match *change {
+                BlockChange::SetVoxel { idx, old, new } => {
+                    changed.write(BlockChanged {
+                        pos: pos(idx),
+                        old: Some(old),
+                        new,
+                    });
+                }
+                BlockChange::FillRun { start, len, new } => {
+                    changed.write_batch((start..start + len).map(|idx| BlockChanged {
+                        pos: pos(idx),
+                        old: None,
+                        new,
+                    }));
+                }
+                BlockChange::SetFlag {
+                    idx,
+                    flag,
+                    set: true,
+                } if flag == VoxelFlags::BURNING => {
+                    ignited.write(BlockIgnited { pos: pos(idx) });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::{spawn_chunk, ChunkData, ChunkIndex};
+
+    #[test]
+    fn test_block_changes_become_messages() {
+        let mut app = App::new();
+        app.init_resource::<ChunkIndex>()
+            .add_message::<BlockChanged>()
+            .add_message::<BlockIgnited>()
+            .add_systems(Update, emit_block_changes);
+
+        let chunk_pos = ChunkPos::new(1, 0, -1);
+        let mut chunk = ChunkData::new();
+        chunk.changes.push(BlockChange::SetVoxel {
+            idx: ChunkData::index(2, 3, 4),
+            old: VoxelKind::Air,
+            new: VoxelKind::Stone,
+        });
+        chunk.changes.push(BlockChange::FillRun {
+            start: 0,
+            len: 3,
+            new: VoxelKind::Water,
+        });
+        spawn_chunk(app.world_mut(), chunk_pos, chunk);
+        app.update();
+
+        let messages = app.world().resource::<Messages<BlockChanged>>();
+        let changed: Vec<BlockChanged> = messages.iter_current_update_messages().copied().collect();
+        assert_eq!(changed.len(), 4);
+        assert_eq!(
+            changed[0].pos,
+            chunk_pos.world_origin() + IVec3::new(2, 3, 4)
+        );
+        assert_eq!(changed[0].old, Some(VoxelKind::Air));
+        assert!(changed[1..]
+            .iter()
+            .all(|change| change.old.is_none() && change.new == VoxelKind::Water));
+    }
+}
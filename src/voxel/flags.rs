@@ -60,6 +60,45 @@ impl Default for VoxelFlags {
     }
 }
 
+/// 各标志位的显示名称
+const FLAG_NAMES: [(VoxelFlags, &str); 16] = [
+    (VoxelFlags::BURNING, "燃烧"),
+    (VoxelFlags::CHARRED, "焦化"),
+    (VoxelFlags::SMOLDERING, "闷烧"),
+    (VoxelFlags::HOT, "高温"),
+    (VoxelFlags::COLD, "低温"),
+    (VoxelFlags::WET, "潮湿"),
+    (VoxelFlags::SOAKED, "浸透"),
+    (VoxelFlags::FROZEN, "冻结"),
+    (VoxelFlags::MELTING, "融化中"),
+    (VoxelFlags::EVAPORATING, "蒸发中"),
+    (VoxelFlags::CONDENSING, "凝结中"),
+    (VoxelFlags::DAMAGED, "损坏"),
+    (VoxelFlags::UNSTABLE, "不稳定"),
+    (VoxelFlags::CORRODED, "腐蚀"),
+    (VoxelFlags::GROWING, "生长中"),
+    (VoxelFlags::WITHERING, "枯萎中"),
+];
+
+impl VoxelFlags {
+    /// 影响方块外观的标志位，变化时需要重建网格
+    ///
+    /// 燃烧的方块发光，焦化、潮湿和冻结的方块在网格构建时改变颜色
+    pub const VISUAL: VoxelFlags = VoxelFlags::BURNING
+        .union(VoxelFlags::CHARRED)
+        .union(VoxelFlags::WET)
+        .union(VoxelFlags::SOAKED)
+        .union(VoxelFlags::FROZEN);
+
+    /// 已设置的标志位的显示名称（按位从低到高）
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        FLAG_NAMES
+            .into_iter()
+            .filter(move |(flag, _)| self.contains(*flag))
+            .map(|(_, name)| name)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +122,12 @@ mod tests {
         assert!(flags.contains(VoxelFlags::COLD));
         assert!(!flags.contains(VoxelFlags::BURNING));
     }
+
+    #[test]
+    fn test_flag_names() {
+        let flags = VoxelFlags::FROZEN | VoxelFlags::BURNING;
+        assert_eq!(flags.names().collect::<Vec<_>>(), vec!["燃烧", "冻结"]);
+        assert_eq!(VoxelFlags::NONE.names().count(), 0);
+        assert_eq!(VoxelFlags::all().names().count(), FLAG_NAMES.len());
+    }
 }
@@ -0,0 +1,329 @@
+//! 无渲染的领域模拟
+//!
+//! 使用 `--headless-sim [tick 数]` 启动时不创建窗口和渲染器：以 MinimalPlugins 运行只包含
+//! 领域系统的 App，生成原点周围水平半径（单位：区块）内的地形，执行 `--sim-command` 给出的
+//! 控制台命令（如 `setblock 0 40 0 lava`、`explode 4 33 4`），再推进指定数量的固定 tick，
+//! 最后汇总温度、燃烧和方块变化。
+//!
+//! 每次 `App::update` 恰好推进一个固定 tick，与帧率和机器快慢无关，
+//! 同一种子、生成器和命令下的汇总可以在 CI 中作为物理领域的回归基线，
+//! 其中的世界摘要（[`WorldDigest::of_state`]）在两次运行之间完全相同，
+//! 也不随编译器版本改变。
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use serde::Serialize;
+
+use crate::console::{parse_line, ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::input::{InputBindings, InputCapture};
+use crate::voxel::chunk::{
+    spawn_chunk, ChunkData, ChunkIndex, ChunkLookup, ChunkPos, Chunks, VoxelWorld,
+};
+use crate::voxel::domains::clock::SimulationClock;
+use crate::voxel::domains::command::commit_system;
+use crate::voxel::domains::thermal::ThermalApi;
+use crate::voxel::domains::{DomainPlugin, SimulationSet};
+use crate::voxel::pregen::{
+    plan_regions, read_structure_templates, read_worldgen_config, vertical_chunk_range, ASSETS_DIR,
+};
+use crate::voxel::registry::{BlockDefinitions, BLOCK_DEFINITIONS_PATH};
+use crate::voxel::seed::WorldSeed;
+use crate::voxel::terrain::digest::WorldDigest;
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::voxel_kind::VoxelKind;
+use crate::voxel::worldgen::WorldGenOptions;
+
+/// 未指定时推进的 tick 数（默认步长下 10 秒）
+pub const DEFAULT_SIM_TICKS: u64 = 640;
+/// 未指定时的水平半径（区块）
+pub const DEFAULT_SIM_RADIUS: i32 = 1;
+
+/// 无渲染模拟参数
+#[derive(Debug, Clone)]
+pub struct HeadlessSimOptions {
+    pub seed: u32,
+    /// 水平半径（区块）
+    pub radius: i32,
+    pub ticks: u64,
+    pub worldgen: WorldGenOptions,
+    /// 第一个 tick 之前执行的控制台命令
+    pub commands: Vec<String>,
+}
+
+/// 无渲染模拟失败原因
+#[derive(Debug, thiserror::Error)]
+pub enum HeadlessSimError {
+    #[error("未知命令：{0}")]
+    UnknownCommand(String),
+}
+
+/// 模拟结束时的汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct SimSummary {
+    pub seed: u32,
+    pub ticks: u64,
+    pub chunks: usize,
+    /// 所有 tick 提交的方块变更条数
+    pub changes: usize,
+    /// 与模拟开始时不同的方块数
+    pub changed_blocks: usize,
+    /// 开始时可燃、结束时变成其他方块的数量
+    pub burned_blocks: usize,
+    /// 结束时仍在燃烧的方块数
+    pub burning: usize,
+    /// 结束时温度仍在变化的方块数
+    pub active_thermal: usize,
+    /// 温度仍在变化的方块中的最高温度，没有这样的方块时为 None
+    pub max_temperature: Option<f32>,
+    /// 温度仍在变化的方块的平均温度
+    pub mean_temperature: Option<f32>,
+    /// 数量发生变化的方块种类及其增减
+    pub block_deltas: BTreeMap<String, i64>,
+    /// 控制台命令的输出
+    pub console: Vec<String>,
+    /// 结束时的世界摘要（十六进制）
+    pub digest: String,
+}
+
+/// 模拟期间提交的方块变更条数
+#[derive(Resource, Debug, Default)]
+struct SimChanges(usize);
+
+/// 运行无渲染模拟
+pub fn run_headless_sim(options: &HeadlessSimOptions) -> Result<SimSummary, HeadlessSimError> {
+    let assets_dir = Path::new(ASSETS_DIR);
+    let mut config = read_worldgen_config(assets_dir);
+    options.worldgen.apply(&mut config);
+    let structures = read_structure_templates(assets_dir);
+    let y_range = vertical_chunk_range(&config, &structures);
+    let terrain = SharedTerrain::new(WorldSeed::new(options.seed), config, Arc::new(structures));
+
+    let mut chunks = HashMap::new();
+    let generator = terrain.generator();
+    for (_, positions) in plan_regions(options.radius.max(0), y_range) {
+        for chunk_pos in positions {
+            let mut chunk = generator.generate_chunk(chunk_pos);
+            ThermalApi::register_heat_sources(&mut chunk);
+            chunks.insert(chunk_pos, chunk);
+        }
+    }
+    let initial: HashMap<ChunkPos, Vec<VoxelKind>> = chunks
+        .iter()
+        .map(|(&chunk_pos, chunk)| (chunk_pos, chunk.voxels.to_vec()))
+        .collect();
+
+    let mut app = build_app(chunks, terrain);
+    for line in &options.commands {
+        let Some(command) = parse_line(line) else {
+            continue;
+        };
+        if app
+            .world()
+            .resource::<ConsoleCommands>()
+            .get(&command.name)
+            .is_none()
+        {
+            return Err(HeadlessSimError::UnknownCommand(command.name));
+        }
+        app.world_mut()
+            .resource_mut::<ConsoleLog>()
+            .print(format!("> {}", line.trim()));
+        app.world_mut().write_message(command);
+    }
+
+    while app.world().resource::<SimulationClock>().ticks() < options.ticks {
+        app.update();
+    }
+
+    Ok(summarize(app.world(), options.seed, &initial))
+}
+
+/// 只包含领域系统的 App，每次 update 推进一个固定 tick
+fn build_app(chunks: HashMap<ChunkPos, ChunkData>, terrain: SharedTerrain) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin))
+        .insert_resource(TimeUpdateStrategy::FixedTimesteps(1))
+        // 模拟时钟的快捷键和控制台命令需要的资源，无渲染时没有设置和控制台界面
+        .init_resource::<InputBindings>()
+        .init_resource::<InputCapture>()
+        .init_resource::<ConsoleLog>()
+        .add_message::<ConsoleCommand>()
+        .init_resource::<VoxelWorld>()
+        .init_resource::<ChunkIndex>()
+        .insert_resource(terrain)
+        .init_resource::<SimChanges>()
+        .add_plugins(DomainPlugin)
+        .add_systems(
+            FixedUpdate,
+            count_changes
+                .in_set(SimulationSet::Commit)
+                .after(commit_system),
+        );
+    for (chunk_pos, chunk) in chunks {
+        spawn_chunk(app.world_mut(), chunk_pos, chunk);
+    }
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// 在变更日志被清理之前累计本 tick 的变更
+fn count_changes(chunks: Chunks, mut changes: ResMut<SimChanges>) {
+    changes.0 += chunks
+        .iter()
+        .map(|(_, chunk)| chunk.changes.len())
+        .sum::<usize>();
+}
+
+fn summarize(
+    app_world: &World,
+    seed: u32,
+    initial: &HashMap<ChunkPos, Vec<VoxelKind>>,
+) -> SimSummary {
+    let mut changed_blocks = 0;
+    let mut burned_blocks = 0;
+    let mut deltas: BTreeMap<String, i64> = BTreeMap::new();
+    for (chunk_pos, before) in initial {
+        let Some(chunk) = app_world.chunk(chunk_pos) else {
+            continue;
+        };
+        for (idx, &old) in before.iter().enumerate() {
+            let new = chunk.voxels.get(idx);
+            if new == old {
+                continue;
+            }
+            changed_blocks += 1;
+            if old.def().props.is_flammable {
+                burned_blocks += 1;
+            }
+            *deltas.entry(format!("{old:?}")).or_default() -= 1;
+            *deltas.entry(format!("{new:?}")).or_default() += 1;
+        }
+    }
+    deltas.retain(|_, delta| *delta != 0);
+
+    // 排序后再求和，平均温度不受活跃集合遍历顺序影响
+    let mut temperatures: Vec<f32> = app_world
+        .iter_chunks()
+        .flat_map(|(_, chunk)| {
+            chunk
+                .active_thermal
+                .iter()
+                .map(|&idx| ThermalApi::get_temp(chunk, idx))
+        })
+        .collect();
+    temperatures.sort_by(f32::total_cmp);
+
+    SimSummary {
+        seed,
+        ticks: app_world.resource::<SimulationClock>().ticks(),
+        chunks: app_world.chunk_positions().count(),
+        changes: app_world.resource::<SimChanges>().0,
+        changed_blocks,
+        burned_blocks,
+        burning: app_world
+            .iter_chunks()
+            .map(|(_, c)| c.active_burning.len())
+            .sum(),
+        active_thermal: temperatures.len(),
+        max_temperature: temperatures.last().copied(),
+        mean_temperature: (!temperatures.is_empty())
+            .then(|| temperatures.iter().sum::<f32>() / temperatures.len() as f32),
+        block_deltas: deltas,
+        console: app_world
+            .resource::<ConsoleLog>()
+            .lines()
+            .map(str::to_string)
+            .collect(),
+        digest: WorldDigest::of_state(app_world.iter_chunks()).to_string(),
+    }
+}
+
+/// 与游戏一样使用资源目录中的方块定义，文件缺失或无效时保留内置定义
+///
+/// 注册表是全局的，所以由启动参数的处理在模拟之前调用，单元测试始终使用内置定义
+pub fn install_block_definitions() {
+    let path = Path::new(ASSETS_DIR).join(BLOCK_DEFINITIONS_PATH);
+    let Ok(bytes) = fs::read(&path) else {
+        return;
+    };
+    match BlockDefinitions::from_ron(&bytes) {
+        Ok(definitions) => definitions.to_registry().install(),
+        Err(err) => eprintln!("[sim] {} 无效，使用内置定义: {err}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::worldgen::TerrainShape;
+
+    fn superflat(commands: &[&str], ticks: u64) -> HeadlessSimOptions {
+        HeadlessSimOptions {
+            seed: 1,
+            radius: 0,
+            ticks,
+            worldgen: WorldGenOptions {
+                shape: TerrainShape::Superflat,
+                ..default()
+            },
+            commands: commands.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_headless_sim_runs_commands_and_ticks() {
+        // 超平坦地表在 y = 32，上方的方块悬空；熔岩是恒定热源，会加热周围的空气
+        let summary = run_headless_sim(&superflat(&["setblock 3 40 3 lava"], 16)).unwrap();
+
+        assert_eq!(summary.ticks, 16);
+        assert!(summary.changes >= 1);
+        assert!(summary.changed_blocks >= 1);
+        assert!(summary.max_temperature.is_some_and(|temp| temp > 20.0));
+        assert_eq!(summary.console[0], "> setblock 3 40 3 lava");
+    }
+
+    #[test]
+    fn test_headless_sim_undoes_fill() {
+        // 同一 tick 内撤销在填充之后提交，单个方块的命令覆盖区域命令
+        let summary =
+            run_headless_sim(&superflat(&["fill 0 40 0 1 41 1 stone", "undo"], 2)).unwrap();
+
+        assert_eq!(summary.changed_blocks, 0);
+        assert_eq!(summary.console[2], "已填充 8 个方块");
+        assert_eq!(summary.console[3], "已撤销 8 个方块");
+    }
+
+    #[test]
+    fn test_headless_sim_is_deterministic() {
+        // 填充、熔岩、爆炸和撤销在同一 tick 内相互冲突，之后还有燃烧、流动和下落
+        let script = [
+            "fill 0 33 0 6 35 6 oak_log",
+            "setblock 3 36 3 lava",
+            "fill 8 36 8 9 40 9 sand",
+            "explode 4 34 4",
+            "undo",
+        ];
+        let first = run_headless_sim(&superflat(&script, 48)).unwrap();
+        let second = run_headless_sim(&superflat(&script, 48)).unwrap();
+
+        assert!(first.changed_blocks > 0);
+        assert_eq!(first.digest, second.digest);
+        assert_eq!(first.changes, second.changes);
+        assert_eq!(first.block_deltas, second.block_deltas);
+    }
+
+    #[test]
+    fn test_headless_sim_rejects_unknown_commands() {
+        let result = run_headless_sim(&superflat(&["teleport 0 0 0"], 1));
+        assert!(
+            matches!(result, Err(HeadlessSimError::UnknownCommand(name)) if name == "teleport")
+        );
+    }
+}
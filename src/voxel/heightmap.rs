@@ -0,0 +1,316 @@
+//! 列高度图与地下区块剔除
+//!
+//! 记录已加载区块中每一列（世界 x, z）最高的不透明方块。整个区块都位于周围 3×3 区块列
+//! 最低地表以下、并且内部没有透明方块（洞穴空气、水）的区块视为被掩埋：
+//!
+//! - 首次生成时不构建网格（相邻区块露出洞穴时再用真实边界补建）
+//! - 摄像机位于地表以上时不渲染
+//!
+//! 高度只来自已加载的区块，地表所在的区块还没加载时高度偏低，只会少剔除，不会误剔除。
+//! 每次刷新递增版本号，地图等使用者据此只重新读取变化过的区块列
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::voxel::chunk::{ChunkData, ChunkLookup, ChunkPos};
+use crate::voxel::constants::CHUNK_SIZE;
+
+/// 一个区块列包含的世界列数
+const COLUMN_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// 一个区块列（相同 x, z 的所有区块）的地表高度
+struct ColumnSurface {
+    /// 每一列最高不透明方块的世界 y（按 z * CHUNK_SIZE + x 排列），整列透明时为 None
+    heights: [Option<i32>; COLUMN_AREA],
+    /// 所有列中最低的地表，有任何一列没有不透明方块时为 None
+    lowest: Option<i32>,
+    /// 有不透明方块的列中最低和最高的地表
+    band: Option<(i32, i32)>,
+    /// 最后一次扫描时高度图的版本号
+    revision: u64,
+}
+
+impl ColumnSurface {
+    /// 从上到下扫描区块列中已加载的区块（layers 为区块 y，从高到低排列）
+    fn scan(chunks: &impl ChunkLookup, column: IVec2, layers: &[i32], revision: u64) -> Self {
+        let mut heights = [None; COLUMN_AREA];
+        let mut remaining = COLUMN_AREA;
+
+        for &layer in layers {
+            let Some(chunk) = chunks.chunk(&ChunkPos::new(column.x, layer, column.y)) else {
+                continue;
+            };
+            // 完全透明的区块（空气、水面以上）不会提供地表
+            if !chunk.voxels.may_contain(|kind| !kind.is_transparent()) {
+                continue;
+            }
+            for (i, height) in heights.iter_mut().enumerate() {
+                if height.is_some() {
+                    continue;
+                }
+                let (x, z) = (i as i32 % CHUNK_SIZE, i as i32 / CHUNK_SIZE);
+                if let Some(y) = (0..CHUNK_SIZE)
+                    .rev()
+                    .find(|&y| !chunk.get(x, y, z).is_transparent())
+                {
+                    *height = Some(layer * CHUNK_SIZE + y);
+                    remaining -= 1;
+                }
+            }
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        let low = heights.iter().flatten().min().copied();
+        let high = heights.iter().flatten().max().copied();
+        let band = low.zip(high);
+        let lowest = band.filter(|_| remaining == 0).map(|(low, _)| low);
+        Self {
+            heights,
+            lowest,
+            band,
+            revision,
+        }
+    }
+}
+
+/// 区块所在的区块列
+fn column_of(chunk_pos: ChunkPos) -> IVec2 {
+    IVec2::new(chunk_pos.x, chunk_pos.z)
+}
+
+/// 区块列及其周围 8 个区块列
+fn neighborhood(column: IVec2) -> impl Iterator<Item = IVec2> {
+    (-1..=1).flat_map(move |dz| (-1..=1).map(move |dx| column + IVec2::new(dx, dz)))
+}
+
+/// 列高度图，随区块加载、卸载和修改更新
+#[derive(Default)]
+pub struct Heightmap {
+    columns: HashMap<IVec2, ColumnSurface>,
+    /// 被掩埋的已加载区块
+    buried: HashSet<ChunkPos>,
+    /// 每次刷新递增的版本号
+    revision: u64,
+}
+
+impl Heightmap {
+    /// 世界列 (x, z) 最高不透明方块的 y，区块列未加载或整列透明时返回 None
+    pub fn height(&self, x: i32, z: i32) -> Option<i32> {
+        let column = self.columns.get(&IVec2::new(
+            x.div_euclid(CHUNK_SIZE),
+            z.div_euclid(CHUNK_SIZE),
+        ))?;
+        let idx = z.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE);
+        column.heights[idx as usize]
+    }
+
+    /// 区块列周围 3×3 区块列中最低的地表，其中任一区块列未加载时返回 None
+    pub fn surface_floor(&self, column: IVec2) -> Option<i32> {
+        neighborhood(column)
+            .map(|column| self.columns.get(&column).and_then(|surface| surface.lowest))
+            .try_fold(i32::MAX, |floor, lowest| Some(floor.min(lowest?)))
+    }
+
+    /// 区块列的地表带（最低和最高地表），用于加载排序
+    ///
+    /// 区块列还没有高度数据时使用周围区块列已知地表带的并集，都没有时返回 None
+    pub fn surface_band(&self, column: IVec2) -> Option<(i32, i32)> {
+        if let Some(band) = self.columns.get(&column).and_then(|surface| surface.band) {
+            return Some(band);
+        }
+        neighborhood(column)
+            .filter_map(|column| self.columns.get(&column)?.band)
+            .reduce(|(low, high), (other_low, other_high)| {
+                (low.min(other_low), high.max(other_high))
+            })
+    }
+
+    /// 区块是否整个位于周围地表以下（不考虑区块内容）
+    pub fn is_below_surface(&self, chunk_pos: ChunkPos) -> bool {
+        let top = chunk_pos.world_origin().y + CHUNK_SIZE - 1;
+        self.surface_floor(column_of(chunk_pos))
+            .is_some_and(|floor| top < floor)
+    }
+
+    /// 当前版本号，每次刷新后递增
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// 版本号 revision 之后重新扫描过的区块列（卸载的区块列不会出现）
+    pub fn columns_since(&self, revision: u64) -> impl Iterator<Item = IVec2> + '_ {
+        self.columns
+            .iter()
+            .filter(move |(_, surface)| surface.revision > revision)
+            .map(|(column, _)| *column)
+    }
+
+    /// 区块是否被掩埋：位于周围地表以下且没有透明方块
+    pub fn is_buried(&self, chunk_pos: ChunkPos) -> bool {
+        self.buried.contains(&chunk_pos)
+    }
+
+    /// 重新计算 changed 中区块所在区块列的高度，并更新受影响区块的掩埋状态
+    pub fn refresh(
+        &mut self,
+        chunks: &impl ChunkLookup,
+        changed: impl IntoIterator<Item = ChunkPos>,
+    ) {
+        let columns: HashSet<IVec2> = changed.into_iter().map(column_of).collect();
+        if columns.is_empty() {
+            return;
+        }
+        self.revision += 1;
+
+        let mut layers: HashMap<IVec2, Vec<i32>> = HashMap::new();
+        for chunk_pos in chunks.chunk_positions() {
+            let column = column_of(chunk_pos);
+            if columns.contains(&column) {
+                layers.entry(column).or_default().push(chunk_pos.y);
+            }
+        }
+        for column in &columns {
+            match layers.get_mut(column) {
+                Some(layers) => {
+                    layers.sort_unstable_by(|a, b| b.cmp(a));
+                    let surface = ColumnSurface::scan(chunks, *column, layers, self.revision);
+                    self.columns.insert(*column, surface);
+                }
+                None => {
+                    self.columns.remove(column);
+                }
+            }
+        }
+
+        // 地表变化影响周围 3×3 区块列中所有区块的掩埋判定
+        let affected: HashSet<IVec2> = columns.iter().flat_map(|c| neighborhood(*c)).collect();
+        self.buried
+            .retain(|chunk_pos| !affected.contains(&column_of(*chunk_pos)));
+        for chunk_pos in chunks.chunk_positions() {
+            if affected.contains(&column_of(chunk_pos))
+                && self.is_below_surface(chunk_pos)
+                && chunks
+                    .chunk(&chunk_pos)
+                    .is_some_and(ChunkData::is_fully_opaque)
+            {
+                self.buried.insert(chunk_pos);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::voxel_kind::VoxelKind;
+
+    /// 3×3 区块列，y = -1 层为实心石头，y = 0 层底部 surface 格为泥土、上面是空气
+    fn layered_world(surface: i32) -> HashMap<ChunkPos, ChunkData> {
+        let mut chunks = HashMap::new();
+        for cx in -1..=1 {
+            for cz in -1..=1 {
+                let mut stone = ChunkData::new();
+                let mut top = ChunkData::new();
+                for idx in 0..ChunkData::VOXEL_COUNT {
+                    stone.voxels.set(idx, VoxelKind::Stone);
+                }
+                for x in 0..CHUNK_SIZE {
+                    for z in 0..CHUNK_SIZE {
+                        for y in 0..surface {
+                            top.set(x, y, z, VoxelKind::Dirt);
+                        }
+                    }
+                }
+                chunks.insert(ChunkPos::new(cx, -1, cz), stone);
+                chunks.insert(ChunkPos::new(cx, 0, cz), top);
+            }
+        }
+        chunks
+    }
+
+    fn refreshed(chunks: &HashMap<ChunkPos, ChunkData>) -> Heightmap {
+        let mut heightmap = Heightmap::default();
+        heightmap.refresh(chunks, chunks.keys().copied());
+        heightmap
+    }
+
+    #[test]
+    fn test_heights_track_highest_opaque_block() {
+        let mut chunks = layered_world(4);
+        chunks
+            .get_mut(&ChunkPos::new(0, 0, 0))
+            .unwrap()
+            .set(3, 9, 5, VoxelKind::Stone);
+        let heightmap = refreshed(&chunks);
+        assert_eq!(heightmap.height(3, 5), Some(9));
+        assert_eq!(heightmap.height(4, 5), Some(3));
+        assert_eq!(heightmap.height(-1, -1), Some(3));
+        assert_eq!(heightmap.height(100, 0), None);
+        assert_eq!(heightmap.surface_floor(IVec2::ZERO), Some(3));
+        assert_eq!(heightmap.surface_band(IVec2::ZERO), Some((3, 9)));
+        // 没有数据的区块列借用相邻区块列的地表带
+        assert_eq!(heightmap.surface_band(IVec2::new(2, 0)), Some((3, 3)));
+        assert_eq!(heightmap.surface_band(IVec2::new(5, 5)), None);
+    }
+
+    #[test]
+    fn test_solid_chunk_under_surface_is_buried() {
+        let chunks = layered_world(4);
+        let heightmap = refreshed(&chunks);
+        assert!(heightmap.is_buried(ChunkPos::new(0, -1, 0)));
+        assert!(!heightmap.is_buried(ChunkPos::new(0, 0, 0)));
+        // 边缘区块列的邻居没有加载
+        assert!(!heightmap.is_buried(ChunkPos::new(1, -1, 0)));
+    }
+
+    #[test]
+    fn test_chunk_reaching_surface_or_with_caves_is_not_buried() {
+        // 地表正好在 y = -1 层顶部
+        let heightmap = refreshed(&layered_world(0));
+        assert!(!heightmap.is_buried(ChunkPos::new(0, -1, 0)));
+
+        // 地下区块中有洞穴
+        let mut chunks = layered_world(4);
+        chunks
+            .get_mut(&ChunkPos::new(0, -1, 0))
+            .unwrap()
+            .set(8, 8, 8, VoxelKind::Air);
+        let heightmap = refreshed(&chunks);
+        assert!(heightmap.is_below_surface(ChunkPos::new(0, -1, 0)));
+        assert!(!heightmap.is_buried(ChunkPos::new(0, -1, 0)));
+    }
+
+    #[test]
+    fn test_unloading_surface_updates_neighbors() {
+        let mut chunks = layered_world(4);
+        let mut heightmap = refreshed(&chunks);
+        assert!(heightmap.is_buried(ChunkPos::new(0, -1, 0)));
+
+        // 邻居区块列的地表被卸载后只剩下方的石头
+        let removed = ChunkPos::new(1, 0, 1);
+        chunks.remove(&removed);
+        heightmap.refresh(&chunks, [removed]);
+        assert_eq!(heightmap.height(16, 16), Some(-1));
+        assert!(!heightmap.is_buried(ChunkPos::new(0, -1, 0)));
+    }
+
+    #[test]
+    fn test_columns_since_reports_rescanned_columns() {
+        let mut chunks = layered_world(4);
+        let mut heightmap = refreshed(&chunks);
+        let revision = heightmap.revision();
+        assert_eq!(heightmap.columns_since(revision).count(), 0);
+
+        chunks
+            .get_mut(&ChunkPos::new(1, 0, 0))
+            .unwrap()
+            .set(0, 8, 0, VoxelKind::Stone);
+        heightmap.refresh(&chunks, [ChunkPos::new(1, 0, 0)]);
+        assert_eq!(
+            heightmap.columns_since(revision).collect::<Vec<_>>(),
+            vec![IVec2::new(1, 0)]
+        );
+    }
+}
@@ -0,0 +1,491 @@
+//! 体素光照传播
+//!
+//! 每个方块记录两种光照等级（0-15），打包在一个字节中：高 4 位为天空光，低 4 位为方块光。
+//!
+//! - 天空光：列高度图中地表以上的透明方块为满级，再向洞口、悬崖下等相邻透明方块逐格衰减
+//! - 方块光：熔岩等发光方块（`light_emission`）和燃烧中的方块作为光源，逐格衰减
+//!
+//! 光照按区块整体重新计算，相邻区块边界上的光照作为额外的光源。区块被修改、加载或
+//! 相邻区块的边界光照变化时重新计算（见 [`relight_chunks`]），边界光照变化会继续传给
+//! 相邻区块，直到不再变化。网格构建时把方块面外侧的光照烘焙进顶点颜色。
+
+use bevy::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+use crate::voxel::chunk::{ChunkData, ChunkPos, ChunksMut, VoxelWorld};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::domains::thermal::api::idx_to_xyz;
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::heightmap::Heightmap;
+use crate::voxel::palette::PalettedArray;
+use crate::voxel::systems::boundary_layer;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 最高光照等级
+pub const MAX_LIGHT: u8 = 15;
+
+/// 燃烧中方块的方块光等级
+pub const BURNING_LIGHT: u8 = 13;
+
+/// 还没有计算过光照的位置使用的值（满级天空光，没有方块光）
+///
+/// 网格构建时相邻区块未加载的一侧也按这个值处理
+pub const DEFAULT_LIGHT: u8 = MAX_LIGHT << 4;
+
+/// 每低一级光照亮度乘以的系数
+const LIGHT_FALLOFF: f32 = 0.8;
+
+/// 光照为 0 时保留的亮度，完全黑暗的洞穴仍能隐约看到轮廓
+const MIN_BRIGHTNESS: f32 = 0.06;
+
+/// 每帧最多重新计算光照的区块数，剩下的留到下一帧
+const MAX_RELIGHTS_PER_FRAME: usize = 64;
+
+/// 六个相邻方向（与 [`compute_light`] 的 neighbors 参数顺序一致）
+pub const NEIGHBOR_DIRS: [IVec3; 6] = [
+    IVec3::X,
+    IVec3::NEG_X,
+    IVec3::Y,
+    IVec3::NEG_Y,
+    IVec3::Z,
+    IVec3::NEG_Z,
+];
+
+/// 打包天空光和方块光
+pub fn pack_light(sky: u8, block: u8) -> u8 {
+    (sky.min(MAX_LIGHT) << 4) | block.min(MAX_LIGHT)
+}
+
+/// 天空光等级
+pub fn sky_light(light: u8) -> u8 {
+    light >> 4
+}
+
+/// 方块光等级
+pub fn block_light(light: u8) -> u8 {
+    light & 0x0F
+}
+
+/// 决定亮度的光照等级（天空光和方块光取较大值）
+pub fn light_level(light: u8) -> u8 {
+    sky_light(light).max(block_light(light))
+}
+
+/// 光照等级（可以是多个位置的平均值）对应的顶点颜色亮度
+pub fn light_brightness(level: f32) -> f32 {
+    let level = level.clamp(0.0, MAX_LIGHT as f32);
+    MIN_BRIGHTNESS + (1.0 - MIN_BRIGHTNESS) * LIGHT_FALLOFF.powf(MAX_LIGHT as f32 - level)
+}
+
+/// 方块发出的光照等级
+pub fn emission(kind: VoxelKind, flags: VoxelFlags) -> u8 {
+    let own = kind.def().props.light_emission.min(MAX_LIGHT);
+    if flags.contains(VoxelFlags::BURNING) {
+        own.max(BURNING_LIGHT)
+    } else {
+        own
+    }
+}
+
+/// 计算区块内每个方块的光照
+///
+/// heightmap 为 None 时（首次生成，还不知道周围的地形）天空光只看区块内部：
+/// 每一列最高的不透明方块以上都当作见天。neighbors 按 [`NEIGHBOR_DIRS`] 的顺序给出
+/// 已加载的相邻区块，它们边界上的光照减一级后照进本区块
+pub fn compute_light(
+    chunk_pos: ChunkPos,
+    chunk: &ChunkData,
+    heightmap: Option<&Heightmap>,
+    neighbors: &[Option<&ChunkData>; 6],
+) -> PalettedArray<u8> {
+    let origin = chunk_pos.world_origin();
+    let transparent: Vec<bool> = chunk
+        .voxels
+        .iter()
+        .map(|kind| kind.is_transparent())
+        .collect();
+    let mut sky = vec![0u8; ChunkData::VOXEL_COUNT];
+    let mut block = vec![0u8; ChunkData::VOXEL_COUNT];
+    let mut sky_queue = VecDeque::new();
+    let mut block_queue = VecDeque::new();
+
+    // 天空光：从区块顶部向下，直到遇到不透明方块或高度图记录的地表
+    for z in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            let floor =
+                heightmap.and_then(|heightmap| heightmap.height(origin.x + x, origin.z + z));
+            for y in (0..CHUNK_SIZE).rev() {
+                let idx = ChunkData::index(x, y, z);
+                if !transparent[idx] || floor.is_some_and(|floor| origin.y + y <= floor) {
+                    break;
+                }
+                sky[idx] = MAX_LIGHT;
+                sky_queue.push_back(idx);
+            }
+        }
+    }
+
+    // 方块光：发光方块和燃烧中的方块
+    let has_emitters = chunk
+        .voxels
+        .may_contain(|kind| kind.def().props.light_emission > 0)
+        || chunk
+            .flags
+            .may_contain(|flags| flags.contains(VoxelFlags::BURNING));
+    if has_emitters {
+        for (idx, light) in block.iter_mut().enumerate() {
+            let level = emission(chunk.voxels.get(idx), chunk.flags.get(idx));
+            if level > 0 {
+                *light = level;
+                block_queue.push_back(idx);
+            }
+        }
+    }
+
+    // 相邻区块边界上的光照
+    for (&dir, neighbor) in NEIGHBOR_DIRS.iter().zip(neighbors) {
+        let Some(neighbor) = neighbor else {
+            continue;
+        };
+        for (own, other) in boundary_layer(dir).zip(boundary_layer(-dir)) {
+            let idx = ChunkData::index(own.x, own.y, own.z);
+            if !transparent[idx] {
+                continue;
+            }
+            let light = neighbor
+                .light
+                .get(ChunkData::index(other.x, other.y, other.z));
+            seed(
+                &mut sky,
+                &mut sky_queue,
+                idx,
+                sky_light(light).saturating_sub(1),
+            );
+            seed(
+                &mut block,
+                &mut block_queue,
+                idx,
+                block_light(light).saturating_sub(1),
+            );
+        }
+    }
+
+    propagate(&mut sky, sky_queue, &transparent);
+    propagate(&mut block, block_queue, &transparent);
+
+    let packed: Vec<u8> = sky
+        .iter()
+        .zip(&block)
+        .map(|(&sky, &block)| pack_light(sky, block))
+        .collect();
+    PalettedArray::from_slice(&packed)
+}
+
+/// 光照比已有等级高时写入并加入传播队列
+fn seed(levels: &mut [u8], queue: &mut VecDeque<usize>, idx: usize, level: u8) {
+    if level > levels[idx] {
+        levels[idx] = level;
+        queue.push_back(idx);
+    }
+}
+
+/// 广度优先向相邻的透明方块传播光照，每格衰减一级
+fn propagate(levels: &mut [u8], mut queue: VecDeque<usize>, transparent: &[bool]) {
+    while let Some(idx) = queue.pop_front() {
+        let level = levels[idx];
+        if level <= 1 {
+            continue;
+        }
+        let (x, y, z) = idx_to_xyz(idx);
+        for dir in NEIGHBOR_DIRS {
+            let pos = IVec3::new(x, y, z) + dir;
+            if pos.min_element() < 0 || pos.max_element() >= CHUNK_SIZE {
+                continue;
+            }
+            let neighbor = ChunkData::index(pos.x, pos.y, pos.z);
+            if transparent[neighbor] {
+                seed(levels, &mut queue, neighbor, level - 1);
+            }
+        }
+    }
+}
+
+/// 区块在 dir 方向的边界层上光照是否有变化
+fn boundary_changed(old: &PalettedArray<u8>, new: &PalettedArray<u8>, dir: IVec3) -> bool {
+    boundary_layer(dir).any(|pos| {
+        let idx = ChunkData::index(pos.x, pos.y, pos.z);
+        old.get(idx) != new.get(idx)
+    })
+}
+
+/// 刚加载的区块在 dir 方向的边界层上是否有与 [`DEFAULT_LIGHT`] 不同的透明方块
+/// （相邻区块构建网格时按默认值取样，这些位置需要重建）
+fn boundary_differs_from_default(chunk: &ChunkData, light: &PalettedArray<u8>, dir: IVec3) -> bool {
+    boundary_layer(dir).any(|pos| {
+        chunk.get(pos.x, pos.y, pos.z).is_transparent()
+            && light.get(ChunkData::index(pos.x, pos.y, pos.z)) != DEFAULT_LIGHT
+    })
+}
+
+/// 区块在 dir 方向的边界层上是否有能照进相邻区块的光
+fn boundary_lit(light: &PalettedArray<u8>, dir: IVec3) -> bool {
+    boundary_layer(dir).any(|pos| light_level(light.get(ChunkData::index(pos.x, pos.y, pos.z))) > 1)
+}
+
+/// dir 方向的相邻区块坐标
+fn neighbor_pos(chunk_pos: ChunkPos, dir: IVec3) -> ChunkPos {
+    ChunkPos::new(
+        chunk_pos.x + dir.x,
+        chunk_pos.y + dir.y,
+        chunk_pos.z + dir.z,
+    )
+}
+
+/// 等待重新计算光照的区块
+#[derive(Resource, Default)]
+pub struct LightUpdates {
+    /// 相邻区块的边界光照变化、或超出上一帧配额的区块
+    pub pending: HashSet<ChunkPos>,
+    /// 刚加载或恢复的区块：相邻区块之前把这一侧当作默认光照构建网格，需要对比后决定是否重建
+    pub arrived: HashSet<ChunkPos>,
+}
+
+/// 重新计算被修改、刚加载和受相邻区块影响的区块的光照
+///
+/// 在派发网格重建之前运行：光照变化的区块标记为脏；边界光照变化时相邻区块也标记为脏
+/// （它们的网格取样了边界光照），并在同一帧内接着重新计算，直到边界不再变化或用完配额
+pub fn relight_chunks(
+    mut world: ResMut<VoxelWorld>,
+    mut chunks: ChunksMut,
+    mut updates: ResMut<LightUpdates>,
+) {
+    let updates = &mut *updates;
+    let dirty: Vec<ChunkPos> = chunks
+        .iter()
+        .filter(|(_, chunk)| chunk.is_dirty)
+        .map(|(pos, _)| pos)
+        .collect();
+    if dirty.is_empty() && updates.pending.is_empty() && updates.arrived.is_empty() {
+        return;
+    }
+
+    // 方块修改可能改变地表高度，天空光依赖最新的高度图
+    world.refresh_heightmap(&chunks, dirty.iter().copied());
+
+    let mut queue: VecDeque<ChunkPos> = VecDeque::new();
+    let mut queued = HashSet::new();
+    for chunk_pos in dirty
+        .into_iter()
+        .chain(updates.arrived.iter().copied())
+        .chain(updates.pending.drain())
+    {
+        if queued.insert(chunk_pos) {
+            queue.push_back(chunk_pos);
+        }
+    }
+
+    let mut relit = 0;
+    while let Some(chunk_pos) = queue.pop_front() {
+        queued.remove(&chunk_pos);
+        if relit >= MAX_RELIGHTS_PER_FRAME {
+            updates.pending.insert(chunk_pos);
+            continue;
+        }
+        let arrived = updates.arrived.remove(&chunk_pos);
+        let Some(chunk) = chunks.get(&chunk_pos) else {
+            continue;
+        };
+        let neighbors = NEIGHBOR_DIRS.map(|dir| chunks.get(&neighbor_pos(chunk_pos, dir)));
+        let light = compute_light(chunk_pos, chunk, Some(&world.heightmap), &neighbors);
+        relit += 1;
+
+        // 需要重建网格和需要重新计算光照的相邻区块
+        let mut remesh = Vec::new();
+        let mut relight = Vec::new();
+        for dir in NEIGHBOR_DIRS {
+            let (changed, lit) = if arrived {
+                (
+                    boundary_differs_from_default(chunk, &light, dir),
+                    boundary_lit(&light, dir),
+                )
+            } else {
+                let changed = boundary_changed(&chunk.light, &light, dir);
+                (changed, changed)
+            };
+            let neighbor = neighbor_pos(chunk_pos, dir);
+            if changed {
+                remesh.push(neighbor);
+            }
+            if changed || lit {
+                relight.push(neighbor);
+            }
+        }
+        let changed = !chunk.light.iter().eq(light.iter());
+
+        if changed && let Some(mut chunk) = chunks.get_mut(&chunk_pos) {
+            chunk.light = light;
+            chunk.is_dirty = true;
+        }
+        for neighbor in remesh {
+            if let Some(mut chunk) = chunks.get_mut(&neighbor) {
+                chunk.is_dirty = true;
+            }
+        }
+        for neighbor in relight {
+            if chunks.contains(&neighbor) && queued.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 从 y = 0 填到 top（含）的实心区块
+    fn filled_chunk(top: i32, kind: VoxelKind) -> ChunkData {
+        let mut chunk = ChunkData::new();
+        for y in 0..=top {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, kind);
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn test_pack_light_round_trip() {
+        let light = pack_light(12, 5);
+        assert_eq!(sky_light(light), 12);
+        assert_eq!(block_light(light), 5);
+        assert_eq!(light_level(light), 12);
+        assert_eq!(pack_light(20, 20), 0xFF);
+        assert_eq!(light_brightness(MAX_LIGHT as f32), 1.0);
+        assert!(light_brightness(0.0) < light_brightness(1.0));
+    }
+
+    #[test]
+    fn test_sky_light_reaches_under_overhang() {
+        // 地面在 y = 3，y = 8 有一块悬空的板子挡住 x < 8 的部分
+        let mut chunk = filled_chunk(3, VoxelKind::Stone);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..8 {
+                chunk.set(x, 8, z, VoxelKind::Stone);
+            }
+        }
+        let light = compute_light(ChunkPos::new(0, 0, 0), &chunk, None, &[None; 6]);
+        let at = |x, y, z| light.get(ChunkData::index(x, y, z));
+
+        assert_eq!(sky_light(at(10, 4, 5)), MAX_LIGHT);
+        assert_eq!(sky_light(at(10, 9, 5)), MAX_LIGHT);
+        // 板子下方离开口越远越暗
+        assert_eq!(sky_light(at(7, 4, 5)), MAX_LIGHT - 1);
+        assert_eq!(sky_light(at(2, 4, 5)), MAX_LIGHT - 6);
+        // 实心方块内部没有光
+        assert_eq!(at(2, 2, 5), 0);
+    }
+
+    #[test]
+    fn test_heightmap_darkens_buried_caves() {
+        // 下面的区块里有一条通到区块顶部的竖井，上面的区块整个是石头
+        let mut caves = filled_chunk(CHUNK_SIZE - 1, VoxelKind::Stone);
+        for y in 5..CHUNK_SIZE {
+            caves.set(5, y, 5, VoxelKind::Air);
+        }
+        let cave_pos = ChunkPos::new(0, 0, 0);
+        let roof_pos = ChunkPos::new(0, 1, 0);
+        let chunks = std::collections::HashMap::from([
+            (cave_pos, caves.clone()),
+            (roof_pos, filled_chunk(CHUNK_SIZE - 1, VoxelKind::Stone)),
+        ]);
+        let mut heightmap = Heightmap::default();
+        heightmap.refresh(&chunks, [cave_pos, roof_pos]);
+
+        // 只看区块内部时竖井见天，加上高度图后被上面的区块挡住
+        let light = compute_light(cave_pos, &caves, None, &[None; 6]);
+        assert_eq!(sky_light(light.get(ChunkData::index(5, 5, 5))), MAX_LIGHT);
+        let light = compute_light(cave_pos, &caves, Some(&heightmap), &[None; 6]);
+        assert_eq!(light.get(ChunkData::index(5, 5, 5)), 0);
+    }
+
+    #[test]
+    fn test_lava_and_fire_emit_block_light() {
+        let mut chunk = filled_chunk(CHUNK_SIZE - 1, VoxelKind::Stone);
+        for x in 1..10 {
+            chunk.set(x, 8, 8, VoxelKind::Air);
+        }
+        chunk.set(0, 8, 8, VoxelKind::Lava);
+        let light = compute_light(ChunkPos::new(0, 0, 0), &chunk, None, &[None; 6]);
+        let at = |x| block_light(light.get(ChunkData::index(x, 8, 8)));
+        assert_eq!(at(0), MAX_LIGHT);
+        assert_eq!(at(1), MAX_LIGHT - 1);
+        assert_eq!(at(9), MAX_LIGHT - 9);
+
+        chunk.set(0, 8, 8, VoxelKind::OakLog);
+        chunk
+            .flags
+            .set(ChunkData::index(0, 8, 8), VoxelFlags::BURNING);
+        let light = compute_light(ChunkPos::new(0, 0, 0), &chunk, None, &[None; 6]);
+        assert_eq!(
+            block_light(light.get(ChunkData::index(1, 8, 8))),
+            BURNING_LIGHT - 1
+        );
+    }
+
+    #[test]
+    fn test_light_crosses_chunk_boundary() {
+        let mut lit = ChunkData::new();
+        lit.light = PalettedArray::filled(ChunkData::VOXEL_COUNT, pack_light(0, 10));
+        // +X 方向的相邻区块边界上方块光为 10
+        let mut neighbors = [None; 6];
+        neighbors[0] = Some(&lit);
+
+        let mut tunnel = filled_chunk(CHUNK_SIZE - 1, VoxelKind::Stone);
+        for x in 10..CHUNK_SIZE {
+            tunnel.set(x, 4, 4, VoxelKind::Air);
+        }
+        // 整个区块都有屋顶，只靠相邻区块照亮
+        let light = compute_light(ChunkPos::new(0, 0, 0), &tunnel, None, &neighbors);
+        let at = |x| block_light(light.get(ChunkData::index(x, 4, 4)));
+        assert_eq!(at(CHUNK_SIZE - 1), 9);
+        assert_eq!(at(10), 9 - (CHUNK_SIZE - 1 - 10) as u8);
+        assert_eq!(sky_light(light.get(ChunkData::index(10, 4, 4))), 0);
+    }
+
+    #[test]
+    fn test_relight_marks_neighbor_when_boundary_changes() {
+        use crate::voxel::chunk::{spawn_chunk, ChunkLookup};
+        use bevy::ecs::system::RunSystemOnce;
+
+        // 两个相邻的实心区块，中间打通一条隧道，左边的区块放入熔岩
+        let left_pos = ChunkPos::new(0, 0, 0);
+        let right_pos = ChunkPos::new(1, 0, 0);
+        let mut left = filled_chunk(CHUNK_SIZE - 1, VoxelKind::Stone);
+        let mut right = left.clone();
+        for x in 0..CHUNK_SIZE {
+            left.set(x, 4, 4, VoxelKind::Air);
+            right.set(x, 4, 4, VoxelKind::Air);
+        }
+        left.set(10, 4, 4, VoxelKind::Lava);
+        right.is_dirty = false;
+
+        let mut ecs = World::new();
+        spawn_chunk(&mut ecs, left_pos, left);
+        spawn_chunk(&mut ecs, right_pos, right);
+        let mut world = VoxelWorld::default();
+        world.refresh_heightmap(&ecs, [left_pos, right_pos]);
+        ecs.insert_resource(world);
+        ecs.init_resource::<LightUpdates>();
+        ecs.run_system_once(relight_chunks).unwrap();
+
+        let right = ecs.chunk(&right_pos).unwrap();
+        assert!(right.is_dirty);
+        assert_eq!(
+            block_light(right.light.get(ChunkData::index(0, 4, 4))),
+            MAX_LIGHT - 6
+        );
+    }
+}
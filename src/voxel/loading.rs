@@ -1,98 +1,125 @@
 //! 异步加载系统的数据类型
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy::tasks::Task;
+use crossbeam_channel::{Receiver, Sender};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::voxel::chunk::{ChunkData, ChunkPos, VoxelWorld};
-use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::chunk::{ChunkData, ChunkLookup, ChunkPos};
+use crate::voxel::constants::{
+    CHUNK_SIZE, RENDER_DISTANCE, SURFACE_RENDER_BUDGET, VERTICAL_RENDER_DISTANCE,
+};
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::heightmap::Heightmap;
+use crate::voxel::mesh::ChunkMeshes;
+use crate::voxel::palette::PalettedArray;
+use crate::voxel::registry::VoxelRegistry;
+use crate::voxel::solid_mask::SolidMask;
 use crate::voxel::voxel_kind::VoxelKind;
 
 // ============================================================================
 // 相邻区块边界数据
 // ============================================================================
 
-/// 相邻区块边界数据 - 用于跨区块面剔除
+/// 相邻区块边界数据 - 用于跨区块面剔除（体素）和光照取样（`NeighborEdges<u8>`）
 #[derive(Clone, Default)]
-pub struct NeighborEdges {
+pub struct NeighborEdges<T = VoxelKind> {
     /// +X方向相邻区块的X=0面
-    pub pos_x: Option<Vec<VoxelKind>>,
+    pub pos_x: Option<Vec<T>>,
     /// -X方向相邻区块的X=CHUNK_SIZE-1面
-    pub neg_x: Option<Vec<VoxelKind>>,
+    pub neg_x: Option<Vec<T>>,
     /// +Y方向相邻区块的Y=0面
-    pub pos_y: Option<Vec<VoxelKind>>,
+    pub pos_y: Option<Vec<T>>,
     /// -Y方向相邻区块的Y=CHUNK_SIZE-1面
-    pub neg_y: Option<Vec<VoxelKind>>,
+    pub neg_y: Option<Vec<T>>,
     /// +Z方向相邻区块的Z=0面
-    pub pos_z: Option<Vec<VoxelKind>>,
+    pub pos_z: Option<Vec<T>>,
     /// -Z方向相邻区块的Z=CHUNK_SIZE-1面
-    pub neg_z: Option<Vec<VoxelKind>>,
+    pub neg_z: Option<Vec<T>>,
 }
 
 impl NeighborEdges {
-    /// 从相邻区块提取边界数据
-    pub fn from_world(world: &VoxelWorld, center: ChunkPos) -> Self {
+    /// 从相邻区块提取边界体素
+    pub fn from_world(world: &impl ChunkLookup, center: ChunkPos) -> Self {
+        Self::extract(world, center, |chunk, x, y, z| chunk.get(x, y, z))
+    }
+}
+
+impl NeighborEdges<u8> {
+    /// 从相邻区块提取边界光照
+    pub fn light_from_world(world: &impl ChunkLookup, center: ChunkPos) -> Self {
+        Self::extract(world, center, |chunk, x, y, z| {
+            chunk.light.get(ChunkData::index(x, y, z))
+        })
+    }
+}
+
+impl<T: Copy> NeighborEdges<T> {
+    /// 用 read 从相邻区块读取边界数据
+    fn extract(
+        world: &impl ChunkLookup,
+        center: ChunkPos,
+        read: impl Fn(&ChunkData, i32, i32, i32) -> T,
+    ) -> Self {
+        let neighbor =
+            |dx, dy, dz| world.chunk(&ChunkPos::new(center.x + dx, center.y + dy, center.z + dz));
         Self {
-            pos_x: world
-                .chunks
-                .get(&ChunkPos::new(center.x + 1, center.y, center.z))
-                .map(|c| Self::extract_x_face(c, 0)),
-            neg_x: world
-                .chunks
-                .get(&ChunkPos::new(center.x - 1, center.y, center.z))
-                .map(|c| Self::extract_x_face(c, CHUNK_SIZE - 1)),
-            pos_y: world
-                .chunks
-                .get(&ChunkPos::new(center.x, center.y + 1, center.z))
-                .map(|c| Self::extract_y_face(c, 0)),
-            neg_y: world
-                .chunks
-                .get(&ChunkPos::new(center.x, center.y - 1, center.z))
-                .map(|c| Self::extract_y_face(c, CHUNK_SIZE - 1)),
-            pos_z: world
-                .chunks
-                .get(&ChunkPos::new(center.x, center.y, center.z + 1))
-                .map(|c| Self::extract_z_face(c, 0)),
-            neg_z: world
-                .chunks
-                .get(&ChunkPos::new(center.x, center.y, center.z - 1))
-                .map(|c| Self::extract_z_face(c, CHUNK_SIZE - 1)),
-        }
-    }
-
-    fn extract_x_face(chunk: &ChunkData, x: i32) -> Vec<VoxelKind> {
+            pos_x: neighbor(1, 0, 0).map(|c| Self::extract_x_face(c, 0, &read)),
+            neg_x: neighbor(-1, 0, 0).map(|c| Self::extract_x_face(c, CHUNK_SIZE - 1, &read)),
+            pos_y: neighbor(0, 1, 0).map(|c| Self::extract_y_face(c, 0, &read)),
+            neg_y: neighbor(0, -1, 0).map(|c| Self::extract_y_face(c, CHUNK_SIZE - 1, &read)),
+            pos_z: neighbor(0, 0, 1).map(|c| Self::extract_z_face(c, 0, &read)),
+            neg_z: neighbor(0, 0, -1).map(|c| Self::extract_z_face(c, CHUNK_SIZE - 1, &read)),
+        }
+    }
+
+    fn extract_x_face(
+        chunk: &ChunkData,
+        x: i32,
+        read: &impl Fn(&ChunkData, i32, i32, i32) -> T,
+    ) -> Vec<T> {
         let mut face = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE) as usize);
         for y in 0..CHUNK_SIZE {
             for z in 0..CHUNK_SIZE {
-                face.push(chunk.get(x, y, z));
+                face.push(read(chunk, x, y, z));
             }
         }
         face
     }
 
-    fn extract_y_face(chunk: &ChunkData, y: i32) -> Vec<VoxelKind> {
+    fn extract_y_face(
+        chunk: &ChunkData,
+        y: i32,
+        read: &impl Fn(&ChunkData, i32, i32, i32) -> T,
+    ) -> Vec<T> {
         let mut face = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE) as usize);
         for z in 0..CHUNK_SIZE {
             for x in 0..CHUNK_SIZE {
-                face.push(chunk.get(x, y, z));
+                face.push(read(chunk, x, y, z));
             }
         }
         face
     }
 
-    fn extract_z_face(chunk: &ChunkData, z: i32) -> Vec<VoxelKind> {
+    fn extract_z_face(
+        chunk: &ChunkData,
+        z: i32,
+        read: &impl Fn(&ChunkData, i32, i32, i32) -> T,
+    ) -> Vec<T> {
         let mut face = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE) as usize);
         for y in 0..CHUNK_SIZE {
             for x in 0..CHUNK_SIZE {
-                face.push(chunk.get(x, y, z));
+                face.push(read(chunk, x, y, z));
             }
         }
         face
     }
 
-    /// 获取指定位置的相邻体素
-    pub fn get_neighbor(&self, local_pos: IVec3, dir: IVec3) -> Option<VoxelKind> {
+    /// 获取指定位置越过区块边界的相邻数据
+    pub fn get_neighbor(&self, local_pos: IVec3, dir: IVec3) -> Option<T> {
         match (dir.x, dir.y, dir.z) {
             (1, 0, 0) if local_pos.x == CHUNK_SIZE - 1 => self
                 .pos_x
@@ -134,8 +161,18 @@ pub struct MeshBuildInput {
     pub chunk_pos: ChunkPos,
     /// 体素数据的副本
     pub voxels: Arc<Vec<VoxelKind>>,
+    /// 体素的不透明位掩码
+    pub solid: SolidMask,
+    /// 变体数据的副本（水位等影响外形的状态）
+    pub variants: Arc<Vec<u8>>,
+    /// 状态标志位的副本（燃烧中的方块带发光色）
+    pub flags: Arc<Vec<VoxelFlags>>,
+    /// 光照的副本（烘焙进顶点颜色）
+    pub light: Arc<Vec<u8>>,
     /// 相邻区块的边界体素数据
     pub neighbor_edges: NeighborEdges,
+    /// 相邻区块的边界光照
+    pub neighbor_light: NeighborEdges<u8>,
 }
 
 impl MeshBuildInput {
@@ -155,6 +192,7 @@ impl MeshBuildInput {
         }
 
         // 检查相邻chunk的边界面是否完全不透明
+        let registry = VoxelRegistry::current();
         let all_opaque = [
             &self.neighbor_edges.pos_x,
             &self.neighbor_edges.neg_x,
@@ -166,7 +204,7 @@ impl MeshBuildInput {
         .iter()
         .all(|edge| {
             edge.as_ref()
-                .map(|face| face.iter().all(|&kind| !kind.is_transparent()))
+                .map(|face| face.iter().all(|&kind| registry.occludes(kind)))
                 .unwrap_or(false)
         });
 
@@ -174,34 +212,445 @@ impl MeshBuildInput {
     }
 }
 
-/// 正在进行的网格生成任务
-#[derive(Component)]
-pub struct ComputeMeshTask {
-    /// 异步任务句柄（包含区块生成和网格构建）
-    pub task: Task<(Vec<VoxelKind>, Mesh)>,
+/// 区块生成任务的取消令牌
+///
+/// 丢弃 Task 只能阻止还没开始运行的任务；已经在工作线程上运行的生成任务会一直跑完。
+/// 卸载区块时调用 [`cancel`](Self::cancel)，任务在生成和网格构建的阶段之间以及
+/// 每处理完一层体素时检查令牌，提前退出
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// 通知任务尽快退出
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// 任务是否已被取消
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 不在视野内的区块的重建优先级惩罚（与区块距离的平方同单位）
+const OFFSCREEN_REMESH_PENALTY: i32 = 64;
+/// 帧时间平滑系数，每帧向新帧时间靠近的比例
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
+
+/// 网格重建的调度
+///
+/// 编辑和相邻区块加载会把区块标记为需要重建。被标记的区块不一次全部派发，而是留在积压中按
+/// 优先级分帧派发：
+/// - 离摄像机近的区块先重建，视野外的区块推后（见 [`RemeshScheduler::priority`]）
+/// - 每帧最多派发 `per_frame` 个任务，同时进行的重建任务不超过 `max_in_flight` 个
+/// - 平滑后的帧时间低于 `idle_frame_time` 时进入空闲模式，每帧改为最多派发
+///   `idle_per_frame` 个，趁 CPU 空闲清掉积压
+#[derive(Resource, Debug)]
+pub struct RemeshScheduler {
+    /// 每帧最多派发的重建任务数
+    pub per_frame: usize,
+    /// 空闲模式下每帧最多派发的重建任务数
+    pub idle_per_frame: usize,
+    /// 同时进行的重建任务上限
+    pub max_in_flight: usize,
+    /// 帧时间低于这个值时进入空闲模式
+    pub idle_frame_time: Duration,
+    /// 上一帧派发后还在等待重建的区块数
+    pub backlog: usize,
+    /// 上一帧是否处于空闲模式
+    pub idle: bool,
+    /// 平滑后的帧时间（秒），还没有记录时为 None
+    frame_time: Option<f32>,
+}
+
+impl Default for RemeshScheduler {
+    fn default() -> Self {
+        Self {
+            per_frame: 4,
+            idle_per_frame: 16,
+            max_in_flight: 16,
+            idle_frame_time: Duration::from_millis(11),
+            backlog: 0,
+            idle: false,
+            frame_time: None,
+        }
+    }
+}
+
+impl RemeshScheduler {
+    /// 记录上一帧的帧时间，返回本帧可以派发的任务数（已有 in_flight 个任务在进行）
+    pub fn budget(&mut self, frame_secs: f32, in_flight: usize) -> usize {
+        let frame_time = match self.frame_time {
+            Some(smoothed) => smoothed + (frame_secs - smoothed) * FRAME_TIME_SMOOTHING,
+            None => frame_secs,
+        };
+        self.frame_time = Some(frame_time);
+        self.idle = frame_time < self.idle_frame_time.as_secs_f32();
+
+        let per_frame = if self.idle {
+            self.idle_per_frame
+        } else {
+            self.per_frame
+        };
+        per_frame.min(self.max_in_flight.saturating_sub(in_flight))
+    }
+
+    /// 区块的重建优先级，数值越小越先重建
+    pub fn priority(chunk_pos: ChunkPos, camera_chunk: ChunkPos, visible: bool) -> i32 {
+        let distance = chunk_distance_squared(chunk_pos, camera_chunk);
+        if visible {
+            distance
+        } else {
+            distance + OFFSCREEN_REMESH_PENALTY
+        }
+    }
+}
+
+/// 区块生成任务的产物：区块数据（光照只按区块内部计算）和网格
+pub type GeneratedChunkData = (Box<ChunkData>, ChunkMeshes);
+
+/// 工作线程发回的任务产物
+pub enum TaskOutput {
+    /// 区块生成或从存档读取
+    Generated(GeneratedChunkData),
+    /// 已加载区块的网格重建
+    Remeshed(ChunkMeshes),
+    /// 按新参数重新生成的方块（仅地形生成）
+    Regenerated(PalettedArray<VoxelKind>),
+    /// 任务被取消、提前退出或 panic，没有产物
+    Abandoned,
+}
+
+/// 工作线程发回的任务结果
+pub struct TaskResult {
     /// 区块位置
     pub chunk_pos: ChunkPos,
+    /// 派发时分配的任务编号，用来认出已取消任务晚到的结果
+    pub task_id: u64,
+    /// 任务产物
+    pub output: TaskOutput,
+}
+
+/// 工作线程发回结果用的句柄
+///
+/// 每个任务恰好发回一个结果：任务没有调用 [`send`](Self::send) 就结束时（包括 panic 展开时），
+/// 句柄在析构时发回 [`TaskOutput::Abandoned`]，主线程照样移除任务记录
+pub struct TaskReporter {
+    sender: Option<Sender<TaskResult>>,
+    chunk_pos: ChunkPos,
+    task_id: u64,
+}
+
+impl TaskReporter {
+    /// 发回任务产物
+    pub fn send(mut self, output: TaskOutput) {
+        self.report(output);
+    }
+
+    fn report(&mut self, output: TaskOutput) {
+        if let Some(sender) = self.sender.take() {
+            // 接收端随资源一起存在，只有退出时才会断开
+            let _ = sender.send(TaskResult {
+                chunk_pos: self.chunk_pos,
+                task_id: self.task_id,
+                output,
+            });
+        }
+    }
+}
+
+impl Drop for TaskReporter {
+    fn drop(&mut self) {
+        self.report(TaskOutput::Abandoned);
+    }
+}
+
+/// 正在进行的区块生成任务
+pub struct PendingChunk {
+    /// 任务编号
+    pub task_id: u64,
+    /// 取消令牌，区块卸载时通知任务提前退出
+    pub cancel: CancelToken,
     /// 占位符实体ID（生成完成后需要替换）
     pub placeholder_entity: Entity,
 }
 
+/// 正在进行的重新生成任务
+pub struct PendingRegen {
+    /// 任务编号
+    pub task_id: u64,
+    /// 取消令牌，新一轮重新生成开始时通知任务提前退出
+    pub cancel: CancelToken,
+}
+
+/// 区块的异步任务：生成（包含从存档读取或生成区块、光照和网格构建）、网格重建和重新生成
+///
+/// 任务不对应跟踪实体：派发时按种类记录在各自的表中并分离运行，
+/// 完成后工作线程通过 [`TaskReporter`] 把结果发进同一个通道，主线程取出后按任务编号
+/// 分给各自的系统，不用逐个轮询任务。任务记录保留到对应的系统取走结果为止，
+/// 其他系统先从通道收到的结果不会让任务提前看起来已经结束。
+/// 取消的任务从表中移除，它晚到的结果按任务编号识别后丢弃，即使同一区块已经重新派发了新任务
+#[derive(Resource)]
+pub struct ChunkTasks {
+    sender: Sender<TaskResult>,
+    receiver: Receiver<TaskResult>,
+    /// 进行中的生成任务，按区块位置索引
+    pub pending: HashMap<ChunkPos, PendingChunk>,
+    /// 进行中的网格重建任务的编号
+    remeshing: HashMap<ChunkPos, u64>,
+    /// 进行中的重新生成任务
+    regenerating: HashMap<ChunkPos, PendingRegen>,
+    /// 已从通道收到、还没有被对应系统取走的结果
+    received: Vec<TaskResult>,
+    next_task_id: u64,
+}
+
+impl Default for ChunkTasks {
+    fn default() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        Self {
+            sender,
+            receiver,
+            pending: HashMap::new(),
+            remeshing: HashMap::new(),
+            regenerating: HashMap::new(),
+            received: Vec::new(),
+            next_task_id: 0,
+        }
+    }
+}
+
+impl ChunkTasks {
+    /// 为新任务分配编号和发回结果用的句柄
+    fn reporter(&mut self, chunk_pos: ChunkPos) -> TaskReporter {
+        let task_id = self.next_task_id;
+        self.next_task_id += 1;
+        TaskReporter {
+            sender: Some(self.sender.clone()),
+            chunk_pos,
+            task_id,
+        }
+    }
+
+    /// 记录新派发的生成任务，返回工作线程发回结果用的句柄
+    pub fn start(
+        &mut self,
+        chunk_pos: ChunkPos,
+        cancel: CancelToken,
+        placeholder_entity: Entity,
+    ) -> TaskReporter {
+        let reporter = self.reporter(chunk_pos);
+        self.pending.insert(
+            chunk_pos,
+            PendingChunk {
+                task_id: reporter.task_id,
+                cancel,
+                placeholder_entity,
+            },
+        );
+        reporter
+    }
+
+    /// 记录新派发的网格重建任务
+    pub fn start_remesh(&mut self, chunk_pos: ChunkPos) -> TaskReporter {
+        let reporter = self.reporter(chunk_pos);
+        self.remeshing.insert(chunk_pos, reporter.task_id);
+        reporter
+    }
+
+    /// 记录新派发的重新生成任务
+    pub fn start_regen(&mut self, chunk_pos: ChunkPos, cancel: CancelToken) -> TaskReporter {
+        let reporter = self.reporter(chunk_pos);
+        self.regenerating.insert(
+            chunk_pos,
+            PendingRegen {
+                task_id: reporter.task_id,
+                cancel,
+            },
+        );
+        reporter
+    }
+
+    /// 进行中的生成任务数
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// 区块是否有生成任务在进行
+    pub fn contains(&self, chunk_pos: &ChunkPos) -> bool {
+        self.pending.contains_key(chunk_pos)
+    }
+
+    /// 进行中的网格重建任务数
+    pub fn remesh_count(&self) -> usize {
+        self.remeshing.len()
+    }
+
+    /// 正在重建网格的区块
+    pub fn remeshing(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.remeshing.keys().copied()
+    }
+
+    /// 区块是否有网格重建任务在进行
+    pub fn is_remeshing(&self, chunk_pos: &ChunkPos) -> bool {
+        self.remeshing.contains_key(chunk_pos)
+    }
+
+    /// 进行中的重新生成任务数
+    pub fn regen_count(&self) -> usize {
+        self.regenerating.len()
+    }
+
+    /// 取消区块的生成任务，返回被取消的任务
+    pub fn cancel(&mut self, chunk_pos: &ChunkPos) -> Option<PendingChunk> {
+        let pending = self.pending.remove(chunk_pos)?;
+        // 通知工作线程提前退出：任务已分离，无法从外部打断
+        pending.cancel.cancel();
+        Some(pending)
+    }
+
+    /// 丢弃区块的网格重建结果（重建任务很短，不提前打断）
+    pub fn cancel_remesh(&mut self, chunk_pos: &ChunkPos) {
+        self.remeshing.remove(chunk_pos);
+    }
+
+    /// 取消所有重新生成任务，已收到的结果一起丢弃
+    pub fn cancel_regens(&mut self) {
+        for (_, pending) in self.regenerating.drain() {
+            pending.cancel.cancel();
+        }
+    }
+
+    /// 结果是否属于仍在记录中的任务
+    ///
+    /// 任务编号在各类任务间不重复，每个结果最多对应一张表中的记录
+    fn is_live(&self, result: &TaskResult) -> bool {
+        has_task(&self.pending, result, |p| p.task_id)
+            || has_task(&self.remeshing, result, |&id| id)
+            || has_task(&self.regenerating, result, |p| p.task_id)
+    }
+
+    /// 取出通道中的结果，返回 `take` 从任务表中取走了记录的结果和对应的记录
+    ///
+    /// 属于其他任务的结果留给它们的系统，已取消任务的结果丢弃
+    fn take_results<T>(
+        &mut self,
+        mut take: impl FnMut(&mut Self, &TaskResult) -> Option<T>,
+    ) -> Vec<(TaskResult, T)> {
+        let mut received = std::mem::take(&mut self.received);
+        received.extend(self.receiver.try_iter());
+
+        let mut taken = Vec::new();
+        for result in received {
+            if let Some(task) = take(self, &result) {
+                taken.push((result, task));
+            } else if self.is_live(&result) {
+                self.received.push(result);
+            }
+        }
+        taken
+    }
+
+    /// 取出已完成生成任务的区块
+    ///
+    /// 没有产物的任务只移除记录，占位符由孤儿清理移除，区块留给加载扫描重新派发
+    pub fn drain_completed(&mut self) -> Vec<CompletedChunk> {
+        self.take_results(|tasks, result| take_task(&mut tasks.pending, result, |p| p.task_id))
+            .into_iter()
+            .filter_map(|(result, pending)| match result.output {
+                TaskOutput::Generated((chunk, meshes)) => Some(CompletedChunk {
+                    chunk_pos: result.chunk_pos,
+                    chunk: *chunk,
+                    meshes,
+                    placeholder_entity: pending.placeholder_entity,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 取出已完成网格重建的区块
+    ///
+    /// 重建失败的区块保留旧网格，下次修改时再重建
+    pub fn drain_remeshed(&mut self) -> Vec<(ChunkPos, ChunkMeshes)> {
+        self.take_results(|tasks, result| take_task(&mut tasks.remeshing, result, |&id| id))
+            .into_iter()
+            .filter_map(|(result, _)| match result.output {
+                TaskOutput::Remeshed(meshes) => Some((result.chunk_pos, meshes)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 取出已结束的重新生成任务，没有产物的为 None
+    pub fn drain_regenerated(&mut self) -> Vec<(ChunkPos, Option<PalettedArray<VoxelKind>>)> {
+        self.take_results(|tasks, result| take_task(&mut tasks.regenerating, result, |p| p.task_id))
+            .into_iter()
+            .map(|(result, _)| match result.output {
+                TaskOutput::Regenerated(voxels) => (result.chunk_pos, Some(voxels)),
+                _ => (result.chunk_pos, None),
+            })
+            .collect()
+    }
+}
+
+/// 表中区块的任务是否就是发回结果的任务
+fn has_task<T>(
+    tasks: &HashMap<ChunkPos, T>,
+    result: &TaskResult,
+    id_of: impl Fn(&T) -> u64,
+) -> bool {
+    tasks.get(&result.chunk_pos).map(id_of) == Some(result.task_id)
+}
+
+/// 任务编号匹配时从表中取出任务记录
+fn take_task<T>(
+    tasks: &mut HashMap<ChunkPos, T>,
+    result: &TaskResult,
+    id_of: impl Fn(&T) -> u64,
+) -> Option<T> {
+    if !has_task(tasks, result, id_of) {
+        return None;
+    }
+    tasks.remove(&result.chunk_pos)
+}
+
 // ============================================================================
 // 加载队列和缓冲区
 // ============================================================================
 
+/// 卸载半径比加载半径多出的区块数
+///
+/// 在加载边界附近来回走动时，刚出界的区块留在这条滞回带内，不会反复卸载又重新生成
+pub const UNLOAD_HYSTERESIS: i32 = 2;
+
 /// 区块加载队列 - 管理需要加载和卸载的区块
 #[derive(Resource)]
 pub struct ChunkLoadQueue {
-    /// 待加载的区块列表
+    /// 待加载的区块列表，按加载优先级（见 [`LoadPriority`]）从高到低排列
     pub to_load: Vec<ChunkPos>,
     /// 待卸载的区块列表
     pub to_unload: Vec<ChunkPos>,
-    /// 当前活跃的异步任务数量
-    pub active_tasks: usize,
+    /// 累计取消的生成任务数（区块在生成完成前离开范围）
+    pub cancelled_tasks: usize,
     /// 最大并发任务数
     pub max_concurrent_tasks: usize,
     /// 待批量创建占位符的区块（新加入队列的区块）
     pub pending_placeholders: Vec<ChunkPos>,
+    /// 每帧扫描加载范围可用的时间，用完后下一帧从断点继续
+    pub scan_budget: Duration,
+    /// 每帧最多卸载的区块数
+    pub max_unloads_per_frame: usize,
+    /// 增量扫描的进度
+    pub scan: LoadScan,
+    /// 摄像机移动的估计，快速移动时沿移动方向预加载
+    pub motion: LoadMotion,
+    /// 暂停加载新区块（例如新建世界界面打开时，种子和生成参数还没确定）
+    pub paused: bool,
 }
 
 impl Default for ChunkLoadQueue {
@@ -209,13 +658,284 @@ impl Default for ChunkLoadQueue {
         Self {
             to_load: Vec::new(),
             to_unload: Vec::new(),
-            active_tasks: 0,
+            cancelled_tasks: 0,
             max_concurrent_tasks: 16, // 优化: 从64降低到16，减少线程竞争和CPU压力
             pending_placeholders: Vec::new(),
+            scan_budget: Duration::from_micros(1500),
+            max_unloads_per_frame: 32,
+            scan: LoadScan::default(),
+            motion: LoadMotion::default(),
+            paused: false,
+        }
+    }
+}
+
+/// 加载范围的增量扫描状态
+///
+/// 加载范围内的偏移按距离预先排好序，每帧从 cursor 处接着检查，直到时间预算用完；
+/// 扫完一遍后从头开始，转动视角后进入视锥的区块会在下一遍被发现。
+/// 中心区块或渲染距离变化时才重新排序队列、检查卸载
+#[derive(Default)]
+pub struct LoadScan {
+    /// 上次扫描时加载范围的中心区块（快速移动时在摄像机所在区块的前方，见 [`LoadMotion`]）
+    pub center: Option<ChunkPos>,
+    /// 上次扫描时的渲染距离
+    pub distance: Option<RenderDistance>,
+    /// 生成 offsets 时的加载范围，中心区块变化时按摄像机高度重新计算
+    pub window: Option<LoadWindow>,
+    /// 加载范围内相对中心区块的偏移，由近到远
+    pub offsets: Vec<IVec3>,
+    /// 下一个要检查的偏移
+    pub cursor: usize,
+    /// 上次按优先级排序队列时的视线方向
+    pub sorted_forward: Option<Vec3>,
+    /// 上次按优先级排序队列时的移动偏好（见 [`LoadMotion::bias`]）
+    pub sorted_motion: Vec3,
+}
+
+impl LoadScan {
+    /// 加载范围内的所有偏移，按到中心的距离从近到远排序
+    pub fn load_offsets(window: LoadWindow) -> Vec<IVec3> {
+        let h = window.horizontal;
+        let mut offsets = Vec::new();
+        for dx in -h..=h {
+            for dy in -window.below..=window.above {
+                for dz in -h..=h {
+                    offsets.push(IVec3::new(dx, dy, dz));
+                }
+            }
+        }
+        offsets.sort_by_key(|offset| offset.length_squared());
+        offsets
+    }
+}
+
+/// 按速度预测摄像机这么多秒后的位置，加载范围的中心随之前移
+const LOOKAHEAD_SECS: f32 = 2.0;
+/// 速度估计的平滑时间（秒），抖动和短暂的停顿不会让加载范围来回移动
+const VELOCITY_SMOOTHING_SECS: f32 = 0.5;
+/// 一帧内移动超过这个距离（方块）视为传送，不计入速度
+const TELEPORT_DISTANCE: f32 = 64.0;
+/// 达到这个速度（方块/秒）时移动方向在加载排序中的权重最大
+const FULL_MOTION_SPEED: f32 = 20.0;
+/// 移动偏好变化超过这个值后重新排序队列
+pub const RESORT_MOTION_DELTA: f32 = 0.25;
+
+/// 摄像机移动的估计
+///
+/// 冲刺或飞行时摄像机很快跑出已生成的范围。按平滑后的速度：
+/// - 加载范围的中心沿水平移动方向前移（见 [`LoadMotion::lead`]），前方多加载、身后相应缩小，
+///   卸载范围跟着一起移动
+/// - 移动方向上的区块在加载排序中提前（见 [`LoadMotion::bias`]）
+///
+/// 步行和冲刺的速度不足以移动加载范围，只影响排序
+#[derive(Debug, Default)]
+pub struct LoadMotion {
+    /// 平滑后的摄像机速度（方块/秒）
+    pub velocity: Vec3,
+    /// 上一次观察到的摄像机位置和时间
+    last: Option<(Vec3, Instant)>,
+}
+
+impl LoadMotion {
+    /// 记录摄像机在 now 时的位置，更新速度估计
+    pub fn observe(&mut self, position: Vec3, now: Instant) {
+        let Some((last_position, last_time)) = self.last.replace((position, now)) else {
+            return;
+        };
+        let dt = now.saturating_duration_since(last_time).as_secs_f32();
+        if dt <= 0.0 {
+            return;
+        }
+        let step = position - last_position;
+        if step.length() > TELEPORT_DISTANCE {
+            self.velocity = Vec3::ZERO;
+            return;
+        }
+        let blend = (dt / VELOCITY_SMOOTHING_SECS).min(1.0);
+        self.velocity = self.velocity.lerp(step / dt, blend);
+    }
+
+    /// 加载范围中心相对摄像机所在区块的水平偏移（区块数）
+    ///
+    /// 取 LOOKAHEAD_SECS 秒后到达的位置，每个轴最多前移水平渲染距离的一半
+    pub fn lead(&self, horizontal: i32) -> IVec3 {
+        let max_lead = horizontal / 2;
+        let ahead = |speed: f32| {
+            ((speed * LOOKAHEAD_SECS / CHUNK_SIZE as f32) as i32).clamp(-max_lead, max_lead)
+        };
+        IVec3::new(ahead(self.velocity.x), 0, ahead(self.velocity.z))
+    }
+
+    /// 加载排序的移动偏好：移动方向按速度缩放，达到 FULL_MOTION_SPEED 时长度为 1
+    pub fn bias(&self) -> Vec3 {
+        (self.velocity / FULL_MOTION_SPEED).clamp_length_max(1.0)
+    }
+}
+
+/// 两个区块间距离的平方，用于加载排序
+pub fn chunk_distance_squared(a: ChunkPos, b: ChunkPos) -> i32 {
+    (a.x - b.x).pow(2) + (a.y - b.y).pow(2) + (a.z - b.z).pow(2)
+}
+
+/// 包含地表带的区块的优先级加成（与区块距离的平方同单位）
+const SURFACE_BONUS: f32 = 8.0;
+/// 地表带以下每深一层区块的优先级惩罚
+const DEPTH_PENALTY: f32 = 12.0;
+/// 正对视线方向的区块的优先级加成，正后方的区块受到同等惩罚
+const VIEW_WEIGHT: f32 = 6.0;
+/// 全速移动时正对移动方向的区块的优先级加成，正后方的区块受到同等惩罚
+const MOTION_WEIGHT: f32 = 10.0;
+/// 视线方向偏离上次排序时超过这个角度（余弦值，约 30°）后重新排序队列
+pub const RESORT_VIEW_COS: f32 = 0.866;
+
+/// 区块加载优先级，数值越小越先加载
+///
+/// 以到中心区块距离的平方为基础：
+/// - 与地表带（见 [`Heightmap::surface_band`]）相交的区块提前，玩家能看到的地形先出现
+/// - 地表带以下的区块按深度推后，不把加载预算花在看不见的石头上
+/// - 视线方向上的区块提前，身后的推后
+/// - 快速移动时移动方向上的区块提前，身后的推后
+///
+/// 还没有高度数据的区块列只按距离、视线方向和移动方向排序
+pub struct LoadPriority<'a> {
+    /// 摄像机所在的区块
+    pub center: ChunkPos,
+    /// 摄像机视线方向（单位向量）
+    pub forward: Vec3,
+    /// 移动偏好（见 [`LoadMotion::bias`]）
+    pub motion: Vec3,
+    pub heightmap: &'a Heightmap,
+}
+
+impl LoadPriority<'_> {
+    /// 区块的优先级
+    pub fn of(&self, chunk_pos: ChunkPos) -> f32 {
+        let offset = IVec3::new(
+            chunk_pos.x - self.center.x,
+            chunk_pos.y - self.center.y,
+            chunk_pos.z - self.center.z,
+        );
+        let mut priority = chunk_distance_squared(chunk_pos, self.center) as f32;
+        if offset != IVec3::ZERO {
+            let direction = offset.as_vec3().normalize();
+            priority -= direction.dot(self.forward) * VIEW_WEIGHT
+                + direction.dot(self.motion) * MOTION_WEIGHT;
+        }
+
+        let bottom = chunk_pos.y * CHUNK_SIZE;
+        let top = bottom + CHUNK_SIZE - 1;
+        if let Some((low, high)) = self
+            .heightmap
+            .surface_band(IVec2::new(chunk_pos.x, chunk_pos.z))
+        {
+            if top < low {
+                let depth = (low - top - 1).div_euclid(CHUNK_SIZE) + 1;
+                priority += depth as f32 * DEPTH_PENALTY;
+            } else if bottom <= high {
+                priority -= SURFACE_BONUS;
+            }
+        }
+        priority
+    }
+
+    /// 按优先级排序整个队列
+    pub fn sort(&self, chunks: &mut [ChunkPos]) {
+        let mut keyed: Vec<(f32, ChunkPos)> =
+            chunks.iter().map(|&pos| (self.of(pos), pos)).collect();
+        keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (slot, (_, pos)) in chunks.iter_mut().zip(keyed) {
+            *slot = pos;
+        }
+    }
+
+    /// 把区块插入已排序的队列
+    pub fn insert(&self, chunks: &mut Vec<ChunkPos>, chunk_pos: ChunkPos) {
+        let priority = self.of(chunk_pos);
+        let index = chunks.partition_point(|&pos| self.of(pos) <= priority);
+        chunks.insert(index, chunk_pos);
+    }
+}
+
+/// 区块是否在中心区块周围的加载范围内（多出 margin 层）
+pub fn chunk_in_range(center: ChunkPos, pos: ChunkPos, window: LoadWindow, margin: i32) -> bool {
+    let dy = pos.y - center.y;
+    (pos.x - center.x).abs() <= window.horizontal + margin
+        && (-window.below - margin..=window.above + margin).contains(&dy)
+        && (pos.z - center.z).abs() <= window.horizontal + margin
+}
+
+/// 渲染距离（单位：区块数）- 默认值取自常量，可在设置页面中调整
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderDistance {
+    /// X-Z 平面的加载半径
+    pub horizontal: i32,
+    /// Y 轴的加载半径
+    pub vertical: i32,
+    /// 摄像机高于地表时，为了让地表保持加载，向下最多额外加载的层数
+    pub surface_budget: i32,
+}
+
+impl RenderDistance {
+    /// 根据水平距离推算垂直距离（保持默认配置的 2:1 比例，至少 2 层）
+    pub fn from_horizontal(horizontal: i32) -> Self {
+        Self {
+            horizontal,
+            vertical: (horizontal / 2).max(2),
+            surface_budget: SURFACE_RENDER_BUDGET,
+        }
+    }
+
+    pub fn with_surface_budget(mut self, surface_budget: i32) -> Self {
+        self.surface_budget = surface_budget;
+        self
+    }
+
+    /// 摄像机位于 center_y 层区块、附近最低的可见地表在 surface_y（世界 y）时的加载范围
+    ///
+    /// 向上固定为 vertical 层；向下至少 vertical 层，地表更远时一直延伸到地表所在的区块，
+    /// 但最多多出 surface_budget 层。飞到浮空岛上时地面不会被卸载，往下看也不会出现空洞
+    pub fn window(self, center_y: i32, surface_y: i32) -> LoadWindow {
+        let to_surface = center_y - surface_y.div_euclid(CHUNK_SIZE);
+        LoadWindow {
+            horizontal: self.horizontal,
+            below: to_surface
+                .min(self.vertical + self.surface_budget)
+                .max(self.vertical),
+            above: self.vertical,
         }
     }
 }
 
+impl Default for RenderDistance {
+    fn default() -> Self {
+        Self {
+            horizontal: RENDER_DISTANCE,
+            vertical: VERTICAL_RENDER_DISTANCE,
+            surface_budget: SURFACE_RENDER_BUDGET,
+        }
+    }
+}
+
+/// 相对中心区块的加载范围（单位：区块数），由 [`RenderDistance::window`] 按摄像机高度计算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadWindow {
+    /// X-Z 平面的加载半径
+    pub horizontal: i32,
+    /// 中心区块以下加载的层数
+    pub below: i32,
+    /// 中心区块以上加载的层数
+    pub above: i32,
+}
+
+/// 卸载时保留的已修改区块 - 重新进入加载范围时直接恢复，不再从种子重新生成
+///
+/// 完整保留区块数据（包括温度、相变进度和活跃集合），恢复后模拟从离开时的状态继续
+#[derive(Resource, Default)]
+pub struct UnloadedChunks {
+    pub chunks: HashMap<ChunkPos, ChunkData>,
+}
+
 /// 占位符实体映射 - 保存每个区块位置对应的占位符实体
 #[derive(Resource, Default)]
 pub struct PlaceholderEntities {
@@ -225,8 +945,9 @@ pub struct PlaceholderEntities {
 /// 完成的区块数据（等待批量替换）
 pub struct CompletedChunk {
     pub chunk_pos: ChunkPos,
-    pub voxels: Vec<VoxelKind>,
-    pub mesh: Mesh,
+    /// 区块数据，光照只按区块内部计算，到达后由 [`relight_chunks`](crate::voxel::light::relight_chunks) 修正
+    pub chunk: ChunkData,
+    pub meshes: ChunkMeshes,
     pub placeholder_entity: Entity,
 }
 
@@ -253,3 +974,285 @@ impl Default for ChunkReplacementBuffer {
         }
     }
 }
+
+/// 正在加载途中的区块：生成任务还在进行，或已生成、等待批量替换
+#[derive(SystemParam)]
+pub struct InFlightChunks<'w> {
+    tasks: Res<'w, ChunkTasks>,
+    buffer: Res<'w, ChunkReplacementBuffer>,
+}
+
+impl InFlightChunks<'_> {
+    /// 所有加载途中的区块位置
+    pub fn positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.tasks.pending.keys().copied().chain(
+            self.buffer
+                .completed
+                .iter()
+                .map(|completed| completed.chunk_pos),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_offsets_sorted_by_distance() {
+        // 地表就在中心区块，上下对称
+        let window = RenderDistance::from_horizontal(3).window(0, 8);
+        let offsets = LoadScan::load_offsets(window);
+        assert_eq!(offsets.len(), 7 * 5 * 7);
+        assert_eq!(offsets[0], IVec3::ZERO);
+        assert!(offsets
+            .windows(2)
+            .all(|pair| pair[0].length_squared() <= pair[1].length_squared()));
+    }
+
+    #[test]
+    fn test_unload_hysteresis() {
+        let window = RenderDistance::from_horizontal(4).window(0, 8);
+        let center = ChunkPos::new(0, 0, 0);
+        let just_outside = ChunkPos::new(5, 0, 0);
+
+        // 刚离开加载范围的区块还在卸载范围内
+        assert!(!chunk_in_range(center, just_outside, window, 0));
+        assert!(chunk_in_range(
+            center,
+            just_outside,
+            window,
+            UNLOAD_HYSTERESIS
+        ));
+        let far = ChunkPos::new(4 + UNLOAD_HYSTERESIS + 1, 0, 0);
+        assert!(!chunk_in_range(center, far, window, UNLOAD_HYSTERESIS));
+    }
+
+    #[test]
+    fn test_load_window_follows_altitude() {
+        let distance = RenderDistance::from_horizontal(8).with_surface_budget(6);
+        assert_eq!(distance.vertical, 4);
+
+        // 贴近地表：上下各 4 层
+        let near = distance.window(2, 40);
+        assert_eq!((near.below, near.above), (4, 4));
+
+        // 高出地表 7 层：向下延伸到地表所在的区块，向上不变
+        let high = distance.window(9, 40);
+        assert_eq!((high.below, high.above), (7, 4));
+        let center = ChunkPos::new(0, 9, 0);
+        assert!(chunk_in_range(center, ChunkPos::new(0, 2, 0), high, 0));
+        assert!(!chunk_in_range(center, ChunkPos::new(0, 14, 0), high, 0));
+
+        // 地表太远时最多多出 surface_budget 层
+        let higher = distance.window(30, 40);
+        assert_eq!(higher.below, 10);
+
+        // 在地下时不受地表影响
+        let deep = distance.window(-3, 40);
+        assert_eq!((deep.below, deep.above), (4, 4));
+    }
+
+    #[test]
+    fn test_load_priority_prefers_surface_and_view() {
+        // 原点区块列的地表在 y = 3
+        let mut surface = ChunkData::new();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in 0..4 {
+                    surface.set(x, y, z, VoxelKind::Dirt);
+                }
+            }
+        }
+        let origin = ChunkPos::new(0, 0, 0);
+        let chunks = HashMap::from([(origin, surface)]);
+        let mut heightmap = Heightmap::default();
+        heightmap.refresh(&chunks, [origin]);
+
+        let priority = LoadPriority {
+            center: origin,
+            forward: Vec3::X,
+            motion: Vec3::ZERO,
+            heightmap: &heightmap,
+        };
+        let ahead = ChunkPos::new(1, 0, 0);
+        let behind = ChunkPos::new(-1, 0, 0);
+        let sky = ChunkPos::new(0, 1, 0);
+        let deep = ChunkPos::new(0, -1, 0);
+
+        let mut queue = vec![deep, sky, behind, ahead];
+        priority.sort(&mut queue);
+        assert_eq!(queue, vec![ahead, behind, sky, deep]);
+
+        // 深处的区块排在更远但还没有高度数据的区块之后
+        let deeper = ChunkPos::new(0, -3, 0);
+        let far = ChunkPos::new(-4, 0, 1);
+        priority.insert(&mut queue, deeper);
+        priority.insert(&mut queue, far);
+        assert!(queue
+            .windows(2)
+            .all(|pair| priority.of(pair[0]) <= priority.of(pair[1])));
+        let index = |pos| queue.iter().position(|&p| p == pos).unwrap();
+        assert!(index(far) < index(deeper));
+    }
+
+    #[test]
+    fn test_fast_motion_leads_loading() {
+        let start = Instant::now();
+        let mut motion = LoadMotion::default();
+        let step = Duration::from_millis(100);
+
+        // 以 40 方块/秒沿 +X 飞行：加载范围前移，移动方向上的区块优先
+        for i in 0..20 {
+            motion.observe(Vec3::new(4.0 * i as f32, 50.0, 0.0), start + step * i);
+        }
+        assert!((motion.velocity.x - 40.0).abs() < 1.0);
+        assert_eq!(motion.lead(8), IVec3::new(4, 0, 0));
+        assert_eq!(motion.lead(4), IVec3::new(2, 0, 0));
+
+        let heightmap = Heightmap::default();
+        let priority = LoadPriority {
+            center: ChunkPos::new(0, 0, 0),
+            forward: Vec3::Z,
+            motion: motion.bias(),
+            heightmap: &heightmap,
+        };
+        assert!(priority.of(ChunkPos::new(2, 0, 0)) < priority.of(ChunkPos::new(-2, 0, 0)));
+
+        // 步行速度不移动加载范围
+        let mut walking = LoadMotion::default();
+        for i in 0..20 {
+            walking.observe(Vec3::new(0.4 * i as f32, 50.0, 0.0), start + step * i);
+        }
+        assert_eq!(walking.lead(8), IVec3::ZERO);
+
+        // 传送不算移动
+        motion.observe(Vec3::new(5000.0, 50.0, 0.0), start + step * 20);
+        assert_eq!(motion.velocity, Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_remesh_budget_grows_when_idle() {
+        let mut scheduler = RemeshScheduler::default();
+
+        // 30 FPS：按正常预算派发，并受同时进行的任务数限制
+        assert_eq!(scheduler.budget(1.0 / 30.0, 0), scheduler.per_frame);
+        assert!(!scheduler.idle);
+        assert_eq!(scheduler.budget(1.0 / 30.0, 14), 2);
+
+        // 帧时间持续很低后进入空闲模式
+        for _ in 0..60 {
+            scheduler.budget(1.0 / 240.0, 0);
+        }
+        assert!(scheduler.idle);
+        assert_eq!(scheduler.budget(1.0 / 240.0, 0), scheduler.idle_per_frame);
+        assert_eq!(scheduler.budget(1.0 / 240.0, scheduler.max_in_flight), 0);
+    }
+
+    #[test]
+    fn test_remesh_priority_prefers_near_and_visible() {
+        let camera = ChunkPos::new(0, 0, 0);
+        let near = RemeshScheduler::priority(ChunkPos::new(1, 0, 0), camera, true);
+        let far = RemeshScheduler::priority(ChunkPos::new(5, 0, 0), camera, true);
+        let behind = RemeshScheduler::priority(ChunkPos::new(-2, 0, 0), camera, false);
+        assert!(near < far);
+        // 身后不远的区块也排在视野内较远的区块之后
+        assert!(far < behind);
+    }
+
+    fn generated() -> TaskOutput {
+        let mut chunk = ChunkData::new();
+        chunk.voxels = PalettedArray::filled(ChunkData::VOXEL_COUNT, VoxelKind::Stone);
+        TaskOutput::Generated((Box::new(chunk), ChunkMeshes::empty()))
+    }
+
+    #[test]
+    fn test_cancelled_task_results_are_dropped() {
+        let mut tasks = ChunkTasks::default();
+        let chunk_pos = ChunkPos::new(1, 0, 0);
+
+        // 区块卸载后又重新派发：旧任务的结果晚于新任务派发到达
+        let old = tasks.start(chunk_pos, CancelToken::default(), Entity::PLACEHOLDER);
+        let cancelled = tasks.cancel(&chunk_pos).unwrap();
+        assert!(cancelled.cancel.is_cancelled());
+        let new = tasks.start(chunk_pos, CancelToken::default(), Entity::PLACEHOLDER);
+        old.send(generated());
+        assert!(tasks.drain_completed().is_empty());
+        assert_eq!(tasks.len(), 1);
+
+        new.send(generated());
+        let completed = tasks.drain_completed();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].chunk_pos, chunk_pos);
+        assert!(tasks.is_empty());
+    }
+
+    #[test]
+    fn test_abandoned_task_is_removed() {
+        let mut tasks = ChunkTasks::default();
+        let chunk_pos = ChunkPos::new(0, 2, 0);
+
+        // 工作线程 panic：句柄在栈展开时发回 Abandoned
+        let reporter = tasks.start(chunk_pos, CancelToken::default(), Entity::PLACEHOLDER);
+        let result = std::thread::spawn(move || {
+            let _reporter = reporter;
+            panic!("generation failed");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(tasks.drain_completed().is_empty());
+        assert!(tasks.is_empty());
+
+        // 没有产物就结束的重新生成任务也会报告结束
+        drop(tasks.start_regen(chunk_pos, CancelToken::default()));
+        let regenerated = tasks.drain_regenerated();
+        assert_eq!(regenerated.len(), 1);
+        assert!(regenerated[0].1.is_none());
+        assert_eq!(tasks.regen_count(), 0);
+    }
+
+    #[test]
+    fn test_results_are_routed_by_kind() {
+        let mut tasks = ChunkTasks::default();
+        let chunk_pos = ChunkPos::new(0, 0, 3);
+
+        let generate = tasks.start(chunk_pos, CancelToken::default(), Entity::PLACEHOLDER);
+        let remesh = tasks.start_remesh(chunk_pos);
+        assert!(tasks.is_remeshing(&chunk_pos));
+        remesh.send(TaskOutput::Remeshed(ChunkMeshes::empty()));
+        generate.send(generated());
+
+        assert_eq!(tasks.drain_remeshed().len(), 1);
+        assert_eq!(tasks.remesh_count(), 0);
+        assert_eq!(tasks.drain_completed().len(), 1);
+
+        // 新一轮开始时取消的重新生成任务，结果丢弃
+        let regen = tasks.start_regen(chunk_pos, CancelToken::default());
+        tasks.cancel_regens();
+        regen.send(TaskOutput::Regenerated(PalettedArray::filled(
+            ChunkData::VOXEL_COUNT,
+            VoxelKind::Stone,
+        )));
+        assert!(tasks.drain_regenerated().is_empty());
+    }
+
+    #[test]
+    fn test_results_received_by_other_drains_keep_their_task() {
+        let mut tasks = ChunkTasks::default();
+        let chunk_pos = ChunkPos::new(2, 0, 0);
+
+        // 重建系统先从通道收到生成结果：区块仍在生成中，卸载时照样可以取消
+        let generate = tasks.start(chunk_pos, CancelToken::default(), Entity::PLACEHOLDER);
+        generate.send(generated());
+        assert!(tasks.drain_remeshed().is_empty());
+        assert!(tasks.contains(&chunk_pos));
+        assert!(tasks.cancel(&chunk_pos).is_some());
+        assert!(tasks.drain_completed().is_empty());
+
+        let generate = tasks.start(chunk_pos, CancelToken::default(), Entity::PLACEHOLDER);
+        generate.send(generated());
+        assert!(tasks.drain_regenerated().is_empty());
+        assert_eq!(tasks.drain_completed().len(), 1);
+        assert!(tasks.is_empty());
+    }
+}
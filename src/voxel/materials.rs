@@ -1,37 +1,254 @@
 //! 材质系统
+//!
+//! 区块使用在 StandardMaterial 上扩展的 ChunkMaterial：顶点只有打包的位置（含面朝向）和颜色，
+//! 区块着色器（`assets/shaders/chunk.wgsl`）在顶点阶段解包出位置、法线和颜色，
+//! 之后交给标准 PBR 光照、阴影和雾效处理。带发光属性的网格（燃烧中的方块）
+//! 额外打开 `VOXEL_GLOW`，发光色叠加到自发光上。
+//!
+//! 阴影等预处理同样要解包位置，使用单独的预处理顶点着色器（`assets/shaders/chunk_prepass.wgsl`）。
+//! 透明和水网格的原点在区块中心，扩展上的 `centered` 打开 `VOXEL_CENTERED`，解包时减去半个区块。
+//!
+//! 扩展上的 [`CloudShadow`] 是唯一的材质参数，受光照的区块材质按它在地面上画出云影，
+//! 由云层系统（[`crate::clouds`]）每帧更新。
+//!
+//! 水使用单独的材质，扩展上的 `water` 打开 `VOXEL_WATER`：顶点阶段按时间让水面起伏，
+//! 片元阶段在水面法线上叠加随时间移动的细小波纹
 
+use bevy::mesh::MeshVertexBufferLayoutRef;
+use bevy::pbr::{
+    ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline,
+};
 use bevy::prelude::*;
+use bevy::render::render_resource::{
+    AsBindGroup, RenderPipelineDescriptor, ShaderType, SpecializedMeshPipelineError,
+};
+use bevy::shader::ShaderRef;
+
+use crate::voxel::mesh::{ATTRIBUTE_VOXEL_COLOR, ATTRIBUTE_VOXEL_GLOW, ATTRIBUTE_VOXEL_POSITION};
+
+/// 区块着色器路径（相对于 assets 目录）
+const CHUNK_SHADER_PATH: &str = "shaders/chunk.wgsl";
+/// 区块预处理顶点着色器路径
+const CHUNK_PREPASS_SHADER_PATH: &str = "shaders/chunk_prepass.wgsl";
+
+/// 区块材质：标准 PBR 材质加上区块顶点格式
+pub type ChunkMaterial = ExtendedMaterial<StandardMaterial, ChunkMaterialExtension>;
+
+/// 区块材质扩展，替换顶点格式和着色器，另外绑定云影参数
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, Default)]
+#[bind_group_data(ChunkMaterialKey)]
+pub struct ChunkMaterialExtension {
+    /// 是否为水面材质（打开水波动画）
+    pub water: bool,
+    /// 网格原点是否在区块中心（透明和水网格）
+    pub centered: bool,
+    #[uniform(100)]
+    pub cloud_shadow: CloudShadow,
+}
+
+/// 云影参数，与区块着色器中的 `CloudShadow` 对应
+#[derive(ShaderType, Reflect, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CloudShadow {
+    /// 云层随风漂移的距离
+    pub offset: Vec2,
+    /// 云量，0 为无云，1 为阴天
+    pub coverage: f32,
+    /// 云最浓处压暗的比例，0 时不画云影
+    pub strength: f32,
+    /// 云层高度，高于云层的方块没有云影
+    pub height: f32,
+}
+
+/// 区块材质的管线特化键
+#[repr(C)]
+#[derive(Eq, PartialEq, Hash, Copy, Clone)]
+pub struct ChunkMaterialKey {
+    water: bool,
+    centered: bool,
+}
+
+impl From<&ChunkMaterialExtension> for ChunkMaterialKey {
+    fn from(extension: &ChunkMaterialExtension) -> Self {
+        Self {
+            water: extension.water,
+            centered: extension.centered,
+        }
+    }
+}
+
+impl MaterialExtension for ChunkMaterialExtension {
+    fn vertex_shader() -> ShaderRef {
+        CHUNK_SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        CHUNK_SHADER_PATH.into()
+    }
+
+    fn prepass_vertex_shader() -> ShaderRef {
+        CHUNK_PREPASS_SHADER_PATH.into()
+    }
+
+    fn deferred_vertex_shader() -> ShaderRef {
+        CHUNK_PREPASS_SHADER_PATH.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // 阴影等预处理只需要打包的位置，片元阶段沿用默认的预处理着色器
+        if descriptor
+            .vertex
+            .shader_defs
+            .contains(&"PREPASS_PIPELINE".into())
+        {
+            let position = ATTRIBUTE_VOXEL_POSITION.at_shader_location(0);
+            descriptor.vertex.buffers = vec![layout.0.get_layout(&[position])?];
+            if key.bind_group_data.centered {
+                descriptor.vertex.shader_defs.push("VOXEL_CENTERED".into());
+            }
+            return Ok(());
+        }
+
+        let mut attributes = vec![
+            ATTRIBUTE_VOXEL_POSITION.at_shader_location(0),
+            ATTRIBUTE_VOXEL_COLOR.at_shader_location(1),
+        ];
+        // 网格没有标准颜色属性，手动打开顶点颜色，让 PBR 片元阶段乘上解包后的颜色
+        let mut shader_defs = vec!["VERTEX_COLORS".into()];
+        if layout.0.contains(ATTRIBUTE_VOXEL_GLOW) {
+            attributes.push(ATTRIBUTE_VOXEL_GLOW.at_shader_location(2));
+            shader_defs.push("VOXEL_GLOW".into());
+        }
+        if key.bind_group_data.water {
+            shader_defs.push("VOXEL_WATER".into());
+        }
+        if key.bind_group_data.centered {
+            shader_defs.push("VOXEL_CENTERED".into());
+        }
+
+        descriptor.vertex.buffers = vec![layout.0.get_layout(&attributes)?];
+        descriptor
+            .vertex
+            .shader_defs
+            .extend_from_slice(&shader_defs);
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.extend(shader_defs);
+        }
+        Ok(())
+    }
+}
 
 /// 区块材质资源 - 存储不透明和透明材质的句柄
 #[derive(Resource)]
 pub struct ChunkMaterials {
     /// 不透明材质（用于大多数方块）
-    pub opaque: Handle<StandardMaterial>,
-    /// 透明材质（用于水、冰、树叶等）
-    pub transparent: Handle<StandardMaterial>,
+    pub opaque: Handle<ChunkMaterial>,
+    /// 透明材质（用于冰、树叶等）
+    pub transparent: Handle<ChunkMaterial>,
+    /// 水面材质（透明材质加上水波动画）
+    pub water: Handle<ChunkMaterial>,
+    /// 自发光材质（用于熔岩）
+    pub emissive: Handle<ChunkMaterial>,
+    /// 加载占位符线框材质（标准顶点格式）
+    pub placeholder: Handle<StandardMaterial>,
+}
+
+/// 自发光材质的亮度倍数，超过 1 的部分由泛光（Bloom）扩散成光晕
+const EMISSIVE_INTENSITY: f32 = 3.0;
+
+/// 以标准材质为基础创建区块材质
+fn chunk_material(base: StandardMaterial) -> ChunkMaterial {
+    ExtendedMaterial {
+        base,
+        extension: ChunkMaterialExtension::default(),
+    }
+}
+
+/// 以标准材质为基础创建透明方块材质（网格原点在区块中心）
+fn transparent_material(base: StandardMaterial) -> ChunkMaterial {
+    ExtendedMaterial {
+        base,
+        extension: ChunkMaterialExtension {
+            centered: true,
+            ..default()
+        },
+    }
+}
+
+/// 以标准材质为基础创建水面材质（网格原点在区块中心）
+fn water_material(base: StandardMaterial) -> ChunkMaterial {
+    ExtendedMaterial {
+        base,
+        extension: ChunkMaterialExtension {
+            water: true,
+            centered: true,
+            ..default()
+        },
+    }
 }
 
 /// 初始化材质系统
-/// 创建不透明和透明两种材质
-pub fn setup_materials(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
+/// 创建不透明、透明、水面和自发光四种区块材质，以及占位符材质
+pub fn setup_materials(
+    mut commands: Commands,
+    mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
     // 不透明材质：高粗糙度，适合大多数方块
     // 优化：使用unlit以减少光照计算开销
-    let opaque = materials.add(StandardMaterial {
+    let opaque = chunk_materials.add(chunk_material(StandardMaterial {
         base_color: Color::WHITE,
         perceptual_roughness: 0.9,
         unlit: false, // 保持光照以获得更好的视觉效果
         cull_mode: Some(bevy::render::render_resource::Face::Back), // 背面剔除
         ..default()
-    });
+    }));
 
     // 透明材质：低粗糙度，支持透明混合
-    let transparent = materials.add(StandardMaterial {
+    let transparent = chunk_materials.add(transparent_material(StandardMaterial {
         base_color: Color::WHITE,
         perceptual_roughness: 0.3,
         alpha_mode: AlphaMode::Blend,
         cull_mode: None, // 透明物体不剔除
         ..default()
+    }));
+
+    // 水面材质：比其他透明方块更光滑，波纹上能看到高光
+    let water = chunk_materials.add(water_material(StandardMaterial {
+        base_color: Color::WHITE,
+        perceptual_roughness: 0.1,
+        reflectance: 0.3,
+        alpha_mode: AlphaMode::Blend,
+        cull_mode: None,
+        ..default()
+    }));
+
+    // 自发光材质：不受光照和曝光影响，顶点颜色乘以 HDR 亮度，夜晚和洞穴中同样明亮
+    let emissive = chunk_materials.add(chunk_material(StandardMaterial {
+        base_color: Color::linear_rgb(EMISSIVE_INTENSITY, EMISSIVE_INTENSITY, EMISSIVE_INTENSITY),
+        unlit: true,
+        cull_mode: Some(bevy::render::render_resource::Face::Back),
+        ..default()
+    }));
+
+    // 占位符线框使用标准顶点格式，其余与透明材质相同
+    let placeholder = materials.add(StandardMaterial {
+        base_color: Color::WHITE,
+        perceptual_roughness: 0.3,
+        alpha_mode: AlphaMode::Blend,
+        cull_mode: None,
+        ..default()
     });
 
-    commands.insert_resource(ChunkMaterials { opaque, transparent });
+    commands.insert_resource(ChunkMaterials {
+        opaque,
+        transparent,
+        water,
+        emissive,
+        placeholder,
+    });
 }
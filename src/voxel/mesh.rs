@@ -1,82 +1,90 @@
 //! 网格构建系统 - 顶点处理、去重和网格构建器
 
-use bevy::mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues};
+use bevy::mesh::{
+    Indices, Mesh, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues, VertexFormat,
+};
 use bevy::prelude::*;
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
 
 use crate::voxel::constants::CHUNK_SIZE;
 
 // ============================================================================
-// 顶点去重
+// 顶点格式
 // ============================================================================
 
-/// 顶点唯一标识键 - 用于HashMap去重
-/// 考虑位置、法线和颜色
-#[derive(Clone, Copy)]
-struct VertexKey {
-    /// 位置 - 使用定点数避免浮点精度问题（乘以1000转整数）
-    pos: [i32; 3],
-    /// 法线方向索引 - 6个方向：0=+X, 1=-X, 2=+Y, 3=-Y, 4=+Z, 5=-Z
-    normal_index: u8,
-    /// 颜色 - 压缩为32位RGBA
-    color_packed: u32,
+/// 区块顶点位置和面朝向，打包为 4 个 u8（见 [`pack_position`]）
+///
+/// 区块内的坐标都在 0-16 之间，按定点数存储就够了。区块网格通常只有这个属性和颜色
+/// （每个顶点 8 字节），区块着色器在顶点阶段解包出位置和法线
+pub const ATTRIBUTE_VOXEL_POSITION: MeshVertexAttribute = MeshVertexAttribute::new(
+    "Voxel_Position",
+    0x766f_7865_6c70_6f73,
+    VertexFormat::Uint8x4,
+);
+
+/// y 坐标小数部分的精度（每格的份数），不满一格的水面按它取整
+pub const HEIGHT_STEPS: f32 = 32.0;
+
+/// x、z 坐标的精度（每格的份数），仙人掌等内缩的方块按它取整
+pub const HORIZONTAL_STEPS: f32 = 8.0;
+
+/// 区块顶点颜色（已烘焙环境光遮蔽），RGBA8 打包为一个 u32，R 在最低字节
+pub const ATTRIBUTE_VOXEL_COLOR: MeshVertexAttribute =
+    MeshVertexAttribute::new("Voxel_Color", 0x766f_7865_6c63_6f6c, VertexFormat::Uint32);
+
+/// 区块顶点发光色（燃烧中的方块），与颜色相同的 RGBA8 打包格式，A 为发光强度
+///
+/// 只有含发光面的网格才带这个属性，区块着色器据此把它叠加到自发光上
+pub const ATTRIBUTE_VOXEL_GLOW: MeshVertexAttribute =
+    MeshVertexAttribute::new("Voxel_Glow", 0x766f_7865_6c67_6c6f, VertexFormat::Uint32);
+
+/// 将 0.0-1.0 的 RGBA 颜色打包为 ATTRIBUTE_VOXEL_COLOR 的格式（着色器中用 unpack4x8unorm 还原）
+pub fn pack_color(color: [f32; 4]) -> u32 {
+    u32::from_le_bytes(color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8))
 }
 
-impl VertexKey {
-    /// 从浮点数据创建顶点键
-    fn new(pos: [f32; 3], normal: [f32; 3], color: [f32; 4]) -> Self {
-        // 位置转定点数
-        let pos_fixed = [
-            (pos[0] * 1000.0) as i32,
-            (pos[1] * 1000.0) as i32,
-            (pos[2] * 1000.0) as i32,
-        ];
-
-        // 法线编码为索引
-        let normal_index = match (normal[0] as i32, normal[1] as i32, normal[2] as i32) {
-            (1, 0, 0) => 0,
-            (-1, 0, 0) => 1,
-            (0, 1, 0) => 2,
-            (0, -1, 0) => 3,
-            (0, 0, 1) => 4,
-            (0, 0, -1) => 5,
-            _ => 0,
-        };
-
-        // 颜色压缩为RGBA8888
-        let color_packed = ((color[0] * 255.0) as u32) << 24
-            | ((color[1] * 255.0) as u32) << 16
-            | ((color[2] * 255.0) as u32) << 8
-            | ((color[3] * 255.0) as u32);
-
-        Self {
-            pos: pos_fixed,
-            normal_index,
-            color_packed,
-        }
+/// 面朝向编号：+X、-X、+Y、-Y、+Z、-Z 依次为 0-5（着色器中编号除以 2 是轴，奇数为负方向）
+pub fn face_index(normal: IVec3) -> u8 {
+    match (normal.x, normal.y, normal.z) {
+        (1, 0, 0) => 0,
+        (-1, 0, 0) => 1,
+        (0, 1, 0) => 2,
+        (0, -1, 0) => 3,
+        (0, 0, 1) => 4,
+        _ => 5,
     }
 }
 
-impl PartialEq for VertexKey {
-    fn eq(&self, other: &Self) -> bool {
-        self.pos == other.pos
-            && self.normal_index == other.normal_index
-            && self.color_packed == other.color_packed
-    }
+/// 将区块内的顶点位置和面朝向打包为 ATTRIBUTE_VOXEL_POSITION 的格式
+///
+/// 第一、三个字节是 x、z 乘以 [`HORIZONTAL_STEPS`]（16 格共 128 份），第二个字节是 y 的整数部分，
+/// 第四个字节的低 3 位是面朝向编号，高 5 位是 y 的小数部分（按 [`HEIGHT_STEPS`] 取整）
+pub fn pack_position(pos: [f32; 3], face: u8) -> [u8; 4] {
+    let y = (pos[1] * HEIGHT_STEPS).round() as u32;
+    let steps = HEIGHT_STEPS as u32;
+    [
+        (pos[0] * HORIZONTAL_STEPS).round() as u8,
+        (y / steps) as u8,
+        (pos[2] * HORIZONTAL_STEPS).round() as u8,
+        face | ((y % steps) << 3) as u8,
+    ]
 }
 
-impl Eq for VertexKey {}
+// ============================================================================
+// 顶点去重
+// ============================================================================
 
-impl Hash for VertexKey {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.pos[0].hash(state);
-        self.pos[1].hash(state);
-        self.pos[2].hash(state);
-        self.normal_index.hash(state);
-        self.color_packed.hash(state);
-    }
+/// 顶点唯一标识键 - 用于HashMap去重
+/// 打包的位置包含面朝向，同一平面上相邻的面共享位置、颜色和发光色都相同的顶点
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct VertexKey {
+    /// 打包后的位置和面朝向
+    position: [u8; 4],
+    /// 打包后的颜色
+    color_packed: u32,
+    /// 打包后的发光色
+    glow_packed: u32,
 }
 
 // ============================================================================
@@ -85,9 +93,9 @@ impl Hash for VertexKey {
 
 /// 网格构建缓冲区 - 避免每次分配新Vec
 pub struct MeshBuffers {
-    positions: Vec<[f32; 3]>,
-    normals: Vec<[f32; 3]>,
-    colors: Vec<[f32; 4]>,
+    positions: Vec<[u8; 4]>,
+    colors: Vec<u32>,
+    glows: Vec<u32>,
     indices: Vec<u32>,
     /// 顶点去重HashMap
     vertex_map: HashMap<VertexKey, u32>,
@@ -98,8 +106,8 @@ impl MeshBuffers {
         // 预分配合理的初始容量
         Self {
             positions: Vec::with_capacity(20000),
-            normals: Vec::with_capacity(20000),
             colors: Vec::with_capacity(20000),
+            glows: Vec::with_capacity(20000),
             indices: Vec::with_capacity(30000),
             vertex_map: HashMap::with_capacity(20000),
         }
@@ -108,16 +116,50 @@ impl MeshBuffers {
     /// 清空缓冲区但保留容量
     fn clear(&mut self) {
         self.positions.clear();
-        self.normals.clear();
         self.colors.clear();
+        self.glows.clear();
         self.indices.clear();
         self.vertex_map.clear();
     }
 }
 
 thread_local! {
-    /// 每个线程独立的网格构建缓冲区
+    /// 每个线程独立的网格构建缓冲区（不透明部分）
     pub static MESH_BUFFERS: RefCell<MeshBuffers> = RefCell::new(MeshBuffers::new());
+    /// 每个线程独立的网格构建缓冲区（透明部分）
+    pub static TRANSPARENT_MESH_BUFFERS: RefCell<MeshBuffers> = RefCell::new(MeshBuffers::new());
+    /// 每个线程独立的网格构建缓冲区（水）
+    pub static WATER_MESH_BUFFERS: RefCell<MeshBuffers> = RefCell::new(MeshBuffers::new());
+    /// 每个线程独立的网格构建缓冲区（自发光部分）
+    pub static EMISSIVE_MESH_BUFFERS: RefCell<MeshBuffers> = RefCell::new(MeshBuffers::new());
+}
+
+// ============================================================================
+// 区块网格
+// ============================================================================
+
+/// 区块网格 - 不透明、透明、水和自发光方块分开构建，分别使用不同材质渲染
+pub struct ChunkMeshes {
+    /// 不透明方块（石头、泥土等）
+    pub opaque: Mesh,
+    /// 除水以外的透明方块（冰、树叶）
+    pub transparent: Mesh,
+    /// 水，使用带水波动画的材质
+    pub water: Mesh,
+    /// 自发光方块（熔岩）
+    pub emissive: Mesh,
+}
+
+impl ChunkMeshes {
+    /// 所有部分都为空的网格
+    pub fn empty() -> Self {
+        Self {
+            opaque: ChunkMeshBuilder::build_empty_mesh(),
+            transparent: ChunkMeshBuilder::build_empty_mesh(),
+            water: ChunkMeshBuilder::build_empty_mesh(),
+            emissive: ChunkMeshBuilder::build_empty_mesh(),
+        }
+    }
 }
 
 // ============================================================================
@@ -136,17 +178,26 @@ impl<'a> ChunkMeshBuilder<'a> {
         Self { buffers }
     }
 
-    /// 添加面片并进行顶点去重
+    /// 添加朝向 normal 的面片并进行顶点去重
+    /// 每个顶点单独指定颜色（已烘焙环境光遮蔽），整个面共用一个打包的发光色（0 表示不发光）
     pub fn add_face_deduplicated(
         &mut self,
         vertices: [[f32; 3]; 4],
-        normal: [f32; 3],
-        color: [f32; 4],
+        normal: IVec3,
+        colors: [[f32; 4]; 4],
+        glow: u32,
     ) {
         let mut face_indices = [0u32; 4];
-
-        for (i, &pos) in vertices.iter().enumerate() {
-            let key = VertexKey::new(pos, normal, color);
+        let face = face_index(normal);
+
+        for (i, (&pos, &color)) in vertices.iter().zip(colors.iter()).enumerate() {
+            let pos = pack_position(pos, face);
+            let color = pack_color(color);
+            let key = VertexKey {
+                position: pos,
+                color_packed: color,
+                glow_packed: glow,
+            };
 
             // 查找或插入顶点
             let index = match self.buffers.vertex_map.get(&key) {
@@ -154,8 +205,8 @@ impl<'a> ChunkMeshBuilder<'a> {
                 None => {
                     let new_index = self.buffers.positions.len() as u32;
                     self.buffers.positions.push(pos);
-                    self.buffers.normals.push(normal);
                     self.buffers.colors.push(color);
+                    self.buffers.glows.push(glow);
                     self.buffers.vertex_map.insert(key, new_index);
                     new_index
                 }
@@ -165,25 +216,29 @@ impl<'a> ChunkMeshBuilder<'a> {
         }
 
         // 添加两个三角形的索引
-        self.buffers.indices.extend_from_slice(&[
-            face_indices[0],
-            face_indices[2],
-            face_indices[1],
-            face_indices[0],
-            face_indices[3],
-            face_indices[2],
-        ]);
+        // 沿较亮的对角线切分，避免遮蔽颜色插值产生各向异性的暗斑
+        let [a, b, c, d] = face_indices;
+        if brightness(colors[1]) + brightness(colors[3])
+            > brightness(colors[0]) + brightness(colors[2])
+        {
+            self.buffers.indices.extend_from_slice(&[a, d, b, b, d, c]);
+        } else {
+            self.buffers.indices.extend_from_slice(&[a, c, b, a, d, c]);
+        }
     }
 
     /// 构建最终网格（从缓冲区克隆数据）
+    /// 没有发光面时不写入发光属性，保持 8 字节的顶点
     pub fn build(&self) -> Mesh {
         let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, default());
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.buffers.positions.clone());
-        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.buffers.normals.clone());
         mesh.insert_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            VertexAttributeValues::Float32x4(self.buffers.colors.clone()),
+            ATTRIBUTE_VOXEL_POSITION,
+            VertexAttributeValues::Uint8x4(self.buffers.positions.clone()),
         );
+        mesh.insert_attribute(ATTRIBUTE_VOXEL_COLOR, self.buffers.colors.clone());
+        if self.buffers.glows.iter().any(|&glow| glow != 0) {
+            mesh.insert_attribute(ATTRIBUTE_VOXEL_GLOW, self.buffers.glows.clone());
+        }
         mesh.insert_indices(Indices::U32(self.buffers.indices.clone()));
         mesh
     }
@@ -191,17 +246,21 @@ impl<'a> ChunkMeshBuilder<'a> {
     /// 构建空网格（用于空气区块优化）
     pub fn build_empty_mesh() -> Mesh {
         let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, default());
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, Vec::<[f32; 3]>::new());
-        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, Vec::<[f32; 3]>::new());
         mesh.insert_attribute(
-            Mesh::ATTRIBUTE_COLOR,
-            VertexAttributeValues::Float32x4(Vec::new()),
+            ATTRIBUTE_VOXEL_POSITION,
+            VertexAttributeValues::Uint8x4(Vec::new()),
         );
+        mesh.insert_attribute(ATTRIBUTE_VOXEL_COLOR, Vec::<u32>::new());
         mesh.insert_indices(Indices::U32(Vec::new()));
         mesh
     }
 }
 
+/// 顶点颜色亮度（用于选择四边形的切分对角线）
+fn brightness(color: [f32; 4]) -> f32 {
+    color[0] + color[1] + color[2]
+}
+
 // ============================================================================
 // 占位符网格
 // ============================================================================
@@ -277,50 +336,153 @@ pub fn create_placeholder_mesh() -> Mesh {
 
 /// 根据方向获取面片的4个顶点坐标
 /// 顶点顺序确保逆时针环绕（用于正确的面剔除）
-pub fn get_face_vertices(x: f32, y: f32, z: f32, dir: IVec3) -> [[f32; 3]; 4] {
+/// 方块顶部位于 y + height，水面等不满一格的方块 height 小于 1.0
+pub fn get_face_vertices(
+    x: f32,
+    y: f32,
+    z: f32,
+    dir: IVec3,
+    height: f32,
+) -> [[f32; 3]; 4] {
+    get_box_face_vertices(
+        Vec3::new(x, y, z),
+        Vec3::new(x + 1.0, y + height, z + 1.0),
+        dir,
+    )
+}
+
+/// 获取 min、max 两角围成的长方体朝向 dir 的面片顶点，环绕顺序与 [`get_face_vertices`] 相同
+pub fn get_box_face_vertices(min: Vec3, max: Vec3, dir: IVec3) -> [[f32; 3]; 4] {
+    let (x0, y0, z0) = (min.x, min.y, min.z);
+    let (x1, y1, z1) = (max.x, max.y, max.z);
     match (dir.x, dir.y, dir.z) {
         // 右面 (+X)
-        (1, 0, 0) => [
-            [x + 1.0, y, z],
-            [x + 1.0, y, z + 1.0],
-            [x + 1.0, y + 1.0, z + 1.0],
-            [x + 1.0, y + 1.0, z],
-        ],
+        (1, 0, 0) => [[x1, y0, z0], [x1, y0, z1], [x1, y1, z1], [x1, y1, z0]],
         // 左面 (-X)
-        (-1, 0, 0) => [
-            [x, y, z + 1.0],
-            [x, y, z],
-            [x, y + 1.0, z],
-            [x, y + 1.0, z + 1.0],
-        ],
+        (-1, 0, 0) => [[x0, y0, z1], [x0, y0, z0], [x0, y1, z0], [x0, y1, z1]],
         // 上面 (+Y)
-        (0, 1, 0) => [
-            [x, y + 1.0, z],
-            [x + 1.0, y + 1.0, z],
-            [x + 1.0, y + 1.0, z + 1.0],
-            [x, y + 1.0, z + 1.0],
-        ],
+        (0, 1, 0) => [[x0, y1, z0], [x1, y1, z0], [x1, y1, z1], [x0, y1, z1]],
         // 下面 (-Y)
-        (0, -1, 0) => [
-            [x, y, z + 1.0],
-            [x + 1.0, y, z + 1.0],
-            [x + 1.0, y, z],
-            [x, y, z],
-        ],
+        (0, -1, 0) => [[x0, y0, z1], [x1, y0, z1], [x1, y0, z0], [x0, y0, z0]],
         // 前面 (+Z)
-        (0, 0, 1) => [
-            [x + 1.0, y, z + 1.0],
-            [x, y, z + 1.0],
-            [x, y + 1.0, z + 1.0],
-            [x + 1.0, y + 1.0, z + 1.0],
-        ],
+        (0, 0, 1) => [[x1, y0, z1], [x0, y0, z1], [x0, y1, z1], [x1, y1, z1]],
         // 后面 (-Z)
-        (0, 0, -1) => [
-            [x, y, z],
-            [x + 1.0, y, z],
-            [x + 1.0, y + 1.0, z],
-            [x, y + 1.0, z],
-        ],
+        (0, 0, -1) => [[x0, y0, z0], [x1, y0, z0], [x1, y1, z0], [x0, y1, z0]],
         _ => [[0.0; 3]; 4],
     }
 }
+
+/// 交叉形方块（花草）的面片：两条对角线上各一片竖直面片，正反两面各一个四边形，
+/// 从任何方向看都不会被背面剔除
+pub fn get_cross_quads(x: f32, y: f32, z: f32) -> [[[f32; 3]; 4]; 4] {
+    let (x1, y1, z1) = (x + 1.0, y + 1.0, z + 1.0);
+    let diagonal = [[x, y, z], [x1, y, z1], [x1, y1, z1], [x, y1, z]];
+    let anti_diagonal = [[x1, y, z], [x, y, z1], [x, y1, z1], [x1, y1, z]];
+    let reversed = |[a, b, c, d]: [[f32; 3]; 4]| [b, a, d, c];
+    [
+        diagonal,
+        reversed(diagonal),
+        anti_diagonal,
+        reversed(anti_diagonal),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_color_puts_red_in_lowest_byte() {
+        assert_eq!(pack_color([1.0, 0.0, 0.0, 0.0]), 0x0000_00FF);
+        assert_eq!(pack_color([0.0, 0.0, 0.0, 1.0]), 0xFF00_0000);
+        assert_eq!(pack_color([0.5, 2.0, -1.0, 1.0]), 0xFF00_FF80);
+    }
+
+    #[test]
+    fn test_pack_position() {
+        assert_eq!(pack_position([16.0, 0.0, 3.0], 2), [128, 0, 24, 2]);
+        assert_eq!(pack_position([0.0, 16.0, 0.0], 5), [0, 16, 0, 5]);
+        // 水面高度按 1/32 格取整：4.9 -> 4 + 29/32
+        assert_eq!(pack_position([1.0, 4.9, 2.0], 3), [8, 4, 16, 3 | (29 << 3)]);
+        // 水平坐标按 1/8 格取整
+        assert_eq!(pack_position([0.125, 0.0, 15.875], 0), [1, 0, 127, 0]);
+        assert_eq!(face_index(IVec3::NEG_Z), 5);
+    }
+
+    #[test]
+    fn test_box_faces_match_unit_cube_faces() {
+        for dir in [IVec3::X, IVec3::NEG_Y, IVec3::Z] {
+            assert_eq!(
+                get_box_face_vertices(Vec3::new(2.0, 3.0, 4.0), Vec3::new(3.0, 3.5, 5.0), dir),
+                get_face_vertices(2.0, 3.0, 4.0, dir, 0.5)
+            );
+        }
+    }
+
+    #[test]
+    fn test_cross_quads_are_double_sided() {
+        let quads = get_cross_quads(0.0, 0.0, 0.0);
+        let normal = |[a, b, c, _]: [[f32; 3]; 4]| {
+            let (a, b, c) = (Vec3::from(a), Vec3::from(b), Vec3::from(c));
+            (b - a).cross(c - a).normalize()
+        };
+        for pair in quads.chunks(2) {
+            assert!(normal(pair[0]).dot(normal(pair[1])) < -0.99);
+            assert!(normal(pair[0]).y.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_coplanar_faces_share_vertices() {
+        let mut buffers = MeshBuffers::new();
+        let mut builder = ChunkMeshBuilder::with_buffers(&mut buffers);
+        let white = [[1.0; 4]; 4];
+        // 相邻两个方块的顶面共享一条边
+        builder.add_face_deduplicated(
+            get_face_vertices(0.0, 0.0, 0.0, IVec3::Y, 1.0),
+            IVec3::Y,
+            white,
+            0,
+        );
+        builder.add_face_deduplicated(
+            get_face_vertices(1.0, 0.0, 0.0, IVec3::Y, 1.0),
+            IVec3::Y,
+            white,
+            0,
+        );
+        assert_eq!(builder.buffers.positions.len(), 6);
+        assert_eq!(builder.buffers.indices.len(), 12);
+        // 朝向不同的面法线不同，不能共享顶点
+        builder.add_face_deduplicated(
+            get_face_vertices(0.0, 0.0, 0.0, IVec3::X, 1.0),
+            IVec3::X,
+            white,
+            0,
+        );
+        assert_eq!(builder.buffers.positions.len(), 10);
+        assert!(builder.build().attribute(ATTRIBUTE_VOXEL_GLOW).is_none());
+    }
+
+    #[test]
+    fn test_glowing_face_keeps_its_own_vertices() {
+        let mut buffers = MeshBuffers::new();
+        let mut builder = ChunkMeshBuilder::with_buffers(&mut buffers);
+        let white = [[1.0; 4]; 4];
+        let glow = pack_color([1.0, 0.5, 0.0, 1.0]);
+        builder.add_face_deduplicated(
+            get_face_vertices(0.0, 0.0, 0.0, IVec3::Y, 1.0),
+            IVec3::Y,
+            white,
+            0,
+        );
+        builder.add_face_deduplicated(
+            get_face_vertices(1.0, 0.0, 0.0, IVec3::Y, 1.0),
+            IVec3::Y,
+            white,
+            glow,
+        );
+        // 共享边上的顶点发光色不同，不能合并
+        assert_eq!(builder.buffers.positions.len(), 8);
+        assert!(builder.build().attribute(ATTRIBUTE_VOXEL_GLOW).is_some());
+    }
+}
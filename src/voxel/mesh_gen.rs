@@ -5,123 +5,628 @@ use std::sync::Arc;
 
 use crate::voxel::chunk::{ChunkData, ChunkPos};
 use crate::voxel::constants::CHUNK_SIZE;
-use crate::voxel::loading::{MeshBuildInput, NeighborEdges};
-use crate::voxel::mesh::{get_face_vertices, ChunkMeshBuilder, MESH_BUFFERS};
-use crate::voxel::seed::WorldSeed;
-use crate::voxel::terrain::TerrainGenerator;
-use crate::voxel::voxel_kind::VoxelKind;
+use crate::voxel::domains::corrosion::{corroded_form, rust_tint};
+use crate::voxel::domains::fluid::surface_height;
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::light::{compute_light, light_brightness, light_level, DEFAULT_LIGHT};
+use crate::voxel::loading::{CancelToken, GeneratedChunkData, MeshBuildInput, NeighborEdges};
+use crate::voxel::mesh::{
+    get_box_face_vertices, get_cross_quads, pack_color, ChunkMeshBuilder, ChunkMeshes,
+    EMISSIVE_MESH_BUFFERS, MESH_BUFFERS, TRANSPARENT_MESH_BUFFERS, WATER_MESH_BUFFERS,
+};
+use crate::voxel::profiling::{Stage, StageTimer};
+use crate::voxel::registry::VoxelRegistry;
+use crate::voxel::solid_mask::SolidMask;
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::voxel_kind::{VoxelKind, VoxelShape};
+
+/// 环境光遮蔽等级对应的亮度（0 = 角落完全被遮挡，3 = 无遮挡）
+const AO_BRIGHTNESS: [f32; 4] = [0.45, 0.65, 0.82, 1.0];
+
+/// 燃烧中方块的发光色（橙红色火光，满强度）
+const BURNING_GLOW: [f32; 4] = [1.0, 0.42, 0.08, 1.0];
+
+/// 焦化方块向炭黑色过渡的比例
+const CHARRED_COLOR: Color = Color::srgb(0.09, 0.08, 0.07);
+const CHARRED_TINT: f32 = 0.7;
+
+/// 冻结方块蒙上的霜色和比例
+const FROST_COLOR: Color = Color::srgb(0.8, 0.9, 1.0);
+const FROST_TINT: f32 = 0.45;
+
+/// 潮湿、浸透的方块的（亮度系数，饱和度系数）：颜色变深、更饱和
+const WET_SHADE: (f32, f32) = (0.75, 1.25);
+const SOAKED_SHADE: (f32, f32) = (0.6, 1.4);
+
+/// 深水的颜色，水色随水深向它过渡
+const DEEP_WATER_COLOR: Color = Color::srgba(0.03, 0.10, 0.26, 0.9);
+
+/// 水深达到此值（方块）后颜色不再加深
+const DEEP_WATER_DEPTH: i32 = 12;
 
 /// 在工作线程中生成区块数据并构建网格
-/// 包含地形生成和网格构建两个阶段
-pub fn generate_chunk_and_mesh_async(chunk_pos: ChunkPos, seed: u32) -> (Vec<VoxelKind>, Mesh) {
+/// 包含地形生成、光照和网格构建三个阶段，噪声生成器由所有任务共享
+///
+/// 区块卸载后 `cancel` 被触发，任务在下一个检查点（阶段之间、每层体素）退出并返回 None
+pub fn generate_chunk_and_mesh_async(
+    chunk_pos: ChunkPos,
+    terrain: SharedTerrain,
+    below_surface: bool,
+    cancel: &CancelToken,
+) -> Option<GeneratedChunkData> {
     // 阶段1：生成区块地形数据
-    let world_seed = WorldSeed::new(seed);
-    let generator = TerrainGenerator::new(&world_seed);
-    let chunk_data = generator.generate_chunk(chunk_pos);
-    let voxels = chunk_data.voxels.clone();
+    let chunk_data = {
+        let _span = info_span!("generate_chunk", ?chunk_pos).entered();
+        let _timer = StageTimer::start(Stage::Generate);
+        terrain
+            .generator()
+            .generate_chunk_cancellable(chunk_pos, cancel)?
+    };
+    if cancel.is_cancelled() {
+        return None;
+    }
+    light_and_mesh_chunk(chunk_pos, chunk_data, below_surface, cancel)
+}
+
+/// 在工作线程中为新加载的区块（刚生成或从存档读取）计算光照并构建网格
+///
+/// 光照只按区块内部计算（见 [`compute_light`]），区块到达后再结合高度图和相邻区块修正
+///
+/// `below_surface` 表示区块整个位于周围已加载地表以下（见 [`Heightmap`]），
+/// 此时没有透明方块的区块不会被看到，直接返回空网格
+///
+/// [`Heightmap`]: crate::voxel::heightmap::Heightmap
+pub fn light_and_mesh_chunk(
+    chunk_pos: ChunkPos,
+    mut chunk_data: ChunkData,
+    below_surface: bool,
+    cancel: &CancelToken,
+) -> Option<GeneratedChunkData> {
+    chunk_data.compact();
+    chunk_data.light = compute_light(chunk_pos, &chunk_data, None, &[None; 6]);
+    let solid = SolidMask::from_chunk(&chunk_data);
+
+    // 被掩埋的区块：相邻区块露出洞穴时由边界重建补上朝向洞穴的面
+    if below_surface && solid.is_full() {
+        return Some((Box::new(chunk_data), ChunkMeshes::empty()));
+    }
 
     // 阶段2：构建网格（需要相邻区块数据，但首次生成时使用空边界）
     let input = MeshBuildInput {
         chunk_pos,
-        voxels: Arc::new(voxels.clone()),
+        voxels: Arc::new(chunk_data.voxels.to_vec()),
+        solid,
+        variants: Arc::new(chunk_data.variant.to_vec()),
+        flags: Arc::new(chunk_data.flags.to_vec()),
+        light: Arc::new(chunk_data.light.to_vec()),
         neighbor_edges: NeighborEdges::default(),
+        neighbor_light: NeighborEdges::default(),
     };
 
-    let mesh = build_chunk_mesh_async(input);
+    let meshes = build_chunk_mesh_cancellable(input, cancel)?;
 
-    (voxels, mesh)
+    Some((Box::new(chunk_data), meshes))
 }
 
 /// 在工作线程中构建区块网格
-/// 使用线程本地缓冲区和顶点去重优化
-pub fn build_chunk_mesh_async(input: MeshBuildInput) -> Mesh {
+/// 使用线程本地缓冲区和顶点去重优化；透明方块（水、冰、树叶）和自发光方块（熔岩）写入单独的网格
+pub fn build_chunk_mesh_async(input: MeshBuildInput) -> ChunkMeshes {
+    // 从不取消的令牌总会得到完整的网格
+    build_chunk_mesh_cancellable(input, &CancelToken::default()).unwrap_or_else(ChunkMeshes::empty)
+}
+
+/// 与 [`build_chunk_mesh_async`] 相同，但每处理完一层体素检查一次取消令牌，被取消时返回 None
+pub fn build_chunk_mesh_cancellable(
+    input: MeshBuildInput,
+    cancel: &CancelToken,
+) -> Option<ChunkMeshes> {
+    let _span = info_span!("build_chunk_mesh", chunk_pos = ?input.chunk_pos).entered();
+    let _timer = StageTimer::start(Stage::Mesh);
+
     // 优化1: 检查是否为空气chunk，如果是则返回空网格
     let is_empty = input.voxels.iter().all(|&kind| kind == VoxelKind::Air);
     if is_empty {
-        return ChunkMeshBuilder::build_empty_mesh();
+        return Some(ChunkMeshes::empty());
     }
 
     // 优化2: 检查是否完全被包围且不透明
     // 如果chunk完全不透明且所有相邻面都是不透明的，表面不可见
-    let is_fully_opaque = input.voxels.iter().all(|&kind| !kind.is_transparent());
-    if is_fully_opaque && input.is_fully_enclosed() {
-        return ChunkMeshBuilder::build_empty_mesh();
-    }
-
-    MESH_BUFFERS.with(|buffers| {
-        let mut buffers = buffers.borrow_mut();
-        let mut builder = ChunkMeshBuilder::with_buffers(&mut buffers);
-
-        // 6个面的方向和法线
-        let directions: [(IVec3, [f32; 3]); 6] = [
-            (IVec3::X, [1.0, 0.0, 0.0]),
-            (IVec3::NEG_X, [-1.0, 0.0, 0.0]),
-            (IVec3::Y, [0.0, 1.0, 0.0]),
-            (IVec3::NEG_Y, [0.0, -1.0, 0.0]),
-            (IVec3::Z, [0.0, 0.0, 1.0]),
-            (IVec3::NEG_Z, [0.0, 0.0, -1.0]),
-        ];
-
-        // 遍历区块中的所有体素
-        for y in 0..CHUNK_SIZE {
-            for z in 0..CHUNK_SIZE {
-                for x in 0..CHUNK_SIZE {
-                    let index = ChunkData::index(x, y, z);
-                    let kind = input.voxels[index];
-
-                    if kind == VoxelKind::Air {
-                        continue;
+    if input.solid.is_full() && input.is_fully_enclosed() {
+        return Some(ChunkMeshes::empty());
+    }
+
+    MESH_BUFFERS.with(|opaque_buffers| {
+        TRANSPARENT_MESH_BUFFERS.with(|transparent_buffers| {
+            WATER_MESH_BUFFERS.with(|water_buffers| {
+                EMISSIVE_MESH_BUFFERS.with(|emissive_buffers| {
+                    let mut opaque_buffers = opaque_buffers.borrow_mut();
+                    let mut transparent_buffers = transparent_buffers.borrow_mut();
+                    let mut water_buffers = water_buffers.borrow_mut();
+                    let mut emissive_buffers = emissive_buffers.borrow_mut();
+                    let mut builders = FaceBuilders {
+                        opaque: ChunkMeshBuilder::with_buffers(&mut opaque_buffers),
+                        transparent: ChunkMeshBuilder::with_buffers(&mut transparent_buffers),
+                        water: ChunkMeshBuilder::with_buffers(&mut water_buffers),
+                        emissive: ChunkMeshBuilder::with_buffers(&mut emissive_buffers),
+                    };
+                    if !build_faces(&input, &mut builders, cancel) {
+                        return None;
                     }
 
-                    let def = kind.def();
-                    let color = def.color.to_srgba();
-                    let base_color = [color.red, color.green, color.blue, color.alpha];
-                    let local_pos = IVec3::new(x, y, z);
-
-                    // 检查每个面
-                    for (dir, normal) in &directions {
-                        let neighbor_local = local_pos + *dir;
-
-                        // 判断相邻位置是否在区块内
-                        let neighbor = if neighbor_local.x >= 0
-                            && neighbor_local.x < CHUNK_SIZE
-                            && neighbor_local.y >= 0
-                            && neighbor_local.y < CHUNK_SIZE
-                            && neighbor_local.z >= 0
-                            && neighbor_local.z < CHUNK_SIZE
-                        {
-                            // 区块内部查询
-                            let ni = ChunkData::index(
-                                neighbor_local.x,
-                                neighbor_local.y,
-                                neighbor_local.z,
-                            );
-                            input.voxels[ni]
-                        } else {
-                            // 查询相邻区块边界
-                            input
-                                .neighbor_edges
-                                .get_neighbor(local_pos, *dir)
-                                .unwrap_or(VoxelKind::Air)
-                        };
-
-                        // 只渲染暴露的面
-                        if !neighbor.is_transparent() {
-                            continue;
-                        }
+                    Some(ChunkMeshes {
+                        opaque: builders.opaque.build(),
+                        transparent: builders.transparent.build(),
+                        water: builders.water.build(),
+                        emissive: builders.emissive.build(),
+                    })
+                })
+            })
+        })
+    })
+}
+
+/// 各部分网格的构建器
+struct FaceBuilders<'a> {
+    opaque: ChunkMeshBuilder<'a>,
+    transparent: ChunkMeshBuilder<'a>,
+    water: ChunkMeshBuilder<'a>,
+    emissive: ChunkMeshBuilder<'a>,
+}
+
+/// 遍历区块内的体素，将暴露的面分别写入不透明、透明、水和自发光构建器
+/// 每层开始前检查取消令牌，被取消时返回 false
+fn build_faces(
+    input: &MeshBuildInput,
+    builders: &mut FaceBuilders,
+    cancel: &CancelToken,
+) -> bool {
+    // 6个面的方向
+    let directions = [
+        IVec3::X,
+        IVec3::NEG_X,
+        IVec3::Y,
+        IVec3::NEG_Y,
+        IVec3::Z,
+        IVec3::NEG_Z,
+    ];
 
-                        // 水面之间不渲染
-                        if kind == VoxelKind::Water && neighbor == VoxelKind::Water {
-                            continue;
+    let registry = VoxelRegistry::current();
+    let burning_glow = pack_color(BURNING_GLOW);
+    let hidden_layers = hidden_layers(input);
+
+    // 遍历区块中的所有体素
+    for y in 0..CHUNK_SIZE {
+        if cancel.is_cancelled() {
+            return false;
+        }
+        if hidden_layers[y as usize] {
+            continue;
+        }
+        for z in 0..CHUNK_SIZE {
+            // 六面都被区块内不透明方块挡住的体素没有可见的面
+            let enclosed = input.solid.enclosed_row(y as usize, z as usize);
+            for x in 0..CHUNK_SIZE {
+                if enclosed & (1 << x) != 0 {
+                    continue;
+                }
+                let index = ChunkData::index(x, y, z);
+                let kind = input.voxels[index];
+
+                if kind == VoxelKind::Air {
+                    continue;
+                }
+
+                let def = registry.get(kind);
+                // 正在腐蚀的金属按腐蚀阶段向锈蚀形式的颜色过渡
+                let rust = corroded_form(kind)
+                    .map(|rusted| (registry.get(rusted), rust_tint(input.variants[index])));
+                let local_pos = IVec3::new(x, y, z);
+                // 水越深颜色越暗，海洋看起来有深度
+                let depth_tint = (kind == VoxelKind::Water)
+                    .then(|| water_depth_tint(water_column_depth(input, local_pos)));
+                let flags = input.flags[index];
+                let face_color = |dir: IVec3| {
+                    let color = match rust {
+                        Some((rusted, tint)) => {
+                            def.face_color(dir).mix(&rusted.face_color(dir), tint)
                         }
+                        None => def.face_color(dir),
+                    };
+                    let color = match depth_tint {
+                        Some(tint) => color.mix(&DEEP_WATER_COLOR, tint),
+                        None => color,
+                    };
+                    let color = flag_tint(color, flags).to_srgba();
+                    [color.red, color.green, color.blue, color.alpha]
+                };
+                let is_transparent = kind.is_transparent();
+                let glow = if flags.contains(VoxelFlags::BURNING) {
+                    burning_glow
+                } else {
+                    0
+                };
 
-                        let vertices = get_face_vertices(x as f32, y as f32, z as f32, *dir);
-                        builder.add_face_deduplicated(vertices, *normal, base_color);
+                // 花草是两片交叉的面片，没有环境光遮蔽，整株使用所在格的光照
+                if def.shape == VoxelShape::Cross {
+                    let base_color = face_color(IVec3::X);
+                    let shade = light_brightness(light_level(input.light[index]) as f32);
+                    let color = if kind.is_emissive() {
+                        base_color
+                    } else {
+                        [
+                            base_color[0] * shade,
+                            base_color[1] * shade,
+                            base_color[2] * shade,
+                            base_color[3],
+                        ]
+                    };
+                    let builder = if kind.is_emissive() {
+                        &mut builders.emissive
+                    } else if is_transparent {
+                        &mut builders.transparent
+                    } else {
+                        &mut builders.opaque
+                    };
+                    // 面片按朝上的面着色，正反两面亮度一致
+                    for quad in get_cross_quads(x as f32, y as f32, z as f32) {
+                        builder.add_face_deduplicated(quad, IVec3::Y, [color; 4], glow);
                     }
+                    continue;
+                }
+
+                // 水面略低于方块顶面并随水位降低；上方有水时保持满格，让水柱连续
+                let height = if kind == VoxelKind::Water
+                    && neighbor_voxel(input, local_pos, IVec3::Y) != VoxelKind::Water
+                {
+                    surface_height(input.variants[index])
+                } else {
+                    1.0
+                };
+                // 积雪的高度由变体记录的层数决定
+                let shape = if kind == VoxelKind::SnowLayer {
+                    VoxelShape::snow_layers(input.variants[index])
+                } else {
+                    def.shape
+                };
+                let (min, max) = shape_bounds(shape, local_pos.as_vec3(), height);
+
+                // 检查每个面
+                for dir in &directions {
+                    let neighbor = neighbor_voxel(input, local_pos, *dir);
+
+                    // 只渲染暴露的面：格子边界上的面可能被相邻方块挡住，
+                    // 半砖顶面和仙人掌侧面这样缩在格子里的面总是渲染
+                    if shape.face_on_boundary(*dir)
+                        && face_hidden(registry, kind, shape, *dir, neighbor)
+                    {
+                        continue;
+                    }
+
+                    let vertices = get_box_face_vertices(min, max, *dir);
+                    let base_color = face_color(*dir);
+                    // 自发光方块自身就是光源，不做环境光遮蔽
+                    if kind.is_emissive() {
+                        builders.emissive.add_face_deduplicated(
+                            vertices,
+                            *dir,
+                            [base_color; 4],
+                            glow,
+                        );
+                        continue;
+                    }
+
+                    let colors = vertices.map(|vertex| {
+                        let ao = AO_BRIGHTNESS[vertex_ao(input, local_pos, *dir, vertex)];
+                        let shade = ao * vertex_light(input, local_pos, *dir, vertex);
+                        [
+                            base_color[0] * shade,
+                            base_color[1] * shade,
+                            base_color[2] * shade,
+                            base_color[3],
+                        ]
+                    });
+                    let builder = if kind == VoxelKind::Water {
+                        &mut builders.water
+                    } else if is_transparent {
+                        &mut builders.transparent
+                    } else {
+                        &mut builders.opaque
+                    };
+                    builder.add_face_deduplicated(vertices, *dir, colors, glow);
                 }
             }
         }
+    }
+    true
+}
 
-        builder.build()
+/// 方块形状在区块内占据的长方体，height 是水面这样按状态决定的顶面高度
+fn shape_bounds(shape: VoxelShape, pos: Vec3, height: f32) -> (Vec3, Vec3) {
+    match shape {
+        VoxelShape::Slab { height } => (pos, pos + Vec3::new(1.0, height, 1.0)),
+        VoxelShape::Inset { inset } => (
+            pos + Vec3::new(inset, 0.0, inset),
+            pos + Vec3::new(1.0 - inset, 1.0, 1.0 - inset),
+        ),
+        VoxelShape::Cube | VoxelShape::Cross => (pos, pos + Vec3::new(1.0, height, 1.0)),
+    }
+}
+
+/// 相邻方块是否挡住格子边界上朝向它的面
+///
+/// 不透明的立方体挡住任何面。同种方块之间，透明方块（水与水、冰与冰、树叶与树叶）、
+/// 柱体上下相接处和半砖侧面相接处也不渲染；相邻积雪的层数可能不同，侧面照常渲染
+fn face_hidden(
+    registry: &VoxelRegistry,
+    kind: VoxelKind,
+    shape: VoxelShape,
+    dir: IVec3,
+    neighbor: VoxelKind,
+) -> bool {
+    if registry.occludes(neighbor) {
+        return true;
+    }
+    neighbor == kind
+        && match shape {
+            VoxelShape::Cube => kind.is_transparent(),
+            VoxelShape::Inset { .. } => dir.y != 0,
+            VoxelShape::Slab { .. } => dir.y == 0 && kind != VoxelKind::SnowLayer,
+            VoxelShape::Cross => false,
+        }
+}
+
+/// 所有面都会被剔除的层：整层不透明，上下两层（区块顶层和底层看相邻区块的边界层）
+/// 也不透明，四周相邻区块在这一层的边界也不透明。相邻区块没有数据时按空气处理，不跳过
+fn hidden_layers(input: &MeshBuildInput) -> [bool; CHUNK_SIZE as usize] {
+    let size = CHUNK_SIZE as usize;
+    let edges = &input.neighbor_edges;
+    let registry = VoxelRegistry::current();
+    let opaque = |kinds: &[VoxelKind]| kinds.iter().all(|&kind| registry.occludes(kind));
+    // 水平方向的边界面按 y 分行存储，第 y 行是这一层的边界
+    let edge_row_opaque = |edge: &Option<Vec<VoxelKind>>, y: usize| {
+        edge.as_deref()
+            .is_some_and(|face| opaque(&face[y * size..(y + 1) * size]))
+    };
+    let below_opaque = edges.neg_y.as_deref().is_some_and(opaque);
+    let above_opaque = edges.pos_y.as_deref().is_some_and(opaque);
+
+    std::array::from_fn(|y| {
+        input.solid.is_layer_full(y)
+            && (if y == 0 {
+                below_opaque
+            } else {
+                input.solid.is_layer_full(y - 1)
+            })
+            && (if y == size - 1 {
+                above_opaque
+            } else {
+                input.solid.is_layer_full(y + 1)
+            })
+            && [&edges.pos_x, &edges.neg_x, &edges.pos_z, &edges.neg_z]
+                .into_iter()
+                .all(|edge| edge_row_opaque(edge, y))
     })
 }
+
+/// 面外侧一层中与顶点相接的三个体素相对面正前方体素的偏移：两个侧边和一个对角
+fn corner_offsets(local_pos: IVec3, normal: IVec3, vertex: [f32; 3]) -> (IVec3, IVec3, IVec3) {
+    // 顶点相对方块中心的方向：法线轴取法线，其余两轴取 ±1
+    let corner = IVec3::new(
+        corner_sign(normal.x, vertex[0] - local_pos.x as f32),
+        corner_sign(normal.y, vertex[1] - local_pos.y as f32),
+        corner_sign(normal.z, vertex[2] - local_pos.z as f32),
+    );
+    let tangent_a = corner - normal;
+    let (side1, side2) = if normal.x != 0 {
+        (IVec3::new(0, tangent_a.y, 0), IVec3::new(0, 0, tangent_a.z))
+    } else if normal.y != 0 {
+        (IVec3::new(tangent_a.x, 0, 0), IVec3::new(0, 0, tangent_a.z))
+    } else {
+        (IVec3::new(tangent_a.x, 0, 0), IVec3::new(0, tangent_a.y, 0))
+    };
+    (side1, side2, tangent_a)
+}
+
+/// 计算面片顶点的环境光遮蔽等级（0-3）
+///
+/// 检查面外侧一层中与该顶点相接的三个体素：两个侧边和一个对角。
+/// 两个侧边都被遮挡时对角不可见，直接取最暗等级
+fn vertex_ao(
+    input: &MeshBuildInput,
+    local_pos: IVec3,
+    normal: IVec3,
+    vertex: [f32; 3],
+) -> usize {
+    let (side1, side2, corner) = corner_offsets(local_pos, normal, vertex);
+    let base = local_pos + normal;
+    let occludes = |offset: IVec3| !sample_voxel(input, base + offset).is_transparent();
+    let side1 = occludes(side1);
+    let side2 = occludes(side2);
+    if side1 && side2 {
+        return 0;
+    }
+    3 - (side1 as usize + side2 as usize + occludes(corner) as usize)
+}
+
+/// 计算面片顶点的光照亮度
+///
+/// 平均面正前方和与顶点相接的透明体素（与环境光遮蔽取样相同的侧边和对角）的光照等级，
+/// 被两个侧边挡住的对角不参与。面正前方没有数据（相邻区块未加载）时按默认光照处理
+fn vertex_light(
+    input: &MeshBuildInput,
+    local_pos: IVec3,
+    normal: IVec3,
+    vertex: [f32; 3],
+) -> f32 {
+    let (side1, side2, corner) = corner_offsets(local_pos, normal, vertex);
+    let base = local_pos + normal;
+    let front = sample_light(input, base).unwrap_or(DEFAULT_LIGHT);
+    let side1 = sample_light(input, base + side1);
+    let side2 = sample_light(input, base + side2);
+    let corner = if side1.is_some() || side2.is_some() {
+        sample_light(input, base + corner)
+    } else {
+        None
+    };
+
+    let (total, count) = [Some(front), side1, side2, corner]
+        .into_iter()
+        .flatten()
+        .fold((0.0, 0.0), |(total, count), light| {
+            (total + light_level(light) as f32, count + 1.0)
+        });
+    light_brightness(total / count)
+}
+
+/// 顶点在某一轴上的方向：法线轴直接使用法线分量，切线轴按顶点在方块哪一侧取 ±1
+fn corner_sign(normal_component: i32, offset: f32) -> i32 {
+    if normal_component != 0 {
+        normal_component
+    } else if offset > 0.5 {
+        1
+    } else {
+        -1
+    }
+}
+
+/// 获取区块局部坐标处的体素
+///
+/// 只越过一个区块面的位置从相邻区块的边界数据读取；
+/// 越过棱或角的位置没有数据，视为空气
+fn sample_voxel(input: &MeshBuildInput, pos: IVec3) -> VoxelKind {
+    let dir = outside_dir(pos);
+    match dir.abs().element_sum() {
+        0 => input.voxels[ChunkData::index(pos.x, pos.y, pos.z)],
+        1 => input
+            .neighbor_edges
+            .get_neighbor(pos - dir, dir)
+            .unwrap_or(VoxelKind::Air),
+        _ => VoxelKind::Air,
+    }
+}
+
+/// 获取区块局部坐标处透明体素的光照，不透明体素或没有数据的位置返回 None
+fn sample_light(input: &MeshBuildInput, pos: IVec3) -> Option<u8> {
+    if !sample_voxel(input, pos).is_transparent() {
+        return None;
+    }
+    let dir = outside_dir(pos);
+    match dir.abs().element_sum() {
+        0 => Some(input.light[ChunkData::index(pos.x, pos.y, pos.z)]),
+        1 => input.neighbor_light.get_neighbor(pos - dir, dir),
+        _ => None,
+    }
+}
+
+/// 局部坐标越出区块的方向（每个轴取 -1、0 或 1）
+fn outside_dir(pos: IVec3) -> IVec3 {
+    let outside = |v: i32| !(0..CHUNK_SIZE).contains(&v);
+    IVec3::new(
+        outside(pos.x) as i32 * pos.x.signum(),
+        outside(pos.y) as i32 * pos.y.signum(),
+        outside(pos.z) as i32 * pos.z.signum(),
+    )
+}
+
+/// 从 local_pos 向下连续的水方块数（包括自身）
+///
+/// 只看本区块和下方区块的边界层：水柱穿过区块底面继续向下时按最深处理
+fn water_column_depth(input: &MeshBuildInput, local_pos: IVec3) -> i32 {
+    let mut pos = local_pos;
+    while pos.y >= 0 && sample_voxel(input, pos) == VoxelKind::Water {
+        pos.y -= 1;
+    }
+    let depth = local_pos.y - pos.y;
+    if pos.y < 0 && sample_voxel(input, pos) == VoxelKind::Water {
+        DEEP_WATER_DEPTH.max(depth)
+    } else {
+        depth
+    }
+}
+
+/// 按状态标志位调整方块颜色：焦化变黑，潮湿变深变饱和，冻结蒙上一层霜色
+///
+/// 透明度保持不变，玻璃和树叶这样的透明方块不会因此变得不透明
+fn flag_tint(color: Color, flags: VoxelFlags) -> Color {
+    let alpha = color.alpha();
+    let mut color = color;
+    if flags.contains(VoxelFlags::CHARRED) {
+        color = color.mix(&CHARRED_COLOR, CHARRED_TINT);
+    }
+    let wet = if flags.contains(VoxelFlags::SOAKED) {
+        Some(SOAKED_SHADE)
+    } else if flags.contains(VoxelFlags::WET) {
+        Some(WET_SHADE)
+    } else {
+        None
+    };
+    if let Some((lightness, saturation)) = wet {
+        let mut hsla = Hsla::from(color);
+        hsla.lightness *= lightness;
+        hsla.saturation = (hsla.saturation * saturation).min(1.0);
+        color = Srgba::from(hsla).into();
+    }
+    if flags.contains(VoxelFlags::FROZEN) {
+        color = color.mix(&FROST_COLOR, FROST_TINT);
+    }
+    color.with_alpha(alpha)
+}
+
+/// 水深对应的深水颜色比例（0.0 为一格深的浅水，1.0 为最深）
+fn water_depth_tint(depth: i32) -> f32 {
+    ((depth - 1) as f32 / (DEEP_WATER_DEPTH - 1) as f32).clamp(0.0, 1.0)
+}
+
+/// 获取相邻位置的体素，越过区块边界时查询相邻区块的边界数据
+fn neighbor_voxel(input: &MeshBuildInput, local_pos: IVec3, dir: IVec3) -> VoxelKind {
+    let neighbor_local = local_pos + dir;
+
+    // 判断相邻位置是否在区块内
+    if neighbor_local.x >= 0
+        && neighbor_local.x < CHUNK_SIZE
+        && neighbor_local.y >= 0
+        && neighbor_local.y < CHUNK_SIZE
+        && neighbor_local.z >= 0
+        && neighbor_local.z < CHUNK_SIZE
+    {
+        // 区块内部查询
+        input.voxels[ChunkData::index(neighbor_local.x, neighbor_local.y, neighbor_local.z)]
+    } else {
+        // 查询相邻区块边界
+        input
+            .neighbor_edges
+            .get_neighbor(local_pos, dir)
+            .unwrap_or(VoxelKind::Air)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_tint() {
+        let grass = Color::srgb(0.3, 0.6, 0.2);
+        assert_eq!(flag_tint(grass, VoxelFlags::HOT), grass);
+
+        // 焦化变暗
+        let charred = flag_tint(grass, VoxelFlags::CHARRED);
+        assert!(charred.luminance() < grass.luminance() * 0.5);
+
+        // 潮湿变深、更饱和，浸透更深
+        let wet = Hsla::from(flag_tint(grass, VoxelFlags::WET));
+        let soaked = Hsla::from(flag_tint(grass, VoxelFlags::SOAKED));
+        let dry = Hsla::from(grass);
+        assert!(wet.lightness < dry.lightness && soaked.lightness < wet.lightness);
+        assert!(wet.saturation > dry.saturation);
+
+        // 冻结偏向蓝白色
+        let frozen = flag_tint(grass, VoxelFlags::FROZEN).to_srgba();
+        assert!(frozen.blue > 0.5 && frozen.red > 0.3);
+
+        // 透明方块保持透明度
+        let glass = Color::srgba(0.8, 0.9, 1.0, 0.3);
+        let tinted = flag_tint(glass, VoxelFlags::CHARRED | VoxelFlags::FROZEN);
+        assert_eq!(tinted.alpha(), 0.3);
+    }
+}
@@ -4,56 +4,101 @@
 //!
 //! - **constants**: 常量定义（区块大小、渲染距离等）
 //! - **voxel_kind**: 体素类型定义（方块种类、属性、颜色）
+//! - **registry**: 体素定义注册表（按编号索引，从资源文件加载方块定义和网格形状并热重载）
 //! - **biome**: 生物群系（平原、森林、沙漠等）
 //! - **seed**: 世界种子与噪声生成器
 //! - **chunk**: 区块数据结构（区块坐标、体素存储、世界管理）
-//! - **terrain**: 地形生成器（程序化地形、洞穴、矿石、树木）
-//! - **mesh**: 网格构建（顶点去重、面剔除、占位符）
+//! - **collision**: 实体与体素的碰撞查询（包围盒重叠、逐轴扫掠）
+//! - **heightmap**: 列高度图（地下区块剔除）
+//! - **light**: 光照传播（天空光、方块光，烘焙进顶点颜色）
+//! - **raycast**: 体素射线检测（方块选取、视线，可选命中流体和花草）
+//! - **palette**: 调色板压缩存储（区块体素、标志位、变体）
+//! - **terrain**: 地形生成器（程序化地形、洞穴、矿石、树木、预制结构）
+//! - **mesh**: 网格构建（顶点去重、面剔除、交叉面片和非立方体形状、占位符）
+//! - **solid_mask**: 区块不透明位掩码（网格构建跳过被包围的区块、层和体素）
 //! - **loading**: 异步加载类型（任务队列、缓冲区）
 //! - **systems**: ECS系统函数（区块加载、卸载、渲染）
-//! - **materials**: 材质系统（不透明/透明材质）
+//! - **materials**: 区块材质与着色器（打包顶点、平面着色、云影）
 //! - **components**: 体素相关组件
 //! - **plugin**: Bevy插件
 //! - **flags**: 方块状态标志位系统
 //! - **change**: 方块变更记录系统
-//! - **domains**: 领域模块系统（温度、湿度、燃烧、相变等）
+//! - **snapshot**: 只读区块快照（Arc 共享，供 AI、光照、网络等只读系统与模拟并行读取）
+//! - **events**: 区块和方块事件（区块加载、卸载、网格重建，方块变化，模拟 tick），供玩法扩展读取
+//! - **domains**: 领域模块系统（温度、湿度、燃烧、相变、流体等）
+//! - **worldgen**: 世界生成配置（可从资源文件加载并热重载，叠加新建世界时选择的预设）
+//! - **regen**: 运行中重新生成区块（生成参数变化后在后台重新生成未修改的区块并替换网格）
+//! - **persistence**: 区块存档（区域文件读写、世界元数据）
+//! - **replay**: 回放录制格式（逐 tick 保存方块变更和观察者位置，回放时写回世界）
+//! - **pregen**: 无渲染的多线程地形预生成
+//! - **headless**: 无渲染的领域模拟（--headless-sim，输出温度、燃烧和方块变化汇总）
+//! - **profiling**: 模拟循环性能统计（tracing span、分阶段耗时）
+//! - **debug**: 区块调试渲染（区块边界、加载状态、世界网格）
+//! - **bench**: 性能基准工作负载（criterion 基准和 --bench-world 共用）
 
+pub mod bench;
 pub mod biome;
 pub mod change;
 pub mod chunk;
+pub mod collision;
 pub mod components;
 pub mod constants;
+pub mod debug;
 pub mod domains;
+pub mod events;
 pub mod flags;
+pub mod headless;
+pub mod heightmap;
+pub mod light;
 pub mod loading;
 pub mod materials;
 pub mod mesh;
 pub mod mesh_gen;
+pub mod palette;
+pub mod persistence;
 pub mod plugin;
+pub mod pregen;
+pub mod profiling;
+pub mod raycast;
+pub mod regen;
+pub mod registry;
+pub mod replay;
 pub mod seed;
+pub mod snapshot;
+pub mod solid_mask;
+pub mod sync;
 pub mod systems;
 pub mod terrain;
 pub mod voxel_kind;
+pub mod worldgen;
 
 // 重新导出常用类型，方便外部使用
 pub use biome::Biome;
 pub use change::BlockChange;
-pub use chunk::{ChunkData, ChunkMarker, ChunkPos, VoxelWorld};
+pub use chunk::{
+    ChunkData, ChunkEntities, ChunkIndex, ChunkLookup, ChunkLookupMut, ChunkMarker, ChunkPos, Chunks,
+    ChunksMut, VoxelWorld,
+};
 pub use components::Voxel;
 pub use constants::{CHUNK_SIZE, RENDER_DISTANCE, VERTICAL_RENDER_DISTANCE};
-pub use domains::{command::DomainCommand, DomainPlugin, SimulationSet};
+pub use domains::{clock::SimulationClock, command::DomainCommand, DomainPlugin, SimulationSet};
 pub use flags::VoxelFlags;
 pub use loading::{
-    ChunkLoadQueue, ChunkReplacementBuffer, CompletedChunk, ComputeMeshTask, MeshBuildInput,
-    NeighborEdges, PlaceholderEntities,
+    CancelToken, ChunkLoadQueue, ChunkReplacementBuffer, ChunkTasks, CompletedChunk,
+    MeshBuildInput, NeighborEdges, PendingChunk, PlaceholderEntities, RemeshScheduler,
+    RenderDistance, TaskOutput, TaskReporter, TaskResult,
 };
 pub use materials::ChunkMaterials;
 pub use mesh::create_placeholder_mesh;
-pub use mesh_gen::{build_chunk_mesh_async, generate_chunk_and_mesh_async};
+pub use mesh_gen::{build_chunk_mesh_async, generate_chunk_and_mesh_async, light_and_mesh_chunk};
 pub use plugin::VoxelPlugin;
-pub use seed::WorldSeed;
+pub use regen::ChunkRegenQueue;
+pub use registry::VoxelRegistry;
+pub use seed::{WorldSeed, XorShift32};
+pub use snapshot::{ChunkSnapshot, WorldSnapshot};
 pub use terrain::TerrainGenerator;
-pub use voxel_kind::{VoxelDef, VoxelKind, VoxelProperties};
+pub use voxel_kind::{FaceColors, VoxelDef, VoxelKind, VoxelProperties, VoxelShape};
+pub use worldgen::{GenPreset, GeneratorMode, TerrainShape, WorldGenConfig, WorldGenOptions};
 
 // ============================================================================
 // 辅助函数
@@ -0,0 +1,317 @@
+//! 调色板压缩存储
+//!
+//! 区块中的方块类型、标志位和变体通常只有少数几种不同的值。
+//! PalettedArray 把出现过的值记录在调色板中，每个位置只存调色板下标，
+//! 下标按 1/2/4/8/16 位紧密打包在 u64 中（位宽取 64 的因数，下标不会跨字存储）。
+//!
+//! 全部位置取同一个值时（全空气、全石头区块）只存这一个值，不分配打包数组。
+//!
+//! 写入新值时调色板只增不减，位宽不够时整体重新打包；
+//! 调用 compact() 可以移除不再使用的调色板项并在可能时退回单值存储。
+
+use std::fmt;
+
+/// 调色板压缩的定长数组
+#[derive(Clone)]
+pub struct PalettedArray<T> {
+    len: usize,
+    storage: Storage<T>,
+}
+
+#[derive(Clone)]
+enum Storage<T> {
+    /// 所有位置都是同一个值
+    Uniform(T),
+    /// 调色板 + 位打包的下标
+    Packed {
+        palette: Vec<T>,
+        bits: u32,
+        words: Vec<u64>,
+    },
+}
+
+impl<T: Copy + PartialEq> PalettedArray<T> {
+    /// 创建所有位置都为 value 的数组
+    pub fn filled(len: usize, value: T) -> Self {
+        Self {
+            len,
+            storage: Storage::Uniform(value),
+        }
+    }
+
+    /// 从普通数组构建（只有一种值时使用单值存储）
+    pub fn from_slice(values: &[T]) -> Self {
+        let Some(&first) = values.first() else {
+            return Self {
+                len: 0,
+                storage: Storage::Packed {
+                    palette: Vec::new(),
+                    bits: 1,
+                    words: Vec::new(),
+                },
+            };
+        };
+
+        let mut palette = vec![first];
+        let indices: Vec<usize> = values
+            .iter()
+            .map(|value| match palette.iter().position(|p| p == value) {
+                Some(index) => index,
+                None => {
+                    palette.push(*value);
+                    palette.len() - 1
+                }
+            })
+            .collect();
+
+        if palette.len() == 1 {
+            return Self::filled(values.len(), first);
+        }
+
+        let bits = bits_for(palette.len());
+        let mut words = vec![0; word_count(values.len(), bits)];
+        for (i, index) in indices.into_iter().enumerate() {
+            write_index(&mut words, bits, i, index);
+        }
+        Self {
+            len: values.len(),
+            storage: Storage::Packed {
+                palette,
+                bits,
+                words,
+            },
+        }
+    }
+
+    /// 元素个数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// 是否没有元素
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 读取指定位置的值，越界时 panic（与 Vec 下标行为一致）
+    #[inline]
+    pub fn get(&self, idx: usize) -> T {
+        assert!(idx < self.len, "index {idx} out of range for length {}", self.len);
+        match &self.storage {
+            Storage::Uniform(value) => *value,
+            Storage::Packed {
+                palette,
+                bits,
+                words,
+            } => palette[read_index(words, *bits, idx)],
+        }
+    }
+
+    /// 写入指定位置的值，越界时 panic
+    pub fn set(&mut self, idx: usize, value: T) {
+        assert!(idx < self.len, "index {idx} out of range for length {}", self.len);
+
+        if let Storage::Uniform(current) = self.storage {
+            if current == value {
+                return;
+            }
+            // 第一次出现第二种值：转为 1 位打包，原有位置都是下标 0
+            self.storage = Storage::Packed {
+                palette: vec![current],
+                bits: 1,
+                words: vec![0; word_count(self.len, 1)],
+            };
+        }
+
+        let Storage::Packed {
+            palette,
+            bits,
+            words,
+        } = &mut self.storage
+        else {
+            unreachable!();
+        };
+
+        let index = match palette.iter().position(|p| *p == value) {
+            Some(index) => index,
+            None => {
+                palette.push(value);
+                let needed = bits_for(palette.len());
+                if needed > *bits {
+                    *words = repack(words, *bits, needed, self.len);
+                    *bits = needed;
+                }
+                palette.len() - 1
+            }
+        };
+        write_index(words, *bits, idx, index);
+    }
+
+    /// 读取、修改并写回指定位置的值
+    pub fn update(&mut self, idx: usize, f: impl FnOnce(&mut T)) {
+        let mut value = self.get(idx);
+        f(&mut value);
+        self.set(idx, value);
+    }
+
+    /// 按顺序遍历所有值
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.len).map(|idx| self.get(idx))
+    }
+
+    /// 展开为普通数组
+    pub fn to_vec(&self) -> Vec<T> {
+        match &self.storage {
+            Storage::Uniform(value) => vec![*value; self.len],
+            Storage::Packed { .. } => self.iter().collect(),
+        }
+    }
+
+    /// 所有位置都为同一个值时返回该值（需要先 compact 才能识别打包存储中的单值情况）
+    pub fn uniform_value(&self) -> Option<T> {
+        match &self.storage {
+            Storage::Uniform(value) => Some(*value),
+            Storage::Packed { .. } => None,
+        }
+    }
+
+    /// 调色板中是否有满足条件的值，用于快速排除整个数组
+    /// （调色板可能保留已被覆盖的旧值，返回 true 时仍需逐个检查）
+    pub fn may_contain(&self, mut predicate: impl FnMut(T) -> bool) -> bool {
+        match &self.storage {
+            Storage::Uniform(value) => predicate(*value),
+            Storage::Packed { palette, .. } => palette.iter().any(|value| predicate(*value)),
+        }
+    }
+
+    /// 移除不再使用的调色板项，只剩一种值时退回单值存储
+    pub fn compact(&mut self) {
+        if let Storage::Packed { .. } = self.storage {
+            *self = Self::from_slice(&self.to_vec());
+        }
+    }
+
+    /// 堆上占用的字节数
+    pub fn heap_size(&self) -> usize {
+        match &self.storage {
+            Storage::Uniform(_) => 0,
+            Storage::Packed { palette, words, .. } => {
+                palette.capacity() * size_of::<T>() + words.capacity() * size_of::<u64>()
+            }
+        }
+    }
+}
+
+impl<T: Copy + PartialEq> PartialEq for PalettedArray<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Copy + PartialEq + fmt::Debug> fmt::Debug for PalettedArray<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.storage {
+            Storage::Uniform(value) => f
+                .debug_struct("PalettedArray")
+                .field("len", &self.len)
+                .field("uniform", value)
+                .finish(),
+            Storage::Packed { palette, bits, .. } => f
+                .debug_struct("PalettedArray")
+                .field("len", &self.len)
+                .field("palette", palette)
+                .field("bits", bits)
+                .finish(),
+        }
+    }
+}
+
+/// 容纳 palette_len 个下标所需的位宽（1/2/4/8/16）
+fn bits_for(palette_len: usize) -> u32 {
+    let needed = usize::BITS - (palette_len.max(2) - 1).leading_zeros();
+    needed.next_power_of_two()
+}
+
+fn word_count(len: usize, bits: u32) -> usize {
+    let per_word = (u64::BITS / bits) as usize;
+    len.div_ceil(per_word)
+}
+
+#[inline]
+fn read_index(words: &[u64], bits: u32, idx: usize) -> usize {
+    let per_word = (u64::BITS / bits) as usize;
+    let shift = (idx % per_word) as u32 * bits;
+    let mask = (1u64 << bits) - 1;
+    ((words[idx / per_word] >> shift) & mask) as usize
+}
+
+#[inline]
+fn write_index(words: &mut [u64], bits: u32, idx: usize, index: usize) {
+    let per_word = (u64::BITS / bits) as usize;
+    let shift = (idx % per_word) as u32 * bits;
+    let mask = (1u64 << bits) - 1;
+    let word = &mut words[idx / per_word];
+    *word = (*word & !(mask << shift)) | ((index as u64) << shift);
+}
+
+/// 以新位宽重新打包所有下标
+fn repack(words: &[u64], old_bits: u32, new_bits: u32, len: usize) -> Vec<u64> {
+    let mut packed = vec![0; word_count(len, new_bits)];
+    for idx in 0..len {
+        write_index(&mut packed, new_bits, idx, read_index(words, old_bits, idx));
+    }
+    packed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_until_second_value() {
+        let mut array = PalettedArray::filled(4096, 7u8);
+        assert_eq!(array.uniform_value(), Some(7));
+        assert_eq!(array.heap_size(), 0);
+
+        array.set(10, 7);
+        assert_eq!(array.uniform_value(), Some(7));
+
+        array.set(10, 3);
+        assert_eq!(array.uniform_value(), None);
+        assert_eq!(array.get(10), 3);
+        assert_eq!(array.get(11), 7);
+    }
+
+    #[test]
+    fn test_grows_bit_width_and_keeps_values() {
+        let mut array = PalettedArray::filled(4096, 0u16);
+        for idx in 0..4096 {
+            array.set(idx, (idx % 300) as u16);
+        }
+        for idx in 0..4096 {
+            assert_eq!(array.get(idx), (idx % 300) as u16);
+        }
+        assert_eq!(array, PalettedArray::from_slice(&array.to_vec()));
+    }
+
+    #[test]
+    fn test_compact_returns_to_uniform() {
+        let mut array = PalettedArray::filled(64, 'a');
+        array.set(5, 'b');
+        array.set(5, 'a');
+        assert_eq!(array.uniform_value(), None);
+
+        array.compact();
+        assert_eq!(array.uniform_value(), Some('a'));
+    }
+
+    #[test]
+    fn test_bits_for() {
+        assert_eq!(bits_for(1), 1);
+        assert_eq!(bits_for(2), 1);
+        assert_eq!(bits_for(3), 2);
+        assert_eq!(bits_for(5), 4);
+        assert_eq!(bits_for(17), 8);
+        assert_eq!(bits_for(257), 16);
+    }
+}
@@ -0,0 +1,668 @@
+//! 区块存档
+//!
+//! 世界按区域（REGION_SIZE³ 个区块）存储，每个区域一个文件：
+//! `<存档目录>/region/r.<x>.<y>.<z>.vxr`。
+//!
+//! ## 文件格式（小端序）
+//!
+//! - 文件头：魔数 `VXRG`、版本号（u16）、区块数量（u32）
+//! - 每个区块：区域内坐标（3 × u8），随后依次是体素编号、变体、标志位三段游程编码数据
+//! - 每段游程编码：段数（u32），每段为重复次数（u16）和值（u16）
+//!
+//! 存档目录下的 `world.ron` 记录世界名称、种子和生成选项（见 [`WorldMeta`]），
+//! 重新打开同名世界时沿用这些参数。
+//!
+//! 游戏中加载区块时先在 [`ActiveWorld`] 的存档里查找（经由 [`RegionCache`]，
+//! 每个区域文件只读取一次），存档中没有的区块才从种子生成，
+//! 因此 `--pregen` 预生成的世界直接从磁盘加载。
+//!
+//! ## 崩溃保护
+//!
+//! 本模块提供原子写入和损坏恢复，但不负责决定何时保存：区域文件目前只由 `--pregen`
+//! 通过 [`WorldStorage::save_region`] 写入，游戏运行时不会自动保存区块，
+//! 玩家修改过的区块在本次运行中保留在内存里（见 [`UnloadedChunks`]）。
+//! 运行时写入的是存档目录下的 `.ron` 文件（世界元数据、状态、路标等），它们都经过 [`write_atomic`]。
+//!
+//! 写入的文件先写到同目录下的 `<文件名>.tmp` 并刷到磁盘，再改名覆盖原文件，
+//! 写到一半时崩溃不会留下不完整的文件。保存区域时原有的区域文件改名为 `<文件名>.bak`，
+//! 保留上一次完整的存档；读取时区域文件缺失或损坏（例如被截断）则改为读取备份，
+//! 损坏的文件改名为 `<文件名>.corrupt` 留作排查。
+//!
+//! [`UnloadedChunks`]: crate::voxel::loading::UnloadedChunks
+
+use bevy::log::warn;
+use bevy::prelude::{FromWorld, Resource, World};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::voxel::chunk::{ChunkData, ChunkPos};
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::palette::PalettedArray;
+use crate::voxel::seed::WorldSeed;
+use crate::voxel::voxel_kind::VoxelKind;
+use crate::voxel::worldgen::WorldGenOptions;
+
+/// 存档根目录（相对于工作目录）
+pub const SAVES_DIR: &str = "saves";
+
+/// 世界元数据文件名（位于存档目录下）
+pub const WORLD_META_FILE: &str = "world.ron";
+
+/// 区域边长（单位：区块）
+pub const REGION_SIZE: i32 = 8;
+
+/// 区域文件魔数
+const REGION_MAGIC: &[u8; 4] = b"VXRG";
+
+/// 区域文件格式版本
+const REGION_VERSION: u16 = 1;
+
+/// 区域缓存最多保留的区域数
+const REGION_CACHE_CAPACITY: usize = 64;
+
+/// 写入中的临时文件扩展名
+const TEMP_EXTENSION: &str = "tmp";
+/// 上一次完整存档的备份扩展名
+const BACKUP_EXTENSION: &str = "bak";
+/// 读取失败的区域文件扩展名
+const CORRUPT_EXTENSION: &str = "corrupt";
+
+/// 区域坐标 - 每个区域包含 REGION_SIZE³ 个区块
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegionPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl RegionPos {
+    /// 区块所在的区域
+    pub fn from_chunk(chunk_pos: ChunkPos) -> Self {
+        Self {
+            x: chunk_pos.x.div_euclid(REGION_SIZE),
+            y: chunk_pos.y.div_euclid(REGION_SIZE),
+            z: chunk_pos.z.div_euclid(REGION_SIZE),
+        }
+    }
+
+    /// 区域内第一个区块的坐标
+    pub fn origin_chunk(&self) -> ChunkPos {
+        ChunkPos::new(
+            self.x * REGION_SIZE,
+            self.y * REGION_SIZE,
+            self.z * REGION_SIZE,
+        )
+    }
+
+    /// 区域文件名
+    fn file_name(&self) -> String {
+        format!("r.{}.{}.{}.vxr", self.x, self.y, self.z)
+    }
+}
+
+/// 存档读写错误
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("无法读写区域文件: {0}")]
+    Io(#[from] io::Error),
+    #[error("区域文件格式错误: {0}")]
+    Corrupt(&'static str),
+    #[error("不支持的区域文件版本: {0}")]
+    UnsupportedVersion(u16),
+    #[error("未知的方块编号: {0}")]
+    UnknownVoxel(u16),
+    #[error("世界信息解析失败: {0}")]
+    MetaParse(#[from] ron::error::SpannedError),
+    #[error("世界信息序列化失败: {0}")]
+    MetaSerialize(#[from] ron::Error),
+}
+
+/// 世界元数据 - 创建世界时的名称和生成参数
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldMeta {
+    pub name: String,
+    pub seed: u32,
+    #[serde(default)]
+    pub options: WorldGenOptions,
+}
+
+/// 世界存档 - 负责区域文件的读写
+#[derive(Debug, Clone)]
+pub struct WorldStorage {
+    root: PathBuf,
+}
+
+impl WorldStorage {
+    /// 使用指定目录作为存档根目录
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// 指定种子的默认存档目录（`saves/<种子>`）
+    pub fn for_seed(seed: u32) -> Self {
+        Self::new(Path::new(SAVES_DIR).join(seed.to_string()))
+    }
+
+    /// 命名世界的存档目录（`saves/<世界名>`，见 [`world_folder_name`]）
+    pub fn for_world(name: &str) -> Self {
+        Self::new(Path::new(SAVES_DIR).join(world_folder_name(name)))
+    }
+
+    /// 存档根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 区域文件路径
+    pub fn region_path(&self, region: RegionPos) -> PathBuf {
+        self.root.join("region").join(region.file_name())
+    }
+
+    /// 区域备份文件路径
+    pub fn region_backup_path(&self, region: RegionPos) -> PathBuf {
+        with_extension_suffix(&self.region_path(region), BACKUP_EXTENSION)
+    }
+
+    /// 读取区域内的所有区块，区域文件和备份都不存在时返回空表
+    ///
+    /// 区域文件缺失（保存到一半时崩溃）或损坏时改为读取备份；
+    /// 损坏的文件改名为 `.corrupt`，下次保存时不会把它当作备份
+    pub fn load_region(
+        &self,
+        region: RegionPos,
+    ) -> Result<HashMap<ChunkPos, ChunkData>, StorageError> {
+        let path = self.region_path(region);
+        let damaged = match read_region_file(region, &path) {
+            Ok(Some(chunks)) => return Ok(chunks),
+            Ok(None) => None,
+            Err(err @ (StorageError::Corrupt(_) | StorageError::UnknownVoxel(_))) => Some(err),
+            Err(err) => return Err(err),
+        };
+
+        match read_region_file(region, &self.region_backup_path(region)) {
+            Ok(Some(chunks)) => {
+                match &damaged {
+                    Some(err) => {
+                        warn!(
+                            "Region file {} is damaged ({err}), using its backup",
+                            path.display()
+                        );
+                        fs::rename(&path, with_extension_suffix(&path, CORRUPT_EXTENSION))?;
+                    }
+                    None => warn!(
+                        "Region file {} is missing, using its backup",
+                        path.display()
+                    ),
+                }
+                Ok(chunks)
+            }
+            Ok(None) => damaged.map_or(Ok(HashMap::new()), Err),
+            Err(backup_err) => Err(damaged.unwrap_or(backup_err)),
+        }
+    }
+
+    /// 写入整个区域，原有文件保留为备份
+    pub fn save_region(
+        &self,
+        region: RegionPos,
+        chunks: &HashMap<ChunkPos, ChunkData>,
+    ) -> Result<(), StorageError> {
+        let path = self.region_path(region);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = write_temp(&path, &encode_region(region, chunks))?;
+        // 两次改名之间崩溃时只缺少区域文件，读取时会使用备份
+        match fs::rename(&path, self.region_backup_path(region)) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        fs::rename(temp, path)?;
+        Ok(())
+    }
+
+    /// 读取世界元数据，还没有保存过时返回 None
+    pub fn load_meta(&self) -> Result<Option<WorldMeta>, StorageError> {
+        match fs::read_to_string(self.root.join(WORLD_META_FILE)) {
+            Ok(text) => Ok(Some(ron::from_str(&text)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 写入世界元数据（覆盖原有文件）
+    pub fn save_meta(&self, meta: &WorldMeta) -> Result<(), StorageError> {
+        fs::create_dir_all(&self.root)?;
+        let text = ron::ser::to_string_pretty(meta, ron::ser::PrettyConfig::default())?;
+        write_atomic(&self.root.join(WORLD_META_FILE), text.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// 读取并解析区域文件，文件不存在时返回 None
+fn read_region_file(
+    region: RegionPos,
+    path: &Path,
+) -> Result<Option<HashMap<ChunkPos, ChunkData>>, StorageError> {
+    match fs::read(path) {
+        Ok(bytes) => decode_region(region, &bytes).map(Some),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// 原子地写入文件：先写入临时文件并刷到磁盘，再改名覆盖目标文件
+///
+/// 写到一半时崩溃只会留下临时文件，目标文件保持原来的完整内容
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = write_temp(path, contents)?;
+    fs::rename(temp, path)
+}
+
+/// 把内容写入 `path` 旁边的临时文件并刷到磁盘，返回临时文件路径
+fn write_temp(path: &Path, contents: &[u8]) -> io::Result<PathBuf> {
+    let temp = with_extension_suffix(path, TEMP_EXTENSION);
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(temp)
+}
+
+/// 在文件名后追加一个扩展名（`r.0.0.0.vxr` -> `r.0.0.0.vxr.bak`）
+fn with_extension_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 世界名对应的目录名：字母、数字、`-` 和 `_` 原样保留，其余字符替换为 `_`
+pub fn world_folder_name(name: &str) -> String {
+    let folder: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if folder.is_empty() {
+        "world".to_string()
+    } else {
+        folder
+    }
+}
+
+/// 当前游玩的世界及其存档目录
+///
+/// 默认使用种子对应的 `saves/<种子>`（命令行指定种子或联机时），
+/// 在新建世界界面创建世界后指向 `saves/<世界名>`
+#[derive(Resource, Debug, Clone)]
+pub struct ActiveWorld {
+    pub meta: WorldMeta,
+    pub storage: WorldStorage,
+}
+
+impl FromWorld for ActiveWorld {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world
+            .get_resource::<WorldSeed>()
+            .map_or(WorldSeed::default().seed, |seed| seed.seed);
+        let options = world
+            .get_resource::<WorldGenOptions>()
+            .copied()
+            .unwrap_or_default();
+        Self {
+            meta: WorldMeta {
+                name: seed.to_string(),
+                seed,
+                options,
+            },
+            storage: WorldStorage::for_seed(seed),
+        }
+    }
+}
+
+/// 读取后的区域内容，区域文件和备份都无法读取时为空表
+type CachedRegion = Arc<OnceLock<HashMap<ChunkPos, ChunkData>>>;
+
+/// 区域文件缓存 - 区块加载任务共享，同一区域文件只从磁盘读取一次
+///
+/// 按区域文件路径索引，切换世界后不会读到旧世界的区域。
+/// 多个任务同时请求同一区域时只有一个读取文件，其余等待它完成；
+/// 超过容量时丢弃最早读取的区域
+#[derive(Resource, Clone, Default)]
+pub struct RegionCache {
+    inner: Arc<Mutex<RegionCacheInner>>,
+}
+
+#[derive(Default)]
+struct RegionCacheInner {
+    regions: HashMap<PathBuf, CachedRegion>,
+    /// 读取顺序，用于淘汰
+    order: VecDeque<PathBuf>,
+}
+
+impl RegionCache {
+    /// 读取存档中保存的区块，存档中没有时返回 None
+    ///
+    /// 区域文件无法读取时记录警告并当作空区域，区块改为从种子生成
+    pub fn load_chunk(&self, storage: &WorldStorage, chunk_pos: ChunkPos) -> Option<ChunkData> {
+        let region = RegionPos::from_chunk(chunk_pos);
+        let path = storage.region_path(region);
+        let cached = {
+            let mut inner = self.inner.lock().unwrap();
+            match inner.regions.get(&path) {
+                Some(cached) => cached.clone(),
+                None => {
+                    if inner.order.len() >= REGION_CACHE_CAPACITY
+                        && let Some(oldest) = inner.order.pop_front()
+                    {
+                        inner.regions.remove(&oldest);
+                    }
+                    inner.order.push_back(path.clone());
+                    inner.regions.entry(path.clone()).or_default().clone()
+                }
+            }
+        };
+
+        // 在锁外读取文件，其他区域的请求不必等待
+        let chunks = cached.get_or_init(|| {
+            storage.load_region(region).unwrap_or_else(|err| {
+                warn!("Failed to read region file {}: {err}", path.display());
+                HashMap::new()
+            })
+        });
+        chunks.get(&chunk_pos).cloned()
+    }
+}
+
+// ============================================================================
+// 编码
+// ============================================================================
+
+/// 将区域内的区块编码为区域文件内容（不属于该区域的区块会被忽略）
+pub fn encode_region(region: RegionPos, chunks: &HashMap<ChunkPos, ChunkData>) -> Vec<u8> {
+    let origin = region.origin_chunk();
+    let mut entries: Vec<_> = chunks
+        .iter()
+        .filter(|(chunk_pos, _)| RegionPos::from_chunk(**chunk_pos) == region)
+        .collect();
+    // 按坐标排序，相同内容总是生成相同的文件
+    entries.sort_by_key(|(chunk_pos, _)| (chunk_pos.x, chunk_pos.y, chunk_pos.z));
+
+    let mut out = Vec::new();
+    out.extend_from_slice(REGION_MAGIC);
+    out.extend_from_slice(&REGION_VERSION.to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+
+    for (chunk_pos, chunk) in entries {
+        out.push((chunk_pos.x - origin.x) as u8);
+        out.push((chunk_pos.y - origin.y) as u8);
+        out.push((chunk_pos.z - origin.z) as u8);
+        write_chunk(&mut out, chunk);
+    }
+    out
+}
+
+/// 写入区块的体素编号、变体、标志位三段游程编码数据（网络同步的区块快照也使用这一格式）
+pub(crate) fn write_chunk(out: &mut Vec<u8>, chunk: &ChunkData) {
+    write_runs(out, chunk.voxels.iter().map(|kind| kind.id() as u16));
+    write_runs(out, chunk.variant.iter().map(|v| v as u16));
+    write_runs(out, chunk.flags.iter().map(|flags| flags.bits()));
+}
+
+/// 游程编码写入一段数据
+fn write_runs(out: &mut Vec<u8>, values: impl Iterator<Item = u16>) {
+    let mut runs: Vec<(u16, u16)> = Vec::new();
+    for value in values {
+        match runs.last_mut() {
+            Some((count, last)) if *last == value && *count < u16::MAX => *count += 1,
+            _ => runs.push((1, value)),
+        }
+    }
+
+    out.extend_from_slice(&(runs.len() as u32).to_le_bytes());
+    for (count, value) in runs {
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+// ============================================================================
+// 解码
+// ============================================================================
+
+/// 解析区域文件内容
+pub fn decode_region(
+    region: RegionPos,
+    bytes: &[u8],
+) -> Result<HashMap<ChunkPos, ChunkData>, StorageError> {
+    let mut reader = ByteReader::new(bytes);
+    if reader.take(4)? != REGION_MAGIC {
+        return Err(StorageError::Corrupt("文件头魔数不匹配"));
+    }
+    let version = reader.u16()?;
+    if version != REGION_VERSION {
+        return Err(StorageError::UnsupportedVersion(version));
+    }
+
+    let origin = region.origin_chunk();
+    let count = reader.u32()?;
+    let mut chunks = HashMap::new();
+    for _ in 0..count {
+        let local = reader.take(3)?;
+        if local.iter().any(|&v| v as i32 >= REGION_SIZE) {
+            return Err(StorageError::Corrupt("区块坐标超出区域范围"));
+        }
+        let chunk_pos = ChunkPos::new(
+            origin.x + local[0] as i32,
+            origin.y + local[1] as i32,
+            origin.z + local[2] as i32,
+        );
+
+        chunks.insert(chunk_pos, reader.chunk()?);
+    }
+
+    Ok(chunks)
+}
+
+/// 体素编号转换为方块类型
+pub(crate) fn voxel_from_id(value: u16) -> Result<VoxelKind, StorageError> {
+    u8::try_from(value)
+        .ok()
+        .and_then(VoxelKind::from_id)
+        .ok_or(StorageError::UnknownVoxel(value))
+}
+
+/// 顺序读取字节，数据不足时返回 Corrupt 错误
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// 尚未读取的字节数
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], StorageError> {
+        if self.bytes.len() < len {
+            return Err(StorageError::Corrupt("数据被截断"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, StorageError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, StorageError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, StorageError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub(crate) fn i32(&mut self) -> Result<i32, StorageError> {
+        Ok(self.u32()? as i32)
+    }
+
+    pub(crate) fn f32(&mut self) -> Result<f32, StorageError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    /// 读取 write_chunk 写入的区块数据
+    pub(crate) fn chunk(&mut self) -> Result<ChunkData, StorageError> {
+        let voxels = self
+            .runs()?
+            .into_iter()
+            .map(voxel_from_id)
+            .collect::<Result<Vec<_>, _>>()?;
+        let variant: Vec<u8> = self.runs()?.into_iter().map(|value| value as u8).collect();
+        let flags: Vec<VoxelFlags> = self
+            .runs()?
+            .into_iter()
+            .map(VoxelFlags::from_bits_truncate)
+            .collect();
+
+        let mut chunk = ChunkData::new();
+        chunk.voxels = PalettedArray::from_slice(&voxels);
+        chunk.variant = PalettedArray::from_slice(&variant);
+        chunk.flags = PalettedArray::from_slice(&flags);
+        Ok(chunk)
+    }
+
+    /// 读取一段游程编码数据，展开后必须正好是一个区块的体素数
+    fn runs(&mut self) -> Result<Vec<u16>, StorageError> {
+        let run_count = self.u32()?;
+        let mut values = Vec::with_capacity(ChunkData::VOXEL_COUNT);
+        for _ in 0..run_count {
+            let count = self.u16()? as usize;
+            let value = self.u16()?;
+            if values.len() + count > ChunkData::VOXEL_COUNT {
+                return Err(StorageError::Corrupt("区块数据长度错误"));
+            }
+            values.resize(values.len() + count, value);
+        }
+        if values.len() != ChunkData::VOXEL_COUNT {
+            return Err(StorageError::Corrupt("区块数据长度错误"));
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_folder_name() {
+        assert_eq!(world_folder_name("My World"), "My_World");
+        assert_eq!(world_folder_name("  岛屿-2 "), "岛屿-2");
+        assert_eq!(world_folder_name("../saves"), "___saves");
+        assert_eq!(world_folder_name("   "), "world");
+    }
+
+    #[test]
+    fn test_voxel_ids_round_trip() {
+        for kind in VoxelKind::ALL {
+            assert_eq!(VoxelKind::from_id(kind.id()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn test_region_round_trip() {
+        let region = RegionPos::from_chunk(ChunkPos::new(-3, 1, 9));
+        assert_eq!(region, RegionPos { x: -1, y: 0, z: 1 });
+
+        let mut chunk = ChunkData::new();
+        chunk.set(1, 2, 3, VoxelKind::Stone);
+        chunk.set(15, 15, 15, VoxelKind::Water);
+        chunk.variant.set(ChunkData::index(15, 15, 15), 5);
+        chunk.flags.set(ChunkData::index(1, 2, 3), VoxelFlags::HOT);
+
+        let mut chunks = HashMap::new();
+        chunks.insert(ChunkPos::new(-3, 1, 9), chunk.clone());
+        // 不属于该区域的区块不会被写入
+        chunks.insert(ChunkPos::new(0, 0, 0), ChunkData::new());
+
+        let bytes = encode_region(region, &chunks);
+        let decoded = decode_region(region, &bytes).unwrap();
+        assert_eq!(decoded.len(), 1);
+
+        let restored = &decoded[&ChunkPos::new(-3, 1, 9)];
+        assert_eq!(restored.voxels, chunk.voxels);
+        assert_eq!(restored.variant, chunk.variant);
+        assert_eq!(restored.flags, chunk.flags);
+    }
+
+    #[test]
+    fn test_truncated_region_is_rejected() {
+        let region = RegionPos { x: 0, y: 0, z: 0 };
+        let mut chunks = HashMap::new();
+        chunks.insert(ChunkPos::new(0, 0, 0), ChunkData::new());
+
+        let bytes = encode_region(region, &chunks);
+        assert!(matches!(
+            decode_region(region, &bytes[..bytes.len() - 1]),
+            Err(StorageError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn test_damaged_region_falls_back_to_backup() {
+        let root = std::env::temp_dir().join(format!("voxworld-backup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let storage = WorldStorage::new(&root);
+        let region = RegionPos { x: 0, y: 0, z: 0 };
+        let chunk_pos = ChunkPos::new(1, 2, 3);
+
+        let mut first = HashMap::new();
+        first.insert(chunk_pos, ChunkData::new());
+        storage.save_region(region, &first).unwrap();
+        let mut second = first.clone();
+        second
+            .get_mut(&chunk_pos)
+            .unwrap()
+            .set(0, 0, 0, VoxelKind::Stone);
+        storage.save_region(region, &second).unwrap();
+        assert!(storage.region_backup_path(region).exists());
+        assert_eq!(
+            storage.load_region(region).unwrap()[&chunk_pos].get(0, 0, 0),
+            VoxelKind::Stone
+        );
+
+        // 截断的区域文件改为读取上一次的存档
+        let path = storage.region_path(region);
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        let restored = storage.load_region(region).unwrap();
+        assert_eq!(restored[&chunk_pos].get(0, 0, 0), VoxelKind::Air);
+        assert!(!path.exists());
+        assert!(with_extension_suffix(&path, CORRUPT_EXTENSION).exists());
+
+        // 损坏的文件不会在下次保存时替换掉备份
+        storage.save_region(region, &second).unwrap();
+        let backup = read_region_file(region, &storage.region_backup_path(region)).unwrap();
+        assert_eq!(backup.unwrap()[&chunk_pos].get(0, 0, 0), VoxelKind::Air);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,15 +1,44 @@
 //! 体素系统插件
 
+use bevy::camera::visibility::VisibilitySystems;
 use bevy::prelude::*;
 
-use crate::voxel::chunk::VoxelWorld;
+use crate::voxel::chunk::{ChunkIndex, VoxelWorld};
+use crate::voxel::debug::{
+    draw_chunk_borders, draw_world_grid, toggle_chunk_debug, ChunkDebugSettings,
+};
+use crate::voxel::domains::thermal::ThermalTestPlugin;
 use crate::voxel::domains::DomainPlugin;
-use crate::voxel::loading::{ChunkLoadQueue, ChunkReplacementBuffer, PlaceholderEntities};
-use crate::voxel::materials::setup_materials;
+use crate::voxel::events::{ChunkLoaded, ChunkRemeshed, ChunkUnloaded};
+use crate::voxel::light::{relight_chunks, LightUpdates};
+use crate::voxel::loading::{
+    ChunkLoadQueue, ChunkReplacementBuffer, ChunkTasks, PlaceholderEntities, RemeshScheduler,
+    RenderDistance, UnloadedChunks,
+};
+use crate::voxel::materials::{setup_materials, ChunkMaterial};
+use crate::voxel::persistence::{ActiveWorld, RegionCache};
+use crate::voxel::profiling::{roll_up_stage_timings, StageTimings};
+use crate::voxel::regen::ChunkRegenPlugin;
+use crate::voxel::registry::{
+    apply_block_definitions, load_block_definitions, BlockDefinitions, BlockDefinitionsLoader,
+};
 use crate::voxel::seed::WorldSeed;
+use crate::voxel::snapshot::track_snapshot_chunks;
 use crate::voxel::systems::{
-    apply_chunk_replacements, cleanup_orphan_placeholders, handle_completed_mesh_tasks,
-    process_chunk_unload, spawn_batch_placeholders, spawn_mesh_tasks, update_chunk_loading,
+    apply_chunk_replacements, apply_remesh_results, cleanup_orphan_placeholders,
+    cull_chunk_visibility, dispatch_remesh_tasks, process_chunk_unload, receive_generated_chunks,
+    restore_unloaded_chunks, settle_arrived_chunks, spawn_batch_placeholders, spawn_mesh_tasks,
+    stash_modified_chunks, update_chunk_loading,
+};
+use crate::voxel::terrain::digest::world_digest_debug_system;
+use crate::voxel::terrain::{sync_shared_terrain, SharedTerrain};
+use crate::voxel::terrain::structures::{
+    apply_structure_templates, load_structure_templates, StructureRegistry, StructureTemplate,
+    StructureTemplateLoader,
+};
+use crate::voxel::worldgen::{
+    apply_worldgen_config, load_worldgen_config, WorldGenConfig, WorldGenConfigLoader,
+    WorldGenOptions,
 };
 
 /// 体素系统插件 - 负责注册体素相关的资源和系统
@@ -18,25 +47,91 @@ pub struct VoxelPlugin;
 impl Plugin for VoxelPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<VoxelWorld>()
+            .init_resource::<ChunkIndex>()
             .init_resource::<WorldSeed>()
+            .init_resource::<WorldGenOptions>()
+            // 依赖种子和生成选项
+            .init_resource::<ActiveWorld>()
+            .init_resource::<RegionCache>()
             .init_resource::<ChunkLoadQueue>()
+            .init_resource::<ChunkTasks>()
+            .init_resource::<RemeshScheduler>()
+            .init_resource::<RenderDistance>()
             .init_resource::<ChunkReplacementBuffer>()
             .init_resource::<PlaceholderEntities>()
-            .add_systems(Startup, setup_materials)
+            .init_resource::<UnloadedChunks>()
+            .init_resource::<ChunkDebugSettings>()
+            .init_resource::<StageTimings>()
+            .init_resource::<LightUpdates>()
+            // 供玩法扩展读取的区块事件
+            .add_message::<ChunkLoaded>()
+            .add_message::<ChunkUnloaded>()
+            .add_message::<ChunkRemeshed>()
+            .add_plugins(MaterialPlugin::<ChunkMaterial>::default())
+            .init_resource::<WorldGenConfig>()
+            .init_asset::<WorldGenConfig>()
+            .init_asset_loader::<WorldGenConfigLoader>()
+            .init_resource::<StructureRegistry>()
+            .init_asset::<StructureTemplate>()
+            .init_asset_loader::<StructureTemplateLoader>()
+            .init_asset::<BlockDefinitions>()
+            .init_asset_loader::<BlockDefinitionsLoader>()
+            // 依赖种子、生成配置和结构注册表，必须在它们之后初始化
+            .init_resource::<SharedTerrain>()
+            .add_systems(
+                Startup,
+                (
+                    setup_materials,
+                    load_worldgen_config,
+                    load_structure_templates,
+                    load_block_definitions,
+                ),
+            )
             .add_systems(
                 Update,
                 (
+                    apply_worldgen_config,
+                    apply_structure_templates,
+                    apply_block_definitions,
+                    sync_shared_terrain,
                     update_chunk_loading,
                     spawn_batch_placeholders,
+                    restore_unloaded_chunks,
                     spawn_mesh_tasks,
-                    handle_completed_mesh_tasks,
+                    receive_generated_chunks,
                     apply_chunk_replacements,
+                    settle_arrived_chunks,
+                    stash_modified_chunks,
                     process_chunk_unload,
+                    track_snapshot_chunks,
                     cleanup_orphan_placeholders,
+                    relight_chunks,
+                    dispatch_remesh_tasks,
+                    apply_remesh_results,
                 )
                     .chain(),
             )
-            // 注册领域系统（温度、湿度、燃烧等物理模拟）
-            .add_plugins(DomainPlugin);
+            .add_systems(Update, world_digest_debug_system)
+            // 本帧所有阶段（包括固定时间步）都跑完后再汇总
+            .add_systems(Last, roll_up_stage_timings)
+            .add_systems(
+                Update,
+                (toggle_chunk_debug, (draw_chunk_borders, draw_world_grid)).chain(),
+            )
+            // 视锥剔除需要当前帧的视锥，且要在可见性传播之前生效
+            .add_systems(
+                PostUpdate,
+                cull_chunk_visibility
+                    .after(VisibilitySystems::UpdateFrusta)
+                    .before(VisibilitySystems::VisibilityPropagate),
+            )
+            // 生成参数变化后在后台重新生成未修改的区块
+            .add_plugins(ChunkRegenPlugin)
+            // 注册领域系统（温度、湿度、燃烧等物理模拟）和交互式温度测试工具
+            .add_plugins((DomainPlugin, ThermalTestPlugin));
+
+        // 脚本规则从资源目录加载，无渲染模拟没有资源服务器，所以不在 DomainPlugin 中注册
+        #[cfg(feature = "scripting")]
+        app.add_plugins(crate::voxel::domains::script::ScriptRulePlugin);
     }
 }
@@ -0,0 +1,224 @@
+//! 无渲染的地形预生成
+//!
+//! 使用 `--pregen <半径>` 启动时不创建窗口和渲染器，直接生成原点周围水平半径
+//! （单位：区块）内的全部区块并写入存档。区块按区域分组，工作线程每次领取一个
+//! 完整区域，生成后整体写入区域文件，线程之间不需要共享任何可变状态。
+//! 存档中已存在的区块会被保留，不会被重新生成的地形覆盖。
+//! 之后用同一种子进入游戏时，这些区块直接从区域文件加载，不再重新生成。
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::voxel::chunk::ChunkPos;
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::persistence::{RegionPos, StorageError, WorldStorage};
+use crate::voxel::seed::WorldSeed;
+use crate::voxel::terrain::structures::{StructureTemplate, STRUCTURES_FOLDER};
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::worldgen::{WorldGenConfig, WORLDGEN_CONFIG_PATH};
+
+/// 资源目录（与 Bevy AssetServer 的默认目录一致）
+pub const ASSETS_DIR: &str = "assets";
+
+/// 树冠等地表以上结构预留的高度（方块）
+const SURFACE_FEATURE_MARGIN: i32 = 8;
+
+/// 预生成参数
+#[derive(Debug, Clone)]
+pub struct PregenOptions {
+    /// 水平半径（单位：区块）
+    pub radius: i32,
+    /// 世界种子
+    pub seed: u32,
+    /// 存档位置
+    pub storage: WorldStorage,
+    /// 工作线程数
+    pub threads: usize,
+}
+
+/// 预生成结果统计
+#[derive(Debug, Clone, Copy)]
+pub struct PregenSummary {
+    pub regions: usize,
+    pub chunks: usize,
+    pub elapsed: Duration,
+}
+
+/// 从资源目录读取世界生成配置，文件缺失或解析失败时使用默认值
+pub fn read_worldgen_config(assets_dir: &Path) -> WorldGenConfig {
+    let path = assets_dir.join(WORLDGEN_CONFIG_PATH);
+    match fs::read(&path) {
+        Ok(bytes) => WorldGenConfig::from_ron(&bytes).unwrap_or_else(|err| {
+            eprintln!("[pregen] {} 解析失败，使用默认配置: {err}", path.display());
+            WorldGenConfig::default()
+        }),
+        Err(_) => WorldGenConfig::default(),
+    }
+}
+
+/// 从资源目录读取全部结构模板（按名称排序，与游戏内的注册表顺序一致）
+pub fn read_structure_templates(assets_dir: &Path) -> Vec<StructureTemplate> {
+    let Ok(entries) = fs::read_dir(assets_dir.join(STRUCTURES_FOLDER)) else {
+        return Vec::new();
+    };
+
+    let mut templates = Vec::new();
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if !path.to_string_lossy().ends_with(".structure.ron") {
+            continue;
+        }
+        match fs::read(&path)
+            .map_err(Into::into)
+            .and_then(|bytes| StructureTemplate::from_ron(&bytes))
+        {
+            Ok(template) => templates.push(template),
+            Err(err) => eprintln!("[pregen] 跳过结构模板 {}: {err}", path.display()),
+        }
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+/// 需要生成的区块 Y 范围：从世界底部到地形（含山脊）、浮空岛和结构可能到达的最高处，
+/// 不超过世界上限
+pub fn vertical_chunk_range(
+    config: &WorldGenConfig,
+    structures: &[StructureTemplate],
+) -> RangeInclusive<i32> {
+    let terrain = &config.terrain;
+    let max_terrain = terrain.base_height as f64
+        + terrain
+            .octaves
+            .iter()
+            .map(|octave| octave.amplitude.abs())
+            .sum::<f64>()
+        + config.mountains.ridge_amplitude.max(0.0);
+    let max_structure = structures
+        .iter()
+        .map(|template| template.size.y + template.placement.y_offset.max(0))
+        .max()
+        .unwrap_or(0);
+
+    let top = (max_terrain.ceil() as i32)
+        .max(config.floating_islands.max_y)
+        .max(terrain.water_level)
+        + SURFACE_FEATURE_MARGIN.max(max_structure);
+    let top = top.min(terrain.max_y);
+
+    terrain.min_y.div_euclid(CHUNK_SIZE)..=top.div_euclid(CHUNK_SIZE)
+}
+
+/// 按区域分组半径内的区块，离原点近的区域排在前面
+pub fn plan_regions(radius: i32, y_range: RangeInclusive<i32>) -> Vec<(RegionPos, Vec<ChunkPos>)> {
+    let mut regions: HashMap<RegionPos, Vec<ChunkPos>> = HashMap::new();
+    for x in -radius..=radius {
+        for z in -radius..=radius {
+            if x * x + z * z > radius * radius {
+                continue;
+            }
+            for y in y_range.clone() {
+                let chunk_pos = ChunkPos::new(x, y, z);
+                regions
+                    .entry(RegionPos::from_chunk(chunk_pos))
+                    .or_default()
+                    .push(chunk_pos);
+            }
+        }
+    }
+
+    let mut planned: Vec<_> = regions.into_iter().collect();
+    planned.sort_by_key(|(region, _)| {
+        (
+            region.x * region.x + region.z * region.z,
+            region.x,
+            region.y,
+            region.z,
+        )
+    });
+    planned
+}
+
+/// 执行预生成
+pub fn run_pregen(options: &PregenOptions) -> Result<PregenSummary, StorageError> {
+    let start = Instant::now();
+    let assets_dir = Path::new(ASSETS_DIR);
+    let config = read_worldgen_config(assets_dir);
+    let structures = read_structure_templates(assets_dir);
+    let y_range = vertical_chunk_range(&config, &structures);
+    // 所有工作线程共享同一份噪声生成器
+    let terrain = SharedTerrain::new(WorldSeed::new(options.seed), config, Arc::new(structures));
+    let regions = plan_regions(options.radius, y_range.clone());
+    let total_chunks: usize = regions.iter().map(|(_, chunks)| chunks.len()).sum();
+
+    println!(
+        "[pregen] seed {}, radius {} chunks, chunk y {}..={}: {} chunks in {} regions, {} threads -> {}",
+        options.seed,
+        options.radius,
+        y_range.start(),
+        y_range.end(),
+        total_chunks,
+        regions.len(),
+        options.threads,
+        options.storage.root().display(),
+    );
+
+    let next_region = AtomicUsize::new(0);
+    let finished_regions = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let first_error: Mutex<Option<StorageError>> = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..options.threads.max(1) {
+            scope.spawn(|| {
+                let generator = terrain.generator();
+
+                while !failed.load(Ordering::Relaxed) {
+                    let index = next_region.fetch_add(1, Ordering::Relaxed);
+                    let Some((region, chunk_positions)) = regions.get(index) else {
+                        break;
+                    };
+
+                    // 区域文件中已有的区块（可能被玩家修改过）保持不变
+                    let result = options.storage.load_region(*region).and_then(|mut chunks| {
+                        for &chunk_pos in chunk_positions {
+                            chunks
+                                .entry(chunk_pos)
+                                .or_insert_with(|| generator.generate_chunk(chunk_pos));
+                        }
+                        options.storage.save_region(*region, &chunks)
+                    });
+                    if let Err(err) = result {
+                        failed.store(true, Ordering::Relaxed);
+                        first_error.lock().unwrap().get_or_insert(err);
+                        break;
+                    }
+
+                    let done = finished_regions.fetch_add(1, Ordering::Relaxed) + 1;
+                    let percent = done * 100 / regions.len();
+                    if percent != (done - 1) * 100 / regions.len() {
+                        println!("[pregen] {done}/{} regions ({percent}%)", regions.len());
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(err) = first_error.into_inner().unwrap() {
+        return Err(err);
+    }
+
+    Ok(PregenSummary {
+        regions: regions.len(),
+        chunks: total_chunks,
+        elapsed: start.elapsed(),
+    })
+}
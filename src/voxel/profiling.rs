@@ -0,0 +1,206 @@
+//! 模拟循环性能统计
+//!
+//! 区块生成、网格构建、热扩散、命令提交和区块替换这几个阶段都包在 `info_span!` 里，
+//! 用 `cargo run --release --features bevy/trace_tracy` 运行时可以在 Tracy 中看到每一帧
+//! 的时间线，定位卡顿来自哪个阶段、哪个区块。
+//!
+//! 同时每个阶段用 [`StageTimer`] 计时，累加到全局计数器（网格和生成在工作线程中运行，
+//! 所以计数器是原子的）。[`roll_up_stage_timings`] 每帧取走计数器，按
+//! [`TIMING_WINDOW_SECS`] 秒的窗口汇总到 [`StageTimings`]，由 F3 调试覆盖层显示
+
+use bevy::prelude::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// 汇总窗口长度（秒），覆盖层的数字按这个间隔刷新
+pub const TIMING_WINDOW_SECS: f32 = 1.0;
+
+/// 被计时的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// 地形生成（工作线程）
+    Generate,
+    /// 网格构建，包括首次构建和重建（工作线程）
+    Mesh,
+    /// 热扩散（固定时间步）
+    Thermal,
+    /// 领域命令提交（固定时间步）
+    Commit,
+    /// 占位符替换为真实区块
+    Replace,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Generate,
+        Stage::Mesh,
+        Stage::Thermal,
+        Stage::Commit,
+        Stage::Replace,
+    ];
+
+    /// 在调试覆盖层中显示的名称（覆盖层是英文的）
+    pub fn label(self) -> &'static str {
+        match self {
+            Stage::Generate => "Generate",
+            Stage::Mesh => "Mesh",
+            Stage::Thermal => "Thermal",
+            Stage::Commit => "Commit",
+            Stage::Replace => "Replace",
+        }
+    }
+}
+
+/// 一个阶段自上次汇总以来的累计值
+struct StageCounter {
+    nanos: AtomicU64,
+    calls: AtomicU64,
+    max_nanos: AtomicU64,
+}
+
+impl StageCounter {
+    const fn new() -> Self {
+        Self {
+            nanos: AtomicU64::new(0),
+            calls: AtomicU64::new(0),
+            max_nanos: AtomicU64::new(0),
+        }
+    }
+
+    fn record(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        self.nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.max_nanos.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// 取出累计值并清零：（总纳秒，次数，单次最大纳秒）
+    fn take(&self) -> (u64, u64, u64) {
+        (
+            self.nanos.swap(0, Ordering::Relaxed),
+            self.calls.swap(0, Ordering::Relaxed),
+            self.max_nanos.swap(0, Ordering::Relaxed),
+        )
+    }
+}
+
+static COUNTERS: [StageCounter; Stage::ALL.len()] = [
+    StageCounter::new(),
+    StageCounter::new(),
+    StageCounter::new(),
+    StageCounter::new(),
+    StageCounter::new(),
+];
+
+/// 阶段计时器，离开作用域时把耗时记到对应阶段
+///
+/// 和 `info_span!(...).entered()` 配合使用：span 给 Tracy 看，计时器给调试覆盖层看
+#[must_use = "计时器离开作用域时才记录耗时"]
+pub struct StageTimer {
+    stage: Stage,
+    started: Instant,
+}
+
+impl StageTimer {
+    pub fn start(stage: Stage) -> Self {
+        Self {
+            stage,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for StageTimer {
+    fn drop(&mut self) {
+        COUNTERS[self.stage as usize].record(self.started.elapsed());
+    }
+}
+
+/// 一个阶段在上一个汇总窗口内的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageStats {
+    /// 平均每帧耗时（毫秒）；工作线程阶段是各线程耗时之和
+    pub ms_per_frame: f32,
+    /// 平均每帧调用次数
+    pub calls_per_frame: f32,
+    /// 单次调用的最大耗时（毫秒）
+    pub max_ms: f32,
+}
+
+/// 各阶段按窗口汇总的耗时，供调试覆盖层显示
+#[derive(Resource, Debug, Default)]
+pub struct StageTimings {
+    /// 上一个完整窗口的统计，按 [`Stage::ALL`] 的顺序
+    stats: [StageStats; Stage::ALL.len()],
+    /// 当前窗口的累计值：（总纳秒，次数，单次最大纳秒）
+    window: [(u64, u64, u64); Stage::ALL.len()],
+    window_frames: u32,
+    window_secs: f32,
+}
+
+impl StageTimings {
+    pub fn get(&self, stage: Stage) -> StageStats {
+        self.stats[stage as usize]
+    }
+
+    /// 把一帧的累计值加进当前窗口，窗口满了就更新统计
+    fn add_frame(&mut self, frame: [(u64, u64, u64); Stage::ALL.len()], delta_secs: f32) {
+        for (window, (nanos, calls, max_nanos)) in self.window.iter_mut().zip(frame) {
+            window.0 += nanos;
+            window.1 += calls;
+            window.2 = window.2.max(max_nanos);
+        }
+        self.window_frames += 1;
+        self.window_secs += delta_secs;
+        if self.window_secs < TIMING_WINDOW_SECS {
+            return;
+        }
+
+        let frames = self.window_frames as f32;
+        for (stats, (nanos, calls, max_nanos)) in self.stats.iter_mut().zip(self.window) {
+            *stats = StageStats {
+                ms_per_frame: nanos as f32 / 1.0e6 / frames,
+                calls_per_frame: calls as f32 / frames,
+                max_ms: max_nanos as f32 / 1.0e6,
+            };
+        }
+        self.window = Default::default();
+        self.window_frames = 0;
+        self.window_secs = 0.0;
+    }
+}
+
+/// 每帧取走各阶段的计数器并汇总
+pub fn roll_up_stage_timings(time: Res<Time<Real>>, mut timings: ResMut<StageTimings>) {
+    let frame = std::array::from_fn(|i| COUNTERS[i].take());
+    timings.add_frame(frame, time.delta_secs());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with(stage: Stage, nanos: u64, calls: u64) -> [(u64, u64, u64); Stage::ALL.len()] {
+        let mut frame = [(0, 0, 0); Stage::ALL.len()];
+        frame[stage as usize] = (nanos, calls, nanos / calls.max(1));
+        frame
+    }
+
+    #[test]
+    fn test_stats_published_once_window_fills() {
+        let mut timings = StageTimings::default();
+        timings.add_frame(frame_with(Stage::Mesh, 4_000_000, 2), 0.5);
+        assert_eq!(timings.get(Stage::Mesh), StageStats::default());
+
+        timings.add_frame(frame_with(Stage::Mesh, 2_000_000, 1), 0.5);
+        let stats = timings.get(Stage::Mesh);
+        assert!((stats.ms_per_frame - 3.0).abs() < 1e-4);
+        assert!((stats.calls_per_frame - 1.5).abs() < 1e-4);
+        assert!((stats.max_ms - 2.0).abs() < 1e-4);
+        assert_eq!(timings.get(Stage::Generate), StageStats::default());
+
+        // 新窗口从零开始累计
+        timings.add_frame(frame_with(Stage::Mesh, 1_000_000, 1), 1.0);
+        assert!((timings.get(Stage::Mesh).ms_per_frame - 1.0).abs() < 1e-4);
+    }
+}
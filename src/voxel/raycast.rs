@@ -0,0 +1,214 @@
+//! 体素射线检测
+//!
+//! 使用 DDA（Digital Differential Analyzer）沿射线逐个方块前进，只访问射线穿过的方块。
+//! [`RaycastFilter`] 决定哪些方块会挡住射线：默认只命中固体方块，水、熔岩和花草被穿过，
+//! 需要时可以分别打开。玩家的方块选取、AI 视线、爆炸遮挡等都可以调用 [`raycast`]。
+
+use bevy::prelude::*;
+
+use crate::voxel::chunk::ChunkLookup;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 射线命中的方块类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RaycastFilter {
+    /// 命中水和熔岩
+    pub fluids: bool,
+    /// 命中花、草、树苗等没有碰撞体积的植物
+    pub foliage: bool,
+}
+
+impl RaycastFilter {
+    /// 射线是否停在该方块上
+    pub fn targets(self, kind: VoxelKind) -> bool {
+        match kind {
+            VoxelKind::Air => false,
+            VoxelKind::Water | VoxelKind::Lava => self.fluids,
+            kind if !kind.is_solid() => self.foliage,
+            _ => true,
+        }
+    }
+}
+
+/// 射线命中结果
+#[derive(Debug, Clone, Copy)]
+pub struct VoxelHit {
+    pub pos: IVec3,
+    pub kind: VoxelKind,
+    /// 从起点到进入命中方块的距离
+    pub distance: f32,
+    /// 射线进入命中方块的面的法线（起点就在命中方块内时为零向量）
+    pub normal: IVec3,
+    /// 命中前最后穿过的方块（可能是被过滤掉的水或花草），起点就在命中方块内时为 None
+    pub previous: Option<IVec3>,
+}
+
+impl VoxelHit {
+    /// 贴着命中面放置方块时占据的位置
+    pub fn placement_pos(&self) -> Option<IVec3> {
+        self.previous
+    }
+}
+
+/// 从 origin 沿 dir 方向检测最多 max_dist 距离内第一个被 filter 命中的方块
+///
+/// dir 不需要归一化，为零向量时返回 None；未加载的区块视为空气
+pub fn raycast(
+    world: &impl ChunkLookup,
+    origin: Vec3,
+    dir: Vec3,
+    max_dist: f32,
+    filter: RaycastFilter,
+) -> Option<VoxelHit> {
+    let dir = dir.try_normalize()?;
+    let mut pos = origin.floor().as_ivec3();
+
+    // 每个轴上的前进方向
+    let step = IVec3::new(
+        if dir.x >= 0.0 { 1 } else { -1 },
+        if dir.y >= 0.0 { 1 } else { -1 },
+        if dir.z >= 0.0 { 1 } else { -1 },
+    );
+
+    // 沿射线穿过一个方块在各轴上需要的距离
+    let delta = Vec3::new(
+        if dir.x.abs() < 1e-10 {
+            f32::MAX
+        } else {
+            (1.0 / dir.x).abs()
+        },
+        if dir.y.abs() < 1e-10 {
+            f32::MAX
+        } else {
+            (1.0 / dir.y).abs()
+        },
+        if dir.z.abs() < 1e-10 {
+            f32::MAX
+        } else {
+            (1.0 / dir.z).abs()
+        },
+    );
+
+    // 到各轴下一个方块边界的距离
+    let mut t_max = Vec3::new(
+        if dir.x >= 0.0 {
+            ((pos.x + 1) as f32 - origin.x) * delta.x
+        } else {
+            (origin.x - pos.x as f32) * delta.x
+        },
+        if dir.y >= 0.0 {
+            ((pos.y + 1) as f32 - origin.y) * delta.y
+        } else {
+            (origin.y - pos.y as f32) * delta.y
+        },
+        if dir.z >= 0.0 {
+            ((pos.z + 1) as f32 - origin.z) * delta.z
+        } else {
+            (origin.z - pos.z as f32) * delta.z
+        },
+    );
+
+    let mut distance = 0.0;
+    let mut normal = IVec3::ZERO;
+    let mut previous = None;
+
+    while distance < max_dist {
+        let kind = world.get_voxel(pos);
+        if filter.targets(kind) {
+            return Some(VoxelHit {
+                pos,
+                kind,
+                distance,
+                normal,
+                previous,
+            });
+        }
+        previous = Some(pos);
+
+        // 沿 t_max 最小的轴前进一格
+        if t_max.x < t_max.y && t_max.x < t_max.z {
+            distance = t_max.x;
+            t_max.x += delta.x;
+            pos.x += step.x;
+            normal = IVec3::new(-step.x, 0, 0);
+        } else if t_max.y < t_max.z {
+            distance = t_max.y;
+            t_max.y += delta.y;
+            pos.y += step.y;
+            normal = IVec3::new(0, -step.y, 0);
+        } else {
+            distance = t_max.z;
+            t_max.z += delta.z;
+            pos.z += step.z;
+            normal = IVec3::new(0, 0, -step.z);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::{ChunkData, ChunkPos, VoxelWorld};
+    use std::collections::HashMap;
+
+    /// 在 y = -1 铺一层石头，上面依次是水和草
+    fn test_world() -> HashMap<ChunkPos, ChunkData> {
+        let mut world: HashMap<ChunkPos, ChunkData> = HashMap::new();
+        for (pos, kind) in [
+            (IVec3::new(-3, -1, -3), VoxelKind::Stone),
+            (IVec3::new(-3, 0, -3), VoxelKind::Water),
+            (IVec3::new(-3, 1, -3), VoxelKind::TallGrass),
+        ] {
+            let (chunk_pos, idx) = VoxelWorld::split_world_pos(pos);
+            world.entry(chunk_pos).or_default().voxels.set(idx, kind);
+        }
+        world
+    }
+
+    #[test]
+    fn test_filter_skips_fluids_and_foliage() {
+        let world = test_world();
+        let origin = Vec3::new(-2.5, 3.5, -2.5);
+
+        let hit = raycast(&world, origin, Vec3::NEG_Y, 8.0, RaycastFilter::default()).unwrap();
+        assert_eq!(
+            (hit.pos, hit.kind),
+            (IVec3::new(-3, -1, -3), VoxelKind::Stone)
+        );
+        assert_eq!(hit.normal, IVec3::Y);
+        assert_eq!(hit.previous, Some(IVec3::new(-3, 0, -3)));
+        assert!((hit.distance - 3.5).abs() < 1e-5);
+
+        let filter = RaycastFilter {
+            fluids: true,
+            foliage: false,
+        };
+        let hit = raycast(&world, origin, Vec3::NEG_Y, 8.0, filter).unwrap();
+        assert_eq!(hit.kind, VoxelKind::Water);
+
+        let filter = RaycastFilter {
+            fluids: false,
+            foliage: true,
+        };
+        let hit = raycast(&world, origin, Vec3::NEG_Y, 8.0, filter).unwrap();
+        assert_eq!(hit.kind, VoxelKind::TallGrass);
+        assert_eq!(hit.placement_pos(), Some(IVec3::new(-3, 2, -3)));
+    }
+
+    #[test]
+    fn test_reach_and_start_inside() {
+        let world = test_world();
+        let filter = RaycastFilter::default();
+        // 方向不需要归一化
+        let origin = Vec3::new(-2.5, 3.5, -2.5);
+        assert!(raycast(&world, origin, Vec3::new(0.0, -2.0, 0.0), 3.0, filter).is_none());
+        assert!(raycast(&world, origin, Vec3::ZERO, 8.0, filter).is_none());
+
+        let hit = raycast(&world, Vec3::new(-2.5, -0.5, -2.5), Vec3::X, 8.0, filter).unwrap();
+        assert_eq!(hit.pos, IVec3::new(-3, -1, -3));
+        assert_eq!((hit.normal, hit.previous), (IVec3::ZERO, None));
+        assert_eq!(hit.placement_pos(), None);
+    }
+}
@@ -0,0 +1,392 @@
+//! 运行中重新生成区块
+//!
+//! 世界生成配置热重载、结构模板变化或 `worldgen` 控制台命令修改生成选项后，
+//! [`ChunkRegenerator`](crate::voxel::worldgen::ChunkRegenerator) 把已加载且未被修改的区块
+//! 排进 [`ChunkRegenQueue`]。区块在后台按新参数重新生成，离摄像机近的先生成，
+//! 完成后原地替换方块数据并标记为脏，光照和网格重建系统随后替换网格，
+//! 区块在重新生成期间保持可见。
+//!
+//! 玩家修改过的区块（包括已卸载暂存的区块）保留不动；排队后才被修改的区块也会跳过。
+//! 任务不对应实体，结果和区块生成、网格重建共用 [`ChunkTasks`](crate::voxel::loading::ChunkTasks) 的通道。
+//! 新一轮重新生成开始时，上一轮还没完成的任务被取消，结果丢弃。
+
+use bevy::prelude::*;
+use bevy::tasks::AsyncComputeTaskPool;
+use std::cmp::Reverse;
+
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::voxel::chunk::{ChunkData, ChunkLookupMut, ChunkPos, Chunks, ChunksMut};
+use crate::voxel::domains::thermal::ThermalApi;
+use crate::voxel::light::{relight_chunks, NEIGHBOR_DIRS};
+use crate::voxel::loading::{CancelToken, ChunkTasks, TaskOutput};
+use crate::voxel::palette::PalettedArray;
+use crate::voxel::snapshot::WorldSnapshot;
+use crate::voxel::systems::apply_chunk_replacements;
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::voxel_kind::VoxelKind;
+use crate::voxel::worldgen::{ChunkRegenerator, GenPreset, TerrainShape, WorldGenOptions};
+
+/// 默认同时进行的重新生成任务数（与区块加载共用异步线程池，留出余量）
+const DEFAULT_MAX_CONCURRENT_TASKS: usize = 4;
+
+/// 等待重新生成的区块和进度
+#[derive(Resource, Debug)]
+pub struct ChunkRegenQueue {
+    /// 等待重新生成的区块，按摄像机距离从远到近排列，从末尾取出
+    pending: Vec<ChunkPos>,
+    /// 新一轮开始后还没按距离排序
+    unsorted: bool,
+    /// 同时进行的任务上限
+    pub max_concurrent_tasks: usize,
+    /// 本轮排队的区块数
+    pub total: usize,
+    /// 本轮已替换或跳过的区块数
+    pub finished: usize,
+}
+
+impl Default for ChunkRegenQueue {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            unsorted: false,
+            max_concurrent_tasks: DEFAULT_MAX_CONCURRENT_TASKS,
+            total: 0,
+            finished: 0,
+        }
+    }
+}
+
+impl ChunkRegenQueue {
+    /// 开始新一轮重新生成，替换上一轮还没开始的区块
+    ///
+    /// 上一轮进行中的任务由调用方通过 [`ChunkTasks::cancel_regens`] 取消
+    pub fn start_round(&mut self, chunks: impl IntoIterator<Item = ChunkPos>) {
+        self.pending = chunks.into_iter().collect();
+        self.unsorted = true;
+        self.total = self.pending.len();
+        self.finished = 0;
+    }
+
+    /// 本轮还没完成的区块数
+    pub fn remaining(&self) -> usize {
+        self.total - self.finished
+    }
+
+    /// 取出下一个要生成的区块，离摄像机最近的优先
+    fn next(&mut self, camera_chunk: Option<ChunkPos>) -> Option<ChunkPos> {
+        if self.unsorted
+            && let Some(camera_chunk) = camera_chunk
+        {
+            self.pending
+                .sort_by_key(|chunk_pos| Reverse(chunk_pos.distance_squared_to(&camera_chunk)));
+            self.unsorted = false;
+        }
+        self.pending.pop()
+    }
+
+    /// 记录本轮完成一个区块，整轮完成时输出日志
+    fn finish_one(&mut self) {
+        self.finished += 1;
+        if self.finished == self.total {
+            info!("Regenerated {} chunks", self.total);
+        }
+    }
+}
+
+/// 重新生成插件
+///
+/// 注册重新生成队列、任务系统和 worldgen 控制台命令
+pub struct ChunkRegenPlugin;
+
+impl Plugin for ChunkRegenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "worldgen",
+            "worldgen [preset <standard|amplified|archipelago>|shape <normal|flat|superflat|showcase>|regen]",
+            "查看或临时修改生成选项（只在本次运行有效），未修改的区块按新参数重新生成",
+        );
+
+        app.init_resource::<ChunkRegenQueue>()
+            .add_systems(Update, worldgen_console_command)
+            // 在共享地形同步之后派发，替换结果赶在同一帧的光照和网格重建之前
+            .add_systems(
+                Update,
+                (spawn_regen_tasks, apply_regen_results)
+                    .chain()
+                    .after(apply_chunk_replacements)
+                    .before(relight_chunks),
+            );
+    }
+}
+
+/// 按新参数在后台生成排队的区块
+///
+/// 任务分离运行（仅地形生成），完成后把方块发进 [`ChunkTasks`] 的通道
+fn spawn_regen_tasks(
+    mut queue: ResMut<ChunkRegenQueue>,
+    mut tasks: ResMut<ChunkTasks>,
+    chunks: Chunks,
+    terrain: Res<SharedTerrain>,
+    camera_query: Query<&Transform, With<Camera3d>>,
+) {
+    if queue.pending.is_empty() || tasks.regen_count() >= queue.max_concurrent_tasks {
+        return;
+    }
+    let camera_chunk = camera_query
+        .single()
+        .ok()
+        .map(|transform| ChunkPos::containing(transform.translation));
+
+    let task_pool = AsyncComputeTaskPool::get();
+    while tasks.regen_count() < queue.max_concurrent_tasks {
+        let Some(chunk_pos) = queue.next(camera_chunk) else {
+            break;
+        };
+        // 排队后被卸载或修改的区块不再重新生成
+        if chunks.get(&chunk_pos).is_none_or(|chunk| chunk.is_modified) {
+            queue.finish_one();
+            continue;
+        }
+
+        let terrain = terrain.clone();
+        let cancel = CancelToken::default();
+        let task_cancel = cancel.clone();
+        let reporter = tasks.start_regen(chunk_pos, cancel);
+        task_pool
+            .spawn(async move {
+                // 被取消时不发产物，句柄析构时报告任务结束
+                if let Some(mut chunk) = terrain
+                    .generator()
+                    .generate_chunk_cancellable(chunk_pos, &task_cancel)
+                {
+                    chunk.compact();
+                    reporter.send(TaskOutput::Regenerated(chunk.voxels));
+                }
+            })
+            .detach();
+    }
+}
+
+/// 用完成的生成结果替换区块
+///
+/// 上一轮的任务在新一轮开始时已经取消，它们的结果不会到这里
+fn apply_regen_results(
+    mut queue: ResMut<ChunkRegenQueue>,
+    mut tasks: ResMut<ChunkTasks>,
+    mut chunks: ChunksMut,
+    mut snapshot: ResMut<WorldSnapshot>,
+) {
+    let mut replaced = Vec::new();
+    for (chunk_pos, voxels) in tasks.drain_regenerated() {
+        queue.finish_one();
+        if let Some(voxels) = voxels
+            && replace_chunk(&mut chunks, chunk_pos, voxels)
+        {
+            replaced.push(chunk_pos);
+        }
+    }
+    if !replaced.is_empty() {
+        snapshot.refresh(&chunks, replaced);
+    }
+}
+
+/// 用新生成的方块替换未修改区块，返回是否替换
+///
+/// 新旧方块相同时不替换。替换后温度等模拟状态随区块一起重置，旧光照保留到重新计算；
+/// 区块和相邻区块（网格取样了共享边界）标记为脏
+fn replace_chunk(
+    world: &mut impl ChunkLookupMut,
+    chunk_pos: ChunkPos,
+    voxels: PalettedArray<VoxelKind>,
+) -> bool {
+    let Some(mut old) = world.chunk_mut(&chunk_pos) else {
+        return false;
+    };
+    if old.is_modified || old.voxels == voxels {
+        return false;
+    }
+
+    let mut chunk = ChunkData::new();
+    chunk.voxels = voxels;
+    chunk.light = old.light.clone();
+    chunk.is_dirty = true;
+    ThermalApi::register_heat_sources(&mut chunk);
+    *old = chunk;
+    drop(old);
+
+    for dir in NEIGHBOR_DIRS {
+        let neighbor_pos = ChunkPos::new(
+            chunk_pos.x + dir.x,
+            chunk_pos.y + dir.y,
+            chunk_pos.z + dir.z,
+        );
+        if let Some(mut neighbor) = world.chunk_mut(&neighbor_pos) {
+            neighbor.is_dirty = true;
+        }
+    }
+    true
+}
+
+const WORLDGEN_USAGE: &str = "用法：worldgen [preset <standard|amplified|archipelago>|shape <normal|flat|superflat|showcase>|regen]";
+
+fn worldgen_console_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    mut options: ResMut<WorldGenOptions>,
+    mut regenerator: ChunkRegenerator,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "worldgen" {
+            continue;
+        }
+        let args: Vec<&str> = command.args.iter().map(String::as_str).collect();
+        let new_options = match args.as_slice() {
+            [] => {
+                log.print(format!(
+                    "生成预设：{}，地形：{}",
+                    options.preset.label(),
+                    options.shape.label()
+                ));
+                let queue = regenerator.queue();
+                if queue.remaining() > 0 {
+                    log.print(format!(
+                        "正在重新生成区块：{}/{}",
+                        queue.finished, queue.total
+                    ));
+                }
+                continue;
+            }
+            ["preset", name] => match GenPreset::from_name(name) {
+                Some(preset) => WorldGenOptions { preset, ..*options },
+                None => {
+                    log.print(format!("worldgen：未知的生成预设：{name}"));
+                    continue;
+                }
+            },
+            ["shape", name] => match TerrainShape::from_name(name) {
+                Some(shape) => WorldGenOptions { shape, ..*options },
+                None => {
+                    log.print(format!("worldgen：未知的地形：{name}"));
+                    continue;
+                }
+            },
+            ["regen"] => {
+                let count = regenerator.regenerate_unmodified();
+                log.print(format!("重新生成 {count} 个未修改的区块"));
+                continue;
+            }
+            _ => {
+                log.print(WORLDGEN_USAGE);
+                continue;
+            }
+        };
+
+        // 配置应用系统发现选项变化后重新生成区块
+        if options.set_if_neq(new_options) {
+            log.print(format!(
+                "生成预设：{}，地形：{}（只在本次运行有效，未修改的区块将重新生成）",
+                new_options.preset.label(),
+                new_options.shape.label()
+            ));
+        } else {
+            log.print("生成选项没有变化");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn filled(kind: VoxelKind) -> PalettedArray<VoxelKind> {
+        PalettedArray::filled(ChunkData::VOXEL_COUNT, kind)
+    }
+
+    /// 已经加载并构建过网格的空区块
+    fn meshed_chunk() -> ChunkData {
+        let mut chunk = ChunkData::new();
+        chunk.is_dirty = false;
+        chunk
+    }
+
+    #[test]
+    fn test_queue_takes_nearest_first() {
+        let mut queue = ChunkRegenQueue::default();
+        queue.start_round([
+            ChunkPos::new(5, 0, 0),
+            ChunkPos::new(1, 0, 0),
+            ChunkPos::new(-3, 0, 0),
+        ]);
+        let camera = Some(ChunkPos::new(0, 0, 0));
+        assert_eq!(queue.next(camera), Some(ChunkPos::new(1, 0, 0)));
+        assert_eq!(queue.next(camera), Some(ChunkPos::new(-3, 0, 0)));
+        assert_eq!(queue.next(camera), Some(ChunkPos::new(5, 0, 0)));
+        assert_eq!(queue.next(camera), None);
+    }
+
+    #[test]
+    fn test_new_round_replaces_pending_chunks() {
+        let mut queue = ChunkRegenQueue::default();
+        queue.start_round([ChunkPos::new(0, 0, 0), ChunkPos::new(1, 0, 0)]);
+        queue.next(None);
+        queue.finish_one();
+
+        queue.start_round([ChunkPos::new(2, 0, 0)]);
+        assert_eq!(queue.remaining(), 1);
+        assert_eq!(queue.next(None), Some(ChunkPos::new(2, 0, 0)));
+        assert_eq!(queue.next(None), None);
+    }
+
+    #[test]
+    fn test_replace_keeps_modified_chunks() {
+        let mut world = HashMap::new();
+        let chunk_pos = ChunkPos::new(0, 0, 0);
+        let mut chunk = meshed_chunk();
+        chunk.is_modified = true;
+        world.insert(chunk_pos, chunk);
+
+        assert!(!replace_chunk(
+            &mut world,
+            chunk_pos,
+            filled(VoxelKind::Stone)
+        ));
+        assert_eq!(world[&chunk_pos].get(0, 0, 0), VoxelKind::Air);
+    }
+
+    #[test]
+    fn test_replace_marks_chunk_and_neighbors_dirty() {
+        let mut world = HashMap::new();
+        let chunk_pos = ChunkPos::new(0, 0, 0);
+        let neighbor_pos = ChunkPos::new(0, 1, 0);
+        let far_pos = ChunkPos::new(3, 0, 0);
+        for pos in [chunk_pos, neighbor_pos, far_pos] {
+            world.insert(pos, meshed_chunk());
+        }
+
+        assert!(replace_chunk(
+            &mut world,
+            chunk_pos,
+            filled(VoxelKind::Stone)
+        ));
+        assert_eq!(world[&chunk_pos].get(0, 0, 0), VoxelKind::Stone);
+        assert!(world[&chunk_pos].is_dirty);
+        assert!(world[&neighbor_pos].is_dirty);
+        assert!(!world[&far_pos].is_dirty);
+    }
+
+    #[test]
+    fn test_unchanged_chunk_is_not_replaced() {
+        let mut world = HashMap::new();
+        let chunk_pos = ChunkPos::new(0, 0, 0);
+        world.insert(chunk_pos, meshed_chunk());
+
+        assert!(!replace_chunk(
+            &mut world,
+            chunk_pos,
+            ChunkData::new().voxels
+        ));
+        assert!(!world[&chunk_pos].is_dirty);
+    }
+}
@@ -0,0 +1,567 @@
+//! 体素定义注册表
+//!
+//! 所有体素种类的定义保存在一张按体素编号索引的表中，`VoxelKind::def()` 只是一次数组下标访问，
+//! 不再每次调用都构造完整的定义。表在首次访问时用内置定义初始化；从数据文件读取的定义通过
+//! `register` 替换对应条目后 `install` 发布，之后的查询立即使用新定义。
+//!
+//! 内置定义就是编译时嵌入的 `assets/voxels.blocks.ron`，代码中不再另写一份，两者不会不一致。
+//!
+//! 体素种类本身仍由 `VoxelKind` 枚举给出（编号写入存档），注册表只负责名称、颜色、网格形状和物理属性。
+//!
+//! 方块定义从 `assets/voxels.blocks.ron` 加载，加载时校验数值范围和名称唯一性，
+//! 文件缺失或校验失败时保留当前定义。调试构建中修改文件会热重载并重建区块网格，
+//! 调整燃烧速率、导热系数等参数不需要重新编译。
+
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+use std::sync::{LazyLock, RwLock};
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::voxel::chunk::ChunksMut;
+use crate::voxel::light::MAX_LIGHT;
+use crate::voxel::voxel_kind::{FaceColors, VoxelDef, VoxelKind, VoxelProperties, VoxelShape};
+
+/// 方块定义文件路径（相对于 assets 目录）
+pub const BLOCK_DEFINITIONS_PATH: &str = "voxels.blocks.ron";
+/// 编译时嵌入的方块定义文件，作为内置定义
+const BUNDLED_DEFINITIONS: &str = include_str!("../../assets/voxels.blocks.ron");
+
+/// 绝对零度（°C），所有温度属性的下限
+const ABSOLUTE_ZERO: f32 = -273.15;
+/// 比例类属性（湿度、硬度等）的取值范围
+const UNIT_RANGE: RangeInclusive<f32> = 0.0..=1.0;
+/// 速率、能量等非负属性的取值范围
+const NON_NEGATIVE: RangeInclusive<f32> = 0.0..=f32::INFINITY;
+/// 温度属性的取值范围
+const TEMPERATURE_RANGE: RangeInclusive<f32> = ABSOLUTE_ZERO..=f32::INFINITY;
+
+/// 内置定义，首次使用时解析
+static BUILTIN: LazyLock<VoxelRegistry> = LazyLock::new(VoxelRegistry::from_bundled);
+
+/// 当前生效的注册表
+///
+/// 发布的表会被泄漏成 'static：网格任务等后台线程可能还持有旧表中定义的引用，
+/// 而替换只在启动和热重载时发生，泄漏量可以忽略
+static CURRENT: LazyLock<RwLock<&'static VoxelRegistry>> =
+    LazyLock::new(|| RwLock::new(Box::leak(Box::new(VoxelRegistry::builtin()))));
+
+/// 按体素编号索引的定义表
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelRegistry {
+    defs: Vec<VoxelDef>,
+}
+
+impl VoxelRegistry {
+    /// 内置定义（编译时嵌入的方块定义文件）
+    pub fn builtin() -> Self {
+        BUILTIN.clone()
+    }
+
+    /// 解析嵌入的方块定义文件，文件必须列出全部方块
+    ///
+    /// 文件随程序一起编译，出错属于程序缺陷，直接 panic
+    fn from_bundled() -> Self {
+        let definitions: BlockDefinitions = ron::de::from_str(BUNDLED_DEFINITIONS)
+            .unwrap_or_else(|e| panic!("内置方块定义解析失败: {e}"));
+        let defs = VoxelKind::ALL
+            .map(|kind| {
+                let block = definitions
+                    .blocks
+                    .iter()
+                    .find(|block| block.kind == kind)
+                    .unwrap_or_else(|| panic!("内置方块定义缺少 {kind:?}"));
+                block.def_named(Box::leak(block.name.clone().into_boxed_str()))
+            })
+            .to_vec();
+        // 所有方块都已列出，校验名称唯一性时不会回头读取内置定义
+        if let Err(e) = definitions.validate() {
+            panic!("内置方块定义无效: {e}");
+        }
+        Self { defs }
+    }
+
+    /// 当前生效的注册表
+    ///
+    /// 逐体素查询的热循环可以先取出注册表再用 `get` 查询，省去每次读取全局表的开销
+    pub fn current() -> &'static VoxelRegistry {
+        *CURRENT.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 体素种类的定义
+    pub fn get(&self, kind: VoxelKind) -> &VoxelDef {
+        &self.defs[kind.id() as usize]
+    }
+
+    /// 体素是否挡住相邻方块朝向它的面：不透明并且占满整格
+    pub fn occludes(&self, kind: VoxelKind) -> bool {
+        !kind.is_transparent() && self.get(kind).shape.is_cube()
+    }
+
+    /// 替换体素种类的定义
+    pub fn register(&mut self, kind: VoxelKind, def: VoxelDef) {
+        self.defs[kind.id() as usize] = def;
+    }
+
+    /// 按体素编号顺序遍历全部定义
+    pub fn iter(&self) -> impl Iterator<Item = (VoxelKind, &VoxelDef)> {
+        VoxelKind::ALL.into_iter().zip(&self.defs)
+    }
+
+    /// 发布为当前生效的注册表
+    pub fn install(self) {
+        let registry: &'static VoxelRegistry = Box::leak(Box::new(self));
+        *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = registry;
+    }
+}
+
+// ============================================================================
+// 方块定义文件
+// ============================================================================
+
+/// 方块定义文件中的一个方块
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockDefinition {
+    pub kind: VoxelKind,
+    pub name: String,
+    /// sRGB 颜色和不透明度（0.0-1.0）
+    pub color: [f32; 4],
+    /// 顶面、侧面、底面的颜色，省略时使用 color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_color: Option<[f32; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub side_color: Option<[f32; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bottom_color: Option<[f32; 4]>,
+    /// 网格形状，省略时为立方体
+    #[serde(default, skip_serializing_if = "VoxelShape::is_cube")]
+    pub shape: VoxelShape,
+    /// 未列出的属性使用 VoxelProperties 的默认值
+    #[serde(default)]
+    pub props: VoxelProperties,
+}
+
+impl BlockDefinition {
+    /// 由已有定义生成
+    pub fn from_def(kind: VoxelKind, def: &VoxelDef) -> Self {
+        Self {
+            kind,
+            name: def.name.to_string(),
+            color: to_rgba(def.color),
+            top_color: def.faces.top.map(to_rgba),
+            side_color: def.faces.side.map(to_rgba),
+            bottom_color: def.faces.bottom.map(to_rgba),
+            shape: def.shape,
+            props: def.props,
+        }
+    }
+
+    fn to_def(&self) -> VoxelDef {
+        // 名称没有改动时沿用内置字符串，只有新名称需要泄漏成 'static
+        let builtin = BUILTIN.get(self.kind).name;
+        let name = if self.name == builtin {
+            builtin
+        } else {
+            Box::leak(self.name.clone().into_boxed_str())
+        };
+        self.def_named(name)
+    }
+
+    /// 使用给定名称字符串的定义
+    fn def_named(&self, name: &'static str) -> VoxelDef {
+        VoxelDef {
+            name,
+            color: from_rgba(self.color),
+            faces: FaceColors {
+                top: self.top_color.map(from_rgba),
+                side: self.side_color.map(from_rgba),
+                bottom: self.bottom_color.map(from_rgba),
+            },
+            shape: self.shape,
+            props: self.props,
+        }
+    }
+
+    fn check(
+        &self,
+        field: &'static str,
+        value: f32,
+        range: RangeInclusive<f32>,
+    ) -> Result<(), BlockDefinitionsError> {
+        if value.is_finite() && range.contains(&value) {
+            return Ok(());
+        }
+        Err(BlockDefinitionsError::Invalid {
+            kind: self.kind,
+            field,
+            reason: format!("{value} 超出范围 {}..={}", range.start(), range.end()),
+        })
+    }
+
+    fn validate(&self) -> Result<(), BlockDefinitionsError> {
+        let invalid = |field, reason: &str| BlockDefinitionsError::Invalid {
+            kind: self.kind,
+            field,
+            reason: reason.to_string(),
+        };
+        if self.name.trim().is_empty() {
+            return Err(invalid("name", "名称不能为空"));
+        }
+        for (field, color) in [
+            ("color", Some(self.color)),
+            ("top_color", self.top_color),
+            ("side_color", self.side_color),
+            ("bottom_color", self.bottom_color),
+        ] {
+            for value in color.into_iter().flatten() {
+                self.check(field, value, UNIT_RANGE)?;
+            }
+        }
+        match self.shape {
+            VoxelShape::Slab { height } if !(height > 0.0 && height <= 1.0) => {
+                return Err(invalid("shape", "半砖高度必须在 0 到 1 之间"));
+            }
+            VoxelShape::Inset { inset } if !(0.0..0.5).contains(&inset) => {
+                return Err(invalid("shape", "内缩量必须在 0 到 0.5 之间"));
+            }
+            _ => {}
+        }
+
+        let props = &self.props;
+        self.check("temperature", props.temperature, TEMPERATURE_RANGE)?;
+        self.check("ignition_temp", props.ignition_temp, TEMPERATURE_RANGE)?;
+        for (field, point) in [
+            ("melting_point", props.melting_point),
+            ("freezing_point", props.freezing_point),
+            ("boiling_point", props.boiling_point),
+        ] {
+            if let Some(point) = point {
+                self.check(field, point, TEMPERATURE_RANGE)?;
+            }
+        }
+        // 热容是温度变化的除数
+        if !(props.heat_capacity.is_finite() && props.heat_capacity > 0.0) {
+            return Err(invalid("heat_capacity", "必须是正数"));
+        }
+        for (field, value) in [
+            ("thermal_conductivity", props.thermal_conductivity),
+            ("evaporation_rate", props.evaporation_rate),
+            ("burn_energy", props.burn_energy),
+            ("burn_rate", props.burn_rate),
+            ("heat_release", props.heat_release),
+            ("heat_output", props.heat_output),
+            ("growth_rate", props.growth_rate),
+        ] {
+            self.check(field, value, NON_NEGATIVE)?;
+        }
+        for (field, value) in [
+            ("env_exchange_coef", props.env_exchange_coef),
+            ("humidity", props.humidity),
+            ("moisture_capacity", props.moisture_capacity),
+            ("hardness", props.hardness),
+            ("ductility", props.ductility),
+            ("integrity", props.integrity),
+            ("corrosion_resistance", props.corrosion_resistance),
+        ] {
+            self.check(field, value, UNIT_RANGE)?;
+        }
+        if props.light_emission > MAX_LIGHT {
+            return Err(invalid("light_emission", "发光等级不能超过 15"));
+        }
+        if props.is_flammable && props.burn_rate <= 0.0 {
+            return Err(invalid("burn_rate", "可燃方块的燃烧速率必须大于 0"));
+        }
+        if props.is_growable && props.max_growth_stage == 0 {
+            return Err(invalid(
+                "max_growth_stage",
+                "可生长方块的最大生长阶段必须大于 0",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn to_rgba(color: Color) -> [f32; 4] {
+    let color = color.to_srgba();
+    [color.red, color.green, color.blue, color.alpha]
+}
+
+fn from_rgba([red, green, blue, alpha]: [f32; 4]) -> Color {
+    Color::srgba(red, green, blue, alpha)
+}
+
+/// 方块定义文件，未列出的方块使用内置定义
+#[derive(Asset, TypePath, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockDefinitions {
+    pub blocks: Vec<BlockDefinition>,
+}
+
+impl BlockDefinitions {
+    /// 解析并校验 RON 格式的方块定义文件
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, BlockDefinitionsError> {
+        let definitions: Self = ron::de::from_bytes(bytes)?;
+        definitions.validate()?;
+        Ok(definitions)
+    }
+
+    /// 检查每个方块的属性范围、方块是否重复定义，以及最终的方块名称是否唯一
+    /// （`VoxelKind::from_name` 按名称查找方块）
+    pub fn validate(&self) -> Result<(), BlockDefinitionsError> {
+        let mut kinds = HashSet::new();
+        for block in &self.blocks {
+            if !kinds.insert(block.kind) {
+                return Err(BlockDefinitionsError::DuplicateKind(block.kind));
+            }
+            block.validate()?;
+        }
+
+        let mut names = HashSet::new();
+        for kind in VoxelKind::ALL {
+            let name = match self.blocks.iter().find(|block| block.kind == kind) {
+                Some(block) => block.name.clone(),
+                None => BUILTIN.get(kind).name.to_string(),
+            };
+            if !names.insert(name.clone()) {
+                return Err(BlockDefinitionsError::DuplicateName(name));
+            }
+        }
+        Ok(())
+    }
+
+    /// 以内置定义为基础，用文件中的方块替换对应条目
+    pub fn to_registry(&self) -> VoxelRegistry {
+        let mut registry = VoxelRegistry::builtin();
+        for block in &self.blocks {
+            registry.register(block.kind, block.to_def());
+        }
+        registry
+    }
+}
+
+// ============================================================================
+// 资源加载
+// ============================================================================
+
+/// 方块定义加载错误
+#[derive(Debug, thiserror::Error)]
+pub enum BlockDefinitionsError {
+    #[error("无法读取方块定义: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("方块定义解析失败: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("方块 {0:?} 重复定义")]
+    DuplicateKind(VoxelKind),
+    #[error("方块名称 \"{0}\" 被多个方块使用")]
+    DuplicateName(String),
+    #[error("方块 {kind:?} 的 {field} 无效: {reason}")]
+    Invalid {
+        kind: VoxelKind,
+        field: &'static str,
+        reason: String,
+    },
+}
+
+/// `.blocks.ron` 方块定义加载器
+#[derive(Default, TypePath)]
+pub struct BlockDefinitionsLoader;
+
+impl AssetLoader for BlockDefinitionsLoader {
+    type Asset = BlockDefinitions;
+    type Settings = ();
+    type Error = BlockDefinitionsError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        BlockDefinitions::from_ron(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["blocks.ron"]
+    }
+}
+
+/// 方块定义文件句柄（保持资源加载状态以支持热重载）
+#[derive(Resource)]
+pub struct BlockDefinitionsHandle(pub Handle<BlockDefinitions>);
+
+/// 启动时加载方块定义
+pub fn load_block_definitions(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load(BLOCK_DEFINITIONS_PATH);
+    commands.insert_resource(BlockDefinitionsHandle(handle));
+}
+
+/// 将加载或修改后的方块定义发布到注册表
+///
+/// 首次加载总是生效，文件修改只在调试构建中生效。定义变化后重建所有区块的网格以更新颜色，
+/// 物理属性在下一次模拟时直接使用新值
+pub fn apply_block_definitions(
+    mut events: MessageReader<AssetEvent<BlockDefinitions>>,
+    handle: Option<Res<BlockDefinitionsHandle>>,
+    assets: Res<Assets<BlockDefinitions>>,
+    mut chunks: ChunksMut,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    let mut changed = false;
+    for event in events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } if *id == handle.0.id() => {
+                changed = true;
+            }
+            AssetEvent::Modified { id } if *id == handle.0.id() && cfg!(debug_assertions) => {
+                changed = true;
+            }
+            _ => {}
+        }
+    }
+
+    if !changed {
+        return;
+    }
+
+    let Some(definitions) = assets.get(&handle.0) else {
+        return;
+    };
+
+    let registry = definitions.to_registry();
+    if registry == *VoxelRegistry::current() {
+        return;
+    }
+
+    registry.install();
+    for (_, mut chunk) in chunks.iter_mut() {
+        chunk.is_dirty = true;
+    }
+    info!(
+        "Block definitions reloaded, remeshing {} chunks",
+        chunks.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin_definitions() -> BlockDefinitions {
+        BlockDefinitions {
+            blocks: VoxelRegistry::builtin()
+                .iter()
+                .map(|(kind, def)| BlockDefinition::from_def(kind, def))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_builtin_registry_covers_every_kind() {
+        let registry = VoxelRegistry::builtin();
+        assert_eq!(registry.iter().count(), VoxelKind::ALL.len());
+        let names: HashSet<&str> = registry.iter().map(|(_, def)| def.name).collect();
+        assert_eq!(names.len(), VoxelKind::ALL.len());
+        assert_eq!(registry.get(VoxelKind::Stone).name, "石头");
+    }
+
+    #[test]
+    fn test_register_replaces_definition() {
+        let mut registry = VoxelRegistry::builtin();
+        let mut def = *registry.get(VoxelKind::Sand);
+        def.props.hardness = 0.9;
+        registry.register(VoxelKind::Sand, def);
+        assert_eq!(registry.get(VoxelKind::Sand).props.hardness, 0.9);
+        assert_eq!(
+            registry.get(VoxelKind::Dirt).props.hardness,
+            VoxelRegistry::builtin().get(VoxelKind::Dirt).props.hardness
+        );
+    }
+
+    #[test]
+    fn test_bundled_definitions_parse() {
+        let definitions =
+            BlockDefinitions::from_ron(include_bytes!("../../assets/voxels.blocks.ron")).unwrap();
+        for kind in VoxelKind::ALL {
+            assert!(definitions.blocks.iter().any(|block| block.kind == kind));
+        }
+        let registry = definitions.to_registry();
+        assert_eq!(registry.get(VoxelKind::Flower).shape, VoxelShape::Cross);
+        assert!(registry.get(VoxelKind::Stone).shape.is_cube());
+    }
+
+    #[test]
+    fn test_face_colors_fall_back_to_block_color() {
+        let registry = VoxelRegistry::builtin();
+        let grass = registry.get(VoxelKind::Grass);
+        assert_eq!(grass.face_color(IVec3::Y), grass.color);
+        assert_ne!(grass.face_color(IVec3::X), grass.color);
+        assert_eq!(
+            grass.face_color(IVec3::NEG_Y),
+            registry.get(VoxelKind::Dirt).color
+        );
+
+        let stone = registry.get(VoxelKind::Stone);
+        for normal in [IVec3::Y, IVec3::NEG_Z, IVec3::NEG_Y] {
+            assert_eq!(stone.face_color(normal), stone.color);
+        }
+    }
+
+    #[test]
+    fn test_builtin_definitions_round_trip() {
+        let definitions = builtin_definitions();
+        definitions.validate().unwrap();
+        assert_eq!(definitions.to_registry(), VoxelRegistry::builtin());
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_rejected() {
+        let mut definitions = builtin_definitions();
+        definitions.blocks[3].props.hardness = 1.5;
+        assert!(matches!(
+            definitions.validate(),
+            Err(BlockDefinitionsError::Invalid {
+                field: "hardness",
+                ..
+            })
+        ));
+
+        let mut definitions = builtin_definitions();
+        definitions.blocks[1].top_color = Some([0.2, 1.2, 0.2, 1.0]);
+        assert!(matches!(
+            definitions.validate(),
+            Err(BlockDefinitionsError::Invalid {
+                field: "top_color",
+                ..
+            })
+        ));
+
+        let mut definitions = builtin_definitions();
+        definitions.blocks[7].shape = VoxelShape::Slab { height: 0.0 };
+        assert!(matches!(
+            definitions.validate(),
+            Err(BlockDefinitionsError::Invalid { field: "shape", .. })
+        ));
+
+        let mut definitions = builtin_definitions();
+        definitions.blocks[2].name = definitions.blocks[1].name.clone();
+        assert!(matches!(
+            definitions.validate(),
+            Err(BlockDefinitionsError::DuplicateName(_))
+        ));
+
+        let mut definitions = builtin_definitions();
+        let duplicate = definitions.blocks[5].clone();
+        definitions.blocks.push(duplicate);
+        assert!(matches!(
+            definitions.validate(),
+            Err(BlockDefinitionsError::DuplicateKind(VoxelKind::Gravel))
+        ));
+    }
+}
@@ -0,0 +1,474 @@
+//! 回放录制格式
+//!
+//! 录制按固定 tick 保存提交的方块变更和观察者（玩家）的位置与视角，回放时在同一种子、
+//! 同一生成选项生成的新世界里逐 tick 重现，用于分享 bug 的复现过程，或回看一场火灾、
+//! 一次洪水的经过。录制和回放的游戏逻辑见 `crate::replay`。
+//!
+//! ## 文件格式（小端序）
+//!
+//! - 文件头：魔数 `VXRP`、版本号（u16）、世界元数据（u32 长度 + RON 文本，见 [`WorldMeta`]）
+//! - 每个 tick 一帧：观察者位置（3 × f32）、偏航角和俯仰角（2 × f32）、数据包数量（u32），
+//!   随后是与区块同步协议相同的带长度前缀的数据包（见 [`crate::voxel::sync`]）
+//!
+//! 第一帧为录制开始时每个修改过的区块保存一个 ChunkSnapshot，之后每帧为每个有变更的区块
+//! 保存一个 ChunkDiff。与同步一样只记录方块、标志位和变体，温度和湿度不记录。
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::voxel::change::BlockChange;
+use crate::voxel::chunk::{ChunkData, ChunkLookup, ChunkLookupMut, ChunkPos};
+use crate::voxel::loading::UnloadedChunks;
+use crate::voxel::persistence::{ByteReader, StorageError, WorldMeta};
+use crate::voxel::sync::{self, apply_changes, decode_packet, Packet, SyncError, MAX_PACKET_SIZE};
+
+/// 回放文件扩展名
+pub const REPLAY_EXTENSION: &str = "vxreplay";
+
+/// 回放文件魔数
+const REPLAY_MAGIC: &[u8; 4] = b"VXRP";
+
+/// 回放文件格式版本
+const REPLAY_VERSION: u16 = 1;
+
+/// 回放文件读写错误
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("无法读写回放文件: {0}")]
+    Io(#[from] io::Error),
+    #[error("不是回放文件")]
+    NotReplay,
+    #[error("不支持的回放文件版本: {0}")]
+    UnsupportedVersion(u16),
+    #[error("回放文件内容错误: {0}")]
+    Data(#[from] StorageError),
+    #[error("回放中的数据包错误: {0}")]
+    Packet(#[from] SyncError),
+}
+
+/// 观察者的位置和视角
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ViewerPose {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+/// 一个 tick 的录制内容
+pub struct ReplayFrame {
+    pub viewer: ViewerPose,
+    pub packets: Vec<Packet>,
+}
+
+/// 完整读入的回放
+pub struct Replay {
+    pub meta: WorldMeta,
+    pub frames: Vec<ReplayFrame>,
+}
+
+// ============================================================================
+// 编码
+// ============================================================================
+
+/// 逐帧写入回放文件
+pub struct ReplayWriter<W: Write> {
+    out: W,
+    frames: u64,
+}
+
+impl<W: Write> ReplayWriter<W> {
+    /// 写入文件头
+    pub fn new(mut out: W, meta: &WorldMeta) -> Result<Self, ReplayError> {
+        let text = ron::to_string(meta).map_err(StorageError::from)?;
+        let mut header = Vec::with_capacity(10 + text.len());
+        header.extend_from_slice(REPLAY_MAGIC);
+        header.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        header.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        header.extend_from_slice(text.as_bytes());
+        out.write_all(&header)?;
+        Ok(Self { out, frames: 0 })
+    }
+
+    /// 写入一帧
+    pub fn write_frame(&mut self, frame: &ReplayFrame) -> io::Result<()> {
+        self.out.write_all(&encode_frame(frame))?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// 已写入的帧数
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// 刷新缓冲并取回底层的写入目标
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn encode_frame(frame: &ReplayFrame) -> Vec<u8> {
+    let ViewerPose {
+        position,
+        yaw,
+        pitch,
+    } = frame.viewer;
+    let mut out = Vec::new();
+    for value in [position.x, position.y, position.z, yaw, pitch] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&(frame.packets.len() as u32).to_le_bytes());
+    for packet in &frame.packets {
+        out.extend_from_slice(&sync::encode_frame(packet));
+    }
+    out
+}
+
+// ============================================================================
+// 解码
+// ============================================================================
+
+/// 读取整个回放文件
+pub fn read_replay(path: &Path) -> Result<Replay, ReplayError> {
+    decode_replay(&fs::read(path)?)
+}
+
+/// 解析回放文件内容
+pub fn decode_replay(bytes: &[u8]) -> Result<Replay, ReplayError> {
+    let mut reader = ByteReader::new(bytes);
+    if reader.take(4).ok() != Some(REPLAY_MAGIC.as_slice()) {
+        return Err(ReplayError::NotReplay);
+    }
+    let version = reader.u16()?;
+    if version != REPLAY_VERSION {
+        return Err(ReplayError::UnsupportedVersion(version));
+    }
+    let len = reader.u32()? as usize;
+    let text = std::str::from_utf8(reader.take(len)?)
+        .map_err(|_| StorageError::Corrupt("世界信息不是有效的 UTF-8"))?;
+    let meta: WorldMeta = ron::from_str(text).map_err(StorageError::from)?;
+
+    let mut frames = Vec::new();
+    while reader.remaining() > 0 {
+        frames.push(read_frame(&mut reader)?);
+    }
+    Ok(Replay { meta, frames })
+}
+
+fn read_frame(reader: &mut ByteReader) -> Result<ReplayFrame, ReplayError> {
+    let viewer = ViewerPose {
+        position: Vec3::new(reader.f32()?, reader.f32()?, reader.f32()?),
+        yaw: reader.f32()?,
+        pitch: reader.f32()?,
+    };
+    let count = reader.u32()? as usize;
+    // 每个数据包至少有 4 字节长度和 1 字节类型，先检查数量，避免按错误的数量预分配
+    if count > reader.remaining() / 5 {
+        return Err(StorageError::Corrupt("数据包数量超出数据长度").into());
+    }
+    let mut packets = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = reader.u32()? as usize;
+        if len > MAX_PACKET_SIZE {
+            return Err(SyncError::TooLarge(len).into());
+        }
+        packets.push(decode_packet(reader.take(len)?)?);
+    }
+    Ok(ReplayFrame { viewer, packets })
+}
+
+// ============================================================================
+// 录制与回放
+// ============================================================================
+
+/// 录制开始时的快照：所有修改过的区块（包括卸载时保留的区块）
+pub fn snapshot_packets(world: &impl ChunkLookup, unloaded: &UnloadedChunks) -> Vec<Packet> {
+    let mut chunks: Vec<(ChunkPos, &ChunkData)> = world
+        .iter_chunks()
+        .filter(|(_, chunk)| chunk.is_modified || !chunk.changes.is_empty())
+        .chain(unloaded.chunks.iter().map(|(&pos, chunk)| (pos, chunk)))
+        .collect();
+    chunks.sort_unstable_by_key(|(pos, _)| *pos);
+    chunks
+        .into_iter()
+        .map(|(pos, chunk)| Packet::ChunkSnapshot {
+            pos,
+            chunk: Box::new(chunk.clone()),
+        })
+        .collect()
+}
+
+/// 本 tick 提交的变更，每个有变更的区块一个 ChunkDiff（按区块坐标排序）
+///
+/// 温度和湿度不记录
+pub fn diff_packets(world: &impl ChunkLookup) -> Vec<Packet> {
+    let mut packets: Vec<(ChunkPos, Vec<BlockChange>)> = world
+        .iter_chunks()
+        .filter_map(|(pos, chunk)| {
+            let changes: Vec<BlockChange> = chunk
+                .changes
+                .iter()
+                .filter(|change| {
+                    !matches!(
+                        change,
+                        BlockChange::SetTemp { .. } | BlockChange::SetMoisture { .. }
+                    )
+                })
+                .cloned()
+                .collect();
+            (!changes.is_empty()).then_some((pos, changes))
+        })
+        .collect();
+    packets.sort_unstable_by_key(|(pos, _)| *pos);
+    packets
+        .into_iter()
+        .map(|(pos, changes)| Packet::ChunkDiff { pos, changes })
+        .collect()
+}
+
+/// 把录制的数据包写入世界
+///
+/// 已加载的区块直接修改并记入变更日志，之后的网格重建照常处理；卸载时保留的区块直接修改；
+/// 快照对应的区块还没有生成时放入保留区，进入加载范围时恢复。变更对应的区块还没有生成时
+/// 原样返回数据包，由调用方在区块加载后再次写入
+pub fn apply_packet(
+    world: &mut impl ChunkLookupMut,
+    unloaded: &mut UnloadedChunks,
+    packet: Packet,
+) -> Option<Packet> {
+    match packet {
+        Packet::ChunkSnapshot { pos, chunk } => {
+            if let Some(mut local) = world.chunk_mut(&pos) {
+                let changes = snapshot_changes(&local, &chunk);
+                apply_logged(&mut local, changes);
+            } else {
+                let mut chunk = *chunk;
+                chunk.is_modified = true;
+                unloaded.chunks.insert(pos, chunk);
+            }
+            None
+        }
+        Packet::ChunkDiff { pos, changes } => {
+            if let Some(mut local) = world.chunk_mut(&pos) {
+                apply_logged(&mut local, changes);
+            } else if let Some(chunk) = unloaded.chunks.get_mut(&pos) {
+                apply_changes(chunk, &changes);
+            } else {
+                return Some(Packet::ChunkDiff { pos, changes });
+            }
+            None
+        }
+        Packet::Welcome { .. } => None,
+    }
+}
+
+/// 快照与区块当前内容之间的差异
+fn snapshot_changes(chunk: &ChunkData, snapshot: &ChunkData) -> Vec<BlockChange> {
+    let mut changes = Vec::new();
+    for idx in 0..ChunkData::VOXEL_COUNT {
+        let (old, new) = (chunk.voxels.get(idx), snapshot.voxels.get(idx));
+        if old != new {
+            changes.push(BlockChange::SetVoxel { idx, old, new });
+        }
+        let (old, new) = (chunk.variant.get(idx), snapshot.variant.get(idx));
+        if old != new {
+            changes.push(BlockChange::SetVariant { idx, old, new });
+        }
+        let (old, new) = (chunk.flags.get(idx), snapshot.flags.get(idx));
+        for flag in (old ^ new).iter() {
+            changes.push(BlockChange::SetFlag {
+                idx,
+                flag,
+                set: new.contains(flag),
+            });
+        }
+    }
+    changes
+}
+
+/// 把变更写入已加载的区块，并像提交系统一样记入变更日志和脏记录
+fn apply_logged(chunk: &mut ChunkData, changes: Vec<BlockChange>) {
+    apply_changes(chunk, &changes);
+    for change in &changes {
+        chunk.dirty_blocks.extend(change.indices());
+    }
+    chunk.dirty_blocks.sort_unstable();
+    chunk.dirty_blocks.dedup();
+    chunk.changes.extend(changes);
+}
+
+/// 按区块保存等待区块加载的变更
+#[derive(Default)]
+pub struct PendingPackets {
+    chunks: HashMap<ChunkPos, Vec<Packet>>,
+}
+
+impl PendingPackets {
+    /// 保存一个等待区块加载的数据包
+    pub fn push(&mut self, packet: Packet) {
+        if let Packet::ChunkDiff { pos, .. } | Packet::ChunkSnapshot { pos, .. } = &packet {
+            self.chunks.entry(*pos).or_default().push(packet);
+        }
+    }
+
+    /// 写入已经加载的区块的变更，返回写入的数据包数量
+    pub fn flush(
+        &mut self,
+        world: &mut impl ChunkLookupMut,
+        unloaded: &mut UnloadedChunks,
+    ) -> usize {
+        let mut loaded: Vec<ChunkPos> = self
+            .chunks
+            .keys()
+            .filter(|pos| world.chunk(pos).is_some() || unloaded.chunks.contains_key(pos))
+            .copied()
+            .collect();
+        loaded.sort_unstable();
+
+        let mut applied = 0;
+        for pos in loaded {
+            for packet in self.chunks.remove(&pos).unwrap_or_default() {
+                applied += 1;
+                if let Some(packet) = apply_packet(world, unloaded, packet) {
+                    self.push(packet);
+                }
+            }
+        }
+        applied
+    }
+
+    /// 还在等待的数据包数量
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::flags::VoxelFlags;
+    use crate::voxel::voxel_kind::VoxelKind;
+    use crate::voxel::worldgen::WorldGenOptions;
+
+    fn meta() -> WorldMeta {
+        WorldMeta {
+            name: "replay".to_string(),
+            seed: 7,
+            options: WorldGenOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_replay_round_trip() {
+        let mut chunk = ChunkData::new();
+        chunk.set(1, 2, 3, VoxelKind::Stone);
+        let mut writer = ReplayWriter::new(Vec::new(), &meta()).unwrap();
+        writer
+            .write_frame(&ReplayFrame {
+                viewer: ViewerPose {
+                    position: Vec3::new(1.5, 40.0, -3.25),
+                    yaw: 0.5,
+                    pitch: -0.25,
+                },
+                packets: vec![Packet::ChunkSnapshot {
+                    pos: ChunkPos::new(0, 2, -1),
+                    chunk: Box::new(chunk),
+                }],
+            })
+            .unwrap();
+        writer
+            .write_frame(&ReplayFrame {
+                viewer: ViewerPose::default(),
+                packets: vec![Packet::ChunkDiff {
+                    pos: ChunkPos::new(0, 2, -1),
+                    changes: vec![BlockChange::SetFlag {
+                        idx: 4,
+                        flag: VoxelFlags::BURNING,
+                        set: true,
+                    }],
+                }],
+            })
+            .unwrap();
+        assert_eq!(writer.frames(), 2);
+        let bytes = writer.finish().unwrap();
+
+        let replay = decode_replay(&bytes).unwrap();
+        assert_eq!(replay.meta, meta());
+        assert_eq!(replay.frames.len(), 2);
+        assert_eq!(
+            replay.frames[0].viewer.position,
+            Vec3::new(1.5, 40.0, -3.25)
+        );
+        assert_eq!(replay.frames[0].viewer.pitch, -0.25);
+        let Packet::ChunkSnapshot { pos, chunk } = &replay.frames[0].packets[0] else {
+            panic!("expected a snapshot packet");
+        };
+        assert_eq!(*pos, ChunkPos::new(0, 2, -1));
+        assert_eq!(chunk.get(1, 2, 3), VoxelKind::Stone);
+        assert!(matches!(
+            &replay.frames[1].packets[0],
+            Packet::ChunkDiff { changes, .. } if changes.len() == 1
+        ));
+
+        assert!(matches!(
+            decode_replay(b"VXRG\x01\x00"),
+            Err(ReplayError::NotReplay)
+        ));
+        assert!(decode_replay(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_apply_packets_to_world() {
+        let mut world = HashMap::new();
+        let mut unloaded = UnloadedChunks::default();
+        world.insert(ChunkPos::new(0, 0, 0), ChunkData::new());
+
+        // 已加载的区块：快照按差异写入并记入变更日志
+        let mut snapshot = ChunkData::new();
+        snapshot.voxels.set(5, VoxelKind::Sand);
+        snapshot.flags.set(5, VoxelFlags::WET);
+        let leftover = apply_packet(
+            &mut world,
+            &mut unloaded,
+            Packet::ChunkSnapshot {
+                pos: ChunkPos::new(0, 0, 0),
+                chunk: Box::new(snapshot),
+            },
+        );
+        assert!(leftover.is_none());
+        let chunk = &world[&ChunkPos::new(0, 0, 0)];
+        assert_eq!(chunk.voxels.get(5), VoxelKind::Sand);
+        assert_eq!(chunk.changes.len(), 2);
+        assert_eq!(chunk.dirty_blocks, vec![5]);
+
+        // 还没有生成的区块：变更等到区块加载后再写入
+        let mut pending = PendingPackets::default();
+        let diff = Packet::ChunkDiff {
+            pos: ChunkPos::new(1, 0, 0),
+            changes: vec![BlockChange::SetVoxel {
+                idx: 9,
+                old: VoxelKind::Air,
+                new: VoxelKind::Water,
+            }],
+        };
+        pending.push(apply_packet(&mut world, &mut unloaded, diff).unwrap());
+        assert_eq!(pending.flush(&mut world, &mut unloaded), 0);
+        assert_eq!(pending.len(), 1);
+
+        world.insert(ChunkPos::new(1, 0, 0), ChunkData::new());
+        assert_eq!(pending.flush(&mut world, &mut unloaded), 1);
+        assert!(pending.is_empty());
+        assert_eq!(
+            world[&ChunkPos::new(1, 0, 0)].voxels.get(9),
+            VoxelKind::Water
+        );
+    }
+}
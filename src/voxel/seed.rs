@@ -1,11 +1,11 @@
-//! 世界种子与噪声生成器
+//! 世界种子、噪声生成器和简单的伪随机数
 
 use bevy::prelude::*;
 use noise::Perlin;
 
 /// 世界种子 - 存储世界生成的随机种子和各种噪声生成器
 /// 使用相同的种子可以生成相同的世界
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 pub struct WorldSeed {
     /// 主种子值
     pub seed: u32,
@@ -19,6 +19,12 @@ pub struct WorldSeed {
     pub cave_noise: Perlin,
     /// 细节噪声生成器（用于矿石、树木等）
     pub detail_noise: Perlin,
+    /// 山地分布噪声生成器
+    pub mountain_noise: Perlin,
+    /// 山脊形状噪声生成器
+    pub ridge_noise: Perlin,
+    /// 河道噪声生成器（零值等高线即河道中心线）
+    pub river_noise: Perlin,
 }
 
 impl WorldSeed {
@@ -32,6 +38,9 @@ impl WorldSeed {
             biome_humid_noise: Perlin::new(seed.wrapping_add(2000)),
             cave_noise: Perlin::new(seed.wrapping_add(3000)),
             detail_noise: Perlin::new(seed.wrapping_add(4000)),
+            mountain_noise: Perlin::new(seed.wrapping_add(5000)),
+            ridge_noise: Perlin::new(seed.wrapping_add(6000)),
+            river_noise: Perlin::new(seed.wrapping_add(7000)),
         }
     }
 
@@ -43,6 +52,23 @@ impl WorldSeed {
             .fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32));
         Self::new(seed)
     }
+
+    /// 解析用户输入的种子：纯数字直接作为种子，其他文本按 [`from_string`](Self::from_string) 哈希
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        match text.parse::<u32>() {
+            Ok(seed) => Self::new(seed),
+            Err(_) => Self::from_string(text),
+        }
+    }
+
+    /// 根据当前时间生成随机种子
+    pub fn random_seed() -> u32 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32 ^ d.subsec_nanos().rotate_left(16))
+            .unwrap_or(12345)
+    }
 }
 
 impl Default for WorldSeed {
@@ -50,3 +76,60 @@ impl Default for WorldSeed {
         Self::new(12345)
     }
 }
+
+/// xorshift32 伪随机数生成器
+///
+/// 用于生长、积雪、粒子和生物等不需要跟世界种子关联的随机判定，同一初始状态总得到相同的序列
+#[derive(Debug, Clone)]
+pub struct XorShift32(u32);
+
+impl XorShift32 {
+    /// 以 state 为初始状态创建，state 不能为 0（否则只会得到 0）
+    pub const fn new(state: u32) -> Self {
+        Self(state)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// [0, 1) 内的随机数
+    ///
+    /// 只取高 24 位：f32 的尾数放得下，结果不会舍入到 1.0
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// [min, max) 内的随机数
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xorshift_stays_below_one() {
+        let mut rng = XorShift32::new(0x9e37_79b9);
+        for _ in 0..100_000 {
+            let value = rng.next_f32();
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_xorshift_is_deterministic() {
+        let mut a = XorShift32::new(42);
+        let mut b = XorShift32::new(42);
+        for _ in 0..16 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+        let value = a.range(-2.0, 3.0);
+        assert!((-2.0..3.0).contains(&value));
+    }
+}
@@ -0,0 +1,255 @@
+//! 只读区块快照
+//!
+//! 模拟阶段一直以可变方式访问区块数据（[`ChunksMut`](crate::voxel::chunk::ChunksMut)），
+//! 只需要读取方块的系统（AI、光照、网络同步等）如果也查询 [`ChunkData`] 就会和模拟互相排队。它们改为读取
+//! [`WorldSnapshot`]：每个已加载区块的方块类型、标志位和变体的不可变副本，
+//! 用 `Arc` 共享，只借用快照的系统可以和模拟并行运行。
+//!
+//! 快照在这些时刻刷新：
+//! - 模拟 tick 的后处理阶段、清理变更日志之前，重新拷贝本 tick 有变更记录的区块
+//! - 区块加载和卸载后（读取 [`ChunkLoaded`] 和 [`ChunkUnloaded`]），加入或移除对应区块
+//!
+//! 刷新只替换变化区块的 `Arc`，其余区块继续共享同一份数据；已经取到的区块快照
+//! 不会被修改。克隆整个 [`WorldSnapshot`] 只增加引用计数，可以交给异步任务在后台读取。
+//!
+//! 光照不在快照中：它在区块到达后异步重算，不经过变更日志。
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::voxel::chunk::{ChunkData, ChunkLookup, ChunkPos, Chunks, VoxelWorld};
+use crate::voxel::events::{ChunkLoaded, ChunkUnloaded};
+use crate::voxel::flags::VoxelFlags;
+use crate::voxel::palette::PalettedArray;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 一个区块在某一时刻的方块数据副本
+#[derive(Debug, Clone)]
+pub struct ChunkSnapshot {
+    pub voxels: PalettedArray<VoxelKind>,
+    pub flags: PalettedArray<VoxelFlags>,
+    pub variant: PalettedArray<u8>,
+}
+
+impl ChunkSnapshot {
+    /// 拷贝区块当前的方块类型、标志位和变体
+    pub fn capture(chunk: &ChunkData) -> Self {
+        Self {
+            voxels: chunk.voxels.clone(),
+            flags: chunk.flags.clone(),
+            variant: chunk.variant.clone(),
+        }
+    }
+
+    /// 获取区块内指定局部坐标的方块类型
+    pub fn get(&self, x: i32, y: i32, z: i32) -> VoxelKind {
+        self.voxels.get(ChunkData::index(x, y, z))
+    }
+}
+
+/// 整个世界的只读快照
+#[derive(Resource, Clone, Default)]
+pub struct WorldSnapshot {
+    chunks: Arc<HashMap<ChunkPos, Arc<ChunkSnapshot>>>,
+    /// 每次刷新加一，读取方可以据此判断快照是否变化
+    generation: u64,
+}
+
+impl WorldSnapshot {
+    /// 获取区块快照，区块未加载时返回 None
+    pub fn chunk(&self, chunk_pos: ChunkPos) -> Option<&Arc<ChunkSnapshot>> {
+        self.chunks.get(&chunk_pos)
+    }
+
+    /// 快照中的区块坐标
+    pub fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// 快照中的区块数
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// 快照被刷新过的次数
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// 获取指定世界坐标的方块类型，区块未加载时视为空气
+    pub fn get_voxel(&self, world_pos: IVec3) -> VoxelKind {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(world_pos);
+        self.chunks
+            .get(&chunk_pos)
+            .map(|chunk| chunk.voxels.get(idx))
+            .unwrap_or(VoxelKind::Air)
+    }
+
+    /// 获取指定世界坐标的标志位，区块未加载时为空
+    pub fn get_flags(&self, world_pos: IVec3) -> VoxelFlags {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(world_pos);
+        self.chunks
+            .get(&chunk_pos)
+            .map(|chunk| chunk.flags.get(idx))
+            .unwrap_or(VoxelFlags::NONE)
+    }
+
+    /// 获取指定世界坐标的变体，区块未加载时为 0
+    pub fn get_variant(&self, world_pos: IVec3) -> u8 {
+        let (chunk_pos, idx) = VoxelWorld::split_world_pos(world_pos);
+        self.chunks
+            .get(&chunk_pos)
+            .map(|chunk| chunk.variant.get(idx))
+            .unwrap_or(0)
+    }
+
+    /// 按世界中的当前数据更新这些区块的快照，世界中已没有的区块从快照移除
+    ///
+    /// 其他持有旧快照的读取方不受影响：区块表被共享时先复制一份（只复制 `Arc`）
+    pub fn refresh(
+        &mut self,
+        world: &impl ChunkLookup,
+        chunk_positions: impl IntoIterator<Item = ChunkPos>,
+    ) {
+        let mut chunk_positions = chunk_positions.into_iter().peekable();
+        if chunk_positions.peek().is_none() {
+            return;
+        }
+
+        let chunks = Arc::make_mut(&mut self.chunks);
+        for chunk_pos in chunk_positions {
+            match world.chunk(&chunk_pos) {
+                Some(chunk) => {
+                    chunks.insert(chunk_pos, Arc::new(ChunkSnapshot::capture(chunk)));
+                }
+                None => {
+                    chunks.remove(&chunk_pos);
+                }
+            }
+        }
+        self.generation += 1;
+    }
+}
+
+/// 重新拷贝本 tick 有变更记录的区块
+///
+/// 在 SimulationSet::Post 中、清理变更日志之前运行
+pub fn refresh_changed_snapshots(chunks: Chunks, mut snapshot: ResMut<WorldSnapshot>) {
+    let changed: Vec<ChunkPos> = chunks
+        .iter()
+        .filter(|(_, chunk)| !chunk.changes.is_empty())
+        .map(|(chunk_pos, _)| chunk_pos)
+        .collect();
+    // 没有变化时不借用为可变，避免触发资源的变化检测
+    if !changed.is_empty() {
+        snapshot.refresh(&chunks, changed);
+    }
+}
+
+/// 加入刚加载的区块、移除已卸载的区块
+///
+/// 同一帧内先卸载又加载（或反过来）的区块按世界中的最终状态处理
+pub fn track_snapshot_chunks(
+    chunks: Chunks,
+    mut loaded: MessageReader<ChunkLoaded>,
+    mut unloaded: MessageReader<ChunkUnloaded>,
+    mut snapshot: ResMut<WorldSnapshot>,
+) {
+    let mut touched: Vec<ChunkPos> = loaded.read().map(|event| event.chunk_pos).collect();
+    touched.extend(unloaded.read().map(|event| event.chunk_pos));
+    if touched.is_empty() {
+        return;
+    }
+    touched.sort_unstable();
+    touched.dedup();
+    snapshot.refresh(&chunks, touched);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::change::BlockChange;
+    use crate::voxel::chunk::{spawn_chunk, ChunkIndex};
+
+    fn world_with_chunk(chunk_pos: ChunkPos, kind: VoxelKind) -> HashMap<ChunkPos, ChunkData> {
+        let mut chunk = ChunkData::new();
+        chunk.set(1, 2, 3, kind);
+        HashMap::from([(chunk_pos, chunk)])
+    }
+
+    #[test]
+    fn test_refresh_copies_and_removes_chunks() {
+        let chunk_pos = ChunkPos::new(-1, 0, 2);
+        let mut world = world_with_chunk(chunk_pos, VoxelKind::Stone);
+        let mut snapshot = WorldSnapshot::default();
+
+        snapshot.refresh(&world, [chunk_pos]);
+        let pos = chunk_pos.world_origin() + IVec3::new(1, 2, 3);
+        assert_eq!(snapshot.get_voxel(pos), VoxelKind::Stone);
+        assert_eq!(snapshot.get_voxel(pos + IVec3::X), VoxelKind::Air);
+        assert_eq!(
+            snapshot.chunk(chunk_pos).unwrap().get(1, 2, 3),
+            VoxelKind::Stone
+        );
+        assert_eq!(snapshot.generation(), 1);
+
+        world.remove(&chunk_pos);
+        snapshot.refresh(&world, [chunk_pos]);
+        assert!(snapshot.is_empty());
+        assert_eq!(snapshot.generation(), 2);
+
+        // 没有区块要刷新时快照不变
+        snapshot.refresh(&world, std::iter::empty());
+        assert_eq!(snapshot.generation(), 2);
+    }
+
+    #[test]
+    fn test_held_snapshot_is_unchanged_by_refresh() {
+        let chunk_pos = ChunkPos::new(0, 0, 0);
+        let mut world = world_with_chunk(chunk_pos, VoxelKind::Stone);
+        let mut snapshot = WorldSnapshot::default();
+        snapshot.refresh(&world, [chunk_pos]);
+
+        let held = snapshot.clone();
+        let held_chunk = snapshot.chunk(chunk_pos).unwrap().clone();
+        world
+            .get_mut(&chunk_pos)
+            .unwrap()
+            .set(1, 2, 3, VoxelKind::Water);
+        snapshot.refresh(&world, [chunk_pos]);
+
+        assert_eq!(snapshot.get_voxel(IVec3::new(1, 2, 3)), VoxelKind::Water);
+        assert_eq!(held.get_voxel(IVec3::new(1, 2, 3)), VoxelKind::Stone);
+        assert_eq!(held_chunk.get(1, 2, 3), VoxelKind::Stone);
+    }
+
+    #[test]
+    fn test_changed_chunks_are_refreshed() {
+        let mut app = App::new();
+        app.init_resource::<ChunkIndex>()
+            .init_resource::<WorldSnapshot>()
+            .add_systems(Update, refresh_changed_snapshots);
+
+        let changed_pos = ChunkPos::new(0, 0, 0);
+        let quiet_pos = ChunkPos::new(1, 0, 0);
+        let mut changed = ChunkData::new();
+        changed.set(0, 0, 0, VoxelKind::Sand);
+        changed.changes.push(BlockChange::SetVoxel {
+            idx: ChunkData::index(0, 0, 0),
+            old: VoxelKind::Air,
+            new: VoxelKind::Sand,
+        });
+        spawn_chunk(app.world_mut(), changed_pos, changed);
+        spawn_chunk(app.world_mut(), quiet_pos, ChunkData::new());
+        app.update();
+
+        let snapshot = app.world().resource::<WorldSnapshot>();
+        assert_eq!(snapshot.get_voxel(IVec3::ZERO), VoxelKind::Sand);
+        assert!(snapshot.chunk(quiet_pos).is_none());
+    }
+}
@@ -0,0 +1,152 @@
+//! 区块不透明位掩码
+//!
+//! 每个体素一位，记录它是否是不透明的整格方块（会遮挡相邻方块的面，见
+//! [`VoxelRegistry::occludes`]）。区块生成或派发重建时构建一次，
+//! 网格构建用它提前跳过不可见的部分，不必逐个查询体素和六个相邻方块：
+//!
+//! - 整个区块不透明并且被不透明的相邻区块包围时直接返回空网格
+//! - 整层不透明、上下两层和四周相邻区块的边界也不透明的层整层跳过
+//! - 六个相邻位置都在区块内且不透明的体素逐个跳过（按行用位运算求出）
+
+use crate::voxel::chunk::ChunkData;
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::registry::VoxelRegistry;
+use crate::voxel::voxel_kind::VoxelKind;
+
+const SIZE: usize = CHUNK_SIZE as usize;
+
+// 每行用一个 u16 存储，区块边长必须是 16
+const _: () = assert!(CHUNK_SIZE == 16);
+
+/// 区块的不透明体素位掩码：`rows[y][z]` 的第 x 位表示 (x, y, z) 是否不透明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidMask {
+    rows: [[u16; SIZE]; SIZE],
+}
+
+impl SolidMask {
+    /// 全部透明
+    pub const EMPTY: Self = Self {
+        rows: [[0; SIZE]; SIZE],
+    };
+    /// 全部不透明
+    pub const FULL: Self = Self {
+        rows: [[u16::MAX; SIZE]; SIZE],
+    };
+
+    /// 按 [`ChunkData::index`] 顺序的体素构建
+    pub fn from_voxels(voxels: impl IntoIterator<Item = VoxelKind>) -> Self {
+        let registry = VoxelRegistry::current();
+        let mut mask = Self::EMPTY;
+        for (index, kind) in voxels.into_iter().enumerate().take(ChunkData::VOXEL_COUNT) {
+            if registry.occludes(kind) {
+                let (x, row) = (index % SIZE, index / SIZE);
+                mask.rows[row / SIZE][row % SIZE] |= 1 << x;
+            }
+        }
+        mask
+    }
+
+    /// 从区块构建；调色板中只有不透明的整格方块时不必逐个检查
+    pub fn from_chunk(chunk: &ChunkData) -> Self {
+        let registry = VoxelRegistry::current();
+        if !chunk.voxels.may_contain(|kind| !registry.occludes(kind)) {
+            return Self::FULL;
+        }
+        Self::from_voxels(chunk.voxels.iter())
+    }
+
+    /// (x, y, z) 是否不透明
+    pub fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+        self.rows[y as usize][z as usize] & (1 << x) != 0
+    }
+
+    /// 区块是否完全不透明
+    pub fn is_full(&self) -> bool {
+        (0..SIZE).all(|y| self.is_layer_full(y))
+    }
+
+    /// 第 y 层是否完全不透明
+    pub fn is_layer_full(&self, y: usize) -> bool {
+        self.rows[y].iter().all(|&row| row == u16::MAX)
+    }
+
+    /// 第 y 层第 z 行中被完全包围的体素：自身和六个相邻位置都在区块内且不透明，第 x 位对应 x
+    ///
+    /// 区块边缘的体素相邻位置在相邻区块中，总是不算被包围
+    pub fn enclosed_row(&self, y: usize, z: usize) -> u16 {
+        if y == 0 || y == SIZE - 1 || z == 0 || z == SIZE - 1 {
+            return 0;
+        }
+        let row = self.rows[y][z];
+        // 左移右移时移入的 0 排除了 x = 0 和 x = 15
+        row & (row << 1)
+            & (row >> 1)
+            & self.rows[y][z - 1]
+            & self.rows[y][z + 1]
+            & self.rows[y - 1][z]
+            & self.rows[y + 1][z]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_matches_voxels() {
+        let mut chunk = ChunkData::new();
+        chunk.set(3, 4, 5, VoxelKind::Stone);
+        chunk.set(15, 15, 15, VoxelKind::Dirt);
+        chunk.set(0, 0, 0, VoxelKind::Water);
+
+        let mask = SolidMask::from_chunk(&chunk);
+        assert!(mask.is_solid(3, 4, 5));
+        assert!(mask.is_solid(15, 15, 15));
+        assert!(!mask.is_solid(0, 0, 0));
+        assert!(!mask.is_solid(4, 4, 5));
+        assert!(!mask.is_full());
+        assert_eq!(mask, SolidMask::from_voxels(chunk.voxels.iter()));
+    }
+
+    #[test]
+    fn test_non_cube_shapes_are_not_solid() {
+        let mut chunk = ChunkData::new();
+        chunk.set(2, 2, 2, VoxelKind::Cactus);
+        chunk.set(3, 2, 2, VoxelKind::Flower);
+        chunk.set(4, 2, 2, VoxelKind::Sand);
+
+        let mask = SolidMask::from_chunk(&chunk);
+        assert!(!mask.is_solid(2, 2, 2));
+        assert!(!mask.is_solid(3, 2, 2));
+        assert!(mask.is_solid(4, 2, 2));
+    }
+
+    #[test]
+    fn test_enclosed_voxels() {
+        let mut chunk = ChunkData::new();
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, VoxelKind::Stone);
+                }
+            }
+        }
+        assert_eq!(SolidMask::from_chunk(&chunk), SolidMask::FULL);
+
+        // 挖掉一格后，它的六个相邻体素不再被包围，它所在的层也不再完整
+        chunk.set(8, 8, 8, VoxelKind::Air);
+        let mask = SolidMask::from_chunk(&chunk);
+        assert!(!mask.is_layer_full(8));
+        assert!(mask.is_layer_full(7));
+        assert_eq!(mask.enclosed_row(8, 8) & (0b111 << 7), 0);
+        assert_eq!(mask.enclosed_row(7, 8) & (1 << 8), 0);
+        assert_eq!(mask.enclosed_row(8, 7) & (1 << 8), 0);
+        assert_ne!(mask.enclosed_row(7, 7) & (1 << 8), 0);
+
+        // 区块边缘的体素不算被包围
+        assert_eq!(mask.enclosed_row(0, 4), 0);
+        assert_eq!(mask.enclosed_row(4, 4) & 1, 0);
+        assert_eq!(mask.enclosed_row(4, 4) & (1 << 15), 0);
+    }
+}
@@ -0,0 +1,415 @@
+//! 区块同步协议
+//!
+//! 多人模式下服务器与客户端之间交换的数据包及其二进制编码（小端序）。
+//!
+//! ## 帧格式
+//!
+//! 每个数据包前是 u32 长度（不含自身），数据包第一个字节是类型：
+//!
+//! - 0 Welcome：协议版本（u16）、世界种子（u32）
+//! - 1 ChunkSnapshot：区块坐标（3 × i32），随后是与区域文件相同的三段游程编码数据
+//! - 2 ChunkDiff：区块坐标（3 × i32）、变更数量（u32），每条变更为类型（u8）、方块下标（u16）和数据：
+//!   - 0 SetVoxel：旧、新体素编号（2 × u8）
+//!   - 1 SetFlag：标志位（u16）、是否设置（u8）
+//!   - 2 SetVariant：旧、新变体（2 × u8）
+//!   - 3 SetTemp / 4 SetMoisture：数值（f32）
+//!   - 5 FillRun：方块下标为段起点，随后是段长度（u16）、新体素编号（u8）
+
+use super::change::BlockChange;
+use super::chunk::{ChunkData, ChunkPos};
+use super::domains::phase::PhaseTransition;
+use super::flags::VoxelFlags;
+use super::persistence::{voxel_from_id, write_chunk, ByteReader, StorageError};
+
+/// 协议版本，客户端与服务器不一致时拒绝连接
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// 单个数据包的最大长度，超过时认为数据流已损坏
+pub const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
+
+/// 同步数据包
+pub enum Packet {
+    /// 服务器 → 客户端：连接后的第一个数据包
+    Welcome { version: u16, seed: u32 },
+    /// 服务器 → 客户端：被修改过的区块的完整数据
+    ChunkSnapshot {
+        pos: ChunkPos,
+        chunk: Box<ChunkData>,
+    },
+    /// 双向：一个 tick 内某个区块的变更
+    ChunkDiff {
+        pos: ChunkPos,
+        changes: Vec<BlockChange>,
+    },
+}
+
+/// 数据包解析错误
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("数据包内容错误: {0}")]
+    Data(#[from] StorageError),
+    #[error("未知的数据包类型: {0}")]
+    UnknownPacket(u8),
+    #[error("未知的变更类型: {0}")]
+    UnknownChange(u8),
+    #[error("数据包过大: {0} 字节")]
+    TooLarge(usize),
+    #[error("数据包末尾有多余的 {0} 字节")]
+    TrailingBytes(usize),
+}
+
+// ============================================================================
+// 编码
+// ============================================================================
+
+/// 编码数据包，包含长度前缀
+pub fn encode_frame(packet: &Packet) -> Vec<u8> {
+    let mut out = vec![0; 4];
+    match packet {
+        Packet::Welcome { version, seed } => {
+            out.push(0);
+            out.extend_from_slice(&version.to_le_bytes());
+            out.extend_from_slice(&seed.to_le_bytes());
+        }
+        Packet::ChunkSnapshot { pos, chunk } => {
+            out.push(1);
+            write_chunk_pos(&mut out, *pos);
+            write_chunk(&mut out, chunk);
+        }
+        Packet::ChunkDiff { pos, changes } => {
+            out.push(2);
+            write_chunk_pos(&mut out, *pos);
+            out.extend_from_slice(&(changes.len() as u32).to_le_bytes());
+            for change in changes {
+                write_change(&mut out, change);
+            }
+        }
+    }
+    let len = (out.len() - 4) as u32;
+    out[..4].copy_from_slice(&len.to_le_bytes());
+    out
+}
+
+fn write_chunk_pos(out: &mut Vec<u8>, pos: ChunkPos) {
+    for coord in [pos.x, pos.y, pos.z] {
+        out.extend_from_slice(&coord.to_le_bytes());
+    }
+}
+
+fn write_change(out: &mut Vec<u8>, change: &BlockChange) {
+    let tag = match change {
+        BlockChange::SetVoxel { .. } => 0u8,
+        BlockChange::SetFlag { .. } => 1,
+        BlockChange::SetVariant { .. } => 2,
+        BlockChange::SetTemp { .. } => 3,
+        BlockChange::SetMoisture { .. } => 4,
+        BlockChange::FillRun { .. } => 5,
+    };
+    out.push(tag);
+    out.extend_from_slice(&(change.idx() as u16).to_le_bytes());
+
+    match change {
+        BlockChange::SetVoxel { old, new, .. } => {
+            out.push(old.id());
+            out.push(new.id());
+        }
+        BlockChange::SetFlag { flag, set, .. } => {
+            out.extend_from_slice(&flag.bits().to_le_bytes());
+            out.push(*set as u8);
+        }
+        BlockChange::SetVariant { old, new, .. } => {
+            out.push(*old);
+            out.push(*new);
+        }
+        BlockChange::SetTemp { temp: value, .. }
+        | BlockChange::SetMoisture {
+            moisture: value, ..
+        } => {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        BlockChange::FillRun { len, new, .. } => {
+            out.extend_from_slice(&(*len as u16).to_le_bytes());
+            out.push(new.id());
+        }
+    }
+}
+
+// ============================================================================
+// 解码
+// ============================================================================
+
+/// 从字节流中拆分数据包
+///
+/// 网络读取到的字节按到达顺序追加进来，凑齐一个完整数据包后才会解析
+#[derive(Default)]
+pub struct FrameReader {
+    buffer: Vec<u8>,
+}
+
+impl FrameReader {
+    /// 追加收到的字节
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// 取出下一个完整的数据包，数据不足时返回 None
+    ///
+    /// 返回错误后数据流已无法继续解析，应断开连接
+    pub fn next_packet(&mut self) -> Option<Result<Packet, SyncError>> {
+        let header: [u8; 4] = self.buffer.get(..4)?.try_into().ok()?;
+        let len = u32::from_le_bytes(header) as usize;
+        if len > MAX_PACKET_SIZE {
+            return Some(Err(SyncError::TooLarge(len)));
+        }
+        if self.buffer.len() < 4 + len {
+            return None;
+        }
+
+        let packet = decode_packet(&self.buffer[4..4 + len]);
+        self.buffer.drain(..4 + len);
+        Some(packet)
+    }
+}
+
+/// 解析不含长度前缀的数据包
+pub fn decode_packet(bytes: &[u8]) -> Result<Packet, SyncError> {
+    let mut reader = ByteReader::new(bytes);
+    let packet = match reader.u8()? {
+        0 => Packet::Welcome {
+            version: reader.u16()?,
+            seed: reader.u32()?,
+        },
+        1 => Packet::ChunkSnapshot {
+            pos: read_chunk_pos(&mut reader)?,
+            chunk: Box::new(reader.chunk()?),
+        },
+        2 => {
+            let pos = read_chunk_pos(&mut reader)?;
+            let count = reader.u32()? as usize;
+            // 每条变更至少 4 字节，先检查长度，避免按错误的数量预分配
+            if count > bytes.len() / 4 {
+                return Err(StorageError::Corrupt("变更数量超出数据长度").into());
+            }
+            let changes = (0..count)
+                .map(|_| read_change(&mut reader))
+                .collect::<Result<Vec<_>, _>>()?;
+            Packet::ChunkDiff { pos, changes }
+        }
+        tag => return Err(SyncError::UnknownPacket(tag)),
+    };
+
+    if reader.remaining() > 0 {
+        return Err(SyncError::TrailingBytes(reader.remaining()));
+    }
+    Ok(packet)
+}
+
+fn read_chunk_pos(reader: &mut ByteReader) -> Result<ChunkPos, StorageError> {
+    Ok(ChunkPos::new(reader.i32()?, reader.i32()?, reader.i32()?))
+}
+
+fn read_change(reader: &mut ByteReader) -> Result<BlockChange, SyncError> {
+    let tag = reader.u8()?;
+    let idx = reader.u16()? as usize;
+    if idx >= ChunkData::VOXEL_COUNT {
+        return Err(StorageError::Corrupt("方块下标超出区块范围").into());
+    }
+
+    let change = match tag {
+        0 => BlockChange::SetVoxel {
+            idx,
+            old: voxel_from_id(reader.u8()? as u16)?,
+            new: voxel_from_id(reader.u8()? as u16)?,
+        },
+        1 => BlockChange::SetFlag {
+            idx,
+            flag: VoxelFlags::from_bits_truncate(reader.u16()?),
+            set: reader.u8()? != 0,
+        },
+        2 => BlockChange::SetVariant {
+            idx,
+            old: reader.u8()?,
+            new: reader.u8()?,
+        },
+        3 => BlockChange::SetTemp {
+            idx,
+            temp: reader.f32()?,
+        },
+        4 => BlockChange::SetMoisture {
+            idx,
+            moisture: reader.f32()?,
+        },
+        5 => {
+            let len = reader.u16()? as usize;
+            if idx + len > ChunkData::VOXEL_COUNT {
+                return Err(StorageError::Corrupt("填充段超出区块范围").into());
+            }
+            BlockChange::FillRun {
+                start: idx,
+                len,
+                new: voxel_from_id(reader.u8()? as u16)?,
+            }
+        }
+        tag => return Err(SyncError::UnknownChange(tag)),
+    };
+    Ok(change)
+}
+
+/// 把变更直接写入区块数据（不经过命令系统，也不记录变更日志）
+///
+/// 用于服务器保存的区块和客户端尚未加载的区块；温度和湿度没有保存在快照中，会被忽略
+pub fn apply_changes(chunk: &mut ChunkData, changes: &[BlockChange]) {
+    for change in changes {
+        match *change {
+            BlockChange::SetVoxel { idx, new, .. } => chunk.voxels.set(idx, new),
+            BlockChange::SetFlag { idx, flag, set } => {
+                chunk.flags.update(idx, |flags| flags.set(flag, set));
+            }
+            BlockChange::SetVariant { idx, new, .. } => chunk.variant.set(idx, new),
+            BlockChange::SetTemp { .. } | BlockChange::SetMoisture { .. } => {}
+            BlockChange::FillRun { start, len, new } => {
+                for idx in start..start + len {
+                    chunk.voxels.set(idx, new);
+                    chunk.variant.set(idx, 0);
+                    for flag in PhaseTransition::ALL_FLAGS {
+                        chunk.flags.update(idx, |flags| flags.remove(flag));
+                    }
+                }
+            }
+        }
+    }
+    chunk.is_modified = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::voxel_kind::VoxelKind;
+
+    fn round_trip(packet: &Packet) -> Packet {
+        let mut reader = FrameReader::default();
+        let frame = encode_frame(packet);
+        // 分两次到达，第一次不足一个数据包
+        reader.extend(&frame[..frame.len() / 2]);
+        assert!(reader.next_packet().is_none());
+        reader.extend(&frame[frame.len() / 2..]);
+        let packet = reader.next_packet().unwrap().unwrap();
+        assert!(reader.next_packet().is_none());
+        packet
+    }
+
+    #[test]
+    fn test_diff_round_trip() {
+        let changes = vec![
+            BlockChange::SetVoxel {
+                idx: 4095,
+                old: VoxelKind::Stone,
+                new: VoxelKind::Air,
+            },
+            BlockChange::SetFlag {
+                idx: 7,
+                flag: VoxelFlags::BURNING,
+                set: true,
+            },
+            BlockChange::SetVariant {
+                idx: 8,
+                old: 0,
+                new: 3,
+            },
+            BlockChange::SetTemp {
+                idx: 9,
+                temp: -12.5,
+            },
+            BlockChange::SetMoisture {
+                idx: 10,
+                moisture: 0.25,
+            },
+            BlockChange::FillRun {
+                start: 4000,
+                len: 96,
+                new: VoxelKind::Water,
+            },
+        ];
+        let packet = Packet::ChunkDiff {
+            pos: ChunkPos::new(-2, 3, 40),
+            changes: changes.clone(),
+        };
+
+        let Packet::ChunkDiff {
+            pos,
+            changes: decoded,
+        } = round_trip(&packet)
+        else {
+            panic!("expected a diff packet");
+        };
+        assert_eq!(pos, ChunkPos::new(-2, 3, 40));
+        assert_eq!(decoded, changes);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut chunk = ChunkData::new();
+        chunk.set(1, 2, 3, VoxelKind::OakLog);
+        chunk.variant.set(5, 2);
+        chunk.flags.set(6, VoxelFlags::WET);
+        let packet = Packet::ChunkSnapshot {
+            pos: ChunkPos::new(0, -1, 0),
+            chunk: Box::new(chunk.clone()),
+        };
+
+        let Packet::ChunkSnapshot {
+            pos,
+            chunk: decoded,
+        } = round_trip(&packet)
+        else {
+            panic!("expected a snapshot packet");
+        };
+        assert_eq!(pos, ChunkPos::new(0, -1, 0));
+        assert_eq!(decoded.voxels, chunk.voxels);
+        assert_eq!(decoded.variant, chunk.variant);
+        assert_eq!(decoded.flags, chunk.flags);
+    }
+
+    #[test]
+    fn test_bad_packets_are_rejected() {
+        let mut frame = encode_frame(&Packet::Welcome {
+            version: PROTOCOL_VERSION,
+            seed: 42,
+        });
+        frame[4] = 9;
+        assert!(matches!(
+            decode_packet(&frame[4..]),
+            Err(SyncError::UnknownPacket(9))
+        ));
+
+        let mut reader = FrameReader::default();
+        reader.extend(&(MAX_PACKET_SIZE as u32 + 1).to_le_bytes());
+        assert!(matches!(
+            reader.next_packet(),
+            Some(Err(SyncError::TooLarge(_)))
+        ));
+    }
+
+    #[test]
+    fn test_apply_changes() {
+        let mut chunk = ChunkData::new();
+        apply_changes(
+            &mut chunk,
+            &[
+                BlockChange::SetVoxel {
+                    idx: 3,
+                    old: VoxelKind::Air,
+                    new: VoxelKind::Water,
+                },
+                BlockChange::SetFlag {
+                    idx: 3,
+                    flag: VoxelFlags::WET,
+                    set: true,
+                },
+            ],
+        );
+        assert_eq!(chunk.voxels.get(3), VoxelKind::Water);
+        assert!(chunk.flags.get(3).contains(VoxelFlags::WET));
+        assert!(chunk.is_modified);
+    }
+}
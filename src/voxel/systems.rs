@@ -1,170 +1,270 @@
 //! 体素世界的系统函数
 
+use bevy::camera::primitives::{Aabb, Frustum};
+use bevy::math::Affine3A;
 use bevy::prelude::*;
 use bevy::tasks::AsyncComputeTaskPool;
-use futures_lite::future;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::voxel::chunk::{ChunkData, ChunkMarker, ChunkPos, VoxelWorld};
-use crate::voxel::constants::{CHUNK_SIZE, RENDER_DISTANCE, VERTICAL_RENDER_DISTANCE};
+use crate::voxel::chunk::{
+    ChunkData, ChunkEntities, ChunkIndex, ChunkMarker, ChunkPos, ChunkSection, ChunksMut,
+    VoxelWorld,
+};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::domains::thermal::ThermalApi;
+use crate::voxel::events::{ChunkLoaded, ChunkRemeshed, ChunkUnloaded};
+use crate::voxel::light::{LightUpdates, NEIGHBOR_DIRS};
 use crate::voxel::loading::{
-    ChunkLoadQueue, ChunkReplacementBuffer, CompletedChunk, ComputeMeshTask, PlaceholderEntities,
+    chunk_in_range, CancelToken, ChunkLoadQueue, ChunkReplacementBuffer, ChunkTasks,
+    InFlightChunks, LoadPriority, LoadScan, MeshBuildInput, NeighborEdges, PlaceholderEntities,
+    RemeshScheduler, RenderDistance, TaskOutput, UnloadedChunks, RESORT_MOTION_DELTA,
+    RESORT_VIEW_COS, UNLOAD_HYSTERESIS,
 };
 use crate::voxel::materials::ChunkMaterials;
-use crate::voxel::mesh::create_placeholder_mesh;
-use crate::voxel::mesh_gen::generate_chunk_and_mesh_async;
-use crate::voxel::seed::WorldSeed;
+use crate::voxel::mesh::{create_placeholder_mesh, ChunkMeshes};
+use crate::voxel::mesh_gen::{
+    build_chunk_mesh_async, generate_chunk_and_mesh_async, light_and_mesh_chunk,
+};
+use crate::voxel::persistence::{ActiveWorld, RegionCache};
+use crate::voxel::profiling::{Stage, StageTimer};
+use crate::voxel::registry::VoxelRegistry;
+use crate::voxel::solid_mask::SolidMask;
+use crate::voxel::terrain::SharedTerrain;
+use crate::voxel::voxel_kind::VoxelKind;
+use crate::voxel::ivec3_to_vec3;
 
 // ============================================================================
 // 视锥剔除
 // ============================================================================
 
-/// 检查区块是否在摄像机视野内
-/// 使用简化的视锥剔除算法，基于摄像机朝向和视角范围
+/// 区块在世界空间中的包围盒
+fn chunk_aabb(chunk_pos: &ChunkPos) -> Aabb {
+    let min = ivec3_to_vec3(chunk_pos.world_origin());
+    Aabb::from_min_max(min, min + Vec3::splat(CHUNK_SIZE as f32))
+}
+
+/// 检查区块包围盒是否与摄像机视锥相交
+/// 不检测远裁剪面，可见距离由渲染距离决定
 ///
 /// # 参数
 /// * `chunk_pos` - 区块位置
-/// * `camera_transform` - 摄像机的Transform（包含位置和旋转）
+/// * `frustum` - 摄像机视锥（由 Bevy 根据投影和摄像机变换计算）
 ///
 /// # 返回值
 /// 如果区块在视野内返回 true
-fn is_chunk_in_frustum(chunk_pos: &ChunkPos, camera_transform: &Transform) -> bool {
-    let origin = chunk_pos.world_origin();
-
-    // 计算区块中心点（世界坐标）
-    let chunk_center = Vec3::new(
-        origin.x as f32 + CHUNK_SIZE as f32 / 2.0,
-        origin.y as f32 + CHUNK_SIZE as f32 / 2.0,
-        origin.z as f32 + CHUNK_SIZE as f32 / 2.0,
-    );
-
-    // 计算从摄像机到区块中心的向量
-    let to_chunk = chunk_center - camera_transform.translation;
-    let distance = to_chunk.length();
+fn is_chunk_in_frustum(chunk_pos: &ChunkPos, frustum: &Frustum) -> bool {
+    frustum.intersects_obb(&chunk_aabb(chunk_pos), &Affine3A::IDENTITY, true, false)
+}
 
-    // 如果距离太近，总是加载（避免摄像机内部的区块被剔除）
-    if distance < CHUNK_SIZE as f32 * 2.0 {
+/// 检查区块是否需要加载：在视锥内，或离摄像机足够近
+/// 摄像机周围的区块即使在身后也提前加载，转身时不会出现空洞
+fn is_chunk_in_load_view(chunk_pos: &ChunkPos, camera_pos: Vec3, frustum: &Frustum) -> bool {
+    let center = chunk_aabb(chunk_pos).center;
+    if Vec3::from(center).distance(camera_pos) < CHUNK_SIZE as f32 * 2.0 {
         return true;
     }
+    is_chunk_in_frustum(chunk_pos, frustum)
+}
 
-    // 获取摄像机的前向向量
-    let camera_forward = camera_transform.forward();
-
-    // 计算摄像机前向向量与到区块向量的夹角
-    let direction_normalized = to_chunk.normalize();
-    let dot_product = camera_forward.dot(direction_normalized);
-
-    // 视野角度约110度（FOV），cos(110°/2) ≈ cos(55°) ≈ 0.57
-    // 使用更宽的角度（130度）以包含边缘区块，避免误剔除
-    // cos(130°/2) = cos(65°) ≈ 0.42
-    const FOV_COS_THRESHOLD: f32 = 0.35;
-
-    // 如果夹角在视野范围内，返回 true
-    dot_product > FOV_COS_THRESHOLD
+/// 渲染剔除：隐藏视锥外的区块实体（子实体中的网格随父实体一起隐藏）
+/// 在 Bevy 更新视锥之后、传播可见性之前运行，使用当前帧的视锥
+///
+/// 摄像机位于地表以上时，被掩埋的地下区块（见 [`Heightmap`](crate::voxel::heightmap::Heightmap)）
+/// 也一并隐藏；进入洞穴后恢复渲染
+pub fn cull_chunk_visibility(
+    camera_query: Query<(&Frustum, &GlobalTransform), With<Camera3d>>,
+    world: Res<VoxelWorld>,
+    mut chunk_query: Query<(&ChunkMarker, &mut Visibility)>,
+) {
+    let Ok((frustum, camera_transform)) = camera_query.single() else {
+        return;
+    };
+    let camera_pos = camera_transform.translation();
+    let above_surface = world
+        .heightmap
+        .height(camera_pos.x.floor() as i32, camera_pos.z.floor() as i32)
+        .is_some_and(|height| camera_pos.y > height as f32);
+
+    for (marker, mut visibility) in chunk_query.iter_mut() {
+        let buried = above_surface && world.heightmap.is_buried(marker.pos);
+        let target = if !buried && is_chunk_in_frustum(&marker.pos, frustum) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(target);
+    }
 }
 
 // ============================================================================
 // 区块加载系统
 // ============================================================================
 
+/// 加载范围扫描中两次检查时间预算之间处理的偏移数
+const SCAN_BATCH: usize = 64;
+
 /// 更新区块加载系统
 /// 根据摄像机位置决定哪些区块需要加载或卸载
-/// 按距离排序：距离近的优先加载
-/// 优化: 添加视锥剔除，只加载视野内的区块
+///
+/// 增量调度，每帧只占用有限的时间：
+/// - 加载范围按距离从近到远分帧扫描（见 [`LoadScan`]），新区块按优先级（见 [`LoadPriority`]）
+///   插入已排序的队列，只有跨越区块边界或视线转过较大角度时才重新排序整个队列
+/// - 只有跨越区块边界或渲染距离改变时，才丢弃超出卸载范围的排队区块并检查卸载
+/// - 卸载范围比加载范围多出 [`UNLOAD_HYSTERESIS`] 层，在边界附近走动不会来回加载卸载
+/// - 向下的加载范围随摄像机离地表的高度延伸（见 [`RenderDistance::window`]）
+/// - 快速移动时加载范围沿移动方向前移、身后缩小，移动方向上的区块先加载
+///   （见 [`LoadMotion`](crate::voxel::loading::LoadMotion)）
+/// - 只加载视野内或离摄像机很近的区块
 pub fn update_chunk_loading(
-    camera_query: Query<&Transform, With<Camera3d>>,
+    camera_query: Query<(&Transform, &Frustum), With<Camera3d>>,
     world: Res<VoxelWorld>,
+    index: Res<ChunkIndex>,
     mut queue: ResMut<ChunkLoadQueue>,
-    pending_query: Query<&ComputeMeshTask>,
+    in_flight: InFlightChunks,
+    render_distance: Res<RenderDistance>,
+    terrain: Res<SharedTerrain>,
 ) {
-    let Ok(camera_transform) = camera_query.single() else {
+    if queue.paused {
+        return;
+    }
+    let Ok((camera_transform, frustum)) = camera_query.single() else {
         return;
     };
+    let started = Instant::now();
+    let queue = &mut *queue;
 
     let camera_pos = camera_transform.translation;
-    let center_chunk = ChunkPos::from_world_pos(
-        camera_pos.x as i32,
-        camera_pos.y as i32,
-        camera_pos.z as i32,
+    let camera_chunk = ChunkPos::containing(camera_pos);
+    let distance = *render_distance;
+    let forward = camera_transform.forward().as_vec3();
+    queue.motion.observe(camera_pos, started);
+    let motion = queue.motion.bias();
+    let priority = LoadPriority {
+        center: camera_chunk,
+        forward,
+        motion,
+        heightmap: &world.heightmap,
+    };
+    // 加载范围的中心，快速移动时在摄像机所在区块的前方
+    let lead = queue.motion.lead(distance.horizontal);
+    let center_chunk = ChunkPos::new(
+        camera_chunk.x + lead.x,
+        camera_chunk.y,
+        camera_chunk.z + lead.z,
     );
 
-    // 收集正在处理中的区块
-    let pending_chunks: Vec<ChunkPos> = pending_query.iter().map(|t| t.chunk_pos).collect();
-
-    // 收集需要加载的区块
-    let mut chunks_to_add = Vec::new();
-    for dx in -RENDER_DISTANCE..=RENDER_DISTANCE {
-        for dy in -VERTICAL_RENDER_DISTANCE..=VERTICAL_RENDER_DISTANCE {
-            for dz in -RENDER_DISTANCE..=RENDER_DISTANCE {
-                let chunk_pos = ChunkPos::new(
-                    center_chunk.x + dx,
-                    center_chunk.y + dy,
-                    center_chunk.z + dz,
-                );
-
-                // 优化: 视锥剔除 - 跳过视野外的区块
-                if !is_chunk_in_frustum(&chunk_pos, camera_transform) {
-                    continue;
-                }
-
-                if !world.loaded_chunks.contains_key(&chunk_pos)
-                    && !world.chunks.contains_key(&chunk_pos)
-                    && !queue.to_load.contains(&chunk_pos)
-                    && !pending_chunks.contains(&chunk_pos)
-                {
-                    chunks_to_add.push(chunk_pos);
-                }
-            }
-        }
+    if queue.scan.distance != Some(distance) {
+        queue.scan.distance = Some(distance);
+        queue.scan.center = None;
     }
 
-    // 按距离排序（距离近的优先）
-    chunks_to_add.sort_by(|a, b| {
-        let dist_a = (a.x - center_chunk.x).pow(2)
-            + (a.y - center_chunk.y).pow(2)
-            + (a.z - center_chunk.z).pow(2);
-        let dist_b = (b.x - center_chunk.x).pow(2)
-            + (b.y - center_chunk.y).pow(2)
-            + (b.z - center_chunk.z).pow(2);
-        dist_a.cmp(&dist_b)
-    });
+    let moved = queue.scan.center != Some(center_chunk);
+    let turned = queue
+        .scan
+        .sorted_forward
+        .is_none_or(|sorted| sorted.dot(forward) < RESORT_VIEW_COS)
+        || queue.scan.sorted_motion.distance(motion) > RESORT_MOTION_DELTA;
+    if moved {
+        queue.scan.center = Some(center_chunk);
+        queue.scan.cursor = 0;
+
+        // 加载范围向下延伸到附近最低的可见地表，高度只取决于种子，不依赖已加载的区块
+        let surface_y = terrain.generator().lowest_visible_surface(
+            camera_pos.x.floor() as i32,
+            camera_pos.z.floor() as i32,
+            distance.horizontal * CHUNK_SIZE,
+        );
+        let window = distance.window(center_chunk.y, surface_y);
+        if queue.scan.window != Some(window) {
+            queue.scan.offsets = LoadScan::load_offsets(window);
+            queue.scan.window = Some(window);
+        }
 
-    // 如果有新区块加入，将它们添加到待批量创建占位符列表
-    if !chunks_to_add.is_empty() {
+        // 走出卸载范围的区块不再加载
+        queue
+            .to_load
+            .retain(|&pos| chunk_in_range(center_chunk, pos, window, UNLOAD_HYSTERESIS));
+        // 又回到范围内、还没来得及卸载的区块保留下来
         queue
-            .pending_placeholders
-            .extend(chunks_to_add.iter().copied());
+            .to_unload
+            .retain(|&pos| !chunk_in_range(center_chunk, pos, window, UNLOAD_HYSTERESIS));
+
+        // 修复：检查所有chunk（包括空mesh的），而不只是loaded_chunks
+        // 还在生成或等待替换的区块也要卸载，否则它们到达后会留在范围外
+        let already_unloading: HashSet<ChunkPos> = queue.to_unload.iter().copied().collect();
+        let out_of_range = index
+            .positions()
+            .chain(in_flight.positions())
+            .filter(|&pos| {
+                !chunk_in_range(center_chunk, pos, window, UNLOAD_HYSTERESIS)
+                    && !already_unloading.contains(&pos)
+            });
+        queue.to_unload.extend(out_of_range);
     }
 
-    queue.to_load.extend(chunks_to_add);
+    if moved || turned {
+        // 排队的区块按新中心、视线方向和移动方向重新排序
+        priority.sort(&mut queue.to_load);
+        queue.scan.sorted_forward = Some(forward);
+        queue.scan.sorted_motion = motion;
+    }
 
-    // 重新排序整个队列（玩家移动后需要重新排序）
-    queue.to_load.sort_by(|a, b| {
-        let dist_a = (a.x - center_chunk.x).pow(2)
-            + (a.y - center_chunk.y).pow(2)
-            + (a.z - center_chunk.z).pow(2);
-        let dist_b = (b.x - center_chunk.x).pow(2)
-            + (b.y - center_chunk.y).pow(2)
-            + (b.z - center_chunk.z).pow(2);
-        dist_a.cmp(&dist_b)
-    });
+    // 世界上下限之外的区块全是空气，不加载
+    let config = terrain.config();
+    let chunk_y_range = config.terrain.min_y.div_euclid(CHUNK_SIZE)
+        ..=config.terrain.max_y.div_euclid(CHUNK_SIZE);
+    // 生成中和等待批量替换的区块也算在加载中
+    let mut queued: HashSet<ChunkPos> = queue
+        .to_load
+        .iter()
+        .copied()
+        .chain(in_flight.positions())
+        .collect();
 
-    // 查找需要卸载的区块（超出渲染距离+1）
-    // 修复：检查所有chunk（包括空mesh的），而不只是loaded_chunks
-    for &chunk_pos in world.chunks.keys() {
-        let dx = (chunk_pos.x - center_chunk.x).abs();
-        let dy = (chunk_pos.y - center_chunk.y).abs();
-        let dz = (chunk_pos.z - center_chunk.z).abs();
-
-        // 基本距离检查
-        let out_of_range = dx > RENDER_DISTANCE + 1
-            || dy > VERTICAL_RENDER_DISTANCE + 1
-            || dz > RENDER_DISTANCE + 1;
-
-        if out_of_range {
-            if !queue.to_unload.contains(&chunk_pos) {
-                queue.to_unload.push(chunk_pos);
-            }
+    if queue.scan.cursor >= queue.scan.offsets.len() {
+        queue.scan.cursor = 0;
+    }
+    let start_cursor = queue.scan.cursor;
+    let mut chunks_to_add = Vec::new();
+    while queue.scan.cursor < queue.scan.offsets.len() {
+        // 每检查一批偏移看一次时间，预算用完就留到下一帧
+        if (queue.scan.cursor - start_cursor) % SCAN_BATCH == SCAN_BATCH - 1
+            && started.elapsed() >= queue.scan_budget
+        {
+            break;
+        }
+        let offset = queue.scan.offsets[queue.scan.cursor];
+        queue.scan.cursor += 1;
+
+        let chunk_pos = ChunkPos::new(
+            center_chunk.x + offset.x,
+            center_chunk.y + offset.y,
+            center_chunk.z + offset.z,
+        );
+        if !chunk_y_range.contains(&chunk_pos.y)
+            || index.contains(&chunk_pos)
+            || queued.contains(&chunk_pos)
+        {
+            continue;
         }
+        // 优化: 视锥剔除 - 跳过视野外的区块
+        if !is_chunk_in_load_view(&chunk_pos, camera_pos, frustum) {
+            continue;
+        }
+        queued.insert(chunk_pos);
+        chunks_to_add.push(chunk_pos);
+    }
+
+    // 如果有新区块加入，将它们添加到待批量创建占位符列表
+    queue
+        .pending_placeholders
+        .extend(chunks_to_add.iter().copied());
+
+    // 按优先级插入，保持队列有序
+    for chunk_pos in chunks_to_add {
+        priority.insert(&mut queue.to_load, chunk_pos);
     }
 }
 
@@ -175,22 +275,20 @@ pub fn spawn_batch_placeholders(
     materials: Res<ChunkMaterials>,
     mut queue: ResMut<ChunkLoadQueue>,
     mut placeholders: ResMut<PlaceholderEntities>,
-    camera_query: Query<&Transform, With<Camera3d>>,
+    camera_query: Query<(&Transform, &Frustum), With<Camera3d>>,
 ) {
     if queue.pending_placeholders.is_empty() {
         return;
     }
+    let (Some(window), Some(center_chunk)) = (queue.scan.window, queue.scan.center) else {
+        return;
+    };
 
     // 获取摄像机位置用于清理不需要的占位符
-    let Ok(camera_transform) = camera_query.single() else {
+    let Ok((camera_transform, frustum)) = camera_query.single() else {
         return;
     };
     let camera_pos = camera_transform.translation;
-    let center_chunk = ChunkPos::from_world_pos(
-        camera_pos.x as i32,
-        camera_pos.y as i32,
-        camera_pos.z as i32,
-    );
 
     // 批量创建所有待创建的占位符（并过滤掉不需要的）
     let chunks_to_create: Vec<_> = queue
@@ -198,11 +296,8 @@ pub fn spawn_batch_placeholders(
         .drain(..)
         .filter(|chunk_pos| {
             // 只创建仍然在范围内且在视锥内的占位符
-            let dx = (chunk_pos.x - center_chunk.x).abs();
-            let dy = (chunk_pos.y - center_chunk.y).abs();
-            let dz = (chunk_pos.z - center_chunk.z).abs();
-            let in_range = dx <= RENDER_DISTANCE && dy <= VERTICAL_RENDER_DISTANCE && dz <= RENDER_DISTANCE;
-            let in_frustum = is_chunk_in_frustum(chunk_pos, camera_transform);
+            let in_range = chunk_in_range(center_chunk, *chunk_pos, window, 0);
+            let in_frustum = is_chunk_in_load_view(chunk_pos, camera_pos, frustum);
             in_range && in_frustum
         })
         .collect();
@@ -217,7 +312,7 @@ pub fn spawn_batch_placeholders(
         let placeholder_entity = commands
             .spawn((
                 Mesh3d(placeholder_handle.clone()),
-                MeshMaterial3d(materials.transparent.clone()),
+                MeshMaterial3d(materials.placeholder.clone()),
                 Transform::from_translation(Vec3::new(
                     origin.x as f32,
                     origin.y as f32,
@@ -232,15 +327,61 @@ pub fn spawn_batch_placeholders(
     }
 }
 
+/// 恢复卸载时保留的已修改区块
+///
+/// 直接放回世界并标记为脏，由网格重建流程使用相邻区块的真实边界构建网格
+pub fn restore_unloaded_chunks(
+    mut commands: Commands,
+    mut chunks: ChunkEntities,
+    mut queue: ResMut<ChunkLoadQueue>,
+    mut placeholders: ResMut<PlaceholderEntities>,
+    mut unloaded: ResMut<UnloadedChunks>,
+    mut light_updates: ResMut<LightUpdates>,
+    mut loaded_events: MessageWriter<ChunkLoaded>,
+) {
+    if unloaded.chunks.is_empty() {
+        return;
+    }
+
+    let mut restored = Vec::new();
+    queue.to_load.retain(|chunk_pos| {
+        let Some(mut chunk) = unloaded.chunks.remove(chunk_pos) else {
+            return true;
+        };
+        chunk.is_dirty = true;
+        chunks.insert(*chunk_pos, chunk);
+        restored.push(*chunk_pos);
+        false
+    });
+
+    for chunk_pos in &restored {
+        if let Some(entity) = placeholders.map.remove(chunk_pos) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    light_updates.arrived.extend(restored.iter().copied());
+    loaded_events.write_batch(restored.iter().map(|&chunk_pos| ChunkLoaded {
+        chunk_pos,
+        restored: true,
+    }));
+}
+
 /// 派发异步网格生成任务（使用已创建的占位符）
+///
+/// 任务先在当前世界的存档中查找区块（见 [`RegionCache`]），存档中没有时才从种子生成。
+/// 任务分离运行，完成后把结果发进 [`ChunkTasks`] 的通道
 pub fn spawn_mesh_tasks(
-    mut commands: Commands,
     mut queue: ResMut<ChunkLoadQueue>,
+    mut tasks: ResMut<ChunkTasks>,
     placeholders: ResMut<PlaceholderEntities>,
-    seed: Res<WorldSeed>,
+    terrain: Res<SharedTerrain>,
+    world: Res<VoxelWorld>,
+    active_world: Res<ActiveWorld>,
+    region_cache: Res<RegionCache>,
 ) {
     // 限制并发任务数
-    let available_slots = queue.max_concurrent_tasks.saturating_sub(queue.active_tasks);
+    let available_slots = queue.max_concurrent_tasks.saturating_sub(tasks.len());
     if available_slots == 0 {
         return;
     }
@@ -249,7 +390,6 @@ pub fn spawn_mesh_tasks(
     let chunks_to_process: Vec<_> = queue.to_load.drain(..count).collect();
 
     let task_pool = AsyncComputeTaskPool::get();
-    let seed_value = seed.seed;
 
     for chunk_pos in chunks_to_process {
         // 从占位符映射中获取已创建的占位符实体
@@ -261,46 +401,47 @@ pub fn spawn_mesh_tasks(
             }
         };
 
-        // 派发异步任务（包含区块生成和网格构建）
-        let task =
-            task_pool.spawn(async move { generate_chunk_and_mesh_async(chunk_pos, seed_value) });
-
-        // 创建任务跟踪实体
-        commands.spawn(ComputeMeshTask {
-            task,
-            chunk_pos,
-            placeholder_entity,
-        });
-
-        queue.active_tasks += 1;
+        // 派发异步任务（包含读取存档或生成区块，以及网格构建）
+        let terrain = terrain.clone();
+        let storage = active_world.storage.clone();
+        let region_cache = region_cache.clone();
+        let below_surface = world.heightmap.is_below_surface(chunk_pos);
+        let cancel = CancelToken::default();
+        let task_cancel = cancel.clone();
+        let reporter = tasks.start(chunk_pos, cancel, placeholder_entity);
+        task_pool
+            .spawn(async move {
+                // 被取消时不发产物，句柄析构时报告任务结束
+                let data = match region_cache.load_chunk(&storage, chunk_pos) {
+                    Some(stored) => {
+                        light_and_mesh_chunk(chunk_pos, stored, below_surface, &task_cancel)
+                    }
+                    None => generate_chunk_and_mesh_async(
+                        chunk_pos,
+                        terrain,
+                        below_surface,
+                        &task_cancel,
+                    ),
+                };
+                if let Some(data) = data {
+                    reporter.send(TaskOutput::Generated(data));
+                }
+            })
+            .detach();
     }
 }
 
-/// 处理完成的网格生成任务（收集到缓冲区，等待批量替换）
-pub fn handle_completed_mesh_tasks(
-    mut commands: Commands,
-    mut queue: ResMut<ChunkLoadQueue>,
+/// 取出完成的网格生成任务（收集到缓冲区，等待批量替换）
+///
+/// 被取消的任务已在 process_chunk_unload 中移除并计数，它们的结果在这里丢弃
+pub fn receive_generated_chunks(
+    mut tasks: ResMut<ChunkTasks>,
     mut buffer: ResMut<ChunkReplacementBuffer>,
-    mut pending_query: Query<(Entity, &mut ComputeMeshTask)>,
 ) {
-    for (entity, mut task) in pending_query.iter_mut() {
-        // 非阻塞地检查任务是否完成
-        if let Some((voxels, mesh)) = future::block_on(future::poll_once(&mut task.task)) {
-            let chunk_pos = task.chunk_pos;
-            let placeholder_entity = task.placeholder_entity;
-
-            // 移除任务跟踪实体
-            commands.entity(entity).despawn();
-            queue.active_tasks = queue.active_tasks.saturating_sub(1);
-
-            // 收集到缓冲区，等待批量替换
-            buffer.completed.push(CompletedChunk {
-                chunk_pos,
-                voxels,
-                mesh,
-                placeholder_entity,
-            });
-        }
+    let completed = tasks.drain_completed();
+    // 没有结果时不借用为可变，避免触发缓冲区的变化检测
+    if !completed.is_empty() {
+        buffer.completed.extend(completed);
     }
 }
 
@@ -312,8 +453,11 @@ pub fn apply_chunk_replacements(
     mut meshes: ResMut<Assets<Mesh>>,
     materials: Res<ChunkMaterials>,
     mut world: ResMut<VoxelWorld>,
+    mut chunks: ChunkEntities,
     mut buffer: ResMut<ChunkReplacementBuffer>,
     mut placeholders: ResMut<PlaceholderEntities>,
+    mut light_updates: ResMut<LightUpdates>,
+    mut loaded_events: MessageWriter<ChunkLoaded>,
 ) {
     if buffer.completed.is_empty() {
         return;
@@ -332,14 +476,18 @@ pub fn apply_chunk_replacements(
 
     // 重置定时器
     buffer.timer = 0.0;
+    let _span = info_span!("apply_chunk_replacements", chunks = buffer.completed.len()).entered();
+    let _timer = StageTimer::start(Stage::Replace);
 
     // 批量替换所有完成的区块
+    let mut arrived = Vec::with_capacity(buffer.completed.len());
     for completed in buffer.completed.drain(..) {
         // 存储区块数据
-        let mut chunk_data = ChunkData::new();
-        chunk_data.voxels = completed.voxels;
+        let mut chunk_data = completed.chunk;
         chunk_data.is_dirty = false;
-        world.chunks.insert(completed.chunk_pos, chunk_data);
+        ThermalApi::register_heat_sources(&mut chunk_data);
+        chunks.insert(completed.chunk_pos, chunk_data);
+        arrived.push(completed.chunk_pos);
 
         // 移除蓝色占位符实体
         commands.entity(completed.placeholder_entity).despawn();
@@ -347,50 +495,361 @@ pub fn apply_chunk_replacements(
 
         // 优化：检查mesh是否为空（没有顶点/索引）
         // 空mesh不创建entity，避免无用的drawcall
-        let has_geometry = completed.mesh.indices().map_or(false, |indices| {
-            match indices {
-                bevy::mesh::Indices::U16(v) => !v.is_empty(),
-                bevy::mesh::Indices::U32(v) => !v.is_empty(),
-            }
-        });
-
-        if !has_geometry {
+        if !has_geometry(&completed.meshes) {
             // 空mesh：不创建渲染实体，只存储数据
             continue;
         }
 
         // 创建真实区块渲染实体（替换占位符）
-        let mesh_handle = meshes.add(completed.mesh);
-        let origin = completed.chunk_pos.world_origin();
-
         let chunk_entity = commands
-            .spawn((
-                Mesh3d(mesh_handle),
-                MeshMaterial3d(materials.opaque.clone()),
-                Transform::from_translation(Vec3::new(
-                    origin.x as f32,
-                    origin.y as f32,
-                    origin.z as f32,
-                )),
-                ChunkMarker {
-                    pos: completed.chunk_pos,
-                },
-            ))
+            .spawn(chunk_entity_bundle(completed.chunk_pos))
             .id();
+        spawn_chunk_sections(
+            &mut commands,
+            chunk_entity,
+            completed.meshes,
+            &mut meshes,
+            &materials,
+        );
 
         world
             .loaded_chunks
             .insert(completed.chunk_pos, chunk_entity);
     }
+
+    light_updates.arrived.extend(arrived.iter().copied());
+    loaded_events.write_batch(arrived.iter().map(|&chunk_pos| ChunkLoaded {
+        chunk_pos,
+        restored: false,
+    }));
+}
+
+/// 区块到达后的收尾：标记需要用真实边界重建网格的区块，更新高度图
+///
+/// 新区块的实体在加入它们的系统结束后才生成，所以在这里读取本帧的 [`ChunkLoaded`] 处理
+pub fn settle_arrived_chunks(
+    mut world: ResMut<VoxelWorld>,
+    mut chunks: ChunksMut,
+    mut loaded_events: MessageReader<ChunkLoaded>,
+) {
+    let arrived: Vec<ChunkPos> = loaded_events.read().map(|event| event.chunk_pos).collect();
+    if arrived.is_empty() {
+        return;
+    }
+
+    mark_boundary_remesh(&world, &mut chunks, &arrived);
+    world.refresh_heightmap(&chunks, arrived);
+}
+
+/// 新区块到达后标记需要用真实边界重建网格的区块
+///
+/// 首次生成的网格把未知的相邻区块当作空气，边界上会多出被遮挡的面。
+/// 只有当新区块与已存在的相邻区块在共享边界上互相遮挡时，有网格的一方才需要重建。
+///
+/// 被掩埋而没有构建网格的实心区块则相反：相邻区块在共享边界上露出透明方块（洞穴）时，
+/// 需要重建以补上朝向洞穴的面
+fn mark_boundary_remesh(world: &VoxelWorld, chunks: &mut ChunksMut, arrived: &[ChunkPos]) {
+    let mut dirty = HashSet::new();
+    let has_mesh = |chunk_pos: &ChunkPos| world.loaded_chunks.contains_key(chunk_pos);
+    let unmeshed_solid = |chunk_pos: &ChunkPos, chunk: &ChunkData| {
+        !has_mesh(chunk_pos) && chunk.is_fully_opaque()
+    };
+
+    for &chunk_pos in arrived {
+        let Some(chunk) = chunks.get(&chunk_pos) else {
+            continue;
+        };
+        for dir in NEIGHBOR_DIRS {
+            let neighbor_pos = ChunkPos::new(
+                chunk_pos.x + dir.x,
+                chunk_pos.y + dir.y,
+                chunk_pos.z + dir.z,
+            );
+            let Some(neighbor) = chunks.get(&neighbor_pos) else {
+                continue;
+            };
+            if boundary_occludes(chunk, neighbor, dir) {
+                dirty.extend([chunk_pos, neighbor_pos].into_iter().filter(has_mesh));
+            }
+            if unmeshed_solid(&neighbor_pos, neighbor) && boundary_exposed(chunk, dir) {
+                dirty.insert(neighbor_pos);
+            }
+            if unmeshed_solid(&chunk_pos, chunk) && boundary_exposed(neighbor, -dir) {
+                dirty.insert(chunk_pos);
+            }
+        }
+    }
+
+    for chunk_pos in dirty {
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos) {
+            chunk.is_dirty = true;
+        }
+    }
+}
+
+/// 区块朝 dir 方向的边界层上的所有局部坐标，按边界面内的两个切向轴排列
+/// （相邻区块对侧边界层用 -dir 得到相同顺序的坐标）
+pub(crate) fn boundary_layer(dir: IVec3) -> impl Iterator<Item = IVec3> {
+    // 边界层在法线轴上的坐标
+    let layer = dir.max(IVec3::ZERO) * (CHUNK_SIZE - 1);
+    // 边界面内的两个切向轴
+    let (u_axis, v_axis) = if dir.x != 0 {
+        (IVec3::Y, IVec3::Z)
+    } else if dir.y != 0 {
+        (IVec3::X, IVec3::Z)
+    } else {
+        (IVec3::X, IVec3::Y)
+    };
+
+    (0..CHUNK_SIZE)
+        .flat_map(move |u| (0..CHUNK_SIZE).map(move |v| layer + u_axis * u + v_axis * v))
+}
+
+/// 判断区块与 dir 方向的相邻区块在共享边界上是否有两侧都不是空气的位置
+/// （此时至少一侧的边界面会被剔除，需要重建网格）
+fn boundary_occludes(chunk: &ChunkData, neighbor: &ChunkData, dir: IVec3) -> bool {
+    boundary_layer(dir)
+        .zip(boundary_layer(-dir))
+        .any(|(own, other)| {
+            chunk.get(own.x, own.y, own.z) != VoxelKind::Air
+                && neighbor.get(other.x, other.y, other.z) != VoxelKind::Air
+        })
+}
+
+/// 判断区块朝 dir 方向的边界层上是否有透明或不占满整格的方块（会露出相邻区块朝向这里的面）
+fn boundary_exposed(chunk: &ChunkData, dir: IVec3) -> bool {
+    let registry = VoxelRegistry::current();
+    boundary_layer(dir).any(|pos| !registry.occludes(chunk.get(pos.x, pos.y, pos.z)))
+}
+
+/// 检查网格是否包含几何体（有索引）
+fn mesh_has_geometry(mesh: &Mesh) -> bool {
+    mesh.indices().is_some_and(|indices| match indices {
+        bevy::mesh::Indices::U16(v) => !v.is_empty(),
+        bevy::mesh::Indices::U32(v) => !v.is_empty(),
+    })
+}
+
+/// 检查区块网格的任一部分是否包含几何体
+fn has_geometry(chunk_meshes: &ChunkMeshes) -> bool {
+    mesh_has_geometry(&chunk_meshes.opaque)
+        || mesh_has_geometry(&chunk_meshes.transparent)
+        || mesh_has_geometry(&chunk_meshes.water)
+        || mesh_has_geometry(&chunk_meshes.emissive)
+}
+
+/// 区块渲染实体的组件（网格由子实体承载）
+fn chunk_entity_bundle(chunk_pos: ChunkPos) -> impl Bundle {
+    let origin = chunk_pos.world_origin();
+
+    (
+        Transform::from_translation(Vec3::new(
+            origin.x as f32,
+            origin.y as f32,
+            origin.z as f32,
+        )),
+        Visibility::default(),
+        ChunkMarker { pos: chunk_pos },
+    )
+}
+
+/// 为区块实体创建不透明、透明、水和自发光网格子实体（空的部分不创建）
+///
+/// 透明和水部分的原点移到区块中心（着色器解包位置时减去半个区块），使半透明排序按
+/// 区块中心的距离进行，避免相邻区块的水面按区块角点排序时前后颠倒。
+/// 区块网格没有浮点位置属性，Bevy 无法自动计算包围盒，这里直接给出整个区块的包围盒用于视锥剔除
+fn spawn_chunk_sections(
+    commands: &mut Commands,
+    chunk_entity: Entity,
+    chunk_meshes: ChunkMeshes,
+    meshes: &mut Assets<Mesh>,
+    materials: &ChunkMaterials,
+) {
+    let ChunkMeshes {
+        opaque,
+        transparent,
+        water,
+        emissive,
+    } = chunk_meshes;
+    let center = Vec3::splat(CHUNK_SIZE as f32 * 0.5);
+    let bounds = Aabb::from_min_max(Vec3::ZERO, Vec3::splat(CHUNK_SIZE as f32));
+    let centered_bounds = Aabb::from_min_max(-center, center);
+
+    commands.entity(chunk_entity).with_children(|parent| {
+        if mesh_has_geometry(&opaque) {
+            parent.spawn((
+                Mesh3d(meshes.add(opaque)),
+                MeshMaterial3d(materials.opaque.clone()),
+                Transform::default(),
+                bounds,
+                ChunkSection::Opaque,
+            ));
+        }
+
+        if mesh_has_geometry(&emissive) {
+            parent.spawn((
+                Mesh3d(meshes.add(emissive)),
+                MeshMaterial3d(materials.emissive.clone()),
+                Transform::default(),
+                bounds,
+                ChunkSection::Emissive,
+            ));
+        }
+
+        if mesh_has_geometry(&transparent) {
+            parent.spawn((
+                Mesh3d(meshes.add(transparent)),
+                MeshMaterial3d(materials.transparent.clone()),
+                Transform::from_translation(center),
+                centered_bounds,
+                ChunkSection::Transparent,
+            ));
+        }
+
+        if mesh_has_geometry(&water) {
+            parent.spawn((
+                Mesh3d(meshes.add(water)),
+                MeshMaterial3d(materials.water.clone()),
+                Transform::from_translation(center),
+                centered_bounds,
+                ChunkSection::Water,
+            ));
+        }
+    });
+}
+
+// ============================================================================
+// 区块网格重建系统
+// ============================================================================
+
+/// 为被修改的已加载区块派发异步网格重建任务
+/// 同一区块同时只有一个重建任务，期间的新修改会在任务完成后再次派发
+///
+/// 按 [`RemeshScheduler`] 的预算和优先级分帧派发，没有派发的区块保持脏标记留到以后的帧。
+/// 高度图和光照已由之前运行的 [`relight_chunks`](crate::voxel::light::relight_chunks) 更新
+pub fn dispatch_remesh_tasks(
+    mut chunks: ChunksMut,
+    mut scheduler: ResMut<RemeshScheduler>,
+    mut tasks: ResMut<ChunkTasks>,
+    time: Res<Time<Real>>,
+    camera_query: Query<(&Transform, &Frustum), With<Camera3d>>,
+) {
+    let mut dirty_chunks: Vec<ChunkPos> = chunks
+        .iter()
+        .filter(|(pos, chunk)| chunk.is_dirty && !tasks.is_remeshing(pos))
+        .map(|(pos, _)| pos)
+        .collect();
+
+    let budget = scheduler.budget(time.delta_secs(), tasks.remesh_count());
+    scheduler.backlog = dirty_chunks.len().saturating_sub(budget);
+    if dirty_chunks.is_empty() || budget == 0 {
+        return;
+    }
+
+    // 离摄像机近、在视野内的区块先重建
+    if dirty_chunks.len() > budget
+        && let Ok((camera_transform, frustum)) = camera_query.single()
+    {
+        let camera_chunk = ChunkPos::containing(camera_transform.translation);
+        dirty_chunks.sort_by_cached_key(|chunk_pos| {
+            let visible = is_chunk_in_frustum(chunk_pos, frustum);
+            RemeshScheduler::priority(*chunk_pos, camera_chunk, visible)
+        });
+    }
+    dirty_chunks.truncate(budget);
+
+    let task_pool = AsyncComputeTaskPool::get();
+
+    for chunk_pos in dirty_chunks {
+        let Some(chunk) = chunks.get(&chunk_pos) else {
+            continue;
+        };
+
+        // 重建时使用相邻区块的真实边界数据
+        let input = MeshBuildInput {
+            chunk_pos,
+            voxels: Arc::new(chunk.voxels.to_vec()),
+            solid: SolidMask::from_chunk(chunk),
+            variants: Arc::new(chunk.variant.to_vec()),
+            flags: Arc::new(chunk.flags.to_vec()),
+            light: Arc::new(chunk.light.to_vec()),
+            neighbor_edges: NeighborEdges::from_world(&chunks, chunk_pos),
+            neighbor_light: NeighborEdges::light_from_world(&chunks, chunk_pos),
+        };
+
+        let reporter = tasks.start_remesh(chunk_pos);
+        task_pool
+            .spawn(async move {
+                reporter.send(TaskOutput::Remeshed(build_chunk_mesh_async(input)));
+            })
+            .detach();
+
+        if let Some(mut chunk) = chunks.get_mut(&chunk_pos) {
+            chunk.is_dirty = false;
+        }
+    }
+}
+
+/// 应用完成的网格重建结果
+/// 替换已有渲染实体的网格；区块从空变为非空时创建实体，反之移除实体
+pub fn apply_remesh_results(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<ChunkMaterials>,
+    mut world: ResMut<VoxelWorld>,
+    index: Res<ChunkIndex>,
+    mut tasks: ResMut<ChunkTasks>,
+    mut remeshed: MessageWriter<ChunkRemeshed>,
+) {
+    for (chunk_pos, chunk_meshes) in tasks.drain_remeshed() {
+        // 区块在重建期间被卸载，丢弃结果
+        if !index.contains(&chunk_pos) {
+            continue;
+        }
+
+        let existing = world.loaded_chunks.get(&chunk_pos).copied();
+        let geometry = has_geometry(&chunk_meshes);
+        remeshed.write(ChunkRemeshed {
+            chunk_pos,
+            has_geometry: geometry,
+        });
+
+        if !geometry {
+            if let Some(chunk_entity) = existing {
+                commands.entity(chunk_entity).despawn();
+                world.loaded_chunks.remove(&chunk_pos);
+            }
+            continue;
+        }
+
+        let chunk_entity = match existing {
+            Some(chunk_entity) => {
+                // 替换旧的网格子实体
+                commands.entity(chunk_entity).despawn_related::<Children>();
+                chunk_entity
+            }
+            None => {
+                let chunk_entity = commands.spawn(chunk_entity_bundle(chunk_pos)).id();
+                world.loaded_chunks.insert(chunk_pos, chunk_entity);
+                chunk_entity
+            }
+        };
+        spawn_chunk_sections(
+            &mut commands,
+            chunk_entity,
+            chunk_meshes,
+            &mut meshes,
+            &materials,
+        );
+    }
 }
 
 /// 清理孤儿占位符（那些chunk已经生成但占位符还在的）
 pub fn cleanup_orphan_placeholders(
     mut commands: Commands,
     mut placeholders: ResMut<PlaceholderEntities>,
-    world: Res<VoxelWorld>,
     queue: Res<ChunkLoadQueue>,
-    pending_query: Query<&ComputeMeshTask>,
+    in_flight: InFlightChunks,
 ) {
     // 收集所有应该有占位符的chunk位置
     let mut should_have_placeholder = std::collections::HashSet::new();
@@ -400,10 +859,8 @@ pub fn cleanup_orphan_placeholders(
         should_have_placeholder.insert(*pos);
     }
 
-    // 正在处理的任务
-    for task in pending_query.iter() {
-        should_have_placeholder.insert(task.chunk_pos);
-    }
+    // 正在处理的任务，以及已完成、等待批量替换的
+    should_have_placeholder.extend(in_flight.positions());
 
     // 待创建的
     for pos in &queue.pending_placeholders {
@@ -426,39 +883,57 @@ pub fn cleanup_orphan_placeholders(
     }
 }
 
+/// 把本帧即将卸载的已修改区块移入保留区，重新加载时恢复
+pub fn stash_modified_chunks(
+    mut chunks: ChunkEntities,
+    queue: Res<ChunkLoadQueue>,
+    mut unloaded: ResMut<UnloadedChunks>,
+    mut unloaded_events: MessageWriter<ChunkUnloaded>,
+) {
+    for chunk_pos in queue.to_unload.iter().take(queue.max_unloads_per_frame) {
+        if chunks.get(chunk_pos).is_some_and(|chunk| chunk.is_modified)
+            && let Some(chunk) = chunks.remove(chunk_pos)
+        {
+            unloaded.chunks.insert(*chunk_pos, chunk);
+            unloaded_events.write(ChunkUnloaded {
+                chunk_pos: *chunk_pos,
+            });
+        }
+    }
+}
+
 /// 处理区块卸载（包括占位符和任务取消）
 pub fn process_chunk_unload(
-    mut commands: Commands,
     mut world: ResMut<VoxelWorld>,
+    mut chunks: ChunkEntities,
     mut queue: ResMut<ChunkLoadQueue>,
     mut buffer: ResMut<ChunkReplacementBuffer>,
     mut placeholders: ResMut<PlaceholderEntities>,
-    pending_query: Query<(Entity, &ComputeMeshTask)>,
+    mut tasks: ResMut<ChunkTasks>,
+    mut unloaded_events: MessageWriter<ChunkUnloaded>,
 ) {
-    // 先收集要卸载的区块和要取消的任务数
-    let chunks_to_unload: Vec<_> = queue.to_unload.drain(..).collect();
-    let mut tasks_to_cancel = 0;
+    // 先收集要卸载的区块和要取消的任务数（每帧最多 max_unloads_per_frame 个）
+    let count = queue.to_unload.len().min(queue.max_unloads_per_frame);
+    let chunks_to_unload: Vec<_> = queue.to_unload.drain(..count).collect();
 
-    for chunk_pos in chunks_to_unload {
+    for &chunk_pos in &chunks_to_unload {
         // 卸载已渲染的区块
         if let Some(entity) = world.loaded_chunks.remove(&chunk_pos) {
-            commands.entity(entity).despawn();
+            chunks.commands().entity(entity).despawn();
         }
 
-        // 取消该区块的待处理任务并删除占位符
-        for (entity, task) in pending_query.iter() {
-            if task.chunk_pos == chunk_pos {
-                // 删除任务跟踪实体
-                commands.entity(entity).despawn();
-                // 删除蓝色占位符实体
-                commands.entity(task.placeholder_entity).despawn();
-                tasks_to_cancel += 1;
-            }
+        // 取消该区块的待处理任务并删除蓝色占位符实体
+        if let Some(task) = tasks.cancel(&chunk_pos) {
+            chunks.commands().entity(task.placeholder_entity).despawn();
+            queue.cancelled_tasks += 1;
         }
 
+        // 丢弃该区块的网格重建结果
+        tasks.cancel_remesh(&chunk_pos);
+
         // 删除独立的占位符（如果存在）
         if let Some(entity) = placeholders.map.remove(&chunk_pos) {
-            commands.entity(entity).despawn();
+            chunks.commands().entity(entity).despawn();
         }
 
         // 从加载队列中移除（如果存在）
@@ -470,9 +945,49 @@ pub fn process_chunk_unload(
         // 从待创建占位符列表中移除（如果存在）
         queue.pending_placeholders.retain(|&pos| pos != chunk_pos);
 
-        world.chunks.remove(&chunk_pos);
+        // 已修改的区块已经由 stash_modified_chunks 移走并发出事件
+        if chunks.remove(&chunk_pos).is_some() {
+            unloaded_events.write(ChunkUnloaded { chunk_pos });
+        }
     }
+    world.refresh_heightmap(&chunks, chunks_to_unload);
+}
 
-    // 更新活跃任务计数
-    queue.active_tasks = queue.active_tasks.saturating_sub(tasks_to_cancel);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EDGE: i32 = CHUNK_SIZE - 1;
+
+    #[test]
+    fn test_boundary_occludes_only_on_matching_positions() {
+        let mut chunk = ChunkData::new();
+        let mut neighbor = ChunkData::new();
+        chunk.set(EDGE, 5, 7, VoxelKind::Stone);
+        assert!(!boundary_occludes(&chunk, &neighbor, IVec3::X));
+
+        // 相邻区块在共享边界另一侧的同一位置有方块
+        neighbor.set(0, 5, 7, VoxelKind::Dirt);
+        assert!(boundary_occludes(&chunk, &neighbor, IVec3::X));
+        assert!(boundary_occludes(&neighbor, &chunk, IVec3::NEG_X));
+        // 其他方向的边界不受影响
+        assert!(!boundary_occludes(&chunk, &neighbor, IVec3::Z));
+
+        // 错开一格时两侧的面都露在外面
+        neighbor.set(0, 5, 7, VoxelKind::Air);
+        neighbor.set(0, 6, 7, VoxelKind::Dirt);
+        assert!(!boundary_occludes(&chunk, &neighbor, IVec3::X));
+    }
+
+    #[test]
+    fn test_boundary_occludes_ignores_inner_blocks() {
+        let mut chunk = ChunkData::new();
+        let mut neighbor = ChunkData::new();
+        chunk.set(3, EDGE - 1, 4, VoxelKind::Stone);
+        neighbor.set(3, 0, 4, VoxelKind::Stone);
+        assert!(!boundary_occludes(&chunk, &neighbor, IVec3::Y));
+
+        chunk.set(3, EDGE, 4, VoxelKind::Stone);
+        assert!(boundary_occludes(&chunk, &neighbor, IVec3::Y));
+    }
 }
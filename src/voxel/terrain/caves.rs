@@ -0,0 +1,281 @@
+//! 洞穴装饰
+//!
+//! 噪声地形生成后，在洞穴地面（上方是洞穴空气的石头或矿石）上放置装饰：
+//!
+//! - 砂砾和黏土斑块：按斑块噪声替换地面方块，两种材料由另一个噪声分区
+//! - 水洼：按水洼噪声把地面方块换成水，只在下方和四周都是石头时放置，水不会流走
+//! - 枯死的灌木：零星长在地面上
+//! - 荧光蘑菇：只长在深层洞穴的地面上，作为方块光光源照亮洞穴
+//!
+//! 装饰只出现在离地表至少 5 格（地表以下石头层）的洞穴中。
+//! 每个位置的判定只依赖世界坐标、种子和配置：区块内的位置读取装饰前的区块，
+//! 区块外的位置重新采样噪声，因此相邻区块以任意顺序生成，边界两侧的结果都一致。
+
+use bevy::prelude::*;
+use noise::NoiseFn;
+
+use super::{TerrainColumn, TerrainGenerator};
+use crate::voxel::chunk::ChunkData;
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 地表以下这么多格以内不放置装饰（泥土等次表层）
+const SUBSURFACE_DEPTH: i32 = 4;
+
+/// 四个水平方向
+const HORIZONTAL_DIRS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+/// 装饰前某个位置的地形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaveCell {
+    /// 地表以下石头层中的石头或矿石
+    Rock,
+    /// 地表以下石头层中的洞穴空气
+    Open,
+    /// 熔岩、次表层、地表以上或世界底部
+    Other,
+}
+
+impl TerrainGenerator<'_> {
+    /// 在区块内的洞穴地面上放置装饰
+    ///
+    /// columns 为区块内每列的采样结果（按 lz * CHUNK_SIZE + lx 排列），
+    /// 应在写入树木和预制结构之前调用
+    pub(super) fn decorate_caves(
+        &self,
+        chunk: &mut ChunkData,
+        origin: IVec3,
+        columns: &[TerrainColumn],
+    ) {
+        let caves = &self.config.caves;
+        // 地面方块可能在区块下方一格，装饰只会出现在洞穴空气中
+        if origin.y > caves.max_y || origin.y + CHUNK_SIZE <= caves.min_y {
+            return;
+        }
+
+        let mut placements = Vec::new();
+        for lz in 0..CHUNK_SIZE {
+            for lx in 0..CHUNK_SIZE {
+                let height = columns[(lz * CHUNK_SIZE + lx) as usize].height;
+                let bottom = origin.y - 1;
+                let top = (origin.y + CHUNK_SIZE - 1).min(height - SUBSURFACE_DEPTH - 2);
+                for y in bottom..=top {
+                    let floor = IVec3::new(origin.x + lx, y, origin.z + lz);
+                    if self.cave_cell(chunk, origin, columns, floor) != CaveCell::Rock
+                        || self.cave_cell(chunk, origin, columns, floor + IVec3::Y)
+                            != CaveCell::Open
+                    {
+                        continue;
+                    }
+                    if let Some(kind) = self.floor_decoration(chunk, origin, columns, floor) {
+                        placements.push((floor, kind));
+                        if kind == VoxelKind::Water {
+                            continue;
+                        }
+                    }
+                    if let Some(plant) = self.floor_plant(floor + IVec3::Y) {
+                        placements.push((floor + IVec3::Y, plant));
+                    }
+                }
+            }
+        }
+
+        for (pos, kind) in placements {
+            let local = pos - origin;
+            if (0..CHUNK_SIZE).contains(&local.y) {
+                chunk.set(local.x, local.y, local.z, kind);
+            }
+        }
+    }
+
+    /// 替换地面方块的装饰：水洼优先，其次是砂砾或黏土斑块
+    fn floor_decoration(
+        &self,
+        chunk: &ChunkData,
+        origin: IVec3,
+        columns: &[TerrainColumn],
+        floor: IVec3,
+    ) -> Option<VoxelKind> {
+        let decoration = &self.config.cave_decoration;
+        let sample = |scale: f64, offset: f64| {
+            self.seed.detail_noise.get([
+                floor.x as f64 * scale + offset,
+                floor.y as f64 * scale,
+                floor.z as f64 * scale - offset,
+            ])
+        };
+
+        if sample(decoration.pool_scale, 3000.0) > decoration.pool_threshold {
+            let contained = std::iter::once(IVec3::NEG_Y)
+                .chain(HORIZONTAL_DIRS)
+                .all(|dir| self.cave_cell(chunk, origin, columns, floor + dir) == CaveCell::Rock);
+            if contained {
+                return Some(VoxelKind::Water);
+            }
+        }
+
+        if sample(decoration.patch_scale, 2000.0) > decoration.patch_threshold {
+            // 斑块的材料按更大尺度分区，同一片斑块通常是同一种材料
+            let material = self.seed.cave_noise.get([
+                floor.x as f64 * decoration.patch_scale * 0.5,
+                floor.y as f64 * decoration.patch_scale * 0.5 + 200.0,
+                floor.z as f64 * decoration.patch_scale * 0.5,
+            ]);
+            return Some(if material > 0.0 {
+                VoxelKind::Gravel
+            } else {
+                VoxelKind::Clay
+            });
+        }
+        None
+    }
+
+    /// 地面上方的植物：深层洞穴中的荧光蘑菇或枯死的灌木
+    fn floor_plant(&self, pos: IVec3) -> Option<VoxelKind> {
+        let decoration = &self.config.cave_decoration;
+        let roll = self.position_roll(pos);
+        let mut cumulative = 0.0;
+        if pos.y <= decoration.glow_mushroom_max_y {
+            cumulative += decoration.glow_mushroom_chance;
+            if roll < cumulative {
+                return Some(VoxelKind::GlowMushroom);
+            }
+        }
+        cumulative += decoration.dead_bush_chance;
+        (roll < cumulative).then_some(VoxelKind::DeadBush)
+    }
+
+    /// 由种子和坐标决定的 [0, 1) 内的随机数
+    fn position_roll(&self, pos: IVec3) -> f64 {
+        let mut h = (self.seed.seed as u64) ^ 0x6A09_E667_F3BC_C909;
+        h ^= (pos.x as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        h ^= (pos.y as u32 as u64).wrapping_mul(0xD6E8_FEB8_6659_FD93);
+        h ^= (pos.z as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+        h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        h ^= h >> 31;
+        (h >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// 装饰前 pos 处的地形：区块内读取区块，区块外重新采样噪声
+    fn cave_cell(
+        &self,
+        chunk: &ChunkData,
+        origin: IVec3,
+        columns: &[TerrainColumn],
+        pos: IVec3,
+    ) -> CaveCell {
+        let local = pos - origin;
+        let in_chunk =
+            local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CHUNK_SIZE)).all();
+        let height = if in_chunk {
+            columns[(local.z * CHUNK_SIZE + local.x) as usize].height
+        } else {
+            self.sample_column(pos.x, pos.z).height
+        };
+        if pos.y <= self.config.terrain.min_y || pos.y >= height - SUBSURFACE_DEPTH {
+            return CaveCell::Other;
+        }
+
+        let kind = if in_chunk {
+            chunk.get(local.x, local.y, local.z)
+        } else if !self.is_cave(pos.x, pos.y, pos.z) {
+            VoxelKind::Stone
+        } else if self.is_lava(pos.x, pos.y, pos.z) {
+            VoxelKind::Lava
+        } else {
+            VoxelKind::Air
+        };
+        match kind {
+            VoxelKind::Air => CaveCell::Open,
+            VoxelKind::Lava => CaveCell::Other,
+            _ => CaveCell::Rock,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::chunk::{ChunkData, ChunkLookup, ChunkPos, VoxelWorld};
+    use crate::voxel::domains::thermal::api::idx_to_xyz;
+    use crate::voxel::seed::WorldSeed;
+    use crate::voxel::worldgen::WorldGenConfig;
+    use std::collections::HashMap;
+
+    /// 原点附近 y = -32 到 -1 的区块（深层洞穴）
+    fn deep_world(generator: &TerrainGenerator) -> HashMap<ChunkPos, ChunkData> {
+        let mut world = HashMap::new();
+        for x in -3..3 {
+            for z in -3..3 {
+                for y in -2..0 {
+                    let chunk_pos = ChunkPos::new(x, y, z);
+                    world.insert(chunk_pos, generator.generate_chunk(chunk_pos));
+                }
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn test_deep_caves_are_decorated() {
+        let seed = WorldSeed::new(12345);
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+        let world = deep_world(&generator);
+
+        let count = |kinds: &[VoxelKind]| {
+            world
+                .values()
+                .flat_map(|chunk| chunk.voxels.iter())
+                .filter(|kind| kinds.contains(kind))
+                .count()
+        };
+        assert!(count(&[VoxelKind::GlowMushroom]) > 0);
+        assert!(count(&[VoxelKind::Gravel, VoxelKind::Clay]) > 0);
+
+        // 同一种子生成的装饰完全相同
+        let chunk_pos = ChunkPos::new(0, -1, 0);
+        let regenerated = TerrainGenerator::new(&seed, &config).generate_chunk(chunk_pos);
+        assert!(world[&chunk_pos]
+            .voxels
+            .iter()
+            .eq(regenerated.voxels.iter()));
+    }
+
+    #[test]
+    fn test_decorations_rest_on_cave_floors() {
+        let seed = WorldSeed::new(12345);
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+        let world = deep_world(&generator);
+        let loaded = |pos: IVec3| world.contains_key(&VoxelWorld::split_world_pos(pos).0);
+
+        for (&chunk_pos, chunk) in &world {
+            for (idx, kind) in chunk.voxels.iter().enumerate() {
+                let (x, y, z) = idx_to_xyz(idx);
+                let pos = chunk_pos.world_origin() + IVec3::new(x, y, z);
+                match kind {
+                    // 蘑菇长在深层洞穴的固体地面上
+                    VoxelKind::GlowMushroom => {
+                        assert!(pos.y <= config.cave_decoration.glow_mushroom_max_y);
+                        if loaded(pos - IVec3::Y) {
+                            assert!(world.get_voxel(pos - IVec3::Y).is_solid());
+                        }
+                    }
+                    // 地下深处的水洼下方和四周都不是空气，水不会流走
+                    VoxelKind::Water
+                        if pos.y < generator.get_height(pos.x, pos.z) - SUBSURFACE_DEPTH =>
+                    {
+                        for dir in std::iter::once(IVec3::NEG_Y).chain(HORIZONTAL_DIRS) {
+                            if loaded(pos + dir) {
+                                assert_ne!(world.get_voxel(pos + dir), VoxelKind::Air);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
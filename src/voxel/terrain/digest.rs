@@ -0,0 +1,244 @@
+//! 世界摘要
+//!
+//! 对一组区块的体素内容计算 64 位摘要，用于检测地形生成结果是否发生变化。
+//! 同一种子、配置和结构模板生成的区块摘要必须保持不变；
+//! 修改噪声、生物群系或结构放置后摘要改变，说明已有世界会与新生成的地形不一致。
+//!
+//! 模拟状态摘要（[`WorldDigest::of_state`]）另外包括标志位和温度，
+//! 用于确定性测试和回放校验。
+//!
+//! 摘要使用 FNV-1a，不依赖标准库哈希器的实现细节，跨平台和编译器版本保持稳定。
+
+use bevy::prelude::*;
+use std::fmt;
+
+use super::{SharedTerrain, TerrainGenerator};
+use crate::input::{Action, ActionInput};
+use crate::voxel::chunk::{ChunkData, ChunkPos, Chunks};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 区块内容摘要
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WorldDigest(pub u64);
+
+impl WorldDigest {
+    /// 标准采样区块：覆盖原点附近的地下、地表和浮空岛高度，以及负坐标区块
+    pub fn sample_chunks() -> Vec<ChunkPos> {
+        let mut positions = Vec::new();
+        for x in [-3, 0, 2] {
+            for z in [-1, 0, 4] {
+                for y in [0, 1, 2, 4, 5] {
+                    positions.push(ChunkPos::new(x, y, z));
+                }
+            }
+        }
+        positions
+    }
+
+    /// 计算一组区块的摘要（与遍历顺序无关）
+    pub fn of_chunks<'a>(chunks: impl IntoIterator<Item = (ChunkPos, &'a ChunkData)>) -> Self {
+        Self::hash_chunks(chunks, |_, _| {})
+    }
+
+    /// 计算一组区块的模拟状态摘要（与遍历顺序无关）
+    ///
+    /// 除方块和变体外还包括标志位和温度覆盖值，
+    /// 两个世界的摘要相同说明它们的模拟状态一致
+    pub fn of_state<'a>(chunks: impl IntoIterator<Item = (ChunkPos, &'a ChunkData)>) -> Self {
+        Self::hash_chunks(chunks, |hasher, chunk| {
+            for flags in chunk.flags.iter() {
+                hasher.write(&flags.bits().to_le_bytes());
+            }
+
+            let mut temps: Vec<(usize, f32)> = chunk
+                .thermal_state
+                .iter()
+                .flat_map(|thermal| thermal.temp_overrides.iter())
+                .map(|(&idx, &temp)| (idx, temp))
+                .collect();
+            temps.sort_unstable_by_key(|&(idx, _)| idx);
+            hasher.write(&(temps.len() as u32).to_le_bytes());
+            for (idx, temp) in temps {
+                hasher.write(&(idx as u32).to_le_bytes());
+                hasher.write(&temp.to_bits().to_le_bytes());
+            }
+        })
+    }
+
+    /// 按坐标顺序哈希每个区块的坐标、方块和变体，`extra` 追加其余内容
+    fn hash_chunks<'a>(
+        chunks: impl IntoIterator<Item = (ChunkPos, &'a ChunkData)>,
+        extra: impl Fn(&mut Fnv1a, &ChunkData),
+    ) -> Self {
+        let mut chunks: Vec<_> = chunks.into_iter().collect();
+        chunks.sort_by_key(|(pos, _)| (pos.x, pos.y, pos.z));
+
+        let mut hasher = Fnv1a(FNV_OFFSET);
+        for (pos, chunk) in chunks {
+            for coord in [pos.x, pos.y, pos.z] {
+                hasher.write(&coord.to_le_bytes());
+            }
+            for kind in chunk.voxels.iter() {
+                hasher.write(&[kind.id()]);
+            }
+            for variant in chunk.variant.iter() {
+                hasher.write(&[variant]);
+            }
+            extra(&mut hasher, chunk);
+        }
+        Self(hasher.0)
+    }
+
+    /// 生成指定区块并计算摘要
+    pub fn generate(generator: &TerrainGenerator, positions: &[ChunkPos]) -> Self {
+        let chunks: Vec<_> = positions
+            .iter()
+            .map(|&pos| (pos, generator.generate_chunk(pos)))
+            .collect();
+        Self::of_chunks(chunks.iter().map(|(pos, chunk)| (*pos, chunk)))
+    }
+}
+
+impl fmt::Display for WorldDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// FNV-1a 64 位哈希
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// 世界摘要调试命令
+///
+/// 按 F9（默认绑定）输出当前种子和配置下标准采样区块的生成摘要，
+/// 以及已加载的采样区块的摘要（两者不同说明区块已被修改或生成结果不一致）
+pub fn world_digest_debug_system(
+    actions: ActionInput,
+    terrain: Res<SharedTerrain>,
+    chunks: Chunks,
+) {
+    if !actions.just_pressed(Action::PrintWorldDigest) {
+        return;
+    }
+
+    let positions = WorldDigest::sample_chunks();
+    let generated = WorldDigest::generate(&terrain.generator(), &positions);
+    info!(
+        "World digest (seed {}, {} sample chunks): {}",
+        terrain.seed(),
+        positions.len(),
+        generated
+    );
+
+    let loaded: Vec<_> = positions
+        .iter()
+        .filter_map(|pos| chunks.get(pos).map(|chunk| (*pos, chunk)))
+        .collect();
+    if loaded.len() == positions.len() {
+        let current = WorldDigest::of_chunks(loaded);
+        if current == generated {
+            info!("Loaded sample chunks match: {}", current);
+        } else {
+            info!("Loaded sample chunks differ: {}", current);
+        }
+    } else {
+        info!(
+            "Only {}/{} sample chunks loaded, skipping loaded digest",
+            loaded.len(),
+            positions.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::domains::thermal::ThermalApi;
+    use crate::voxel::flags::VoxelFlags;
+    use crate::voxel::seed::WorldSeed;
+    use crate::voxel::terrain::structures::StructureTemplate;
+    use crate::voxel::voxel_kind::VoxelKind;
+    use crate::voxel::worldgen::WorldGenConfig;
+
+    fn digest(seed: u32, structures: &[StructureTemplate]) -> WorldDigest {
+        let seed = WorldSeed::new(seed);
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config).with_structures(structures);
+        WorldDigest::generate(&generator, &WorldDigest::sample_chunks())
+    }
+
+    fn bundled_structures() -> Vec<StructureTemplate> {
+        let files = [
+            include_str!("../../../assets/structures/dungeon.structure.ron"),
+            include_str!("../../../assets/structures/ruin.structure.ron"),
+            include_str!("../../../assets/structures/village.structure.ron"),
+        ];
+        files
+            .iter()
+            .map(|source| StructureTemplate::from_ron(source.as_bytes()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_digest_ignores_order_and_detects_changes() {
+        let a = (ChunkPos::new(0, 0, 0), ChunkData::new());
+        let mut b = (ChunkPos::new(1, 0, 0), ChunkData::new());
+        b.1.set(3, 3, 3, VoxelKind::Stone);
+
+        let forward = WorldDigest::of_chunks([(a.0, &a.1), (b.0, &b.1)]);
+        let reverse = WorldDigest::of_chunks([(b.0, &b.1), (a.0, &a.1)]);
+        assert_eq!(forward, reverse);
+
+        b.1.variant.set(0, 1);
+        assert_ne!(forward, WorldDigest::of_chunks([(a.0, &a.1), (b.0, &b.1)]));
+    }
+
+    #[test]
+    fn test_state_digest_covers_flags_and_temperatures() {
+        let chunk_pos = ChunkPos::new(0, -1, 0);
+        let mut chunk = ChunkData::new();
+        let terrain = WorldDigest::of_chunks([(chunk_pos, &chunk)]);
+        let initial = WorldDigest::of_state([(chunk_pos, &chunk)]);
+        // 不依赖标准库哈希器，摘要在不同编译器版本下保持不变
+        assert_eq!(initial.to_string(), "877395f511f73141");
+
+        chunk.flags.set(5, VoxelFlags::BURNING);
+        let burning = WorldDigest::of_state([(chunk_pos, &chunk)]);
+        assert_ne!(burning, initial);
+
+        ThermalApi::set_temp(&mut chunk, 5, 300.0);
+        assert_ne!(WorldDigest::of_state([(chunk_pos, &chunk)]), burning);
+        // 地形摘要只看方块和变体
+        assert_eq!(WorldDigest::of_chunks([(chunk_pos, &chunk)]), terrain);
+    }
+
+    // 以下摘要固定了默认配置下的生成结果。
+    // 如果有意修改了地形生成，确认变化符合预期后更新这些值。
+
+    #[test]
+    fn test_pinned_digest_seed_12345() {
+        assert_eq!(digest(12345, &[]).to_string(), "64d7e2cf43252eb3");
+    }
+
+    #[test]
+    fn test_pinned_digest_seed_42() {
+        assert_eq!(digest(42, &[]).to_string(), "d22680319cf151f6");
+    }
+
+    #[test]
+    fn test_pinned_digest_with_bundled_structures() {
+        let structures = bundled_structures();
+        assert_eq!(digest(7, &structures).to_string(), "5d0d086911c20610");
+    }
+}
@@ -0,0 +1,1211 @@
+//! 地形生成器
+//!
+//! 除噪声地形外还支持超平坦和方块展示两种生成模式（见 [`modes`]），由配置的 `mode` 选择。
+//! 噪声地形的洞穴地面生成后再铺上砂砾、水洼和荧光蘑菇等装饰（见 [`caves`]）
+
+pub mod caves;
+pub mod digest;
+pub mod modes;
+pub mod structures;
+
+use bevy::prelude::*;
+use noise::NoiseFn;
+use std::sync::Arc;
+
+use crate::voxel::biome::Biome;
+use crate::voxel::chunk::{ChunkData, ChunkPos};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::loading::CancelToken;
+use crate::voxel::seed::WorldSeed;
+use crate::voxel::voxel_kind::VoxelKind;
+use crate::voxel::worldgen::{GeneratorMode, NoiseSource, WorldGenConfig};
+use structures::{StructureRegistry, StructureTemplate};
+
+/// 搜索出生点时相邻两圈（以及圈上相邻两个采样点）的间隔（方块）
+const SPAWN_SEARCH_STEP: i32 = 4;
+
+/// 地表树木的最大高度（树干 + 树冠），超出此范围的区块不会包含地表树木
+const MAX_TREE_HEIGHT: i32 = 10;
+
+/// 浅层与深层洞穴的过渡半宽（方块）
+const DEEP_CAVE_BLEND: f64 = 8.0;
+
+/// 树木的方块（相对树干底部的偏移和方块类型），按写入顺序排列
+///
+/// 树种和高度由生物群系决定，不生长树木的生物群系返回空列表
+pub fn tree_blocks(biome: Biome) -> Vec<(IVec3, VoxelKind)> {
+    let (log, leaves, trunk_h) = match biome {
+        Biome::Forest | Biome::Plains => (VoxelKind::OakLog, VoxelKind::OakLeaves, 5),
+        Biome::BirchForest => (VoxelKind::BirchLog, VoxelKind::BirchLeaves, 6),
+        Biome::Taiga | Biome::Snowy => (VoxelKind::SpruceLog, VoxelKind::SpruceLeaves, 6),
+        Biome::Mountains => (VoxelKind::SpruceLog, VoxelKind::SpruceLeaves, 7),
+        Biome::Swamp => (VoxelKind::OakLog, VoxelKind::OakLeaves, 4), // 沼泽里低矮的橡树
+        Biome::FloatingIslands => (VoxelKind::OakLog, VoxelKind::OakLeaves, 4), // 浮空岛上的小树
+        _ => return Vec::new(),
+    };
+
+    // 树干
+    let mut blocks: Vec<_> = (0..trunk_h).map(|dy| (IVec3::new(0, dy, 0), log)).collect();
+
+    // 树叶
+    let leaf_start = trunk_h - 2;
+    for dy in leaf_start..trunk_h + 2 {
+        let radius: i32 = if dy >= trunk_h { 1 } else { 2 };
+        for dx in -radius..=radius {
+            for dz in -radius..=radius {
+                // 树干位置不放置树叶
+                if dx == 0 && dz == 0 && dy < trunk_h {
+                    continue;
+                }
+                // 使用曼哈顿距离创建菱形树冠
+                if dx.abs() + dz.abs() <= radius + 1 {
+                    blocks.push((IVec3::new(dx, dy, dz), leaves));
+                }
+            }
+        }
+    }
+    blocks
+}
+
+/// 一列地形的采样结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerrainColumn {
+    /// 地形高度（地表方块位于 height - 1）
+    pub height: i32,
+    pub biome: Biome,
+}
+
+/// 地形生成器 - 使用程序化生成算法创建地形
+/// 基于柏林噪声（Perlin Noise）生成自然的地形特征
+pub struct TerrainGenerator<'a> {
+    seed: &'a WorldSeed,
+    config: &'a WorldGenConfig,
+    structures: &'a [StructureTemplate],
+}
+
+impl<'a> TerrainGenerator<'a> {
+    /// 创建新的地形生成器（不放置预制结构）
+    pub fn new(seed: &'a WorldSeed, config: &'a WorldGenConfig) -> Self {
+        Self {
+            seed,
+            config,
+            structures: &[],
+        }
+    }
+
+    /// 设置要放置的预制结构模板
+    pub fn with_structures(mut self, structures: &'a [StructureTemplate]) -> Self {
+        self.structures = structures;
+        self
+    }
+
+    /// 计算指定位置的地形高度
+    /// 使用多层噪声叠加（分形噪声）生成更自然的地形
+    /// 默认配置包含三层：
+    /// - 第一层：大尺度地形特征（山脉、山谷）
+    /// - 第二层：中等尺度起伏
+    /// - 第三层：小尺度细节
+    ///
+    /// 山地区域再叠加山脊噪声，沼泽区域被拉平到水位附近
+    pub fn get_height(&self, x: i32, z: i32) -> i32 {
+        self.sample_column(x, z).height
+    }
+
+    /// 以 (x, z) 为中心、边长 2 * radius 的方形区域内最低的可见地表 y（水面以下取水面）
+    ///
+    /// 只在 5 × 5 的网格上采样，用于估计飞到高处时加载范围需要向下延伸到哪里
+    pub fn lowest_visible_surface(&self, x: i32, z: i32, radius: i32) -> i32 {
+        let step = (radius / 2).max(1);
+        let water_level = self.config.terrain.water_level;
+        let mut lowest = i32::MAX;
+        for dz in -2..=2 {
+            for dx in -2..=2 {
+                let height = self.get_height(x + dx * step, z + dz * step);
+                lowest = lowest.min(height.max(water_level) - 1);
+            }
+        }
+        lowest
+    }
+
+    /// 根据温度、湿度和高度确定生物群系类型
+    /// 使用噪声函数生成温度和湿度图，模拟真实的气候分布
+    pub fn get_biome(&self, x: i32, z: i32) -> Biome {
+        self.sample_column(x, z).biome
+    }
+
+    /// (x, y, z) 是否在寒冷地区：雪原生物群系，或者高于雪线。这里的降水是雪，夜里露天会冻伤
+    pub fn is_cold(&self, x: i32, y: i32, z: i32) -> bool {
+        y >= self.config.mountains.snow_height || self.get_biome(x, z) == Biome::Snowy
+    }
+
+    /// 计算一列的地形高度和生物群系
+    ///
+    /// 高度和生物群系相互依赖（山地和沼泽会改变高度，海洋和海滩由高度决定），
+    /// 同时需要两者时应调用此方法，避免重复采样噪声
+    pub fn sample_column(&self, x: i32, z: i32) -> TerrainColumn {
+        if let Some(column) = self.flat_column() {
+            return column;
+        }
+        let (temp, humid) = self.climate(x, z);
+        let mountain = self.mountain_weight(x, z);
+        // 山地优先，两者重叠时沼泽减弱
+        let swamp = self.swamp_weight(temp, humid) * (1.0 - mountain);
+
+        let mut height = self.shape_sea_floor(self.base_height(x, z));
+        if mountain > 0.0 {
+            height += mountain * self.ridge_height(x, z);
+        }
+        if swamp > 0.0 {
+            let swamps = &self.config.swamps;
+            let patch = self.seed.detail_noise.get([
+                x as f64 * swamps.patch_scale,
+                z as f64 * swamps.patch_scale,
+            ]);
+            // 目标高度在水位上下浮动，低于水位的地方形成浅水塘
+            let target = (self.config.terrain.water_level + 1) as f64 + patch * swamps.patch_depth;
+            height += (target - height) * swamp * swamps.flatten;
+        }
+        let (valley, channel) = self.river_weights(x, z);
+        if valley > 0.0 {
+            // 河道中心最深，河岸边缘正好与水位齐平；只下切，不抬高已经低于河床的地形
+            let rivers = &self.config.rivers;
+            let bed = self.config.terrain.water_level as f64 - rivers.depth * channel;
+            if bed < height {
+                height += (bed - height) * valley;
+            }
+        }
+        let terrain = &self.config.terrain;
+        let height = (height as i32).max(terrain.min_y + 1).min(terrain.max_y);
+
+        TerrainColumn {
+            height,
+            biome: self.classify_biome(height, temp, humid, mountain, swamp, channel),
+        }
+    }
+
+    /// 寻找出生点：从 (x, z) 开始由近到远逐圈检查，返回第一处陆地地表上方的位置
+    ///
+    /// 陆地指地表高于水位、且不是海洋或河流的列；每圈间隔 SPAWN_SEARCH_STEP 方块采样。
+    /// max_radius 内找不到陆地时返回起点列的水面上方
+    pub fn find_spawn(&self, x: i32, z: i32, max_radius: i32) -> IVec3 {
+        // 超平坦和方块展示世界没有水体，起点就是陆地
+        if self.config.mode != GeneratorMode::Noise {
+            return IVec3::new(x, self.get_height(x, z), z);
+        }
+        let water_level = self.config.terrain.water_level;
+        let is_land = |column: TerrainColumn| {
+            column.height > water_level + 1
+                && !matches!(column.biome, Biome::Ocean | Biome::River)
+        };
+
+        for radius in (0..=max_radius).step_by(SPAWN_SEARCH_STEP as usize) {
+            let ring = ring_offsets(radius, SPAWN_SEARCH_STEP);
+            // 同一圈内取离起点最近的陆地
+            let nearest = ring
+                .into_iter()
+                .map(|(dx, dz)| (dx, dz, self.sample_column(x + dx, z + dz)))
+                .filter(|(_, _, column)| is_land(*column))
+                .min_by_key(|(dx, dz, _)| dx * dx + dz * dz);
+            if let Some((dx, dz, column)) = nearest {
+                return IVec3::new(x + dx, column.height, z + dz);
+            }
+        }
+
+        let height = self.get_height(x, z).max(water_level + 1);
+        IVec3::new(x, height, z)
+    }
+
+    /// 分形噪声叠加出的基础高度（未经山地和沼泽修整）
+    fn base_height(&self, x: i32, z: i32) -> f64 {
+        let terrain = &self.config.terrain;
+        let fx = x as f64 * terrain.scale;
+        let fz = z as f64 * terrain.scale;
+
+        let mut height = 0.0;
+        for octave in &terrain.octaves {
+            let noise = match octave.source {
+                NoiseSource::Terrain => &self.seed.terrain_noise,
+                NoiseSource::Detail => &self.seed.detail_noise,
+            };
+            height +=
+                noise.get([fx * octave.frequency, fz * octave.frequency]) * octave.amplitude;
+        }
+
+        terrain.base_height as f64 + height
+    }
+
+    /// 海洋高度以下超过大陆架深度的部分按 drop_off 倍加速下沉，形成陡峭的大陆坡，
+    /// 最深不低于水位以下 max_depth
+    fn shape_sea_floor(&self, height: f64) -> f64 {
+        let oceans = &self.config.oceans;
+        let depth = self.config.biomes.ocean_height as f64 - height;
+        if depth <= oceans.shelf_depth {
+            return height;
+        }
+        let shaped = height - (depth - oceans.shelf_depth) * (oceans.drop_off - 1.0);
+        let deepest = self.config.terrain.water_level as f64 - oceans.max_depth;
+        shaped.max(deepest.min(height))
+    }
+
+    /// 温度和湿度（范围：-1.0 到 1.0）
+    pub fn climate(&self, x: i32, z: i32) -> (f64, f64) {
+        let scale = self.config.biomes.scale;
+        let point = [x as f64 * scale, z as f64 * scale];
+        (
+            self.seed.biome_temp_noise.get(point),
+            self.seed.biome_humid_noise.get(point),
+        )
+    }
+
+    /// 山地权重（0.0 为非山地，1.0 为完全隆起）
+    fn mountain_weight(&self, x: i32, z: i32) -> f64 {
+        let mountains = &self.config.mountains;
+        let value = self.seed.mountain_noise.get([
+            x as f64 * mountains.region_scale,
+            z as f64 * mountains.region_scale,
+        ]);
+        smoothstep(
+            mountains.region_threshold,
+            mountains.region_threshold + mountains.blend,
+            value,
+        )
+    }
+
+    /// 山脊高度：1 - |噪声| 在噪声过零处形成尖锐的山脊，平方后山谷更宽、山峰更陡
+    fn ridge_height(&self, x: i32, z: i32) -> f64 {
+        let mountains = &self.config.mountains;
+        let fx = x as f64 * mountains.ridge_scale;
+        let fz = z as f64 * mountains.ridge_scale;
+        let ridge = |frequency: f64| {
+            let value = 1.0 - self.seed.ridge_noise.get([fx * frequency, fz * frequency]).abs();
+            value * value
+        };
+        (ridge(1.0) * 0.7 + ridge(2.0) * 0.3) * mountains.ridge_amplitude
+    }
+
+    /// 沼泽权重（温暖且潮湿的地区）
+    fn swamp_weight(&self, temp: f64, humid: f64) -> f64 {
+        let swamps = &self.config.swamps;
+        smoothstep(swamps.humidity, swamps.humidity + swamps.blend, humid)
+            * smoothstep(
+                swamps.min_temperature,
+                swamps.min_temperature + swamps.blend,
+                temp,
+            )
+    }
+
+    /// 河流权重：(河谷, 河道)
+    /// - 河谷：河岸过渡带外侧为 0，进入河道时为 1，决定地形被下切的程度
+    /// - 河道：河道边缘为 0，中心线为 1，决定河床深度
+    fn river_weights(&self, x: i32, z: i32) -> (f64, f64) {
+        let rivers = &self.config.rivers;
+        let value = self
+            .seed
+            .river_noise
+            .get([x as f64 * rivers.scale, z as f64 * rivers.scale]);
+        let ridge = 1.0 - value.abs();
+        let channel_edge = 1.0 - rivers.width;
+
+        let valley = smoothstep(channel_edge - rivers.bank_width, channel_edge, ridge);
+        let channel = if ridge > channel_edge {
+            (ridge - channel_edge) / rivers.width.max(f64::EPSILON)
+        } else {
+            0.0
+        };
+        (valley, channel)
+    }
+
+    fn classify_biome(
+        &self,
+        height: i32,
+        temp: f64,
+        humid: f64,
+        mountain: f64,
+        swamp: f64,
+        river: f64,
+    ) -> Biome {
+        let biomes = &self.config.biomes;
+
+        // 低海拔地区为海洋
+        if height < biomes.ocean_height {
+            return Biome::Ocean;
+        }
+        // 河道（河岸仍属于周围的生物群系）
+        if river > 0.0 && height <= self.config.terrain.water_level {
+            return Biome::River;
+        }
+        if mountain > 0.5 {
+            return Biome::Mountains;
+        }
+        // 沼泽贴近水位，需要在海滩之前判断
+        if swamp > 0.5 {
+            return Biome::Swamp;
+        }
+        // 海拔稍高的地区为海滩
+        if height < biomes.beach_height {
+            return Biome::Beach;
+        }
+
+        // 根据温度和湿度确定生物群系
+        match (temp, humid) {
+            // 低温地区
+            (t, _) if t < biomes.cold_temperature => {
+                if humid > biomes.taiga_humidity {
+                    Biome::Taiga // 湿润的寒带 → 针叶林
+                } else {
+                    Biome::Snowy // 干燥的寒带 → 雪地
+                }
+            }
+            // 高温低湿 → 沙漠
+            (t, h) if t > biomes.desert_temperature && h < biomes.desert_humidity => {
+                Biome::Desert
+            }
+            // 高湿度 → 森林
+            (_, h) if h > biomes.forest_humidity => Biome::Forest,
+            // 中等湿度 → 白桦林
+            (_, h) if h > biomes.birch_humidity => Biome::BirchForest,
+            // 默认 → 平原
+            _ => Biome::Plains,
+        }
+    }
+
+    /// 根据生物群系和地表高度确定地表层和次表层方块
+    /// - 山地：岩石线以上为裸露岩石，雪线以上覆盖积雪
+    /// - 沼泽：水下的地表为黏土
+    /// - 河流：浅滩为沙子，水深超过一格的河床为砂砾
+    /// - 海洋：海底按水深依次为沙子、砂砾和黏土
+    pub fn surface_layers(&self, biome: Biome, height: i32) -> (VoxelKind, VoxelKind) {
+        let surface_y = height - 1;
+        match biome {
+            Biome::Ocean => {
+                let oceans = &self.config.oceans;
+                let depth = self.config.terrain.water_level - surface_y;
+                let floor = if depth <= oceans.sand_depth {
+                    VoxelKind::Sand
+                } else if depth <= oceans.gravel_depth {
+                    VoxelKind::Gravel
+                } else {
+                    VoxelKind::Clay
+                };
+                (floor, floor)
+            }
+            Biome::River if surface_y < self.config.terrain.water_level - 1 => {
+                (VoxelKind::Gravel, VoxelKind::Gravel)
+            }
+            Biome::Mountains if surface_y >= self.config.mountains.snow_height => {
+                (VoxelKind::Snow, VoxelKind::Stone)
+            }
+            Biome::Mountains if surface_y >= self.config.mountains.rock_height => {
+                (VoxelKind::Stone, VoxelKind::Stone)
+            }
+            Biome::Swamp if surface_y < self.config.terrain.water_level => {
+                (VoxelKind::Clay, VoxelKind::Clay)
+            }
+            _ => (biome.surface_block(), biome.subsurface_block()),
+        }
+    }
+
+    /// 判断指定位置是否应该生成洞穴
+    /// 使用3D噪声生成自然的洞穴系统
+    /// 洞穴只在配置的高度范围内生成（默认Y=-60到Y=60），
+    /// deep_y 以下逐渐过渡为尺度更大的深层洞穴
+    pub fn is_cave(&self, x: i32, y: i32, z: i32) -> bool {
+        let caves = &self.config.caves;
+        if y > caves.max_y || y < caves.min_y {
+            return false;
+        }
+        let sample = |scale: f64, y_offset: f64| {
+            self.seed.cave_noise.get([
+                x as f64 * scale,
+                y as f64 * scale + y_offset,
+                z as f64 * scale,
+            ])
+        };
+
+        // 比较噪声与阈值的差值，两种洞穴按深度插值
+        let mut margin = sample(caves.scale, 0.0) - caves.threshold;
+        let deep = 1.0
+            - smoothstep(
+                caves.deep_y as f64 - DEEP_CAVE_BLEND,
+                caves.deep_y as f64 + DEEP_CAVE_BLEND,
+                y as f64,
+            );
+        if deep > 0.0 {
+            // 偏移采样位置，深层洞穴不与浅层洞穴重合
+            let deep_margin = sample(caves.deep_scale, 100.0) - caves.deep_threshold;
+            margin += (deep_margin - margin) * deep;
+        }
+        margin > 0.0
+    }
+
+    /// 判断深层洞穴中的指定位置是否被熔岩填充
+    pub fn is_lava(&self, x: i32, y: i32, z: i32) -> bool {
+        let lava = &self.config.lava;
+        if y > lava.max_y {
+            return false;
+        }
+        let scale = lava.scale;
+        let value = self.seed.detail_noise.get([
+            x as f64 * scale,
+            y as f64 * scale,
+            z as f64 * scale + 500.0,
+        ]);
+        value > lava.threshold
+    }
+
+    /// 判断指定位置是否属于浮空岛
+    /// 使用3D噪声生成浮空岛地形
+    /// 浮空岛在配置的高度范围内生成（默认Y=65到Y=110）
+    pub fn is_floating_island(&self, x: i32, y: i32, z: i32) -> bool {
+        let islands = &self.config.floating_islands;
+
+        // 浮空岛只在高空生成
+        if y < islands.min_y || y > islands.max_y {
+            return false;
+        }
+
+        // 使用3D噪声生成浮空岛
+        let scale = islands.scale; // 较大的尺度产生较大的浮空岛
+        let noise_value = self.seed.terrain_noise.get([
+            x as f64 * scale,
+            y as f64 * scale * 0.5, // Y轴压缩，让岛更扁平
+            z as f64 * scale,
+        ]);
+
+        // 添加细节噪声
+        let detail_scale = islands.detail_scale;
+        let detail = self.seed.detail_noise.get([
+            x as f64 * detail_scale,
+            y as f64 * detail_scale,
+            z as f64 * detail_scale,
+        ]);
+
+        // 计算到岛中心的距离，让浮空岛有边界
+        let island_center_scale = islands.region_scale;
+        let island_noise = self.seed.biome_temp_noise.get([
+            x as f64 * island_center_scale,
+            z as f64 * island_center_scale,
+        ]);
+
+        // 只在特定区域生成浮空岛（稀疏分布）
+        if island_noise < islands.region_threshold {
+            return false;
+        }
+
+        // 高度衰减：越接近上下边界，越难生成
+        let y_normalized =
+            (y - islands.min_y) as f64 / (islands.max_y - islands.min_y).max(1) as f64; // 0.0 到 1.0
+        let y_factor = if y_normalized < 0.5 {
+            // 下半部分：从底部向上逐渐增强
+            y_normalized * 2.0
+        } else {
+            // 上半部分：从顶部向下逐渐减弱
+            (1.0 - y_normalized) * 2.0
+        };
+
+        // 综合判断
+        let threshold = 0.4 - y_factor * 0.2;
+        (noise_value + detail * 0.3) > threshold
+    }
+
+    /// 根据位置和深度生成矿石
+    /// 越深的地方生成越稀有的矿石（默认配置）
+    /// - 钻石矿：Y < 16，最稀有
+    /// - 金矿：Y < 32，稀有
+    /// - 铁矿：Y < 48，常见
+    /// - 煤矿：Y >= 48，最常见
+    pub fn get_ore(&self, x: i32, y: i32, z: i32) -> Option<VoxelKind> {
+        let ores = &self.config.ores;
+        let scale = ores.scale;
+        let noise = self
+            .seed
+            .detail_noise
+            .get([x as f64 * scale, y as f64 * scale, z as f64 * scale]);
+
+        if noise > ores.threshold {
+            if y < ores.diamond_max_y && noise > ores.diamond_threshold {
+                Some(VoxelKind::DiamondOre)
+            } else if y < ores.gold_max_y && noise > ores.gold_threshold {
+                Some(VoxelKind::GoldOre)
+            } else if y < ores.iron_max_y {
+                Some(VoxelKind::IronOre)
+            } else {
+                Some(VoxelKind::CoalOre)
+            }
+        } else {
+            None
+        }
+    }
+
+    /// 判断指定位置是否应该放置树木
+    /// 不同生物群系有不同的树木生成概率
+    pub fn should_place_tree(&self, x: i32, z: i32, biome: Biome) -> bool {
+        let trees = &self.config.trees;
+
+        // 根据生物群系设置树木生成概率
+        let tree_chance = match biome {
+            Biome::Forest | Biome::Taiga => trees.forest_chance, // 森林和针叶林：默认6%
+            Biome::BirchForest => trees.birch_forest_chance,     // 白桦林：默认4%
+            Biome::Plains => trees.plains_chance,                // 平原：默认0.3%
+            Biome::Swamp => trees.swamp_chance,                  // 沼泽：默认2%
+            Biome::Mountains => trees.mountain_chance,           // 山地：默认1.5%
+            _ => 0.0,                                            // 其他生物群系不生成树木
+        };
+
+        if tree_chance <= 0.0 {
+            return false;
+        }
+
+        // 使用噪声函数随机决定是否生成树木
+        let scale = trees.noise_scale;
+        let noise = self.seed.detail_noise.get([x as f64 * scale, z as f64 * scale]);
+        noise > (1.0 - tree_chance * 2.0)
+    }
+
+    /// 确定指定位置的地表植被（高草丛、枯死的灌木等）
+    pub fn get_vegetation(&self, x: i32, z: i32, biome: Biome) -> Option<VoxelKind> {
+        let vegetation = &self.config.vegetation;
+        // 稀有的植物排在前面，占据噪声最高的区间
+        let plants: &[(VoxelKind, f64)] = match biome {
+            Biome::Swamp => &[
+                (VoxelKind::DeadBush, vegetation.swamp_dead_bush_chance),
+                (VoxelKind::TallGrass, vegetation.swamp_tall_grass_chance),
+            ],
+            _ => &[],
+        };
+        if plants.is_empty() {
+            return None;
+        }
+
+        // 与树木使用同一噪声源，偏移采样位置避免植被与树木重合
+        let scale = vegetation.noise_scale;
+        let noise = self
+            .seed
+            .detail_noise
+            .get([x as f64 * scale + 1000.0, z as f64 * scale - 1000.0]);
+
+        let mut cumulative = 0.0;
+        for &(plant, chance) in plants {
+            cumulative += chance;
+            if noise > 1.0 - cumulative * 2.0 {
+                return Some(plant);
+            }
+        }
+        None
+    }
+
+    /// 生成指定区块的完整地形数据（3D分层版本）
+    /// 生成步骤：
+    /// 1. 计算chunk的世界Y范围
+    /// 2. 遍历chunk内的每个体素
+    /// 3. 根据世界坐标决定体素类型
+    /// 4. 装饰洞穴地面（见 [`caves`]）
+    /// 5. 生成跨chunk结构（树木等）的部分
+    /// 6. 写入与chunk相交的预制结构（村庄、遗迹、地牢等）
+    pub fn generate_chunk(&self, chunk_pos: ChunkPos) -> ChunkData {
+        // 从不取消的令牌总会得到完整的区块
+        self.generate_chunk_cancellable(chunk_pos, &CancelToken::default())
+            .unwrap_or_default()
+    }
+
+    /// 与 [`generate_chunk`](Self::generate_chunk) 相同，但每生成一层体素检查一次取消令牌，
+    /// 区块不再需要时提前返回 None
+    pub fn generate_chunk_cancellable(
+        &self,
+        chunk_pos: ChunkPos,
+        cancel: &CancelToken,
+    ) -> Option<ChunkData> {
+        if self.config.mode != GeneratorMode::Noise {
+            return Some(self.generate_flat_chunk(chunk_pos));
+        }
+        let mut chunk = ChunkData::new();
+        let origin = chunk_pos.world_origin();
+
+        // 计算chunk的世界Y范围
+        let chunk_y_min = origin.y;
+        let chunk_y_max = origin.y + CHUNK_SIZE - 1;
+
+        let terrain = &self.config.terrain;
+        let water_level = terrain.water_level;
+        let islands = &self.config.floating_islands;
+
+        // 完全位于世界高度范围之外的区块为空
+        if chunk_y_max < terrain.min_y || chunk_y_min > terrain.max_y {
+            return Some(chunk);
+        }
+
+        // 每列的地形高度和生物群系只采样一次
+        let columns: Vec<TerrainColumn> = (0..CHUNK_SIZE)
+            .flat_map(|lz| {
+                (0..CHUNK_SIZE).map(move |lx| self.sample_column(origin.x + lx, origin.z + lz))
+            })
+            .collect();
+        let column = |lx: i32, lz: i32| columns[(lz * CHUNK_SIZE + lx) as usize];
+
+        // 遍历chunk内的每个体素
+        for ly in 0..CHUNK_SIZE {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let world_y = chunk_y_min + ly;
+
+            for lz in 0..CHUNK_SIZE {
+                for lx in 0..CHUNK_SIZE {
+                    let world_x = origin.x + lx;
+                    let world_z = origin.z + lz;
+
+                    // 获取该列的地形高度和生物群系
+                    let TerrainColumn { height, biome } = column(lx, lz);
+
+                    // 判断当前体素应该是什么类型
+                    let kind = if world_y < terrain.min_y || world_y > terrain.max_y {
+                        // 世界高度范围之外
+                        VoxelKind::Air
+                    } else if world_y == terrain.min_y {
+                        // 世界底部：基岩（不可破坏）
+                        VoxelKind::Stone // 或者添加 VoxelKind::Bedrock
+                    } else if world_y < height {
+                        // 地表以下：根据深度生成不同材料
+                        if self.is_cave(world_x, world_y, world_z) {
+                            // 洞穴空间：空气，深处的部分洞穴积满熔岩
+                            if self.is_lava(world_x, world_y, world_z) {
+                                VoxelKind::Lava
+                            } else {
+                                VoxelKind::Air
+                            }
+                        } else if world_y == height - 1 {
+                            // 地表层
+                            self.surface_layers(biome, height).0
+                        } else if world_y > height - 5 {
+                            // 次表层（地表下1-4层）
+                            self.surface_layers(biome, height).1
+                        } else {
+                            // 深层：石头或矿石
+                            self.get_ore(world_x, world_y, world_z)
+                                .unwrap_or(VoxelKind::Stone)
+                        }
+                    } else if world_y >= height && world_y <= water_level {
+                        // 地表到水位之间：水体
+                        if biome == Biome::Snowy && world_y == water_level {
+                            VoxelKind::Ice
+                        } else {
+                            VoxelKind::Water
+                        }
+                    } else if world_y > water_level && self.is_floating_island(world_x, world_y, world_z) {
+                        // 高空：浮空岛
+                        // 使用简单的高度判断来决定岛的材质
+                        // 计算浮空岛局部的顶部和底部
+                        let above = self.is_floating_island(world_x, world_y + 1, world_z);
+                        let below = self.is_floating_island(world_x, world_y - 1, world_z);
+
+                        if !above {
+                            // 顶层：草地
+                            VoxelKind::Grass
+                        } else if !below || world_y < islands.min_y + 3 {
+                            // 底层或接近底部：石头
+                            VoxelKind::Stone
+                        } else {
+                            // 中间层：泥土
+                            VoxelKind::Dirt
+                        }
+                    } else {
+                        // 地表以上：空气（或树木，后续处理）
+                        VoxelKind::Air
+                    };
+
+                    if kind != VoxelKind::Air {
+                        chunk.set(lx, ly, lz, kind);
+                    }
+                }
+            }
+        }
+
+        // 装饰洞穴地面（砂砾、黏土、水洼、枯死的灌木和荧光蘑菇）
+        self.decorate_caves(&mut chunk, origin, &columns);
+
+        // 生成树木和植被（在地表和浮空岛上生成）
+        // 地表树木
+        let max_height = columns.iter().map(|c| c.height).max().unwrap_or(0);
+        if chunk_y_min <= max_height + MAX_TREE_HEIGHT && chunk_y_max >= water_level {
+            for lz in 0..CHUNK_SIZE {
+                for lx in 0..CHUNK_SIZE {
+                    let world_x = origin.x + lx;
+                    let world_z = origin.z + lz;
+                    let TerrainColumn { height, biome } = column(lx, lz);
+                    if height <= water_level || height + MAX_TREE_HEIGHT > terrain.max_y {
+                        continue;
+                    }
+
+                    // 树木只长在草地上（山地的岩石和积雪上不长树）
+                    let on_grass = matches!(
+                        self.surface_layers(biome, height).0,
+                        VoxelKind::Grass | VoxelKind::SwampGrass
+                    );
+                    if on_grass && self.should_place_tree(world_x, world_z, biome) {
+                        let tree_base_y = height + 1;
+                        // 只生成位于当前chunk Y范围内的树木部分
+                        self.generate_tree_partial(
+                            &mut chunk,
+                            lx,
+                            tree_base_y,
+                            lz,
+                            biome,
+                            chunk_y_min,
+                            chunk_y_max,
+                        );
+                    } else if let Some(plant) = self.get_vegetation(world_x, world_z, biome) {
+                        // 植被放在地表方块正上方，地表被洞穴挖空时不放置
+                        let local_y = height - chunk_y_min;
+                        if (0..CHUNK_SIZE).contains(&local_y)
+                            && chunk.get(lx, local_y, lz) == VoxelKind::Air
+                            && !self.is_cave(world_x, height - 1, world_z)
+                        {
+                            chunk.set(lx, local_y, lz, plant);
+                        }
+                    }
+                }
+            }
+        }
+
+        // 浮空岛树木
+        let island_tree_max_y = islands.max_y - 10;
+        if chunk_y_min <= island_tree_max_y && chunk_y_max >= islands.min_y {
+            for lz in 0..CHUNK_SIZE {
+                for lx in 0..CHUNK_SIZE {
+                    let world_x = origin.x + lx;
+                    let world_z = origin.z + lz;
+
+                    // 在浮空岛上寻找合适的位置生成树木
+                    for ly in 0..CHUNK_SIZE {
+                        let world_y = chunk_y_min + ly;
+                        if world_y < islands.min_y || world_y > island_tree_max_y {
+                            continue;
+                        }
+
+                        // 检查当前位置是草地且上方是空气（浮空岛表面）
+                        if chunk.get(lx, ly, lz) == VoxelKind::Grass
+                            && chunk.get(lx, ly + 1, lz) == VoxelKind::Air
+                        {
+                            // 使用噪声决定是否生成树木（稀疏分布）
+                            let scale = 0.3;
+                            let noise = self.seed.detail_noise.get([
+                                world_x as f64 * scale,
+                                world_z as f64 * scale,
+                            ]);
+                            if noise > islands.tree_threshold {
+                                // 浮空岛上生成小树
+                                self.generate_tree_partial(
+                                    &mut chunk,
+                                    lx,
+                                    world_y + 1,
+                                    lz,
+                                    Biome::FloatingIslands,
+                                    chunk_y_min,
+                                    chunk_y_max,
+                                );
+                            }
+                            break; // 每列只检查一次
+                        }
+                    }
+                }
+            }
+        }
+
+        // 预制结构最后写入，覆盖地形和树木
+        self.generate_structures(&mut chunk, chunk_pos);
+
+        Some(chunk)
+    }
+
+    /// 生成树木的部分（仅生成在当前chunk范围内的部分）
+    /// 支持跨chunk的树木生成
+    fn generate_tree_partial(
+        &self,
+        chunk: &mut ChunkData,
+        local_x: i32,
+        tree_base_world_y: i32,
+        local_z: i32,
+        biome: Biome,
+        chunk_y_min: i32,
+        chunk_y_max: i32,
+    ) {
+        let origin = IVec3::new(local_x, tree_base_world_y - chunk_y_min, local_z);
+        for (offset, kind) in tree_blocks(biome) {
+            let pos = origin + offset;
+            let world_y = chunk_y_min + pos.y;
+            // 只写入本区块内的部分，跨区块的树木由相邻区块各自补全
+            if world_y >= chunk_y_min
+                && world_y <= chunk_y_max
+                && (0..CHUNK_SIZE).contains(&pos.x)
+                && (0..CHUNK_SIZE).contains(&pos.z)
+            {
+                chunk.set(pos.x, pos.y, pos.z, kind);
+            }
+        }
+    }
+}
+
+/// 可跨线程共享的地形生成输入
+///
+/// 种子中的噪声生成器、生成配置和结构模板只在发生变化时构建一次，
+/// 异步任务克隆 SharedTerrain（仅增加引用计数），再在任务内通过 generator()
+/// 借用它创建 TerrainGenerator。今后加入构建成本更高的噪声生成器时，
+/// 只需放进 WorldSeed，不会影响每个区块任务的开销。
+#[derive(Resource, Clone)]
+pub struct SharedTerrain {
+    seed: Arc<WorldSeed>,
+    config: Arc<WorldGenConfig>,
+    structures: Arc<Vec<StructureTemplate>>,
+}
+
+impl SharedTerrain {
+    pub fn new(
+        seed: WorldSeed,
+        config: WorldGenConfig,
+        structures: Arc<Vec<StructureTemplate>>,
+    ) -> Self {
+        Self {
+            seed: Arc::new(seed),
+            config: Arc::new(config),
+            structures,
+        }
+    }
+
+    /// 世界种子值
+    pub fn seed(&self) -> u32 {
+        self.seed.seed
+    }
+
+    /// 生成配置
+    pub fn config(&self) -> &WorldGenConfig {
+        &self.config
+    }
+
+    /// 创建借用共享数据的地形生成器
+    pub fn generator(&self) -> TerrainGenerator<'_> {
+        TerrainGenerator::new(&self.seed, &self.config).with_structures(&self.structures)
+    }
+}
+
+impl FromWorld for SharedTerrain {
+    fn from_world(world: &mut World) -> Self {
+        let seed = world.get_resource::<WorldSeed>().cloned().unwrap_or_default();
+        let config = world.get_resource::<WorldGenConfig>().cloned().unwrap_or_default();
+        let structures = world
+            .get_resource::<StructureRegistry>()
+            .map(|registry| registry.templates.clone())
+            .unwrap_or_default();
+        Self::new(seed, config, structures)
+    }
+}
+
+/// 种子、生成配置或结构模板变化时重建共享地形数据
+pub fn sync_shared_terrain(
+    seed: Res<WorldSeed>,
+    config: Res<WorldGenConfig>,
+    structures: Res<StructureRegistry>,
+    mut shared: ResMut<SharedTerrain>,
+) {
+    if !seed.is_changed() && !config.is_changed() && !structures.is_changed() {
+        return;
+    }
+    *shared = SharedTerrain::new(seed.clone(), config.clone(), structures.templates.clone());
+}
+
+/// 边长为 2 * radius 的方形环上间隔 step 的偏移（radius 为 0 时只有原点）
+fn ring_offsets(radius: i32, step: i32) -> Vec<(i32, i32)> {
+    if radius == 0 {
+        return vec![(0, 0)];
+    }
+    let mut offsets = Vec::new();
+    for d in (-radius..radius).step_by(step as usize) {
+        offsets.push((d, -radius));
+        offsets.push((radius, d));
+        offsets.push((-d, radius));
+        offsets.push((-radius, -d));
+    }
+    offsets
+}
+
+/// 平滑阶跃：x 在 edge0 以下为 0，edge1 以上为 1，中间平滑过渡
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0).max(f64::EPSILON)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::worldgen::{TerrainShape, WorldGenOptions};
+
+    /// 在原点附近稀疏采样，返回满足条件的列
+    fn find_columns(
+        generator: &TerrainGenerator,
+        mut predicate: impl FnMut(TerrainColumn) -> bool,
+    ) -> Vec<(i32, i32, TerrainColumn)> {
+        let mut found = Vec::new();
+        for x in (-2048..2048).step_by(16) {
+            for z in (-2048..2048).step_by(16) {
+                let column = generator.sample_column(x, z);
+                if predicate(column) {
+                    found.push((x, z, column));
+                }
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn test_mountains_rise_above_snow_line() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+
+        let mountains = find_columns(&generator, |c| c.biome == Biome::Mountains);
+        assert!(!mountains.is_empty());
+
+        let snow_height = config.mountains.snow_height;
+        let (x, z, peak) = mountains
+            .into_iter()
+            .max_by_key(|(_, _, column)| column.height)
+            .unwrap();
+        assert!(peak.height > snow_height, "highest peak {}", peak.height);
+        assert_eq!(
+            generator.surface_layers(peak.biome, peak.height).0,
+            VoxelKind::Snow,
+            "peak at ({x}, {z})"
+        );
+    }
+
+    #[test]
+    fn test_swamps_stay_near_water_level() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+        let water_level = config.terrain.water_level;
+
+        let swamps = find_columns(&generator, |c| c.biome == Biome::Swamp);
+        assert!(!swamps.is_empty());
+        // 过渡带边缘只被部分拉平，绝大多数沼泽仍应贴近水位
+        let near_water = swamps
+            .iter()
+            .filter(|(_, _, c)| (c.height - water_level - 1).abs() <= 3)
+            .count();
+        assert!(near_water * 10 >= swamps.len() * 9);
+        // 既有露出水面的陆地，也有浅水塘
+        assert!(swamps.iter().any(|(_, _, c)| c.height > water_level + 1));
+        assert!(swamps.iter().any(|(_, _, c)| c.height <= water_level));
+    }
+
+    #[test]
+    fn test_ocean_floor_bands_by_depth() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+        let water_level = config.terrain.water_level;
+
+        let oceans = find_columns(&generator, |c| c.biome == Biome::Ocean);
+        assert!(!oceans.is_empty());
+        // 大陆坡让海底比海洋边界深得多，但不超过最大深度
+        let deepest = oceans.iter().map(|(_, _, c)| c.height).min().unwrap();
+        assert!(deepest < config.biomes.ocean_height - 12, "deepest {deepest}");
+        assert!(deepest as f64 >= water_level as f64 - config.oceans.max_depth);
+
+        let floors: Vec<_> = oceans
+            .iter()
+            .map(|(_, _, c)| generator.surface_layers(c.biome, c.height).0)
+            .collect();
+        for kind in [VoxelKind::Sand, VoxelKind::Gravel, VoxelKind::Clay] {
+            assert!(floors.contains(&kind), "no {kind:?} on the ocean floor");
+        }
+        // 越深的海底材料越靠后
+        let band = |height: i32| match generator.surface_layers(Biome::Ocean, height).0 {
+            VoxelKind::Sand => 0,
+            VoxelKind::Gravel => 1,
+            _ => 2,
+        };
+        assert!((deepest..config.biomes.ocean_height)
+            .collect::<Vec<_>>()
+            .windows(2)
+            .all(|pair| band(pair[0]) >= band(pair[1])));
+    }
+
+    #[test]
+    fn test_lava_only_in_deep_caves() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+
+        let mut lava = 0;
+        for cx in -4..4 {
+            for cz in -4..4 {
+                for cy in config.terrain.min_y.div_euclid(CHUNK_SIZE)..0 {
+                    let chunk_pos = ChunkPos::new(cx, cy, cz);
+                    let chunk = generator.generate_chunk(chunk_pos);
+                    let origin = chunk_pos.world_origin();
+                    for (idx, kind) in chunk.voxels.iter().enumerate() {
+                        if kind != VoxelKind::Lava {
+                            continue;
+                        }
+                        let y = idx as i32 / (CHUNK_SIZE * CHUNK_SIZE);
+                        assert!(origin.y + y <= config.lava.max_y);
+                        lava += 1;
+                    }
+                }
+            }
+        }
+        assert!(lava > 0);
+    }
+
+    #[test]
+    fn test_world_limits() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+        let min_y = config.terrain.min_y;
+
+        let below_pos = ChunkPos::new(0, min_y.div_euclid(CHUNK_SIZE) - 1, 0);
+        let below = generator.generate_chunk(below_pos);
+        assert_eq!(below.voxels.uniform_value(), Some(VoxelKind::Air));
+
+        let bottom_pos = ChunkPos::from_world_pos(0, min_y, 0);
+        let bottom = generator.generate_chunk(bottom_pos);
+        let local_y = min_y - bottom_pos.world_origin().y;
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                assert_eq!(bottom.get(x, local_y, z), VoxelKind::Stone);
+            }
+        }
+    }
+
+    #[test]
+    fn test_configurable_depth_limit() {
+        let seed = WorldSeed::default();
+        let mut config = WorldGenConfig::default();
+        let options = WorldGenOptions {
+            shape: TerrainShape::Flat,
+            ..Default::default()
+        };
+        options.apply(&mut config);
+        config.terrain.min_y = -128;
+        let generator = TerrainGenerator::new(&seed, &config);
+
+        // 默认最低高度以下仍是连续的深层岩石（平坦地形没有洞穴）
+        let bottom = ChunkPos::from_world_pos(-5, -128, -5);
+        assert_eq!(bottom, ChunkPos::new(-1, -8, -1));
+        for chunk_y in bottom.y..0 {
+            let chunk = generator.generate_chunk(ChunkPos::new(bottom.x, chunk_y, bottom.z));
+            assert!(chunk.voxels.iter().all(|kind| kind.is_solid()));
+        }
+
+        let below = generator.generate_chunk(ChunkPos::new(-1, bottom.y - 1, -1));
+        assert_eq!(below.voxels.uniform_value(), Some(VoxelKind::Air));
+    }
+
+    #[test]
+    fn test_rivers_fill_channels_across_chunk_boundaries() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+        let water_level = config.terrain.water_level;
+
+        // 找一处河道横跨区块边界（x = 16k - 1 与 16k）的位置
+        let (x, z) = (-2048..2048)
+            .step_by(4)
+            .flat_map(|z| (-128..128).map(move |k| (k * CHUNK_SIZE - 1, z)))
+            .find(|&(x, z)| {
+                generator.get_biome(x, z) == Biome::River
+                    && generator.get_biome(x + 1, z) == Biome::River
+            })
+            .expect("no river crosses a chunk boundary");
+
+        for world_x in [x, x + 1] {
+            let column = generator.sample_column(world_x, z);
+            assert!(column.height <= water_level);
+
+            let chunk_pos = ChunkPos::from_world_pos(world_x, water_level, z);
+            let chunk = generator.generate_chunk(chunk_pos);
+            let origin = chunk_pos.world_origin();
+            let (lx, lz) = (world_x - origin.x, z - origin.z);
+
+            assert_eq!(chunk.get(lx, water_level - origin.y, lz), VoxelKind::Water);
+            let bed_y = column.height - 1;
+            if bed_y >= origin.y && !generator.is_cave(world_x, bed_y, z) {
+                let bed = chunk.get(lx, bed_y - origin.y, lz);
+                assert!(matches!(bed, VoxelKind::Sand | VoxelKind::Gravel), "{bed:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_spawn_on_land() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+        let water_level = config.terrain.water_level;
+
+        // 从海洋中间出发也能找到附近的陆地
+        let (ocean_x, ocean_z, _) = find_columns(&generator, |c| c.biome == Biome::Ocean)
+            .into_iter()
+            .next()
+            .expect("no ocean near the origin");
+        let spawn = generator.find_spawn(ocean_x, ocean_z, 2048);
+        let column = generator.sample_column(spawn.x, spawn.z);
+        assert_eq!(spawn.y, column.height);
+        assert!(column.height > water_level + 1);
+        assert!(!matches!(column.biome, Biome::Ocean | Biome::River));
+
+        // 搜索半径内没有陆地时停在水面上
+        let fallback = generator.find_spawn(ocean_x, ocean_z, 0);
+        assert_eq!(fallback.y, water_level + 1);
+    }
+
+    #[test]
+    fn test_ring_offsets() {
+        assert_eq!(ring_offsets(0, 4), vec![(0, 0)]);
+        let ring = ring_offsets(8, 4);
+        assert_eq!(ring.len(), 16);
+        assert!(ring.iter().all(|(dx, dz)| dx.abs().max(dz.abs()) == 8));
+    }
+
+    #[test]
+    fn test_flat_terrain_has_constant_height() {
+        let seed = WorldSeed::default();
+        let mut config = WorldGenConfig::default();
+        let options = WorldGenOptions {
+            shape: TerrainShape::Flat,
+            ..Default::default()
+        };
+        options.apply(&mut config);
+        let generator = TerrainGenerator::new(&seed, &config);
+
+        let base_height = config.terrain.base_height;
+        for x in (-1024..1024).step_by(37) {
+            for z in (-1024..1024).step_by(41) {
+                assert_eq!(generator.get_height(x, z), base_height);
+                assert!(!generator.is_cave(x, base_height - 8, z));
+            }
+        }
+    }
+
+    #[test]
+    fn test_cancelled_generation_returns_none() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig::default();
+        let generator = TerrainGenerator::new(&seed, &config);
+        let chunk_pos = ChunkPos::new(0, 0, 0);
+
+        let cancel = CancelToken::default();
+        let chunk = generator
+            .generate_chunk_cancellable(chunk_pos, &cancel)
+            .expect("uncancelled generation should finish");
+        assert_eq!(
+            chunk.voxels.to_vec(),
+            generator.generate_chunk(chunk_pos).voxels.to_vec()
+        );
+
+        // 克隆的令牌共享同一个标志
+        cancel.clone().cancel();
+        assert!(generator.generate_chunk_cancellable(chunk_pos, &cancel).is_none());
+    }
+}
@@ -0,0 +1,149 @@
+//! 替代生成模式：超平坦世界和方块展示世界
+//!
+//! 两种模式都不采样噪声，也不生成洞穴、水体、树木和预制结构，
+//! 同一份配置总是生成完全相同的世界，便于复现温度、燃烧等领域模拟的测试场景。
+//!
+//! 方块展示世界在 [`SHOWCASE_FLOOR_Y`] 处铺一层石质地面，地面上按网格摆放除空气外的每种方块，
+//! 相邻方块之间隔开 [`SHOWCASE_SPACING`] 格。网格从出生点 (0, 0) 的斜前方开始，
+//! 每行 [`SHOWCASE_COLUMNS`] 个，按方块编号顺序排列。
+
+use super::{TerrainColumn, TerrainGenerator};
+use crate::voxel::biome::Biome;
+use crate::voxel::chunk::{ChunkData, ChunkPos};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::voxel_kind::VoxelKind;
+use crate::voxel::worldgen::GeneratorMode;
+
+/// 方块展示世界地面的最上层高度
+pub const SHOWCASE_FLOOR_Y: i32 = 31;
+/// 方块展示世界地面的厚度
+const SHOWCASE_FLOOR_DEPTH: i32 = 4;
+/// 展示网格中相邻方块的间距，中间的空气避免相邻方块互相导热或引燃
+pub const SHOWCASE_SPACING: i32 = 4;
+/// 展示网格每行的方块数
+pub const SHOWCASE_COLUMNS: i32 = 6;
+
+/// 展示网格中 (x, z) 处摆放的方块
+pub fn showcase_block(x: i32, z: i32) -> Option<VoxelKind> {
+    if x <= 0 || z <= 0 || x % SHOWCASE_SPACING != 0 || z % SHOWCASE_SPACING != 0 {
+        return None;
+    }
+    let column = x / SHOWCASE_SPACING - 1;
+    let row = z / SHOWCASE_SPACING - 1;
+    if column >= SHOWCASE_COLUMNS {
+        return None;
+    }
+    VoxelKind::ALL
+        .into_iter()
+        .filter(|&kind| kind != VoxelKind::Air)
+        .nth((row * SHOWCASE_COLUMNS + column) as usize)
+}
+
+impl TerrainGenerator<'_> {
+    /// 超平坦和方块展示模式下每一列都相同的地表；噪声模式返回 None
+    pub(super) fn flat_column(&self) -> Option<TerrainColumn> {
+        let height = match self.config.mode {
+            GeneratorMode::Noise => return None,
+            GeneratorMode::Superflat => self.config.superflat.surface_height(),
+            GeneratorMode::Showcase => SHOWCASE_FLOOR_Y + 1,
+        };
+        Some(TerrainColumn {
+            height,
+            biome: Biome::Plains,
+        })
+    }
+
+    /// 生成超平坦或方块展示区块
+    pub(super) fn generate_flat_chunk(&self, chunk_pos: ChunkPos) -> ChunkData {
+        let mut chunk = ChunkData::new();
+        let origin = chunk_pos.world_origin();
+        for ly in 0..CHUNK_SIZE {
+            for lz in 0..CHUNK_SIZE {
+                for lx in 0..CHUNK_SIZE {
+                    let kind = self.flat_block(origin.x + lx, origin.y + ly, origin.z + lz);
+                    if kind != VoxelKind::Air {
+                        chunk.set(lx, ly, lz, kind);
+                    }
+                }
+            }
+        }
+        chunk
+    }
+
+    fn flat_block(&self, x: i32, y: i32, z: i32) -> VoxelKind {
+        match self.config.mode {
+            GeneratorMode::Noise => VoxelKind::Air,
+            GeneratorMode::Superflat => self.config.superflat.block_at(y),
+            GeneratorMode::Showcase => {
+                if y == SHOWCASE_FLOOR_Y + 1 {
+                    showcase_block(x, z).unwrap_or(VoxelKind::Air)
+                } else if y <= SHOWCASE_FLOOR_Y && y > SHOWCASE_FLOOR_Y - SHOWCASE_FLOOR_DEPTH {
+                    VoxelKind::Stone
+                } else {
+                    VoxelKind::Air
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::seed::WorldSeed;
+    use crate::voxel::worldgen::WorldGenConfig;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_superflat_layers() {
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig {
+            mode: GeneratorMode::Superflat,
+            ..Default::default()
+        };
+        let generator = TerrainGenerator::new(&seed, &config);
+
+        // 默认层叠：28 层石头、3 层泥土、1 层草地，地表与噪声地形的基准高度相同
+        assert_eq!(generator.get_height(123, -456), 32);
+        let chunk = generator.generate_chunk(ChunkPos::new(5, 1, -3));
+        assert_eq!(chunk.get(0, 27 - 16, 0), VoxelKind::Stone);
+        assert_eq!(chunk.get(7, 30 - 16, 9), VoxelKind::Dirt);
+        assert_eq!(chunk.get(15, 31 - 16, 15), VoxelKind::Grass);
+        assert!(generator.generate_chunk(ChunkPos::new(0, 2, 0)).is_empty());
+        assert!(generator.generate_chunk(ChunkPos::new(0, -1, 0)).is_empty());
+
+        let spawn = generator.find_spawn(0, 0, 512);
+        assert_eq!(spawn.y, 32);
+    }
+
+    #[test]
+    fn test_showcase_lists_every_block_once() {
+        let extent = SHOWCASE_SPACING * (VoxelKind::ALL.len() as i32 + 1);
+        let mut shown = HashSet::new();
+        for x in -extent..extent {
+            for z in -extent..extent {
+                if let Some(kind) = showcase_block(x, z) {
+                    assert!(shown.insert(kind), "{kind:?} shown twice");
+                }
+            }
+        }
+        assert_eq!(shown.len(), VoxelKind::ALL.len() - 1);
+        assert!(!shown.contains(&VoxelKind::Air));
+
+        let seed = WorldSeed::default();
+        let config = WorldGenConfig {
+            mode: GeneratorMode::Showcase,
+            ..Default::default()
+        };
+        let generator = TerrainGenerator::new(&seed, &config);
+        let chunk = generator.generate_chunk(ChunkPos::new(0, 2, 0));
+        let local_y = SHOWCASE_FLOOR_Y + 1 - 32;
+        assert_eq!(
+            chunk.get(SHOWCASE_SPACING, local_y, SHOWCASE_SPACING),
+            VoxelKind::Grass
+        );
+        assert_eq!(chunk.get(0, local_y, 0), VoxelKind::Air);
+        let floor = generator.generate_chunk(ChunkPos::new(0, 1, 0));
+        assert_eq!(floor.get(0, SHOWCASE_FLOOR_Y - 16, 0), VoxelKind::Stone);
+    }
+}
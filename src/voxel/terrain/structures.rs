@@ -0,0 +1,463 @@
+//! 预制结构生成
+//!
+//! 在世界中放置村庄、遗迹、地牢等预制结构。结构模板定义在
+//! `assets/structures/*.structure.ron` 中：每一层是若干行字符（行对应 Z，列对应 X），
+//! palette 指定字符对应的方块，空格表示保留原有地形。
+//!
+//! ## 确定性放置
+//!
+//! XZ 平面按模板的 spacing 划分为网格区域，每个区域最多放置一个该结构。
+//! 是否生成以及区域内的偏移只由种子、区域坐标和结构名决定，结构完全位于区域内部。
+//! 因此每个区块都能独立算出与自己相交的结构，只写入位于本区块内的部分，
+//! 跨区块的结构在相邻区块各自生成后自然拼接完整。
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, LoadedFolder};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::TerrainGenerator;
+use crate::voxel::biome::Biome;
+use crate::voxel::chunk::{ChunkData, ChunkPos};
+use crate::voxel::constants::CHUNK_SIZE;
+use crate::voxel::voxel_kind::VoxelKind;
+use crate::voxel::worldgen::ChunkRegenerator;
+
+/// 结构模板目录（相对于 assets 目录）
+pub const STRUCTURES_FOLDER: &str = "structures";
+
+/// 结构的竖直锚定方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StructureAnchor {
+    /// 底层与结构中心处的地表方块上方对齐
+    Surface,
+    /// 埋在地下，底层高度在 [min_y, max_y] 内随机
+    Underground { min_y: i32, max_y: i32 },
+}
+
+/// 结构放置规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructurePlacement {
+    /// 网格区域边长（方块），小于结构尺寸时按结构尺寸计算
+    pub spacing: i32,
+    /// 每个区域生成该结构的概率
+    pub chance: f64,
+    /// 竖直锚定方式
+    pub anchor: StructureAnchor,
+    /// 底层相对锚点的竖直偏移
+    #[serde(default)]
+    pub y_offset: i32,
+    /// 允许生成的生物群系，为空表示不限制（按结构中心的生物群系判断）
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+}
+
+/// 结构模板文件格式
+#[derive(Deserialize)]
+struct StructureTemplateFile {
+    name: String,
+    placement: StructurePlacement,
+    palette: HashMap<char, VoxelKind>,
+    /// 从下到上的各层，每层为若干行字符
+    layers: Vec<Vec<String>>,
+}
+
+/// 结构模板
+#[derive(Asset, TypePath, Debug, Clone, PartialEq)]
+pub struct StructureTemplate {
+    /// 结构名称（同时作为放置哈希的盐值）
+    pub name: String,
+    /// 放置规则
+    pub placement: StructurePlacement,
+    /// 尺寸（X 列数、Y 层数、Z 行数）
+    pub size: IVec3,
+    /// 方块数据，None 表示保留原有地形
+    blocks: Vec<Option<VoxelKind>>,
+}
+
+impl StructureTemplate {
+    /// 解析 RON 格式的模板文件内容
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, StructureTemplateLoaderError> {
+        let file: StructureTemplateFile = ron::de::from_bytes(bytes)?;
+        Self::from_layers(file.name, file.placement, &file.palette, &file.layers)
+    }
+
+    /// 根据字符层构建模板
+    pub fn from_layers(
+        name: impl Into<String>,
+        placement: StructurePlacement,
+        palette: &HashMap<char, VoxelKind>,
+        layers: &[Vec<String>],
+    ) -> Result<Self, StructureTemplateLoaderError> {
+        let width = layers
+            .iter()
+            .flatten()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let depth = layers.iter().map(Vec::len).max().unwrap_or(0);
+        if width == 0 || depth == 0 {
+            return Err(StructureTemplateLoaderError::Empty);
+        }
+
+        let size = IVec3::new(width as i32, layers.len() as i32, depth as i32);
+        let mut blocks = vec![None; width * depth * layers.len()];
+
+        for (y, layer) in layers.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, symbol) in row.chars().enumerate() {
+                    if symbol == ' ' {
+                        continue;
+                    }
+                    let Some(&kind) = palette.get(&symbol) else {
+                        return Err(StructureTemplateLoaderError::UnknownSymbol {
+                            layer: y,
+                            row: z,
+                            symbol,
+                        });
+                    };
+                    blocks[x + z * width + y * width * depth] = Some(kind);
+                }
+            }
+        }
+
+        Ok(Self {
+            name: name.into(),
+            placement,
+            size,
+            blocks,
+        })
+    }
+
+    /// 获取模板局部坐标处的方块，超出范围或保留地形时返回 None
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Option<VoxelKind> {
+        if x < 0 || y < 0 || z < 0 || x >= self.size.x || y >= self.size.y || z >= self.size.z {
+            return None;
+        }
+        self.blocks[(x + z * self.size.x + y * self.size.x * self.size.z) as usize]
+    }
+
+    /// 网格区域边长（保证结构能完整放入区域）
+    pub fn region_size(&self) -> i32 {
+        self.placement
+            .spacing
+            .max(self.size.x)
+            .max(self.size.z)
+            .max(1)
+    }
+
+    /// 由结构名得到的哈希盐值，使不同结构的放置互相独立
+    fn salt(&self) -> u32 {
+        self.name
+            .bytes()
+            .fold(0u32, |acc, b| acc.wrapping_mul(31).wrapping_add(b as u32))
+    }
+}
+
+/// 区域哈希（splitmix64 终结函数），相同输入总是得到相同结果
+fn region_hash(seed: u32, salt: u32, region_x: i32, region_z: i32) -> u64 {
+    let mut h = ((seed as u64) << 32) | salt as u64;
+    h ^= (region_x as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    h ^= (region_z as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
+
+impl TerrainGenerator<'_> {
+    /// 计算指定网格区域内结构的最小角世界坐标，该区域不生成时返回 None
+    pub fn structure_origin(
+        &self,
+        template: &StructureTemplate,
+        region_x: i32,
+        region_z: i32,
+    ) -> Option<IVec3> {
+        let placement = &template.placement;
+        let hash = region_hash(self.seed.seed, template.salt(), region_x, region_z);
+
+        // 低 16 位用于概率判定，其余位决定区域内的偏移和地下高度
+        let roll = (hash & 0xFFFF) as f64 / 65536.0;
+        if roll >= placement.chance {
+            return None;
+        }
+
+        let region_size = template.region_size();
+        let slack_x = (region_size - template.size.x + 1) as u64;
+        let slack_z = (region_size - template.size.z + 1) as u64;
+        let x = region_x * region_size + ((hash >> 16) % slack_x) as i32;
+        let z = region_z * region_size + ((hash >> 32) % slack_z) as i32;
+
+        let y = match placement.anchor {
+            StructureAnchor::Surface => {
+                let center_x = x + template.size.x / 2;
+                let center_z = z + template.size.z / 2;
+                if !placement.biomes.is_empty()
+                    && !placement
+                        .biomes
+                        .contains(&self.get_biome(center_x, center_z))
+                {
+                    return None;
+                }
+                self.get_height(center_x, center_z)
+            }
+            StructureAnchor::Underground { min_y, max_y } => {
+                let range = (max_y - min_y).max(0) as u64 + 1;
+                min_y + ((hash >> 48) % range) as i32
+            }
+        };
+
+        Some(IVec3::new(x, y + placement.y_offset, z))
+    }
+
+    /// 将与区块相交的所有结构写入区块（只写入位于区块内的部分）
+    pub(super) fn generate_structures(&self, chunk: &mut ChunkData, chunk_pos: ChunkPos) {
+        let origin = chunk_pos.world_origin();
+        let last = origin + IVec3::splat(CHUNK_SIZE - 1);
+
+        for template in self.structures {
+            let region_size = template.region_size();
+            for region_z in origin.z.div_euclid(region_size)..=last.z.div_euclid(region_size) {
+                for region_x in origin.x.div_euclid(region_size)..=last.x.div_euclid(region_size) {
+                    if let Some(start) = self.structure_origin(template, region_x, region_z) {
+                        stamp_structure(chunk, origin, template, start);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 将结构与区块重叠的部分写入区块
+fn stamp_structure(
+    chunk: &mut ChunkData,
+    chunk_origin: IVec3,
+    template: &StructureTemplate,
+    start: IVec3,
+) {
+    let min = start.max(chunk_origin);
+    let max = (start + template.size).min(chunk_origin + IVec3::splat(CHUNK_SIZE));
+
+    for y in min.y..max.y {
+        for z in min.z..max.z {
+            for x in min.x..max.x {
+                let pos = IVec3::new(x, y, z);
+                let local = pos - start;
+                if let Some(kind) = template.get(local.x, local.y, local.z) {
+                    let chunk_local = pos - chunk_origin;
+                    chunk.set(chunk_local.x, chunk_local.y, chunk_local.z, kind);
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// 资源加载
+// ============================================================================
+
+/// 结构模板加载错误
+#[derive(Debug, thiserror::Error)]
+pub enum StructureTemplateLoaderError {
+    #[error("无法读取结构模板: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("结构模板解析失败: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("结构模板不包含任何方块")]
+    Empty,
+    #[error("第 {layer} 层第 {row} 行包含未定义的字符 '{symbol}'")]
+    UnknownSymbol {
+        layer: usize,
+        row: usize,
+        symbol: char,
+    },
+}
+
+/// `.structure.ron` 结构模板加载器
+#[derive(Default, TypePath)]
+pub struct StructureTemplateLoader;
+
+impl AssetLoader for StructureTemplateLoader {
+    type Asset = StructureTemplate;
+    type Settings = ();
+    type Error = StructureTemplateLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        StructureTemplate::from_ron(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["structure.ron"]
+    }
+}
+
+/// 当前生效的结构模板（按名称排序，保证生成顺序确定）
+#[derive(Resource, Default, Debug, Clone, PartialEq)]
+pub struct StructureRegistry {
+    pub templates: Arc<Vec<StructureTemplate>>,
+}
+
+/// 结构模板目录句柄
+#[derive(Resource)]
+pub struct StructureFolderHandle(pub Handle<LoadedFolder>);
+
+/// 启动时加载结构模板目录
+pub fn load_structure_templates(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load_folder(STRUCTURES_FOLDER);
+    commands.insert_resource(StructureFolderHandle(handle));
+}
+
+/// 模板目录加载完成或模板修改后更新注册表，并重新生成未修改的区块
+pub fn apply_structure_templates(
+    mut folder_events: MessageReader<AssetEvent<LoadedFolder>>,
+    mut template_events: MessageReader<AssetEvent<StructureTemplate>>,
+    handle: Option<Res<StructureFolderHandle>>,
+    folders: Res<Assets<LoadedFolder>>,
+    templates: Res<Assets<StructureTemplate>>,
+    mut registry: ResMut<StructureRegistry>,
+    mut regenerator: ChunkRegenerator,
+) {
+    // 两个读取器都需要消费完，避免事件残留到下一帧
+    let folder_changed = folder_events.read().count() > 0;
+    let templates_changed = template_events.read().count() > 0;
+    if !folder_changed && !templates_changed {
+        return;
+    }
+
+    let Some(handle) = handle else {
+        return;
+    };
+    let Some(folder) = folders.get(&handle.0) else {
+        return;
+    };
+
+    let mut loaded: Vec<StructureTemplate> = folder
+        .handles
+        .iter()
+        .filter_map(|handle| handle.clone().try_typed::<StructureTemplate>().ok())
+        .filter_map(|handle| templates.get(&handle).cloned())
+        .collect();
+    loaded.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if *registry.templates == loaded {
+        return;
+    }
+
+    let template_count = loaded.len();
+    registry.templates = Arc::new(loaded);
+    let chunk_count = regenerator.regenerate_unmodified();
+    info!(
+        "Loaded {template_count} structure templates, regenerating {chunk_count} unmodified chunks"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::seed::WorldSeed;
+    use crate::voxel::worldgen::WorldGenConfig;
+
+    fn stone_bar(length: usize) -> StructureTemplate {
+        let placement = StructurePlacement {
+            spacing: length as i32,
+            chance: 1.0,
+            anchor: StructureAnchor::Underground { min_y: 4, max_y: 4 },
+            y_offset: 0,
+            biomes: Vec::new(),
+        };
+        let palette = HashMap::from([('#', VoxelKind::Stone)]);
+        let layers = vec![vec!["#".repeat(length)]];
+        StructureTemplate::from_layers("bar", placement, &palette, &layers).unwrap()
+    }
+
+    #[test]
+    fn test_template_from_layers() {
+        let placement = stone_bar(1).placement;
+        let palette = HashMap::from([('#', VoxelKind::Stone), ('.', VoxelKind::Air)]);
+        let layers = vec![vec!["#.".to_string(), " #".to_string()]];
+        let template = StructureTemplate::from_layers("t", placement, &palette, &layers).unwrap();
+
+        assert_eq!(template.size, IVec3::new(2, 1, 2));
+        assert_eq!(template.get(0, 0, 0), Some(VoxelKind::Stone));
+        assert_eq!(template.get(1, 0, 0), Some(VoxelKind::Air));
+        assert_eq!(template.get(0, 0, 1), None);
+        assert_eq!(template.get(2, 0, 0), None);
+    }
+
+    #[test]
+    fn test_unknown_symbol_is_rejected() {
+        let placement = stone_bar(1).placement;
+        let layers = vec![vec!["#?".to_string()]];
+        let result = StructureTemplate::from_layers(
+            "t",
+            placement,
+            &HashMap::from([('#', VoxelKind::Stone)]),
+            &layers,
+        );
+        assert!(matches!(
+            result,
+            Err(StructureTemplateLoaderError::UnknownSymbol { symbol: '?', .. })
+        ));
+    }
+
+    #[test]
+    fn test_bundled_templates_parse() {
+        let files = [
+            include_str!("../../../assets/structures/dungeon.structure.ron"),
+            include_str!("../../../assets/structures/ruin.structure.ron"),
+            include_str!("../../../assets/structures/village.structure.ron"),
+        ];
+        for source in files {
+            let file: StructureTemplateFile = ron::de::from_str(source).unwrap();
+            StructureTemplate::from_layers(file.name, file.placement, &file.palette, &file.layers)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_placement_is_deterministic() {
+        let config = WorldGenConfig::default();
+        let template = stone_bar(24);
+        let seed_a = WorldSeed::new(7);
+        let seed_b = WorldSeed::new(7);
+        let a = TerrainGenerator::new(&seed_a, &config);
+        let b = TerrainGenerator::new(&seed_b, &config);
+
+        for region in -4..4 {
+            let origin = a.structure_origin(&template, region, -region).unwrap();
+            assert_eq!(Some(origin), b.structure_origin(&template, region, -region));
+            // 结构与区域等宽，只能从区域起点开始
+            assert_eq!(origin.x, region * 24);
+            assert_eq!(origin.y, 4);
+        }
+    }
+
+    #[test]
+    fn test_structure_spans_chunk_boundary() {
+        let template = stone_bar(12);
+        let start = IVec3::new(10, 4, 3);
+
+        let mut stone = Vec::new();
+        for chunk_x in 0..2 {
+            let mut chunk = ChunkData::new();
+            let origin = ChunkPos::new(chunk_x, 0, 0).world_origin();
+            stamp_structure(&mut chunk, origin, &template, start);
+            stone.push(
+                chunk
+                    .voxels
+                    .iter()
+                    .filter(|&kind| kind == VoxelKind::Stone)
+                    .count(),
+            );
+        }
+        assert_eq!(stone, vec![6, 6]);
+    }
+}
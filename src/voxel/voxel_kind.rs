@@ -1,9 +1,12 @@
 //! 体素（方块）类型定义
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::voxel::registry::VoxelRegistry;
 
 /// 体素种类枚举 - 定义游戏中所有可用的方块类型
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 pub enum VoxelKind {
     #[default]
     Air,
@@ -30,10 +33,19 @@ pub enum VoxelKind {
     Flower,
     TallGrass,
     DeadBush,
+    SwampGrass,
+    Lava,
+    Obsidian,
+    Sapling,
+    RustedIron,
+    GlowMushroom,
+    /// 薄积雪，变体记录层数减一（见 [`VoxelShape::snow_layers`]）
+    SnowLayer,
 }
 
 /// 体素的物理属性
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VoxelProperties {
     // === 热学属性 ===
     /// 默认温度（摄氏度）
@@ -64,6 +76,8 @@ pub struct VoxelProperties {
     pub burn_rate: f32,
     /// 热释放率（W），燃烧时每秒释放的热量
     pub heat_release: f32,
+    /// 持续产热功率（W），不需要燃烧的热源（如熔岩）每秒向周围释放的热量
+    pub heat_output: f32,
 
     // === 相变属性 ===
     /// 熔点（°C），None 表示不会熔化
@@ -94,6 +108,10 @@ pub struct VoxelProperties {
     pub growth_rate: f32,
     /// 最大生长阶段
     pub max_growth_stage: u8,
+
+    // === 光照属性 ===
+    /// 发光等级（0-15），大于 0 的方块是方块光的光源
+    pub light_emission: u8,
 }
 
 impl Default for VoxelProperties {
@@ -114,6 +132,7 @@ impl Default for VoxelProperties {
             burn_energy: 0.0,
             burn_rate: 0.0,
             heat_release: 0.0,
+            heat_output: 0.0,
             // 相变
             melting_point: None,
             freezing_point: None,
@@ -129,453 +148,162 @@ impl Default for VoxelProperties {
             is_growable: false,
             growth_rate: 0.0,
             max_growth_stage: 0,
+            // 光照
+            light_emission: 0,
+        }
+    }
+}
+
+/// 按面方向覆盖的方块颜色，未指定的面使用方块颜色
+///
+/// 不需要纹理图集就能区分顶面和侧面：草方块顶面是草、侧面是泥土，原木两端露出浅色的年轮
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FaceColors {
+    pub top: Option<Color>,
+    pub side: Option<Color>,
+    pub bottom: Option<Color>,
+}
+
+impl FaceColors {
+    /// 所有面都使用方块颜色
+    pub const NONE: Self = Self {
+        top: None,
+        side: None,
+        bottom: None,
+    };
+}
+
+/// 积雪最多的层数，满层时与整格一样高
+pub const MAX_SNOW_LAYERS: u8 = 8;
+
+/// 方块的网格形状
+///
+/// 顶点的水平坐标精度是 1/8 格、高度精度是 1/32 格（见 [`pack_position`]），
+/// 内缩量和高度按这个精度取整
+///
+/// [`pack_position`]: crate::voxel::mesh::pack_position
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum VoxelShape {
+    /// 占满一格的立方体
+    #[default]
+    Cube,
+    /// 沿两条对角线交叉的两片竖直面片（花草、树苗）
+    Cross,
+    /// 贴着底面、高度不满一格的方块（0.0-1.0）
+    Slab { height: f32 },
+    /// 水平四周各内缩 inset 格的柱体（仙人掌），上下仍与相邻格相接
+    Inset { inset: f32 },
+}
+
+impl VoxelShape {
+    /// 是否占满一格
+    pub fn is_cube(&self) -> bool {
+        *self == VoxelShape::Cube
+    }
+
+    /// 变体为 variant 的积雪的形状：每层 1/8 格高
+    pub fn snow_layers(variant: u8) -> Self {
+        let layers = variant.min(MAX_SNOW_LAYERS - 1) + 1;
+        VoxelShape::Slab {
+            height: layers as f32 / MAX_SNOW_LAYERS as f32,
+        }
+    }
+
+    /// 法线为 normal 的面是否位于格子边界上，只有边界上的面会被相邻方块挡住
+    pub fn face_on_boundary(self, normal: IVec3) -> bool {
+        match self {
+            VoxelShape::Cube => true,
+            VoxelShape::Cross => false,
+            VoxelShape::Slab { height } => normal.y != 1 || height >= 1.0,
+            VoxelShape::Inset { inset } => normal.y != 0 || inset <= 0.0,
         }
     }
 }
 
 /// 体素定义 - 包含体素的所有基础信息
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct VoxelDef {
     /// 方块名称
     pub name: &'static str,
-    /// 方块颜色
+    /// 方块颜色（地图、掉落物和粒子使用的代表色）
     pub color: Color,
+    /// 按面方向覆盖的颜色
+    pub faces: FaceColors,
+    /// 网格形状
+    pub shape: VoxelShape,
     /// 方块物理属性
     pub props: VoxelProperties,
 }
 
+impl VoxelDef {
+    /// 法线为 normal 的面的颜色
+    pub fn face_color(&self, normal: IVec3) -> Color {
+        let face = match normal.y {
+            1 => self.faces.top,
+            -1 => self.faces.bottom,
+            _ => self.faces.side,
+        };
+        face.unwrap_or(self.color)
+    }
+}
+
 impl VoxelKind {
-    /// 获取当前体素种类的完整定义信息
-    pub fn def(self) -> VoxelDef {
-        match self {
-            VoxelKind::Air => VoxelDef {
-                name: "空气",
-                color: Color::NONE,
-                props: VoxelProperties {
-                    temperature: 20.0,
-                    heat_capacity: 1.0,
-                    thermal_conductivity: 0.026, // 空气导热系数很低
-                    env_exchange_coef: 0.0,
-                    humidity: 0.5,
-                    hardness: 0.0,
-                    ductility: 0.0,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Grass => VoxelDef {
-                name: "草方块",
-                color: Color::srgb(0.28, 0.62, 0.25),
-                props: VoxelProperties {
-                    temperature: 18.0,
-                    heat_capacity: 800.0,
-                    thermal_conductivity: 0.25,
-                    env_exchange_coef: 0.1,
-                    humidity: 0.6,
-                    moisture_capacity: 0.4,
-                    is_flammable: true,
-                    ignition_temp: 400.0,
-                    burn_energy: 30.0,
-                    burn_rate: 0.3,
-                    heat_release: 50.0,
-                    hardness: 0.2,
-                    ductility: 0.35,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Dirt => VoxelDef {
-                name: "泥土",
-                color: Color::srgb(0.42, 0.30, 0.18),
-                props: VoxelProperties {
-                    temperature: 16.0,
-                    heat_capacity: 1500.0,
-                    thermal_conductivity: 0.8,
-                    env_exchange_coef: 0.05,
-                    humidity: 0.4,
-                    moisture_capacity: 0.6,
-                    hardness: 0.35,
-                    ductility: 0.2,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Stone => VoxelDef {
-                name: "石头",
-                color: Color::srgb(0.55, 0.55, 0.58),
-                props: VoxelProperties {
-                    temperature: 12.0,
-                    heat_capacity: 2000.0,
-                    thermal_conductivity: 2.5, // 石头导热好
-                    env_exchange_coef: 0.02,
-                    humidity: 0.1,
-                    melting_point: Some(1200.0), // 石头熔点
-                    hardness: 0.9,
-                    ductility: 0.05,
-                    integrity: 1.0,
-                    corrosion_resistance: 0.9,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Sand => VoxelDef {
-                name: "沙子",
-                color: Color::srgb(0.86, 0.82, 0.58),
-                props: VoxelProperties {
-                    temperature: 28.0,
-                    heat_capacity: 830.0,
-                    thermal_conductivity: 0.25,
-                    env_exchange_coef: 0.15,
-                    humidity: 0.05,
-                    evaporation_rate: 0.2,
-                    melting_point: Some(1700.0), // 沙子熔点（变玻璃）
-                    hardness: 0.25,
-                    ductility: 0.45,
-                    integrity: 0.3, // 沙子结构不稳
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Gravel => VoxelDef {
-                name: "砂砾",
-                color: Color::srgb(0.52, 0.50, 0.48),
-                props: VoxelProperties {
-                    temperature: 14.0,
-                    heat_capacity: 1200.0,
-                    thermal_conductivity: 1.5,
-                    env_exchange_coef: 0.05,
-                    humidity: 0.15,
-                    hardness: 0.4,
-                    ductility: 0.3,
-                    integrity: 0.4,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Clay => VoxelDef {
-                name: "黏土",
-                color: Color::srgb(0.62, 0.64, 0.68),
-                props: VoxelProperties {
-                    temperature: 15.0,
-                    heat_capacity: 900.0,
-                    thermal_conductivity: 1.0,
-                    env_exchange_coef: 0.03,
-                    humidity: 0.7,
-                    moisture_capacity: 0.8,
-                    hardness: 0.3,
-                    ductility: 0.5,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Snow => VoxelDef {
-                name: "雪块",
-                color: Color::srgb(0.95, 0.97, 1.0),
-                props: VoxelProperties {
-                    temperature: -5.0,
-                    heat_capacity: 2090.0, // 冰的热容
-                    thermal_conductivity: 0.1, // 雪导热差
-                    env_exchange_coef: 0.2,
-                    humidity: 0.8,
-                    melting_point: Some(0.0),
-                    liquid_form: Some(VoxelKind::Water),
-                    hardness: 0.1,
-                    ductility: 0.2,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Ice => VoxelDef {
-                name: "冰块",
-                color: Color::srgba(0.68, 0.85, 0.95, 0.85),
-                props: VoxelProperties {
-                    temperature: -10.0,
-                    heat_capacity: 2090.0,
-                    thermal_conductivity: 2.22, // 冰导热系数
-                    env_exchange_coef: 0.1,
-                    humidity: 0.9,
-                    melting_point: Some(0.0),
-                    liquid_form: Some(VoxelKind::Water),
-                    hardness: 0.3,
-                    ductility: 0.1,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Water => VoxelDef {
-                name: "水",
-                color: Color::srgba(0.20, 0.45, 0.78, 0.7),
-                props: VoxelProperties {
-                    temperature: 14.0,
-                    heat_capacity: 4186.0, // 水的比热容
-                    thermal_conductivity: 0.6,
-                    env_exchange_coef: 0.3,
-                    humidity: 1.0,
-                    evaporation_rate: 0.1,
-                    freezing_point: Some(0.0),
-                    boiling_point: Some(100.0),
-                    solid_form: Some(VoxelKind::Ice),
-                    hardness: 0.0,
-                    ductility: 1.0,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::OakLog => VoxelDef {
-                name: "橡木原木",
-                color: Color::srgb(0.40, 0.30, 0.18),
-                props: VoxelProperties {
-                    temperature: 20.0,
-                    heat_capacity: 1700.0,
-                    thermal_conductivity: 0.12, // 木材导热差
-                    env_exchange_coef: 0.05,
-                    humidity: 0.3,
-                    moisture_capacity: 0.5,
-                    is_flammable: true,
-                    ignition_temp: 300.0, // 木材着火点
-                    burn_energy: 100.0,
-                    burn_rate: 0.2,
-                    heat_release: 200.0,
-                    hardness: 0.5,
-                    ductility: 0.6,
-                    corrosion_resistance: 0.3,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::OakLeaves => VoxelDef {
-                name: "橡树树叶",
-                color: Color::srgba(0.22, 0.52, 0.20, 0.9),
-                props: VoxelProperties {
-                    temperature: 22.0,
-                    heat_capacity: 500.0,
-                    thermal_conductivity: 0.05,
-                    env_exchange_coef: 0.3,
-                    humidity: 0.5,
-                    is_flammable: true,
-                    ignition_temp: 250.0, // 树叶更易燃
-                    burn_energy: 20.0,
-                    burn_rate: 0.5, // 树叶烧得快
-                    heat_release: 100.0,
-                    hardness: 0.05,
-                    ductility: 0.1,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::BirchLog => VoxelDef {
-                name: "白桦原木",
-                color: Color::srgb(0.85, 0.82, 0.75),
-                props: VoxelProperties {
-                    temperature: 18.0,
-                    heat_capacity: 1600.0,
-                    thermal_conductivity: 0.14,
-                    env_exchange_coef: 0.05,
-                    humidity: 0.35,
-                    is_flammable: true,
-                    ignition_temp: 280.0,
-                    burn_energy: 90.0,
-                    burn_rate: 0.22,
-                    heat_release: 180.0,
-                    hardness: 0.45,
-                    ductility: 0.55,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::BirchLeaves => VoxelDef {
-                name: "白桦树叶",
-                color: Color::srgba(0.45, 0.62, 0.35, 0.9),
-                props: VoxelProperties {
-                    temperature: 20.0,
-                    heat_capacity: 500.0,
-                    thermal_conductivity: 0.05,
-                    env_exchange_coef: 0.3,
-                    humidity: 0.45,
-                    is_flammable: true,
-                    ignition_temp: 240.0,
-                    burn_energy: 18.0,
-                    burn_rate: 0.55,
-                    heat_release: 90.0,
-                    hardness: 0.05,
-                    ductility: 0.1,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::SpruceLog => VoxelDef {
-                name: "云杉原木",
-                color: Color::srgb(0.30, 0.22, 0.12),
-                props: VoxelProperties {
-                    temperature: 8.0,
-                    heat_capacity: 1800.0,
-                    thermal_conductivity: 0.11,
-                    env_exchange_coef: 0.04,
-                    humidity: 0.4,
-                    is_flammable: true,
-                    ignition_temp: 320.0,
-                    burn_energy: 110.0,
-                    burn_rate: 0.18,
-                    heat_release: 220.0,
-                    hardness: 0.55,
-                    ductility: 0.5,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::SpruceLeaves => VoxelDef {
-                name: "云杉树叶",
-                color: Color::srgba(0.15, 0.35, 0.22, 0.9),
-                props: VoxelProperties {
-                    temperature: 6.0,
-                    heat_capacity: 550.0,
-                    thermal_conductivity: 0.06,
-                    env_exchange_coef: 0.25,
-                    humidity: 0.5,
-                    is_flammable: true,
-                    ignition_temp: 260.0,
-                    burn_energy: 25.0,
-                    burn_rate: 0.45,
-                    heat_release: 110.0,
-                    hardness: 0.05,
-                    ductility: 0.1,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Cactus => VoxelDef {
-                name: "仙人掌",
-                color: Color::srgb(0.25, 0.55, 0.20),
-                props: VoxelProperties {
-                    temperature: 35.0,
-                    heat_capacity: 3500.0, // 仙人掌含水量高
-                    thermal_conductivity: 0.5,
-                    env_exchange_coef: 0.1,
-                    humidity: 0.1,
-                    moisture_capacity: 0.9, // 仙人掌储水
-                    is_flammable: false, // 太湿，不易燃
-                    is_growable: true,
-                    growth_rate: 0.01,
-                    max_growth_stage: 3,
-                    hardness: 0.2,
-                    ductility: 0.3,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::CoalOre => VoxelDef {
-                name: "煤矿石",
-                color: Color::srgb(0.25, 0.25, 0.28),
-                props: VoxelProperties {
-                    temperature: 12.0,
-                    heat_capacity: 1300.0,
-                    thermal_conductivity: 0.2, // 煤导热差
-                    env_exchange_coef: 0.02,
-                    humidity: 0.1,
-                    is_flammable: true,
-                    ignition_temp: 450.0, // 煤着火点高
-                    burn_energy: 500.0, // 煤能量高
-                    burn_rate: 0.05, // 煤烧得慢
-                    heat_release: 300.0,
-                    hardness: 0.85,
-                    ductility: 0.05,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::IronOre => VoxelDef {
-                name: "铁矿石",
-                color: Color::srgb(0.58, 0.52, 0.48),
-                props: VoxelProperties {
-                    temperature: 12.0,
-                    heat_capacity: 450.0, // 铁热容低
-                    thermal_conductivity: 80.0, // 铁导热极好
-                    env_exchange_coef: 0.02,
-                    humidity: 0.1,
-                    melting_point: Some(1538.0), // 铁熔点
-                    hardness: 0.9,
-                    ductility: 0.05,
-                    corrosion_resistance: 0.3, // 铁容易生锈
-                    ..Default::default()
-                },
-            },
-            VoxelKind::GoldOre => VoxelDef {
-                name: "金矿石",
-                color: Color::srgb(0.72, 0.65, 0.35),
-                props: VoxelProperties {
-                    temperature: 12.0,
-                    heat_capacity: 129.0, // 金热容很低
-                    thermal_conductivity: 317.0, // 金导热极好
-                    env_exchange_coef: 0.02,
-                    humidity: 0.1,
-                    melting_point: Some(1064.0),
-                    hardness: 0.85,
-                    ductility: 0.15,
-                    corrosion_resistance: 1.0, // 金不腐蚀
-                    ..Default::default()
-                },
-            },
-            VoxelKind::DiamondOre => VoxelDef {
-                name: "钻石矿石",
-                color: Color::srgb(0.45, 0.72, 0.78),
-                props: VoxelProperties {
-                    temperature: 12.0,
-                    heat_capacity: 509.0,
-                    thermal_conductivity: 2200.0, // 钻石导热极好
-                    env_exchange_coef: 0.01,
-                    humidity: 0.1,
-                    hardness: 0.98,
-                    ductility: 0.02,
-                    corrosion_resistance: 1.0,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::Flower => VoxelDef {
-                name: "花",
-                color: Color::srgb(0.85, 0.35, 0.40),
-                props: VoxelProperties {
-                    temperature: 22.0,
-                    heat_capacity: 300.0,
-                    thermal_conductivity: 0.1,
-                    env_exchange_coef: 0.5,
-                    humidity: 0.6,
-                    is_flammable: true,
-                    ignition_temp: 200.0,
-                    burn_energy: 5.0,
-                    burn_rate: 1.0,
-                    heat_release: 20.0,
-                    is_growable: true,
-                    growth_rate: 0.05,
-                    max_growth_stage: 2,
-                    hardness: 0.01,
-                    ductility: 0.05,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::TallGrass => VoxelDef {
-                name: "高草丛",
-                color: Color::srgb(0.35, 0.58, 0.28),
-                props: VoxelProperties {
-                    temperature: 20.0,
-                    heat_capacity: 200.0,
-                    thermal_conductivity: 0.08,
-                    env_exchange_coef: 0.6,
-                    humidity: 0.5,
-                    is_flammable: true,
-                    ignition_temp: 180.0,
-                    burn_energy: 8.0,
-                    burn_rate: 0.8,
-                    heat_release: 30.0,
-                    is_growable: true,
-                    growth_rate: 0.1,
-                    max_growth_stage: 2,
-                    hardness: 0.01,
-                    ductility: 0.05,
-                    ..Default::default()
-                },
-            },
-            VoxelKind::DeadBush => VoxelDef {
-                name: "枯死的灌木",
-                color: Color::srgb(0.55, 0.45, 0.28),
-                props: VoxelProperties {
-                    temperature: 32.0,
-                    heat_capacity: 150.0,
-                    thermal_conductivity: 0.05,
-                    env_exchange_coef: 0.4,
-                    humidity: 0.05,
-                    is_flammable: true,
-                    ignition_temp: 150.0, // 干燥，极易燃
-                    burn_energy: 10.0,
-                    burn_rate: 1.0,
-                    heat_release: 40.0,
-                    hardness: 0.01,
-                    ductility: 0.02,
-                    ..Default::default()
-                },
-            },
-        }
+    /// 所有体素种类，下标即存档中使用的数字编号
+    pub const ALL: [VoxelKind; 31] = [
+        VoxelKind::Air,
+        VoxelKind::Grass,
+        VoxelKind::Dirt,
+        VoxelKind::Stone,
+        VoxelKind::Sand,
+        VoxelKind::Gravel,
+        VoxelKind::Clay,
+        VoxelKind::Snow,
+        VoxelKind::Ice,
+        VoxelKind::Water,
+        VoxelKind::OakLog,
+        VoxelKind::OakLeaves,
+        VoxelKind::BirchLog,
+        VoxelKind::BirchLeaves,
+        VoxelKind::SpruceLog,
+        VoxelKind::SpruceLeaves,
+        VoxelKind::Cactus,
+        VoxelKind::CoalOre,
+        VoxelKind::IronOre,
+        VoxelKind::GoldOre,
+        VoxelKind::DiamondOre,
+        VoxelKind::Flower,
+        VoxelKind::TallGrass,
+        VoxelKind::DeadBush,
+        VoxelKind::SwampGrass,
+        VoxelKind::Lava,
+        VoxelKind::Obsidian,
+        VoxelKind::Sapling,
+        VoxelKind::RustedIron,
+        VoxelKind::GlowMushroom,
+        VoxelKind::SnowLayer,
+    ];
+
+    /// 存档中使用的数字编号
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// 从数字编号还原体素种类，未知编号返回 None
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.get(id as usize).copied()
+    }
+
+    /// 按名称查找体素种类，接受英文名（不区分大小写，可用下划线分词，如 oak_log）或中文名
+    pub fn from_name(name: &str) -> Option<Self> {
+        let ascii: String = name.chars().filter(|&c| c != '_').collect();
+        Self::ALL.into_iter().find(|kind| {
+            format!("{kind:?}").eq_ignore_ascii_case(&ascii) || kind.def().name == name
+        })
+    }
+
+    /// 获取当前体素种类的完整定义信息（来自体素定义注册表）
+    pub fn def(self) -> &'static VoxelDef {
+        VoxelRegistry::current().get(self)
     }
 
     /// 判断体素是否透明（用于渲染优化，透明方块需要渲染相邻面）
@@ -591,14 +319,27 @@ impl VoxelKind {
                 | VoxelKind::Flower
                 | VoxelKind::TallGrass
                 | VoxelKind::DeadBush
+                | VoxelKind::Sapling
+                | VoxelKind::GlowMushroom
         )
     }
 
+    /// 判断体素是否自发光（不受光照影响，亮度足以产生泛光）
+    pub fn is_emissive(self) -> bool {
+        matches!(self, VoxelKind::Lava | VoxelKind::GlowMushroom)
+    }
+
     /// 判断体素是否为固体（用于碰撞检测）
     pub fn is_solid(self) -> bool {
         !matches!(
             self,
-            VoxelKind::Air | VoxelKind::Flower | VoxelKind::TallGrass | VoxelKind::DeadBush
+            VoxelKind::Air
+                | VoxelKind::Flower
+                | VoxelKind::TallGrass
+                | VoxelKind::DeadBush
+                | VoxelKind::Sapling
+                | VoxelKind::GlowMushroom
+                | VoxelKind::SnowLayer // 薄薄的积雪一踩就陷下去
         )
     }
 }
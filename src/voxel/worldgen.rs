@@ -0,0 +1,936 @@
+//! 世界生成配置
+//!
+//! 所有地形参数（世界高度范围与水平边界、噪声尺度、分形层、水位、生物群系阈值、海底、山地与沼泽地形、河流、洞穴与洞穴装饰、熔岩、
+//! 矿石分布、树木和植被概率）
+//! 集中在 WorldGenConfig 中，默认值与原先硬编码的常量一致。
+//!
+//! `mode` 选择生成模式：默认的噪声地形，或用于测试模拟的超平坦世界和方块展示世界
+//! （见 [`GeneratorMode`]）。
+//!
+//! 配置从 `assets/worldgen.ron` 加载，支持热重载：文件修改后未被修改的区块在后台
+//! 按新参数重新生成并原地替换（见 [`regen`](crate::voxel::regen)），玩家修改过的区块保留。
+//! 文件缺失或解析失败时继续使用默认值。
+//!
+//! 新建世界时选择的 [`WorldGenOptions`]（生成预设、普通或平坦地形）在加载后叠加到配置上，
+//! 同一份配置文件可以生成不同风格的世界。
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::voxel::chunk::{ChunkPos, Chunks};
+use crate::voxel::loading::{ChunkLoadQueue, ChunkReplacementBuffer, ChunkTasks};
+use crate::voxel::regen::ChunkRegenQueue;
+use crate::voxel::voxel_kind::VoxelKind;
+
+/// 配置文件路径（相对于 assets 目录）
+pub const WORLDGEN_CONFIG_PATH: &str = "worldgen.ron";
+
+/// 世界生成配置
+#[derive(Asset, Resource, TypePath, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldGenConfig {
+    /// 生成模式
+    pub mode: GeneratorMode,
+    /// 超平坦世界的方块层（仅超平坦模式使用）
+    pub superflat: SuperflatConfig,
+    /// 地形高度
+    pub terrain: TerrainConfig,
+    /// 生物群系划分
+    pub biomes: BiomeConfig,
+    /// 海底
+    pub oceans: OceanConfig,
+    /// 洞穴
+    pub caves: CaveConfig,
+    /// 洞穴装饰
+    pub cave_decoration: CaveDecorationConfig,
+    /// 深层熔岩
+    pub lava: LavaConfig,
+    /// 浮空岛
+    pub floating_islands: FloatingIslandConfig,
+    /// 山地
+    pub mountains: MountainConfig,
+    /// 沼泽
+    pub swamps: SwampConfig,
+    /// 河流
+    pub rivers: RiverConfig,
+    /// 矿石分布
+    pub ores: OreConfig,
+    /// 树木
+    pub trees: TreeConfig,
+    /// 地表植被
+    pub vegetation: VegetationConfig,
+}
+
+/// 地形生成模式
+///
+/// 超平坦和方块展示世界没有洞穴、水体、树木和预制结构，方便复现模拟测试
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeneratorMode {
+    /// 噪声地形（生物群系、洞穴、河流、树木、预制结构）
+    #[default]
+    Noise,
+    /// 超平坦：按 superflat 配置的方块层铺满整个世界
+    Superflat,
+    /// 方块展示：平坦的石质地面上按网格摆放每种方块
+    Showcase,
+}
+
+/// 超平坦世界的一层方块
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FlatLayer {
+    pub block: VoxelKind,
+    /// 层厚（方块）
+    pub thickness: i32,
+}
+
+/// 超平坦配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SuperflatConfig {
+    /// 最底层的高度
+    pub base_y: i32,
+    /// 从下到上的方块层
+    pub layers: Vec<FlatLayer>,
+}
+
+impl Default for SuperflatConfig {
+    fn default() -> Self {
+        let layer = |block, thickness| FlatLayer { block, thickness };
+        Self {
+            base_y: 0,
+            // 地表高度与噪声地形的基准高度相同
+            layers: vec![
+                layer(VoxelKind::Stone, 28),
+                layer(VoxelKind::Dirt, 3),
+                layer(VoxelKind::Grass, 1),
+            ],
+        }
+    }
+}
+
+impl SuperflatConfig {
+    /// 第一个空气方块的高度（最顶层方块上方）
+    pub fn surface_height(&self) -> i32 {
+        self.base_y + self.layers.iter().map(|l| l.thickness.max(0)).sum::<i32>()
+    }
+
+    /// 指定高度的方块，层叠范围之外为空气
+    pub fn block_at(&self, y: i32) -> VoxelKind {
+        let mut top = self.base_y;
+        if y < top {
+            return VoxelKind::Air;
+        }
+        for layer in &self.layers {
+            top += layer.thickness.max(0);
+            if y < top {
+                return layer.block;
+            }
+        }
+        VoxelKind::Air
+    }
+}
+
+/// 高度噪声使用的噪声源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NoiseSource {
+    /// 地形噪声（大尺度特征）
+    Terrain,
+    /// 细节噪声（小尺度起伏）
+    Detail,
+}
+
+/// 单层分形噪声
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HeightOctave {
+    /// 噪声源
+    pub source: NoiseSource,
+    /// 相对于基础尺度的频率倍数
+    pub frequency: f64,
+    /// 振幅（方块）
+    pub amplitude: f64,
+}
+
+/// 地形高度配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainConfig {
+    /// 基础噪声尺度
+    pub scale: f64,
+    /// 基准地表高度
+    pub base_height: i32,
+    /// 叠加的噪声层
+    pub octaves: Vec<HeightOctave>,
+    /// 水位高度
+    pub water_level: i32,
+    /// 世界最低高度：此处为基岩层，以下不生成任何方块
+    pub min_y: i32,
+    /// 世界最高高度：以上不生成任何方块，地形高度也不会超过此值
+    pub max_y: i32,
+    /// 世界边界：玩家的 x、z 坐标限制在 -border 到 border 之间
+    ///
+    /// 远离原点时坐标的浮点精度下降，边界让玩家停在精度还足够的范围内
+    pub border: i32,
+}
+
+impl TerrainConfig {
+    /// 把水平坐标限制在世界边界以内，与边界保持 margin 的距离
+    pub fn clamp_to_border(&self, pos: Vec3, margin: f32) -> Vec3 {
+        let limit = (self.border as f32 - margin).max(0.0);
+        Vec3::new(
+            pos.x.clamp(-limit, limit),
+            pos.y,
+            pos.z.clamp(-limit, limit),
+        )
+    }
+
+    /// 水平方向到最近的世界边界的距离，越过边界时为负数
+    pub fn distance_to_border(&self, pos: Vec3) -> f32 {
+        self.border as f32 - pos.x.abs().max(pos.z.abs())
+    }
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        Self {
+            scale: 0.02,
+            base_height: 32,
+            octaves: vec![
+                // 大陆尺度起伏（高原与低地）
+                HeightOctave {
+                    source: NoiseSource::Terrain,
+                    frequency: 0.25,
+                    amplitude: 20.0,
+                },
+                // 大尺度地形
+                HeightOctave {
+                    source: NoiseSource::Terrain,
+                    frequency: 1.0,
+                    amplitude: 12.0,
+                },
+                // 中等尺度起伏
+                HeightOctave {
+                    source: NoiseSource::Terrain,
+                    frequency: 2.0,
+                    amplitude: 6.0,
+                },
+                // 小尺度细节
+                HeightOctave {
+                    source: NoiseSource::Detail,
+                    frequency: 4.0,
+                    amplitude: 3.0,
+                },
+            ],
+            water_level: 30,
+            min_y: -64,
+            max_y: 192,
+            border: 1_000_000,
+        }
+    }
+}
+
+/// 生物群系配置（温度/湿度噪声范围 -1.0 到 1.0）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BiomeConfig {
+    /// 温度/湿度噪声尺度
+    pub scale: f64,
+    /// 低于此高度为海洋
+    pub ocean_height: i32,
+    /// 低于此高度为海滩
+    pub beach_height: i32,
+    /// 低于此温度为寒带
+    pub cold_temperature: f64,
+    /// 寒带中高于此湿度为针叶林（否则为雪地）
+    pub taiga_humidity: f64,
+    /// 高于此温度且低于沙漠湿度为沙漠
+    pub desert_temperature: f64,
+    /// 沙漠湿度上限
+    pub desert_humidity: f64,
+    /// 高于此湿度为森林
+    pub forest_humidity: f64,
+    /// 高于此湿度为白桦林
+    pub birch_humidity: f64,
+}
+
+impl Default for BiomeConfig {
+    fn default() -> Self {
+        Self {
+            scale: 0.008,
+            ocean_height: 28,
+            beach_height: 32,
+            cold_temperature: -0.3,
+            taiga_humidity: 0.2,
+            desert_temperature: 0.3,
+            desert_humidity: -0.2,
+            forest_humidity: 0.3,
+            birch_humidity: 0.0,
+        }
+    }
+}
+
+/// 海底配置
+///
+/// 海洋生物群系的海底在大陆架以外加速下沉，海底表层按水深从沙子过渡到砂砾和黏土
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OceanConfig {
+    /// 大陆架深度：低于海洋高度不超过此值的海底保持原有坡度
+    pub shelf_depth: f64,
+    /// 大陆架以外海底坡度的倍数
+    pub drop_off: f64,
+    /// 海底最深处在水位以下的深度
+    pub max_depth: f64,
+    /// 水深不超过此值的海底为沙子
+    pub sand_depth: i32,
+    /// 水深不超过此值的海底为砂砾，更深处为黏土
+    pub gravel_depth: i32,
+}
+
+impl Default for OceanConfig {
+    fn default() -> Self {
+        Self {
+            shelf_depth: 4.0,
+            drop_off: 1.6,
+            max_depth: 48.0,
+            sand_depth: 6,
+            gravel_depth: 14,
+        }
+    }
+}
+
+/// 洞穴配置
+///
+/// deep_y 以下为深层洞穴：噪声尺度更大、阈值更低，形成更宽阔连通的洞窟
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaveConfig {
+    /// 洞穴最低高度
+    pub min_y: i32,
+    /// 洞穴最高高度
+    pub max_y: i32,
+    /// 3D 噪声尺度
+    pub scale: f64,
+    /// 噪声高于此值为洞穴
+    pub threshold: f64,
+    /// 深层洞穴的起始高度（上下 8 格内与浅层洞穴平滑过渡）
+    pub deep_y: i32,
+    /// 深层洞穴的 3D 噪声尺度
+    pub deep_scale: f64,
+    /// 深层洞穴的噪声阈值
+    pub deep_threshold: f64,
+}
+
+impl Default for CaveConfig {
+    fn default() -> Self {
+        Self {
+            min_y: -60,
+            max_y: 60,
+            scale: 0.08,
+            threshold: 0.55,
+            deep_y: 0,
+            deep_scale: 0.035,
+            deep_threshold: 0.45,
+        }
+    }
+}
+
+/// 洞穴装饰配置
+///
+/// 装饰只放在离地表至少 5 格的洞穴地面上（见 [`terrain::caves`](crate::voxel::terrain::caves)）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaveDecorationConfig {
+    /// 砂砾和黏土斑块的噪声尺度
+    pub patch_scale: f64,
+    /// 噪声高于此值的地面铺成砂砾或黏土
+    pub patch_threshold: f64,
+    /// 水洼的噪声尺度
+    pub pool_scale: f64,
+    /// 噪声高于此值、四周被石头围住的地面积成水洼
+    pub pool_threshold: f64,
+    /// 地面上长枯死灌木的概率
+    pub dead_bush_chance: f64,
+    /// 地面上长荧光蘑菇的概率
+    pub glow_mushroom_chance: f64,
+    /// 荧光蘑菇出现的最高高度（只长在深层洞穴中）
+    pub glow_mushroom_max_y: i32,
+}
+
+impl Default for CaveDecorationConfig {
+    fn default() -> Self {
+        Self {
+            patch_scale: 0.12,
+            patch_threshold: 0.3,
+            pool_scale: 0.09,
+            pool_threshold: 0.4,
+            dead_bush_chance: 0.015,
+            glow_mushroom_chance: 0.04,
+            glow_mushroom_max_y: -8,
+        }
+    }
+}
+
+/// 熔岩配置
+///
+/// 熔岩袋只出现在深层洞穴中：洞穴空间与熔岩噪声重叠的部分填充熔岩
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LavaConfig {
+    /// 熔岩出现的最高高度
+    pub max_y: i32,
+    /// 熔岩分布噪声尺度
+    pub scale: f64,
+    /// 噪声高于此值的洞穴空间填充熔岩
+    pub threshold: f64,
+}
+
+impl Default for LavaConfig {
+    fn default() -> Self {
+        Self {
+            max_y: -32,
+            scale: 0.05,
+            threshold: 0.2,
+        }
+    }
+}
+
+/// 浮空岛配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FloatingIslandConfig {
+    /// 浮空岛最低高度
+    pub min_y: i32,
+    /// 浮空岛最高高度
+    pub max_y: i32,
+    /// 岛体噪声尺度
+    pub scale: f64,
+    /// 岛体细节噪声尺度
+    pub detail_scale: f64,
+    /// 岛屿分布噪声尺度
+    pub region_scale: f64,
+    /// 分布噪声高于此值的区域才生成岛屿
+    pub region_threshold: f64,
+    /// 岛上树木的噪声阈值（越高越稀疏）
+    pub tree_threshold: f64,
+}
+
+impl Default for FloatingIslandConfig {
+    fn default() -> Self {
+        Self {
+            min_y: 65,
+            max_y: 110,
+            scale: 0.015,
+            detail_scale: 0.06,
+            region_scale: 0.005,
+            region_threshold: 0.3,
+            tree_threshold: 0.88,
+        }
+    }
+}
+
+/// 山地配置
+///
+/// 山地分布噪声高于阈值的区域叠加山脊噪声抬高地形，
+/// 在 region_threshold 到 region_threshold + blend 之间平滑过渡
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MountainConfig {
+    /// 山地分布噪声尺度
+    pub region_scale: f64,
+    /// 分布噪声高于此值开始隆起
+    pub region_threshold: f64,
+    /// 过渡带宽度（噪声值）
+    pub blend: f64,
+    /// 山脊噪声尺度
+    pub ridge_scale: f64,
+    /// 山脊最大高度（方块）
+    pub ridge_amplitude: f64,
+    /// 地表高于此高度为裸露岩石
+    pub rock_height: i32,
+    /// 地表高于此高度为积雪
+    pub snow_height: i32,
+}
+
+impl Default for MountainConfig {
+    fn default() -> Self {
+        Self {
+            region_scale: 0.004,
+            region_threshold: 0.35,
+            blend: 0.2,
+            ridge_scale: 0.012,
+            ridge_amplitude: 64.0,
+            rock_height: 64,
+            snow_height: 80,
+        }
+    }
+}
+
+/// 沼泽配置
+///
+/// 温暖潮湿的区域地形被拉平到水位附近，低洼处形成浅水塘
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SwampConfig {
+    /// 湿度高于此值开始形成沼泽
+    pub humidity: f64,
+    /// 温度高于此值才形成沼泽
+    pub min_temperature: f64,
+    /// 湿度和温度的过渡带宽度
+    pub blend: f64,
+    /// 向水位拉平的程度（0.0-1.0）
+    pub flatten: f64,
+    /// 水塘噪声尺度
+    pub patch_scale: f64,
+    /// 水塘起伏幅度（方块），越大水塘越多越深
+    pub patch_depth: f64,
+}
+
+impl Default for SwampConfig {
+    fn default() -> Self {
+        Self {
+            humidity: 0.4,
+            min_temperature: -0.1,
+            blend: 0.15,
+            flatten: 0.9,
+            patch_scale: 0.08,
+            patch_depth: 2.5,
+        }
+    }
+}
+
+/// 河流配置
+///
+/// 河道沿河道噪声的零值等高线分布：1 - |噪声| 接近 1 的窄带被下切到水位以下，
+/// 两侧的河岸带向河道平滑过渡。只依赖世界坐标，跨区块边界保持连续
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiverConfig {
+    /// 河道噪声尺度（越小河流越长越直）
+    pub scale: f64,
+    /// 河道宽度（噪声值）
+    pub width: f64,
+    /// 河岸过渡带宽度（噪声值）
+    pub bank_width: f64,
+    /// 河道中心低于水位的深度（方块）
+    pub depth: f64,
+}
+
+impl Default for RiverConfig {
+    fn default() -> Self {
+        Self {
+            scale: 0.003,
+            width: 0.025,
+            bank_width: 0.04,
+            depth: 3.0,
+        }
+    }
+}
+
+/// 矿石分布配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OreConfig {
+    /// 3D 噪声尺度
+    pub scale: f64,
+    /// 噪声高于此值生成矿石
+    pub threshold: f64,
+    /// 钻石矿最高高度
+    pub diamond_max_y: i32,
+    /// 钻石矿噪声阈值
+    pub diamond_threshold: f64,
+    /// 金矿最高高度
+    pub gold_max_y: i32,
+    /// 金矿噪声阈值
+    pub gold_threshold: f64,
+    /// 铁矿最高高度（更高处为煤矿）
+    pub iron_max_y: i32,
+}
+
+impl Default for OreConfig {
+    fn default() -> Self {
+        Self {
+            scale: 0.15,
+            threshold: 0.7,
+            diamond_max_y: 16,
+            diamond_threshold: 0.85,
+            gold_max_y: 32,
+            gold_threshold: 0.80,
+            iron_max_y: 48,
+        }
+    }
+}
+
+/// 树木配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreeConfig {
+    /// 森林和针叶林的树木概率
+    pub forest_chance: f64,
+    /// 白桦林的树木概率
+    pub birch_forest_chance: f64,
+    /// 平原的树木概率
+    pub plains_chance: f64,
+    /// 沼泽的树木概率
+    pub swamp_chance: f64,
+    /// 山地的树木概率（只长在岩石线以下）
+    pub mountain_chance: f64,
+    /// 树木分布噪声尺度
+    pub noise_scale: f64,
+}
+
+impl Default for TreeConfig {
+    fn default() -> Self {
+        Self {
+            forest_chance: 0.06,
+            birch_forest_chance: 0.04,
+            plains_chance: 0.003,
+            swamp_chance: 0.02,
+            mountain_chance: 0.015,
+            noise_scale: 0.5,
+        }
+    }
+}
+
+/// 地表植被配置（高草丛、枯死的灌木等不占满方块的植物）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VegetationConfig {
+    /// 沼泽的高草丛概率
+    pub swamp_tall_grass_chance: f64,
+    /// 沼泽的枯死灌木概率
+    pub swamp_dead_bush_chance: f64,
+    /// 植被分布噪声尺度
+    pub noise_scale: f64,
+}
+
+impl Default for VegetationConfig {
+    fn default() -> Self {
+        Self {
+            swamp_tall_grass_chance: 0.08,
+            swamp_dead_bush_chance: 0.03,
+            noise_scale: 0.7,
+        }
+    }
+}
+
+impl WorldGenConfig {
+    /// 解析 RON 格式的配置文件内容
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
+    }
+}
+
+// ============================================================================
+// 生成预设
+// ============================================================================
+
+/// 噪声范围为 -1.0 到 1.0，阈值设为此值即关闭对应的地形特征
+const DISABLED_THRESHOLD: f64 = 2.0;
+
+/// 生成预设：在配置文件的基础上整体调整地形风格
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GenPreset {
+    /// 按配置文件生成
+    #[default]
+    Standard,
+    /// 起伏和山脊加倍
+    Amplified,
+    /// 抬高水位，陆地被海洋分割成群岛
+    Archipelago,
+}
+
+impl GenPreset {
+    /// 所有预设，按界面上的切换顺序排列
+    pub const ALL: [GenPreset; 3] = [
+        GenPreset::Standard,
+        GenPreset::Amplified,
+        GenPreset::Archipelago,
+    ];
+
+    /// 界面上显示的名称
+    pub fn label(self) -> &'static str {
+        match self {
+            GenPreset::Standard => "标准",
+            GenPreset::Amplified => "放大化",
+            GenPreset::Archipelago => "群岛",
+        }
+    }
+
+    /// 控制台命令中的名称
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "standard" => Some(GenPreset::Standard),
+            "amplified" => Some(GenPreset::Amplified),
+            "archipelago" => Some(GenPreset::Archipelago),
+            _ => None,
+        }
+    }
+
+    /// 切换顺序中的下一个预设（末尾回到开头）
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&p| p == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// 地形形态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TerrainShape {
+    /// 按配置文件的生成模式
+    #[default]
+    Normal,
+    /// 地表固定在基准高度，没有山地、河流、沼泽、洞穴和浮空岛（生物群系和树木保留）
+    Flat,
+    /// 超平坦模式（见 [`GeneratorMode::Superflat`]）
+    Superflat,
+    /// 方块展示模式（见 [`GeneratorMode::Showcase`]）
+    Showcase,
+}
+
+impl TerrainShape {
+    /// 所有形态，按界面上的切换顺序排列
+    pub const ALL: [TerrainShape; 4] = [
+        TerrainShape::Normal,
+        TerrainShape::Flat,
+        TerrainShape::Superflat,
+        TerrainShape::Showcase,
+    ];
+
+    /// 界面上显示的名称
+    pub fn label(self) -> &'static str {
+        match self {
+            TerrainShape::Normal => "普通",
+            TerrainShape::Flat => "平坦",
+            TerrainShape::Superflat => "超平坦",
+            TerrainShape::Showcase => "方块展示",
+        }
+    }
+
+    /// 命令行参数中的名称（`--generator <名称>`）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "normal" => Some(TerrainShape::Normal),
+            "flat" => Some(TerrainShape::Flat),
+            "superflat" => Some(TerrainShape::Superflat),
+            "showcase" => Some(TerrainShape::Showcase),
+            _ => None,
+        }
+    }
+
+    /// 切换顺序中的下一个形态（末尾回到开头）
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// 当前世界的生成选项，新建世界时选择并保存在世界元数据中
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldGenOptions {
+    pub preset: GenPreset,
+    pub shape: TerrainShape,
+}
+
+impl WorldGenOptions {
+    /// 把预设和地形形态叠加到从配置文件加载的配置上
+    pub fn apply(&self, config: &mut WorldGenConfig) {
+        match self.preset {
+            GenPreset::Standard => {}
+            GenPreset::Amplified => {
+                for octave in &mut config.terrain.octaves {
+                    octave.amplitude *= 2.0;
+                }
+                config.mountains.ridge_amplitude *= 1.5;
+            }
+            GenPreset::Archipelago => {
+                const RISE: i32 = 12;
+                config.terrain.water_level += RISE;
+                config.biomes.ocean_height += RISE;
+                config.biomes.beach_height += RISE;
+            }
+        }
+
+        match self.shape {
+            TerrainShape::Normal => {}
+            TerrainShape::Flat => Self::flatten(config),
+            TerrainShape::Superflat => config.mode = GeneratorMode::Superflat,
+            TerrainShape::Showcase => config.mode = GeneratorMode::Showcase,
+        }
+    }
+
+    /// 拉平噪声地形：去掉高度噪声，关闭山地、沼泽、河流、洞穴和浮空岛
+    fn flatten(config: &mut WorldGenConfig) {
+        config.terrain.octaves.clear();
+        config.mountains.region_threshold = DISABLED_THRESHOLD;
+        config.swamps.humidity = DISABLED_THRESHOLD;
+        config.rivers.width = 0.0;
+        config.rivers.bank_width = 0.0;
+        config.caves.threshold = DISABLED_THRESHOLD;
+        config.caves.deep_threshold = DISABLED_THRESHOLD;
+        config.floating_islands.region_threshold = DISABLED_THRESHOLD;
+    }
+}
+
+// ============================================================================
+// 资源加载
+// ============================================================================
+
+/// 配置加载错误
+#[derive(Debug, thiserror::Error)]
+pub enum WorldGenConfigLoaderError {
+    #[error("无法读取世界生成配置: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("世界生成配置解析失败: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+/// RON 格式的世界生成配置加载器
+#[derive(Default, TypePath)]
+pub struct WorldGenConfigLoader;
+
+impl AssetLoader for WorldGenConfigLoader {
+    type Asset = WorldGenConfig;
+    type Settings = ();
+    type Error = WorldGenConfigLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(WorldGenConfig::from_ron(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+/// 配置文件句柄（保持资源加载状态以支持热重载）
+#[derive(Resource)]
+pub struct WorldGenConfigHandle(pub Handle<WorldGenConfig>);
+
+/// 启动时加载配置文件
+pub fn load_worldgen_config(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handle = asset_server.load(WORLDGEN_CONFIG_PATH);
+    commands.insert_resource(WorldGenConfigHandle(handle));
+}
+
+/// 应用加载/修改后的配置
+///
+/// 配置文件或生成选项变化时，把生成选项叠加到配置文件上；结果与当前值不同时替换资源，
+/// 并按新参数重新生成未修改的区块
+pub fn apply_worldgen_config(
+    mut events: MessageReader<AssetEvent<WorldGenConfig>>,
+    handle: Option<Res<WorldGenConfigHandle>>,
+    assets: Res<Assets<WorldGenConfig>>,
+    options: Res<WorldGenOptions>,
+    mut config: ResMut<WorldGenConfig>,
+    mut regenerator: ChunkRegenerator,
+) {
+    let Some(handle) = handle else {
+        return;
+    };
+
+    let mut changed = options.is_changed();
+    for event in events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }
+                if *id == handle.0.id() =>
+            {
+                changed = true;
+            }
+            _ => {}
+        }
+    }
+
+    if !changed {
+        return;
+    }
+
+    // 配置文件还没加载完时以默认配置为基础，加载完成后会再应用一次
+    let mut new_config = assets.get(&handle.0).cloned().unwrap_or_default();
+    options.apply(&mut new_config);
+
+    if new_config == *config {
+        return;
+    }
+
+    *config = new_config;
+    let count = regenerator.regenerate_unmodified();
+    info!("World generation config reloaded, regenerating {count} unmodified chunks");
+}
+
+/// 生成参数变化后重新生成区块
+#[derive(SystemParam)]
+pub struct ChunkRegenerator<'w, 's> {
+    chunks: Chunks<'w, 's>,
+    load_queue: ResMut<'w, ChunkLoadQueue>,
+    buffer: Res<'w, ChunkReplacementBuffer>,
+    tasks: ResMut<'w, ChunkTasks>,
+    regen_queue: ResMut<'w, ChunkRegenQueue>,
+}
+
+impl ChunkRegenerator<'_, '_> {
+    /// 按当前参数重新生成所有未修改的区块，返回排队的已加载区块数
+    ///
+    /// 已加载的区块在后台重新生成后原地替换，上一轮还没完成的任务被取消；
+    /// 生成中、还没放进世界的区块按旧参数生成，直接卸载后由区块加载系统重新加载。
+    /// 修改过的区块（包括暂存的已卸载区块）保留
+    pub fn regenerate_unmodified(&mut self) -> usize {
+        self.tasks.cancel_regens();
+
+        let generating = self.tasks.pending.keys().copied();
+        let completed = self.buffer.completed.iter().map(|chunk| chunk.chunk_pos);
+        for chunk_pos in generating.chain(completed) {
+            if !self.load_queue.to_unload.contains(&chunk_pos) {
+                self.load_queue.to_unload.push(chunk_pos);
+            }
+        }
+
+        let chunks: Vec<ChunkPos> = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| !chunk.is_modified)
+            .map(|(chunk_pos, _)| chunk_pos)
+            .collect();
+        let count = chunks.len();
+        self.regen_queue.start_round(chunks);
+        count
+    }
+
+    /// 重新生成的进度
+    pub fn queue(&self) -> &ChunkRegenQueue {
+        &self.regen_queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_border() {
+        let terrain = TerrainConfig {
+            border: 100,
+            ..default()
+        };
+        let inside = Vec3::new(-20.0, 64.0, 90.0);
+        assert_eq!(terrain.clamp_to_border(inside, 0.3), inside);
+        assert_eq!(terrain.distance_to_border(inside), 10.0);
+
+        let outside = Vec3::new(-250.0, 64.0, 100.0);
+        assert_eq!(
+            terrain.clamp_to_border(outside, 0.5),
+            Vec3::new(-99.5, 64.0, 99.5)
+        );
+        assert_eq!(terrain.distance_to_border(outside), -150.0);
+    }
+}
@@ -0,0 +1,337 @@
+//! Teleport waypoints
+//!
+//! A waypoint is a named position and view direction. [`Action::SaveWaypoint`] (`B` by
+//! default) saves one where the player stands with the next free default name, and the
+//! `waypoint` console command adds, lists, removes and teleports to waypoints by name.
+//! The waypoints page of the pause menu lists them with teleport and delete buttons.
+//!
+//! Waypoints belong to a world, so they're stored next to its regions in
+//! `<save directory>/waypoints.ron` (see [`ActiveWorld`]) and written back whenever they
+//! change. Waypoints
+//! within [`BEACON_RANGE`] show a beacon of light when beacons are enabled in the
+//! settings.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::console::{ConsoleCommand, ConsoleCommands, ConsoleLog};
+use crate::input::{Action, ActionInput};
+use crate::player::{LookAngles, PlayerCamera, PlayerStance, TeleportPlayer};
+use crate::settings::GameSettings;
+use crate::voxel::persistence::{write_atomic, ActiveWorld};
+
+/// Waypoint file inside the world's save directory
+pub const WAYPOINTS_FILE: &str = "waypoints.ron";
+/// Horizontal distance within which waypoint beacons are drawn (blocks)
+pub const BEACON_RANGE: f32 = 160.0;
+/// Height of a beacon above its waypoint (blocks)
+const BEACON_HEIGHT: f32 = 96.0;
+const BEACON_COLOR: Color = Color::srgba(0.45, 0.85, 1.0, 0.85);
+/// Longest waypoint name, in characters
+const MAX_NAME_CHARS: usize = 32;
+
+/// A saved place to teleport back to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub name: String,
+    /// Position of the player's feet
+    pub feet: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Waypoint {
+    /// Block the waypoint is in, for display
+    pub fn block(&self) -> IVec3 {
+        self.feet.floor().as_ivec3()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WaypointError {
+    #[error("could not access waypoint file: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not parse waypoints: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize waypoints: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+/// Waypoints of the current world, in the order they were added
+#[derive(Resource, Debug, Default)]
+pub struct Waypoints {
+    list: Vec<Waypoint>,
+    /// File the waypoints are saved to, None when they aren't persisted
+    path: Option<PathBuf>,
+}
+
+impl Waypoints {
+    /// Reads the waypoints from `path`; a missing file means no waypoints yet, an
+    /// unreadable one is reported and ignored
+    pub fn load(path: PathBuf) -> Self {
+        let list = match Self::read(&path) {
+            Ok(list) => list,
+            Err(WaypointError::Io(err)) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => {
+                warn!("Ignoring {}: {err}", path.display());
+                Vec::new()
+            }
+        };
+        Self {
+            list,
+            path: Some(path),
+        }
+    }
+
+    fn read(path: &Path) -> Result<Vec<Waypoint>, WaypointError> {
+        let text = fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    pub fn save(&self) -> Result<(), WaypointError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(&self.list, ron::ser::PrettyConfig::default())?;
+        write_atomic(path, text.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Waypoint> {
+        self.list.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Waypoint> {
+        self.list.get(index)
+    }
+
+    /// Waypoint with the given name, ignoring case
+    pub fn find(&self, name: &str) -> Option<&Waypoint> {
+        self.list.iter().find(|w| w.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds a waypoint, replacing one with the same name. Returns whether one was
+    /// replaced.
+    pub fn add(&mut self, waypoint: Waypoint) -> bool {
+        match self
+            .list
+            .iter_mut()
+            .find(|w| w.name.eq_ignore_ascii_case(&waypoint.name))
+        {
+            Some(existing) => {
+                *existing = waypoint;
+                true
+            }
+            None => {
+                self.list.push(waypoint);
+                false
+            }
+        }
+    }
+
+    /// Removes the waypoint with the given name, ignoring case
+    pub fn remove(&mut self, name: &str) -> Option<Waypoint> {
+        let index = self
+            .list
+            .iter()
+            .position(|w| w.name.eq_ignore_ascii_case(name))?;
+        Some(self.list.remove(index))
+    }
+
+    pub fn remove_at(&mut self, index: usize) -> Option<Waypoint> {
+        (index < self.list.len()).then(|| self.list.remove(index))
+    }
+
+    /// First of "路标 1", "路标 2", ... not taken yet
+    pub fn next_default_name(&self) -> String {
+        (1..)
+            .map(|n| format!("路标 {n}"))
+            .find(|name| self.find(name).is_none())
+            .unwrap_or_default()
+    }
+}
+
+/// Trims a name typed by the player and limits its length; None if nothing is left
+fn clean_name(name: &str) -> Option<String> {
+    let name: String = name.trim().chars().take(MAX_NAME_CHARS).collect();
+    (!name.is_empty()).then_some(name)
+}
+
+pub struct WaypointsPlugin;
+
+impl Plugin for WaypointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>();
+        app.world_mut().resource_mut::<ConsoleCommands>().register(
+            "waypoint",
+            "waypoint add [名称] | list | tp <名称> | remove <名称>",
+            "保存、列出、传送到或删除路标",
+        );
+
+        app.init_resource::<Waypoints>().add_systems(
+            Update,
+            (
+                load_waypoints,
+                save_waypoint_key,
+                handle_waypoint_command,
+                save_waypoints,
+                draw_beacons,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Reads the waypoints of the world being played, again when the new world screen
+/// opens another world
+fn load_waypoints(world: Res<ActiveWorld>, mut waypoints: ResMut<Waypoints>) {
+    if !world.is_changed() {
+        return;
+    }
+    let path = world.storage.root().join(WAYPOINTS_FILE);
+    *waypoints = Waypoints::load(path);
+    info!("Loaded {} waypoints", waypoints.len());
+}
+
+/// Writes the waypoint file whenever the waypoints change
+fn save_waypoints(waypoints: Res<Waypoints>) {
+    if !waypoints.is_changed() || waypoints.is_added() {
+        return;
+    }
+    if let Err(err) = waypoints.save() {
+        warn!("Failed to save waypoints: {err}");
+    }
+}
+
+/// Waypoint at the player's current position and view direction
+fn waypoint_here(
+    name: String,
+    camera_q: &Query<(&Transform, &LookAngles, &PlayerStance), With<PlayerCamera>>,
+) -> Option<Waypoint> {
+    let (transform, angles, stance) = camera_q.single().ok()?;
+    Some(Waypoint {
+        name,
+        feet: stance.feet(transform.translation),
+        yaw: angles.yaw,
+        pitch: angles.pitch,
+    })
+}
+
+fn save_waypoint_key(
+    actions: ActionInput,
+    camera_q: Query<(&Transform, &LookAngles, &PlayerStance), With<PlayerCamera>>,
+    mut waypoints: ResMut<Waypoints>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    if !actions.just_pressed(Action::SaveWaypoint) {
+        return;
+    }
+    let Some(waypoint) = waypoint_here(waypoints.next_default_name(), &camera_q) else {
+        return;
+    };
+    log.print(format!(
+        "已保存路标 {}（{}）",
+        waypoint.name,
+        waypoint.block()
+    ));
+    waypoints.add(waypoint);
+}
+
+fn handle_waypoint_command(
+    mut commands_in: MessageReader<ConsoleCommand>,
+    camera_q: Query<(&Transform, &LookAngles, &PlayerStance), With<PlayerCamera>>,
+    mut waypoints: ResMut<Waypoints>,
+    mut teleport: MessageWriter<TeleportPlayer>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for command in commands_in.read() {
+        if command.name != "waypoint" {
+            continue;
+        }
+        let (sub, rest) = match command.args.split_first() {
+            Some((sub, rest)) => (sub.to_lowercase(), rest.join(" ")),
+            None => ("list".to_string(), String::new()),
+        };
+        match sub.as_str() {
+            "add" => {
+                let name = clean_name(&rest).unwrap_or_else(|| waypoints.next_default_name());
+                let Some(waypoint) = waypoint_here(name, &camera_q) else {
+                    continue;
+                };
+                let message = format!("{}（{}）", waypoint.name, waypoint.block());
+                if waypoints.add(waypoint) {
+                    log.print(format!("已更新路标 {message}"));
+                } else {
+                    log.print(format!("已保存路标 {message}"));
+                }
+            }
+            "list" => {
+                if waypoints.is_empty() {
+                    log.print("还没有路标（按 B 或输入 /waypoint add 保存）");
+                }
+                for waypoint in waypoints.iter() {
+                    log.print(format!("  {}（{}）", waypoint.name, waypoint.block()));
+                }
+            }
+            "tp" => match waypoints.find(&rest) {
+                Some(waypoint) => {
+                    teleport.write(TeleportPlayer {
+                        feet: waypoint.feet,
+                        yaw: waypoint.yaw,
+                        pitch: waypoint.pitch,
+                    });
+                    log.print(format!("已传送到路标 {}", waypoint.name));
+                }
+                None => log.print(format!("waypoint：没有名为 {rest} 的路标")),
+            },
+            "remove" => match waypoints.remove(&rest) {
+                Some(waypoint) => log.print(format!("已删除路标 {}", waypoint.name)),
+                None => log.print(format!("waypoint：没有名为 {rest} 的路标")),
+            },
+            _ => log.print("用法：/waypoint add [名称] | list | tp <名称> | remove <名称>"),
+        }
+    }
+}
+
+/// Draws a column of light over each waypoint near the player
+fn draw_beacons(
+    settings: Res<GameSettings>,
+    waypoints: Res<Waypoints>,
+    camera_q: Query<&Transform, With<PlayerCamera>>,
+    mut gizmos: Gizmos,
+) {
+    if !settings.waypoint_beacons || waypoints.is_empty() {
+        return;
+    }
+    let Ok(camera) = camera_q.single() else {
+        return;
+    };
+    let eye = camera.translation.xz();
+    for waypoint in waypoints.iter() {
+        if waypoint.feet.xz().distance(eye) > BEACON_RANGE {
+            continue;
+        }
+        let base = waypoint.feet;
+        gizmos.line(base, base + Vec3::Y * BEACON_HEIGHT, BEACON_COLOR);
+        gizmos.circle(
+            Isometry3d::new(base + Vec3::Y * 0.05, Quat::from_rotation_x(FRAC_PI_2)),
+            0.5,
+            BEACON_COLOR,
+        );
+    }
+}
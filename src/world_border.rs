@@ -0,0 +1,171 @@
+//! World border effects
+//!
+//! The border itself is `terrain.border` in the world generation config, and the player
+//! module keeps movement and teleports inside it. This module makes it visible: within
+//! [`WALL_VISIBLE_DISTANCE`] blocks a shimmering grid is drawn on the border plane around
+//! the player, and within [`WARNING_DISTANCE`] blocks a warning at the top of the screen
+//! counts down the distance left.
+
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+use crate::player::PlayerCamera;
+use crate::ui::UI_FONT_PATH;
+use crate::voxel::WorldGenConfig;
+
+/// The wall is drawn when the player is closer than this to the border (blocks)
+pub const WALL_VISIBLE_DISTANCE: f32 = 48.0;
+/// The UI warning shows when the player is closer than this to the border (blocks)
+pub const WARNING_DISTANCE: f32 = 128.0;
+/// Closer than this, the warning says the border has been reached (blocks)
+const REACHED_DISTANCE: f32 = 1.0;
+
+/// Half of the wall's extent around the player, along the plane and vertically (blocks)
+const WALL_HALF_EXTENT: f32 = 24.0;
+/// Distance between grid lines on the wall (blocks)
+const WALL_LINE_SPACING: f32 = 2.0;
+/// How fast the grid lines scroll along the wall (blocks per second)
+const WALL_SCROLL_SPEED: f32 = 0.6;
+/// Seconds per shimmer pulse
+const WALL_PULSE_PERIOD: f32 = 2.5;
+const WALL_COLOR: Color = Color::srgb(0.35, 0.65, 1.0);
+const WALL_MAX_ALPHA: f32 = 0.8;
+
+/// Root node of the border warning
+#[derive(Component)]
+struct BorderWarning;
+
+#[derive(Component)]
+struct BorderWarningText;
+
+pub struct WorldBorderPlugin;
+
+impl Plugin for WorldBorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_border_warning)
+            .add_systems(Update, (draw_border_wall, update_border_warning));
+    }
+}
+
+fn setup_border_warning(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: px(64.0),
+                width: percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            Visibility::Hidden,
+            BorderWarning,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        padding: UiRect::axes(px(14.0), px(6.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgba(0.05, 0.1, 0.2, 0.6)),
+                ))
+                .with_child((
+                    Text::new(""),
+                    TextFont {
+                        font: asset_server.load(UI_FONT_PATH),
+                        font_size: 18.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(0.75, 0.88, 1.0)),
+                    BorderWarningText,
+                ));
+        });
+}
+
+fn update_border_warning(
+    config: Res<WorldGenConfig>,
+    camera_q: Query<&Transform, With<PlayerCamera>>,
+    mut root_q: Query<&mut Visibility, With<BorderWarning>>,
+    mut text_q: Query<&mut Text, With<BorderWarningText>>,
+) {
+    let (Ok(camera), Ok(mut visibility), Ok(mut text)) =
+        (camera_q.single(), root_q.single_mut(), text_q.single_mut())
+    else {
+        return;
+    };
+
+    let distance = config.terrain.distance_to_border(camera.translation);
+    if distance > WARNING_DISTANCE {
+        visibility.set_if_neq(Visibility::Hidden);
+        return;
+    }
+    visibility.set_if_neq(Visibility::Inherited);
+    let message = if distance < REACHED_DISTANCE {
+        "已到达世界边界".to_string()
+    } else {
+        format!("接近世界边界：还剩 {distance:.0} 格")
+    };
+    if text.0 != message {
+        text.0 = message;
+    }
+}
+
+/// Draws a scrolling, pulsing grid on each border plane near the player, fading out
+/// towards the edges of the grid and with the player's distance from the plane
+fn draw_border_wall(
+    time: Res<Time>,
+    config: Res<WorldGenConfig>,
+    camera_q: Query<&Transform, With<PlayerCamera>>,
+    mut gizmos: Gizmos,
+) {
+    let Ok(camera) = camera_q.single() else {
+        return;
+    };
+    let eye = camera.translation;
+    let border = config.terrain.border as f32;
+    let elapsed = time.elapsed_secs();
+    let pulse = 0.75 + 0.25 * (elapsed * TAU / WALL_PULSE_PERIOD).sin();
+    let scroll = (elapsed * WALL_SCROLL_SPEED).rem_euclid(WALL_LINE_SPACING);
+
+    // Each plane as (normal axis, sign): x = ±border and z = ±border
+    for (axis, sign) in [(0, 1.0), (0, -1.0), (2, 1.0), (2, -1.0)] {
+        let distance = border - sign * eye[axis];
+        if !(0.0..WALL_VISIBLE_DISTANCE).contains(&distance) {
+            continue;
+        }
+        let tangent = if axis == 0 { Vec3::Z } else { Vec3::X };
+        let mut center = eye;
+        center[axis] = sign * border;
+        let alpha = WALL_MAX_ALPHA * pulse * (1.0 - distance / WALL_VISIBLE_DISTANCE);
+
+        // Snap the grid to world coordinates so it doesn't slide along with the player
+        let along = center.dot(tangent);
+        let first = ((along - WALL_HALF_EXTENT) / WALL_LINE_SPACING).floor() * WALL_LINE_SPACING;
+        let top = Vec3::Y * WALL_HALF_EXTENT;
+        let mut offset = first + scroll - along;
+        while offset < WALL_HALF_EXTENT {
+            let fade = 1.0 - offset.abs() / WALL_HALF_EXTENT;
+            if fade > 0.0 {
+                let base = center + tangent * offset;
+                gizmos.line(base - top, base + top, WALL_COLOR.with_alpha(alpha * fade));
+            }
+            offset += WALL_LINE_SPACING;
+        }
+
+        let side = tangent * WALL_HALF_EXTENT;
+        let first = ((eye.y - WALL_HALF_EXTENT) / WALL_LINE_SPACING).floor() * WALL_LINE_SPACING;
+        let mut height = first + scroll - eye.y;
+        while height < WALL_HALF_EXTENT {
+            let fade = 1.0 - height.abs() / WALL_HALF_EXTENT;
+            if fade > 0.0 {
+                let base = center + Vec3::Y * height;
+                gizmos.line(
+                    base - side,
+                    base + side,
+                    WALL_COLOR.with_alpha(alpha * fade * 0.6),
+                );
+            }
+            height += WALL_LINE_SPACING;
+        }
+    }
+}
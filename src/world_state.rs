@@ -0,0 +1,254 @@
+//! World time and simulation state
+//!
+//! What a world needs to resume where it was left rather than at noon at the spawn point:
+//! the [`GameClock`], the [`WeatherState`], the motion of the sun and moon
+//! ([`CelestialSettings`]) and where the player stands and looks. They're stored next to
+//! the world's regions in `<save directory>/state.ron` (see [`ActiveWorld`]) and written
+//! every [`SAVE_INTERVAL_SECS`] seconds, before another world is opened and when the game
+//! exits.
+//!
+//! A world without the file starts at noon at the spawn point. The saved weather is put
+//! back once the terrain uses the world's seed, since the weather domain starts the
+//! weather over whenever the seed changes. The player's place is only recorded outside
+//! photo mode, whose free camera isn't where the player is.
+
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::celestial::{CelestialSettings, GameClock};
+use crate::photo_mode::photo_mode_inactive;
+use crate::player::{LookAngles, MovementMode, PlayerCamera, PlayerStance, TeleportPlayer};
+use crate::voxel::domains::weather::WeatherState;
+use crate::voxel::persistence::{write_atomic, ActiveWorld};
+use crate::voxel::terrain::SharedTerrain;
+
+/// State file inside the world's save directory
+pub const STATE_FILE: &str = "state.ron";
+/// How often the state is written, in seconds
+pub const SAVE_INTERVAL_SECS: f32 = 30.0;
+
+/// Motion of the sun and moon
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SavedCelestial {
+    pub paused: bool,
+    pub rotation_speed: f32,
+}
+
+/// Where the player was and how they were moving
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SavedPlayer {
+    pub feet: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub movement: MovementMode,
+}
+
+/// Everything saved in the state file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldState {
+    /// [`GameClock::ticks`]
+    pub ticks: u64,
+    pub celestial: SavedCelestial,
+    pub weather: WeatherState,
+    /// None until the player has been seen in the world
+    pub player: Option<SavedPlayer>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorldStateError {
+    #[error("could not access world state file: {0}")]
+    Io(#[from] io::Error),
+    #[error("could not parse world state: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize world state: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+/// Where the state of the current world is saved, and what's kept of it between saves
+#[derive(Resource, Debug, Default)]
+struct WorldStateFile {
+    /// None when the state isn't persisted
+    path: Option<PathBuf>,
+    /// Weather read from the file, waiting for the terrain to use the world's seed
+    pending_weather: Option<WeatherState>,
+    /// Where the player was last seen outside photo mode
+    player: Option<SavedPlayer>,
+}
+
+impl WorldStateFile {
+    fn read(path: &Path) -> Result<WorldState, WorldStateError> {
+        let text = fs::read_to_string(path)?;
+        Ok(ron::from_str(&text)?)
+    }
+
+    fn save(&self, state: &WorldState) -> Result<(), WorldStateError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(state, ron::ser::PrettyConfig::default())?;
+        write_atomic(path, text.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// The resources saved with the world
+#[derive(SystemParam)]
+struct Simulation<'w> {
+    clock: ResMut<'w, GameClock>,
+    celestial: ResMut<'w, CelestialSettings>,
+    weather: ResMut<'w, WeatherState>,
+    file: ResMut<'w, WorldStateFile>,
+}
+
+impl Simulation<'_> {
+    /// The state as it should be saved; weather still waiting to be restored is kept
+    fn capture(&self) -> WorldState {
+        WorldState {
+            ticks: self.clock.ticks,
+            celestial: SavedCelestial {
+                paused: self.celestial.paused,
+                rotation_speed: self.celestial.rotation_speed,
+            },
+            weather: self
+                .file
+                .pending_weather
+                .clone()
+                .unwrap_or_else(|| self.weather.clone()),
+            player: self.file.player,
+        }
+    }
+
+    /// Writes the state, reporting failures
+    fn save(&self) {
+        if let Err(err) = self.file.save(&self.capture()) {
+            warn!("Failed to save world state: {err}");
+        }
+    }
+}
+
+pub struct WorldStatePlugin;
+
+impl Plugin for WorldStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldStateFile>()
+            // Before the player systems, so they handle the teleport in the same frame
+            .add_systems(PreUpdate, load_world_state)
+            .add_systems(Update, restore_weather)
+            .add_systems(
+                PostUpdate,
+                (
+                    track_player.run_if(photo_mode_inactive),
+                    save_world_state_periodically,
+                )
+                    .chain(),
+            )
+            .add_systems(Last, save_world_state_on_exit);
+    }
+}
+
+/// Reads the state of the world being played, again when the new world screen opens
+/// another world; the state of the previous world is written first
+fn load_world_state(
+    world: Res<ActiveWorld>,
+    mut sim: Simulation,
+    mut teleport: MessageWriter<TeleportPlayer>,
+    mut player_q: Query<&mut MovementMode, With<PlayerCamera>>,
+) {
+    if !world.is_changed() {
+        return;
+    }
+    sim.save();
+
+    let path = world.storage.root().join(STATE_FILE);
+    let state = match WorldStateFile::read(&path) {
+        Ok(state) => Some(state),
+        Err(WorldStateError::Io(err)) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => {
+            warn!("Ignoring {}: {err}", path.display());
+            None
+        }
+    };
+
+    let Some(state) = state else {
+        *sim.clock = GameClock::default();
+        *sim.file = WorldStateFile {
+            path: Some(path),
+            pending_weather: Some(WeatherState::new(world.meta.seed)),
+            player: None,
+        };
+        return;
+    };
+    let clock = GameClock::at_ticks(state.ticks);
+    info!(
+        "Resuming world on day {} at tick {}",
+        clock.day, clock.ticks
+    );
+    *sim.clock = clock;
+    sim.celestial.paused = state.celestial.paused;
+    sim.celestial.rotation_speed = state.celestial.rotation_speed;
+    if let Some(player) = state.player {
+        teleport.write(TeleportPlayer {
+            feet: player.feet,
+            yaw: player.yaw,
+            pitch: player.pitch,
+        });
+        if let Ok(mut movement) = player_q.single_mut() {
+            *movement = player.movement;
+        }
+    }
+    *sim.file = WorldStateFile {
+        path: Some(path),
+        pending_weather: Some(state.weather),
+        player: state.player,
+    };
+}
+
+/// Puts the saved weather back once the terrain uses the seed it was saved with
+fn restore_weather(
+    terrain: Res<SharedTerrain>,
+    mut file: ResMut<WorldStateFile>,
+    mut weather: ResMut<WeatherState>,
+) {
+    let seed = terrain.seed();
+    if let Some(saved) = file.pending_weather.take_if(|saved| saved.seed() == seed) {
+        *weather = saved;
+    }
+}
+
+fn track_player(
+    player_q: Query<(&Transform, &PlayerStance, &LookAngles, &MovementMode), With<PlayerCamera>>,
+    mut file: ResMut<WorldStateFile>,
+) {
+    let Ok((transform, stance, angles, movement)) = player_q.single() else {
+        return;
+    };
+    file.player = Some(SavedPlayer {
+        feet: stance.feet(transform.translation),
+        yaw: angles.yaw,
+        pitch: angles.pitch,
+        movement: *movement,
+    });
+}
+
+fn save_world_state_periodically(time: Res<Time>, mut elapsed: Local<f32>, sim: Simulation) {
+    *elapsed += time.delta_secs();
+    if *elapsed < SAVE_INTERVAL_SECS {
+        return;
+    }
+    *elapsed = 0.0;
+    sim.save();
+}
+
+fn save_world_state_on_exit(mut exit: MessageReader<AppExit>, sim: Simulation) {
+    if exit.read().next().is_some() {
+        sim.save();
+    }
+}
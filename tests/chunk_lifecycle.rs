@@ -0,0 +1,516 @@
+//! Chunk lifecycle integration tests
+//!
+//! Runs [`VoxelPlugin`] in a headless app (MinimalPlugins plus the asset, mesh and gizmo
+//! plugins it needs, no window or renderer) on a superflat world with a small render
+//! distance. The tests move the camera, tick the app frame by frame and check the loading
+//! pipeline's bookkeeping after every frame:
+//!
+//! - every tracked placeholder belongs to a chunk that is still loading and exists
+//! - every generation task in flight replaces its chunk's tracked placeholder, and there
+//!   are no more of them than `ChunkLoadQueue::max_concurrent_tasks`
+//! - every chunk entity is either a loaded chunk or a tracked placeholder
+//!
+//! Generation and meshing run on the async compute pool, so the tests poll until the
+//! pipeline settles instead of counting frames.
+//!
+//! Each harness saves to its own directory under the system temp directory, so region files
+//! left in `saves/` by a local `--pregen` run don't leak into the tests.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use bevy::asset::{RecursiveDependencyLoadState, UntypedAssetId};
+use bevy::camera::primitives::Frustum;
+use bevy::camera::CameraProjection;
+use bevy::gizmos::GizmoPlugin;
+use bevy::input::InputPlugin;
+use bevy::mesh::MeshPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::transform::TransformPlugin;
+
+use voxworld::console::{ConsoleCommand, ConsoleLog};
+use voxworld::input::{InputBindings, InputCapture};
+use voxworld::voxel::domains::command::CommandQueue;
+use voxworld::voxel::loading::{chunk_in_range, UNLOAD_HYSTERESIS};
+use voxworld::voxel::persistence::{ActiveWorld, RegionPos, WorldMeta, WorldStorage};
+use voxworld::voxel::registry::BlockDefinitionsHandle;
+use voxworld::voxel::terrain::structures::StructureFolderHandle;
+use voxworld::voxel::worldgen::WorldGenConfigHandle;
+use voxworld::voxel::{
+    ChunkData, ChunkLoadQueue, ChunkLookup, ChunkMarker, ChunkPos, ChunkRegenQueue,
+    ChunkReplacementBuffer, ChunkTasks, DomainCommand, PlaceholderEntities, RenderDistance,
+    TerrainShape, VoxelKind, VoxelPlugin, VoxelWorld, WorldGenConfig, WorldGenOptions, WorldSeed,
+    CHUNK_SIZE,
+};
+
+/// Simulated frame time
+const FRAME: Duration = Duration::from_millis(16);
+/// Frames to wait for a condition before failing the test
+const MAX_FRAMES: usize = 3000;
+/// Frames for the asset events of freshly loaded assets to reach the systems applying them
+const ASSET_EVENT_FRAMES: usize = 4;
+/// Camera height above the superflat ground, in blocks
+const EYE_HEIGHT: i32 = 8;
+
+/// Headless app running the voxel plugin
+struct Harness {
+    app: App,
+}
+
+impl Harness {
+    /// Builds the app, waits for the block definitions, generation config and structure
+    /// templates to load, then starts loading chunks around `(8, ground + EYE_HEIGHT, 24)`
+    fn new() -> Self {
+        Self::with_save_dir(temp_save_dir("empty"))
+    }
+
+    /// Like [`new`](Self::new), saving the world to `root`. Chunks start loading on the
+    /// first update, so a test can write region files there before that
+    fn with_save_dir(root: PathBuf) -> Self {
+        let options = WorldGenOptions {
+            shape: TerrainShape::Superflat,
+            ..default()
+        };
+        let seed = WorldSeed::default().seed;
+        let active_world = ActiveWorld {
+            meta: WorldMeta {
+                name: seed.to_string(),
+                seed,
+                options,
+            },
+            storage: WorldStorage::new(root),
+        };
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                watch_for_changes_override: Some(false),
+                ..default()
+            },
+            TransformPlugin,
+            InputPlugin,
+            MeshPlugin,
+            GizmoPlugin,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        .init_asset::<StandardMaterial>()
+        // The debug and thermal test shortcuts and the console commands, without the
+        // settings and console UI
+        .init_resource::<InputBindings>()
+        .init_resource::<InputCapture>()
+        .init_resource::<ConsoleLog>()
+        .add_message::<ConsoleCommand>()
+        // Inserted before the plugin so its defaults don't replace them
+        .insert_resource(options)
+        .insert_resource(active_world)
+        .insert_resource(RenderDistance {
+            horizontal: 2,
+            vertical: 1,
+            surface_budget: 0,
+        })
+        .add_plugins(VoxelPlugin);
+        app.finish();
+        app.cleanup();
+
+        // Nothing loads until the assets have been applied, a config arriving later would
+        // regenerate every chunk
+        let mut queue = app.world_mut().resource_mut::<ChunkLoadQueue>();
+        queue.paused = true;
+        // Scan the whole (small) load window every frame
+        queue.scan_budget = Duration::from_secs(1);
+        let transform = Transform::default();
+        app.world_mut()
+            .spawn((Camera3d::default(), transform, camera_frustum(&transform)));
+
+        let mut harness = Self { app };
+        harness.wait_for_assets();
+        let ground = harness.ground_y();
+        harness.move_camera(
+            IVec3::new(8, ground + EYE_HEIGHT, 24),
+            IVec3::new(8, ground - 1, 8),
+        );
+        harness
+            .app
+            .world_mut()
+            .resource_mut::<ChunkLoadQueue>()
+            .paused = false;
+        harness
+    }
+
+    fn wait_for_assets(&mut self) {
+        self.run_until("the voxel plugin's assets to load", |world| {
+            let ids: [UntypedAssetId; 3] = [
+                world.resource::<WorldGenConfigHandle>().0.id().untyped(),
+                world.resource::<BlockDefinitionsHandle>().0.id().untyped(),
+                world.resource::<StructureFolderHandle>().0.id().untyped(),
+            ];
+            let server = world.resource::<AssetServer>();
+            ids.into_iter().all(|id| {
+                matches!(
+                    server.get_recursive_dependency_load_state(id),
+                    Some(
+                        RecursiveDependencyLoadState::Loaded
+                            | RecursiveDependencyLoadState::Failed(_)
+                    )
+                )
+            })
+        });
+        for _ in 0..ASSET_EVENT_FRAMES {
+            self.update();
+        }
+    }
+
+    /// Height of the first air block above the superflat ground
+    fn ground_y(&self) -> i32 {
+        self.app
+            .world()
+            .resource::<WorldGenConfig>()
+            .superflat
+            .surface_height()
+    }
+
+    /// Puts the camera at `eye` looking at `target`
+    fn move_camera(&mut self, eye: IVec3, target: IVec3) {
+        let transform =
+            Transform::from_translation(eye.as_vec3()).looking_at(target.as_vec3(), Vec3::Y);
+        let world = self.app.world_mut();
+        // The camera is the only entity with a frustum
+        let mut camera_q = world.query::<(&mut Transform, &mut Frustum)>();
+        let (mut camera_transform, mut frustum) = camera_q.single_mut(world).unwrap();
+        *camera_transform = transform;
+        *frustum = camera_frustum(&transform);
+    }
+
+    /// Queues a command for the next commit, like a domain system would
+    fn push_command(&mut self, chunk_pos: ChunkPos, command: DomainCommand) {
+        let world = self.app.world_mut();
+        // The domain plugin spawns a single command queue entity
+        let mut queue_q = world.query::<&mut CommandQueue>();
+        queue_q.single_mut(world).unwrap().push(chunk_pos, command);
+    }
+
+    /// Runs one frame and checks the invariants
+    fn update(&mut self) {
+        self.app.update();
+        check_invariants(self.app.world_mut());
+        // Give the task pool's threads time to make progress on generation and meshing
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    fn run_until(&mut self, what: &str, mut done: impl FnMut(&mut World) -> bool) {
+        for _ in 0..MAX_FRAMES {
+            self.update();
+            if done(self.app.world_mut()) {
+                return;
+            }
+        }
+        panic!("gave up waiting for {what} after {MAX_FRAMES} frames");
+    }
+
+    /// Runs until nothing is queued, generating, regenerating, waiting to be swapped in,
+    /// unloading or remeshing
+    fn settle(&mut self) {
+        self.run_until("chunk loading to settle", is_settled);
+    }
+
+    fn storage(&self) -> &WorldStorage {
+        &self.app.world().resource::<ActiveWorld>().storage
+    }
+
+    fn world(&self) -> &VoxelWorld {
+        self.app.world().resource::<VoxelWorld>()
+    }
+
+    /// Chunk data of the loaded chunk entities
+    fn chunks(&self) -> &World {
+        self.app.world()
+    }
+}
+
+/// A save directory of its own for one test, empty
+fn temp_save_dir(name: &str) -> PathBuf {
+    let root =
+        std::env::temp_dir().join(format!("voxworld-lifecycle-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    root
+}
+
+/// The frustum Bevy's camera systems would compute for a default perspective camera;
+/// MinimalPlugins has no camera plugin to keep it up to date
+fn camera_frustum(transform: &Transform) -> Frustum {
+    PerspectiveProjection::default().compute_frustum(&GlobalTransform::from(*transform))
+}
+
+fn check_invariants(world: &mut World) {
+    let markers: Vec<(Entity, ChunkPos)> = world
+        .query::<(Entity, &ChunkMarker)>()
+        .iter(world)
+        .map(|(entity, marker)| (entity, marker.pos))
+        .collect();
+
+    let queue = world.resource::<ChunkLoadQueue>();
+    let tasks = world.resource::<ChunkTasks>();
+    assert!(
+        tasks.len() <= queue.max_concurrent_tasks,
+        "{} generation tasks in flight, more than the limit",
+        tasks.len()
+    );
+
+    let buffer = world.resource::<ChunkReplacementBuffer>();
+    let loading: HashSet<ChunkPos> = queue
+        .to_load
+        .iter()
+        .chain(&queue.pending_placeholders)
+        .chain(tasks.pending.keys())
+        .copied()
+        .chain(buffer.completed.iter().map(|completed| completed.chunk_pos))
+        .collect();
+    let placeholders = world.resource::<PlaceholderEntities>();
+    for (chunk_pos, task) in &tasks.pending {
+        assert_eq!(
+            placeholders.map.get(chunk_pos),
+            Some(&task.placeholder_entity),
+            "the generation task for {chunk_pos:?} doesn't replace its tracked placeholder"
+        );
+    }
+    for (chunk_pos, &entity) in &placeholders.map {
+        assert!(
+            loading.contains(chunk_pos),
+            "orphan placeholder for {chunk_pos:?}"
+        );
+        assert!(
+            world.get_entity(entity).is_ok(),
+            "placeholder for {chunk_pos:?} was despawned but is still tracked"
+        );
+    }
+
+    let voxel_world = world.resource::<VoxelWorld>();
+    for chunk_pos in voxel_world.loaded_chunks.keys() {
+        assert!(
+            world.chunk(chunk_pos).is_some(),
+            "{chunk_pos:?} has a chunk entity but no data"
+        );
+    }
+    for (entity, chunk_pos) in markers {
+        let tracked = voxel_world.loaded_chunks.get(&chunk_pos) == Some(&entity)
+            || placeholders.map.get(&chunk_pos) == Some(&entity);
+        assert!(
+            tracked,
+            "entity {entity} for {chunk_pos:?} is neither a loaded chunk nor a placeholder"
+        );
+    }
+}
+
+fn is_settled(world: &mut World) -> bool {
+    let tasks = world.resource::<ChunkTasks>();
+    let generating = !tasks.is_empty();
+    let remeshing = tasks.remesh_count() > 0;
+    let regenerating =
+        tasks.regen_count() > 0 || world.resource::<ChunkRegenQueue>().remaining() > 0;
+    let queue = world.resource::<ChunkLoadQueue>();
+    let buffer = world.resource::<ChunkReplacementBuffer>();
+    !generating
+        && !remeshing
+        && !regenerating
+        && queue.to_load.is_empty()
+        && queue.pending_placeholders.is_empty()
+        && queue.to_unload.is_empty()
+        && buffer.completed.is_empty()
+        && world.iter_chunks().all(|(_, chunk)| !chunk.is_dirty)
+}
+
+/// Mesh assets of a chunk entity's sections
+fn section_meshes(world: &World, chunk_entity: Entity) -> HashSet<AssetId<Mesh>> {
+    let children = world
+        .get::<Children>(chunk_entity)
+        .map(|children| children.to_vec())
+        .unwrap_or_default();
+    children
+        .into_iter()
+        .filter_map(|child| world.get::<Mesh3d>(child))
+        .map(|mesh| mesh.0.id())
+        .collect()
+}
+
+#[test]
+fn test_chunks_load_around_camera() {
+    let mut harness = Harness::new();
+    harness.settle();
+
+    let ground = harness.ground_y();
+    let world = harness.world();
+    assert!(harness
+        .app
+        .world()
+        .resource::<PlaceholderEntities>()
+        .map
+        .is_empty());
+    // The ground under the camera and the air above it are loaded, the ground has a mesh
+    let ground_chunk = ChunkPos::from_world_pos(8, ground - 1, 8);
+    let air_chunk = ChunkPos::from_world_pos(8, ground, 8);
+    assert!(harness.chunks().chunk(&air_chunk).is_some());
+    assert!(world.loaded_chunks.contains_key(&ground_chunk));
+}
+
+#[test]
+fn test_chunks_out_of_range_unload() {
+    let mut harness = Harness::new();
+    // Walk while the first chunks are still generating
+    let ground = harness.ground_y();
+    for step in 0..10 {
+        let eye = IVec3::new(8, ground + EYE_HEIGHT, 24 - step * 4);
+        harness.move_camera(eye, eye + IVec3::new(0, -EYE_HEIGHT, -16));
+        harness.update();
+    }
+    // Then teleport far enough that nothing around the start stays in range
+    let far = 20 * CHUNK_SIZE;
+    let eye = IVec3::new(far + 8, ground + EYE_HEIGHT, 24);
+    harness.move_camera(eye, IVec3::new(far + 8, ground - 1, 8));
+    harness.settle();
+
+    let queue = harness.app.world().resource::<ChunkLoadQueue>();
+    let center = queue.scan.center.unwrap();
+    let window = queue.scan.window.unwrap();
+    let world = harness.world();
+    for chunk_pos in harness.chunks().chunk_positions() {
+        assert!(
+            chunk_in_range(center, chunk_pos, window, UNLOAD_HYSTERESIS),
+            "{chunk_pos:?} is still loaded outside the unload range around {center:?}"
+        );
+    }
+    let start = ChunkPos::from_world_pos(8, ground - 1, 8);
+    assert!(harness.chunks().chunk(&start).is_none());
+    assert!(!world.loaded_chunks.contains_key(&start));
+    assert!(world
+        .loaded_chunks
+        .contains_key(&ChunkPos::from_world_pos(far + 8, ground - 1, 8)));
+}
+
+#[test]
+fn test_edit_swaps_chunk_mesh() {
+    let mut harness = Harness::new();
+    harness.settle();
+
+    // Dig out the grass block the camera is looking at
+    let target = IVec3::new(8, harness.ground_y() - 1, 8);
+    let (chunk_pos, idx) = VoxelWorld::split_world_pos(target);
+    let chunk_entity = harness.world().loaded_chunks[&chunk_pos];
+    let before = section_meshes(harness.app.world(), chunk_entity);
+    assert!(!before.is_empty());
+
+    harness.push_command(
+        chunk_pos,
+        DomainCommand::SetBlock {
+            idx,
+            new_voxel: VoxelKind::Air,
+        },
+    );
+    harness.run_until("the edited chunk to be remeshed", |world| {
+        let edited = world.get_voxel(target) == VoxelKind::Air;
+        let after = section_meshes(world, chunk_entity);
+        edited && !after.is_empty() && after.is_disjoint(&before)
+    });
+
+    // The new meshes replace the old ones on the same, still visible chunk entity
+    let app_world = harness.app.world();
+    assert_eq!(
+        harness.world().loaded_chunks.get(&chunk_pos),
+        Some(&chunk_entity)
+    );
+    let meshes = app_world.resource::<Assets<Mesh>>();
+    assert!(section_meshes(app_world, chunk_entity)
+        .iter()
+        .all(|&id| meshes.contains(id)));
+    assert_ne!(
+        app_world.get::<Visibility>(chunk_entity),
+        Some(&Visibility::Hidden)
+    );
+}
+
+#[test]
+fn test_worldgen_change_regenerates_unmodified_chunks() {
+    let mut harness = Harness::new();
+    harness.settle();
+
+    // Dig out the grass block the camera is looking at
+    let target = IVec3::new(8, harness.ground_y() - 1, 8);
+    let (edited_chunk, idx) = VoxelWorld::split_world_pos(target);
+    harness.push_command(
+        edited_chunk,
+        DomainCommand::SetBlock {
+            idx,
+            new_voxel: VoxelKind::Air,
+        },
+    );
+    harness.run_until("the edit to apply", |world| {
+        world.get_voxel(target) == VoxelKind::Air
+    });
+    harness.settle();
+
+    let untouched = ChunkPos::new(edited_chunk.x + 1, edited_chunk.y, edited_chunk.z);
+    let chunk_entity = harness.world().loaded_chunks[&untouched];
+    let voxels_before = harness.chunks().chunk(&untouched).unwrap().voxels.clone();
+    let meshes_before = section_meshes(harness.app.world(), chunk_entity);
+
+    harness
+        .app
+        .world_mut()
+        .resource_mut::<WorldGenOptions>()
+        .shape = TerrainShape::Showcase;
+    harness.run_until("regeneration to start", |world| {
+        world.resource::<ChunkRegenQueue>().total > 0
+    });
+    harness.settle();
+
+    // The untouched chunk was generated again and its new meshes went on the same entity,
+    // without it being unloaded
+    let world = harness.world();
+    assert!(harness.chunks().chunk(&untouched).unwrap().voxels != voxels_before);
+    assert_eq!(world.loaded_chunks.get(&untouched), Some(&chunk_entity));
+    let meshes_after = section_meshes(harness.app.world(), chunk_entity);
+    assert!(meshes_after.is_disjoint(&meshes_before));
+
+    // The edited chunk kept the player's change
+    let chunks = harness.chunks();
+    assert!(chunks.chunk(&edited_chunk).unwrap().is_modified);
+    assert_eq!(chunks.get_voxel(target), VoxelKind::Air);
+}
+
+#[test]
+fn test_stored_chunks_load_from_disk() {
+    let root = temp_save_dir("stored");
+    let mut harness = Harness::with_save_dir(root.clone());
+
+    // Save a chunk with a stone block in the air above the ground, which the superflat
+    // generator would never produce, before any chunk starts loading
+    let stored_block = IVec3::new(8, harness.ground_y(), 8);
+    let (stored_chunk, idx) = VoxelWorld::split_world_pos(stored_block);
+    let mut chunk = ChunkData::new();
+    chunk.voxels.set(idx, VoxelKind::Stone);
+    let stored_voxels = chunk.voxels.clone();
+    let region = RegionPos::from_chunk(stored_chunk);
+    harness
+        .storage()
+        .save_region(region, &HashMap::from([(stored_chunk, chunk)]))
+        .unwrap();
+    harness.settle();
+
+    // The stored chunk was loaded as saved instead of being generated from the seed
+    let chunks = harness.chunks();
+    assert!(chunks.chunk(&stored_chunk).unwrap().voxels == stored_voxels);
+    assert_eq!(chunks.get_voxel(stored_block), VoxelKind::Stone);
+    // Chunks that aren't in the save are still generated
+    let below = ChunkPos::new(stored_chunk.x, stored_chunk.y - 1, stored_chunk.z);
+    assert!(chunks
+        .chunk(&below)
+        .unwrap()
+        .voxels
+        .iter()
+        .any(|kind| kind != VoxelKind::Air));
+    fs::remove_dir_all(&root).unwrap();
+}